@@ -1,13 +1,30 @@
+#[cfg(feature = "ffi")]
 use std::path::PathBuf;
 
+#[cfg(feature = "csharp")]
+#[path = "build/csharp.rs"]
+mod csharp;
+
 fn main() {
-    // Линковка системных библиотек для Windows
-    println!("cargo:rustc-link-lib=ntdll");
+    // Линковка системных библиотек для Windows (Unix backend обходится libc)
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+        println!("cargo:rustc-link-lib=ntdll");
+    }
+
+    // Без C API заголовок не нужен (и cbindgen не собирается)
+    #[cfg(feature = "ffi")]
+    generate_headers();
+}
 
+#[cfg(feature = "ffi")]
+fn generate_headers() {
     println!("cargo:rerun-if-changed=src/ffi.rs");
     println!("cargo:rerun-if-changed=src/multi/ffi.rs");
     println!("cargo:rerun-if-changed=src/dispatch/ffi.rs");
     println!("cargo:rerun-if-changed=src/dispatch/protocol.rs");
+    println!("cargo:rerun-if-changed=src/pinvoke.rs");
+    println!("cargo:rerun-if-changed=build/csharp.rs");
+    println!("cargo:rerun-if-changed=xshm-core/src/constants.rs");
     println!("cargo:rerun-if-changed=cbindgen.toml");
     let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR");
     let header_dir = PathBuf::from(&crate_dir).join("include");
@@ -17,10 +34,19 @@ fn main() {
     let config =
         cbindgen::Config::from_file(PathBuf::from(&crate_dir).join("cbindgen.toml")).unwrap();
 
-    cbindgen::Builder::new()
+    // Протокольные константы живут в no_std xshm-core; cbindgen не берёт
+    // const'ы зависимостей, поэтому файл разбирается как часть крейта.
+    let core_constants = PathBuf::from(&crate_dir).join("xshm-core/src/constants.rs");
+
+    let bindings = cbindgen::Builder::new()
+        .with_src(core_constants)
         .with_crate(crate_dir)
         .with_config(config)
         .generate()
-        .expect("Unable to generate xshm.h")
-        .write_to_file(header_path);
+        .expect("Unable to generate xshm.h");
+    bindings.write_to_file(header_path);
+
+    // P/Invoke-объявления для C# по тому же разбору (feature `csharp`)
+    #[cfg(feature = "csharp")]
+    csharp::generate(&bindings, &header_dir.join("xshm.cs"));
 }
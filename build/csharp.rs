@@ -0,0 +1,535 @@
+//! Генерация `include/xshm.cs` (feature `csharp`).
+//!
+//! Источник -- тот же разбор cbindgen, из которого пишется `xshm.h`, так что
+//! P/Invoke-объявления не расходятся с заголовком. Правила отображения:
+//!
+//! - структуры -- `LayoutKind.Sequential`, `bool` -- `MarshalAs(U1)`;
+//! - указатели на функции в структурах -- `IntPtr` плюс вложенный
+//!   `cdecl`-делегат `<поле>_fn` для `Marshal.GetFunctionPointerForDelegate`;
+//! - `void*` и handle-typedef'ы -- `IntPtr`, `const uint16_t*` -- `LPWStr`,
+//!   остальные указатели -- unsafe-указатели C#;
+//! - константы профилей колец (`ring-*`) и всё, что из них вычисляется, --
+//!   во вложенных классах `NativeMethods.Ring*`, по одному на профиль:
+//!   файл не зависит от фич сборки, которая его сгенерировала.
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use cbindgen::ir::{
+    Cfg, Documentation, Enum, Function, IntKind, ItemContainer, Literal, PrimitiveType, Struct,
+    Type,
+};
+use cbindgen::Bindings;
+
+/// Профили колец: фича (`None` -- профиль по умолчанию) и вложенный класс.
+const RING_PROFILES: &[(Option<&str>, &str)] = &[
+    (None, "RingDefault"),
+    (Some("ring-64k"), "Ring64K"),
+    (Some("ring-256k"), "Ring256K"),
+    (Some("ring-8m"), "Ring8M"),
+];
+
+const KEYWORDS: &[&str] = &[
+    "base",
+    "checked",
+    "class",
+    "default",
+    "delegate",
+    "event",
+    "fixed",
+    "in",
+    "internal",
+    "lock",
+    "namespace",
+    "object",
+    "operator",
+    "out",
+    "params",
+    "ref",
+    "string",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Param,
+    Field,
+    Callback,
+}
+
+struct Generator<'a> {
+    bindings: &'a Bindings,
+    /// Typedef'ы на `void` и opaque-типы: за указателем на них -- `IntPtr`.
+    opaque: HashSet<String>,
+    out: String,
+}
+
+pub fn generate(bindings: &Bindings, path: &Path) {
+    let opaque = bindings
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            ItemContainer::OpaqueItem(item) => Some(item.export_name.clone()),
+            ItemContainer::Typedef(typedef)
+                if matches!(typedef.aliased, Type::Primitive(PrimitiveType::Void)) =>
+            {
+                Some(typedef.export_name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut generator = Generator {
+        bindings,
+        opaque,
+        out: String::new(),
+    };
+    generator.file();
+    std::fs::write(path, generator.out).expect("write xshm.cs");
+}
+
+impl Generator<'_> {
+    fn file(&mut self) {
+        self.out.push_str(
+            "// <auto-generated>\n\
+             // Сгенерировано build.rs (feature `csharp`) по тому же разбору, что и xshm.h.\n\
+             // Не редактировать вручную.\n\
+             // </auto-generated>\n\
+             using System;\n\
+             using System.Collections.Generic;\n\
+             using System.Runtime.InteropServices;\n\n\
+             namespace Xshm\n{\n",
+        );
+        for item in &self.bindings.items {
+            match item {
+                ItemContainer::Enum(item) if cfg_enabled(item.cfg.as_ref(), None) => {
+                    self.enumeration(item)
+                }
+                ItemContainer::Struct(item) if cfg_enabled(item.cfg.as_ref(), None) => {
+                    self.structure(item)
+                }
+                _ => {}
+            }
+        }
+        self.native_methods();
+        self.out.push_str(CALLBACK_REGISTRATION);
+        self.out.push_str("}\n");
+    }
+
+    fn enumeration(&mut self, item: &Enum) {
+        self.doc(&item.documentation, 1);
+        let _ = writeln!(
+            self.out,
+            "    public enum {} : int\n    {{",
+            item.export_name
+        );
+        for variant in &item.variants {
+            self.doc(&variant.documentation, 2);
+            match variant.discriminant.as_ref().and_then(literal) {
+                Some(value) => {
+                    let _ = writeln!(self.out, "        {} = {value},", variant.export_name);
+                }
+                None => {
+                    let _ = writeln!(self.out, "        {},", variant.export_name);
+                }
+            }
+        }
+        self.out.push_str("    }\n\n");
+    }
+
+    fn structure(&mut self, item: &Struct) {
+        self.doc(&item.documentation, 1);
+        let _ = writeln!(
+            self.out,
+            "    [StructLayout(LayoutKind.Sequential)]\n    public unsafe struct {}\n    {{",
+            item.export_name
+        );
+        for field in &item.fields {
+            self.doc(&field.documentation, 2);
+            let name = ident(&field.name);
+            if let Type::FuncPtr { ret, args, .. } = &field.ty {
+                let params = args
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (arg, ty))| {
+                        let arg = arg.clone().unwrap_or_else(|| format!("arg{index}"));
+                        format!("{} {}", self.ty(ty, Position::Callback), ident(&arg))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let _ = writeln!(
+                    self.out,
+                    "        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]\n        \
+                     public delegate {} {}_fn({params});",
+                    self.ty(ret, Position::Callback),
+                    field.name
+                );
+                let _ = writeln!(self.out, "        public IntPtr {name};");
+            } else {
+                let marshal = match field.ty {
+                    Type::Primitive(PrimitiveType::Bool) => "[MarshalAs(UnmanagedType.U1)] ",
+                    _ => "",
+                };
+                let _ = writeln!(
+                    self.out,
+                    "        {marshal}public {} {name};",
+                    self.ty(&field.ty, Position::Field)
+                );
+            }
+        }
+        self.out.push_str("    }\n\n");
+    }
+
+    fn native_methods(&mut self) {
+        self.out.push_str(
+            "    public static unsafe partial class NativeMethods\n    {\n        \
+             public const string Lib = \"xshm\";\n\n",
+        );
+        let profiled = self.profiled_constants();
+        self.constants(None, |name| !profiled.contains(name), "        ");
+        for (feature, class) in RING_PROFILES {
+            let profile = match feature {
+                Some(feature) => format!("фичи `{feature}`"),
+                None => "по умолчанию".to_owned(),
+            };
+            let _ = writeln!(
+                self.out,
+                "\n        /// <summary>Профиль колец {profile} (XSHM_RING_* в xshm.h).</summary>\n        \
+                 public static class {class}\n        {{"
+            );
+            self.constants(*feature, |name| profiled.contains(name), "            ");
+            self.out.push_str("        }\n");
+        }
+        self.out.push('\n');
+        for function in &self.bindings.functions {
+            if cfg_enabled(function.cfg.as_ref(), None) {
+                self.function(function);
+            }
+        }
+        self.verify_layout();
+        self.out.push_str("    }\n\n");
+    }
+
+    /// Константы под `#[cfg]` с `ring-*` и те, что ссылаются на них.
+    fn profiled_constants(&self) -> HashSet<String> {
+        let mut profiled: HashSet<String> = self
+            .bindings
+            .constants
+            .iter()
+            .filter(|constant| constant.cfg.as_ref().is_some_and(mentions_ring_profile))
+            .map(|constant| constant.export_name.clone())
+            .collect();
+        loop {
+            let dependent: Vec<String> = self
+                .bindings
+                .constants
+                .iter()
+                .filter(|constant| !profiled.contains(&constant.export_name))
+                .filter(|constant| {
+                    literal(&constant.value).is_some_and(|value| {
+                        value
+                            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                            .any(|word| profiled.contains(word))
+                    })
+                })
+                .map(|constant| constant.export_name.clone())
+                .collect();
+            if dependent.is_empty() {
+                return profiled;
+            }
+            profiled.extend(dependent);
+        }
+    }
+
+    /// Целочисленные константы, выбранные `include`, для профиля колец
+    /// `ring`.
+    fn constants(&mut self, ring: Option<&str>, include: impl Fn(&str) -> bool, indent: &str) {
+        let mut seen = HashSet::new();
+        for constant in &self.bindings.constants {
+            if !include(&constant.export_name)
+                || !cfg_enabled(constant.cfg.as_ref(), ring)
+                || !seen.insert(&constant.export_name)
+            {
+                continue;
+            }
+            let (Type::Primitive(ty @ PrimitiveType::Integer { .. }), Some(value)) =
+                (&constant.ty, literal(&constant.value))
+            else {
+                continue;
+            };
+            // IntPtr/UIntPtr не бывают const: usize-константы -- ulong.
+            let ty = match primitive(ty) {
+                "IntPtr" => "long",
+                "UIntPtr" => "ulong",
+                ty => ty,
+            };
+            let _ = writeln!(
+                self.out,
+                "{indent}public const {ty} {} = unchecked(({ty})({value}));",
+                constant.export_name
+            );
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.doc(&function.documentation, 2);
+        let _ = writeln!(
+            self.out,
+            "        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]"
+        );
+        if matches!(function.ret, Type::Primitive(PrimitiveType::Bool)) {
+            self.out
+                .push_str("        [return: MarshalAs(UnmanagedType.U1)]\n");
+        }
+        let params = function
+            .args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                let name = arg.name.clone().unwrap_or_else(|| format!("arg{index}"));
+                format!("{} {}", self.ty(&arg.ty, Position::Param), ident(&name))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(
+            self.out,
+            "        public static extern {} {}({params});\n",
+            self.ty(&function.ret, Position::Field),
+            function.path.name()
+        );
+    }
+
+    /// Сверка `Marshal.SizeOf` с `shm_abi_layout()`: поле `<x>_size`
+    /// соответствует структуре `shm_<x>_t`, `pointer_size` -- `IntPtr.Size`.
+    fn verify_layout(&mut self) {
+        let Some(layout) = self.bindings.items.iter().find_map(|item| match item {
+            ItemContainer::Struct(item) if item.export_name == "shm_abi_layout_t" => Some(item),
+            _ => None,
+        }) else {
+            return;
+        };
+        self.out.push_str(
+            "        /// <summary>\n        \
+             /// Сверяет раскладку структур с библиотекой; вызывать один раз при загрузке.\n        \
+             /// </summary>\n        \
+             public static void VerifyLayout()\n        {\n            \
+             shm_abi_layout_t layout = shm_abi_layout();\n            \
+             if (layout.abi_version != SHM_ABI_VERSION)\n                \
+             throw new InvalidOperationException(\"xshm ABI version \" + layout.abi_version + \", expected \" + SHM_ABI_VERSION);\n",
+        );
+        for field in &layout.fields {
+            let Some(stem) = field.name.strip_suffix("_size") else {
+                continue;
+            };
+            if stem == "pointer" {
+                let _ = writeln!(
+                    self.out,
+                    "            CheckSize(\"pointer\", layout.pointer_size, IntPtr.Size);"
+                );
+                continue;
+            }
+            let _ = writeln!(
+                self.out,
+                "            CheckSize(\"shm_{stem}_t\", layout.{}, Marshal.SizeOf<shm_{stem}_t>());",
+                field.name
+            );
+        }
+        self.out.push_str(
+            "        }\n\n        \
+             private static void CheckSize(string name, uint native, int managed)\n        {\n            \
+             if (native != managed)\n                \
+             throw new InvalidOperationException(name + \": native size \" + native + \", managed \" + managed);\n        \
+             }\n",
+        );
+    }
+
+    fn ty(&self, ty: &Type, position: Position) -> String {
+        match ty {
+            Type::Primitive(PrimitiveType::Bool) if position != Position::Field => {
+                "[MarshalAs(UnmanagedType.U1)] bool".to_owned()
+            }
+            Type::Primitive(ty) => primitive(ty).to_owned(),
+            Type::Path(path) => match self.resolve(path.export_name()) {
+                Some(aliased) => self.ty(aliased, position),
+                None => path.export_name().to_owned(),
+            },
+            Type::Ptr { .. } | Type::FuncPtr { .. } if position == Position::Callback => {
+                "IntPtr".to_owned()
+            }
+            Type::Ptr { ty, is_const, .. } => match &**ty {
+                Type::Primitive(PrimitiveType::Void) => "IntPtr".to_owned(),
+                Type::Path(path) if self.opaque.contains(path.export_name()) => "IntPtr".to_owned(),
+                Type::Primitive(PrimitiveType::Char) => "byte*".to_owned(),
+                Type::Primitive(PrimitiveType::Integer {
+                    signed: false,
+                    kind: IntKind::B16,
+                    ..
+                }) if *is_const && position == Position::Param => {
+                    "[MarshalAs(UnmanagedType.LPWStr)] string".to_owned()
+                }
+                inner => format!("{}*", self.ty(inner, Position::Field)),
+            },
+            Type::FuncPtr { .. } => "IntPtr".to_owned(),
+            Type::Array(..) => panic!("xshm.cs: массивы в C API не поддержаны"),
+        }
+    }
+
+    /// Typedef на не-`void` раскрывается до исходного типа.
+    fn resolve(&self, name: &str) -> Option<&Type> {
+        self.bindings.items.iter().find_map(|item| match item {
+            ItemContainer::Typedef(typedef)
+                if typedef.export_name == name && !self.opaque.contains(name) =>
+            {
+                Some(&typedef.aliased)
+            }
+            _ => None,
+        })
+    }
+
+    fn doc(&mut self, doc: &Documentation, depth: usize) {
+        if doc.doc_comment.is_empty() {
+            return;
+        }
+        let indent = "    ".repeat(depth);
+        let _ = writeln!(self.out, "{indent}/// <summary>");
+        for line in &doc.doc_comment {
+            let line = line
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            let _ = writeln!(self.out, "{indent}///{line}");
+        }
+        let _ = writeln!(self.out, "{indent}/// </summary>");
+    }
+}
+
+fn primitive(ty: &PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::Void => "void",
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::Char | PrimitiveType::SChar => "sbyte",
+        PrimitiveType::UChar => "byte",
+        PrimitiveType::Char32 => "uint",
+        PrimitiveType::Float => "float",
+        PrimitiveType::Double => "double",
+        PrimitiveType::VaList => panic!("xshm.cs: va_list не поддержан"),
+        PrimitiveType::PtrDiffT => "IntPtr",
+        PrimitiveType::Integer { signed, kind, .. } => match (kind, signed) {
+            (IntKind::B8, true) => "sbyte",
+            (IntKind::B8, false) => "byte",
+            (IntKind::B16 | IntKind::Short, true) => "short",
+            (IntKind::B16 | IntKind::Short, false) => "ushort",
+            (IntKind::B32 | IntKind::Int, true) => "int",
+            (IntKind::B32 | IntKind::Int, false) => "uint",
+            (IntKind::B64 | IntKind::LongLong, true) => "long",
+            (IntKind::B64 | IntKind::LongLong, false) => "ulong",
+            (IntKind::Long | IntKind::Size | IntKind::SizeT, true) => "IntPtr",
+            (IntKind::Long | IntKind::Size | IntKind::SizeT, false) => "UIntPtr",
+        },
+    }
+}
+
+fn literal(value: &Literal) -> Option<String> {
+    Some(match value {
+        Literal::Expr(expr) => expr.clone(),
+        Literal::Path { name, .. } => name.clone(),
+        Literal::PostfixUnaryOp { op, value } => format!("{op}{}", literal(value)?),
+        Literal::BinOp { left, op, right } => {
+            format!("({} {op} {})", literal(left)?, literal(right)?)
+        }
+        Literal::Cast {
+            ty: Type::Primitive(ty),
+            value,
+        } => format!("(({}){})", primitive(ty), literal(value)?),
+        _ => return None,
+    })
+}
+
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("@{name}")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// `#[cfg]` элемента относительно текущей сборки: тестовые и
+/// инструментальные варианты (`loom`, `fuzzing`, `test`) отбрасываются.
+/// Фичи `ring-*` берутся не из сборки, а из `ring` -- профиля, для которого
+/// пишется класс констант (`None` -- профиль по умолчанию).
+fn cfg_enabled(cfg: Option<&Cfg>, ring: Option<&str>) -> bool {
+    let Some(cfg) = cfg else {
+        return true;
+    };
+    match cfg {
+        Cfg::Boolean(key) => env::var("CARGO_CFG_TARGET_FAMILY")
+            .is_ok_and(|families| families.split(',').any(|family| family == key)),
+        Cfg::Named(key, value) if is_ring_profile(key, value) => ring == Some(value.as_str()),
+        Cfg::Named(key, value) if key == "feature" => env::var_os(format!(
+            "CARGO_FEATURE_{}",
+            value.to_uppercase().replace('-', "_")
+        ))
+        .is_some(),
+        Cfg::Named(key, value) => env::var(format!("CARGO_CFG_{}", key.to_uppercase()))
+            .is_ok_and(|actual| actual.split(',').any(|actual| actual == value)),
+        Cfg::Any(cfgs) => cfgs.iter().any(|cfg| cfg_enabled(Some(cfg), ring)),
+        Cfg::All(cfgs) => cfgs.iter().all(|cfg| cfg_enabled(Some(cfg), ring)),
+        Cfg::Not(cfg) => !cfg_enabled(Some(cfg), ring),
+    }
+}
+
+fn is_ring_profile(key: &str, value: &str) -> bool {
+    key == "feature" && value.starts_with("ring-")
+}
+
+fn mentions_ring_profile(cfg: &Cfg) -> bool {
+    match cfg {
+        Cfg::Boolean(_) => false,
+        Cfg::Named(key, value) => is_ring_profile(key, value),
+        Cfg::Any(cfgs) | Cfg::All(cfgs) => cfgs.iter().any(mentions_ring_profile),
+        Cfg::Not(cfg) => mentions_ring_profile(cfg),
+    }
+}
+
+const CALLBACK_REGISTRATION: &str = r#"    /// <summary>
+    /// Удерживает делегаты callbacks и GCHandle для user_data, пока нативная
+    /// сторона может их вызвать. Dispose -- только после возврата из
+    /// shm_*_stop / shm_*_disconnect: остановка синхронна, поздних вызовов нет.
+    /// </summary>
+    public sealed class CallbackRegistration : IDisposable
+    {
+        private readonly List<Delegate> _delegates = new List<Delegate>();
+        private GCHandle _target;
+
+        public CallbackRegistration(object target)
+        {
+            _target = GCHandle.Alloc(target);
+        }
+
+        /// <summary>Значение для поля user_data (обычный, не pinned GCHandle).</summary>
+        public IntPtr UserData
+        {
+            get { return GCHandle.ToIntPtr(_target); }
+        }
+
+        /// <summary>Указатель на функцию для поля callbacks; делегат живёт до Dispose.</summary>
+        public IntPtr Add(Delegate callback)
+        {
+            _delegates.Add(callback);
+            return Marshal.GetFunctionPointerForDelegate(callback);
+        }
+
+        /// <summary>Объект, переданный в конструктор, по user_data из callback'а.</summary>
+        public static T Target<T>(IntPtr userData) where T : class
+        {
+            return (T)GCHandle.FromIntPtr(userData).Target;
+        }
+
+        public void Dispose()
+        {
+            if (_target.IsAllocated)
+                _target.Free();
+            _delegates.Clear();
+        }
+    }
+"#;
@@ -0,0 +1,14 @@
+//! Запрос регистрации в лобби от произвольного (враждебного) клиента.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xshm::dispatch::protocol::{decode_request, encode_request};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(request) = decode_request(data) {
+        // Разобранное обязано кодироваться обратно без потерь в полях.
+        let again = decode_request(&encode_request(&request)).unwrap();
+        assert_eq!(again.pid, request.pid);
+        assert_eq!(again.revision, request.revision);
+    }
+});
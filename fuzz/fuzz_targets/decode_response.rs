@@ -0,0 +1,13 @@
+//! Ответ лобби, подделанный процессом, захватившим имя сервера.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use xshm::dispatch::protocol::{decode_response, encode_response};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(response) = decode_response(data) {
+        let again = decode_response(&encode_response(&response)).unwrap();
+        assert_eq!(again.status, response.status);
+        assert_eq!(again.client_id, response.client_id);
+    }
+});
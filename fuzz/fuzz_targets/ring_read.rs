@@ -0,0 +1,8 @@
+//! Пир записал мусор в заголовок и данные кольца.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    xshm::fuzz::ring_with_hostile_header(data);
+});
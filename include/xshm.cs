@@ -0,0 +1,1216 @@
+// <auto-generated>
+// Сгенерировано build.rs (feature `csharp`) по тому же разбору, что и xshm.h.
+// Не редактировать вручную.
+// </auto-generated>
+using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
+
+namespace Xshm
+{
+    public enum shm_error_t : int
+    {
+        SHM_SUCCESS = 0,
+        SHM_ERROR_INVALID_PARAM = -1,
+        SHM_ERROR_MEMORY = -2,
+        SHM_ERROR_TIMEOUT = -3,
+        SHM_ERROR_EMPTY = -4,
+        SHM_ERROR_EXISTS = -5,
+        SHM_ERROR_NOT_FOUND = -6,
+        SHM_ERROR_ACCESS = -7,
+        SHM_ERROR_NOT_READY = -8,
+        SHM_ERROR_PROTOCOL = -9,
+        SHM_ERROR_FULL = -10,
+        SHM_ERROR_NO_SLOT = -11,
+        SHM_ERROR_CHECKSUM = -12,
+        SHM_ERROR_WORKER_PANICKED = -13,
+    }
+
+    public enum shm_direction_t : int
+    {
+        SHM_DIR_SERVER_TO_CLIENT = 0,
+        SHM_DIR_CLIENT_TO_SERVER = 1,
+    }
+
+    /// <summary>
+    /// Callbacks на стороне сервера.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_dispatch_callbacks_t
+    {
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_client_connect_fn(uint client_id, uint pid, ushort revision, IntPtr name, IntPtr user_data);
+        public IntPtr on_client_connect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_client_disconnect_fn(uint client_id, IntPtr user_data);
+        public IntPtr on_client_disconnect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_message_fn(uint client_id, IntPtr data, uint size, IntPtr user_data);
+        public IntPtr on_message;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_error_fn(int client_id, shm_error_t error, IntPtr user_data);
+        public IntPtr on_error;
+        public IntPtr user_data;
+    }
+
+    /// <summary>
+    /// Настройки сервера; нулевые поля -- значения по умолчанию.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_dispatch_options_t
+    {
+        public uint lobby_timeout_ms;
+        public uint channel_connect_timeout_ms;
+        public uint poll_timeout_ms;
+        public uint recv_batch;
+    }
+
+    /// <summary>
+    /// Данные регистрации, передаваемые от C-клиента.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_dispatch_registration_t
+    {
+        public uint pid;
+        public ushort revision;
+        public byte* name;
+    }
+
+    /// <summary>
+    /// Callbacks на стороне клиента.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_dispatch_client_callbacks_t
+    {
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_connect_fn(uint client_id, IntPtr channel_name, IntPtr user_data);
+        public IntPtr on_connect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_disconnect_fn(IntPtr user_data);
+        public IntPtr on_disconnect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_message_fn(IntPtr data, uint size, IntPtr user_data);
+        public IntPtr on_message;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_error_fn(shm_error_t error, IntPtr user_data);
+        public IntPtr on_error;
+        public IntPtr user_data;
+    }
+
+    /// <summary>
+    /// Настройки клиента; нулевые поля -- значения по умолчанию.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_dispatch_client_options_t
+    {
+        public uint lobby_timeout_ms;
+        public uint response_timeout_ms;
+        public uint channel_timeout_ms;
+        public uint poll_timeout_ms;
+        public uint recv_batch;
+        public uint max_send_queue;
+    }
+
+    /// <summary>
+    /// Настройки auto-режима; нулевые таймауты и размеры -- значения по
+    /// умолчанию.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_auto_options_t
+    {
+        public uint poll_timeout_ms;
+        public uint reconnect_delay_ms;
+        public uint connect_timeout_ms;
+        public uint max_send_queue;
+        public uint recv_batch;
+        [MarshalAs(UnmanagedType.U1)] public bool checksum;
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_endpoint_config_t
+    {
+        public byte* name;
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_callbacks_t
+    {
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_connect_fn(IntPtr user_data);
+        public IntPtr on_connect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_disconnect_fn(IntPtr user_data);
+        public IntPtr on_disconnect;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_data_available_fn(IntPtr user_data);
+        public IntPtr on_data_available;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_space_available_fn(IntPtr user_data);
+        public IntPtr on_space_available;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_error_fn(shm_error_t error, IntPtr user_data);
+        public IntPtr on_error;
+        public IntPtr user_data;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_message_fn(shm_direction_t direction, IntPtr data, uint size, IntPtr user_data);
+        public IntPtr on_message;
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_overflow_fn(shm_direction_t direction, uint dropped, IntPtr user_data);
+        public IntPtr on_overflow;
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_auto_stats_t
+    {
+        public ulong sent_messages;
+        public ulong send_overflows;
+        public ulong received_messages;
+        public ulong receive_overflows;
+        public ulong checksum_errors;
+    }
+
+    /// <summary>
+    /// Опции для мультиклиентного сервера (нулевые поля -- по умолчанию)
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_multi_options_t
+    {
+        /// <summary>
+        /// Максимальное количество клиентов (по умолчанию 20)
+        /// </summary>
+        public uint max_clients;
+        /// <summary>
+        /// Таймаут ожидания событий в мс (по умолчанию 50)
+        /// </summary>
+        public uint poll_timeout_ms;
+        /// <summary>
+        /// Количество сообщений за один цикл (по умолчанию 32)
+        /// </summary>
+        public uint recv_batch;
+    }
+
+    /// <summary>
+    /// Callbacks для мультиклиентного сервера
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_multi_callbacks_t
+    {
+        /// <summary>
+        /// Вызывается при подключении клиента
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_client_connect_fn(uint client_id, IntPtr user_data);
+        public IntPtr on_client_connect;
+        /// <summary>
+        /// Вызывается при отключении клиента
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_client_disconnect_fn(uint client_id, IntPtr user_data);
+        public IntPtr on_client_disconnect;
+        /// <summary>
+        /// Вызывается при получении сообщения
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_message_fn(uint client_id, IntPtr data, uint size, IntPtr user_data);
+        public IntPtr on_message;
+        /// <summary>
+        /// Вызывается при ошибке (client_id = u32::MAX для общих ошибок)
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_error_fn(uint client_id, shm_error_t error, IntPtr user_data);
+        public IntPtr on_error;
+        /// <summary>
+        /// Пользовательские данные, передаются во все callbacks
+        /// </summary>
+        public IntPtr user_data;
+    }
+
+    /// <summary>
+    /// Опции для мультиклиента (нулевые поля -- по умолчанию)
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_multi_client_options_t
+    {
+        /// <summary>
+        /// Таймаут подключения к слоту в мс (по умолчанию 5000)
+        /// </summary>
+        public uint slot_timeout_ms;
+        /// <summary>
+        /// Таймаут ожидания событий в мс (по умолчанию 50)
+        /// </summary>
+        public uint poll_timeout_ms;
+        /// <summary>
+        /// Количество сообщений за один цикл (по умолчанию 32)
+        /// </summary>
+        public uint recv_batch;
+        /// <summary>
+        /// Максимум неотправленных сообщений во внутренней очереди перед сбросом
+        /// самого старого (по умолчанию 256)
+        /// </summary>
+        public uint max_send_queue;
+    }
+
+    /// <summary>
+    /// Callbacks для мультиклиента
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_multi_client_callbacks_t
+    {
+        /// <summary>
+        /// Вызывается при успешном подключении (slot_id — назначенный слот)
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_connect_fn(uint slot_id, IntPtr user_data);
+        public IntPtr on_connect;
+        /// <summary>
+        /// Вызывается при отключении
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_disconnect_fn(IntPtr user_data);
+        public IntPtr on_disconnect;
+        /// <summary>
+        /// Вызывается при получении сообщения от сервера
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_message_fn(IntPtr data, uint size, IntPtr user_data);
+        public IntPtr on_message;
+        /// <summary>
+        /// Вызывается при переполнении внутренней send-очереди (`max_send_queue`)
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_overflow_fn(uint dropped, IntPtr user_data);
+        public IntPtr on_overflow;
+        /// <summary>
+        /// Вызывается при ошибке
+        /// </summary>
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate void on_error_fn(shm_error_t error, IntPtr user_data);
+        public IntPtr on_error;
+        /// <summary>
+        /// Пользовательские данные
+        /// </summary>
+        public IntPtr user_data;
+    }
+
+    /// <summary>
+    /// Размеры структур C API в байтах (для сверки с `Marshal.SizeOf`).
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe struct shm_abi_layout_t
+    {
+        public uint abi_version;
+        public uint pointer_size;
+        public uint endpoint_config_size;
+        public uint callbacks_size;
+        public uint auto_options_size;
+        public uint auto_stats_size;
+        public uint multi_options_size;
+        public uint multi_callbacks_size;
+        public uint multi_client_options_size;
+        public uint multi_client_callbacks_size;
+        public uint dispatch_registration_size;
+        public uint dispatch_callbacks_size;
+        public uint dispatch_client_callbacks_size;
+        public uint dispatch_options_size;
+        public uint dispatch_client_options_size;
+    }
+
+    public static unsafe partial class NativeMethods
+    {
+        public const string Lib = "xshm";
+
+        public const uint SHARED_MAGIC = unchecked((uint)(1481853005));
+        public const uint SHARED_VERSION = unchecked((uint)(131072));
+        public const uint SHARED_VERSION_MIN_COMPATIBLE = unchecked((uint)(131072));
+        public const uint SHARED_VERSION_MAX_COMPATIBLE = unchecked((uint)(196607));
+        public const ulong CONTROL_RING_CAPACITY = unchecked((ulong)((16 * 1024)));
+        public const ulong MIN_MESSAGE_SIZE = unchecked((ulong)(2));
+        public const ulong MESSAGE_HEADER_SIZE = unchecked((ulong)(4));
+        public const ushort MSG_FLAG_CHECKSUM = unchecked((ushort)(32768));
+        public const ulong CHECKSUM_SIZE = unchecked((ulong)(4));
+        public const ushort MSG_FLAG_HANDLE = unchecked((ushort)(16384));
+        public const ulong HANDLE_FRAME_SIZE = unchecked((ulong)(8));
+        public const ushort MSG_FLAG_TIMESTAMP = unchecked((ushort)(8192));
+        public const ulong TIMESTAMP_SIZE = unchecked((ulong)(8));
+        public const ushort MSG_FLAG_DEADLINE = unchecked((ushort)(4096));
+        public const ulong DEADLINE_SIZE = unchecked((ulong)(8));
+        public const ushort MSG_FLAG_COMMIT = unchecked((ushort)(2048));
+        public const ushort MSG_COMMIT_LAP_MASK = unchecked((ushort)(1792));
+        public const ushort MSG_FLAG_CHUNK = unchecked((ushort)(128));
+        public const ushort MSG_FLAG_SEQUENCE = unchecked((ushort)(64));
+        public const ulong SEQUENCE_SIZE = unchecked((ulong)(4));
+        public const ushort MSG_USER_FLAGS_MASK = unchecked((ushort)(63));
+        public const ulong CHUNK_HEADER_SIZE = unchecked((ulong)(8));
+        public const ulong MAX_CHUNKED_MESSAGE_SIZE = unchecked((ulong)((64 << 20)));
+        public const uint HANDSHAKE_IDLE = unchecked((uint)(0));
+        public const uint HANDSHAKE_CLIENT_HELLO = unchecked((uint)(1));
+        public const uint HANDSHAKE_SERVER_READY = unchecked((uint)(2));
+        public const uint SLOT_ID_NO_SLOT = unchecked((uint)(4294967295));
+        public const ulong RESERVED_CLAIM_INDEX = unchecked((ulong)(0));
+        public const uint CLAIM_FREE = unchecked((uint)(0));
+        public const ulong RESERVED_OWNER_PID_INDEX = unchecked((ulong)(1));
+        public const ulong RESERVED_SERVER_PID_INDEX = unchecked((ulong)(2));
+        public const ulong RESERVED_CLIENT_PID_INDEX = unchecked((ulong)(3));
+        public const ulong RESERVED_LAYOUT_FLAGS_INDEX = unchecked((ulong)(4));
+        public const uint LAYOUT_FLAG_CONTROL_RINGS = unchecked((uint)(1));
+        public const uint LAYOUT_RING_SHIFT_MASK = unchecked((uint)(16128));
+        public const uint LAYOUT_RING_SHIFT_OFFSET = unchecked((uint)(8));
+        public const ulong RESERVED_SERVER_FEATURES_INDEX = unchecked((ulong)(5));
+        public const ulong RESERVED_CLIENT_FEATURES_INDEX = unchecked((ulong)(6));
+        public const ulong RESERVED_NEGOTIATED_FEATURES_INDEX = unchecked((ulong)(7));
+        public const ulong RESERVED_CLIENT_VERSION_INDEX = unchecked((ulong)(8));
+        public const ulong RESERVED_UPGRADE_TOKEN_INDEX = unchecked((ulong)(9));
+        public const ulong RESERVED_RING_GEOMETRY_INDEX = unchecked((ulong)(10));
+        public const uint GEOMETRY_MAX_MESSAGES_MASK = unchecked((uint)(65535));
+        public const uint GEOMETRY_RING_SHIFT_MASK = unchecked((uint)(16711680));
+        public const uint GEOMETRY_RING_SHIFT_OFFSET = unchecked((uint)(16));
+        public const uint GEOMETRY_LAYOUT_VERSION_MASK = unchecked((uint)(4278190080));
+        public const uint GEOMETRY_LAYOUT_VERSION_OFFSET = unchecked((uint)(24));
+        public const ulong RESERVED_SERVER_HEARTBEAT_INDEX = unchecked((ulong)(11));
+        public const ulong RESERVED_CLIENT_HEARTBEAT_INDEX = unchecked((ulong)(12));
+        public const ulong RESERVED_SERVER_DISCONNECT_REASON_INDEX = unchecked((ulong)(13));
+        public const ulong RESERVED_CLIENT_DISCONNECT_REASON_INDEX = unchecked((ulong)(14));
+        public const uint DISCONNECT_REASON_UNKNOWN = unchecked((uint)(0));
+        public const uint DISCONNECT_REASON_GRACEFUL = unchecked((uint)(1));
+        public const uint DISCONNECT_REASON_PROTOCOL_ERROR = unchecked((uint)(2));
+        public const uint DISCONNECT_REASON_KICKED = unchecked((uint)(3));
+        public const uint DISCONNECT_REASON_UPGRADING = unchecked((uint)(4));
+        public const ulong CONTROL_RESERVED_SLOTS = unchecked((ulong)(27));
+        public const uint LAYOUT_VERSION = unchecked((uint)(1));
+        public const uint FEATURE_CHECKSUM = unchecked((uint)(1));
+        public const uint FEATURE_CONTROL_RINGS = unchecked((uint)(2));
+        public const uint FEATURE_CREDITS = unchecked((uint)(4));
+        public const uint FEATURE_TIMESTAMPS = unchecked((uint)(8));
+        public const uint FEATURE_HANDLES = unchecked((uint)(16));
+        public const uint FEATURE_TTL = unchecked((uint)(32));
+        public const uint FEATURE_CHUNKING = unchecked((uint)(64));
+        public const uint FEATURE_SEQUENCE = unchecked((uint)(128));
+        public const uint SUPPORTED_FEATURES = unchecked((uint)((((((((FEATURE_CHECKSUM | FEATURE_CONTROL_RINGS) | FEATURE_CREDITS) | FEATURE_TIMESTAMPS) | FEATURE_HANDLES) | FEATURE_TTL) | FEATURE_CHUNKING) | FEATURE_SEQUENCE)));
+        public const uint DEFAULT_FEATURES = unchecked((uint)((SUPPORTED_FEATURES & ~FEATURE_CHUNKING)));
+        public const uint AUTO_PEER_ID = unchecked((uint)(0));
+        public const ulong ARENA_ALIGN = unchecked((ulong)(64));
+        public const ulong ARENA_HANDLE_SIZE = unchecked((ulong)(12));
+        public const ulong BRIDGE_FRAME_HEADER = unchecked((ulong)(4));
+        public const ulong BROADCAST_FRAME_HEADER = unchecked((ulong)(12));
+        public const ulong MIN_BROADCAST_CAPACITY = unchecked((ulong)(4096));
+        public const ulong DUMP_WINDOW = unchecked((ulong)(64));
+        public const byte STATUS_OK = unchecked((byte)(0));
+        public const byte STATUS_REJECTED = unchecked((byte)(1));
+        public const uint DEFAULT_MAX_CLIENTS = unchecked((uint)(20));
+        public const uint MAX_MULTI_CLIENTS = unchecked((uint)(31));
+        public const ulong MAX_NAME_LEN = unchecked((ulong)(200));
+        public const uint SHM_ABI_VERSION = unchecked((uint)(1));
+        public const ulong RELIABLE_HEADER_SIZE = unchecked((ulong)(((1 + 8) + 8)));
+        public const ulong MAX_ACK_RANGES = unchecked((ulong)(32));
+        public const ulong STATE_CELLS = unchecked((ulong)(64));
+        public const ulong STATE_SLOTS = unchecked((ulong)(16));
+        public const ulong STATE_KEY_MAX = unchecked((ulong)(40));
+        public const ulong STATE_SLOT_SIZE = unchecked((ulong)(192));
+
+        /// <summary>Профиль колец по умолчанию (XSHM_RING_* в xshm.h).</summary>
+        public static class RingDefault
+        {
+            public const ulong RING_CAPACITY = unchecked((ulong)(((2 * 1024) * 1024)));
+            public const uint RING_MASK = unchecked((uint)((((uint)RING_CAPACITY) - 1)));
+            public const uint MAX_MESSAGES = unchecked((uint)(500));
+            public const ulong MAX_MESSAGE_SIZE = unchecked((ulong)(65535));
+            public const uint MIN_BYTES = unchecked((uint)(((uint)(((((MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE) + TIMESTAMP_SIZE) + DEADLINE_SIZE) + SEQUENCE_SIZE) + CHECKSUM_SIZE))));
+            public const ulong MAX_RELIABLE_PAYLOAD = unchecked((ulong)((MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE)));
+        }
+
+        /// <summary>Профиль колец фичи `ring-64k` (XSHM_RING_* в xshm.h).</summary>
+        public static class Ring64K
+        {
+            public const ulong RING_CAPACITY = unchecked((ulong)((64 * 1024)));
+            public const uint RING_MASK = unchecked((uint)((((uint)RING_CAPACITY) - 1)));
+            public const uint MAX_MESSAGES = unchecked((uint)(64));
+            public const ulong MAX_MESSAGE_SIZE = unchecked((ulong)(((16 * 1024) - 1)));
+            public const uint MIN_BYTES = unchecked((uint)(((uint)(((((MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE) + TIMESTAMP_SIZE) + DEADLINE_SIZE) + SEQUENCE_SIZE) + CHECKSUM_SIZE))));
+            public const ulong MAX_RELIABLE_PAYLOAD = unchecked((ulong)((MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE)));
+        }
+
+        /// <summary>Профиль колец фичи `ring-256k` (XSHM_RING_* в xshm.h).</summary>
+        public static class Ring256K
+        {
+            public const ulong RING_CAPACITY = unchecked((ulong)((256 * 1024)));
+            public const uint RING_MASK = unchecked((uint)((((uint)RING_CAPACITY) - 1)));
+            public const uint MAX_MESSAGES = unchecked((uint)(128));
+            public const ulong MAX_MESSAGE_SIZE = unchecked((ulong)(65535));
+            public const uint MIN_BYTES = unchecked((uint)(((uint)(((((MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE) + TIMESTAMP_SIZE) + DEADLINE_SIZE) + SEQUENCE_SIZE) + CHECKSUM_SIZE))));
+            public const ulong MAX_RELIABLE_PAYLOAD = unchecked((ulong)((MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE)));
+        }
+
+        /// <summary>Профиль колец фичи `ring-8m` (XSHM_RING_* в xshm.h).</summary>
+        public static class Ring8M
+        {
+            public const ulong RING_CAPACITY = unchecked((ulong)(((8 * 1024) * 1024)));
+            public const uint RING_MASK = unchecked((uint)((((uint)RING_CAPACITY) - 1)));
+            public const uint MAX_MESSAGES = unchecked((uint)(2000));
+            public const ulong MAX_MESSAGE_SIZE = unchecked((ulong)(65535));
+            public const uint MIN_BYTES = unchecked((uint)(((uint)(((((MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE) + TIMESTAMP_SIZE) + DEADLINE_SIZE) + SEQUENCE_SIZE) + CHECKSUM_SIZE))));
+            public const ulong MAX_RELIABLE_PAYLOAD = unchecked((ulong)((MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE)));
+        }
+
+        /// <summary>
+        /// # Safety
+        /// Все указатели обязаны быть валидны либо null там, где это задокументировано.
+        /// `name` обязан быть валидной C-строкой.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_dispatch_server_start(byte* name, shm_dispatch_callbacks_t* callbacks, shm_dispatch_options_t* options);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным DispatchServerHandle. `data` обязан указывать на `size` байт.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_dispatch_server_send_to(IntPtr handle, uint client_id, IntPtr data, uint size);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным. `data` обязан указывать на `size` байт. `sent_count` может быть null.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_dispatch_server_broadcast(IntPtr handle, IntPtr data, uint size, uint* sent_count);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным DispatchServerHandle либо null.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_dispatch_server_client_count(IntPtr handle);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным DispatchServerHandle либо null. Поглощает handle.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_dispatch_server_stop(IntPtr handle);
+
+        /// <summary>
+        /// # Safety
+        /// Все указатели обязаны быть валидны. `name` и `reg.name` обязаны быть валидными C-строками.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_dispatch_client_connect(byte* name, shm_dispatch_registration_t* reg, shm_dispatch_client_callbacks_t* callbacks, shm_dispatch_client_options_t* options);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным DispatchClientHandle. `data` обязан указывать на `size` байт.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_dispatch_client_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// # Safety
+        /// `handle` обязан быть валидным DispatchClientHandle либо null. Поглощает handle.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_dispatch_client_stop(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_dispatch_options_t shm_dispatch_options_default();
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_dispatch_client_options_t shm_dispatch_client_options_default();
+
+        /// <summary>
+        /// Текст последней ошибки текущего потока, например
+        /// `client 'Telemetry': connect: operation timed out`.
+        ///
+        /// Пишет в `buffer` не больше `capacity - 1` байт и завершающий NUL;
+        /// возвращает полную длину текста без NUL (больше `capacity - 1` -- текст
+        /// обрезан). 0 -- ошибок не было. `buffer` может быть NULL, чтобы узнать
+        /// длину.
+        ///
+        /// Запоминаются ошибки канала; неверные аргументы
+        /// (`SHM_ERROR_INVALID_PARAM` до обращения к каналу) и успешные вызовы
+        /// последнюю ошибку не меняют.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_last_error(byte* buffer, uint capacity);
+
+        /// <summary>
+        /// Код последней ошибки текущего потока (`SHM_SUCCESS`, если её нет).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_last_error_code();
+
+        /// <summary>
+        /// Сбрасывает последнюю ошибку текущего потока.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_clear_last_error();
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_auto_options_t shm_auto_options_default();
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_server_start_auto(shm_endpoint_config_t* config, shm_callbacks_t* callbacks, shm_auto_options_t* options);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send_auto(IntPtr handle, IntPtr data, uint size);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_server_stats_auto(IntPtr handle, shm_auto_stats_t* @out);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_server_stop_auto(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_client_connect_auto(shm_endpoint_config_t* config, shm_callbacks_t* callbacks, shm_auto_options_t* options);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send_auto(IntPtr handle, IntPtr data, uint size);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_client_stats_auto(IntPtr handle, shm_auto_stats_t* @out);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_client_disconnect_auto(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_server_start(shm_endpoint_config_t* config, shm_callbacks_t* callbacks);
+
+        /// <summary>
+        /// Как `shm_server_start`, но с управляющими кольцами: срочные сообщения
+        /// (`shm_server_send_control`/`shm_client_send_control`) читаются раньше
+        /// накопленных bulk-кадров. Клиент подключается обычным `shm_client_connect`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_server_start_control_rings(shm_endpoint_config_t* config, shm_callbacks_t* callbacks);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_wait_for_client(IntPtr handle, uint timeout_ms);
+
+        /// <summary>
+        /// Как `shm_server_wait_for_client`, но сначала отпускает прежнего клиента
+        /// (см. `SharedServer::accept_next_client`): сервер принимает клиентов по
+        /// очереди без пересоздания.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_accept_next_client(IntPtr handle, uint timeout_ms);
+
+        /// <summary>
+        /// PID процесса клиента из handshake; 0 -- неизвестен.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_server_peer_pid(IntPtr handle);
+
+        /// <summary>
+        /// Жив ли процесс клиента; без PID и при любой неясности -- `true`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_server_is_peer_process_alive(IntPtr handle);
+
+        /// <summary>
+        /// Жив ли клиент: соединение не сброшено, клиент не отключился и его
+        /// процесс жив (см. `SharedServer::is_peer_alive`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_server_is_peer_alive(IntPtr handle);
+
+        /// <summary>
+        /// Отмечает heartbeat сервера (см. `SharedServer::heartbeat`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_server_heartbeat(IntPtr handle);
+
+        /// <summary>
+        /// Клиент отмечал heartbeat не дольше `max_age_ms` назад; клиент без
+        /// heartbeat считается отзывчивым.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_server_is_peer_responsive(IntPtr handle, uint max_age_ms);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_server_stop(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// Как `shm_server_send`, но не ждёт и не вытесняет непрочитанное при любой
+        /// политике записи: нет места -- `SHM_ERROR_FULL`, ничего не отправлено.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_try_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// Отправляет, дожидаясь места не дольше `timeout_ms`: не дождался --
+        /// `SHM_ERROR_TIMEOUT`. Непрочитанное не вытесняется.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send_blocking(IntPtr handle, IntPtr data, uint size, uint timeout_ms);
+
+        /// <summary>
+        /// Отправляет `count` сообщений одной публикацией: клиент увидит либо все,
+        /// либо ни одного. Не хватает места -- `SHM_ERROR_FULL`, ничего не
+        /// отправлено.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send_batch_atomic(IntPtr handle, IntPtr* messages, uint* sizes, uint count);
+
+        /// <summary>
+        /// Срочное сообщение через управляющее кольцо. Канал без control-колец --
+        /// `SHM_ERROR_INVALID_PARAM`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send_control(IntPtr handle, IntPtr data, uint size);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_receive(IntPtr handle, IntPtr buffer, uint* size);
+
+        /// <summary>
+        /// Передать handle (секция, событие, файл) клиенту: handle дублируется в
+        /// процесс клиента, клиент получает новое значение через
+        /// `shm_client_receive_any`. Исходный handle остаётся за вызывающим.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_send_handle(IntPtr handle, IntPtr @object);
+
+        /// <summary>
+        /// Как `shm_server_receive`, но handle-кадры не пропускаются: для них
+        /// `*received_handle` -- handle в текущем процессе (закрывает вызывающий),
+        /// `*size` = 0. Для обычного сообщения `*received_handle` = NULL.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_receive_any(IntPtr handle, IntPtr buffer, uint* size, IntPtr* received_handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_poll(IntPtr handle, uint timeout_ms);
+
+        /// <summary>
+        /// Включить/выключить CRC-32 трейлер для сообщений клиенту.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_set_checksum(IntPtr handle, [MarshalAs(UnmanagedType.U1)] bool enabled);
+
+        /// <summary>
+        /// Кредитный режим для сообщений от клиента: не больше `messages`
+        /// непрочитанных сообщений и `bytes` байт. `messages == 0` -- выключить.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_set_credit_window(IntPtr handle, uint messages, uint bytes);
+
+        /// <summary>
+        /// Число сообщений от клиента, отброшенных из-за несовпадения CRC-32.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_server_checksum_errors(IntPtr handle);
+
+        /// <summary>
+        /// Срок годности сообщений клиенту по умолчанию в миллисекундах; 0 --
+        /// бессрочно.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_server_set_ttl(IntPtr handle, uint ttl_ms);
+
+        /// <summary>
+        /// Число просроченных сообщений от клиента, пропущенных или убранных.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_server_expired_messages(IntPtr handle);
+
+        /// <summary>
+        /// Возможности (`FEATURE_*`), согласованные с текущим клиентом; 0 -- нет
+        /// соединения или клиент старой версии.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_server_negotiated_features(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_client_connect(shm_endpoint_config_t* config, shm_callbacks_t* callbacks, uint timeout_ms);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_client_disconnect(IntPtr handle);
+
+        /// <summary>
+        /// PID процесса сервера; 0 -- неизвестен.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_client_peer_pid(IntPtr handle);
+
+        /// <summary>
+        /// См. `shm_server_is_peer_process_alive`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_client_is_peer_process_alive(IntPtr handle);
+
+        /// <summary>
+        /// См. `shm_server_is_peer_alive`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_client_is_peer_alive(IntPtr handle);
+
+        /// <summary>
+        /// См. `shm_server_heartbeat`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_client_heartbeat(IntPtr handle);
+
+        /// <summary>
+        /// См. `shm_server_is_peer_responsive`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_client_is_peer_responsive(IntPtr handle, uint max_age_ms);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_client_is_connected(IntPtr handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// См. `shm_server_try_send`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_try_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// См. `shm_server_send_blocking`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send_blocking(IntPtr handle, IntPtr data, uint size, uint timeout_ms);
+
+        /// <summary>
+        /// См. `shm_server_send_batch_atomic`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send_batch_atomic(IntPtr handle, IntPtr* messages, uint* sizes, uint count);
+
+        /// <summary>
+        /// См. `shm_server_send_control`.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send_control(IntPtr handle, IntPtr data, uint size);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_receive(IntPtr handle, IntPtr buffer, uint* size);
+
+        /// <summary>
+        /// Передать handle серверу (см. `shm_server_send_handle`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_send_handle(IntPtr handle, IntPtr @object);
+
+        /// <summary>
+        /// Приём сообщения или handle'а от сервера (см. `shm_server_receive_any`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_receive_any(IntPtr handle, IntPtr buffer, uint* size, IntPtr* received_handle);
+
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_poll(IntPtr handle, uint timeout_ms);
+
+        /// <summary>
+        /// Включить/выключить CRC-32 трейлер для сообщений серверу.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_set_checksum(IntPtr handle, [MarshalAs(UnmanagedType.U1)] bool enabled);
+
+        /// <summary>
+        /// Кредитный режим для сообщений от сервера (см.
+        /// `shm_server_set_credit_window`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_set_credit_window(IntPtr handle, uint messages, uint bytes);
+
+        /// <summary>
+        /// Число сообщений от сервера, отброшенных из-за несовпадения CRC-32.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_client_checksum_errors(IntPtr handle);
+
+        /// <summary>
+        /// Срок годности сообщений серверу по умолчанию (см. `shm_server_set_ttl`).
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_client_set_ttl(IntPtr handle, uint ttl_ms);
+
+        /// <summary>
+        /// Число просроченных сообщений от сервера, пропущенных или убранных.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_client_expired_messages(IntPtr handle);
+
+        /// <summary>
+        /// Возможности (`FEATURE_*`), согласованные с сервером; 0 -- сервер
+        /// старой версии.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_client_negotiated_features(IntPtr handle);
+
+        /// <summary>
+        /// Получить event handles для передачи в kernel driver
+        ///
+        /// Возвращает структуру с raw handles (isize) для event-driven IPC.
+        /// Для anonymous серверов (без событий) возвращает handles с нулевыми значениями.
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `out`: Указатель на структуру для записи handles (может быть NULL)
+        ///
+        /// # Returns
+        /// true если handles успешно получены, false при ошибке
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_server_get_event_handles(IntPtr handle, IntPtr @out);
+
+        /// <summary>
+        /// Получить опции по умолчанию
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_multi_options_t shm_multi_options_default();
+
+        /// <summary>
+        /// Получить callbacks по умолчанию (все NULL)
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_multi_callbacks_t shm_multi_callbacks_default();
+
+        /// <summary>
+        /// Запуск мультиклиентного сервера
+        ///
+        /// # Parameters
+        /// - `base_name`: Базовое имя канала (клиенты подключаются к "{base_name}_{slot_id}")
+        /// - `callbacks`: Callbacks для событий
+        /// - `options`: Опции сервера (NULL для значений по умолчанию)
+        ///
+        /// # Returns
+        /// Handle сервера или NULL при ошибке
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_multi_server_start(byte* base_name, shm_multi_callbacks_t* callbacks, shm_multi_options_t* options);
+
+        /// <summary>
+        /// Отправка сообщения конкретному клиенту
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `client_id`: ID клиента
+        /// - `data`: Данные для отправки
+        /// - `size`: Размер данных
+        ///
+        /// # Returns
+        /// SHM_SUCCESS или код ошибки
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_multi_server_send_to(IntPtr handle, uint client_id, IntPtr data, uint size);
+
+        /// <summary>
+        /// Отправка сообщения всем подключённым клиентам
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `data`: Данные для отправки
+        /// - `size`: Размер данных
+        /// - `sent_count`: (out) Количество клиентов, которым отправлено (может быть NULL)
+        ///
+        /// # Returns
+        /// SHM_SUCCESS или код ошибки
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_multi_server_broadcast(IntPtr handle, IntPtr data, uint size, uint* sent_count);
+
+        /// <summary>
+        /// Отключение клиента
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `client_id`: ID клиента для отключения
+        ///
+        /// # Returns
+        /// SHM_SUCCESS или код ошибки
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_multi_server_disconnect_client(IntPtr handle, uint client_id);
+
+        /// <summary>
+        /// Получение количества подключённых клиентов
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        ///
+        /// # Returns
+        /// Количество подключённых клиентов или 0 при ошибке
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_multi_server_client_count(IntPtr handle);
+
+        /// <summary>
+        /// Проверка подключения конкретного клиента
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `client_id`: ID клиента
+        ///
+        /// # Returns
+        /// true если клиент подключён
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_multi_server_is_client_connected(IntPtr handle, uint client_id);
+
+        /// <summary>
+        /// Получение списка подключённых клиентов
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `client_ids`: Буфер для записи ID клиентов
+        /// - `max_count`: Размер буфера
+        /// - `actual_count`: (out) Фактическое количество клиентов
+        ///
+        /// # Returns
+        /// SHM_SUCCESS или код ошибки
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_multi_server_get_clients(IntPtr handle, uint* client_ids, uint max_count, uint* actual_count);
+
+        /// <summary>
+        /// Получение имени канала для конкретного слота
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// - `slot_id`: ID слота
+        /// - `buffer`: Буфер для записи имени
+        /// - `buffer_size`: Размер буфера
+        ///
+        /// # Returns
+        /// Длина имени (без null-терминатора) или 0 при ошибке
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_multi_server_channel_name(IntPtr handle, uint slot_id, byte* buffer, uint buffer_size);
+
+        /// <summary>
+        /// Остановка мультиклиентного сервера
+        ///
+        /// # Parameters
+        /// - `handle`: Handle сервера
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_multi_server_stop(IntPtr handle);
+
+        /// <summary>
+        /// Получить опции клиента по умолчанию
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_multi_client_options_t shm_multi_client_options_default();
+
+        /// <summary>
+        /// Получить callbacks клиента по умолчанию (все NULL)
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_multi_client_callbacks_t shm_multi_client_callbacks_default();
+
+        /// <summary>
+        /// Подключение мультиклиента к серверу
+        ///
+        /// Клиент автоматически:
+        /// 1. Подключается к lobby (base_name)
+        /// 2. Получает назначенный slot_id от сервера
+        /// 3. Переподключается к слоту (base_name_N)
+        ///
+        /// # Parameters
+        /// - `base_name`: Базовое имя канала (то же что у MultiServer)
+        /// - `callbacks`: Callbacks для событий
+        /// - `options`: Опции клиента (NULL для значений по умолчанию)
+        ///
+        /// # Returns
+        /// Handle клиента или NULL при ошибке
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_multi_client_connect(byte* base_name, shm_multi_client_callbacks_t* callbacks, shm_multi_client_options_t* options);
+
+        /// <summary>
+        /// Отправка сообщения серверу
+        ///
+        /// # Parameters
+        /// - `handle`: Handle клиента
+        /// - `data`: Данные для отправки
+        /// - `size`: Размер данных
+        ///
+        /// # Returns
+        /// SHM_SUCCESS или код ошибки
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_error_t shm_multi_client_send(IntPtr handle, IntPtr data, uint size);
+
+        /// <summary>
+        /// Получение назначенного slot_id
+        ///
+        /// # Parameters
+        /// - `handle`: Handle клиента
+        ///
+        /// # Returns
+        /// slot_id или SLOT_ID_NO_SLOT (0xFFFFFFFF) если не подключён
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint shm_multi_client_slot_id(IntPtr handle);
+
+        /// <summary>
+        /// Проверка подключения клиента
+        ///
+        /// # Parameters
+        /// - `handle`: Handle клиента
+        ///
+        /// # Returns
+        /// true если клиент подключён к слоту
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool shm_multi_client_is_connected(IntPtr handle);
+
+        /// <summary>
+        /// Отключение мультиклиента
+        ///
+        /// # Parameters
+        /// - `handle`: Handle клиента
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void shm_multi_client_disconnect(IntPtr handle);
+
+        /// <summary>
+        /// Фактическая раскладка структур в этой сборке библиотеки.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern shm_abi_layout_t shm_abi_layout();
+
+        /// <summary>
+        /// `shm_server_start` с именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_server_start_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_callbacks_t* callbacks);
+
+        /// <summary>
+        /// `shm_client_connect` с именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_client_connect_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_callbacks_t* callbacks, uint timeout_ms);
+
+        /// <summary>
+        /// `shm_server_start_auto` с именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_server_start_auto_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_callbacks_t* callbacks, shm_auto_options_t* options);
+
+        /// <summary>
+        /// `shm_client_connect_auto` с именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_client_connect_auto_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_callbacks_t* callbacks, shm_auto_options_t* options);
+
+        /// <summary>
+        /// `shm_multi_server_start` с базовым именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_multi_server_start_w([MarshalAs(UnmanagedType.LPWStr)] string base_name, shm_multi_callbacks_t* callbacks, shm_multi_options_t* options);
+
+        /// <summary>
+        /// `shm_multi_client_connect` с базовым именем в UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_multi_client_connect_w([MarshalAs(UnmanagedType.LPWStr)] string base_name, shm_multi_client_callbacks_t* callbacks, shm_multi_client_options_t* options);
+
+        /// <summary>
+        /// `shm_dispatch_server_start` с именем в UTF-16.
+        ///
+        /// # Safety
+        /// `name` обязан быть валидной NUL-terminated UTF-16 строкой либо null.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_dispatch_server_start_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_dispatch_callbacks_t* callbacks, shm_dispatch_options_t* options);
+
+        /// <summary>
+        /// `shm_dispatch_client_connect` с именем lobby в UTF-16 (`reg.name`
+        /// остаётся UTF-8).
+        ///
+        /// # Safety
+        /// Как у `shm_dispatch_client_connect`; `name` -- NUL-terminated UTF-16.
+        /// </summary>
+        [DllImport(Lib, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern IntPtr shm_dispatch_client_connect_w([MarshalAs(UnmanagedType.LPWStr)] string name, shm_dispatch_registration_t* reg, shm_dispatch_client_callbacks_t* callbacks, shm_dispatch_client_options_t* options);
+
+        /// <summary>
+        /// Сверяет раскладку структур с библиотекой; вызывать один раз при загрузке.
+        /// </summary>
+        public static void VerifyLayout()
+        {
+            shm_abi_layout_t layout = shm_abi_layout();
+            if (layout.abi_version != SHM_ABI_VERSION)
+                throw new InvalidOperationException("xshm ABI version " + layout.abi_version + ", expected " + SHM_ABI_VERSION);
+            CheckSize("pointer", layout.pointer_size, IntPtr.Size);
+            CheckSize("shm_endpoint_config_t", layout.endpoint_config_size, Marshal.SizeOf<shm_endpoint_config_t>());
+            CheckSize("shm_callbacks_t", layout.callbacks_size, Marshal.SizeOf<shm_callbacks_t>());
+            CheckSize("shm_auto_options_t", layout.auto_options_size, Marshal.SizeOf<shm_auto_options_t>());
+            CheckSize("shm_auto_stats_t", layout.auto_stats_size, Marshal.SizeOf<shm_auto_stats_t>());
+            CheckSize("shm_multi_options_t", layout.multi_options_size, Marshal.SizeOf<shm_multi_options_t>());
+            CheckSize("shm_multi_callbacks_t", layout.multi_callbacks_size, Marshal.SizeOf<shm_multi_callbacks_t>());
+            CheckSize("shm_multi_client_options_t", layout.multi_client_options_size, Marshal.SizeOf<shm_multi_client_options_t>());
+            CheckSize("shm_multi_client_callbacks_t", layout.multi_client_callbacks_size, Marshal.SizeOf<shm_multi_client_callbacks_t>());
+            CheckSize("shm_dispatch_registration_t", layout.dispatch_registration_size, Marshal.SizeOf<shm_dispatch_registration_t>());
+            CheckSize("shm_dispatch_callbacks_t", layout.dispatch_callbacks_size, Marshal.SizeOf<shm_dispatch_callbacks_t>());
+            CheckSize("shm_dispatch_client_callbacks_t", layout.dispatch_client_callbacks_size, Marshal.SizeOf<shm_dispatch_client_callbacks_t>());
+            CheckSize("shm_dispatch_options_t", layout.dispatch_options_size, Marshal.SizeOf<shm_dispatch_options_t>());
+            CheckSize("shm_dispatch_client_options_t", layout.dispatch_client_options_size, Marshal.SizeOf<shm_dispatch_client_options_t>());
+        }
+
+        private static void CheckSize(string name, uint native, int managed)
+        {
+            if (native != managed)
+                throw new InvalidOperationException(name + ": native size " + native + ", managed " + managed);
+        }
+    }
+
+    /// <summary>
+    /// Удерживает делегаты callbacks и GCHandle для user_data, пока нативная
+    /// сторона может их вызвать. Dispose -- только после возврата из
+    /// shm_*_stop / shm_*_disconnect: остановка синхронна, поздних вызовов нет.
+    /// </summary>
+    public sealed class CallbackRegistration : IDisposable
+    {
+        private readonly List<Delegate> _delegates = new List<Delegate>();
+        private GCHandle _target;
+
+        public CallbackRegistration(object target)
+        {
+            _target = GCHandle.Alloc(target);
+        }
+
+        /// <summary>Значение для поля user_data (обычный, не pinned GCHandle).</summary>
+        public IntPtr UserData
+        {
+            get { return GCHandle.ToIntPtr(_target); }
+        }
+
+        /// <summary>Указатель на функцию для поля callbacks; делегат живёт до Dispose.</summary>
+        public IntPtr Add(Delegate callback)
+        {
+            _delegates.Add(callback);
+            return Marshal.GetFunctionPointerForDelegate(callback);
+        }
+
+        /// <summary>Объект, переданный в конструктор, по user_data из callback'а.</summary>
+        public static T Target<T>(IntPtr userData) where T : class
+        {
+            return (T)GCHandle.FromIntPtr(userData).Target;
+        }
+
+        public void Dispose()
+        {
+            if (_target.IsAllocated)
+                _target.Free();
+            _delegates.Clear();
+        }
+    }
+}
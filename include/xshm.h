@@ -17,29 +17,81 @@
 #define SHARED_MAGIC 1481853005
 
 /**
- * Текущая версия протокола.
+ * Текущая версия протокола: старшие 16 бит -- major (раскладка секции),
+ * младшие -- minor (только добавления в reserved-поля и флаги кадров).
  */
-#define SHARED_VERSION 65536
+#define SHARED_VERSION 131072
 
+/**
+ * Диапазон версий пира, с которыми совместима эта сборка: тот же major,
+ * любой minor. Новые возможности включаются не по версии, а по
+ * согласованным `FEATURE_*`.
+ */
+#define SHARED_VERSION_MIN_COMPATIBLE 131072
+
+#define SHARED_VERSION_MAX_COMPATIBLE 196607
+
+#if !(defined(XSHM_RING_64K) || defined(XSHM_RING_256K) || defined(XSHM_RING_8M))
 /**
  * Размер каждого кольцевого буфера (байты).
  */
 #define RING_CAPACITY ((2 * 1024) * 1024)
+#endif
+
+#if (defined(XSHM_RING_64K) && !(defined(XSHM_RING_256K) || defined(XSHM_RING_8M)))
+#define RING_CAPACITY (64 * 1024)
+#endif
+
+#if (defined(XSHM_RING_256K) && !defined(XSHM_RING_8M))
+#define RING_CAPACITY (256 * 1024)
+#endif
+
+#if defined(XSHM_RING_8M)
+#define RING_CAPACITY ((8 * 1024) * 1024)
+#endif
 
 /**
  * Маска размера (так как это степень двойки).
  */
 #define RING_MASK ((uint32_t)RING_CAPACITY - 1)
 
+/**
+ * Размер управляющего кольца канала с control-кольцами (байты): маленькие
+ * срочные команды не стоят в очереди за 64 КБ bulk-кадрами.
+ */
+#define CONTROL_RING_CAPACITY (16 * 1024)
+
+#if !(defined(XSHM_RING_64K) || defined(XSHM_RING_256K) || defined(XSHM_RING_8M))
 /**
  * Максимальное количество сообщений в очереди.
  */
 #define MAX_MESSAGES 500
+#endif
+
+#if (defined(XSHM_RING_64K) && !(defined(XSHM_RING_256K) || defined(XSHM_RING_8M)))
+#define MAX_MESSAGES 64
+#endif
+
+#if (defined(XSHM_RING_256K) && !defined(XSHM_RING_8M))
+#define MAX_MESSAGES 128
+#endif
+
+#if defined(XSHM_RING_8M)
+#define MAX_MESSAGES 2000
+#endif
 
+#if (!defined(XSHM_RING_64K) || defined(XSHM_RING_256K) || defined(XSHM_RING_8M))
 /**
- * Максимальный размер одного сообщения.
+ * Максимальный размер одного сообщения (длина в заголовке кадра -- u16).
+ * В 64 КБ кольце -- четверть кольца, чтобы вытеснение оставалось
+ * возможным.
  */
 #define MAX_MESSAGE_SIZE 65535
+#endif
+
+#if (defined(XSHM_RING_64K) && !(defined(XSHM_RING_256K) || defined(XSHM_RING_8M)))
+#define MAX_MESSAGE_SIZE ((16 * 1024) - 1)
+#endif
 
 /**
  * Минимальный размер сообщения.
@@ -51,6 +103,108 @@
  */
 #define MESSAGE_HEADER_SIZE 4
 
+/**
+ * Флаги заголовка сообщения. Старший байт зарезервирован за библиотекой,
+ * младший -- за пользовательскими флагами (кроме `MSG_FLAG_CHUNK` и
+ * `MSG_FLAG_SEQUENCE`).
+ *
+ * За payload следует CRC-32 трейлер (`CHECKSUM_SIZE` байт), покрывающий
+ * заголовок (длину + флаги) и payload.
+ */
+#define MSG_FLAG_CHECKSUM 32768
+
+/**
+ * Размер CRC-32 трейлера сообщения (байты).
+ */
+#define CHECKSUM_SIZE 4
+
+/**
+ * Управляющий кадр: payload -- значение handle'а (u64 LE), уже
+ * продублированного в процесс получателя. Такие кадры writer никогда не
+ * вытесняет при overflow -- иначе handle утёк бы в чужом процессе.
+ */
+#define MSG_FLAG_HANDLE 16384
+
+/**
+ * Размер payload'а handle-кадра (байты).
+ */
+#define HANDLE_FRAME_SIZE 8
+
+/**
+ * За payload (перед CRC-32 трейлером) следует метка времени записи: u64
+ * LE, наносекунды монотонных часов машины (`CLOCK_MONOTONIC` / QPC),
+ * общих для всех процессов. Получатель считает по ней задержку доставки.
+ */
+#define MSG_FLAG_TIMESTAMP 8192
+
+/**
+ * Размер метки времени сообщения (байты).
+ */
+#define TIMESTAMP_SIZE 8
+
+/**
+ * За payload (после метки времени, перед CRC-32) следует срок годности
+ * кадра: u64 LE, наносекунды тех же монотонных часов. Просроченный кадр
+ * читатель пропускает, а writer убирает из головы очереди перед записью.
+ */
+#define MSG_FLAG_DEADLINE 4096
+
+/**
+ * Размер срока годности сообщения (байты).
+ */
+#define DEADLINE_SIZE 8
+
+/**
+ * Метка фиксации кадра: в битах `MSG_COMMIT_LAP_MASK` флагов -- номер
+ * круга кольца, на котором кадр начат (позиция / ёмкость, по модулю 8).
+ * Писатель бьёт `message_count` раньше `write_pos`; если он умер между
+ * ними, reader по метке отличает дописанный кадр за `write_pos` (и сам
+ * публикует его) от мусора прошлых кругов (и пропускает).
+ */
+#define MSG_FLAG_COMMIT 2048
+
+/**
+ * Биты флагов с номером круга кадра (`MSG_FLAG_COMMIT`).
+ */
+#define MSG_COMMIT_LAP_MASK 1792
+
+/**
+ * Кадр -- кусок сообщения больше `MAX_MESSAGE_SIZE`: payload начинается
+ * с заголовка куска (`CHUNK_HEADER_SIZE` байт: полная длина сообщения и
+ * смещение куска, оба u32 LE), дальше -- данные. Старший байт флагов
+ * занят целиком, поэтому бит взят из младшего.
+ */
+#define MSG_FLAG_CHUNK 128
+
+/**
+ * За payload (после срока годности, перед CRC-32) следует номер кадра:
+ * u32 LE, число кадров, записанных в кольцо до него (по модулю 2^32,
+ * с нуля после сброса кольца). Разрыв в номерах -- кадры, вытесненные
+ * до чтения.
+ */
+#define MSG_FLAG_SEQUENCE 64
+
+/**
+ * Размер номера кадра (байты).
+ */
+#define SEQUENCE_SIZE 4
+
+/**
+ * Биты флагов, которые задаёт приложение (`send_with_flags`) и которые
+ * получатель видит как есть; остальное -- библиотечные флаги.
+ */
+#define MSG_USER_FLAGS_MASK 63
+
+/**
+ * Размер заголовка куска (байты).
+ */
+#define CHUNK_HEADER_SIZE 8
+
+/**
+ * Наибольшее сообщение, которое собирается из кусков (64 МБ).
+ */
+#define MAX_CHUNKED_MESSAGE_SIZE (64 << 20)
+
 /**
  * Состояния handshake.
  */
@@ -87,6 +241,240 @@
  */
 #define RESERVED_OWNER_PID_INDEX 1
 
+/**
+ * Индексы в reserved[] CONTROL BLOCK для PID сторон канала. Сервер пишет
+ * свой PID при создании секции, клиент -- перед HELLO. Нужны для
+ * дублирования handle'ов в процесс пира (`send_handle_to_*`); 0 -- пир
+ * PID не опубликовал (старая версия библиотеки).
+ */
+#define RESERVED_SERVER_PID_INDEX 2
+
+#define RESERVED_CLIENT_PID_INDEX 3
+
+/**
+ * Индекс в reserved[] CONTROL BLOCK для флагов раскладки секции
+ * (`LAYOUT_FLAG_*`). Выставляется сервером при создании секции.
+ */
+#define RESERVED_LAYOUT_FLAGS_INDEX 4
+
+/**
+ * За bulk-кольцами секции лежат два управляющих кольца
+ * (`CONTROL_RING_CAPACITY`), см. `dual_mapping_size`.
+ */
+#define LAYOUT_FLAG_CONTROL_RINGS 1
+
+/**
+ * Биты флагов раскладки с log2(`RING_CAPACITY`) сервера; 0 -- сервер
+ * без профилей (2 МБ).
+ */
+#define LAYOUT_RING_SHIFT_MASK 16128
+
+/**
+ * Сдвиг поля `LAYOUT_RING_SHIFT_MASK`.
+ */
+#define LAYOUT_RING_SHIFT_OFFSET 8
+
+/**
+ * Индексы в reserved[] CONTROL BLOCK для согласования возможностей.
+ * Сервер публикует свою маску `FEATURE_*` при создании секции, клиент
+ * перед HELLO пишет свою версию и маску, сервер на HELLO пишет
+ * пересечение. 0 в версии/маске -- пир старой версии без согласования.
+ */
+#define RESERVED_SERVER_FEATURES_INDEX 5
+
+#define RESERVED_CLIENT_FEATURES_INDEX 6
+
+#define RESERVED_NEGOTIATED_FEATURES_INDEX 7
+
+#define RESERVED_CLIENT_VERSION_INDEX 8
+
+/**
+ * Индекс в reserved[] CONTROL BLOCK для токена передачи канала новой
+ * версии сервера (`SharedServer::detach`/`adopt`); 0 -- канал никому не
+ * передаётся.
+ */
+#define RESERVED_UPGRADE_TOKEN_INDEX 9
+
+/**
+ * Индекс в reserved[] CONTROL BLOCK для геометрии секции
+ * ([`ring_geometry`]): `MAX_MESSAGES`, log2(`RING_CAPACITY`) и
+ * `LAYOUT_VERSION` создавшей её сборки. Пишется вместе с magic в
+ * `ControlBlock::reset`; 0 -- секция сервера старой версии, у которой
+ * есть только `LAYOUT_RING_SHIFT_MASK`.
+ */
+#define RESERVED_RING_GEOMETRY_INDEX 10
+
+/**
+ * Биты слова геометрии с `MAX_MESSAGES`.
+ */
+#define GEOMETRY_MAX_MESSAGES_MASK 65535
+
+/**
+ * Биты слова геометрии с log2(`RING_CAPACITY`) и их сдвиг.
+ */
+#define GEOMETRY_RING_SHIFT_MASK 16711680
+
+#define GEOMETRY_RING_SHIFT_OFFSET 16
+
+/**
+ * Биты слова геометрии с `LAYOUT_VERSION` и их сдвиг.
+ */
+#define GEOMETRY_LAYOUT_VERSION_MASK 4278190080
+
+#define GEOMETRY_LAYOUT_VERSION_OFFSET 24
+
+/**
+ * Индексы в reserved[] CONTROL BLOCK для heartbeat сторон: миллисекунды
+ * монотонных часов (по модулю 2^32, не 0) последнего
+ * `SharedServer::heartbeat`/`SharedClient::heartbeat`; 0 -- сторона
+ * heartbeat не ведёт. Часы общие для всех процессов машины, так что
+ * возраст считается без обмена.
+ */
+#define RESERVED_SERVER_HEARTBEAT_INDEX 11
+
+#define RESERVED_CLIENT_HEARTBEAT_INDEX 12
+
+/**
+ * Индексы в reserved[] CONTROL BLOCK для причины отключения сторон
+ * (`DISCONNECT_REASON_*`): уходящая сторона пишет свою перед тем, как
+ * выставить событие disconnect, пир читает её, получив событие. Сервер
+ * обнуляет оба поля при каждом подключении; 0 -- причина не указана (пир
+ * упал или собран старой версией).
+ */
+#define RESERVED_SERVER_DISCONNECT_REASON_INDEX 13
+
+#define RESERVED_CLIENT_DISCONNECT_REASON_INDEX 14
+
+/**
+ * Причина не указана.
+ */
+#define DISCONNECT_REASON_UNKNOWN 0
+
+/**
+ * Штатное закрытие (drop, остановка).
+ */
+#define DISCONNECT_REASON_GRACEFUL 1
+
+/**
+ * Пир нарушил протокол (битый кусок сообщения, чужой ключ шифрования).
+ */
+#define DISCONNECT_REASON_PROTOCOL_ERROR 2
+
+/**
+ * Сервер отключил клиента по своей инициативе.
+ */
+#define DISCONNECT_REASON_KICKED 3
+
+/**
+ * Сторона уходит на обновление и скоро вернётся.
+ */
+#define DISCONNECT_REASON_UPGRADING 4
+
+/**
+ * Число reserved-полей control block: с ними `ControlBlock` занимает две
+ * кэш-линии (128 байт). До major-версии 2 полей было 11 (одна линия).
+ */
+#define CONTROL_RESERVED_SLOTS 27
+
+/**
+ * Версия раскладки `ControlBlock`/`RingHeader` и формата кадра. Растёт с
+ * любым изменением этих структур, которое не отражено в
+ * `SHARED_VERSION`: сборки с разной версией раскладки секцию друг друга
+ * не открывают.
+ */
+#define LAYOUT_VERSION 1
+
+/**
+ * Кадры с CRC-32 трейлером (`MSG_FLAG_CHECKSUM`).
+ */
+#define FEATURE_CHECKSUM 1
+
+/**
+ * Управляющие (приоритетные) кольца (`LAYOUT_FLAG_CONTROL_RINGS`).
+ */
+#define FEATURE_CONTROL_RINGS 2
+
+/**
+ * Кредитный режим колец (окно сообщений/байт вместо overwrite).
+ */
+#define FEATURE_CREDITS 4
+
+/**
+ * Метки времени кадров (`MSG_FLAG_TIMESTAMP`).
+ */
+#define FEATURE_TIMESTAMPS 8
+
+/**
+ * Передача handle'ов (`MSG_FLAG_HANDLE`).
+ */
+#define FEATURE_HANDLES 16
+
+/**
+ * Срок годности сообщений (`MSG_FLAG_DEADLINE`).
+ */
+#define FEATURE_TTL 32
+
+/**
+ * Сообщения больше `MAX_MESSAGE_SIZE` кусками (`MSG_FLAG_CHUNK`).
+ */
+#define FEATURE_CHUNKING 64
+
+/**
+ * Номера кадров (`MSG_FLAG_SEQUENCE`).
+ */
+#define FEATURE_SEQUENCE 128
+
+/**
+ * Все возможности, которые понимает эта сборка.
+ */
+#define SUPPORTED_FEATURES (((((((FEATURE_CHECKSUM | FEATURE_CONTROL_RINGS) | FEATURE_CREDITS) | FEATURE_TIMESTAMPS) | FEATURE_HANDLES) | FEATURE_TTL) | FEATURE_CHUNKING) | FEATURE_SEQUENCE)
+
+/**
+ * Возможности, которые endpoint предлагает по умолчанию: куски собирает
+ * только auto-режим, он и добавляет `FEATURE_CHUNKING`.
+ */
+#define DEFAULT_FEATURES (SUPPORTED_FEATURES & ~FEATURE_CHUNKING)
+
+/**
+ * Id единственного клиента `AutoServer` в терминах [`ChannelServer`].
+ */
+#define AUTO_PEER_ID 0
+
+/**
+ * Минимальное окно в байтах: один кадр `MAX_MESSAGE_SIZE` с трейлерами.
+ */
+#define CreditWindow_MIN_BYTES (uint32_t)(((((MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE) + TIMESTAMP_SIZE) + DEADLINE_SIZE) + SEQUENCE_SIZE) + CHECKSUM_SIZE)
+
+/**
+ * Выравнивание начала каждого слота (как у структур layout'а).
+ */
+#define ARENA_ALIGN 64
+
+/**
+ * Размер сериализованного [`ArenaHandle`].
+ */
+#define ARENA_HANDLE_SIZE 12
+
+/**
+ * Размер префикса длины TCP-кадра.
+ */
+#define BRIDGE_FRAME_HEADER 4
+
+/**
+ * Заголовок кадра: длина payload (u32) + порядковый номер (u64).
+ */
+#define BROADCAST_FRAME_HEADER 12
+
+/**
+ * Минимальная ёмкость кольца шины.
+ */
+#define MIN_BROADCAST_CAPACITY 4096
+
+/**
+ * Сколько байт кольца захватывается после `read_pos` и перед `write_pos`.
+ */
+#define DUMP_WINDOW 64
+
 /**
  * Статус ответа: успех.
  */
@@ -109,6 +497,59 @@
  */
 #define MAX_MULTI_CLIENTS 31
 
+/**
+ * Максимальная длина базового имени в байтах. Запас до POSIX `NAME_MAX`
+ * (255) -- под суффиксы событий (`_C2S_CONNECT_REQ`) и слотов multi.
+ */
+#define MAX_NAME_LEN 200
+
+/**
+ * Версия раскладки структур C API; растёт при любом её изменении.
+ */
+#define SHM_ABI_VERSION 1
+
+/**
+ * Заголовок кадра данных: тег, session, seq.
+ */
+#define RELIABLE_HEADER_SIZE ((1 + 8) + 8)
+
+/**
+ * Наибольший payload в надёжном режиме.
+ */
+#define MAX_RELIABLE_PAYLOAD (MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE)
+
+/**
+ * Сколько диапазонов помнит получатель (и шлёт в одном подтверждении).
+ * При большем числе разрывов старейшие забываются: их повтор будет
+ * принят как новое сообщение.
+ */
+#define MAX_ACK_RANGES 32
+
+/**
+ * Число `u64`-ячеек.
+ */
+#define STATE_CELLS 64
+
+/**
+ * Число байтовых слотов.
+ */
+#define STATE_SLOTS 16
+
+/**
+ * Максимальная длина ключа (байты UTF-8).
+ */
+#define STATE_KEY_MAX 40
+
+/**
+ * Максимальный размер значения байтового слота.
+ */
+#define STATE_SLOT_SIZE 192
+
+/**
+ * Минимальный размер большой страницы на x64 и ARM64 (`GetLargePageMinimum`).
+ */
+#define LARGE_PAGE_SIZE ((2 * 1024) * 1024)
+
 typedef enum shm_error_t {
   SHM_SUCCESS = 0,
   SHM_ERROR_INVALID_PARAM = -1,
@@ -122,6 +563,8 @@ typedef enum shm_error_t {
   SHM_ERROR_PROTOCOL = -9,
   SHM_ERROR_FULL = -10,
   SHM_ERROR_NO_SLOT = -11,
+  SHM_ERROR_CHECKSUM = -12,
+  SHM_ERROR_WORKER_PANICKED = -13,
 } shm_error_t;
 
 typedef enum shm_direction_t {
@@ -155,7 +598,7 @@ typedef struct shm_dispatch_callbacks_t {
 } shm_dispatch_callbacks_t;
 
 /**
- * Настройки сервера.
+ * Настройки сервера; нулевые поля -- значения по умолчанию.
  */
 typedef struct shm_dispatch_options_t {
   uint32_t lobby_timeout_ms;
@@ -187,7 +630,7 @@ typedef struct shm_dispatch_client_callbacks_t {
 } shm_dispatch_client_callbacks_t;
 
 /**
- * Настройки клиента.
+ * Настройки клиента; нулевые поля -- значения по умолчанию.
  */
 typedef struct shm_dispatch_client_options_t {
   uint32_t lobby_timeout_ms;
@@ -198,12 +641,17 @@ typedef struct shm_dispatch_client_options_t {
   uint32_t max_send_queue;
 } shm_dispatch_client_options_t;
 
+/**
+ * Настройки auto-режима; нулевые таймауты и размеры -- значения по
+ * умолчанию.
+ */
 typedef struct shm_auto_options_t {
   uint32_t poll_timeout_ms;
   uint32_t reconnect_delay_ms;
   uint32_t connect_timeout_ms;
   uint32_t max_send_queue;
   uint32_t recv_batch;
+  bool checksum;
 } shm_auto_options_t;
 
 typedef void AutoServerHandle;
@@ -231,6 +679,7 @@ typedef struct shm_auto_stats_t {
   uint64_t send_overflows;
   uint64_t received_messages;
   uint64_t receive_overflows;
+  uint64_t checksum_errors;
 } shm_auto_stats_t;
 
 typedef void AutoClientHandle;
@@ -240,7 +689,7 @@ typedef void ServerHandle;
 typedef void ClientHandle;
 
 /**
- * Опции для мультиклиентного сервера
+ * Опции для мультиклиентного сервера (нулевые поля -- по умолчанию)
  */
 typedef struct shm_multi_options_t {
   /**
@@ -289,7 +738,7 @@ typedef struct shm_multi_callbacks_t {
 typedef void MultiServerHandle;
 
 /**
- * Опции для мультиклиента
+ * Опции для мультиклиента (нулевые поля -- по умолчанию)
  */
 typedef struct shm_multi_client_options_t {
   /**
@@ -346,6 +795,83 @@ typedef struct shm_multi_client_callbacks_t {
  */
 typedef void MultiClientHandle;
 
+/**
+ * Размеры структур C API в байтах (для сверки с `Marshal.SizeOf`).
+ */
+typedef struct shm_abi_layout_t {
+  uint32_t abi_version;
+  uint32_t pointer_size;
+  uint32_t endpoint_config_size;
+  uint32_t callbacks_size;
+  uint32_t auto_options_size;
+  uint32_t auto_stats_size;
+  uint32_t multi_options_size;
+  uint32_t multi_callbacks_size;
+  uint32_t multi_client_options_size;
+  uint32_t multi_client_callbacks_size;
+  uint32_t dispatch_registration_size;
+  uint32_t dispatch_callbacks_size;
+  uint32_t dispatch_client_callbacks_size;
+  uint32_t dispatch_options_size;
+  uint32_t dispatch_client_options_size;
+} shm_abi_layout_t;
+
+/**
+ * Право дублировать handle'ы в процесс (NtDuplicateObject, target).
+ */
+#define PROCESS_DUP_HANDLE 64
+
+/**
+ * Опции NtDuplicateObject: закрыть исходный handle; права и атрибуты
+ * как у исходного handle.
+ */
+#define DUPLICATE_CLOSE_SOURCE 1
+
+#define DUPLICATE_SAME_ACCESS 2
+
+#define DUPLICATE_SAME_ATTRIBUTES 4
+
+/**
+ * В буфере часть записей каталога, есть ещё (NtQueryDirectoryObject).
+ */
+#define STATUS_MORE_ENTRIES 261
+
+#define STATUS_SECTION_TOO_BIG (int32_t)3221225536u
+
+#define STATUS_ACCESS_DENIED (int32_t)3221225506u
+
+#define STATUS_PRIVILEGE_NOT_HELD (int32_t)3221225569u
+
+#define STATUS_OBJECT_NAME_NOT_FOUND (int32_t)3221225524u
+
+#define STATUS_OBJECT_PATH_NOT_FOUND (int32_t)3221225530u
+
+/**
+ * Секция на больших страницах: только вместе с SEC_COMMIT, размер кратен
+ * `LARGE_PAGE_SIZE`, нужна SeLockMemoryPrivilege.
+ */
+#define SEC_LARGE_PAGES 2147483648
+
+/**
+ * AllocationType для NtMapViewOfSection: view на больших страницах.
+ */
+#define MEM_LARGE_PAGES 536870912
+
+/**
+ * SE_LOCK_MEMORY_PRIVILEGE для RtlAdjustPrivilege.
+ */
+#define SE_LOCK_MEMORY_PRIVILEGE 4
+
+/**
+ * SE_CREATE_GLOBAL_PRIVILEGE: объекты в `\BaseNamedObjects` из сессии != 0.
+ */
+#define SE_CREATE_GLOBAL_PRIVILEGE 30
+
+/**
+ * DIRECTORY_QUERY - перечисление каталога объектов
+ */
+#define DIRECTORY_QUERY 1
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -416,6 +942,32 @@ struct shm_dispatch_options_t shm_dispatch_options_default(void);
 
 struct shm_dispatch_client_options_t shm_dispatch_client_options_default(void);
 
+/**
+ * Текст последней ошибки текущего потока, например
+ * `client 'Telemetry': connect: operation timed out`.
+ *
+ * Пишет в `buffer` не больше `capacity - 1` байт и завершающий NUL;
+ * возвращает полную длину текста без NUL (больше `capacity - 1` -- текст
+ * обрезан). 0 -- ошибок не было. `buffer` может быть NULL, чтобы узнать
+ * длину.
+ *
+ * Запоминаются ошибки канала; неверные аргументы
+ * (`SHM_ERROR_INVALID_PARAM` до обращения к каналу) и успешные вызовы
+ * последнюю ошибку не меняют.
+ */
+uint32_t shm_last_error(char *buffer,
+                        uint32_t capacity);
+
+/**
+ * Код последней ошибки текущего потока (`SHM_SUCCESS`, если её нет).
+ */
+enum shm_error_t shm_last_error_code(void);
+
+/**
+ * Сбрасывает последнюю ошибку текущего потока.
+ */
+void shm_clear_last_error(void);
+
 struct shm_auto_options_t shm_auto_options_default(void);
 
 AutoServerHandle *shm_server_start_auto(const struct shm_endpoint_config_t *config,
@@ -441,30 +993,264 @@ void shm_client_disconnect_auto(AutoClientHandle *handle);
 ServerHandle *shm_server_start(const struct shm_endpoint_config_t *config,
                                const struct shm_callbacks_t *callbacks);
 
+/**
+ * Как `shm_server_start`, но с управляющими кольцами: срочные сообщения
+ * (`shm_server_send_control`/`shm_client_send_control`) читаются раньше
+ * накопленных bulk-кадров. Клиент подключается обычным `shm_client_connect`.
+ */
+ServerHandle *shm_server_start_control_rings(const struct shm_endpoint_config_t *config,
+                                             const struct shm_callbacks_t *callbacks);
+
 enum shm_error_t shm_server_wait_for_client(ServerHandle *handle, uint32_t timeout_ms);
 
+/**
+ * Как `shm_server_wait_for_client`, но сначала отпускает прежнего клиента
+ * (см. `SharedServer::accept_next_client`): сервер принимает клиентов по
+ * очереди без пересоздания.
+ */
+enum shm_error_t shm_server_accept_next_client(ServerHandle *handle,
+                                               uint32_t timeout_ms);
+
+/**
+ * PID процесса клиента из handshake; 0 -- неизвестен.
+ */
+uint32_t shm_server_peer_pid(ServerHandle *handle);
+
+/**
+ * Жив ли процесс клиента; без PID и при любой неясности -- `true`.
+ */
+bool shm_server_is_peer_process_alive(ServerHandle *handle);
+
+/**
+ * Жив ли клиент: соединение не сброшено, клиент не отключился и его
+ * процесс жив (см. `SharedServer::is_peer_alive`).
+ */
+bool shm_server_is_peer_alive(ServerHandle *handle);
+
+/**
+ * Отмечает heartbeat сервера (см. `SharedServer::heartbeat`).
+ */
+void shm_server_heartbeat(ServerHandle *handle);
+
+/**
+ * Клиент отмечал heartbeat не дольше `max_age_ms` назад; клиент без
+ * heartbeat считается отзывчивым.
+ */
+bool shm_server_is_peer_responsive(ServerHandle *handle,
+                                   uint32_t max_age_ms);
+
 void shm_server_stop(ServerHandle *handle);
 
 enum shm_error_t shm_server_send(ServerHandle *handle, const void *data, uint32_t size);
 
+/**
+ * Как `shm_server_send`, но не ждёт и не вытесняет непрочитанное при любой
+ * политике записи: нет места -- `SHM_ERROR_FULL`, ничего не отправлено.
+ */
+enum shm_error_t shm_server_try_send(ServerHandle *handle,
+                                     const void *data,
+                                     uint32_t size);
+
+/**
+ * Отправляет, дожидаясь места не дольше `timeout_ms`: не дождался --
+ * `SHM_ERROR_TIMEOUT`. Непрочитанное не вытесняется.
+ */
+enum shm_error_t shm_server_send_blocking(ServerHandle *handle,
+                                          const void *data,
+                                          uint32_t size,
+                                          uint32_t timeout_ms);
+
+/**
+ * Отправляет `count` сообщений одной публикацией: клиент увидит либо все,
+ * либо ни одного. Не хватает места -- `SHM_ERROR_FULL`, ничего не
+ * отправлено.
+ */
+enum shm_error_t shm_server_send_batch_atomic(ServerHandle *handle,
+                                              const void *const *messages,
+                                              const uint32_t *sizes,
+                                              uint32_t count);
+
+/**
+ * Срочное сообщение через управляющее кольцо. Канал без control-колец --
+ * `SHM_ERROR_INVALID_PARAM`.
+ */
+enum shm_error_t shm_server_send_control(ServerHandle *handle,
+                                         const void *data,
+                                         uint32_t size);
+
 enum shm_error_t shm_server_receive(ServerHandle *handle, void *buffer, uint32_t *size);
 
+/**
+ * Передать handle (секция, событие, файл) клиенту: handle дублируется в
+ * процесс клиента, клиент получает новое значение через
+ * `shm_client_receive_any`. Исходный handle остаётся за вызывающим.
+ */
+enum shm_error_t shm_server_send_handle(ServerHandle *handle,
+                                        void *object);
+
+/**
+ * Как `shm_server_receive`, но handle-кадры не пропускаются: для них
+ * `*received_handle` -- handle в текущем процессе (закрывает вызывающий),
+ * `*size` = 0. Для обычного сообщения `*received_handle` = NULL.
+ */
+enum shm_error_t shm_server_receive_any(ServerHandle *handle,
+                                        void *buffer,
+                                        uint32_t *size,
+                                        void **received_handle);
+
 enum shm_error_t shm_server_poll(ServerHandle *handle, uint32_t timeout_ms);
 
+/**
+ * Включить/выключить CRC-32 трейлер для сообщений клиенту.
+ */
+enum shm_error_t shm_server_set_checksum(ServerHandle *handle,
+                                         bool enabled);
+
+/**
+ * Кредитный режим для сообщений от клиента: не больше `messages`
+ * непрочитанных сообщений и `bytes` байт. `messages == 0` -- выключить.
+ */
+enum shm_error_t shm_server_set_credit_window(ServerHandle *handle,
+                                              uint32_t messages,
+                                              uint32_t bytes);
+
+/**
+ * Число сообщений от клиента, отброшенных из-за несовпадения CRC-32.
+ */
+uint32_t shm_server_checksum_errors(ServerHandle *handle);
+
+/**
+ * Срок годности сообщений клиенту по умолчанию в миллисекундах; 0 --
+ * бессрочно.
+ */
+enum shm_error_t shm_server_set_ttl(ServerHandle *handle,
+                                    uint32_t ttl_ms);
+
+/**
+ * Число просроченных сообщений от клиента, пропущенных или убранных.
+ */
+uint32_t shm_server_expired_messages(ServerHandle *handle);
+
+/**
+ * Возможности (`FEATURE_*`), согласованные с текущим клиентом; 0 -- нет
+ * соединения или клиент старой версии.
+ */
+uint32_t shm_server_negotiated_features(ServerHandle *handle);
+
 ClientHandle *shm_client_connect(const struct shm_endpoint_config_t *config,
                                  const struct shm_callbacks_t *callbacks,
                                  uint32_t timeout_ms);
 
 void shm_client_disconnect(ClientHandle *handle);
 
+/**
+ * PID процесса сервера; 0 -- неизвестен.
+ */
+uint32_t shm_client_peer_pid(ClientHandle *handle);
+
+/**
+ * См. `shm_server_is_peer_process_alive`.
+ */
+bool shm_client_is_peer_process_alive(ClientHandle *handle);
+
+/**
+ * См. `shm_server_is_peer_alive`.
+ */
+bool shm_client_is_peer_alive(ClientHandle *handle);
+
+/**
+ * См. `shm_server_heartbeat`.
+ */
+void shm_client_heartbeat(ClientHandle *handle);
+
+/**
+ * См. `shm_server_is_peer_responsive`.
+ */
+bool shm_client_is_peer_responsive(ClientHandle *handle, uint32_t max_age_ms);
+
 bool shm_client_is_connected(const ClientHandle *handle);
 
 enum shm_error_t shm_client_send(ClientHandle *handle, const void *data, uint32_t size);
 
+/**
+ * См. `shm_server_try_send`.
+ */
+enum shm_error_t shm_client_try_send(ClientHandle *handle, const void *data, uint32_t size);
+
+/**
+ * См. `shm_server_send_blocking`.
+ */
+enum shm_error_t shm_client_send_blocking(ClientHandle *handle,
+                                          const void *data,
+                                          uint32_t size,
+                                          uint32_t timeout_ms);
+
+/**
+ * См. `shm_server_send_batch_atomic`.
+ */
+enum shm_error_t shm_client_send_batch_atomic(ClientHandle *handle,
+                                              const void *const *messages,
+                                              const uint32_t *sizes,
+                                              uint32_t count);
+
+/**
+ * См. `shm_server_send_control`.
+ */
+enum shm_error_t shm_client_send_control(ClientHandle *handle, const void *data, uint32_t size);
+
 enum shm_error_t shm_client_receive(ClientHandle *handle, void *buffer, uint32_t *size);
 
+/**
+ * Передать handle серверу (см. `shm_server_send_handle`).
+ */
+enum shm_error_t shm_client_send_handle(ClientHandle *handle, void *object);
+
+/**
+ * Приём сообщения или handle'а от сервера (см. `shm_server_receive_any`).
+ */
+enum shm_error_t shm_client_receive_any(ClientHandle *handle,
+                                        void *buffer,
+                                        uint32_t *size,
+                                        void **received_handle);
+
 enum shm_error_t shm_client_poll(ClientHandle *handle, uint32_t timeout_ms);
 
+/**
+ * Включить/выключить CRC-32 трейлер для сообщений серверу.
+ */
+enum shm_error_t shm_client_set_checksum(ClientHandle *handle,
+                                         bool enabled);
+
+/**
+ * Кредитный режим для сообщений от сервера (см.
+ * `shm_server_set_credit_window`).
+ */
+enum shm_error_t shm_client_set_credit_window(ClientHandle *handle,
+                                              uint32_t messages,
+                                              uint32_t bytes);
+
+/**
+ * Число сообщений от сервера, отброшенных из-за несовпадения CRC-32.
+ */
+uint32_t shm_client_checksum_errors(ClientHandle *handle);
+
+/**
+ * Срок годности сообщений серверу по умолчанию (см. `shm_server_set_ttl`).
+ */
+enum shm_error_t shm_client_set_ttl(ClientHandle *handle,
+                                    uint32_t ttl_ms);
+
+/**
+ * Число просроченных сообщений от сервера, пропущенных или убранных.
+ */
+uint32_t shm_client_expired_messages(ClientHandle *handle);
+
+/**
+ * Возможности (`FEATURE_*`), согласованные с сервером; 0 -- сервер
+ * старой версии.
+ */
+uint32_t shm_client_negotiated_features(ClientHandle *handle);
+
 /**
  * Получить event handles для передачи в kernel driver
  *
@@ -690,6 +1476,73 @@ bool shm_multi_client_is_connected(const MultiClientHandle *handle);
  */
 void shm_multi_client_disconnect(MultiClientHandle *handle);
 
+/**
+ * Фактическая раскладка структур в этой сборке библиотеки.
+ */
+struct shm_abi_layout_t shm_abi_layout(void);
+
+/**
+ * `shm_server_start` с именем в UTF-16.
+ */
+ServerHandle *shm_server_start_w(const uint16_t *name, const struct shm_callbacks_t *callbacks);
+
+/**
+ * `shm_client_connect` с именем в UTF-16.
+ */
+ClientHandle *shm_client_connect_w(const uint16_t *name,
+                                   const struct shm_callbacks_t *callbacks,
+                                   uint32_t timeout_ms);
+
+/**
+ * `shm_server_start_auto` с именем в UTF-16.
+ */
+AutoServerHandle *shm_server_start_auto_w(const uint16_t *name,
+                                          const struct shm_callbacks_t *callbacks,
+                                          const struct shm_auto_options_t *options);
+
+/**
+ * `shm_client_connect_auto` с именем в UTF-16.
+ */
+AutoClientHandle *shm_client_connect_auto_w(const uint16_t *name,
+                                            const struct shm_callbacks_t *callbacks,
+                                            const struct shm_auto_options_t *options);
+
+/**
+ * `shm_multi_server_start` с базовым именем в UTF-16.
+ */
+MultiServerHandle *shm_multi_server_start_w(const uint16_t *base_name,
+                                            const struct shm_multi_callbacks_t *callbacks,
+                                            const struct shm_multi_options_t *options);
+
+/**
+ * `shm_multi_client_connect` с базовым именем в UTF-16.
+ */
+MultiClientHandle *shm_multi_client_connect_w(const uint16_t *base_name,
+                                              const struct shm_multi_client_callbacks_t *callbacks,
+                                              const struct shm_multi_client_options_t *options);
+
+/**
+ * `shm_dispatch_server_start` с именем в UTF-16.
+ *
+ * # Safety
+ * `name` обязан быть валидной NUL-terminated UTF-16 строкой либо null.
+ */
+DispatchServerHandle *shm_dispatch_server_start_w(const uint16_t *name,
+                                                  const struct shm_dispatch_callbacks_t *callbacks,
+                                                  const struct shm_dispatch_options_t *options);
+
+/**
+ * `shm_dispatch_client_connect` с именем lobby в UTF-16 (`reg.name`
+ * остаётся UTF-8).
+ *
+ * # Safety
+ * Как у `shm_dispatch_client_connect`; `name` -- NUL-terminated UTF-16.
+ */
+DispatchClientHandle *shm_dispatch_client_connect_w(const uint16_t *name,
+                                                    const struct shm_dispatch_registration_t *reg,
+                                                    const struct shm_dispatch_client_callbacks_t *callbacks,
+                                                    const struct shm_dispatch_client_options_t *options);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
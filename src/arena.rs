@@ -0,0 +1,599 @@
+//! Arena в shared memory для zero-copy передачи структур и блобов.
+//!
+//! Arena -- отдельная именованная секция, нарезанная на слоты одинакового
+//! размера. Producer кладёт данные в слот и передаёт через кольцо только
+//! компактный [`ArenaHandle`] (смещение + длина + поколение, 12 байт);
+//! consumer получает [`ArenaGuard`] -- view прямо в секцию, без копирования.
+//! Слот освобождается, когда guard уничтожен.
+//!
+//! Жизненный цикл слота:
+//! `FREE -> WRITING` (alloc) `-> PUBLISHED` (publish) `-> READING` (take)
+//! `-> FREE` (drop guard). Поколение слота растёт при каждом alloc, поэтому
+//! handle на уже освобождённый и переиспользованный слот отвергается с
+//! [`ShmError::StaleHandle`], а не читает чужие данные.
+//!
+//! Handle, потерянный по дороге (например, вытесненный при overflow
+//! кольца), держит слот до [`Arena::release`]; слот процесса, упавшего
+//! посреди записи/чтения, не возвращается до пересоздания arena.
+//!
+//! ```no_run
+//! use xshm::arena::{Arena, ArenaHandle, ArenaOptions};
+//!
+//! // producer
+//! let arena = Arena::create("CHAN", ArenaOptions::default())?;
+//! let handle = arena.put_bytes(b"large payload")?;
+//! let wire = handle.to_bytes(); // отправить через кольцо
+//!
+//! // consumer
+//! let arena = Arena::open("CHAN")?;
+//! let handle = ArenaHandle::from_bytes(&wire).unwrap();
+//! let guard = arena.take(handle)?;
+//! assert_eq!(&*guard, b"large payload");
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::error::{Result, ShmError};
+use crate::naming::arena_name;
+use crate::platform::{Mapping, PlatformMapping};
+
+/// 'XARN'
+const ARENA_MAGIC: u32 = 0x5841_524E;
+const ARENA_VERSION: u32 = 1;
+
+/// Выравнивание начала каждого слота (как у структур layout'а).
+pub const ARENA_ALIGN: usize = 64;
+
+const SLOT_FREE: u32 = 0;
+const SLOT_WRITING: u32 = 1;
+const SLOT_PUBLISHED: u32 = 2;
+const SLOT_READING: u32 = 3;
+
+/// Размер сериализованного [`ArenaHandle`].
+pub const ARENA_HANDLE_SIZE: usize = 12;
+
+#[repr(C, align(64))]
+struct ArenaHeader {
+    /// Пишется последним (Release) при создании: пир, увидевший magic
+    /// (Acquire), видит и остальной заголовок, и таблицу слотов.
+    magic: AtomicU32,
+    version: u32,
+    slot_size: u32,
+    slot_count: u32,
+    /// Курсор поиска свободного слота (подсказка, не инвариант).
+    next: AtomicU32,
+    reserved: [u32; 11],
+}
+
+#[repr(C)]
+struct SlotMeta {
+    state: AtomicU32,
+    generation: AtomicU32,
+    len: AtomicU32,
+    reserved: u32,
+}
+
+/// Параметры arena. Задаются создателем, пир читает их из секции.
+#[derive(Debug, Clone)]
+pub struct ArenaOptions {
+    /// Максимальный размер одного объекта. Округляется вверх до
+    /// `ARENA_ALIGN`.
+    pub slot_size: usize,
+    /// Число слотов (одновременно живых объектов).
+    pub slot_count: usize,
+}
+
+impl Default for ArenaOptions {
+    fn default() -> Self {
+        Self {
+            slot_size: 4096,
+            slot_count: 256,
+        }
+    }
+}
+
+/// Ссылка на объект в arena, передаваемая через кольцо.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaHandle {
+    /// Смещение данных от начала секции arena.
+    pub offset: u32,
+    /// Длина данных.
+    pub len: u32,
+    /// Поколение слота на момент publish.
+    pub generation: u32,
+}
+
+impl ArenaHandle {
+    pub fn to_bytes(&self) -> [u8; ARENA_HANDLE_SIZE] {
+        let mut out = [0u8; ARENA_HANDLE_SIZE];
+        out[0..4].copy_from_slice(&self.offset.to_le_bytes());
+        out[4..8].copy_from_slice(&self.len.to_le_bytes());
+        out[8..12].copy_from_slice(&self.generation.to_le_bytes());
+        out
+    }
+
+    /// `None`, если `bytes` не ровно `ARENA_HANDLE_SIZE` байт.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != ARENA_HANDLE_SIZE {
+            return None;
+        }
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        Some(Self {
+            offset: word(0),
+            len: word(4),
+            generation: word(8),
+        })
+    }
+}
+
+/// Arena, отображённая в процесс. Создатель и пир равноправны: любой из
+/// них может и класть объекты, и забирать их.
+pub struct Arena {
+    mapping: Mapping,
+    slot_size: usize,
+    slot_count: usize,
+    data_start: usize,
+}
+
+/// Начало области данных и полный размер секции; `None` -- не помещается
+/// в 4 GiB (размеры слотов `u32`-смещения handle'а не адресуют дальше).
+/// Пир читает размеры из чужой секции, поэтому вся арифметика проверяемая.
+fn layout_sizes(slot_size: usize, slot_count: usize) -> Option<(usize, usize)> {
+    let meta = slot_count
+        .checked_mul(std::mem::size_of::<SlotMeta>())?
+        .checked_next_multiple_of(ARENA_ALIGN)?;
+    let data_start = std::mem::size_of::<ArenaHeader>().checked_add(meta)?;
+    let total = slot_size.checked_mul(slot_count)?.checked_add(data_start)?;
+    (slot_size <= u32::MAX as usize && total <= u32::MAX as usize).then_some((data_start, total))
+}
+
+impl Arena {
+    /// Создаёт arena `name` (секция `Local\{name}_ARENA`).
+    pub fn create(name: &str, options: ArenaOptions) -> Result<Self> {
+        if options.slot_size == 0 || options.slot_count == 0 {
+            return Err(ShmError::InvalidConfig(
+                "arena slot_size and slot_count must be non-zero",
+            ));
+        }
+        let arena_too_large = ShmError::InvalidConfig("arena does not fit in 4 GiB");
+        let slot_size = options
+            .slot_size
+            .checked_next_multiple_of(ARENA_ALIGN)
+            .ok_or(arena_too_large.clone())?;
+        let (data_start, total) =
+            layout_sizes(slot_size, options.slot_count).ok_or(arena_too_large)?;
+
+        let mapping = Mapping::create_sized(&arena_name(name), total)?;
+        let arena = Arena {
+            mapping,
+            slot_size,
+            slot_count: options.slot_count,
+            data_start,
+        };
+        // SAFETY: секция только что создана нами и имеет размер total.
+        unsafe {
+            let header = arena.header_ptr();
+            (*header).version = ARENA_VERSION;
+            (*header).slot_size = slot_size as u32;
+            (*header).slot_count = options.slot_count as u32;
+            (*header).next.store(0, Ordering::Relaxed);
+        }
+        for index in 0..arena.slot_count {
+            let meta = arena.meta(index);
+            meta.state.store(SLOT_FREE, Ordering::Relaxed);
+            meta.len.store(0, Ordering::Relaxed);
+        }
+        // Публикация: до magic пир отвергает секцию как `Corrupted`.
+        arena.header().magic.store(ARENA_MAGIC, Ordering::Release);
+        Ok(arena)
+    }
+
+    /// Открывает arena, созданную другой стороной.
+    pub fn open(name: &str) -> Result<Self> {
+        let section = arena_name(name);
+        let (slot_size, slot_count) = {
+            let probe = Mapping::open_sized(&section, std::mem::size_of::<ArenaHeader>())?;
+            // SAFETY: отображено не меньше заголовка.
+            let header = unsafe { &*(probe.as_ptr() as *const ArenaHeader) };
+            if header.magic.load(Ordering::Acquire) != ARENA_MAGIC {
+                return Err(ShmError::Corrupted);
+            }
+            if header.version != ARENA_VERSION {
+                return Err(ShmError::HandshakeFailed);
+            }
+            (header.slot_size as usize, header.slot_count as usize)
+        };
+        if slot_size == 0 || slot_size % ARENA_ALIGN != 0 || slot_count == 0 {
+            return Err(ShmError::Corrupted);
+        }
+        let (data_start, total) = layout_sizes(slot_size, slot_count).ok_or(ShmError::Corrupted)?;
+        Ok(Arena {
+            mapping: Mapping::open_sized(&section, total)?,
+            slot_size,
+            slot_count,
+            data_start,
+        })
+    }
+
+    /// Максимальный размер одного объекта.
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slot_count
+    }
+
+    /// Число свободных слотов (снимок).
+    pub fn free_slots(&self) -> usize {
+        (0..self.slot_count)
+            .filter(|&index| self.meta(index).state.load(Ordering::Relaxed) == SLOT_FREE)
+            .count()
+    }
+
+    fn header_ptr(&self) -> *mut ArenaHeader {
+        self.mapping.as_ptr() as *mut ArenaHeader
+    }
+
+    fn header(&self) -> &ArenaHeader {
+        // SAFETY: секция содержит заголовок и живёт не меньше self.
+        unsafe { &*self.header_ptr() }
+    }
+
+    fn meta(&self, index: usize) -> &SlotMeta {
+        debug_assert!(index < self.slot_count);
+        // SAFETY: таблица слотов сразу за заголовком, index < slot_count.
+        unsafe {
+            let table = self
+                .mapping
+                .as_ptr()
+                .add(std::mem::size_of::<ArenaHeader>());
+            &*(table as *const SlotMeta).add(index)
+        }
+    }
+
+    fn data_offset(&self, index: usize) -> usize {
+        self.data_start + index * self.slot_size
+    }
+
+    fn data_ptr(&self, index: usize) -> *mut u8 {
+        // SAFETY: data_offset(index) + slot_size <= размер секции.
+        unsafe { self.mapping.as_ptr().add(self.data_offset(index)) }
+    }
+
+    /// Резервирует слот под объект длиной `len`. Данные пишутся через
+    /// `DerefMut`, затем [`ArenaWriter::publish`] выдаёт handle. Writer,
+    /// уничтоженный без publish, возвращает слот.
+    pub fn alloc(&self, len: usize) -> Result<ArenaWriter<'_>> {
+        if len > self.slot_size {
+            return Err(ShmError::MessageTooLarge);
+        }
+        let start = self.header().next.fetch_add(1, Ordering::Relaxed) as usize;
+        for step in 0..self.slot_count {
+            let index = (start + step) % self.slot_count;
+            let meta = self.meta(index);
+            if meta
+                .state
+                .compare_exchange(
+                    SLOT_FREE,
+                    SLOT_WRITING,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+            {
+                // 0 не выдаётся: зануленный handle никогда не валиден.
+                let mut generation = meta.generation.load(Ordering::Relaxed).wrapping_add(1);
+                if generation == 0 {
+                    generation = 1;
+                }
+                meta.generation.store(generation, Ordering::Relaxed);
+                return Ok(ArenaWriter {
+                    arena: self,
+                    index,
+                    len,
+                    generation,
+                    published: false,
+                });
+            }
+        }
+        Err(ShmError::ArenaFull)
+    }
+
+    /// Копирует `data` в новый слот и публикует его.
+    pub fn put_bytes(&self, data: &[u8]) -> Result<ArenaHandle> {
+        let mut writer = self.alloc(data.len())?;
+        writer.copy_from_slice(data);
+        Ok(writer.publish())
+    }
+
+    /// Кладёт `#[repr(C)]`-значение в новый слот (слоты выровнены на
+    /// `ARENA_ALIGN`).
+    pub fn put<T: Copy>(&self, value: &T) -> Result<ArenaHandle> {
+        if std::mem::align_of::<T>() > ARENA_ALIGN {
+            return Err(ShmError::InvalidConfig(
+                "type alignment exceeds ARENA_ALIGN",
+            ));
+        }
+        let writer = self.alloc(std::mem::size_of::<T>())?;
+        // SAFETY: слот наш (WRITING), выровнен на ARENA_ALIGN >= align_of::<T>()
+        // и вмещает size_of::<T>() (проверено в alloc).
+        unsafe { std::ptr::write(writer.arena.data_ptr(writer.index) as *mut T, *value) };
+        Ok(writer.publish())
+    }
+
+    /// Проверяет handle и переводит слот `from -> to`. Поколение
+    /// перепроверяется после захвата: между проверкой и CAS слот мог быть
+    /// освобождён и опубликован заново.
+    fn claim(&self, handle: ArenaHandle, from: u32, to: u32) -> Result<usize> {
+        let relative = (handle.offset as usize)
+            .checked_sub(self.data_start)
+            .ok_or(ShmError::StaleHandle)?;
+        let index = relative / self.slot_size;
+        if relative % self.slot_size != 0
+            || index >= self.slot_count
+            || handle.len as usize > self.slot_size
+            || handle.generation == 0
+        {
+            return Err(ShmError::StaleHandle);
+        }
+
+        let meta = self.meta(index);
+        if meta.generation.load(Ordering::Acquire) != handle.generation {
+            return Err(ShmError::StaleHandle);
+        }
+        meta.state
+            .compare_exchange(from, to, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| ShmError::StaleHandle)?;
+        if meta.generation.load(Ordering::Acquire) != handle.generation
+            || meta.len.load(Ordering::Relaxed) != handle.len
+        {
+            meta.state.store(from, Ordering::Release);
+            return Err(ShmError::StaleHandle);
+        }
+        Ok(index)
+    }
+
+    /// Забирает опубликованный объект. Каждый handle можно забрать один
+    /// раз; слот освобождается при уничтожении guard'а.
+    pub fn take(&self, handle: ArenaHandle) -> Result<ArenaGuard<'_>> {
+        let index = self.claim(handle, SLOT_PUBLISHED, SLOT_READING)?;
+        Ok(ArenaGuard {
+            arena: self,
+            index,
+            len: handle.len as usize,
+        })
+    }
+
+    /// Освобождает опубликованный, но так и не забранный объект (например,
+    /// handle потерялся при overflow кольца).
+    pub fn release(&self, handle: ArenaHandle) -> Result<()> {
+        let index = self.claim(handle, SLOT_PUBLISHED, SLOT_READING)?;
+        self.meta(index).state.store(SLOT_FREE, Ordering::Release);
+        Ok(())
+    }
+}
+
+/// Зарезервированный под запись слот.
+pub struct ArenaWriter<'a> {
+    arena: &'a Arena,
+    index: usize,
+    len: usize,
+    generation: u32,
+    published: bool,
+}
+
+impl ArenaWriter<'_> {
+    /// Делает объект видимым для `take` и возвращает его handle.
+    pub fn publish(mut self) -> ArenaHandle {
+        let meta = self.arena.meta(self.index);
+        meta.len.store(self.len as u32, Ordering::Relaxed);
+        // Release: данные слота видны тому, кто увидит PUBLISHED.
+        meta.state.store(SLOT_PUBLISHED, Ordering::Release);
+        self.published = true;
+        ArenaHandle {
+            offset: self.arena.data_offset(self.index) as u32,
+            len: self.len as u32,
+            generation: self.generation,
+        }
+    }
+}
+
+impl Deref for ArenaWriter<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: слот в состоянии WRITING принадлежит этому writer'у.
+        unsafe { std::slice::from_raw_parts(self.arena.data_ptr(self.index), self.len) }
+    }
+}
+
+impl DerefMut for ArenaWriter<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: см. deref; &mut self -- единственный доступ.
+        unsafe { std::slice::from_raw_parts_mut(self.arena.data_ptr(self.index), self.len) }
+    }
+}
+
+impl Drop for ArenaWriter<'_> {
+    fn drop(&mut self) {
+        if !self.published {
+            self.arena
+                .meta(self.index)
+                .state
+                .store(SLOT_FREE, Ordering::Release);
+        }
+    }
+}
+
+/// Zero-copy view на забранный объект. Слот освобождается в Drop.
+pub struct ArenaGuard<'a> {
+    arena: &'a Arena,
+    index: usize,
+    len: usize,
+}
+
+impl ArenaGuard<'_> {
+    /// Данные как `&T`. `None`, если длина объекта не равна
+    /// `size_of::<T>()`.
+    ///
+    /// # Safety
+    /// Producer обязан был положить именно `T` (через [`Arena::put`]) --
+    /// произвольные байты могут быть невалидным значением `T`.
+    pub unsafe fn as_value<T: Copy>(&self) -> Option<&T> {
+        if self.len != std::mem::size_of::<T>() || std::mem::align_of::<T>() > ARENA_ALIGN {
+            return None;
+        }
+        // SAFETY: размер и выравнивание проверены, валидность значения --
+        // контракт вызывающего.
+        Some(unsafe { &*(self.arena.data_ptr(self.index) as *const T) })
+    }
+}
+
+impl Deref for ArenaGuard<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: слот в состоянии READING принадлежит этому guard'у.
+        unsafe { std::slice::from_raw_parts(self.arena.data_ptr(self.index), self.len) }
+    }
+}
+
+impl Drop for ArenaGuard<'_> {
+    fn drop(&mut self) {
+        self.arena
+            .meta(self.index)
+            .state
+            .store(SLOT_FREE, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_ARENA_{tag}_{}", std::process::id())
+    }
+
+    fn small() -> ArenaOptions {
+        ArenaOptions {
+            slot_size: 100,
+            slot_count: 2,
+        }
+    }
+
+    #[test]
+    fn bytes_roundtrip_between_creator_and_peer() {
+        let name = unique("BYTES");
+        let producer = Arena::create(&name, small()).unwrap();
+        let consumer = Arena::open(&name).unwrap();
+        assert_eq!(consumer.slot_size(), 128);
+        assert_eq!(consumer.slot_count(), 2);
+
+        let handle = producer.put_bytes(b"zero-copy").unwrap();
+        let wire = ArenaHandle::from_bytes(&handle.to_bytes()).unwrap();
+        let guard = consumer.take(wire).unwrap();
+        assert_eq!(&*guard, b"zero-copy");
+    }
+
+    #[test]
+    fn struct_is_visible_through_guard() {
+        #[repr(C)]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Point {
+            x: u64,
+            y: u64,
+        }
+
+        let arena = Arena::create(&unique("STRUCT"), small()).unwrap();
+        let handle = arena.put(&Point { x: 3, y: 4 }).unwrap();
+        let guard = arena.take(handle).unwrap();
+        // SAFETY: положен именно Point.
+        assert_eq!(
+            unsafe { guard.as_value::<Point>() },
+            Some(&Point { x: 3, y: 4 })
+        );
+        assert_eq!(unsafe { guard.as_value::<u32>() }, None);
+    }
+
+    #[test]
+    fn slots_are_reclaimed_when_guards_drop() {
+        let arena = Arena::create(&unique("RECLAIM"), small()).unwrap();
+        let a = arena.put_bytes(b"aa").unwrap();
+        let _b = arena.put_bytes(b"bb").unwrap();
+        assert_eq!(arena.put_bytes(b"cc").unwrap_err(), ShmError::ArenaFull);
+
+        drop(arena.take(a).unwrap());
+        assert_eq!(arena.free_slots(), 1);
+        arena.put_bytes(b"cc").unwrap();
+        assert_eq!(arena.alloc(1).err(), Some(ShmError::ArenaFull));
+    }
+
+    #[test]
+    fn stale_and_forged_handles_are_rejected() {
+        let arena = Arena::create(&unique("STALE"), small()).unwrap();
+        let handle = arena.put_bytes(b"once").unwrap();
+        drop(arena.take(handle).unwrap());
+        // Повторный take того же handle и take после переиспользования слота.
+        assert_eq!(arena.take(handle).err(), Some(ShmError::StaleHandle));
+        let reused = arena.put_bytes(b"next").unwrap();
+        assert_eq!(arena.take(handle).err(), Some(ShmError::StaleHandle));
+
+        let forged = ArenaHandle {
+            offset: reused.offset + 1,
+            ..reused
+        };
+        assert_eq!(arena.take(forged).err(), Some(ShmError::StaleHandle));
+        assert_eq!(
+            arena
+                .take(ArenaHandle {
+                    offset: 0,
+                    len: 0,
+                    generation: 0
+                })
+                .err(),
+            Some(ShmError::StaleHandle)
+        );
+
+        arena.release(reused).unwrap();
+        assert_eq!(arena.free_slots(), 2);
+    }
+
+    #[test]
+    fn dropped_writer_returns_its_slot() {
+        let arena = Arena::create(&unique("WRITER"), small()).unwrap();
+        drop(arena.alloc(10).unwrap());
+        drop(arena.alloc(10).unwrap());
+        assert_eq!(arena.free_slots(), 2);
+        assert_eq!(arena.alloc(129).err(), Some(ShmError::MessageTooLarge));
+    }
+
+    #[test]
+    fn oversized_layout_is_rejected_on_both_sides() {
+        let huge = ArenaOptions {
+            slot_size: usize::MAX,
+            slot_count: 2,
+        };
+        assert!(matches!(
+            Arena::create(&unique("HUGE"), huge),
+            Err(ShmError::InvalidConfig(_))
+        ));
+
+        // Размеры из чужого заголовка, не помещающиеся в 4 GiB.
+        let name = unique("HOSTILE");
+        let arena = Arena::create(&name, small()).unwrap();
+        unsafe { (*arena.header_ptr()).slot_count = u32::MAX };
+        assert_eq!(Arena::open(&name).err(), Some(ShmError::Corrupted));
+    }
+
+    #[test]
+    fn section_without_published_magic_is_rejected() {
+        let name = unique("UNPUBLISHED");
+        let _section = Mapping::create_sized(&arena_name(&name), 4096).unwrap();
+        assert_eq!(Arena::open(&name).err(), Some(ShmError::Corrupted));
+    }
+}
@@ -0,0 +1,135 @@
+//! Ограничение очереди отправки на стороне вызывающего
+//! (`QueuePolicy::Block`).
+//!
+//! Очередь worker'а наполняется через канал команд, поэтому вызывающий
+//! её длины не видит. Gate считает сообщения, отданные worker'у и ещё не
+//! покинувшие очередь (записаны, просрочены или отброшены): каждое несёт
+//! [`QueuePermit`], который при drop освобождает место и будит ждущий
+//! `send`.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancellationToken;
+use crate::error::{Result, ShmError};
+
+use super::{AutoOptions, QueuePolicy};
+
+#[derive(Default)]
+struct Occupancy {
+    messages: usize,
+    bytes: usize,
+}
+
+pub(crate) struct SendGate {
+    policy: QueuePolicy,
+    /// Наибольший payload, который worker сможет отправить (с шифрованием
+    /// -- `MAX_PLAINTEXT_SIZE`); `None` -- без предела.
+    max_payload: Option<usize>,
+    max_messages: usize,
+    max_bytes: Option<usize>,
+    occupancy: Mutex<Occupancy>,
+    space: Condvar,
+}
+
+impl SendGate {
+    pub(crate) fn new(options: &AutoOptions) -> Arc<Self> {
+        #[cfg(feature = "encryption")]
+        let max_payload = options
+            .encryption
+            .as_ref()
+            .map(|_| crate::crypto::MAX_PLAINTEXT_SIZE);
+        #[cfg(not(feature = "encryption"))]
+        let max_payload = None;
+        Arc::new(Self {
+            policy: options.queue_policy,
+            max_payload,
+            max_messages: options.max_send_queue,
+            max_bytes: options.max_send_queue_bytes,
+            occupancy: Mutex::new(Occupancy::default()),
+            space: Condvar::new(),
+        })
+    }
+
+    /// Payload, который worker всё равно не отправит, отвергается сразу в
+    /// `send` (`ShmError::MessageTooLarge`), а не уходит в `on_error`.
+    pub(crate) fn check_size(&self, bytes: usize) -> Result<()> {
+        match self.max_payload {
+            Some(max) if bytes > max => Err(ShmError::MessageTooLarge),
+            _ => Ok(()),
+        }
+    }
+
+    /// Место под сообщение из `bytes` байт. `DropOldest` -- без ожидания и
+    /// без permit'а (лишнее вытеснит worker). `Block` -- ждёт места не
+    /// дольше `timeout`: не дождался -- `ShmError::QueueFull`, worker
+    /// остановлен -- `ShmError::NotReady`. Дедлайн сообщения `expires`
+    /// наступил раньше -- `ShmError::Timeout`.
+    pub(crate) fn acquire(
+        self: &Arc<Self>,
+        bytes: usize,
+        expires: Option<Instant>,
+        stop: &CancellationToken,
+    ) -> Result<Option<QueuePermit>> {
+        let QueuePolicy::Block { timeout } = self.policy else {
+            return Ok(None);
+        };
+        let full_after = Instant::now() + timeout;
+        let mut occupancy = self.occupancy.lock().unwrap();
+        while !self.fits(&occupancy, bytes) {
+            if stop.is_cancelled() {
+                return Err(ShmError::NotReady);
+            }
+            let now = Instant::now();
+            if expires.is_some_and(|expires| now >= expires) {
+                return Err(ShmError::Timeout);
+            }
+            if now >= full_after {
+                return Err(ShmError::QueueFull);
+            }
+            let deadline = expires.map_or(full_after, |expires| expires.min(full_after));
+            // Отмену токена без выхода worker'а (его permit'ы ещё живы)
+            // видно только по таймеру.
+            let slice = (deadline - now).min(Duration::from_millis(50));
+            occupancy = self.space.wait_timeout(occupancy, slice).unwrap().0;
+        }
+        if stop.is_cancelled() {
+            return Err(ShmError::NotReady);
+        }
+        occupancy.messages += 1;
+        occupancy.bytes += bytes;
+        Ok(Some(QueuePermit {
+            gate: self.clone(),
+            bytes,
+        }))
+    }
+
+    /// Пустая очередь принимает сообщение любого размера (кадр больше
+    /// бюджета иначе не ушёл бы никогда).
+    fn fits(&self, occupancy: &Occupancy, bytes: usize) -> bool {
+        occupancy.messages == 0
+            || (occupancy.messages < self.max_messages
+                && self
+                    .max_bytes
+                    .is_none_or(|budget| occupancy.bytes + bytes <= budget))
+    }
+
+    fn release(&self, bytes: usize) {
+        let mut occupancy = self.occupancy.lock().unwrap();
+        occupancy.messages -= 1;
+        occupancy.bytes -= bytes;
+        self.space.notify_all();
+    }
+}
+
+/// Место сообщения в очереди; освобождается, когда сообщение её покидает.
+pub(crate) struct QueuePermit {
+    gate: Arc<SendGate>,
+    bytes: usize,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        self.gate.release(self.bytes);
+    }
+}
@@ -0,0 +1,173 @@
+//! Handler из замыканий ([`AutoHandlerFn`]) -- для утилит и тестов, где
+//! отдельный тип с `impl AutoHandler` ради пары callback'ов лишний.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::ShmError;
+
+use super::{AutoHandler, ChannelKind};
+
+type Event = Box<dyn Fn() + Send + Sync>;
+type Payload = Box<dyn Fn(ChannelKind, &[u8]) + Send + Sync>;
+type Overflow = Box<dyn Fn(ChannelKind, u32) + Send + Sync>;
+type Direction = Box<dyn Fn(ChannelKind) + Send + Sync>;
+type Error = Box<dyn Fn(ShmError) + Send + Sync>;
+type Reconnect = Box<dyn Fn(u32, &ShmError) + Send + Sync>;
+type Scheduled = Box<dyn Fn(Duration, u32) + Send + Sync>;
+type Tick = Box<dyn Fn(Duration) + Send + Sync>;
+
+/// [`AutoHandler`] из замыканий: каждое `on_*` задаёт один callback,
+/// незаданные ничего не делают, как методы трейта по умолчанию. Варианты с
+/// epoch/причиной/флагами приходят в простые (`on_connect`,
+/// `on_disconnect`, `on_message`).
+///
+/// ```no_run
+/// use xshm::{AutoHandlerFn, AutoOptions, AutoServer};
+///
+/// let handler = AutoHandlerFn::new()
+///     .on_message(|_, data| println!("{} bytes", data.len()))
+///     .on_disconnect(|| eprintln!("client gone"))
+///     .build();
+/// let _server = AutoServer::start("tool", handler, AutoOptions::default())?;
+/// # Ok::<(), xshm::ShmError>(())
+/// ```
+#[derive(Default)]
+pub struct AutoHandlerFn {
+    connect: Option<Event>,
+    disconnect: Option<Event>,
+    message: Option<Payload>,
+    overflow: Option<Overflow>,
+    space_available: Option<Direction>,
+    expired: Option<Payload>,
+    error: Option<Error>,
+    reconnect: Option<Reconnect>,
+    reconnect_scheduled: Option<Scheduled>,
+    tick: Option<Tick>,
+}
+
+impl AutoHandlerFn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_connect(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.connect = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_disconnect(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.disconnect = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_message(mut self, f: impl Fn(ChannelKind, &[u8]) + Send + Sync + 'static) -> Self {
+        self.message = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_overflow(mut self, f: impl Fn(ChannelKind, u32) + Send + Sync + 'static) -> Self {
+        self.overflow = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_space_available(mut self, f: impl Fn(ChannelKind) + Send + Sync + 'static) -> Self {
+        self.space_available = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_expired(mut self, f: impl Fn(ChannelKind, &[u8]) + Send + Sync + 'static) -> Self {
+        self.expired = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_error(mut self, f: impl Fn(ShmError) + Send + Sync + 'static) -> Self {
+        self.error = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_reconnect(mut self, f: impl Fn(u32, &ShmError) + Send + Sync + 'static) -> Self {
+        self.reconnect = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_reconnect_scheduled(
+        mut self,
+        f: impl Fn(Duration, u32) + Send + Sync + 'static,
+    ) -> Self {
+        self.reconnect_scheduled = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_tick(mut self, f: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.tick = Some(Box::new(f));
+        self
+    }
+
+    /// Готовый handler для `AutoServer::start`/`AutoClient::connect`.
+    pub fn build(self) -> Arc<dyn AutoHandler> {
+        Arc::new(self)
+    }
+}
+
+impl AutoHandler for AutoHandlerFn {
+    fn on_connect(&self) {
+        if let Some(f) = &self.connect {
+            f();
+        }
+    }
+
+    fn on_disconnect(&self) {
+        if let Some(f) = &self.disconnect {
+            f();
+        }
+    }
+
+    fn on_reconnect(&self, attempt: u32, last_error: &ShmError) {
+        if let Some(f) = &self.reconnect {
+            f(attempt, last_error);
+        }
+    }
+
+    fn on_reconnect_scheduled(&self, delay: Duration, attempt: u32) {
+        if let Some(f) = &self.reconnect_scheduled {
+            f(delay, attempt);
+        }
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        if let Some(f) = &self.tick {
+            f(elapsed);
+        }
+    }
+
+    fn on_message(&self, direction: ChannelKind, payload: &[u8]) {
+        if let Some(f) = &self.message {
+            f(direction, payload);
+        }
+    }
+
+    fn on_overflow(&self, direction: ChannelKind, count: u32) {
+        if let Some(f) = &self.overflow {
+            f(direction, count);
+        }
+    }
+
+    fn on_space_available(&self, direction: ChannelKind) {
+        if let Some(f) = &self.space_available {
+            f(direction);
+        }
+    }
+
+    fn on_expired(&self, direction: ChannelKind, payload: &[u8]) {
+        if let Some(f) = &self.expired {
+            f(direction, payload);
+        }
+    }
+
+    fn on_error(&self, err: ShmError) {
+        if let Some(f) = &self.error {
+            f(err);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Состояние соединения Auto endpoint'а (`state()`/`wait_connected()`).
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancellationToken;
+use crate::error::{Result, ShmError};
+
+/// Где сейчас `AutoServer`/`AutoClient`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// Первого соединения ещё не было: сервер ждёт клиента, клиент
+    /// подключается.
+    Connecting,
+    Connected,
+    /// Соединение было и потеряно: сервер ждёт следующего клиента, клиент
+    /// переподключается.
+    Reconnecting,
+    /// `stop()`, отмена токена или паника worker'а.
+    Stopped,
+}
+
+const CONNECTING: u8 = 0;
+const CONNECTED: u8 = 1;
+const RECONNECTING: u8 = 2;
+
+/// Состояние, которое публикует worker; `Stopped` берётся из токена.
+#[derive(Default)]
+pub(crate) struct ConnectionCell {
+    state: AtomicU8,
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+impl ConnectionCell {
+    pub(crate) fn set_connected(&self, connected: bool) {
+        let state = if connected { CONNECTED } else { RECONNECTING };
+        let _guard = self.lock.lock().unwrap();
+        self.state.store(state, Ordering::Release);
+        self.changed.notify_all();
+    }
+
+    pub(crate) fn get(&self, stop: &CancellationToken) -> ConnectionState {
+        if stop.is_cancelled() {
+            return ConnectionState::Stopped;
+        }
+        match self.state.load(Ordering::Acquire) {
+            CONNECTING => ConnectionState::Connecting,
+            CONNECTED => ConnectionState::Connected,
+            _ => ConnectionState::Reconnecting,
+        }
+    }
+
+    /// Ждёт `Connected` до `timeout` (`None` -- без ограничения): не
+    /// дождался -- `ShmError::Timeout`, worker остановлен --
+    /// `ShmError::NotReady`.
+    pub(crate) fn wait_connected(
+        &self,
+        timeout: Option<Duration>,
+        stop: &CancellationToken,
+    ) -> Result<()> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut guard = self.lock.lock().unwrap();
+        loop {
+            match self.get(stop) {
+                ConnectionState::Connected => return Ok(()),
+                ConnectionState::Stopped => return Err(ShmError::NotReady),
+                _ => {}
+            }
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                return Err(ShmError::Timeout);
+            }
+            // Остановку worker'а видно только по токену -- просыпаемся и
+            // без уведомления.
+            let slice = deadline.map_or(Duration::from_millis(50), |deadline| {
+                (deadline - now).min(Duration::from_millis(50))
+            });
+            guard = self.changed.wait_timeout(guard, slice).unwrap().0;
+        }
+    }
+}
@@ -1,17 +1,46 @@
+use std::borrow::Cow;
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use std::sync::mpsc::{self, Receiver, Sender};
 
+use crate::cancel::CancellationToken;
+use crate::chunking::{self, Reassembly};
 use crate::client::SharedClient;
-use crate::constants::MAX_MESSAGE_SIZE;
-use crate::error::{Result, ShmError};
-use crate::server::SharedServer;
-use crate::wait_delay;
-use crate::win::{self};
+use crate::constants::{
+    FEATURE_CHUNKING, MAX_MESSAGE_SIZE, MSG_FLAG_CHUNK, MSG_USER_FLAGS_MASK, SUPPORTED_FEATURES,
+};
+#[cfg(feature = "encryption")]
+use crate::crypto::{ChannelCipher, EncryptionOptions, Role};
+use crate::disconnect::DisconnectReason;
+use crate::error::{ensure_config, ErrorContext, Result, ResultExt, ShmError};
+use crate::health::{ChannelHealth, LinkActivity, LinkState, QueueDepths};
+use crate::latency::{self, LatencyHistogram, LatencySnapshot};
+use crate::metrics::{self, ChannelRole, MetricsSource};
+use crate::naming::Namespace;
+use crate::registry::{self, ServiceKind, ServiceSource};
+use crate::retry::BackoffStrategy;
+use crate::ring::{ensure_space_threshold, CreditWindow};
+use crate::server::{ServerOptions, SharedServer};
+use crate::platform::{self, PlatformEvent};
+
+mod gate;
+mod handler_fn;
+mod link;
+mod pool;
+mod ticket;
+
+use gate::{QueuePermit, SendGate};
+pub use handler_fn::AutoHandlerFn;
+use link::ConnectionCell;
+pub use link::ConnectionState;
+use pool::BufferPool;
+pub use pool::PooledMessage;
+use ticket::Completion;
+pub use ticket::SendTicket;
 
 fn map_spawn_error(err: std::io::Error, context: &'static str) -> ShmError {
     let code = err.raw_os_error().map(|c| c as u32).unwrap_or(0xFFFFFFFF);
@@ -31,7 +60,7 @@ fn map_spawn_error(err: std::io::Error, context: &'static str) -> ShmError {
 /// модуля, а не только для одного места использования внутри библиотеки).
 ///
 /// При обнаружении self-join `JoinHandle` просто дропается без join: поток
-/// уже видит `running=false` (устанавливается до этого вызова) и завершится
+/// уже видит отменённый `CancellationToken` (до этого вызова) и завершится
 /// сам -- безопасный detach силами ОС, не утечка (тред всё равно скоро
 /// вернёт управление и выйдет из своего цикла).
 fn join_unless_self(handle: JoinHandle<()>) {
@@ -40,6 +69,43 @@ fn join_unless_self(handle: JoinHandle<()>) {
     }
 }
 
+/// Просит worker дописать очередь отправки и ждёт ответа до `timeout`.
+/// Из собственного worker'а (drop внутри callback'а) не ждёт: дописывать
+/// было бы некому.
+fn flush_before_drop(
+    cmd_tx: &Sender<WorkerCommand>,
+    join: &Mutex<Option<JoinHandle<()>>>,
+    stop: &CancellationToken,
+    timeout: Option<Duration>,
+) {
+    let Some(timeout) = timeout.filter(|timeout| !timeout.is_zero()) else {
+        return;
+    };
+    let on_worker = join
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|handle| handle.thread().id() == thread::current().id());
+    if on_worker || stop.is_cancelled() {
+        return;
+    }
+    let (done_tx, done_rx) = mpsc::channel();
+    if cmd_tx.send(WorkerCommand::Flush(done_tx)).is_ok() {
+        // Выход worker'а роняет `done_tx` -- ожидание тоже заканчивается.
+        let _ = done_rx.recv_timeout(timeout);
+    }
+}
+
+/// Отвечает на `WorkerCommand::Flush`, если очередь пуста и пир вычитал
+/// исходящее кольцо (`tx_ring` -- его глубина).
+fn settle_flush(flush: &mut Option<Sender<()>>, queue: &SendQueue, tx_ring: u32) {
+    if tx_ring == 0 && queue.len() == 0 {
+        if let Some(done) = flush.take() {
+            let _ = done.send(());
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelKind {
     ServerToClient,
@@ -57,15 +123,214 @@ pub enum ChannelKind {
 /// 2026-07-10). Это исключает deadlock, но означает, что к моменту
 /// возврата из `Drop` поток может быть ещё не завершён -- если нужна
 /// гарантия полного завершения, дропайте объект из ДРУГОГО потока.
+///
+/// Обёртки, передающие callback'и внутреннему handler'у (`TapHandler`,
+/// `ErrorRecorder`, `record::RecordingHandler`), переопределяют КАЖДЫЙ
+/// метод трейта: новый метод нужно добавить и в них, иначе для
+/// обёрнутого handler'а он молча останется методом по умолчанию.
 pub trait AutoHandler: Send + Sync + 'static {
     fn on_connect(&self) {}
+    /// Подключение с номером соединения (`connection_epoch`, одинаков у
+    /// обеих сторон): другой номер у следующего `on_connect_with_epoch` --
+    /// пир переподключился, состояние прежней сессии можно выбросить.
+    /// Worker зовёт этот метод, по умолчанию он передаёт вызов в
+    /// `on_connect`.
+    fn on_connect_with_epoch(&self, _epoch: u32) {
+        self.on_connect();
+    }
     fn on_disconnect(&self) {}
+    /// Отключение с причиной: что оставил пир (`Unknown` -- пир упал);
+    /// `ProtocolError` -- соединение сбросила эта сторона, потому что пир
+    /// нарушил протокол. Worker зовёт этот метод, по умолчанию он
+    /// передаёт вызов в `on_disconnect`.
+    fn on_disconnect_with_reason(&self, _reason: DisconnectReason) {
+        self.on_disconnect();
+    }
+    /// Клиент не подключился (или потерял соединение) и следующую попытку
+    /// сделает через `delay` (`AutoOptions::backoff`); `attempt` -- номер
+    /// неудачной попытки подряд, с 1.
+    fn on_reconnect_scheduled(&self, _delay: Duration, _attempt: u32) {}
+    /// Попытка подключения клиента `attempt` (с 1, подряд) не удалась с
+    /// `last_error` (тот же `on_error` тоже приходит). Прекратить попытки
+    /// можно токеном отмены из `AutoOptions::cancel`.
+    fn on_reconnect(&self, _attempt: u32, _last_error: &ShmError) {}
+    /// Периодический вызов с worker'а (`AutoOptions::tick_interval`), в том
+    /// числе без соединения; `elapsed` -- время с прошлого `on_tick` (или со
+    /// старта worker'а). Место для heartbeat'ов и сброса агрегатов без
+    /// своего таймера на канал.
+    fn on_tick(&self, _elapsed: Duration) {}
     fn on_message(&self, _direction: ChannelKind, _payload: &[u8]) {}
+    /// Сообщение вместе с флагами отправителя (`send_with_flags`, биты
+    /// `MSG_USER_FLAGS_MASK`; 0 -- без флагов). Worker зовёт этот метод,
+    /// по умолчанию он передаёт сообщение в `on_message`.
+    fn on_message_with_flags(&self, direction: ChannelKind, payload: &[u8], _flags: u16) {
+        self.on_message(direction, payload);
+    }
+    /// Сообщение во владение handler'у -- вместо `on_message_with_flags`,
+    /// если включён `AutoOptions::message_pool`. Буфер взят из пула
+    /// worker'а и вернётся в него при drop [`PooledMessage`]. По умолчанию
+    /// передаёт сообщение в `on_message_with_flags`.
+    fn on_message_owned(&self, direction: ChannelKind, message: PooledMessage, flags: u16) {
+        self.on_message_with_flags(direction, &message, flags);
+    }
     fn on_overflow(&self, _direction: ChannelKind, _count: u32) {}
     fn on_space_available(&self, _direction: ChannelKind) {}
+    /// Сообщение `send_with_deadline` не ушло до дедлайна и отброшено.
+    fn on_expired(&self, _direction: ChannelKind, _payload: &[u8]) {}
     fn on_error(&self, _err: ShmError) {}
+    /// Ошибка worker'а вместе с каналом, стороной и операцией. Worker
+    /// сообщает ошибки через этот метод; по умолчанию `error.error`
+    /// передаётся в `on_error`.
+    fn on_error_with_context(&self, error: ErrorContext) {
+        self.on_error(error.error);
+    }
+    /// Сообщение из очереди отправки сейчас уйдёт в кольцо (один раз на
+    /// сообщение, повтор после `QueueFull` не в счёт).
+    fn on_before_send(&self, _direction: ChannelKind, _payload: &[u8]) {}
+    /// Вызывается перед `on_message`; `enqueue_latency` -- путь от записи
+    /// отправителем до чтения, если кадр с меткой (`AutoOptions::latency`
+    /// у отправителя).
+    fn on_after_receive(
+        &self,
+        _direction: ChannelKind,
+        _payload: &[u8],
+        _enqueue_latency: Option<Duration>,
+    ) {
+    }
+}
+
+/// Handler с «прослушкой»: все callback'и уходят в `inner`, а
+/// `on_before_send`/`on_after_receive` -- сначала в `tap`, потом в `inner`.
+/// Аудит или сэмплинг задержки навешиваются на любой handler без его
+/// правки; обёртки вкладываются друг в друга.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use xshm::{AutoHandler, AutoOptions, AutoServer, ChannelKind, TapHandler};
+///
+/// struct App;
+/// impl AutoHandler for App {}
+///
+/// struct Audit;
+/// impl AutoHandler for Audit {
+///     fn on_after_receive(&self, _: ChannelKind, payload: &[u8], latency: Option<Duration>) {
+///         eprintln!("recv {} bytes, latency {latency:?}", payload.len());
+///     }
+/// }
+///
+/// let handler = Arc::new(TapHandler::new(Arc::new(App), Arc::new(Audit)));
+/// let options = AutoOptions::builder().latency(true).build()?;
+/// let _server = AutoServer::start("app", handler, options)?;
+/// # Ok::<(), xshm::ShmError>(())
+/// ```
+pub struct TapHandler {
+    inner: Arc<dyn AutoHandler>,
+    tap: Arc<dyn AutoHandler>,
+}
+
+impl TapHandler {
+    pub fn new(inner: Arc<dyn AutoHandler>, tap: Arc<dyn AutoHandler>) -> Self {
+        Self { inner, tap }
+    }
+}
+
+impl AutoHandler for TapHandler {
+    fn on_connect(&self) {
+        self.inner.on_connect();
+    }
+
+    fn on_connect_with_epoch(&self, epoch: u32) {
+        self.inner.on_connect_with_epoch(epoch);
+    }
+
+    fn on_disconnect(&self) {
+        self.inner.on_disconnect();
+    }
+
+    fn on_disconnect_with_reason(&self, reason: DisconnectReason) {
+        self.inner.on_disconnect_with_reason(reason);
+    }
+
+    fn on_reconnect_scheduled(&self, delay: Duration, attempt: u32) {
+        self.inner.on_reconnect_scheduled(delay, attempt);
+    }
+
+    fn on_reconnect(&self, attempt: u32, last_error: &ShmError) {
+        self.inner.on_reconnect(attempt, last_error);
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.inner.on_tick(elapsed);
+    }
+
+    fn on_message(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_message(direction, payload);
+    }
+
+    fn on_message_with_flags(&self, direction: ChannelKind, payload: &[u8], flags: u16) {
+        self.inner.on_message_with_flags(direction, payload, flags);
+    }
+
+    fn on_message_owned(&self, direction: ChannelKind, message: PooledMessage, flags: u16) {
+        self.inner.on_message_owned(direction, message, flags);
+    }
+
+    fn on_overflow(&self, direction: ChannelKind, count: u32) {
+        self.inner.on_overflow(direction, count);
+    }
+
+    fn on_space_available(&self, direction: ChannelKind) {
+        self.inner.on_space_available(direction);
+    }
+
+    fn on_expired(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_expired(direction, payload);
+    }
+
+    fn on_error(&self, err: ShmError) {
+        self.inner.on_error(err);
+    }
+
+    fn on_error_with_context(&self, error: ErrorContext) {
+        self.inner.on_error_with_context(error);
+    }
+
+    fn on_before_send(&self, direction: ChannelKind, payload: &[u8]) {
+        self.tap.on_before_send(direction, payload);
+        self.inner.on_before_send(direction, payload);
+    }
+
+    fn on_after_receive(
+        &self,
+        direction: ChannelKind,
+        payload: &[u8],
+        enqueue_latency: Option<Duration>,
+    ) {
+        self.tap
+            .on_after_receive(direction, payload, enqueue_latency);
+        self.inner
+            .on_after_receive(direction, payload, enqueue_latency);
+    }
 }
 
+/// Что `send` делает, когда очередь отправки worker'а полна
+/// (`max_send_queue`/`max_send_queue_bytes`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum QueuePolicy {
+    /// Новое сообщение встаёт в очередь, самое старое вытесняется
+    /// (`AutoStatsSnapshot::send_queue_drops`/`send_queue_byte_drops`).
+    #[default]
+    DropOldest,
+    /// `send` ждёт места в очереди не дольше `timeout`, затем --
+    /// `ShmError::QueueFull`. Сообщения не теряются, пока worker работает.
+    Block { timeout: Duration },
+}
+
+/// Фильтр входящих сообщений: `false` -- сообщение отбрасывается до
+/// `on_after_receive`/`on_message` (см. `AutoOptions::filter`).
+pub type MessageFilter = Arc<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
 #[derive(Clone)]
 pub struct AutoOptions {
     /// Интервал опроса в worker loop. Переименовано из `wait_timeout` (0.6.0)
@@ -74,9 +339,77 @@ pub struct AutoOptions {
     /// перекладывать значения при построении `AutoOptions` внутри `dispatch/`.
     pub poll_timeout: Duration,
     pub reconnect_delay: Duration,
+    /// Пауза клиента между попытками подключения (см. [`BackoffStrategy`]).
+    /// `None` -- постоянная `reconnect_delay`.
+    pub backoff: Option<BackoffStrategy>,
     pub connect_timeout: Duration,
     pub max_send_queue: usize,
+    /// Бюджет очереди отправки в байтах payload'ов поверх `max_send_queue`:
+    /// при превышении вытесняются самые старые сообщения. `None` -- только
+    /// лимит по числу (до `max_send_queue` x 64 KiB на канал).
+    pub max_send_queue_bytes: Option<usize>,
+    /// Вытеснять старые сообщения или ждать места (см. [`QueuePolicy`]).
+    pub queue_policy: QueuePolicy,
     pub recv_batch: usize,
+    /// CRC-32 трейлер на каждое исходящее сообщение (см.
+    /// `SharedServer::set_checksum`). Битые входящие сообщения
+    /// отбрасываются и считаются в `AutoStatsSnapshot::checksum_errors`.
+    pub checksum: bool,
+    /// Кредитный режим для входящих сообщений (см.
+    /// `SharedServer::set_credit_window`): отправитель на другой стороне
+    /// ждёт кредитов в очереди отправки вместо вытеснения старых
+    /// сообщений. `None` -- обычный режим.
+    pub credit_window: Option<CreditWindow>,
+    /// Порог space-события для входящих сообщений в процентах ёмкости
+    /// кольца (см. `SharedServer::set_space_threshold`): отправитель на
+    /// другой стороне продолжает, когда worker освободил столько места, а
+    /// не только опустошив кольцо. `None` -- только пустое кольцо.
+    pub space_threshold: Option<u8>,
+    /// Метки времени на исходящих сообщениях и гистограмма задержки
+    /// входящих (`AutoStatsSnapshot::latency`, см. `xshm::latency`).
+    /// Задержку входящих видно, только если метки ставит и другая сторона.
+    pub latency: bool,
+    /// Срок годности исходящих сообщений (см. `SharedServer::set_default_ttl`):
+    /// не прочитанное другой стороной за `ttl` отбрасывается. Отсчёт идёт с
+    /// записи в кольцо, время в очереди отправки worker'а не считается.
+    /// Просроченные входящие -- `AutoStatsSnapshot::expired_messages`.
+    pub ttl: Option<Duration>,
+    /// Шифрование payload'ов (feature `encryption`). `None` -- открытый
+    /// текст. Должно быть включено на ОБЕИХ сторонах: обмен ключами идёт
+    /// первым сообщением каждого соединения, до него отправка удерживается
+    /// в очереди. Payload больше `crypto::MAX_PLAINTEXT_SIZE` не делится на
+    /// куски: `send` сразу возвращает `ShmError::MessageTooLarge`.
+    #[cfg(feature = "encryption")]
+    pub encryption: Option<EncryptionOptions>,
+    /// Внешний токен отмены: worker берёт от него дочерний, и `cancel()`
+    /// этого токена (или его предка) останавливает канал так же, как
+    /// `stop()`. `None` -- только `stop()`/drop.
+    pub cancel: Option<CancellationToken>,
+    /// Предикат над payload'ом (после расшифровки), вызывается на worker'е
+    /// до handler'а: отвергнутые сообщения не доходят до callback'ов и
+    /// считаются в `AutoStatsSnapshot::filtered_messages`. `None` -- всё.
+    pub filter: Option<MessageFilter>,
+    /// Сколько `Drop` ждёт, пока worker допишет очередь отправки и пир
+    /// вычитает исходящее кольцо; по истечении -- обрыв, как при `None`.
+    /// `None` -- `Drop` останавливает worker сразу, неотправленное теряется.
+    pub flush_on_drop: Option<Duration>,
+    /// Входящие сообщения во владение handler'у (`on_message_owned`):
+    /// worker копирует payload в буфер из пула, который хранит до
+    /// `message_pool` свободных буферов. 0 -- пула нет, сообщения приходят
+    /// ссылкой в `on_message_with_flags`.
+    pub message_pool: usize,
+    /// Проверять на простое worker'а (раз в `poll_timeout`), жив ли процесс
+    /// пира (PID из handshake, см. `SharedServer::is_peer_process_alive`):
+    /// упавший без disconnect пир иначе считается подключённым вечно.
+    /// Мёртвый пир -- `on_disconnect` и переподключение.
+    pub check_peer_process: bool,
+    /// Как часто worker зовёт `AutoHandler::on_tick`. Тик приходит между
+    /// проходами цикла worker'а: пока клиент подключается или выжидает
+    /// паузу переподключения, тики откладываются. `None` -- без тиков.
+    pub tick_interval: Option<Duration>,
+    /// Namespace объектов канала (см. [`Namespace`]); у сервера и клиента
+    /// должен совпадать.
+    pub namespace: Namespace,
 }
 
 impl Default for AutoOptions {
@@ -84,73 +417,589 @@ impl Default for AutoOptions {
         Self {
             poll_timeout: Duration::from_millis(50),
             reconnect_delay: Duration::from_millis(250),
+            backoff: None,
             connect_timeout: Duration::from_secs(2),
             max_send_queue: 256,
+            max_send_queue_bytes: None,
+            queue_policy: QueuePolicy::DropOldest,
             recv_batch: 32,
+            checksum: false,
+            credit_window: None,
+            space_threshold: None,
+            latency: false,
+            ttl: None,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            cancel: None,
+            filter: None,
+            flush_on_drop: None,
+            message_pool: 0,
+            check_peer_process: true,
+            tick_interval: None,
+            namespace: Namespace::Session,
         }
     }
 }
 
-#[derive(Default, Clone, Debug)]
+impl AutoOptions {
+    /// Построитель с проверкой значений в [`AutoOptionsBuilder::build`].
+    pub fn builder() -> AutoOptionsBuilder {
+        AutoOptionsBuilder::default()
+    }
+
+    /// Отбрасывает значения, с которыми worker крутится вхолостую или
+    /// зависает: нулевые таймауты, пачки и очередь отправки. Вызывается
+    /// и в `AutoServer::start`/`AutoClient::connect`.
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(
+            !self.poll_timeout.is_zero(),
+            "poll_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.reconnect_delay.is_zero(),
+            "reconnect_delay must be non-zero",
+        )?;
+        self.backoff
+            .as_ref()
+            .map_or(Ok(()), BackoffStrategy::validate)?;
+        ensure_config(
+            !self.connect_timeout.is_zero(),
+            "connect_timeout must be non-zero",
+        )?;
+        ensure_config(self.max_send_queue > 0, "max_send_queue must be non-zero")?;
+        ensure_send_queue_bytes(self.max_send_queue_bytes)?;
+        ensure_config(self.recv_batch > 0, "recv_batch must be non-zero")?;
+        ensure_config(
+            self.ttl.is_none_or(|ttl| !ttl.is_zero()),
+            "ttl must be non-zero",
+        )?;
+        ensure_config(
+            self.tick_interval
+                .is_none_or(|interval| !interval.is_zero()),
+            "tick_interval must be non-zero",
+        )?;
+        ensure_space_threshold(self.space_threshold)?;
+        self.credit_window
+            .as_ref()
+            .map_or(Ok(()), CreditWindow::validate)
+    }
+}
+
+/// Бюджет очереди отправки должен вмещать хотя бы одно сообщение
+/// максимального размера, иначе крупное сообщение вытесняло бы всю очередь.
+pub(crate) fn ensure_send_queue_bytes(budget: Option<usize>) -> Result<()> {
+    ensure_config(
+        budget.is_none_or(|bytes| bytes >= MAX_MESSAGE_SIZE),
+        "max_send_queue_bytes must fit one MAX_MESSAGE_SIZE message",
+    )
+}
+
+/// Построитель [`AutoOptions`]; незаданные поля -- из `Default`.
+#[derive(Clone, Default)]
+pub struct AutoOptionsBuilder {
+    options: AutoOptions,
+}
+
+impl AutoOptionsBuilder {
+    pub fn poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.options.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.options.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    pub fn backoff(mut self, backoff: Option<BackoffStrategy>) -> Self {
+        self.options.backoff = backoff;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.options.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn max_send_queue(mut self, max_send_queue: usize) -> Self {
+        self.options.max_send_queue = max_send_queue;
+        self
+    }
+
+    pub fn max_send_queue_bytes(mut self, max_send_queue_bytes: Option<usize>) -> Self {
+        self.options.max_send_queue_bytes = max_send_queue_bytes;
+        self
+    }
+
+    pub fn queue_policy(mut self, queue_policy: QueuePolicy) -> Self {
+        self.options.queue_policy = queue_policy;
+        self
+    }
+
+    pub fn recv_batch(mut self, recv_batch: usize) -> Self {
+        self.options.recv_batch = recv_batch;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    pub fn credit_window(mut self, credit_window: Option<CreditWindow>) -> Self {
+        self.options.credit_window = credit_window;
+        self
+    }
+
+    pub fn space_threshold(mut self, space_threshold: Option<u8>) -> Self {
+        self.options.space_threshold = space_threshold;
+        self
+    }
+
+    pub fn latency(mut self, latency: bool) -> Self {
+        self.options.latency = latency;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.options.ttl = ttl;
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, encryption: Option<EncryptionOptions>) -> Self {
+        self.options.encryption = encryption;
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.options.cancel = cancel;
+        self
+    }
+
+    pub fn filter(mut self, filter: Option<MessageFilter>) -> Self {
+        self.options.filter = filter;
+        self
+    }
+
+    pub fn flush_on_drop(mut self, flush_on_drop: Option<Duration>) -> Self {
+        self.options.flush_on_drop = flush_on_drop;
+        self
+    }
+
+    pub fn message_pool(mut self, message_pool: usize) -> Self {
+        self.options.message_pool = message_pool;
+        self
+    }
+
+    pub fn check_peer_process(mut self, check_peer_process: bool) -> Self {
+        self.options.check_peer_process = check_peer_process;
+        self
+    }
+
+    pub fn tick_interval(mut self, tick_interval: Option<Duration>) -> Self {
+        self.options.tick_interval = tick_interval;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    /// Проверяет значения (см. [`AutoOptions::validate`]).
+    pub fn build(self) -> Result<AutoOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct AutoStatsSnapshot {
     pub sent_messages: u64,
+    /// Сумма длин записанных сообщений (до сжатия и шифрования).
+    pub sent_bytes: u64,
     pub send_overflows: u64,
+    /// Сообщения, вытесненные из очереди отправки лимитом `max_send_queue`.
+    pub send_queue_drops: u64,
+    /// Сообщения, вытесненные из очереди отправки бюджетом
+    /// `max_send_queue_bytes`.
+    pub send_queue_byte_drops: u64,
+    /// Сообщения `send_with_deadline`, отброшенные из очереди отправки по
+    /// истечении дедлайна.
+    pub send_expired: u64,
+    /// Сообщений в очереди отправки на момент снимка (ещё не записаны в
+    /// кольцо).
+    pub send_queue_depth: usize,
+    /// Сумма их длин.
+    pub send_queue_bytes: usize,
     pub received_messages: u64,
+    /// Сумма длин сообщений, переданных handler'у.
+    pub received_bytes: u64,
+    /// Входящие сообщения, отвергнутые `AutoOptions::filter` (в
+    /// `received_messages` не входят).
+    pub filtered_messages: u64,
     pub receive_overflows: u64,
+    /// Входящие сообщения, отброшенные из-за несовпадения CRC-32.
+    pub checksum_errors: u64,
+    /// Входящие сообщения, просроченные до прочтения (`AutoOptions::ttl`
+    /// другой стороны).
+    pub expired_messages: u64,
+    /// Установленные соединения (первое + каждый reconnect).
+    pub connects: u64,
+    /// Потерянные соединения.
+    pub disconnects: u64,
+    /// Задержка доставки входящих сообщений в одну сторону, от записи
+    /// отправителем до чтения здесь (`AutoOptions::latency` у
+    /// отправителя): p50/p99 и последняя измеренная (`last_ns`). Ответ
+    /// пира в неё не входит; часы общие только у процессов одной машины.
+    pub latency: LatencySnapshot,
+}
+
+impl AutoStatsSnapshot {
+    /// Прибавляет счётчики `other` (сумма по каналам одного сервиса в
+    /// `metrics`). Задержку не трогает: квантили разных каналов не
+    /// складываются.
+    pub(crate) fn accumulate(&mut self, other: &Self) {
+        self.sent_messages += other.sent_messages;
+        self.sent_bytes += other.sent_bytes;
+        self.send_overflows += other.send_overflows;
+        self.send_queue_drops += other.send_queue_drops;
+        self.send_queue_byte_drops += other.send_queue_byte_drops;
+        self.send_expired += other.send_expired;
+        self.send_queue_depth += other.send_queue_depth;
+        self.send_queue_bytes += other.send_queue_bytes;
+        self.received_messages += other.received_messages;
+        self.received_bytes += other.received_bytes;
+        self.filtered_messages += other.filtered_messages;
+        self.receive_overflows += other.receive_overflows;
+        self.checksum_errors += other.checksum_errors;
+        self.expired_messages += other.expired_messages;
+        self.connects += other.connects;
+        self.disconnects += other.disconnects;
+    }
 }
 
 #[derive(Default)]
 struct AutoStats {
     sent_messages: AtomicU64,
+    sent_bytes: AtomicU64,
     send_overflows: AtomicU64,
+    send_queue_drops: AtomicU64,
+    send_queue_byte_drops: AtomicU64,
+    send_expired: AtomicU64,
     received_messages: AtomicU64,
+    received_bytes: AtomicU64,
+    filtered_messages: AtomicU64,
     receive_overflows: AtomicU64,
+    checksum_errors: AtomicU64,
+    expired_messages: AtomicU64,
+    connects: AtomicU64,
+    disconnects: AtomicU64,
+    latency: Arc<LatencyHistogram>,
+    /// Итерации цикла server worker'а; не входит в снимок, нужен `watchdog`.
+    heartbeat: AtomicU64,
+    /// `pause()`: worker не читает входящее кольцо.
+    paused: AtomicBool,
+    /// Для `state()`: обновляется на connect/disconnect (`ErrorRecorder`).
+    connection: ConnectionCell,
+    /// Для `health()` и снимка: активность и глубины очередей, которые
+    /// публикует worker (`publish_depths`).
+    activity: LinkActivity,
+    send_queue_depth: AtomicUsize,
+    send_queue_bytes: AtomicUsize,
+    rx_ring_depth: AtomicU32,
+    tx_ring_depth: AtomicU32,
 }
 
 impl AutoStats {
     fn snapshot(&self) -> AutoStatsSnapshot {
         AutoStatsSnapshot {
             sent_messages: self.sent_messages.load(Ordering::Relaxed),
+            sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
             send_overflows: self.send_overflows.load(Ordering::Relaxed),
+            send_queue_drops: self.send_queue_drops.load(Ordering::Relaxed),
+            send_queue_byte_drops: self.send_queue_byte_drops.load(Ordering::Relaxed),
+            send_expired: self.send_expired.load(Ordering::Relaxed),
+            send_queue_depth: self.send_queue_depth.load(Ordering::Relaxed),
+            send_queue_bytes: self.send_queue_bytes.load(Ordering::Relaxed),
             received_messages: self.received_messages.load(Ordering::Relaxed),
+            received_bytes: self.received_bytes.load(Ordering::Relaxed),
+            filtered_messages: self.filtered_messages.load(Ordering::Relaxed),
             receive_overflows: self.receive_overflows.load(Ordering::Relaxed),
+            checksum_errors: self.checksum_errors.load(Ordering::Relaxed),
+            expired_messages: self.expired_messages.load(Ordering::Relaxed),
+            connects: self.connects.load(Ordering::Relaxed),
+            disconnects: self.disconnects.load(Ordering::Relaxed),
+            latency: self.latency.snapshot(),
+        }
+    }
+
+    fn health(&self, stop: &CancellationToken) -> ChannelHealth {
+        let connects = self.connects.load(Ordering::Relaxed);
+        let state = if stop.is_cancelled() {
+            LinkState::Stopped
+        } else if connects > self.disconnects.load(Ordering::Relaxed) {
+            LinkState::Connected
+        } else {
+            LinkState::Waiting
+        };
+        let depths = QueueDepths {
+            send_queue: self.send_queue_depth.load(Ordering::Relaxed),
+            send_queue_bytes: self.send_queue_bytes.load(Ordering::Relaxed),
+            rx_ring: self.rx_ring_depth.load(Ordering::Relaxed),
+            tx_ring: self.tx_ring_depth.load(Ordering::Relaxed),
+        };
+        self.activity
+            .health(state, depths, connects.saturating_sub(1))
+    }
+
+    /// Глубины очередей для `health()`; `rings` -- (входящие, исходящие).
+    fn publish_depths(&self, queue: &SendQueue, rings: (u32, u32)) {
+        self.send_queue_depth.store(queue.len(), Ordering::Relaxed);
+        self.send_queue_bytes
+            .store(queue.bytes(), Ordering::Relaxed);
+        self.rx_ring_depth.store(rings.0, Ordering::Relaxed);
+        self.tx_ring_depth.store(rings.1, Ordering::Relaxed);
+    }
+}
+
+/// Handler worker'а: запоминает последнюю ошибку для `health()` и
+/// состояние соединения для `state()` и передаёт все callback'и
+/// пользовательскому handler'у.
+struct ErrorRecorder {
+    inner: Arc<dyn AutoHandler>,
+    stats: Arc<AutoStats>,
+}
+
+impl AutoHandler for ErrorRecorder {
+    fn on_connect(&self) {
+        self.inner.on_connect();
+    }
+
+    fn on_connect_with_epoch(&self, epoch: u32) {
+        self.stats.connection.set_connected(true);
+        self.inner.on_connect_with_epoch(epoch);
+    }
+
+    fn on_disconnect(&self) {
+        self.inner.on_disconnect();
+    }
+
+    fn on_disconnect_with_reason(&self, reason: DisconnectReason) {
+        self.stats.connection.set_connected(false);
+        self.inner.on_disconnect_with_reason(reason);
+    }
+
+    fn on_reconnect_scheduled(&self, delay: Duration, attempt: u32) {
+        self.inner.on_reconnect_scheduled(delay, attempt);
+    }
+
+    fn on_reconnect(&self, attempt: u32, last_error: &ShmError) {
+        self.inner.on_reconnect(attempt, last_error);
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.inner.on_tick(elapsed);
+    }
+
+    fn on_message(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_message(direction, payload);
+    }
+
+    fn on_message_with_flags(&self, direction: ChannelKind, payload: &[u8], flags: u16) {
+        self.inner.on_message_with_flags(direction, payload, flags);
+    }
+
+    fn on_message_owned(&self, direction: ChannelKind, message: PooledMessage, flags: u16) {
+        self.inner.on_message_owned(direction, message, flags);
+    }
+
+    fn on_overflow(&self, direction: ChannelKind, count: u32) {
+        self.inner.on_overflow(direction, count);
+    }
+
+    fn on_space_available(&self, direction: ChannelKind) {
+        self.inner.on_space_available(direction);
+    }
+
+    fn on_expired(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_expired(direction, payload);
+    }
+
+    fn on_error(&self, err: ShmError) {
+        self.stats.activity.record_error(&err);
+        self.inner.on_error(err);
+    }
+
+    fn on_error_with_context(&self, error: ErrorContext) {
+        self.stats.activity.record_error(&error.error);
+        self.inner.on_error_with_context(error);
+    }
+
+    fn on_before_send(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_before_send(direction, payload);
+    }
+
+    fn on_after_receive(
+        &self,
+        direction: ChannelKind,
+        payload: &[u8],
+        enqueue_latency: Option<Duration>,
+    ) {
+        self.inner
+            .on_after_receive(direction, payload, enqueue_latency);
+    }
+}
+
+fn recording(handler: Arc<dyn AutoHandler>, stats: &Arc<AutoStats>) -> Arc<dyn AutoHandler> {
+    Arc::new(ErrorRecorder {
+        inner: handler,
+        stats: stats.clone(),
+    })
+}
+
+impl MetricsSource for AutoStats {
+    fn snapshot(&self) -> AutoStatsSnapshot {
+        AutoStats::snapshot(self)
+    }
+}
+
+impl ServiceSource for AutoStats {
+    fn stats(&self) -> Option<AutoStatsSnapshot> {
+        Some(self.snapshot())
+    }
+}
+
+fn weak_source(stats: &Arc<AutoStats>) -> Weak<dyn MetricsSource> {
+    let weak: Weak<AutoStats> = Arc::downgrade(stats);
+    weak
+}
+
+/// Сообщение в очереди отправки worker'а.
+struct Outgoing {
+    data: Vec<u8>,
+    /// `send_with_deadline`: позже этого момента сообщение не отправляется.
+    deadline: Option<Instant>,
+    /// Флаги `send_with_flags` (биты `MSG_USER_FLAGS_MASK`).
+    flags: u16,
+    /// `on_before_send` уже вызван -- при повторе после `QueueFull` не звать.
+    traced: bool,
+    /// Кадр, разрезанный на куски и записанный не до конца: продолжение
+    /// идёт с `sent` байт того же кадра, а не с заново зашифрованного.
+    sealed: Option<Vec<u8>>,
+    sent: usize,
+    /// Квитанция `send_with_ticket`.
+    completion: Option<Completion>,
+    /// Место в очереди при `QueuePolicy::Block`.
+    _permit: Option<QueuePermit>,
+    /// `send_urgent`: в очередь перед обычными сообщениями.
+    urgent: bool,
+}
+
+impl Outgoing {
+    fn new(data: &[u8], deadline: Option<Instant>, flags: u16) -> Self {
+        Self {
+            data: data.to_vec(),
+            deadline,
+            flags,
+            traced: false,
+            sealed: None,
+            sent: 0,
+            completion: None,
+            _permit: None,
+            urgent: false,
+        }
+    }
+
+    /// Сообщает итог квитанции, если она есть.
+    fn complete(&mut self, result: Result<()>) {
+        if let Some(completion) = self.completion.take() {
+            completion.complete(result);
         }
     }
 }
 
 enum WorkerCommand {
-    Send(Vec<u8>),
+    Send(Outgoing),
     Shutdown,
+    /// Разорвать текущее соединение, не останавливая worker.
+    Disconnect,
+    /// Ответить, когда отправлять станет нечего (см. `flush_before_drop`).
+    Flush(Sender<()>),
+}
+
+#[derive(Default)]
+struct Queued {
+    messages: VecDeque<Outgoing>,
+    /// Сумма длин `messages` (для `max_send_queue_bytes`).
+    bytes: usize,
 }
 
 struct SendQueue {
-    queue: Mutex<VecDeque<Vec<u8>>>,
+    queue: Mutex<Queued>,
 }
 
 impl SendQueue {
     fn new() -> Self {
         Self {
-            queue: Mutex::new(VecDeque::new()),
+            queue: Mutex::new(Queued::default()),
         }
     }
 
-    fn push(&self, data: Vec<u8>) {
+    fn push(&self, msg: Outgoing) {
+        let mut guard = self.queue.lock().unwrap();
+        guard.bytes += msg.data.len();
+        guard.messages.push_back(msg);
+    }
+
+    fn pop(&self) -> Option<Outgoing> {
         let mut guard = self.queue.lock().unwrap();
-        guard.push_back(data);
+        let msg = guard.messages.pop_front()?;
+        guard.bytes -= msg.data.len();
+        Some(msg)
     }
 
-    fn pop(&self) -> Option<Vec<u8>> {
+    fn push_front(&self, msg: Outgoing) {
         let mut guard = self.queue.lock().unwrap();
-        guard.pop_front()
+        guard.bytes += msg.data.len();
+        guard.messages.push_front(msg);
     }
 
-    fn push_front(&self, data: Vec<u8>) {
+    /// Срочное сообщение -- после уже стоящих срочных и после кадра,
+    /// записанного кусками не до конца (его продолжение разрывать нельзя),
+    /// но раньше всех обычных.
+    fn push_urgent(&self, msg: Outgoing) {
         let mut guard = self.queue.lock().unwrap();
-        guard.push_front(data);
+        guard.bytes += msg.data.len();
+        let at = guard
+            .messages
+            .iter()
+            .position(|queued| !queued.urgent && queued.sent == 0)
+            .unwrap_or(guard.messages.len());
+        guard.messages.insert(at, msg);
+    }
+
+    /// Вытесняет самое старое обычное сообщение; срочные -- только когда
+    /// обычных не осталось.
+    fn evict(&self) -> Option<Outgoing> {
+        let mut guard = self.queue.lock().unwrap();
+        let at = guard
+            .messages
+            .iter()
+            .position(|queued| !queued.urgent)
+            .unwrap_or(0);
+        let msg = guard.messages.remove(at)?;
+        guard.bytes -= msg.data.len();
+        Some(msg)
     }
 
     fn len(&self) -> usize {
-        self.queue.lock().unwrap().len()
+        self.queue.lock().unwrap().messages.len()
+    }
+
+    fn bytes(&self) -> usize {
+        self.queue.lock().unwrap().bytes
     }
 }
 
@@ -158,18 +1007,52 @@ pub struct AutoServer {
     cmd_tx: Sender<WorkerCommand>,
     join: Mutex<Option<JoinHandle<()>>>,
     stats: Arc<AutoStats>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
+    flush_on_drop: Option<Duration>,
+    gate: Arc<SendGate>,
+    /// Запись в `metrics`; у выделенных каналов `dispatch` её нет.
+    _metrics: Option<metrics::Registration>,
 }
 
 impl AutoServer {
     pub fn start(name: &str, handler: Arc<dyn AutoHandler>, options: AutoOptions) -> Result<Self> {
-        let mut server = SharedServer::start(name)?;
+        let mut server = Self::start_unlisted(name, handler, options)?;
+        let weak: Weak<AutoStats> = Arc::downgrade(&server.stats);
+        registry::register(name, ServiceKind::Auto, weak);
+        server._metrics = Some(metrics::register(
+            name,
+            ChannelRole::Server,
+            weak_source(&server.stats),
+        ));
+        Ok(server)
+    }
+
+    /// Как `start`, но без записи в `registry` и `metrics`: выделенные
+    /// каналы `dispatch` -- деталь реализации, а не самостоятельный сервис
+    /// (их счётчики выгружает `DispatchServer`).
+    pub(crate) fn start_unlisted(
+        name: &str,
+        handler: Arc<dyn AutoHandler>,
+        options: AutoOptions,
+    ) -> Result<Self> {
+        options.validate()?;
+        let flush_on_drop = options.flush_on_drop;
+        let gate = SendGate::new(&options);
+        let mut server = SharedServer::start_with_options(
+            name,
+            &ServerOptions {
+                namespace: options.namespace,
+                ..ServerOptions::default()
+            },
+        )?;
+        // Куски собирает только auto-слой -- предлагаем их отсюда.
+        server.set_features(server.features() | FEATURE_CHUNKING);
         let (tx, rx) = mpsc::channel();
         let stats = Arc::new(AutoStats::default());
-        let running = Arc::new(AtomicBool::new(true));
-        let join_running = running.clone();
+        let stop = CancellationToken::for_worker(options.cancel.as_ref())?;
+        let join_stop = stop.clone();
         let join_stats = stats.clone();
-        let join_handler = handler.clone();
+        let join_handler = recording(handler, &stats);
         let name_str = name.to_owned();
         // Thread name in debug only (opaque short tag `xsa-{name}` so
         // local traces still line up with the segment), anonymous in
@@ -183,14 +1066,23 @@ impl AutoServer {
         }
         let join = builder
             .spawn(move || {
-                server_worker(
-                    &name_str,
-                    &mut server,
-                    join_handler,
-                    options,
-                    rx,
-                    join_stats,
-                    join_running,
+                let guard = join_stop.clone();
+                let report = join_handler.clone();
+                guard.run_worker(
+                    || {
+                        server_worker(
+                            &name_str,
+                            &mut server,
+                            join_handler,
+                            options,
+                            rx,
+                            join_stats,
+                            join_stop,
+                        )
+                    },
+                    |err| {
+                        Origin::new(&name_str, ChannelRole::Server).report(&report, "worker", err)
+                    },
                 );
             })
             .map_err(|err| map_spawn_error(err, "spawn server worker"))?;
@@ -198,32 +1090,157 @@ impl AutoServer {
             cmd_tx: tx,
             join: Mutex::new(Some(join)),
             stats,
-            running,
+            stop,
+            flush_on_drop,
+            gate,
+            _metrics: None,
         })
     }
 
     pub fn send(&self, data: &[u8]) -> Result<()> {
-        if !self.running.load(Ordering::Acquire) {
-            return Err(ShmError::NotReady);
-        }
-        let msg = data.to_vec();
-        self.cmd_tx
-            .send(WorkerCommand::Send(msg))
-            .map_err(|_| ShmError::NotReady)
+        let msg = Outgoing::new(data, None, 0);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, но сообщение, не ушедшее в кольцо до `deadline`
+    /// (например, пролежавшее в очереди весь reconnect), отбрасывается и
+    /// передаётся в `AutoHandler::on_expired`.
+    pub fn send_with_deadline(&self, data: &[u8], deadline: Instant) -> Result<()> {
+        let msg = Outgoing::new(data, Some(deadline), 0);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, плюс флаги (биты `MSG_USER_FLAGS_MASK`, иначе
+    /// `ShmError::InvalidConfig`), которые получатель видит в
+    /// `AutoHandler::on_message_with_flags`. Флаги идут в заголовке кадра
+    /// и с `AutoOptions::encryption` не шифруются.
+    pub fn send_with_flags(&self, data: &[u8], flags: u16) -> Result<()> {
+        let msg = Outgoing::new(data, None, flags);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, плюс [`SendTicket`]: по нему видно, когда worker записал
+    /// сообщение в кольцо или отбросил его насовсем.
+    pub fn send_with_ticket(&self, data: &[u8]) -> Result<SendTicket> {
+        let (ticket, completion) = SendTicket::new();
+        let msg = Outgoing {
+            completion: Some(completion),
+            ..Outgoing::new(data, None, 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)?;
+        Ok(ticket)
+    }
+
+    /// Как `send`, но сообщение встаёт в очередь отправки перед всеми
+    /// обычными (за ранее отправленными срочными): короткие управляющие
+    /// сообщения не ждут сотни объёмных, пока кольцо забито. Лимиты очереди
+    /// те же, но вытесняются срочные последними.
+    pub fn send_urgent(&self, data: &[u8]) -> Result<()> {
+        let msg = Outgoing {
+            urgent: true,
+            ..Outgoing::new(data, None, 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Отправка с ожиданием: `Ok` -- сообщение записано в исходящее кольцо
+    /// не позже чем через `timeout`. Иначе -- `ShmError::Timeout`, и
+    /// сообщение, ещё лежащее в очереди, отбрасывается по дедлайну, как у
+    /// `send_with_deadline` (`on_expired`). Прочие ошибки -- как у
+    /// [`SendTicket`].
+    pub fn send_timeout(&self, data: &[u8], timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let (ticket, completion) = SendTicket::new();
+        let msg = Outgoing {
+            completion: Some(completion),
+            ..Outgoing::new(data, Some(deadline), 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)?;
+        ticket.wait(Some(deadline.saturating_duration_since(Instant::now())))
     }
 
     pub fn stop(&self) {
         let _ = self.cmd_tx.send(WorkerCommand::Shutdown);
     }
 
+    /// Worker остановлен: `stop()`, отмена токена из опций или паника
+    /// (о ней сообщает `on_error` с `ShmError::WorkerPanicked`).
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
     pub fn stats(&self) -> AutoStatsSnapshot {
         self.stats.snapshot()
     }
+
+    /// Состояние связи для liveness-пробы (см. `xshm::health`).
+    pub fn health(&self) -> ChannelHealth {
+        self.stats.health(&self.stop)
+    }
+
+    /// Приостанавливает приём: worker перестаёт читать входящее кольцо и
+    /// звать `on_message*`, соединение и отправка продолжаются. Пир,
+    /// заполнив кольцо, упирается в `QueueFull` (или ждёт кредитов) --
+    /// backpressure на время обслуживания без разрыва связи.
+    pub fn pause(&self) {
+        self.stats.paused.store(true, Ordering::Release);
+    }
+
+    /// Возобновляет приём после `pause()`: накопленное в кольце читается
+    /// с ближайшего прохода worker'а (не позже `poll_timeout`).
+    pub fn resume(&self) {
+        self.stats.paused.store(false, Ordering::Release);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.stats.paused.load(Ordering::Acquire)
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.stats.connection.get(&self.stop)
+    }
+
+    /// Ждёт соединения с пиром до `timeout` (`None` -- без ограничения):
+    /// не дождался -- `ShmError::Timeout`, worker остановлен --
+    /// `ShmError::NotReady`.
+    pub fn wait_connected(&self, timeout: Option<Duration>) -> Result<()> {
+        self.stats.connection.wait_connected(timeout, &self.stop)
+    }
+
+    /// Счётчик итераций worker'а: если он не растёт, worker завис
+    /// (например, в callback'е handler'а).
+    pub(crate) fn heartbeat(&self) -> u64 {
+        self.stats.heartbeat.load(Ordering::Relaxed)
+    }
+
+    /// Просит worker разорвать текущее соединение (пир умер, не выставив
+    /// disconnect).
+    pub(crate) fn disconnect_peer(&self) {
+        let _ = self.cmd_tx.send(WorkerCommand::Disconnect);
+    }
+
+    /// Неблокирующая остановка: worker выйдет, как только вернётся в цикл.
+    /// Drop после `is_worker_finished() == true` уже не ждёт.
+    pub(crate) fn retire(&self) {
+        self.stop.cancel();
+        let _ = self.cmd_tx.send(WorkerCommand::Shutdown);
+    }
+
+    pub(crate) fn is_worker_finished(&self) -> bool {
+        self.join
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+    }
 }
 
+/// С `AutoOptions::flush_on_drop` сначала ждёт (не дольше заданного), пока
+/// очередь отправки уйдёт пиру, затем останавливает worker и джойнит его.
 impl Drop for AutoServer {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::Release);
+        flush_before_drop(&self.cmd_tx, &self.join, &self.stop, self.flush_on_drop);
+        self.stop.cancel();
         let _ = self.cmd_tx.send(WorkerCommand::Shutdown);
         if let Some(handle) = self.join.lock().unwrap().take() {
             join_unless_self(handle);
@@ -231,17 +1248,55 @@ impl Drop for AutoServer {
     }
 }
 
+/// Канал и сторона worker'а: с ними его ошибки уходят в
+/// `AutoHandler::on_error_with_context`, от стороны зависят направления
+/// отправки и приёма.
+#[derive(Clone, Copy)]
+struct Origin<'a> {
+    channel: &'a str,
+    role: ChannelRole,
+}
+
+impl<'a> Origin<'a> {
+    fn new(channel: &'a str, role: ChannelRole) -> Self {
+        Self { channel, role }
+    }
+
+    /// Направление отправки этой стороны.
+    fn outgoing(self) -> ChannelKind {
+        match self.role {
+            ChannelRole::Server => ChannelKind::ServerToClient,
+            ChannelRole::Client => ChannelKind::ClientToServer,
+        }
+    }
+
+    /// Направление приёма этой стороны.
+    fn incoming(self) -> ChannelKind {
+        match self.role {
+            ChannelRole::Server => ChannelKind::ClientToServer,
+            ChannelRole::Client => ChannelKind::ServerToClient,
+        }
+    }
+
+    fn report(self, handler: &Arc<dyn AutoHandler>, operation: &'static str, err: ShmError) {
+        if let Err(error) = Err::<(), _>(err).context(self.channel, self.role, operation) {
+            handler.on_error_with_context(error);
+        }
+    }
+}
+
 fn server_worker(
-    _name: &str,
+    name: &str,
     server: &mut SharedServer,
     handler: Arc<dyn AutoHandler>,
     options: AutoOptions,
     cmd_rx: Receiver<WorkerCommand>,
     stats: Arc<AutoStats>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
 ) {
+    let origin = Origin::new(name, ChannelRole::Server);
     let send_queue = SendQueue::new();
-    let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
+    let mut buffers = ReceiveBuffers::new(&options);
     // Anonymous режим не поддерживается в auto-mode
     let server_events = server
         .events()
@@ -250,54 +1305,94 @@ fn server_worker(
         server_events.disconnect.raw_handle(),
         server_events.c2s.data.raw_handle(),
         server_events.s2c.space.raw_handle(),
+        stop.raw_handle(),
     ];
 
-    let mut connected = false;
+    server.set_checksum(options.checksum);
+    server.set_timestamps(options.latency);
+    server.set_default_ttl(options.ttl);
+    server.set_latency_histogram(options.latency.then(|| stats.latency.clone()));
+    if let Err(err) = server.set_credit_window(options.credit_window) {
+        origin.report(&handler, "set_credit_window", err);
+    }
+    if let Err(err) = server.set_space_threshold(options.space_threshold) {
+        origin.report(&handler, "set_space_threshold", err);
+    }
+    let mut connected = false;
+    let mut pipeline = Pipeline::default();
+    let mut flush = None;
+    let mut ticker = Ticker::new(&options);
 
-    while running.load(Ordering::Acquire) {
+    while !stop.is_cancelled() {
+        stats.heartbeat.fetch_add(1, Ordering::Relaxed);
+        ticker.poll(&handler);
         if !connected {
-            match server.wait_for_client(Some(options.poll_timeout)) {
-                Ok(_) => {
-                    connected = true;
-                    handler.on_connect();
-                }
-                Err(ShmError::Timeout) => {
-                    drain_commands(&send_queue, &cmd_rx, &options, &running);
+            let wait = ticker.wait(options.poll_timeout);
+            match server.wait_for_client_or_cancel(Some(wait), &stop) {
+                Ok(_) => match Pipeline::connect(server, &options, ChannelKind::ServerToClient) {
+                    Ok(p) => {
+                        pipeline = p;
+                        connected = true;
+                        stats.connects.fetch_add(1, Ordering::Relaxed);
+                        handler.on_connect_with_epoch(server.connection_epoch().unwrap_or(0));
+                    }
+                    Err(err) => {
+                        origin.report(&handler, "handshake", err);
+                        server.mark_disconnected();
+                        continue;
+                    }
+                },
+                Err(ShmError::Timeout | ShmError::Cancelled) => {
+                    drain_commands(&send_queue, &cmd_rx, &options, &stats, &stop, &mut flush);
+                    stats.publish_depths(&send_queue, (0, 0));
+                    settle_flush(&mut flush, &send_queue, 0);
                     continue;
                 }
                 Err(err) => {
-                    handler.on_error(err.clone());
-                    drain_commands(&send_queue, &cmd_rx, &options, &running);
+                    origin.report(&handler, "wait_for_client", err.clone());
+                    drain_commands(&send_queue, &cmd_rx, &options, &stats, &stop, &mut flush);
                     continue;
                 }
             }
         }
 
-        drain_commands(&send_queue, &cmd_rx, &options, &running);
+        let disconnect = drain_commands(&send_queue, &cmd_rx, &options, &stats, &stop, &mut flush);
 
         if !connected {
             continue;
         }
+        if disconnect {
+            stats.disconnects.fetch_add(1, Ordering::Relaxed);
+            handler.on_disconnect_with_reason(server.peer_disconnect_reason());
+            server.mark_disconnected();
+            connected = false;
+            continue;
+        }
 
-        process_send_queue(
-            server,
-            &send_queue,
-            &handler,
-            &stats,
-            ChannelKind::ServerToClient,
-        );
+        process_send_queue(server, &mut pipeline, &send_queue, &handler, &stats, origin);
 
-        let outcome = process_receive_queue(
-            server,
-            &handler,
-            &stats,
-            &mut buffer,
-            options.recv_batch,
-            ChannelKind::ClientToServer,
-        );
+        let outcome = if stats.paused.load(Ordering::Acquire) {
+            ReceiveOutcome::default()
+        } else {
+            process_receive_queue(
+                server,
+                &mut pipeline,
+                &handler,
+                &stats,
+                &mut buffers,
+                &options,
+                origin,
+            )
+        };
         if outcome.fatal {
-            handler.on_disconnect();
-            server.mark_disconnected();
+            stats.disconnects.fetch_add(1, Ordering::Relaxed);
+            if outcome.protocol_error {
+                handler.on_disconnect_with_reason(DisconnectReason::ProtocolError);
+                server.disconnect(DisconnectReason::ProtocolError);
+            } else {
+                handler.on_disconnect_with_reason(server.peer_disconnect_reason());
+                server.mark_disconnected();
+            }
             connected = false;
             continue;
         }
@@ -305,10 +1400,14 @@ fn server_worker(
             // Ещё есть данные — не блокируемся, сразу следующий проход.
             continue;
         }
+        let rings = server.ring_depths();
+        stats.publish_depths(&send_queue, rings);
+        settle_flush(&mut flush, &send_queue, rings.1);
 
-        match win::wait_any(&handles, Some(options.poll_timeout)) {
+        match platform::wait_any(&handles, Some(ticker.wait(options.poll_timeout))) {
             Ok(Some(0)) => {
-                handler.on_disconnect();
+                stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                handler.on_disconnect_with_reason(server.peer_disconnect_reason());
                 server.mark_disconnected();
                 connected = false;
             }
@@ -319,10 +1418,19 @@ fn server_worker(
                 handler.on_space_available(ChannelKind::ServerToClient);
             }
             Ok(Some(_)) => {}
-            Ok(None) => {}
+            Ok(None) => {
+                // Клиент мог упасть, не выставив disconnect.
+                if options.check_peer_process && !server.is_peer_process_alive() {
+                    stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                    handler.on_disconnect_with_reason(DisconnectReason::Unknown);
+                    server.mark_disconnected();
+                    connected = false;
+                }
+            }
             Err(err) => {
-                handler.on_error(err.clone());
-                handler.on_disconnect();
+                origin.report(&handler, "wait", err.clone());
+                stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                handler.on_disconnect_with_reason(DisconnectReason::Unknown);
                 server.mark_disconnected();
                 connected = false;
             }
@@ -334,7 +1442,11 @@ pub struct AutoClient {
     cmd_tx: Sender<WorkerCommand>,
     join: Mutex<Option<JoinHandle<()>>>,
     stats: Arc<AutoStats>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
+    flush_on_drop: Option<Duration>,
+    gate: Arc<SendGate>,
+    /// Запись в `metrics`; у выделенных каналов `dispatch` её нет.
+    _metrics: Option<metrics::Registration>,
 }
 
 impl AutoClient {
@@ -343,24 +1455,43 @@ impl AutoClient {
         handler: Arc<dyn AutoHandler>,
         options: AutoOptions,
     ) -> Result<Self> {
+        let mut client = Self::connect_unlisted(name, handler, options)?;
+        client._metrics = Some(metrics::register(
+            name,
+            ChannelRole::Client,
+            weak_source(&client.stats),
+        ));
+        Ok(client)
+    }
+
+    /// Как `connect`, но без записи в `metrics`: счётчики выделенного
+    /// канала выгружает `DispatchClient` под своим именем.
+    pub(crate) fn connect_unlisted(
+        name: &str,
+        handler: Arc<dyn AutoHandler>,
+        options: AutoOptions,
+    ) -> Result<Self> {
+        options.validate()?;
+        let flush_on_drop = options.flush_on_drop;
+        let gate = SendGate::new(&options);
         let (tx, rx) = mpsc::channel();
         let stats = Arc::new(AutoStats::default());
-        let running = Arc::new(AtomicBool::new(true));
+        let stop = CancellationToken::for_worker(options.cancel.as_ref())?;
         let join_stats = stats.clone();
-        let join_running = running.clone();
-        let handler_clone = handler.clone();
+        let join_stop = stop.clone();
+        let handler_clone = recording(handler, &stats);
         let name_str = name.to_owned();
 
         let join = thread::Builder::new()
             .name(format!("xshm-auto-client-{}", name))
             .spawn(move || {
-                client_worker(
-                    &name_str,
-                    handler_clone,
-                    options,
-                    rx,
-                    join_stats,
-                    join_running,
+                let guard = join_stop.clone();
+                let report = handler_clone.clone();
+                guard.run_worker(
+                    || client_worker(&name_str, handler_clone, options, rx, join_stats, join_stop),
+                    |err| {
+                        Origin::new(&name_str, ChannelRole::Client).report(&report, "worker", err)
+                    },
                 );
             })
             .map_err(|err| map_spawn_error(err, "spawn client worker"))?;
@@ -369,32 +1500,135 @@ impl AutoClient {
             cmd_tx: tx,
             join: Mutex::new(Some(join)),
             stats,
-            running,
+            stop,
+            flush_on_drop,
+            gate,
+            _metrics: None,
         })
     }
 
     pub fn send(&self, data: &[u8]) -> Result<()> {
-        if !self.running.load(Ordering::Acquire) {
-            return Err(ShmError::NotReady);
-        }
-        let msg = data.to_vec();
-        self.cmd_tx
-            .send(WorkerCommand::Send(msg))
-            .map_err(|_| ShmError::NotReady)
+        let msg = Outgoing::new(data, None, 0);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, но сообщение, не ушедшее в кольцо до `deadline`
+    /// (например, пролежавшее в очереди весь reconnect), отбрасывается и
+    /// передаётся в `AutoHandler::on_expired`.
+    pub fn send_with_deadline(&self, data: &[u8], deadline: Instant) -> Result<()> {
+        let msg = Outgoing::new(data, Some(deadline), 0);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, плюс флаги (биты `MSG_USER_FLAGS_MASK`, иначе
+    /// `ShmError::InvalidConfig`), которые получатель видит в
+    /// `AutoHandler::on_message_with_flags`. Флаги идут в заголовке кадра
+    /// и с `AutoOptions::encryption` не шифруются.
+    pub fn send_with_flags(&self, data: &[u8], flags: u16) -> Result<()> {
+        let msg = Outgoing::new(data, None, flags);
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Как `send`, плюс [`SendTicket`]: по нему видно, когда worker записал
+    /// сообщение в кольцо или отбросил его насовсем.
+    pub fn send_with_ticket(&self, data: &[u8]) -> Result<SendTicket> {
+        let (ticket, completion) = SendTicket::new();
+        let msg = Outgoing {
+            completion: Some(completion),
+            ..Outgoing::new(data, None, 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)?;
+        Ok(ticket)
+    }
+
+    /// Как `send`, но сообщение встаёт в очередь отправки перед всеми
+    /// обычными (за ранее отправленными срочными): короткие управляющие
+    /// сообщения не ждут сотни объёмных, пока кольцо забито. Лимиты очереди
+    /// те же, но вытесняются срочные последними.
+    pub fn send_urgent(&self, data: &[u8]) -> Result<()> {
+        let msg = Outgoing {
+            urgent: true,
+            ..Outgoing::new(data, None, 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)
+    }
+
+    /// Отправка с ожиданием: `Ok` -- сообщение записано в исходящее кольцо
+    /// не позже чем через `timeout`. Иначе -- `ShmError::Timeout`, и
+    /// сообщение, ещё лежащее в очереди, отбрасывается по дедлайну, как у
+    /// `send_with_deadline` (`on_expired`). Прочие ошибки -- как у
+    /// [`SendTicket`].
+    pub fn send_timeout(&self, data: &[u8], timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let (ticket, completion) = SendTicket::new();
+        let msg = Outgoing {
+            completion: Some(completion),
+            ..Outgoing::new(data, Some(deadline), 0)
+        };
+        enqueue(&self.cmd_tx, &self.stop, &self.gate, msg)?;
+        ticket.wait(Some(deadline.saturating_duration_since(Instant::now())))
     }
 
     pub fn stop(&self) {
         let _ = self.cmd_tx.send(WorkerCommand::Shutdown);
     }
 
+    /// Worker остановлен: `stop()`, отмена токена из опций или паника
+    /// (о ней сообщает `on_error` с `ShmError::WorkerPanicked`).
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
     pub fn stats(&self) -> AutoStatsSnapshot {
         self.stats.snapshot()
     }
+
+    /// Состояние связи для liveness-пробы (см. `xshm::health`).
+    pub fn health(&self) -> ChannelHealth {
+        self.stats.health(&self.stop)
+    }
+
+    /// Счётчики для записи в `metrics` под чужим именем (`DispatchClient`).
+    pub(crate) fn metrics_source(&self) -> Weak<dyn MetricsSource> {
+        weak_source(&self.stats)
+    }
+
+    /// Приостанавливает приём: worker перестаёт читать входящее кольцо и
+    /// звать `on_message*`, соединение и отправка продолжаются. Пир,
+    /// заполнив кольцо, упирается в `QueueFull` (или ждёт кредитов) --
+    /// backpressure на время обслуживания без разрыва связи.
+    pub fn pause(&self) {
+        self.stats.paused.store(true, Ordering::Release);
+    }
+
+    /// Возобновляет приём после `pause()`: накопленное в кольце читается
+    /// с ближайшего прохода worker'а (не позже `poll_timeout`).
+    pub fn resume(&self) {
+        self.stats.paused.store(false, Ordering::Release);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.stats.paused.load(Ordering::Acquire)
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.stats.connection.get(&self.stop)
+    }
+
+    /// Ждёт соединения с пиром до `timeout` (`None` -- без ограничения):
+    /// не дождался -- `ShmError::Timeout`, worker остановлен --
+    /// `ShmError::NotReady`.
+    pub fn wait_connected(&self, timeout: Option<Duration>) -> Result<()> {
+        self.stats.connection.wait_connected(timeout, &self.stop)
+    }
 }
 
+/// С `AutoOptions::flush_on_drop` сначала ждёт (не дольше заданного), пока
+/// очередь отправки уйдёт пиру, затем останавливает worker и джойнит его.
 impl Drop for AutoClient {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::Release);
+        flush_before_drop(&self.cmd_tx, &self.join, &self.stop, self.flush_on_drop);
+        self.stop.cancel();
         let _ = self.cmd_tx.send(WorkerCommand::Shutdown);
         if let Some(handle) = self.join.lock().unwrap().take() {
             join_unless_self(handle);
@@ -408,122 +1642,409 @@ fn client_worker(
     options: AutoOptions,
     cmd_rx: Receiver<WorkerCommand>,
     stats: Arc<AutoStats>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
 ) {
+    let origin = Origin::new(name, ChannelRole::Client);
     let send_queue = SendQueue::new();
-    let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
-
-    while running.load(Ordering::Acquire) {
-        let mut client = match SharedClient::connect(name, options.connect_timeout) {
+    let mut buffers = ReceiveBuffers::new(&options);
+    let mut flush = None;
+    let mut backoff = Backoff::default();
+    let mut ticker = Ticker::new(&options);
+
+    while !stop.is_cancelled() {
+        ticker.poll(&handler);
+        let connected = SharedClient::connect_in_with_features(
+            name,
+            options.namespace,
+            options.connect_timeout,
+            SUPPORTED_FEATURES,
+        );
+        let mut client = match connected {
             Ok(client) => client,
             Err(err) => {
-                handler.on_error(err.clone());
-                if !wait_delay(&running, options.reconnect_delay) {
+                origin.report(&handler, "connect", err.clone());
+                // Без сервера дописывать некуда: пустая очередь -- уже flush.
+                drain_commands(&send_queue, &cmd_rx, &options, &stats, &stop, &mut flush);
+                settle_flush(&mut flush, &send_queue, 0);
+                if !backoff.sleep(Some(&err), &options, &handler, &stop) {
                     break;
                 }
                 continue;
             }
         };
 
-        handler.on_connect();
-        // SharedClient всегда использует named events (не anonymous)
-        let client_events = client.events();
+        client.set_checksum(options.checksum);
+        client.set_timestamps(options.latency);
+        client.set_default_ttl(options.ttl);
+        client.set_latency_histogram(options.latency.then(|| stats.latency.clone()));
+        if let Err(err) = client.set_credit_window(options.credit_window) {
+            origin.report(&handler, "set_credit_window", err);
+        }
+        if let Err(err) = client.set_space_threshold(options.space_threshold) {
+            origin.report(&handler, "set_space_threshold", err);
+        }
+        let mut pipeline = match Pipeline::connect(&client, &options, ChannelKind::ClientToServer) {
+            Ok(p) => p,
+            Err(err) => {
+                origin.report(&handler, "handshake", err.clone());
+                client.mark_disconnected();
+                if !backoff.sleep(Some(&err), &options, &handler, &stop) {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        stats.connects.fetch_add(1, Ordering::Relaxed);
+        backoff = Backoff::default();
+
+        handler.on_connect_with_epoch(client.connection_epoch().unwrap_or(0));
+        // Клиент, подключённый по имени, всегда с named events
+        let client_events = client.events().expect("named client always has events");
         let handles = [
             client_events.disconnect.raw_handle(),
             client_events.s2c.data.raw_handle(),
             client_events.c2s.space.raw_handle(),
+            stop.raw_handle(),
         ];
 
         loop {
-            if !running.load(Ordering::Acquire) {
+            if stop.is_cancelled() {
                 break;
             }
+            ticker.poll(&handler);
 
-            drain_commands(&send_queue, &cmd_rx, &options, &running);
+            drain_commands(&send_queue, &cmd_rx, &options, &stats, &stop, &mut flush);
             process_send_queue(
                 &client,
+                &mut pipeline,
                 &send_queue,
                 &handler,
                 &stats,
-                ChannelKind::ClientToServer,
-            );
-            let outcome = process_receive_queue(
-                &client,
-                &handler,
-                &stats,
-                &mut buffer,
-                options.recv_batch,
-                ChannelKind::ServerToClient,
+                origin,
             );
+            let outcome = if stats.paused.load(Ordering::Acquire) {
+                ReceiveOutcome::default()
+            } else {
+                process_receive_queue(
+                    &client,
+                    &mut pipeline,
+                    &handler,
+                    &stats,
+                    &mut buffers,
+                    &options,
+                    origin,
+                )
+            };
             if outcome.fatal {
-                handler.on_disconnect();
-                client.mark_disconnected();
+                stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                if outcome.protocol_error {
+                    handler.on_disconnect_with_reason(DisconnectReason::ProtocolError);
+                    client.disconnect(DisconnectReason::ProtocolError);
+                } else {
+                    handler.on_disconnect_with_reason(client.peer_disconnect_reason());
+                    client.mark_disconnected();
+                }
                 break;
             }
             if outcome.more_pending {
                 continue;
             }
+            let rings = client.ring_depths();
+            stats.publish_depths(&send_queue, rings);
+            settle_flush(&mut flush, &send_queue, rings.1);
 
-            match win::wait_any(&handles, Some(options.poll_timeout)) {
+            match platform::wait_any(&handles, Some(ticker.wait(options.poll_timeout))) {
                 Ok(Some(0)) => {
-                    handler.on_disconnect();
+                    stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                    handler.on_disconnect_with_reason(client.peer_disconnect_reason());
                     client.mark_disconnected();
                     break;
                 }
                 Ok(Some(1)) => {}
                 Ok(Some(2)) => handler.on_space_available(ChannelKind::ClientToServer),
                 Ok(Some(_)) => {}
-                Ok(None) => {}
+                Ok(None) => {
+                    // Сервер мог упасть, не выставив disconnect.
+                    if options.check_peer_process && !client.is_peer_process_alive() {
+                        stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                        handler.on_disconnect_with_reason(DisconnectReason::Unknown);
+                        client.mark_disconnected();
+                        break;
+                    }
+                }
                 Err(err) => {
-                    handler.on_error(err.clone());
-                    handler.on_disconnect();
+                    origin.report(&handler, "wait", err.clone());
+                    stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                    handler.on_disconnect_with_reason(DisconnectReason::Unknown);
                     client.mark_disconnected();
                     break;
                 }
             }
         }
 
-        if !wait_delay(&running, options.reconnect_delay) {
+        if !backoff.sleep(None, &options, &handler, &stop) {
             break;
         }
     }
 }
 
+/// Счёт неудачных попыток подключения подряд у `client_worker`.
+#[derive(Default)]
+struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Пауза перед следующей попыткой; `failed` -- ошибка неудавшейся
+    /// попытки (`None` -- соединение было и потеряно). `false` -- worker
+    /// остановлен.
+    fn sleep(
+        &mut self,
+        failed: Option<&ShmError>,
+        options: &AutoOptions,
+        handler: &Arc<dyn AutoHandler>,
+        stop: &CancellationToken,
+    ) -> bool {
+        self.attempt = self.attempt.saturating_add(1);
+        if let Some(err) = failed {
+            handler.on_reconnect(self.attempt, err);
+        }
+        let delay = options
+            .backoff
+            .as_ref()
+            .map_or(options.reconnect_delay, |backoff| {
+                backoff.delay(self.attempt)
+            });
+        handler.on_reconnect_scheduled(delay, self.attempt);
+        stop.sleep(delay)
+    }
+}
+
+/// Отсчёт `AutoHandler::on_tick` (`AutoOptions::tick_interval`).
+struct Ticker {
+    interval: Option<Duration>,
+    last: Instant,
+}
+
+impl Ticker {
+    fn new(options: &AutoOptions) -> Self {
+        Self {
+            interval: options.tick_interval,
+            last: Instant::now(),
+        }
+    }
+
+    /// Зовёт `on_tick`, если интервал прошёл.
+    fn poll(&mut self, handler: &Arc<dyn AutoHandler>) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let elapsed = self.last.elapsed();
+        if elapsed >= interval {
+            self.last = Instant::now();
+            handler.on_tick(elapsed);
+        }
+    }
+
+    /// Ожидание событий: `poll_timeout`, но не дольше следующего тика.
+    fn wait(&self, poll_timeout: Duration) -> Duration {
+        self.interval.map_or(poll_timeout, |interval| {
+            interval
+                .saturating_sub(self.last.elapsed())
+                .min(poll_timeout)
+        })
+    }
+}
+
+fn enqueue(
+    tx: &Sender<WorkerCommand>,
+    stop: &CancellationToken,
+    gate: &Arc<SendGate>,
+    mut msg: Outgoing,
+) -> Result<()> {
+    if stop.is_cancelled() {
+        return Err(ShmError::NotReady);
+    }
+    ensure_config(
+        msg.flags & !MSG_USER_FLAGS_MASK == 0,
+        "message flags must fit MSG_USER_FLAGS_MASK",
+    )?;
+    gate.check_size(msg.data.len())?;
+    msg._permit = gate.acquire(msg.data.len(), msg.deadline, stop)?;
+    tx.send(WorkerCommand::Send(msg))
+        .map_err(|_| ShmError::NotReady)
+}
+
+/// Возвращает `true`, если среди команд был `Disconnect`. `Flush`
+/// запоминается в `flush` до ответа из `settle_flush`.
 fn drain_commands(
     queue: &SendQueue,
     rx: &Receiver<WorkerCommand>,
     options: &AutoOptions,
-    running: &Arc<AtomicBool>,
-) {
+    stats: &AutoStats,
+    stop: &CancellationToken,
+    flush: &mut Option<Sender<()>>,
+) -> bool {
+    let mut disconnect = false;
     while let Ok(cmd) = rx.try_recv() {
         match cmd {
             WorkerCommand::Send(msg) => {
+                // drop oldest (overwrite semantics): сначала лимит по числу,
+                // затем бюджет в байтах. Срочные вытесняются последними.
                 if queue.len() >= options.max_send_queue {
-                    // drop oldest (overwrite semantics)
-                    let _ = queue.pop();
+                    if let Some(mut dropped) = queue.evict() {
+                        dropped.complete(Err(ShmError::QueueFull));
+                        stats.send_queue_drops.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                if let Some(budget) = options.max_send_queue_bytes {
+                    while queue.bytes() + msg.data.len() > budget {
+                        let Some(mut dropped) = queue.evict() else {
+                            break;
+                        };
+                        dropped.complete(Err(ShmError::QueueFull));
+                        stats.send_queue_byte_drops.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                if msg.urgent {
+                    queue.push_urgent(msg);
+                } else {
+                    queue.push(msg);
                 }
-                queue.push(msg);
             }
             WorkerCommand::Shutdown => {
-                running.store(false, Ordering::Release);
+                stop.cancel();
             }
+            WorkerCommand::Disconnect => disconnect = true,
+            WorkerCommand::Flush(done) => *flush = Some(done),
         }
     }
+    disconnect
+}
+
+/// Преобразование payload'ов одного соединения между очередью/handler'ом и
+/// кольцом. Без feature `encryption` (или с `AutoOptions::encryption ==
+/// None`) -- прозрачная передача. Состояние живёт ровно одно соединение:
+/// после reconnect создаётся заново (новые эфемерные ключи).
+#[derive(Default)]
+struct Pipeline {
+    #[cfg(feature = "encryption")]
+    cipher: Option<ChannelCipher>,
+    /// Счётчик просроченных входящих кольца, уже учтённый в статистике.
+    expired_seen: u32,
+    /// Недособранное сообщение из кусков (`MSG_FLAG_CHUNK`).
+    chunks: Reassembly,
+}
+
+impl Pipeline {
+    /// Начинает новое соединение. Кадр обмена ключами (если шифрование
+    /// включено) пишется в кольцо сразу, раньше любых сообщений из очереди.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn connect<E: SendEndpoint>(
+        endpoint: &E,
+        options: &AutoOptions,
+        outgoing: ChannelKind,
+    ) -> Result<Self> {
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = &options.encryption {
+            let role = match outgoing {
+                ChannelKind::ServerToClient => Role::Server,
+                ChannelKind::ClientToServer => Role::Client,
+            };
+            let (cipher, key_frame) = ChannelCipher::start(role, encryption);
+            endpoint.write(&key_frame, 0)?;
+            return Ok(Self {
+                cipher: Some(cipher),
+                ..Self::default()
+            });
+        }
+        Ok(Self::default())
+    }
+
+    /// Можно ли отправлять пользовательские сообщения (ключ пира получен).
+    fn ready(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            return cipher.is_ready();
+        }
+        true
+    }
+
+    fn outgoing<'a>(&mut self, msg: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &mut self.cipher {
+            return cipher.seal(msg).map(Cow::Owned);
+        }
+        Ok(Cow::Borrowed(msg))
+    }
+
+    /// `Ok(None)` -- служебный кадр, поглощён без передачи handler'у.
+    fn incoming<'a>(&mut self, frame: &'a [u8]) -> Result<Option<Cow<'a, [u8]>>> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &mut self.cipher {
+            return cipher.open(frame).map(|plain| plain.map(Cow::Owned));
+        }
+        Ok(Some(Cow::Borrowed(frame)))
+    }
 }
 
 fn process_send_queue<E>(
     endpoint: &E,
+    pipeline: &mut Pipeline,
     queue: &SendQueue,
     handler: &Arc<dyn AutoHandler>,
     stats: &Arc<AutoStats>,
-    direction: ChannelKind,
+    origin: Origin,
 ) where
     E: SendEndpoint,
 {
-    while let Some(msg) = queue.pop() {
-        match endpoint.write(&msg) {
+    if !pipeline.ready() {
+        // Обмен ключами ещё идёт -- сообщения ждут в очереди.
+        return;
+    }
+    let direction = origin.outgoing();
+    let now = Instant::now();
+    while let Some(mut msg) = queue.pop() {
+        if msg.deadline.is_some_and(|deadline| deadline <= now) {
+            stats.send_expired.fetch_add(1, Ordering::Relaxed);
+            handler.on_expired(direction, &msg.data);
+            msg.complete(Err(ShmError::Timeout));
+            continue;
+        }
+        if !msg.traced {
+            handler.on_before_send(direction, &msg.data);
+            msg.traced = true;
+        }
+        let frame = match msg.sealed.take() {
+            Some(frame) => Cow::Owned(frame),
+            None => match pipeline.outgoing(&msg.data) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    // Сообщение, которое нельзя зашифровать, не отправится и
+                    // при повторе -- отбрасываем, чтобы не блокировать очередь.
+                    msg.complete(Err(err.clone()));
+                    origin.report(handler, "encrypt", err);
+                    continue;
+                }
+            },
+        };
+        // Пир без `FEATURE_CHUNKING` получит `MessageTooLarge`, как раньше.
+        let written = if frame.len() > MAX_MESSAGE_SIZE && endpoint.chunking() {
+            chunking::write_chunks(&frame, &mut msg.sent, |chunk| {
+                endpoint.write_chunk(chunk, msg.flags)
+            })
+        } else {
+            endpoint.write(&frame, msg.flags)
+        };
+        match written {
             Ok(outcome) => {
                 stats.sent_messages.fetch_add(1, Ordering::Relaxed);
+                stats
+                    .sent_bytes
+                    .fetch_add(msg.data.len() as u64, Ordering::Relaxed);
+                stats.activity.touch_tx();
+                msg.complete(Ok(()));
                 if outcome.overwritten > 0 {
                     stats
                         .send_overflows
@@ -532,108 +2053,269 @@ fn process_send_queue<E>(
                 }
             }
             Err(ShmError::QueueFull) => {
+                if msg.sent > 0 {
+                    msg.sealed = Some(frame.into_owned());
+                }
                 queue.push_front(msg);
                 break;
             }
-            Err(err) => {
-                handler.on_error(err.clone());
+            Err(err) if err.is_retryable() || err.is_disconnect() => {
+                // Куски уже записанного начала получатель отбросит:
+                // повтор начинается с нулевого смещения.
+                msg.sent = 0;
+                origin.report(handler, "send", err);
                 queue.push_front(msg);
                 break;
             }
+            Err(err) => {
+                // С этим сообщением повтор не поможет (например, слишком
+                // большое) -- отбрасываем, чтобы не заклинить очередь.
+                msg.complete(Err(err.clone()));
+                origin.report(handler, "send", err);
+            }
         }
     }
 }
 
 /// Результат обработки приёмной очереди за один проход.
+#[derive(Default)]
 struct ReceiveOutcome {
     /// Фатальная ошибка — соединение надо сбросить.
     fatal: bool,
+    /// Причина -- нарушение протокола пиром (ему сообщается
+    /// `DisconnectReason::ProtocolError`).
+    protocol_error: bool,
     /// Батч упёрся в лимит, в кольце вероятно ещё есть данные — не спать.
     more_pending: bool,
 }
 
-/// Обрабатывает до `batch` сообщений за вызов.
+/// Буфер кадра worker'а и пул сообщений для `on_message_owned`; живут
+/// дольше соединения.
+struct ReceiveBuffers {
+    frame: Vec<u8>,
+    pool: Option<Arc<BufferPool>>,
+}
+
+impl ReceiveBuffers {
+    fn new(options: &AutoOptions) -> Self {
+        Self {
+            frame: Vec::with_capacity(MAX_MESSAGE_SIZE),
+            pool: (options.message_pool > 0).then(|| BufferPool::new(options.message_pool)),
+        }
+    }
+}
+
+/// Обрабатывает до `recv_batch` сообщений за вызов и отсеивает их `filter`-ом.
 fn process_receive_queue<R>(
     endpoint: &R,
+    pipeline: &mut Pipeline,
     handler: &Arc<dyn AutoHandler>,
     stats: &Arc<AutoStats>,
-    buffer: &mut Vec<u8>,
-    batch: usize,
-    direction: ChannelKind,
+    buffers: &mut ReceiveBuffers,
+    options: &AutoOptions,
+    origin: Origin,
 ) -> ReceiveOutcome
 where
     R: ReceiveEndpoint,
 {
+    let direction = origin.incoming();
     let mut drained = false;
-    for _ in 0..batch.max(1) {
+    let buffer = &mut buffers.frame;
+    for _ in 0..options.recv_batch.max(1) {
         match endpoint.read(buffer) {
-            Ok(len) => {
-                stats.received_messages.fetch_add(1, Ordering::Relaxed);
-                handler.on_message(direction, &buffer[..len]);
+            Ok((len, flags)) => {
+                // Забираем сразу: метка относится только к этому кадру.
+                let timestamp = endpoint.take_timestamp();
+                let assembled;
+                let frame = if flags & MSG_FLAG_CHUNK != 0 {
+                    match pipeline.chunks.push(&buffer[..len]) {
+                        Ok(Some(message)) => {
+                            assembled = message;
+                            &assembled[..]
+                        }
+                        Ok(None) => continue,
+                        Err(err) => {
+                            // Заголовок куска, которого пир написать не мог.
+                            origin.report(handler, "reassemble", err);
+                            return ReceiveOutcome {
+                                fatal: true,
+                                protocol_error: true,
+                                more_pending: false,
+                            };
+                        }
+                    }
+                } else {
+                    &buffer[..len]
+                };
+                match pipeline.incoming(frame) {
+                    Ok(Some(payload)) => {
+                        stats.activity.touch_rx();
+                        if options
+                            .filter
+                            .as_ref()
+                            .is_some_and(|accept| !accept(&payload))
+                        {
+                            stats.filtered_messages.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        stats.received_messages.fetch_add(1, Ordering::Relaxed);
+                        stats
+                            .received_bytes
+                            .fetch_add(payload.len() as u64, Ordering::Relaxed);
+                        let latency = timestamp.map(|timestamp| {
+                            Duration::from_nanos(latency::timestamp_ns().saturating_sub(timestamp))
+                        });
+                        handler.on_after_receive(direction, &payload, latency);
+                        let flags = flags & MSG_USER_FLAGS_MASK;
+                        match &buffers.pool {
+                            Some(pool) => {
+                                handler.on_message_owned(direction, pool.message(&payload), flags)
+                            }
+                            None => handler.on_message_with_flags(direction, &payload, flags),
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) if err.is_disconnect() => {
+                        // Пир не прислал ключ -- шифрование не настроено на
+                        // другой стороне или кадр чужой; соединение сбрасываем.
+                        origin.report(handler, "decrypt", err);
+                        return ReceiveOutcome {
+                            fatal: true,
+                            protocol_error: true,
+                            more_pending: false,
+                        };
+                    }
+                    Err(err) => {
+                        // Подделанный/повторный кадр -- отбрасываем только его.
+                        origin.report(handler, "decrypt", err);
+                    }
+                }
             }
             Err(ShmError::QueueEmpty) => {
                 drained = true;
                 break;
             }
-            Err(ShmError::NotConnected) | Err(ShmError::NotReady) | Err(ShmError::Timeout) => {
+            Err(err @ ShmError::ChecksumMismatch) => {
+                // Битое сообщение уже изъято из кольца -- продолжаем батч.
+                stats.checksum_errors.fetch_add(1, Ordering::Relaxed);
+                origin.report(handler, "receive", err);
+            }
+            // Разрыв сообщит событие disconnect, отдельно не сбрасываем.
+            Err(ShmError::NotConnected) => {
                 drained = true;
                 break;
             }
-            Err(ref err @ ShmError::Corrupted) => {
-                handler.on_error(err.clone());
+            Err(err) if err.is_disconnect() => {
+                origin.report(handler, "receive", err);
                 return ReceiveOutcome {
                     fatal: true,
+                    protocol_error: false,
                     more_pending: false,
                 };
             }
+            Err(err) if err.is_retryable() => {
+                drained = true;
+                break;
+            }
             Err(err) => {
-                handler.on_error(err.clone());
+                origin.report(handler, "receive", err.clone());
                 drained = true;
                 break;
             }
         }
     }
+    // Просроченные кадры кольцо пропускает само (а writer убирает их и
+    // между проходами) -- переносим прирост его счётчика в статистику.
+    let expired = endpoint.expired();
+    stats.expired_messages.fetch_add(
+        expired.wrapping_sub(pipeline.expired_seen) as u64,
+        Ordering::Relaxed,
+    );
+    pipeline.expired_seen = expired;
     ReceiveOutcome {
         fatal: false,
+        protocol_error: false,
         more_pending: !drained,
     }
 }
 
 trait SendEndpoint {
-    fn write(&self, data: &[u8]) -> Result<crate::ring::WriteOutcome>;
+    fn write(&self, data: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome>;
+    fn write_chunk(&self, chunk: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome>;
+    /// Пир текущего соединения собирает куски (`FEATURE_CHUNKING`).
+    fn chunking(&self) -> bool;
 }
 
 trait ReceiveEndpoint {
-    fn read(&self, buffer: &mut Vec<u8>) -> Result<usize>;
+    /// Длина payload'а и флаги заголовка кадра.
+    fn read(&self, buffer: &mut Vec<u8>) -> Result<(usize, u16)>;
+    /// Счётчик просроченных входящих кадров текущего соединения.
+    fn expired(&self) -> u32;
+    /// Метка времени только что прочитанного кадра (`AutoOptions::latency`).
+    fn take_timestamp(&self) -> Option<u64>;
 }
 
 impl SendEndpoint for SharedServer {
-    fn write(&self, data: &[u8]) -> Result<crate::ring::WriteOutcome> {
-        self.send_to_client(data)
+    fn write(&self, data: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome> {
+        self.send_to_client_with_flags(data, flags)
+    }
+
+    fn write_chunk(&self, chunk: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome> {
+        self.send_chunk_to_client(chunk, flags)
+    }
+
+    fn chunking(&self) -> bool {
+        self.negotiated_features() & FEATURE_CHUNKING != 0
     }
 }
 
 impl ReceiveEndpoint for SharedServer {
-    fn read(&self, buffer: &mut Vec<u8>) -> Result<usize> {
-        self.receive_from_client(buffer)
+    fn read(&self, buffer: &mut Vec<u8>) -> Result<(usize, u16)> {
+        self.receive_frame_from_client(buffer)
+    }
+
+    fn expired(&self) -> u32 {
+        self.expired_messages()
+    }
+
+    fn take_timestamp(&self) -> Option<u64> {
+        self.take_rx_timestamp()
     }
 }
 
 impl SendEndpoint for SharedClient {
-    fn write(&self, data: &[u8]) -> Result<crate::ring::WriteOutcome> {
-        self.send_to_server(data)
+    fn write(&self, data: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome> {
+        self.send_to_server_with_flags(data, flags)
+    }
+
+    fn write_chunk(&self, chunk: &[u8], flags: u16) -> Result<crate::ring::WriteOutcome> {
+        self.send_chunk_to_server(chunk, flags)
+    }
+
+    fn chunking(&self) -> bool {
+        self.negotiated_features() & FEATURE_CHUNKING != 0
     }
 }
 
 impl ReceiveEndpoint for SharedClient {
-    fn read(&self, buffer: &mut Vec<u8>) -> Result<usize> {
-        self.receive_from_server(buffer)
+    fn read(&self, buffer: &mut Vec<u8>) -> Result<(usize, u16)> {
+        self.receive_frame_from_server(buffer)
+    }
+
+    fn expired(&self) -> u32 {
+        self.expired_messages()
+    }
+
+    fn take_timestamp(&self) -> Option<u64> {
+        self.take_rx_timestamp()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::{MAX_CHUNKED_MESSAGE_SIZE, MAX_MESSAGES};
+    use std::sync::atomic::AtomicBool;
 
     /// Handler, который в `on_disconnect` дропает контейнер, содержащий сам
     /// `AutoServer` -- воспроизводит паттерн, который вызвал self-join
@@ -699,4 +2381,1201 @@ mod tests {
             "on_disconnect не вернулся за 10с -- self-join deadlock"
         );
     }
+    #[test]
+    fn latency_option_fills_receiver_histogram() {
+        let name = format!("TEST_AUTO_LATENCY_{}", std::process::id());
+        let options = AutoOptions {
+            latency: true,
+            ..AutoOptions::default()
+        };
+        let server = AutoServer::start(&name, Arc::new(NoopHandler), options.clone()).unwrap();
+        let client = AutoClient::connect(&name, Arc::new(NoopHandler), options).unwrap();
+
+        // Очередь отправки держит сообщения до подключения.
+        for _ in 0..10 {
+            client.send(b"ping").unwrap();
+        }
+        let start = std::time::Instant::now();
+        while server.stats().received_messages < 10 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let latency = server.stats().latency;
+        assert_eq!(latency.count, 10);
+        assert!(latency.p50_ns <= latency.p999_ns && latency.p999_ns <= latency.max_ns);
+        assert!(latency.last_ns > 0 && latency.last_ns <= latency.max_ns);
+        assert_eq!(client.stats().latency.count, 0);
+    }
+
+    #[test]
+    fn stats_count_payload_bytes_and_queue_depth() {
+        let name = format!("TEST_AUTO_BYTES_{}", std::process::id());
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        server.pause();
+        // Сервер без клиента держит всё в очереди отправки.
+        for len in [3, 5, 8] {
+            server.send(&vec![0u8; len]).unwrap();
+        }
+        let start = std::time::Instant::now();
+        while server.stats().send_queue_depth < 3 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let stats = server.stats();
+        assert_eq!((stats.send_queue_depth, stats.send_queue_bytes), (3, 16));
+        assert_eq!(stats.sent_bytes, 0);
+
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        client.send(b"hello").unwrap();
+        let start = std::time::Instant::now();
+        while (client.stats().received_bytes < 16 || client.stats().sent_bytes < 5)
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let stats = server.stats();
+        assert_eq!((stats.send_queue_depth, stats.send_queue_bytes), (0, 0));
+        assert_eq!(stats.sent_bytes, 16);
+        assert_eq!(client.stats().received_bytes, 16);
+        assert_eq!(client.stats().sent_bytes, 5);
+        // Сервер на паузе: байты клиента ещё в кольце.
+        assert_eq!(stats.received_bytes, 0);
+    }
+
+    #[test]
+    fn expired_messages_are_counted_not_delivered() {
+        let name = format!("TEST_AUTO_TTL_{}", std::process::id());
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        let client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        // Нулевой срок: кадр просрочен раньше, чем worker его прочтёт.
+        client.set_default_ttl(Some(Duration::ZERO));
+        for _ in 0..5 {
+            client.send_to_server(b"late").unwrap();
+        }
+        client.send_to_server_with_ttl(b"on time", None).unwrap();
+
+        let start = std::time::Instant::now();
+        let mut stats = server.stats();
+        while (stats.received_messages < 1 || stats.expired_messages < 5)
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+            stats = server.stats();
+        }
+        assert_eq!(stats.received_messages, 1);
+        assert_eq!(stats.expired_messages, 5);
+    }
+
+    #[test]
+    fn options_that_would_spin_are_rejected() {
+        let options = AutoOptions::builder()
+            .poll_timeout(Duration::from_millis(5))
+            .recv_batch(8)
+            .build()
+            .unwrap();
+        assert_eq!(options.poll_timeout, Duration::from_millis(5));
+        assert_eq!(
+            options.max_send_queue,
+            AutoOptions::default().max_send_queue
+        );
+
+        for builder in [
+            AutoOptions::builder().poll_timeout(Duration::ZERO),
+            AutoOptions::builder().recv_batch(0),
+            AutoOptions::builder().max_send_queue(0),
+            AutoOptions::builder().ttl(Some(Duration::ZERO)),
+            AutoOptions::builder().credit_window(Some(CreditWindow {
+                messages: 0,
+                bytes: 0,
+            })),
+        ] {
+            assert!(matches!(builder.build(), Err(ShmError::InvalidConfig(_))));
+        }
+
+        // Литерал структуры проверяется при запуске.
+        let name = format!("TEST_AUTO_OPTIONS_{}", std::process::id());
+        let options = AutoOptions {
+            recv_batch: 0,
+            ..AutoOptions::default()
+        };
+        assert!(matches!(
+            AutoServer::start(&name, Arc::new(NoopHandler), options.clone()),
+            Err(ShmError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            AutoClient::connect(&name, Arc::new(NoopHandler), options),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn cancelling_parent_token_stops_every_channel() {
+        let root = CancellationToken::new().unwrap();
+        // Длинные паузы: без токена worker'ы заметили бы остановку только
+        // через 10с.
+        let options = AutoOptions::builder()
+            .poll_timeout(Duration::from_secs(10))
+            .reconnect_delay(Duration::from_secs(10))
+            .cancel(Some(root.clone()))
+            .build()
+            .unwrap();
+        let pid = std::process::id();
+        let first = AutoServer::start(
+            &format!("TEST_AUTO_CANCEL_A_{pid}"),
+            Arc::new(NoopHandler),
+            options.clone(),
+        )
+        .unwrap();
+        let second = AutoServer::start(
+            &format!("TEST_AUTO_CANCEL_B_{pid}"),
+            Arc::new(NoopHandler),
+            options.clone(),
+        )
+        .unwrap();
+        let orphan = AutoClient::connect(
+            &format!("TEST_AUTO_CANCEL_NONE_{pid}"),
+            Arc::new(NoopHandler),
+            options,
+        )
+        .unwrap();
+
+        // Остановка одного канала не задевает остальные.
+        first.retire();
+        thread::sleep(Duration::from_millis(100));
+        assert!(second.send(b"still running").is_ok());
+
+        let start = std::time::Instant::now();
+        root.cancel();
+        while !second.is_worker_finished() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(first.is_worker_finished() && second.is_worker_finished());
+        assert_eq!(second.send(b"late"), Err(ShmError::NotReady));
+        assert_eq!(orphan.send(b"late"), Err(ShmError::NotReady));
+        drop(orphan);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn send_queue_drops_are_split_by_budget() {
+        let pid = std::process::id();
+        let wait_for = |server: &AutoServer, drops: fn(&AutoStatsSnapshot) -> u64, n: u64| {
+            let start = std::time::Instant::now();
+            while drops(&server.stats()) < n && start.elapsed() < Duration::from_secs(5) {
+                thread::sleep(Duration::from_millis(10));
+            }
+            server.stats()
+        };
+
+        // Без клиента server worker только складывает сообщения в очередь.
+        let len = MAX_MESSAGE_SIZE / 2;
+        let options = AutoOptions::builder()
+            .max_send_queue(4)
+            .max_send_queue_bytes(Some(3 * len))
+            .build()
+            .unwrap();
+        let by_bytes = AutoServer::start(
+            &format!("TEST_AUTO_QBYTES_{pid}"),
+            Arc::new(NoopHandler),
+            options,
+        )
+        .unwrap();
+        for _ in 0..10 {
+            by_bytes.send(&vec![0u8; len]).unwrap();
+        }
+        let stats = wait_for(&by_bytes, |s| s.send_queue_byte_drops, 7);
+        assert_eq!(
+            (stats.send_queue_byte_drops, stats.send_queue_drops),
+            (7, 0)
+        );
+
+        let options = AutoOptions::builder().max_send_queue(2).build().unwrap();
+        let by_count = AutoServer::start(
+            &format!("TEST_AUTO_QCOUNT_{pid}"),
+            Arc::new(NoopHandler),
+            options,
+        )
+        .unwrap();
+        for _ in 0..5 {
+            by_count.send(b"small").unwrap();
+        }
+        let stats = wait_for(&by_count, |s| s.send_queue_drops, 3);
+        assert_eq!(
+            (stats.send_queue_drops, stats.send_queue_byte_drops),
+            (3, 0)
+        );
+
+        assert!(matches!(
+            AutoOptions::builder()
+                .max_send_queue_bytes(Some(MAX_MESSAGE_SIZE - 1))
+                .build(),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn blocking_queue_policy_waits_instead_of_dropping() {
+        let name = format!("TEST_AUTO_QBLOCK_{}", std::process::id());
+        let options = AutoOptions::builder()
+            .max_send_queue(2)
+            .queue_policy(QueuePolicy::Block {
+                timeout: Duration::from_millis(50),
+            })
+            .build()
+            .unwrap();
+        let server = Arc::new(AutoServer::start(&name, Arc::new(NoopHandler), options).unwrap());
+        server.send(b"m0").unwrap();
+        server.send(b"m1").unwrap();
+        let start = Instant::now();
+        assert_eq!(server.send(b"lost"), Err(ShmError::QueueFull));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // Место освобождает подключившийся клиент, вычитывая очередь.
+        let blocked = thread::spawn({
+            let server = server.clone();
+            move || {
+                let start = Instant::now();
+                let mut result = Err(ShmError::QueueFull);
+                while result == Err(ShmError::QueueFull) && start.elapsed() < Duration::from_secs(5)
+                {
+                    result = server.send(b"m2");
+                }
+                result
+            }
+        });
+        let recorder = Arc::new(TraceRecorder::default());
+        let _client = AutoClient::connect(&name, recorder.clone(), AutoOptions::default()).unwrap();
+        assert_eq!(blocked.join().unwrap(), Ok(()));
+
+        let start = Instant::now();
+        while recorder.messages.load(Ordering::Relaxed) < 3
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let received: Vec<_> = recorder
+            .received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(p, _)| p.clone())
+            .collect();
+        assert_eq!(received, [b"m0".to_vec(), b"m1".to_vec(), b"m2".to_vec()]);
+        let stats = server.stats();
+        assert_eq!(
+            (stats.send_queue_drops, stats.send_queue_byte_drops),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn send_tickets_resolve_on_write_eviction_and_stop() {
+        let name = format!("TEST_AUTO_TICKET_{}", std::process::id());
+        let options = AutoOptions::builder().max_send_queue(2).build().unwrap();
+        let server = AutoServer::start(&name, Arc::new(NoopHandler), options).unwrap();
+        let evicted = server.send_with_ticket(b"first").unwrap();
+        let kept = server.send_with_ticket(b"second").unwrap();
+        let last = server.send_with_ticket(b"third").unwrap();
+        assert_eq!(
+            evicted.wait(Some(Duration::from_secs(5))),
+            Err(ShmError::QueueFull)
+        );
+        assert_eq!(evicted.try_result(), Some(Err(ShmError::QueueFull)));
+        assert_eq!(kept.try_result(), None);
+        assert_eq!(
+            kept.wait(Some(Duration::from_millis(20))),
+            Err(ShmError::Timeout)
+        );
+
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        assert_eq!(kept.wait(Some(Duration::from_secs(5))), Ok(()));
+        assert_eq!(last.wait(Some(Duration::from_secs(5))), Ok(()));
+        drop(client);
+
+        // Клиента нет -- сообщение в очереди, пока worker не остановлен.
+        let start = Instant::now();
+        while server.health().state == LinkState::Connected
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let orphan = server.send_with_ticket(b"orphan").unwrap();
+        drop(server);
+        assert_eq!(orphan.wait(None), Err(ShmError::NotReady));
+    }
+
+    #[test]
+    fn urgent_messages_jump_the_queue_and_are_evicted_last() {
+        let name = format!("TEST_AUTO_URGENT_{}", std::process::id());
+        let options = AutoOptions::builder().max_send_queue(4).build().unwrap();
+        let server = AutoServer::start(&name, Arc::new(NoopHandler), options).unwrap();
+        // Без клиента всё копится в очереди: [u1, u2, b2, b3] -- b0 и b1
+        // вытеснены вместо срочных.
+        server.send(b"b0").unwrap();
+        server.send(b"b1").unwrap();
+        server.send_urgent(b"u1").unwrap();
+        server.send(b"b2").unwrap();
+        server.send_urgent(b"u2").unwrap();
+        server.send(b"b3").unwrap();
+
+        let seen = Arc::new(TraceRecorder::default());
+        let _client = AutoClient::connect(&name, seen.clone(), AutoOptions::default()).unwrap();
+        let start = Instant::now();
+        while seen.received.lock().unwrap().len() < 4 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let received: Vec<Vec<u8>> = seen
+            .received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(payload, _)| payload.clone())
+            .collect();
+        assert_eq!(received, [b"u1", b"u2", b"b2", b"b3"]);
+        assert_eq!(server.stats().send_queue_drops, 2);
+
+        // Продолжение кадра, начатого кусками, срочное не разрывает.
+        let queue = SendQueue::new();
+        queue.push(Outgoing {
+            sent: 10,
+            ..Outgoing::new(b"chunked", None, 0)
+        });
+        queue.push_urgent(Outgoing {
+            urgent: true,
+            ..Outgoing::new(b"u", None, 0)
+        });
+        assert_eq!(queue.pop().unwrap().data, b"chunked");
+        assert_eq!(queue.pop().unwrap().data, b"u");
+    }
+
+    #[derive(Default)]
+    struct ExpiryRecorder {
+        expired: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl AutoHandler for ExpiryRecorder {
+        fn on_expired(&self, _direction: ChannelKind, payload: &[u8]) {
+            self.expired.lock().unwrap().push(payload.to_vec());
+        }
+    }
+
+    #[test]
+    fn stale_deadline_messages_are_dropped_from_send_queue() {
+        let name = format!("TEST_AUTO_DEADLINE_{}", std::process::id());
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        let recorder = Arc::new(ExpiryRecorder::default());
+        let client = AutoClient::connect(&name, recorder.clone(), AutoOptions::default()).unwrap();
+
+        // Дедлайн «сейчас» истекает раньше, чем worker доберётся до очереди.
+        for _ in 0..3 {
+            client.send_with_deadline(b"stale", Instant::now()).unwrap();
+        }
+        let later = Instant::now() + Duration::from_secs(60);
+        client.send_with_deadline(b"fresh", later).unwrap();
+        client.send(b"plain").unwrap();
+
+        let start = Instant::now();
+        while server.stats().received_messages < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.stats().received_messages, 2);
+        assert_eq!(client.stats().send_expired, 3);
+        assert_eq!(
+            *recorder.expired.lock().unwrap(),
+            vec![b"stale".to_vec(); 3]
+        );
+    }
+
+    #[test]
+    fn send_timeout_waits_for_the_ring_or_expires() {
+        let name = format!("TEST_AUTO_SEND_TIMEOUT_{}", std::process::id());
+        let recorder = Arc::new(ExpiryRecorder::default());
+        let server = AutoServer::start(&name, recorder.clone(), AutoOptions::default()).unwrap();
+        // Без клиента worker в кольцо не пишет.
+        let start = Instant::now();
+        assert_eq!(
+            server.send_timeout(b"late", Duration::from_millis(50)),
+            Err(ShmError::Timeout)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        assert_eq!(
+            server.send_timeout(b"on time", Duration::from_secs(5)),
+            Ok(())
+        );
+        let start = Instant::now();
+        while client.stats().received_messages < 1 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.stats().received_messages, 1);
+        assert_eq!(*recorder.expired.lock().unwrap(), [b"late".to_vec()]);
+    }
+
+    #[derive(Default)]
+    struct TraceRecorder {
+        sent: Mutex<Vec<Vec<u8>>>,
+        received: Mutex<Vec<(Vec<u8>, Option<Duration>)>>,
+        messages: AtomicUsize,
+    }
+
+    impl AutoHandler for TraceRecorder {
+        fn on_message(&self, _direction: ChannelKind, _payload: &[u8]) {
+            self.messages.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_before_send(&self, _direction: ChannelKind, payload: &[u8]) {
+            self.sent.lock().unwrap().push(payload.to_vec());
+        }
+
+        fn on_after_receive(
+            &self,
+            _direction: ChannelKind,
+            payload: &[u8],
+            latency: Option<Duration>,
+        ) {
+            self.received
+                .lock()
+                .unwrap()
+                .push((payload.to_vec(), latency));
+        }
+    }
+
+    #[test]
+    fn tap_handler_sees_traffic_of_any_handler() {
+        let name = format!("TEST_AUTO_TAP_{}", std::process::id());
+        let server_inner = Arc::new(TraceRecorder::default());
+        let server_tap = Arc::new(TraceRecorder::default());
+        let options = AutoOptions::builder().latency(true).build().unwrap();
+        let _server = AutoServer::start(
+            &name,
+            Arc::new(TapHandler::new(server_inner.clone(), server_tap.clone())),
+            options.clone(),
+        )
+        .unwrap();
+        let client_tap = Arc::new(TraceRecorder::default());
+        let client = AutoClient::connect(
+            &name,
+            Arc::new(TapHandler::new(Arc::new(NoopHandler), client_tap.clone())),
+            options,
+        )
+        .unwrap();
+
+        for payload in [&b"one"[..], b"two"] {
+            client.send(payload).unwrap();
+        }
+        let start = Instant::now();
+        while server_inner.messages.load(Ordering::Relaxed) < 2
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let expected = vec![b"one".to_vec(), b"two".to_vec()];
+        assert_eq!(*client_tap.sent.lock().unwrap(), expected);
+        for recorder in [&server_tap, &server_inner] {
+            let received = recorder.received.lock().unwrap();
+            let payloads: Vec<_> = received
+                .iter()
+                .map(|(payload, _)| payload.clone())
+                .collect();
+            assert_eq!(payloads, expected);
+            assert!(received.iter().all(|(_, latency)| latency.is_some()));
+        }
+        assert_eq!(server_tap.messages.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn receive_filter_drops_before_handler() {
+        let name = format!("TEST_AUTO_FILTER_{}", std::process::id());
+        let only_type_one: MessageFilter = Arc::new(|payload: &[u8]| payload.first() == Some(&1));
+        let options = AutoOptions::builder()
+            .filter(Some(only_type_one))
+            .build()
+            .unwrap();
+        let recorder = Arc::new(TraceRecorder::default());
+        let server = AutoServer::start(&name, recorder.clone(), options).unwrap();
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        for payload in [[1u8, 10], [2, 20], [1, 30], [3, 40]] {
+            client.send(&payload).unwrap();
+        }
+
+        let start = Instant::now();
+        while server.stats().filtered_messages < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let stats = server.stats();
+        assert_eq!((stats.received_messages, stats.filtered_messages), (2, 2));
+        assert_eq!(recorder.messages.load(Ordering::Relaxed), 2);
+        let received: Vec<_> = recorder
+            .received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(p, _)| p.clone())
+            .collect();
+        assert_eq!(received, [vec![1, 10], vec![1, 30]]);
+    }
+
+    #[test]
+    fn connection_state_follows_worker() {
+        let name = format!("TEST_AUTO_STATE_{}", std::process::id());
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        assert_eq!(client.state(), ConnectionState::Connecting);
+        assert_eq!(
+            client.wait_connected(Some(Duration::from_millis(20))),
+            Err(ShmError::Timeout)
+        );
+
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        client.wait_connected(Some(Duration::from_secs(5))).unwrap();
+        server.wait_connected(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(server.state(), ConnectionState::Connected);
+
+        drop(server);
+        let start = Instant::now();
+        while client.state() == ConnectionState::Connected
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.state(), ConnectionState::Reconnecting);
+
+        client.stop();
+        assert_eq!(client.wait_connected(None), Err(ShmError::NotReady));
+        assert_eq!(client.state(), ConnectionState::Stopped);
+    }
+
+    #[test]
+    fn paused_server_keeps_messages_in_ring_until_resume() {
+        let name = format!("TEST_AUTO_PAUSE_{}", std::process::id());
+        let recorder = Arc::new(TraceRecorder::default());
+        let server = AutoServer::start(&name, recorder.clone(), AutoOptions::default()).unwrap();
+        server.pause();
+        assert!(server.is_paused());
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        for i in 0..3u8 {
+            client.send(&[i, i]).unwrap();
+        }
+
+        let start = Instant::now();
+        while client.stats().sent_messages < 3 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.stats().sent_messages, 3);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(recorder.messages.load(Ordering::Relaxed), 0);
+        assert_eq!(server.health().state, LinkState::Connected);
+
+        server.resume();
+        assert!(!server.is_paused());
+        while recorder.messages.load(Ordering::Relaxed) < 3
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.stats().received_messages, 3);
+        assert_eq!(server.stats().disconnects, 0);
+    }
+
+    #[test]
+    fn drop_flushes_send_queue_when_configured() {
+        let name = format!("TEST_AUTO_FLUSH_DROP_{}", std::process::id());
+        let recorder = Arc::new(TraceRecorder::default());
+        let server = AutoServer::start(&name, recorder.clone(), AutoOptions::default()).unwrap();
+        let options = AutoOptions::builder()
+            .flush_on_drop(Some(Duration::from_secs(10)))
+            .build()
+            .unwrap();
+        let client = AutoClient::connect(&name, Arc::new(NoopHandler), options).unwrap();
+        // Столько, сколько кольцо держит без вытеснения в любом профиле.
+        let count = (MAX_MESSAGES as usize).min(100);
+        // Drop сразу после send: соединение ещё может подниматься.
+        for i in 0..count as u8 {
+            client.send(&[i, i]).unwrap();
+        }
+        let start = Instant::now();
+        drop(client);
+        // Ответ на flush, а не истечение таймаута.
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        while recorder.messages.load(Ordering::Relaxed) < count
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(recorder.messages.load(Ordering::Relaxed), count);
+        assert_eq!(server.stats().received_messages, count as u64);
+    }
+
+    #[derive(Default)]
+    struct ContextRecorder {
+        errors: Mutex<Vec<ErrorContext>>,
+    }
+
+    impl AutoHandler for ContextRecorder {
+        fn on_error_with_context(&self, error: ErrorContext) {
+            self.errors.lock().unwrap().push(error);
+        }
+    }
+
+    #[test]
+    fn health_tracks_link_state_activity_and_errors() {
+        let name = format!("TEST_AUTO_HEALTH_{}", std::process::id());
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        let idle = server.health();
+        assert_eq!(idle.state, LinkState::Waiting);
+        assert_eq!((idle.last_rx_age, idle.last_tx_age), (None, None));
+
+        let errors = Arc::new(ContextRecorder::default());
+        let client = AutoClient::connect(&name, errors.clone(), AutoOptions::default()).unwrap();
+        client.send(b"ping").unwrap();
+        // Слишком большое сообщение worker отбрасывает с on_error.
+        client
+            .send(&vec![0u8; MAX_CHUNKED_MESSAGE_SIZE + 1])
+            .unwrap();
+        let start = Instant::now();
+        while (server.stats().received_messages < 1 || client.health().last_error.is_none())
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let health = server.health();
+        assert_eq!(health.state, LinkState::Connected);
+        assert!(health.last_rx_age.is_some() && health.last_tx_age.is_none());
+        assert_eq!(health.reconnects, 0);
+        let health = client.health();
+        assert!(health.last_tx_age.is_some());
+        assert_eq!(health.last_error, Some(ShmError::MessageTooLarge));
+        // Handler получает ошибку вместе с каналом и стороной.
+        assert_eq!(
+            errors.errors.lock().unwrap().as_slice(),
+            [ErrorContext::new(
+                name.as_str(),
+                ChannelRole::Client,
+                "send",
+                ShmError::MessageTooLarge
+            )]
+        );
+
+        server.stop();
+        while !server.is_stopped() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.health().state, LinkState::Stopped);
+    }
+
+    #[test]
+    fn messages_above_max_size_are_chunked_both_ways() {
+        let name = format!("TEST_AUTO_CHUNKED_{}", std::process::id());
+        let server_seen = Arc::new(TraceRecorder::default());
+        let server = AutoServer::start(&name, server_seen.clone(), AutoOptions::default()).unwrap();
+        let client_seen = Arc::new(TraceRecorder::default());
+        let client =
+            AutoClient::connect(&name, client_seen.clone(), AutoOptions::default()).unwrap();
+        let large: Vec<u8> = (0..3 << 20).map(|i: u32| (i % 251) as u8).collect();
+        client.send(&large).unwrap();
+        client.send(b"after").unwrap();
+        server.send(&large[..MAX_MESSAGE_SIZE + 1]).unwrap();
+
+        let start = Instant::now();
+        while (server_seen.messages.load(Ordering::Relaxed) < 2
+            || client_seen.messages.load(Ordering::Relaxed) < 1)
+            && start.elapsed() < Duration::from_secs(10)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let received: Vec<_> = server_seen
+            .received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(payload, _)| payload.clone())
+            .collect();
+        assert_eq!(received, [large.clone(), b"after".to_vec()]);
+        let received = client_seen.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, large[..MAX_MESSAGE_SIZE + 1]);
+        assert_eq!(server.stats().received_messages, 2);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypted_send_rejects_payload_above_plaintext_limit() {
+        use crate::crypto::MAX_PLAINTEXT_SIZE;
+
+        let name = format!("TEST_AUTO_SEALED_LIMIT_{}", std::process::id());
+        let options = AutoOptions::builder()
+            .encryption(Some(EncryptionOptions::default()))
+            .build()
+            .unwrap();
+        let server_seen = Arc::new(TraceRecorder::default());
+        let server = AutoServer::start(&name, server_seen.clone(), options.clone()).unwrap();
+        let client_seen = Arc::new(TraceRecorder::default());
+        let client = AutoClient::connect(&name, client_seen.clone(), options).unwrap();
+
+        let large = vec![7u8; MAX_PLAINTEXT_SIZE + 1];
+        assert_eq!(client.send(&large), Err(ShmError::MessageTooLarge));
+        assert_eq!(server.send(&large), Err(ShmError::MessageTooLarge));
+        client.send(&large[..MAX_PLAINTEXT_SIZE]).unwrap();
+
+        let start = Instant::now();
+        while server_seen.messages.load(Ordering::Relaxed) < 1
+            && start.elapsed() < Duration::from_secs(10)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let received = server_seen.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0.len(), MAX_PLAINTEXT_SIZE);
+    }
+
+    #[derive(Default)]
+    struct FlagRecorder {
+        received: Mutex<Vec<(Vec<u8>, u16)>>,
+    }
+
+    impl AutoHandler for FlagRecorder {
+        fn on_message_with_flags(&self, _direction: ChannelKind, payload: &[u8], flags: u16) {
+            self.received
+                .lock()
+                .unwrap()
+                .push((payload.to_vec(), flags));
+        }
+    }
+
+    #[test]
+    fn send_with_flags_delivers_the_flags() {
+        let name = format!("TEST_AUTO_FLAGS_{}", std::process::id());
+        let seen = Arc::new(FlagRecorder::default());
+        let server = AutoServer::start(&name, seen.clone(), AutoOptions::default()).unwrap();
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        assert_eq!(
+            client.send_with_flags(b"bad", MSG_USER_FLAGS_MASK + 1),
+            Err(ShmError::InvalidConfig(
+                "message flags must fit MSG_USER_FLAGS_MASK"
+            ))
+        );
+        let large = vec![3u8; MAX_MESSAGE_SIZE + 1];
+        client.send_with_flags(b"tagged", 0x05).unwrap();
+        client.send(b"plain").unwrap();
+        client.send_with_flags(&large, MSG_USER_FLAGS_MASK).unwrap();
+
+        let start = Instant::now();
+        while seen.received.lock().unwrap().len() < 3 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            *seen.received.lock().unwrap(),
+            [
+                (b"tagged".to_vec(), 0x05),
+                (b"plain".to_vec(), 0),
+                (large, MSG_USER_FLAGS_MASK),
+            ]
+        );
+        drop(server);
+    }
+
+    /// `(delay, attempt)` одного `on_reconnect_scheduled`; подключение --
+    /// `(0, 0)`.
+    type Scheduled = (Duration, u32);
+
+    #[derive(Default)]
+    struct BackoffRecorder {
+        log: Mutex<Vec<Scheduled>>,
+    }
+
+    impl AutoHandler for BackoffRecorder {
+        fn on_connect(&self) {
+            self.log.lock().unwrap().push((Duration::ZERO, 0));
+        }
+
+        fn on_reconnect_scheduled(&self, delay: Duration, attempt: u32) {
+            self.log.lock().unwrap().push((delay, attempt));
+        }
+    }
+
+    #[test]
+    fn backoff_grows_between_attempts_and_restarts_after_connect() {
+        let name = format!("TEST_AUTO_BACKOFF_{}", std::process::id());
+        let ms = Duration::from_millis;
+        let options = AutoOptions::builder()
+            .backoff(Some(BackoffStrategy::Exponential {
+                initial: ms(5),
+                max: ms(40),
+                multiplier: 2,
+                jitter: false,
+            }))
+            .build()
+            .unwrap();
+        let seen = Arc::new(BackoffRecorder::default());
+        let client = AutoClient::connect(&name, seen.clone(), options).unwrap();
+        let wait_for = |done: &dyn Fn(&[Scheduled]) -> bool| {
+            let start = Instant::now();
+            while !done(&seen.log.lock().unwrap()) && start.elapsed() < Duration::from_secs(5) {
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        wait_for(&|log| log.len() >= 5);
+        assert_eq!(
+            seen.log.lock().unwrap()[..5],
+            [
+                (ms(5), 1),
+                (ms(10), 2),
+                (ms(20), 3),
+                (ms(40), 4),
+                (ms(40), 5)
+            ]
+        );
+
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        wait_for(&|log| log.contains(&(Duration::ZERO, 0)));
+        drop(server);
+        wait_for(&|log| log.last().is_some_and(|&(_, attempt)| attempt > 0));
+        let log = seen.log.lock().unwrap();
+        let connected = log.iter().position(|&(_, attempt)| attempt == 0).unwrap();
+        assert_eq!(log[connected + 1], (ms(5), 1));
+        drop(client);
+    }
+
+    /// Отменяет канал на `limit`-й неудачной попытке подключения.
+    struct GiveUp {
+        limit: u32,
+        token: CancellationToken,
+        attempts: Mutex<Vec<(u32, bool)>>,
+    }
+
+    impl AutoHandler for GiveUp {
+        fn on_reconnect(&self, attempt: u32, last_error: &ShmError) {
+            let missing = platform::is_not_found(last_error);
+            self.attempts.lock().unwrap().push((attempt, missing));
+            if attempt == self.limit {
+                self.token.cancel();
+            }
+        }
+    }
+
+    #[test]
+    fn on_reconnect_reports_failures_and_can_abort() {
+        let name = format!("TEST_AUTO_GIVE_UP_{}", std::process::id());
+        let token = CancellationToken::new().unwrap();
+        let handler = Arc::new(GiveUp {
+            limit: 3,
+            token: token.clone(),
+            attempts: Mutex::default(),
+        });
+        let options = AutoOptions::builder()
+            .reconnect_delay(Duration::from_millis(5))
+            .cancel(Some(token))
+            .build()
+            .unwrap();
+        let client = AutoClient::connect(&name, handler.clone(), options).unwrap();
+
+        let start = Instant::now();
+        while !client.is_stopped() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(client.is_stopped());
+        assert_eq!(
+            *handler.attempts.lock().unwrap(),
+            [(1, true), (2, true), (3, true)]
+        );
+    }
+
+    #[derive(Default)]
+    struct Ticks(Mutex<Vec<Duration>>);
+
+    impl AutoHandler for Ticks {
+        fn on_tick(&self, elapsed: Duration) {
+            self.0.lock().unwrap().push(elapsed);
+        }
+    }
+
+    #[test]
+    fn on_tick_fires_at_interval_with_and_without_peer() {
+        let name = format!("TEST_AUTO_TICK_{}", std::process::id());
+        let interval = Duration::from_millis(20);
+        // Интервал короче poll_timeout: тик не ждёт конца опроса.
+        let options = AutoOptions::builder()
+            .poll_timeout(Duration::from_millis(500))
+            .tick_interval(Some(interval))
+            .build()
+            .unwrap();
+        let server_ticks = Arc::new(Ticks::default());
+        let server = AutoServer::start(&name, server_ticks.clone(), options.clone()).unwrap();
+        let wait_for = |ticks: &Ticks, count: usize| {
+            let start = Instant::now();
+            while ticks.0.lock().unwrap().len() < count && start.elapsed() < Duration::from_secs(5)
+            {
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert!(ticks.0.lock().unwrap().len() >= count);
+        };
+        // Клиента ещё нет -- тики всё равно идут.
+        wait_for(&server_ticks, 3);
+
+        let client_ticks = Arc::new(Ticks::default());
+        let client = AutoClient::connect(&name, client_ticks.clone(), options).unwrap();
+        client.wait_connected(Some(Duration::from_secs(5))).unwrap();
+        let seen = server_ticks.0.lock().unwrap().len();
+        wait_for(&client_ticks, 3);
+        wait_for(&server_ticks, seen + 3);
+
+        for ticks in [&server_ticks, &client_ticks] {
+            let ticks = ticks.0.lock().unwrap();
+            assert!(ticks.iter().all(|&elapsed| elapsed >= interval));
+            // Тики не копятся до poll_timeout.
+            assert!(ticks
+                .iter()
+                .skip(1)
+                .all(|&elapsed| elapsed < Duration::from_millis(400)));
+        }
+
+        assert!(AutoOptions::builder()
+            .tick_interval(Some(Duration::ZERO))
+            .build()
+            .is_err());
+        drop(client);
+        drop(server);
+    }
+
+    #[test]
+    fn closure_handler_receives_callbacks() {
+        let name = format!("TEST_AUTO_FN_{}", std::process::id());
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let messages = messages.clone();
+            let (connects, disconnects) = (events.clone(), events.clone());
+            AutoHandlerFn::new()
+                .on_message(move |direction, data| {
+                    messages.lock().unwrap().push((direction, data.to_vec()));
+                })
+                .on_connect(move || connects.lock().unwrap().push("connect"))
+                .on_disconnect(move || disconnects.lock().unwrap().push("disconnect"))
+                .build()
+        };
+        let server = AutoServer::start(&name, handler, AutoOptions::default()).unwrap();
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        client.send(b"hi").unwrap();
+
+        let start = Instant::now();
+        while messages.lock().unwrap().is_empty() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            *messages.lock().unwrap(),
+            [(ChannelKind::ClientToServer, b"hi".to_vec())]
+        );
+
+        drop(client);
+        while events.lock().unwrap().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*events.lock().unwrap(), ["connect", "disconnect"]);
+        drop(server);
+    }
+
+    #[test]
+    fn closure_handler_observes_reconnect_backoff() {
+        let name = format!("TEST_AUTO_FN_BACKOFF_{}", std::process::id());
+        let scheduled = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let scheduled = scheduled.clone();
+            AutoHandlerFn::new()
+                .on_reconnect_scheduled(move |delay, attempt| {
+                    scheduled.lock().unwrap().push((delay, attempt));
+                })
+                .build()
+        };
+        let options = AutoOptions::builder()
+            .reconnect_delay(Duration::from_millis(5))
+            .connect_timeout(Duration::from_millis(10))
+            .build()
+            .unwrap();
+        // Сервера нет: каждая попытка подключения неудачна.
+        let client = AutoClient::connect(&name, handler, options).unwrap();
+
+        let start = Instant::now();
+        while scheduled.lock().unwrap().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(client);
+        assert_eq!(
+            scheduled.lock().unwrap()[..2],
+            [(Duration::from_millis(5), 1), (Duration::from_millis(5), 2)]
+        );
+    }
+
+    #[derive(Default)]
+    struct SessionRecorder {
+        epochs: Mutex<Vec<u32>>,
+        reasons: Mutex<Vec<DisconnectReason>>,
+    }
+
+    impl AutoHandler for SessionRecorder {
+        fn on_connect_with_epoch(&self, epoch: u32) {
+            self.epochs.lock().unwrap().push(epoch);
+        }
+
+        fn on_disconnect_with_reason(&self, reason: DisconnectReason) {
+            self.reasons.lock().unwrap().push(reason);
+        }
+    }
+
+    #[test]
+    fn disconnect_reason_and_epoch_reach_handler() {
+        let name = format!("TEST_AUTO_REASON_{}", std::process::id());
+        let seen = Arc::new(SessionRecorder::default());
+        let _server = AutoServer::start(&name, seen.clone(), AutoOptions::default()).unwrap();
+        let wait_for = |count: usize| {
+            let start = Instant::now();
+            while seen.reasons.lock().unwrap().len() < count
+                && start.elapsed() < Duration::from_secs(5)
+            {
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        let mut client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        let first = client.connection_epoch().unwrap();
+        client.disconnect(DisconnectReason::Upgrading);
+        assert_eq!(client.connection_epoch(), None);
+        assert_eq!(
+            client.send_to_server(b"late").err(),
+            Some(ShmError::NotConnected)
+        );
+        wait_for(1);
+        let client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        let second = client.connection_epoch().unwrap();
+        assert_ne!(first, second);
+        drop(client);
+        wait_for(2);
+        assert_eq!(
+            *seen.reasons.lock().unwrap(),
+            [DisconnectReason::Upgrading, DisconnectReason::Graceful]
+        );
+        assert_eq!(*seen.epochs.lock().unwrap(), [first, second]);
+    }
+
+    #[derive(Default)]
+    struct OwnedRecorder {
+        kept: Mutex<Vec<(PooledMessage, u16)>>,
+    }
+
+    impl AutoHandler for OwnedRecorder {
+        fn on_message_owned(&self, _direction: ChannelKind, message: PooledMessage, flags: u16) {
+            self.kept.lock().unwrap().push((message, flags));
+        }
+    }
+
+    #[test]
+    fn message_pool_hands_over_owned_messages() {
+        let name = format!("TEST_AUTO_POOL_{}", std::process::id());
+        let seen = Arc::new(OwnedRecorder::default());
+        let options = AutoOptions::builder().message_pool(4).build().unwrap();
+        let server = AutoServer::start(&name, seen.clone(), options).unwrap();
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        client.send(b"first").unwrap();
+        client.send_with_flags(b"second", 0x02).unwrap();
+
+        let start = Instant::now();
+        while seen.kept.lock().unwrap().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let kept: Vec<_> = std::mem::take(&mut *seen.kept.lock().unwrap());
+        let kept: Vec<_> = kept
+            .into_iter()
+            .map(|(message, flags)| (message.into_vec(), flags))
+            .collect();
+        assert_eq!(kept, [(b"first".to_vec(), 0), (b"second".to_vec(), 0x02)]);
+        assert_eq!(server.stats().received_messages, 2);
+    }
+
+    #[test]
+    fn dead_peer_process_is_disconnected() {
+        use crate::constants::RESERVED_CLIENT_PID_INDEX;
+        use crate::naming::mapping_name;
+        use crate::platform::{Mapping, PlatformMapping};
+        use crate::shared::SharedView;
+
+        let name = format!("TEST_AUTO_DEAD_PEER_{}", std::process::id());
+        let server =
+            AutoServer::start(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        let client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        let start = Instant::now();
+        while server.stats().connects == 0 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Клиент «упал»: disconnect не выставлен, PID -- завершённого процесса.
+        let mut child = platform::spawn_exiting_child();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        let mapping = Mapping::open(&mapping_name(&name)).unwrap();
+        // SAFETY: отображение живо до конца теста.
+        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+        view.control_block().reserved[RESERVED_CLIENT_PID_INDEX].store(dead_pid, Ordering::Release);
+        std::mem::forget(client);
+
+        let start = Instant::now();
+        while server.stats().disconnects == 0 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.stats().disconnects, 1);
+    }
+
+    struct PanickingHandler {
+        errors: Mutex<Vec<ShmError>>,
+    }
+
+    impl AutoHandler for PanickingHandler {
+        fn on_message(&self, _direction: ChannelKind, _payload: &[u8]) {
+            panic!("handler bug");
+        }
+
+        fn on_error(&self, err: ShmError) {
+            self.errors.lock().unwrap().push(err);
+        }
+    }
+
+    #[test]
+    fn worker_panic_is_reported_and_stops_endpoint() {
+        let name = format!("TEST_AUTO_PANIC_{}", std::process::id());
+        let handler = Arc::new(PanickingHandler {
+            errors: Mutex::new(Vec::new()),
+        });
+        let server = AutoServer::start(&name, handler.clone(), AutoOptions::default()).unwrap();
+        let client =
+            AutoClient::connect(&name, Arc::new(NoopHandler), AutoOptions::default()).unwrap();
+        client.send(b"boom").unwrap();
+
+        let start = std::time::Instant::now();
+        // Токен отменяется до вызова on_error, поэтому ждём именно отчёт.
+        while handler.errors.lock().unwrap().is_empty() && start.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(server.is_stopped() && !client.is_stopped());
+        assert_eq!(server.send(b"late"), Err(ShmError::NotReady));
+        assert!(handler
+            .errors
+            .lock()
+            .unwrap()
+            .contains(&ShmError::WorkerPanicked("handler bug".to_owned())));
+    }
 }
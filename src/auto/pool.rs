@@ -0,0 +1,139 @@
+//! Пул буферов входящих сообщений worker'а (`AutoOptions::message_pool`).
+//!
+//! Handler, которому сообщение нужно во владение, получает его как
+//! [`PooledMessage`]: буфер берётся из пула, а при drop возвращается в
+//! него, так что поток в сотни тысяч сообщений в секунду не аллоцирует на
+//! каждое.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use crate::constants::MAX_MESSAGE_SIZE;
+
+/// Свободные буферы; больше `limit` не хранится, лишние освобождаются.
+pub(crate) struct BufferPool {
+    free: Mutex<Vec<Vec<u8>>>,
+    limit: usize,
+}
+
+impl BufferPool {
+    pub(crate) fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            free: Mutex::new(Vec::with_capacity(limit)),
+            limit,
+        })
+    }
+
+    /// Копия `payload` в буфер из пула (или в новый, если пул пуст).
+    pub(crate) fn message(self: &Arc<Self>, payload: &[u8]) -> PooledMessage {
+        let mut data = self.free.lock().unwrap().pop().unwrap_or_default();
+        data.extend_from_slice(payload);
+        PooledMessage {
+            data,
+            pool: Some(self.clone()),
+        }
+    }
+
+    fn put(&self, mut data: Vec<u8>) {
+        // Буфер собранного chunked-сообщения больше обычного кадра --
+        // его не держим, чтобы пул не разрастался до мегабайт.
+        if data.capacity() > MAX_MESSAGE_SIZE {
+            return;
+        }
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.limit {
+            data.clear();
+            free.push(data);
+        }
+    }
+
+    #[cfg(test)]
+    fn idle(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+}
+
+/// Входящее сообщение во владении handler'а (`AutoHandler::on_message_owned`).
+/// Разыменовывается в `[u8]`; при drop буфер возвращается в пул worker'а.
+/// Хранить можно сколько угодно и на любом потоке: пул лишь перестаёт
+/// экономить аллокации, пока буферы не вернутся.
+pub struct PooledMessage {
+    data: Vec<u8>,
+    /// `None` -- буфер забран `into_vec` и в пул не вернётся.
+    pool: Option<Arc<BufferPool>>,
+}
+
+impl PooledMessage {
+    /// Забирает буфер насовсем (в пул он не вернётся).
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.pool = None;
+        std::mem::take(&mut self.data)
+    }
+}
+
+impl Deref for PooledMessage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for PooledMessage {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl AsRef<[u8]> for PooledMessage {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl fmt::Debug for PooledMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PooledMessage").field(&self.data).finish()
+    }
+}
+
+impl Drop for PooledMessage {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.put(std::mem::take(&mut self.data));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_message_returns_its_buffer() {
+        let pool = BufferPool::new(2);
+        let first = pool.message(b"first");
+        let address = first.as_ptr();
+        assert_eq!(&*first, b"first");
+        drop(first);
+        assert_eq!(pool.idle(), 1);
+
+        let second = pool.message(b"2nd");
+        assert_eq!(second.as_ptr(), address);
+        assert_eq!(&*second, b"2nd");
+        assert_eq!(second.into_vec(), b"2nd");
+        assert_eq!(pool.idle(), 0);
+    }
+
+    #[test]
+    fn pool_keeps_at_most_limit_buffers() {
+        let pool = BufferPool::new(1);
+        let held: Vec<_> = (0..3).map(|_| pool.message(b"x")).collect();
+        drop(held);
+        assert_eq!(pool.idle(), 1);
+        // Свободный буфер дорос до chunked-размера -- обратно не берётся.
+        drop(pool.message(&vec![0; MAX_MESSAGE_SIZE + 1]));
+        assert_eq!(pool.idle(), 0);
+    }
+}
@@ -0,0 +1,78 @@
+//! Квитанция отправки Auto-слоя (`send_with_ticket`).
+//!
+//! `send` только ставит сообщение в очередь worker'а; квитанция сообщает,
+//! чем кончилось именно это сообщение: записано в кольцо или отброшено
+//! насовсем.
+
+use std::cell::OnceCell;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::time::Duration;
+
+use crate::error::{Result, ShmError};
+
+/// Итог одного сообщения `AutoServer::send_with_ticket` /
+/// `AutoClient::send_with_ticket`:
+/// - `Ok(())` -- сообщение записано в исходящее кольцо (прочитал ли его
+///   пир, квитанция не говорит);
+/// - `Err(ShmError::QueueFull)` -- вытеснено из очереди отправки лимитом
+///   `max_send_queue`/`max_send_queue_bytes`;
+/// - `Err(ShmError::Timeout)` -- дедлайн истёк в очереди;
+/// - `Err(ShmError::NotReady)` -- worker остановлен раньше, чем сообщение
+///   ушло;
+/// - другая ошибка -- сообщение не отправится никогда (например, слишком
+///   большое для пира без кусков).
+pub struct SendTicket {
+    rx: Receiver<Result<()>>,
+    result: OnceCell<Result<()>>,
+}
+
+impl SendTicket {
+    pub(crate) fn new() -> (Self, Completion) {
+        let (tx, rx) = mpsc::channel();
+        let ticket = Self {
+            rx,
+            result: OnceCell::new(),
+        };
+        (ticket, Completion(tx))
+    }
+
+    /// Итог, если он уже известен; не ждёт.
+    pub fn try_result(&self) -> Option<Result<()>> {
+        if let Some(result) = self.result.get() {
+            return Some(result.clone());
+        }
+        let result = match self.rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(ShmError::NotReady),
+        };
+        Some(self.result.get_or_init(|| result).clone())
+    }
+
+    /// Ждёт итога до `timeout` (`None` -- без ограничения). Не дождался --
+    /// `ShmError::Timeout`, квитанцию можно ждать дальше.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<()> {
+        if let Some(result) = self.result.get() {
+            return result.clone();
+        }
+        let result = match timeout {
+            Some(timeout) => match self.rx.recv_timeout(timeout) {
+                Ok(result) => result,
+                Err(RecvTimeoutError::Timeout) => return Err(ShmError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => Err(ShmError::NotReady),
+            },
+            None => self.rx.recv().unwrap_or(Err(ShmError::NotReady)),
+        };
+        self.result.get_or_init(|| result).clone()
+    }
+}
+
+/// Сторона worker'а: сообщение, потерянное без ответа (drop очереди при
+/// остановке), разрешает квитанцию как `ShmError::NotReady`.
+pub(crate) struct Completion(Sender<Result<()>>);
+
+impl Completion {
+    pub(crate) fn complete(self, result: Result<()>) {
+        let _ = self.0.send(result);
+    }
+}
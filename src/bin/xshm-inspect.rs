@@ -0,0 +1,291 @@
+//! xshm-inspect: консольная утилита для разбора живых каналов.
+//!
+//! ```text
+//! xshm-inspect list [REGISTRY]                 сервисы из службы обнаружения
+//! xshm-inspect channels [PREFIX]               каналы по секциям (xshm::discover)
+//! xshm-inspect dump NAME                       control block и заголовки колец
+//! xshm-inspect tail NAME [--hex|--text] [--count N]
+//!                                              новые кадры обоих направлений
+//! xshm-inspect inject NAME [--hex] [--repeat N] MESSAGE
+//!                                              подключиться клиентом и отправить
+//! ```
+//!
+//! `dump` и `tail` только читают секцию (`xshm::diagnostics`) и не
+//! мешают пирам. `inject` -- обычный `SharedClient`: работает, только
+//! пока к серверу не подключён другой клиент.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use xshm::diagnostics::{self, ChannelTap, TappedFrame};
+use xshm::{reclaim, registry, ChannelKind, SharedClient, ShmError};
+
+const USAGE: &str = "\
+usage:
+  xshm-inspect list [REGISTRY]
+  xshm-inspect channels [PREFIX]
+  xshm-inspect dump NAME
+  xshm-inspect tail NAME [--hex|--text] [--count N]
+  xshm-inspect inject NAME [--hex] [--repeat N] MESSAGE";
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+const TAIL_IDLE: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Render {
+    /// Текст, если payload -- печатаемый UTF-8, иначе hex.
+    Auto,
+    Hex,
+    Text,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("xshm-inspect: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (command, rest) = args.split_first().ok_or(USAGE)?;
+    match command.as_str() {
+        "list" => list(rest),
+        "channels" => channels(rest),
+        "dump" => dump(rest),
+        "tail" => tail(rest),
+        "inject" => inject(rest),
+        "-h" | "--help" | "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => Err(format!("unknown command {other:?}\n{USAGE}")),
+    }
+}
+
+fn channel_error(name: &str, err: ShmError) -> String {
+    format!("{name}: {err}")
+}
+
+/// Разбирает позиционные аргументы и флаги `--flag [VALUE]`.
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    flags: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(args: &'a [String], with_value: &[&str]) -> Result<Self, String> {
+        let mut parsed = Self {
+            positional: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                let value = if with_value.contains(&flag) {
+                    Some(
+                        iter.next()
+                            .ok_or_else(|| format!("--{flag} needs a value"))?
+                            .as_str(),
+                    )
+                } else {
+                    None
+                };
+                parsed.flags.push((flag, value));
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.iter().any(|(name, _)| *name == flag)
+    }
+
+    fn number(&self, flag: &str) -> Result<Option<u64>, String> {
+        self.flags
+            .iter()
+            .find(|(name, _)| *name == flag)
+            .and_then(|(_, value)| *value)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("--{flag}: not a number: {value:?}"))
+            })
+            .transpose()
+    }
+
+    fn name(&self) -> Result<&'a str, String> {
+        self.positional
+            .first()
+            .copied()
+            .ok_or_else(|| format!("channel name is required\n{USAGE}"))
+    }
+}
+
+fn list(args: &[String]) -> Result<(), String> {
+    let parsed = Args::parse(args, &[])?;
+    let channel = parsed
+        .positional
+        .first()
+        .copied()
+        .unwrap_or(registry::REGISTRY_CHANNEL);
+    let services = registry::query(channel, TIMEOUT).map_err(|err| channel_error(channel, err))?;
+    if services.is_empty() {
+        println!("no services registered");
+    }
+    for service in services {
+        print!(
+            "{:<32} {:<8} pid={}",
+            service.name,
+            service.kind.as_str(),
+            service.pid
+        );
+        if let Some(stats) = service.stats {
+            print!(
+                " sent={} received={} connects={}",
+                stats.sent_messages, stats.received_messages, stats.connects
+            );
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn channels(args: &[String]) -> Result<(), String> {
+    let parsed = Args::parse(args, &[])?;
+    let prefix = parsed.positional.first().copied().unwrap_or("");
+    let channels = xshm::discover(prefix);
+    if channels.is_empty() {
+        println!("no channels found");
+    }
+    for channel in channels {
+        let namespace = format!("{:?}", channel.namespace).to_lowercase();
+        let compatible = if channel.compatible {
+            ""
+        } else {
+            " (incompatible)"
+        };
+        let client = if channel.client_connected {
+            "connected"
+        } else {
+            "none"
+        };
+        println!(
+            "{:<32} {namespace:<8} v{:#010x}{compatible} owner={:?} client={client}",
+            channel.name, channel.version, channel.owner,
+        );
+    }
+    Ok(())
+}
+
+fn dump(args: &[String]) -> Result<(), String> {
+    let name = Args::parse(args, &[])?.name()?;
+    let dump = diagnostics::dump_by_name(name).map_err(|err| channel_error(name, err))?;
+    let owner = reclaim::owner(name).map_err(|err| channel_error(name, err))?;
+    println!("channel {name} (owner: {owner:?})");
+    print!("{dump}");
+    Ok(())
+}
+
+fn render_mode(parsed: &Args<'_>) -> Render {
+    if parsed.has("hex") {
+        Render::Hex
+    } else if parsed.has("text") {
+        Render::Text
+    } else {
+        Render::Auto
+    }
+}
+
+fn render(payload: &[u8], mode: Render) -> String {
+    let text = std::str::from_utf8(payload)
+        .ok()
+        .filter(|text| mode == Render::Text || !text.chars().any(|c| c.is_control() && c != '\n'));
+    match (mode, text) {
+        (Render::Hex, _) | (Render::Auto, None) => payload
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" "),
+        (_, Some(text)) => format!("{text:?}"),
+        (Render::Text, None) => format!("{:?}", String::from_utf8_lossy(payload)),
+    }
+}
+
+fn print_frame(frame: &TappedFrame, mode: Render) {
+    let direction = match frame.direction {
+        ChannelKind::ServerToClient => "s2c",
+        ChannelKind::ClientToServer => "c2s",
+    };
+    println!(
+        "{direction} {:>5}B flags={:#06x} {}",
+        frame.payload.len(),
+        frame.flags,
+        render(&frame.payload, mode)
+    );
+}
+
+fn tail(args: &[String]) -> Result<(), String> {
+    let parsed = Args::parse(args, &["count"])?;
+    let name = parsed.name()?;
+    let mode = render_mode(&parsed);
+    let limit = parsed.number("count")?;
+    let mut tap = ChannelTap::open(name).map_err(|err| channel_error(name, err))?;
+    let mut shown = 0u64;
+    let mut lost = 0;
+    while limit.is_none_or(|limit| shown < limit) {
+        let Some(frame) = tap.next_frame() else {
+            std::thread::sleep(TAIL_IDLE);
+            continue;
+        };
+        if tap.lost() != lost {
+            lost = tap.lost();
+            println!("-- fell behind the writer, frames lost (total gaps: {lost})");
+        }
+        print_frame(&frame, mode);
+        shown += 1;
+    }
+    Ok(())
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in {text:?}"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("invalid hex byte {pair:?}"))
+        })
+        .collect()
+}
+
+fn inject(args: &[String]) -> Result<(), String> {
+    let parsed = Args::parse(args, &["repeat"])?;
+    let name = parsed.name()?;
+    let message = parsed
+        .positional
+        .get(1)
+        .ok_or_else(|| format!("message is required\n{USAGE}"))?;
+    let payload = if parsed.has("hex") {
+        parse_hex(message)?
+    } else {
+        message.as_bytes().to_vec()
+    };
+    let repeat = parsed.number("repeat")?.unwrap_or(1);
+    let client = SharedClient::connect(name, TIMEOUT).map_err(|err| channel_error(name, err))?;
+    for _ in 0..repeat {
+        client
+            .send_to_server(&payload)
+            .map_err(|err| channel_error(name, err))?;
+    }
+    println!("sent {repeat} x {}B to {name}", payload.len());
+    Ok(())
+}
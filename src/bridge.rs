@@ -0,0 +1,458 @@
+//! TCP-мост: локальный канал xshm поверх TCP.
+//!
+//! [`expose`] публикует канал, который хостит сервер этой машины, на
+//! TCP-порту: на каждое TCP-подключение мост подключается к каналу как
+//! `AutoClient` и перекладывает сообщения в обе стороны. [`attach`] --
+//! обратная сторона: поднимает на этой машине `AutoServer` с тем же (или
+//! другим) именем и соединяет его с удалённым `expose`. Локальные клиенты
+//! подключаются к нему как к обычному каналу и не знают, что сервер на
+//! другой машине (отладка на стенде, не-Windows пиры).
+//!
+//! Формат TCP-потока -- кадры `u32 LE длина` + payload, длина не больше
+//! `MAX_CHUNKED_MESSAGE_SIZE`: сообщение идёт по TCP целиком, а в канал
+//! попадает кусками (см. `chunking`); без рукопожатия, поэтому со стороны TCP можно
+//! говорить и без xshm. Шифрования и аутентификации нет: слушать стоит
+//! только доверенную сеть (или `127.0.0.1` за SSH-туннелем).
+//!
+//! ```no_run
+//! use xshm::bridge;
+//! use xshm::AutoOptions;
+//!
+//! // Стенд: канал "Telemetry" хостит сервис на этой машине.
+//! let _exposed = bridge::expose("Telemetry", "0.0.0.0:7070", AutoOptions::default())?;
+//!
+//! // Машина разработчика: локальный "Telemetry" ведёт на стенд.
+//! let _attached = bridge::attach("Telemetry", "rig.local:7070", AutoOptions::default())?;
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::auto::{AutoClient, AutoHandler, AutoOptions, AutoServer, ChannelKind};
+use crate::constants::MAX_CHUNKED_MESSAGE_SIZE;
+use crate::error::{io_error, Result, ShmError};
+
+/// Размер префикса длины TCP-кадра.
+pub const BRIDGE_FRAME_HEADER: usize = 4;
+
+/// Пишет один кадр одним `write_all` (префикс и payload вместе).
+fn write_frame(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(BRIDGE_FRAME_HEADER + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+/// Сборщик кадров из потока с таймаутом чтения: недочитанный кадр
+/// сохраняется между вызовами.
+#[derive(Default)]
+struct FrameReader {
+    header: [u8; BRIDGE_FRAME_HEADER],
+    filled: usize,
+    payload: Vec<u8>,
+}
+
+impl FrameReader {
+    /// `Ok(None)` -- таймаут чтения, кадр ещё не собран. Конец потока --
+    /// `ShmError::NotConnected`, длина больше `MAX_CHUNKED_MESSAGE_SIZE` --
+    /// `ShmError::Corrupted`.
+    fn poll(&mut self, stream: &mut impl Read) -> Result<Option<&[u8]>> {
+        loop {
+            let target = if self.filled < BRIDGE_FRAME_HEADER {
+                &mut self.header[self.filled..]
+            } else {
+                let len = u32::from_le_bytes(self.header) as usize;
+                if len > MAX_CHUNKED_MESSAGE_SIZE {
+                    return Err(ShmError::Corrupted);
+                }
+                let offset = self.filled - BRIDGE_FRAME_HEADER;
+                if offset == len {
+                    self.filled = 0;
+                    return Ok(Some(&self.payload[..len]));
+                }
+                self.payload.resize(len, 0);
+                &mut self.payload[offset..len]
+            };
+            match stream.read(target) {
+                Ok(0) => return Err(ShmError::NotConnected),
+                Ok(n) => self.filled += n,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(io_error(err, "bridge read")),
+            }
+        }
+    }
+}
+
+/// Handler канала: входящие сообщения уходят кадрами в текущий
+/// TCP-поток. Пока потока нет, сообщения отбрасываются.
+#[derive(Default)]
+struct TcpForward {
+    stream: Mutex<Option<TcpStream>>,
+}
+
+impl TcpForward {
+    fn set(&self, stream: TcpStream) {
+        *self.stream.lock().unwrap() = Some(stream);
+    }
+
+    fn is_open(&self) -> bool {
+        self.stream.lock().unwrap().is_some()
+    }
+
+    fn close(&self) {
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl AutoHandler for TcpForward {
+    fn on_message(&self, _direction: ChannelKind, payload: &[u8]) {
+        let mut guard = self.stream.lock().unwrap();
+        if let Some(stream) = guard.as_mut() {
+            if write_frame(stream, payload).is_err() {
+                let _ = stream.shutdown(Shutdown::Both);
+                *guard = None;
+            }
+        }
+    }
+}
+
+/// Запущенный мост ([`expose`] или [`attach`]). Останавливается при drop.
+pub struct Bridge {
+    local_addr: Option<SocketAddr>,
+    running: Arc<AtomicBool>,
+    join: Option<JoinHandle<()>>,
+}
+
+impl Bridge {
+    /// Адрес, который слушает [`expose`] (полезно при порте 0); у
+    /// [`attach`] -- `None`.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(join) = self.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn spawn_bridge(
+    name: &str,
+    local_addr: Option<SocketAddr>,
+    worker: impl FnOnce(&AtomicBool) + Send + 'static,
+) -> Result<Bridge> {
+    let running = Arc::new(AtomicBool::new(true));
+    let worker_running = running.clone();
+    #[cfg_attr(not(debug_assertions), allow(unused_mut))]
+    let mut builder = thread::Builder::new();
+    #[cfg(debug_assertions)]
+    {
+        builder = builder.name(format!("xsb-{name}"));
+    }
+    #[cfg(not(debug_assertions))]
+    let _ = name;
+    let join = builder
+        .spawn(move || worker(&worker_running))
+        .map_err(|err| io_error(err, "spawn bridge worker"))?;
+    Ok(Bridge {
+        local_addr,
+        running,
+        join: Some(join),
+    })
+}
+
+/// Настраивает принятый/установленный TCP-поток: блокирующий, без
+/// Nagle, чтение с таймаутом `poll_timeout` (проверка остановки).
+fn configure(stream: &TcpStream, options: &AutoOptions) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(options.poll_timeout))
+}
+
+/// Перекладывает кадры из TCP в канал, пока поток жив и мост не
+/// остановлен.
+fn pump_from_tcp(
+    mut stream: TcpStream,
+    forward: &TcpForward,
+    running: &AtomicBool,
+    send: impl Fn(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut reader = FrameReader::default();
+    while running.load(Ordering::Acquire) && forward.is_open() {
+        if let Some(payload) = reader.poll(&mut stream)? {
+            send(payload)?;
+        }
+    }
+    Ok(())
+}
+
+/// Публикует канал `name` (его хостит сервер этой машины) на TCP-адресе
+/// `addr`. TCP-пиры обслуживаются по одному: на время подключения мост
+/// держит `AutoClient` канала, после отключения пира -- отпускает его и
+/// принимает следующего.
+pub fn expose(name: &str, addr: impl ToSocketAddrs, options: AutoOptions) -> Result<Bridge> {
+    let listener = TcpListener::bind(addr).map_err(|err| io_error(err, "bridge bind"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| io_error(err, "bridge listen"))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|err| io_error(err, "bridge listen"))?;
+    let channel = name.to_owned();
+    spawn_bridge(name, Some(local_addr), move |running| {
+        while running.load(Ordering::Acquire) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = serve_peer(&channel, stream, &options, running);
+                }
+                Err(_) => thread::sleep(options.poll_timeout),
+            }
+        }
+    })
+}
+
+fn serve_peer(
+    name: &str,
+    stream: TcpStream,
+    options: &AutoOptions,
+    running: &AtomicBool,
+) -> Result<()> {
+    configure(&stream, options).map_err(|err| io_error(err, "bridge accept"))?;
+    let forward = Arc::new(TcpForward::default());
+    forward.set(
+        stream
+            .try_clone()
+            .map_err(|err| io_error(err, "bridge accept"))?,
+    );
+    let client = AutoClient::connect(name, forward.clone(), options.clone())?;
+    let result = pump_from_tcp(stream, &forward, running, |payload| client.send(payload));
+    client.stop();
+    drop(client);
+    forward.close();
+    result
+}
+
+/// Поднимает на этой машине канал `name` и соединяет его с удалённым
+/// [`expose`] по адресу `addr`. Пока TCP-соединения нет (удалённая
+/// сторона недоступна или переподключение), сообщения локального клиента
+/// отбрасываются; соединение восстанавливается каждые `reconnect_delay`.
+pub fn attach(name: &str, addr: impl ToSocketAddrs, options: AutoOptions) -> Result<Bridge> {
+    let addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .map_err(|err| io_error(err, "bridge resolve"))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(ShmError::InvalidConfig(
+            "bridge address resolved to nothing",
+        ));
+    }
+    let forward = Arc::new(TcpForward::default());
+    let server = AutoServer::start(name, forward.clone(), options.clone())?;
+    spawn_bridge(name, None, move |running| {
+        while running.load(Ordering::Acquire) {
+            let Some(stream) = addrs
+                .iter()
+                .find_map(|addr| TcpStream::connect_timeout(addr, options.connect_timeout).ok())
+            else {
+                thread::sleep(options.reconnect_delay);
+                continue;
+            };
+            let Ok(writer) = configure(&stream, &options).and_then(|()| stream.try_clone()) else {
+                continue;
+            };
+            forward.set(writer);
+            let _ = pump_from_tcp(stream, &forward, running, |payload| server.send(payload));
+            forward.close();
+        }
+        server.stop();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedClient, SharedServer};
+    use std::time::{Duration, Instant};
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_BRIDGE_{tag}_{}", std::process::id())
+    }
+
+    /// Отдаёт по одному байту за вызов, через раз -- «таймаут».
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+        stall: bool,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.stall = !self.stall;
+            if self.stall {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            if self.pos == self.data.len() {
+                return Ok(0);
+            }
+            buf[0] = self.data[self.pos];
+            self.pos += 1;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn frames_survive_partial_reads() {
+        let mut data = Vec::new();
+        for payload in [&b"first"[..], b"", b"third frame"] {
+            write_frame(&mut data, payload).unwrap();
+        }
+        let mut stream = Trickle {
+            data,
+            pos: 0,
+            stall: false,
+        };
+        let mut reader = FrameReader::default();
+        let mut frames = Vec::new();
+        loop {
+            match reader.poll(&mut stream) {
+                Ok(Some(payload)) => frames.push(payload.to_vec()),
+                Ok(None) => {}
+                Err(err) => {
+                    assert_eq!(err, ShmError::NotConnected);
+                    break;
+                }
+            }
+        }
+        assert_eq!(frames, [&b"first"[..], b"", b"third frame"]);
+
+        let mut oversized = &((MAX_CHUNKED_MESSAGE_SIZE as u32 + 1).to_le_bytes())[..];
+        assert_eq!(
+            FrameReader::default().poll(&mut oversized),
+            Err(ShmError::Corrupted)
+        );
+    }
+
+    fn receive(
+        poll: impl Fn() -> Result<bool>,
+        receive: impl Fn(&mut Vec<u8>) -> Result<usize>,
+    ) -> Vec<u8> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut buffer = Vec::new();
+        while Instant::now() < deadline {
+            if poll().unwrap() {
+                if let Ok(len) = receive(&mut buffer) {
+                    buffer.truncate(len);
+                    return buffer;
+                }
+            }
+        }
+        panic!("nothing arrived through the bridge");
+    }
+
+    #[test]
+    fn local_client_reaches_remote_server_through_tcp() {
+        let remote_name = unique("REMOTE");
+        let local_name = unique("LOCAL");
+        let mut remote = SharedServer::start(&remote_name).unwrap();
+        let options = AutoOptions {
+            poll_timeout: Duration::from_millis(10),
+            reconnect_delay: Duration::from_millis(20),
+            ..AutoOptions::default()
+        };
+        let exposed = expose(&remote_name, "127.0.0.1:0", options.clone()).unwrap();
+        let _attached = attach(&local_name, exposed.local_addr().unwrap(), options).unwrap();
+
+        // attach подключается к expose, тот -- к удалённому серверу.
+        remote
+            .wait_for_client(Some(Duration::from_secs(5)))
+            .unwrap();
+        let client = SharedClient::connect(&local_name, Duration::from_secs(5)).unwrap();
+
+        client.send_to_server(b"ping").unwrap();
+        let poll = || remote.poll_client(Some(Duration::from_millis(20)));
+        assert_eq!(
+            receive(poll, |buffer| remote.receive_from_client(buffer)),
+            b"ping"
+        );
+
+        remote.send_to_client(b"pong").unwrap();
+        let poll = || client.poll_server(Some(Duration::from_millis(20)));
+        assert_eq!(
+            receive(poll, |buffer| client.receive_from_server(buffer)),
+            b"pong"
+        );
+    }
+
+    /// Складывает входящие сообщения канала.
+    #[derive(Default)]
+    struct Inbox(Mutex<Vec<Vec<u8>>>);
+
+    impl Inbox {
+        fn wait(&self) -> Vec<u8> {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if let Some(message) = self.0.lock().unwrap().pop() {
+                    return message;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            panic!("nothing arrived through the bridge");
+        }
+    }
+
+    impl AutoHandler for Inbox {
+        fn on_message(&self, _direction: ChannelKind, payload: &[u8]) {
+            self.0.lock().unwrap().push(payload.to_vec());
+        }
+    }
+
+    #[test]
+    fn chunked_messages_cross_the_bridge_whole() {
+        let remote_name = unique("CHUNKED_REMOTE");
+        let local_name = unique("CHUNKED_LOCAL");
+        let options = AutoOptions {
+            poll_timeout: Duration::from_millis(10),
+            reconnect_delay: Duration::from_millis(20),
+            ..AutoOptions::default()
+        };
+        let remote_inbox = Arc::new(Inbox::default());
+        let remote = AutoServer::start(&remote_name, remote_inbox.clone(), options.clone()).unwrap();
+        let exposed = expose(&remote_name, "127.0.0.1:0", options.clone()).unwrap();
+        let _attached = attach(&local_name, exposed.local_addr().unwrap(), options.clone()).unwrap();
+        let local_inbox = Arc::new(Inbox::default());
+        let local = AutoClient::connect(&local_name, local_inbox.clone(), options).unwrap();
+
+        // Больше MAX_MESSAGE_SIZE: в каналах идёт кусками, по TCP -- одним кадром.
+        let big: Vec<u8> = (0..200 * 1024).map(|i| i as u8).collect();
+        // Удалённый сервер подключён -- значит, TCP-соединение моста уже есть.
+        remote.wait_connected(Some(Duration::from_secs(5))).unwrap();
+        local.wait_connected(Some(Duration::from_secs(5))).unwrap();
+        local.send(&big).unwrap();
+        assert_eq!(remote_inbox.wait(), big);
+
+        remote.send(&big).unwrap();
+        assert_eq!(local_inbox.wait(), big);
+    }
+}
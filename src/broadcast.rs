@@ -0,0 +1,463 @@
+//! Broadcast-шина: один writer, много читателей (SPMC).
+//!
+//! Шина -- отдельная именованная секция с одним кольцом. Writer пишет
+//! кадры, не зная о читателях; каждый [`BroadcastReader`] держит
+//! собственный курсор в памяти своего процесса, поэтому N подписчиков
+//! читают одни и те же байты вместо N копий в N каналах.
+//!
+//! Writer никогда не ждёт читателей: при нехватке места он вытесняет
+//! самые старые кадры. Читатель, которого обогнали, сам переходит к
+//! самому старому уцелевшему кадру и учитывает пропуск в
+//! [`BroadcastReader::lost`] -- остальные читатели этого не замечают.
+//!
+//! Кадр: `[len: u32][seq: u64][payload]`. Порядковый номер `seq` растёт на
+//! 1 с каждым кадром, по разрыву читатель считает пропущенные сообщения.
+//! Чтение -- seqlock: payload копируется оптимистично, затем проверяется,
+//! что writer не начал перезаписывать эти байты (`claim_pos`).
+//!
+//! Событий для читателей нет (auto-reset событие будит только одного
+//! ожидающего): [`BroadcastReader::poll`] опрашивает позицию записи.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use xshm::broadcast::{BroadcastOptions, BroadcastReader, BroadcastWriter};
+//!
+//! let mut writer = BroadcastWriter::create("TICKS", BroadcastOptions::default())?;
+//! let mut reader = BroadcastReader::open("TICKS")?;
+//!
+//! writer.publish(b"EURUSD 1.0842")?;
+//! let mut buffer = Vec::new();
+//! if reader.poll(Some(Duration::from_millis(10)))? {
+//!     let len = reader.receive(&mut buffer)?;
+//!     assert_eq!(&buffer[..len], b"EURUSD 1.0842");
+//! }
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::{Result, ShmError};
+use crate::naming::broadcast_name;
+use crate::platform::{Mapping, PlatformMapping};
+
+/// 'XBCS'
+const BROADCAST_MAGIC: u32 = 0x5842_4353;
+const BROADCAST_VERSION: u32 = 1;
+
+/// Заголовок кадра: длина payload (u32) + порядковый номер (u64).
+pub const BROADCAST_FRAME_HEADER: usize = 12;
+
+/// Минимальная ёмкость кольца шины.
+pub const MIN_BROADCAST_CAPACITY: usize = 4096;
+
+#[repr(C, align(64))]
+struct BroadcastHeader {
+    magic: u32,
+    version: u32,
+    capacity: u32,
+    /// Число открытых `BroadcastReader` (для диагностики writer'а).
+    readers: AtomicU32,
+    /// Конец последнего опубликованного кадра.
+    write_pos: AtomicU64,
+    /// Конец кадра, который writer сейчас пишет: байты до него могут
+    /// быть перезаписаны. `claim_pos >= write_pos`.
+    claim_pos: AtomicU64,
+    /// Начало самого старого кадра, ещё целиком лежащего в кольце.
+    tail_pos: AtomicU64,
+    /// `seq` следующего кадра.
+    next_seq: AtomicU64,
+    reserved: [u32; 4],
+}
+
+/// Параметры шины. Задаются writer'ом, читатели берут их из секции.
+#[derive(Debug, Clone)]
+pub struct BroadcastOptions {
+    /// Размер кольца в байтах: степень двойки, не меньше
+    /// `MIN_BROADCAST_CAPACITY`. Кадр (payload + 12 байт) должен
+    /// помещаться в кольцо.
+    pub capacity: usize,
+}
+
+impl Default for BroadcastOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 2 * 1024 * 1024,
+        }
+    }
+}
+
+/// Отображённое кольцо шины (общее для writer'а и читателей).
+struct Bus {
+    mapping: Mapping,
+    capacity: usize,
+}
+
+impl Bus {
+    fn header(&self) -> &BroadcastHeader {
+        // SAFETY: секция начинается с заголовка и живёт не меньше self.
+        unsafe { &*(self.mapping.as_ptr() as *const BroadcastHeader) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        // SAFETY: кольцо сразу за заголовком, размер секции
+        // size_of::<BroadcastHeader>() + capacity.
+        unsafe {
+            self.mapping
+                .as_ptr()
+                .add(std::mem::size_of::<BroadcastHeader>())
+        }
+    }
+
+    /// Копирует `src` в кольцо начиная с позиции `pos` (с переносом).
+    fn write_at(&self, pos: u64, src: &[u8]) {
+        let start = pos as usize & (self.capacity - 1);
+        let first = src.len().min(self.capacity - start);
+        // SAFETY: обе части внутри [0, capacity): src.len() <= capacity
+        // (кадр проверен в publish), first <= capacity - start.
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), self.data().add(start), first);
+            std::ptr::copy_nonoverlapping(src.as_ptr().add(first), self.data(), src.len() - first);
+        }
+    }
+
+    /// Копирует байты кольца с позиции `pos` в `dst` (с переносом). Байты
+    /// могут быть порваны writer'ом -- вызывающий перепроверяет claim_pos.
+    fn read_at(&self, pos: u64, dst: &mut [u8]) {
+        let start = pos as usize & (self.capacity - 1);
+        let first = dst.len().min(self.capacity - start);
+        // SAFETY: как в write_at, dst.len() <= capacity.
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.data().add(start), dst.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(
+                self.data(),
+                dst.as_mut_ptr().add(first),
+                dst.len() - first,
+            );
+        }
+    }
+
+    fn frame_header(&self, pos: u64) -> (usize, u64) {
+        let mut raw = [0u8; BROADCAST_FRAME_HEADER];
+        self.read_at(pos, &mut raw);
+        let len = u32::from_le_bytes(raw[0..4].try_into().unwrap()) as usize;
+        let seq = u64::from_le_bytes(raw[4..12].try_into().unwrap());
+        (len, seq)
+    }
+}
+
+fn section_size(capacity: usize) -> usize {
+    std::mem::size_of::<BroadcastHeader>() + capacity
+}
+
+/// Единственный writer шины.
+pub struct BroadcastWriter {
+    bus: Bus,
+}
+
+impl BroadcastWriter {
+    /// Создаёт шину `name` (секция `Local\{name}_BCAST`).
+    pub fn create(name: &str, options: BroadcastOptions) -> Result<Self> {
+        let capacity = options.capacity;
+        if !capacity.is_power_of_two() || capacity < MIN_BROADCAST_CAPACITY {
+            return Err(ShmError::InvalidConfig(
+                "broadcast capacity must be a power of two >= MIN_BROADCAST_CAPACITY",
+            ));
+        }
+        if capacity > u32::MAX as usize / 2 {
+            return Err(ShmError::InvalidConfig("broadcast ring is too large"));
+        }
+
+        let mapping = Mapping::create_sized(&broadcast_name(name), section_size(capacity))?;
+        let header = mapping.as_ptr() as *mut BroadcastHeader;
+        // SAFETY: секция только что создана нами и вмещает заголовок.
+        unsafe {
+            (*header).magic = BROADCAST_MAGIC;
+            (*header).version = BROADCAST_VERSION;
+            (*header).capacity = capacity as u32;
+            (*header).readers.store(0, Ordering::Relaxed);
+            (*header).write_pos.store(0, Ordering::Relaxed);
+            (*header).claim_pos.store(0, Ordering::Relaxed);
+            (*header).tail_pos.store(0, Ordering::Relaxed);
+            (*header).next_seq.store(0, Ordering::Release);
+        }
+        Ok(Self {
+            bus: Bus { mapping, capacity },
+        })
+    }
+
+    /// Максимальный payload одного кадра.
+    pub fn max_message_size(&self) -> usize {
+        (self.bus.capacity - BROADCAST_FRAME_HEADER).min(MAX_MESSAGE_SIZE)
+    }
+
+    /// Число открытых читателей (снимок).
+    pub fn readers(&self) -> u32 {
+        self.bus.header().readers.load(Ordering::Relaxed)
+    }
+
+    /// Публикует кадр для всех читателей; возвращает его `seq`. Никогда не
+    /// блокируется: старые кадры вытесняются.
+    pub fn publish(&mut self, payload: &[u8]) -> Result<u64> {
+        if payload.is_empty() {
+            return Err(ShmError::MessageTooSmall);
+        }
+        if payload.len() > self.max_message_size() {
+            return Err(ShmError::MessageTooLarge);
+        }
+        let header = self.bus.header();
+        let frame = (BROADCAST_FRAME_HEADER + payload.len()) as u64;
+        // Позиции двигает только этот writer.
+        let head = header.write_pos.load(Ordering::Relaxed);
+        let end = head + frame;
+
+        let mut tail = header.tail_pos.load(Ordering::Relaxed);
+        while end - tail > self.bus.capacity as u64 {
+            let (len, _) = self.bus.frame_header(tail);
+            tail += (BROADCAST_FRAME_HEADER + len) as u64;
+        }
+        header.tail_pos.store(tail, Ordering::Release);
+
+        // Seqlock: claim виден раньше любых байт нового кадра.
+        header.claim_pos.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        let seq = header.next_seq.load(Ordering::Relaxed);
+        let mut frame_header = [0u8; BROADCAST_FRAME_HEADER];
+        frame_header[0..4].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame_header[4..12].copy_from_slice(&seq.to_le_bytes());
+        self.bus.write_at(head, &frame_header);
+        self.bus
+            .write_at(head + BROADCAST_FRAME_HEADER as u64, payload);
+
+        header.next_seq.store(seq + 1, Ordering::Relaxed);
+        header.write_pos.store(end, Ordering::Release);
+        Ok(seq)
+    }
+}
+
+/// Читатель шины с собственным курсором.
+pub struct BroadcastReader {
+    bus: Bus,
+    cursor: u64,
+    next_seq: u64,
+    lost: u64,
+}
+
+impl BroadcastReader {
+    /// Подключается к шине. Читатель видит только кадры, опубликованные
+    /// после подключения.
+    pub fn open(name: &str) -> Result<Self> {
+        let section = broadcast_name(name);
+        let capacity = {
+            let probe = Mapping::open_sized(&section, std::mem::size_of::<BroadcastHeader>())?;
+            // SAFETY: отображено не меньше заголовка.
+            let header = unsafe { &*(probe.as_ptr() as *const BroadcastHeader) };
+            if header.magic != BROADCAST_MAGIC {
+                return Err(ShmError::Corrupted);
+            }
+            if header.version != BROADCAST_VERSION {
+                return Err(ShmError::HandshakeFailed);
+            }
+            header.capacity as usize
+        };
+        if !capacity.is_power_of_two() || capacity < MIN_BROADCAST_CAPACITY {
+            return Err(ShmError::Corrupted);
+        }
+        let bus = Bus {
+            mapping: Mapping::open_sized(&section, section_size(capacity))?,
+            capacity,
+        };
+        bus.header().readers.fetch_add(1, Ordering::Relaxed);
+        let cursor = bus.header().write_pos.load(Ordering::Acquire);
+        // Может оказаться на 1 впереди кадра в cursor (writer между
+        // next_seq и write_pos) -- тогда пропуск просто не насчитается.
+        let next_seq = bus.header().next_seq.load(Ordering::Relaxed);
+        Ok(Self {
+            bus,
+            cursor,
+            next_seq,
+            lost: 0,
+        })
+    }
+
+    /// Сколько кадров этот читатель пропустил, отстав от writer'а.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Есть ли непрочитанные кадры.
+    pub fn has_pending(&self) -> bool {
+        self.bus.header().write_pos.load(Ordering::Acquire) != self.cursor
+    }
+
+    /// Ждёт кадра не дольше `timeout` (`None` -- бесконечно), опрашивая
+    /// позицию записи.
+    pub fn poll(&self, timeout: Option<Duration>) -> Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut spins = 0u32;
+        loop {
+            if self.has_pending() {
+                return Ok(true);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(false);
+            }
+            if spins < 1000 {
+                spins += 1;
+                std::hint::spin_loop();
+            } else {
+                std::thread::sleep(Duration::from_micros(100));
+            }
+        }
+    }
+
+    /// Читает следующий кадр в `buffer`, возвращает длину payload.
+    /// `ShmError::QueueEmpty`, если новых кадров нет.
+    pub fn receive(&mut self, buffer: &mut Vec<u8>) -> Result<usize> {
+        let header = self.bus.header();
+        loop {
+            let write = header.write_pos.load(Ordering::Acquire);
+            if self.cursor == write {
+                return Err(ShmError::QueueEmpty);
+            }
+            if write - self.cursor > self.bus.capacity as u64 {
+                // Обогнали: переходим к самому старому уцелевшему кадру.
+                self.cursor = header.tail_pos.load(Ordering::Acquire);
+                continue;
+            }
+
+            let (len, seq) = self.bus.frame_header(self.cursor);
+            let frame = (BROADCAST_FRAME_HEADER + len) as u64;
+            let valid = (1..=MAX_MESSAGE_SIZE).contains(&len) && self.cursor + frame <= write;
+            if valid {
+                buffer.resize(len, 0);
+                self.bus.read_at(
+                    self.cursor + BROADCAST_FRAME_HEADER as u64,
+                    &mut buffer[..len],
+                );
+            }
+
+            // Seqlock: байты не тронуты, если writer не заявил позицию
+            // дальше cursor + capacity.
+            fence(Ordering::Acquire);
+            let claim = header.claim_pos.load(Ordering::Relaxed);
+            if claim - self.cursor > self.bus.capacity as u64 {
+                self.cursor = header.tail_pos.load(Ordering::Acquire);
+                continue;
+            }
+            if !valid {
+                return Err(ShmError::Corrupted);
+            }
+
+            self.lost += seq.saturating_sub(self.next_seq);
+            self.next_seq = seq + 1;
+            self.cursor += frame;
+            return Ok(len);
+        }
+    }
+}
+
+impl Drop for BroadcastReader {
+    fn drop(&mut self) {
+        self.bus.header().readers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_BCAST_{tag}_{}", std::process::id())
+    }
+
+    fn small() -> BroadcastOptions {
+        BroadcastOptions {
+            capacity: MIN_BROADCAST_CAPACITY,
+        }
+    }
+
+    #[test]
+    fn every_reader_sees_every_frame() {
+        let name = unique("FANOUT");
+        let mut writer = BroadcastWriter::create(&name, small()).unwrap();
+        let mut a = BroadcastReader::open(&name).unwrap();
+        let mut b = BroadcastReader::open(&name).unwrap();
+        assert_eq!(writer.readers(), 2);
+
+        for i in 0..10u8 {
+            writer.publish(&[i; 100]).unwrap();
+        }
+        let mut buffer = Vec::new();
+        for reader in [&mut a, &mut b] {
+            for i in 0..10u8 {
+                let len = reader.receive(&mut buffer).unwrap();
+                assert_eq!(&buffer[..len], &[i; 100]);
+            }
+            assert_eq!(reader.receive(&mut buffer), Err(ShmError::QueueEmpty));
+            assert_eq!(reader.lost(), 0);
+        }
+
+        drop(b);
+        assert_eq!(writer.readers(), 1);
+    }
+
+    #[test]
+    fn slow_reader_skips_only_its_own_frames() {
+        let name = unique("LAPPED");
+        let mut writer = BroadcastWriter::create(&name, small()).unwrap();
+        let mut slow = BroadcastReader::open(&name).unwrap();
+        let mut fast = BroadcastReader::open(&name).unwrap();
+
+        let mut buffer = Vec::new();
+        // 100 кадров по ~1 КБ при кольце 4 КБ: медленный читатель отстаёт.
+        for i in 0..100u32 {
+            writer.publish(&[i as u8; 1000]).unwrap();
+            let len = fast.receive(&mut buffer).unwrap();
+            assert_eq!(&buffer[..len], &[i as u8; 1000]);
+        }
+        assert_eq!(fast.lost(), 0);
+
+        let mut received = Vec::new();
+        while let Ok(len) = slow.receive(&mut buffer) {
+            received.push(buffer[..len][0]);
+        }
+        // Уцелели только последние кадры, и они идут по порядку.
+        assert!(!received.is_empty() && received.len() < 5);
+        assert_eq!(*received.last().unwrap(), 99);
+        assert!(received.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert_eq!(slow.lost(), 100 - received.len() as u64);
+    }
+
+    #[test]
+    fn late_reader_starts_at_the_head() {
+        let name = unique("LATE");
+        let mut writer = BroadcastWriter::create(&name, small()).unwrap();
+        writer.publish(b"before").unwrap();
+        let mut reader = BroadcastReader::open(&name).unwrap();
+        assert!(!reader.poll(Some(Duration::from_millis(1))).unwrap());
+
+        writer.publish(b"after").unwrap();
+        assert!(reader.poll(Some(Duration::ZERO)).unwrap());
+        let mut buffer = Vec::new();
+        let len = reader.receive(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"after");
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected() {
+        let name = unique("SIZES");
+        assert!(matches!(
+            BroadcastWriter::create(&name, BroadcastOptions { capacity: 5000 }),
+            Err(ShmError::InvalidConfig(_))
+        ));
+        let mut writer = BroadcastWriter::create(&name, small()).unwrap();
+        assert_eq!(writer.publish(b""), Err(ShmError::MessageTooSmall));
+        assert_eq!(
+            writer.publish(&vec![0; MIN_BROADCAST_CAPACITY]),
+            Err(ShmError::MessageTooLarge)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Токен отмены worker-потоков.
+//!
+//! Флаг плюс локальное событие: handle события входит в `wait_any` рядом
+//! с событиями канала, паузы между переподключениями ждут на нём же, и
+//! `cancel()` будит worker сразу, а не по истечении `poll_timeout` или
+//! очередного шага опроса.
+//!
+//! Токены образуют дерево: [`CancellationToken::child`] отменяется вместе
+//! с родителем, но не наоборот. Auto/Multi/Dispatch берут дочерний токен
+//! от переданного в опциях (`cancel`), поэтому хост может остановить все
+//! свои каналы одним `cancel()` корня, а `stop()` отдельного канала не
+//! задевает соседей.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use xshm::{AutoHandler, AutoOptions, AutoServer, CancellationToken};
+//!
+//! struct Quiet;
+//! impl AutoHandler for Quiet {}
+//!
+//! let root = CancellationToken::new()?;
+//! let options = AutoOptions::builder().cancel(Some(root.clone())).build()?;
+//! let _a = AutoServer::start("app_a", Arc::new(Quiet), options.clone())?;
+//! let _b = AutoServer::start("app_b", Arc::new(Quiet), options)?;
+//! root.cancel(); // оба worker'а выходят, не дожидаясь poll_timeout
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::error::{Result, ShmError};
+use crate::platform::{EventHandle, PlatformEvent};
+
+struct Node {
+    cancelled: AtomicBool,
+    event: EventHandle,
+    children: Mutex<Vec<Weak<Node>>>,
+}
+
+/// Клонируемый токен отмены; клоны -- один и тот же токен.
+#[derive(Clone)]
+pub struct CancellationToken {
+    node: Arc<Node>,
+}
+
+impl CancellationToken {
+    /// Корневой токен.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            node: Arc::new(Node {
+                cancelled: AtomicBool::new(false),
+                event: EventHandle::create_local()?,
+                children: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Дочерний токен: отменяется вместе с этим (или сразу, если этот уже
+    /// отменён), его собственная отмена родителя не трогает.
+    pub fn child(&self) -> Result<Self> {
+        let child = Self::new()?;
+        let mut children = self.node.children.lock().unwrap();
+        // Флаг читается под lock-ом: `cancel()` выставляет его до того, как
+        // забрать список, поэтому ребёнок либо попадёт в список, либо
+        // увидит отмену здесь.
+        if self.is_cancelled() {
+            drop(children);
+            child.cancel();
+        } else {
+            children.retain(|weak| weak.strong_count() > 0);
+            children.push(Arc::downgrade(&child.node));
+        }
+        Ok(child)
+    }
+
+    /// Отменяет токен и всех потомков. Повторный вызов -- no-op.
+    pub fn cancel(&self) {
+        if self.node.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let _ = self.node.event.set();
+        let children = std::mem::take(&mut *self.node.children.lock().unwrap());
+        for child in children.iter().filter_map(Weak::upgrade) {
+            Self { node: child }.cancel();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.node.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Токен worker'а: дочерний от заданного в опциях, иначе новый корень.
+    pub(crate) fn for_worker(parent: Option<&Self>) -> Result<Self> {
+        match parent {
+            Some(parent) => parent.child(),
+            None => Self::new(),
+        }
+    }
+
+    /// Handle для `platform::wait_any`: сработавший индекс токена значит
+    /// «отменён» (флаг уже выставлен). Событие auto-reset, поэтому ждать
+    /// на нём должен один поток -- у каждого worker'а свой токен.
+    pub(crate) fn raw_handle(&self) -> isize {
+        self.node.event.raw_handle()
+    }
+
+    /// Тело worker-потока под `catch_unwind`: паника не убивает канал
+    /// молча, а отменяет токен (endpoint переходит в остановленное
+    /// состояние) и уходит в `report` как `ShmError::WorkerPanicked`.
+    /// Ловится только раскручиваемая паника: с `panic = "abort"` сюда не
+    /// доходит.
+    pub(crate) fn run_worker(&self, body: impl FnOnce(), report: impl FnOnce(ShmError)) {
+        let Err(payload) = panic::catch_unwind(AssertUnwindSafe(body)) else {
+            return;
+        };
+        self.cancel();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|text| text.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_owned());
+        // Паника в самом on_error уже некому сообщать.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            report(ShmError::WorkerPanicked(message))
+        }));
+    }
+
+    /// Пауза до `delay`, прерываемая `cancel()`. `false` -- отменён.
+    pub(crate) fn sleep(&self, delay: Duration) -> bool {
+        if !self.is_cancelled() && !delay.is_zero() {
+            let _ = self.node.event.wait(Some(delay));
+        }
+        !self.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn cancel_interrupts_sleep_and_wait_any() {
+        let token = CancellationToken::new().unwrap();
+        assert!(token.sleep(Duration::from_millis(1)));
+
+        let canceller = std::thread::spawn({
+            let token = token.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                token.cancel();
+            }
+        });
+        let start = Instant::now();
+        assert!(!token.sleep(Duration::from_secs(10)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        canceller.join().unwrap();
+
+        // Отмена липкая: и пауза, и флаг видны без ожидания.
+        assert!(token.is_cancelled());
+        assert!(!token.sleep(Duration::from_secs(10)));
+
+        let fresh = CancellationToken::new().unwrap();
+        fresh.cancel();
+        let start = Instant::now();
+        assert_eq!(
+            crate::platform::wait_any(&[fresh.raw_handle()], Some(Duration::from_secs(10))),
+            Ok(Some(0))
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn cancel_propagates_down_the_tree_only() {
+        let root = CancellationToken::new().unwrap();
+        let child = root.child().unwrap();
+        let grandchild = child.child().unwrap();
+        let sibling = root.child().unwrap();
+
+        child.cancel();
+        assert!(child.is_cancelled() && grandchild.is_cancelled());
+        assert!(!root.is_cancelled() && !sibling.is_cancelled());
+
+        root.cancel();
+        assert!(sibling.is_cancelled());
+        assert!(!sibling.sleep(Duration::from_secs(10)));
+        assert!(root.child().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn worker_panic_cancels_and_is_reported() {
+        let token = CancellationToken::new().unwrap();
+        let mut reported = None;
+        token.run_worker(|| {}, |err| reported = Some(err));
+        assert!(reported.is_none() && !token.is_cancelled());
+
+        token.run_worker(|| panic!("poisoned slot {}", 7), |err| reported = Some(err));
+        assert!(token.is_cancelled());
+        assert_eq!(
+            reported,
+            Some(ShmError::WorkerPanicked("poisoned slot 7".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Общий интерфейс серверов Auto/Multi/Dispatch.
+//!
+//! Хост, который держит серверы разных режимов вперемешку, управляет ими
+//! через `Box<dyn ChannelServer>`: остановка, состояние, счётчики,
+//! перечисление клиентов и отправка. У `AutoServer` ровно один пир --
+//! клиент с id [`AUTO_PEER_ID`].
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use xshm::{AutoHandler, AutoOptions, AutoServer, ChannelServer};
+//! use xshm::{MultiHandler, MultiOptions, MultiServer};
+//!
+//! struct Quiet;
+//! impl AutoHandler for Quiet {}
+//!
+//! struct Echo;
+//! impl MultiHandler for Echo {
+//!     fn on_client_connect(&self, _client_id: u32) {}
+//!     fn on_client_disconnect(&self, _client_id: u32) {}
+//!     fn on_message(&self, _client_id: u32, _data: &[u8]) {}
+//! }
+//!
+//! // `MultiServer::start`/`DispatchServer::start` отдают `Arc` -- он тоже
+//! // `ChannelServer`.
+//! let servers: Vec<Box<dyn ChannelServer>> = vec![
+//!     Box::new(AutoServer::start("app_auto", Arc::new(Quiet), AutoOptions::default())?),
+//!     Box::new(MultiServer::start("app_multi", Arc::new(Echo), MultiOptions::default())?),
+//! ];
+//! for server in &servers {
+//!     println!("{}: {:?}", server.kind().as_str(), server.connected_clients());
+//!     server.broadcast(b"shutting down")?;
+//!     server.stop();
+//! }
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::Arc;
+
+use crate::auto::{AutoServer, AutoStatsSnapshot};
+use crate::dispatch::DispatchServer;
+use crate::error::{Result, ShmError};
+use crate::multi::MultiServer;
+use crate::registry::ServiceKind;
+
+/// Id единственного клиента `AutoServer` в терминах [`ChannelServer`].
+pub const AUTO_PEER_ID: u32 = 0;
+
+/// Сервер любого режима.
+pub trait ChannelServer: Send + Sync {
+    fn kind(&self) -> ServiceKind;
+
+    /// Останавливает worker (см. `stop` конкретного сервера).
+    fn stop(&self);
+
+    /// `false` после `stop()`, отмены токена или паники worker'а.
+    fn is_running(&self) -> bool;
+
+    /// Счётчики канала; есть только у `AutoServer`.
+    fn stats(&self) -> Option<AutoStatsSnapshot> {
+        None
+    }
+
+    /// Id подключённых сейчас клиентов.
+    fn connected_clients(&self) -> Vec<u32>;
+
+    /// Отправка одному клиенту; неизвестный или отключённый id --
+    /// `ShmError::NotConnected`.
+    fn send_to(&self, client_id: u32, data: &[u8]) -> Result<()>;
+
+    /// Рассылка всем подключённым клиентам; возвращает число адресатов.
+    fn broadcast(&self, data: &[u8]) -> Result<u32>;
+}
+
+impl<T: ChannelServer + ?Sized> ChannelServer for Arc<T> {
+    fn kind(&self) -> ServiceKind {
+        (**self).kind()
+    }
+
+    fn stop(&self) {
+        (**self).stop();
+    }
+
+    fn is_running(&self) -> bool {
+        (**self).is_running()
+    }
+
+    fn stats(&self) -> Option<AutoStatsSnapshot> {
+        (**self).stats()
+    }
+
+    fn connected_clients(&self) -> Vec<u32> {
+        (**self).connected_clients()
+    }
+
+    fn send_to(&self, client_id: u32, data: &[u8]) -> Result<()> {
+        (**self).send_to(client_id, data)
+    }
+
+    fn broadcast(&self, data: &[u8]) -> Result<u32> {
+        (**self).broadcast(data)
+    }
+}
+
+impl ChannelServer for AutoServer {
+    fn kind(&self) -> ServiceKind {
+        ServiceKind::Auto
+    }
+
+    fn stop(&self) {
+        AutoServer::stop(self);
+    }
+
+    fn is_running(&self) -> bool {
+        !self.is_stopped()
+    }
+
+    fn stats(&self) -> Option<AutoStatsSnapshot> {
+        Some(AutoServer::stats(self))
+    }
+
+    fn connected_clients(&self) -> Vec<u32> {
+        let stats = AutoServer::stats(self);
+        if stats.connects > stats.disconnects {
+            vec![AUTO_PEER_ID]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn send_to(&self, client_id: u32, data: &[u8]) -> Result<()> {
+        // Без пира `send` копил бы сообщение в очереди -- здесь, как у
+        // Multi/Dispatch, отключённый клиент -- ошибка.
+        if !self.connected_clients().contains(&client_id) {
+            return Err(ShmError::NotConnected);
+        }
+        self.send(data)
+    }
+
+    fn broadcast(&self, data: &[u8]) -> Result<u32> {
+        match self.send_to(AUTO_PEER_ID, data) {
+            Ok(()) => Ok(1),
+            Err(ShmError::NotConnected) => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl ChannelServer for MultiServer {
+    fn kind(&self) -> ServiceKind {
+        ServiceKind::Multi
+    }
+
+    fn stop(&self) {
+        MultiServer::stop(self);
+    }
+
+    fn is_running(&self) -> bool {
+        !self.is_stopped()
+    }
+
+    fn connected_clients(&self) -> Vec<u32> {
+        MultiServer::connected_clients(self)
+    }
+
+    fn send_to(&self, client_id: u32, data: &[u8]) -> Result<()> {
+        MultiServer::send_to(self, client_id, data)
+    }
+
+    fn broadcast(&self, data: &[u8]) -> Result<u32> {
+        MultiServer::broadcast(self, data)
+    }
+}
+
+impl ChannelServer for DispatchServer {
+    fn kind(&self) -> ServiceKind {
+        ServiceKind::Dispatch
+    }
+
+    fn stop(&self) {
+        DispatchServer::stop(self);
+    }
+
+    fn is_running(&self) -> bool {
+        !self.is_stopped()
+    }
+
+    fn connected_clients(&self) -> Vec<u32> {
+        DispatchServer::connected_clients(self)
+    }
+
+    fn send_to(&self, client_id: u32, data: &[u8]) -> Result<()> {
+        DispatchServer::send_to(self, client_id, data)
+    }
+
+    fn broadcast(&self, data: &[u8]) -> Result<u32> {
+        DispatchServer::broadcast(self, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::{AutoClient, AutoHandler, AutoOptions};
+    use crate::dispatch::{ClientRegistration, DispatchHandler, DispatchOptions};
+    use crate::multi::{MultiHandler, MultiOptions};
+    use std::time::{Duration, Instant};
+
+    struct Quiet;
+
+    impl AutoHandler for Quiet {}
+
+    impl MultiHandler for Quiet {
+        fn on_client_connect(&self, _client_id: u32) {}
+        fn on_client_disconnect(&self, _client_id: u32) {}
+        fn on_message(&self, _client_id: u32, _data: &[u8]) {}
+    }
+
+    impl DispatchHandler for Quiet {
+        fn on_client_connect(&self, _client_id: u32, _info: &ClientRegistration) {}
+        fn on_client_disconnect(&self, _client_id: u32) {}
+        fn on_message(&self, _client_id: u32, _data: &[u8]) {}
+    }
+
+    #[test]
+    fn servers_of_every_kind_are_managed_uniformly() {
+        let pid = std::process::id();
+        let auto_name = format!("TEST_CHANNEL_AUTO_{pid}");
+        let servers: Vec<Box<dyn ChannelServer>> = vec![
+            Box::new(
+                AutoServer::start(&auto_name, Arc::new(Quiet), AutoOptions::default()).unwrap(),
+            ),
+            Box::new(
+                MultiServer::start(
+                    &format!("TEST_CHANNEL_MULTI_{pid}"),
+                    Arc::new(Quiet),
+                    MultiOptions::default(),
+                )
+                .unwrap(),
+            ),
+            Box::new(
+                DispatchServer::start(
+                    &format!("TEST_CHANNEL_DISPATCH_{pid}"),
+                    Arc::new(Quiet),
+                    DispatchOptions::default(),
+                )
+                .unwrap(),
+            ),
+        ];
+        let kinds: Vec<_> = servers.iter().map(|server| server.kind()).collect();
+        assert_eq!(
+            kinds,
+            [ServiceKind::Auto, ServiceKind::Multi, ServiceKind::Dispatch]
+        );
+        for server in &servers {
+            assert!(server.is_running());
+            assert!(server.connected_clients().is_empty());
+            assert_eq!(server.broadcast(b"nobody"), Ok(0));
+            assert_eq!(server.send_to(7, b"nobody"), Err(ShmError::NotConnected));
+            assert_eq!(server.stats().is_some(), server.kind() == ServiceKind::Auto);
+        }
+
+        let client =
+            AutoClient::connect(&auto_name, Arc::new(Quiet), AutoOptions::default()).unwrap();
+        let start = Instant::now();
+        while servers[0].connected_clients().is_empty() && start.elapsed() < Duration::from_secs(5)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(servers[0].connected_clients(), [AUTO_PEER_ID]);
+        assert_eq!(servers[0].broadcast(b"hello"), Ok(1));
+        while client.stats().received_messages < 1 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.stats().received_messages, 1);
+
+        for server in &servers {
+            server.stop();
+        }
+        while servers.iter().any(|server| server.is_running())
+            && start.elapsed() < Duration::from_secs(5)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(servers.iter().all(|server| !server.is_running()));
+    }
+}
@@ -0,0 +1,193 @@
+//! Сообщения больше `MAX_MESSAGE_SIZE` в auto-режиме.
+//!
+//! Сообщение режется на куски -- кадры с `MSG_FLAG_CHUNK`, в payload'е
+//! каждого заголовок (полная длина сообщения и смещение куска, u32 LE) и
+//! данные. Режет отправитель, только если пир согласовал
+//! `FEATURE_CHUNKING`: старая сборка отдала бы куски handler'у как
+//! отдельные сообщения. Куски пишутся без вытеснения (кольцо полно --
+//! `QueueFull`, отправка продолжается с того же куска), но более поздние
+//! обычные сообщения и сроки годности могут выбить кусок из кольца --
+//! получатель тогда отбрасывает недособранное сообщение целиком.
+
+use crate::constants::{CHUNK_HEADER_SIZE, MAX_CHUNKED_MESSAGE_SIZE, MAX_MESSAGE_SIZE};
+use crate::error::{Result, ShmError};
+use crate::ring::WriteOutcome;
+
+/// Данных в одном куске.
+const CHUNK_DATA_SIZE: usize = MAX_MESSAGE_SIZE - CHUNK_HEADER_SIZE;
+
+/// Пишет куски `message` с `*sent` байт до конца; `*sent` двигается за
+/// каждым записанным куском, так что после `QueueFull` вызов с тем же
+/// `sent` продолжает с места остановки.
+pub(crate) fn write_chunks(
+    message: &[u8],
+    sent: &mut usize,
+    mut write: impl FnMut(&[u8]) -> Result<WriteOutcome>,
+) -> Result<WriteOutcome> {
+    if message.len() > MAX_CHUNKED_MESSAGE_SIZE {
+        return Err(ShmError::MessageTooLarge);
+    }
+    let mut total = WriteOutcome {
+        overwritten: 0,
+        was_empty: false,
+        free_bytes_after: 0,
+        queue_len_after: 0,
+    };
+    let mut chunk = Vec::with_capacity(MAX_MESSAGE_SIZE);
+    while *sent < message.len() {
+        let data = &message[*sent..message.len().min(*sent + CHUNK_DATA_SIZE)];
+        chunk.clear();
+        chunk.extend_from_slice(&(message.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&(*sent as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        let outcome = write(&chunk)?;
+        total.overwritten += outcome.overwritten;
+        total.was_empty |= outcome.was_empty;
+        total.free_bytes_after = outcome.free_bytes_after;
+        total.queue_len_after = outcome.queue_len_after;
+        *sent += data.len();
+    }
+    Ok(total)
+}
+
+/// Сборка сообщения из кусков одного соединения.
+#[derive(Default)]
+pub(crate) struct Reassembly {
+    message: Vec<u8>,
+    /// Полная длина собираемого сообщения, 0 -- ничего не собирается.
+    total: usize,
+}
+
+impl Reassembly {
+    /// Принимает кусок; `Ok(Some(_))` -- сообщение собрано. Кусок не с
+    /// того места (предыдущий выбит из кольца) сбрасывает недособранное.
+    /// Заголовок, которого отправитель написать не мог, --
+    /// `ShmError::Corrupted`.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some((header, data)) = chunk.split_first_chunk::<CHUNK_HEADER_SIZE>() else {
+            return Err(self.fail());
+        };
+        let total = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let offset = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+        if total > MAX_CHUNKED_MESSAGE_SIZE || data.is_empty() || offset + data.len() > total {
+            return Err(self.fail());
+        }
+        if offset == 0 {
+            self.message.clear();
+            self.message.reserve(total);
+            self.total = total;
+        } else if offset != self.message.len() || total != self.total {
+            self.reset();
+            return Ok(None);
+        }
+        self.message.extend_from_slice(data);
+        if self.message.len() < total {
+            return Ok(None);
+        }
+        self.total = 0;
+        Ok(Some(std::mem::take(&mut self.message)))
+    }
+
+    /// Отбрасывает недособранное сообщение.
+    fn reset(&mut self) {
+        self.message = Vec::new();
+        self.total = 0;
+    }
+
+    fn fail(&mut self) -> ShmError {
+        self.reset();
+        ShmError::Corrupted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks_of(message: &[u8]) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        let mut sent = 0;
+        write_chunks(message, &mut sent, |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(WriteOutcome {
+                overwritten: 0,
+                was_empty: chunks.len() == 1,
+                free_bytes_after: 0,
+                queue_len_after: chunks.len() as u32,
+            })
+        })
+        .unwrap();
+        assert_eq!(sent, message.len());
+        chunks
+    }
+
+    #[test]
+    fn chunks_reassemble_and_gaps_drop_the_message() {
+        let message: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let chunks = chunks_of(&message);
+        let last = chunks.len() - 1;
+        assert_eq!(chunks.len(), message.len().div_ceil(CHUNK_DATA_SIZE));
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_MESSAGE_SIZE));
+
+        let mut reassembly = Reassembly::default();
+        for chunk in &chunks[..last] {
+            assert_eq!(reassembly.push(chunk), Ok(None));
+        }
+        assert_eq!(reassembly.push(&chunks[last]), Ok(Some(message.clone())));
+
+        // Выбитый кусок: хвост без начала не собирается, следующее
+        // сообщение -- собирается.
+        assert_eq!(reassembly.push(&chunks[0]), Ok(None));
+        assert_eq!(reassembly.push(&chunks[2]), Ok(None));
+        assert_eq!(reassembly.push(&chunks[last]), Ok(None));
+        for chunk in &chunks[..last] {
+            assert_eq!(reassembly.push(chunk), Ok(None));
+        }
+        assert_eq!(reassembly.push(&chunks[last]), Ok(Some(message)));
+
+        assert_eq!(reassembly.push(b"short"), Err(ShmError::Corrupted));
+        let mut oversized = chunks[0].clone();
+        oversized[..4].copy_from_slice(&(MAX_CHUNKED_MESSAGE_SIZE as u32 + 1).to_le_bytes());
+        assert_eq!(reassembly.push(&oversized), Err(ShmError::Corrupted));
+    }
+
+    #[test]
+    fn write_resumes_after_queue_full() {
+        let message = vec![7u8; 3 * CHUNK_DATA_SIZE];
+        let mut written = Vec::new();
+        let mut sent = 0;
+        let mut room = 2;
+        let full = write_chunks(&message, &mut sent, |chunk| {
+            if room == 0 {
+                return Err(ShmError::QueueFull);
+            }
+            room -= 1;
+            written.push(chunk.to_vec());
+            Ok(WriteOutcome {
+                overwritten: 0,
+                was_empty: false,
+                free_bytes_after: 0,
+                queue_len_after: 0,
+            })
+        });
+        assert_eq!(full.err(), Some(ShmError::QueueFull));
+        assert_eq!(sent, 2 * CHUNK_DATA_SIZE);
+
+        write_chunks(&message, &mut sent, |chunk| {
+            written.push(chunk.to_vec());
+            Ok(WriteOutcome {
+                overwritten: 0,
+                was_empty: false,
+                free_bytes_after: 0,
+                queue_len_after: 0,
+            })
+        })
+        .unwrap();
+        let mut reassembly = Reassembly::default();
+        let complete: Vec<_> = written
+            .iter()
+            .filter_map(|chunk| reassembly.push(chunk).unwrap())
+            .collect();
+        assert_eq!(complete, [message]);
+    }
+}
@@ -1,23 +1,122 @@
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::constants::{
-    HANDSHAKE_CLIENT_HELLO, HANDSHAKE_IDLE, HANDSHAKE_SERVER_READY, SHARED_MAGIC, SHARED_VERSION,
+    version_compatible, DEFAULT_FEATURES, HANDSHAKE_CLIENT_HELLO, HANDSHAKE_IDLE,
+    HANDSHAKE_SERVER_READY, LAYOUT_FLAG_CONTROL_RINGS, MSG_USER_FLAGS_MASK,
+    RESERVED_CLIENT_DISCONNECT_REASON_INDEX, RESERVED_CLIENT_FEATURES_INDEX,
+    RESERVED_CLIENT_HEARTBEAT_INDEX, RESERVED_CLIENT_PID_INDEX, RESERVED_CLIENT_VERSION_INDEX,
+    RESERVED_LAYOUT_FLAGS_INDEX, RESERVED_NEGOTIATED_FEATURES_INDEX,
+    RESERVED_SERVER_DISCONNECT_REASON_INDEX, RESERVED_SERVER_HEARTBEAT_INDEX,
+    RESERVED_SERVER_PID_INDEX, SHARED_MAGIC, SHARED_VERSION, SUPPORTED_FEATURES,
 };
-use crate::error::{Result, ShmError};
+use crate::diagnostics::{self, ChannelDump};
+use crate::disconnect::DisconnectReason;
+use crate::error::{ensure_config, Result, ShmError};
 use crate::events::SharedEvents;
-use crate::naming::mapping_name;
-use crate::ring::{RingBuffer, WriteOutcome};
+use crate::handles::{self, Received, ReceivedMessage};
+use crate::latency::LatencyHistogram;
+use crate::layout::{dual_mapping_size, shared_mapping_size};
+use crate::naming::{self, mapping_name, Namespace};
+use crate::poll::{self, PollResult};
+use crate::retry::RetryPolicy;
+use crate::ring::{
+    ChannelStats, CreditWindow, RingBuffer, RingStatus, WriteGuard, WriteOutcome, WritePolicy,
+};
+use crate::server::ensure_ring_capacity;
 use crate::shared::SharedView;
-use crate::win::Mapping;
+use crate::split::{self, RecvHalf, SendHalf};
+use crate::state::SharedState;
+use crate::platform::{self, Backend, Mapping, PlatformEvent, PlatformMapping};
+
+/// Опции [`SharedClient::connect_with_options`].
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+    /// Namespace сервера (`ServerOptions::namespace`).
+    pub namespace: Namespace,
+    /// Предлагаемые серверу возможности (`FEATURE_*`), см.
+    /// [`SharedClient::connect_with_features`].
+    pub features: u32,
+    /// Сколько ждать ответа сервера на handshake.
+    pub timeout: Duration,
+    /// Ожидаемый размер кольца (см. `ServerOptions::ring_capacity`).
+    pub ring_capacity: Option<usize>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            namespace: Namespace::Session,
+            features: DEFAULT_FEATURES,
+            timeout: Duration::from_secs(2),
+            ring_capacity: None,
+        }
+    }
+}
+
+/// Построитель подключения ([`SharedClient::builder`]); незаданные поля
+/// -- из `ClientOptions::default()`.
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    name: String,
+    options: ClientOptions,
+}
+
+impl ClientBuilder {
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    pub fn features(mut self, features: u32) -> Self {
+        self.options.features = features;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    pub fn ring_capacity(mut self, ring_capacity: usize) -> Self {
+        self.options.ring_capacity = Some(ring_capacity);
+        self
+    }
+
+    /// Собранные опции.
+    pub fn options(&self) -> &ClientOptions {
+        &self.options
+    }
+
+    pub fn connect(self) -> Result<SharedClient> {
+        SharedClient::connect_with_options(&self.name, &self.options)
+    }
+
+    /// `connect`, повторяемый по `policy` (см.
+    /// [`SharedClient::connect_with_retry`]).
+    pub fn connect_with_retry(self, policy: &RetryPolicy) -> Result<SharedClient> {
+        policy.run(|| SharedClient::connect_with_options(&self.name, &self.options))
+    }
+}
 
 pub struct SharedClient {
     _name: String,
+    /// Backend объектов канала (см. `SharedServer`).
+    backend: Backend,
     _mapping: Mapping,
     view: SharedView,
-    events: SharedEvents,
+    events: Option<SharedEvents>, // None у клиента по handle'у секции
     ring_tx: RingBuffer,
     ring_rx: RingBuffer,
+    /// Управляющие кольца `(tx, rx)`, если сервер создал их.
+    control: Option<(RingBuffer, RingBuffer)>,
+    state: Option<SharedState>,
+    /// Возможности, согласованные с сервером (`FEATURE_*`).
+    negotiated_features: u32,
+    /// Generation control block на момент подключения: другое значение
+    /// там -- сервер сбросил канал (`ShmError::ConnectionReset`).
+    connection_gen: u32,
     connected: bool,
 }
 
@@ -25,66 +124,173 @@ unsafe impl Send for SharedClient {}
 
 impl SharedClient {
     pub fn connect(name: &str, timeout: Duration) -> Result<Self> {
+        Self::connect_with_features(name, timeout, DEFAULT_FEATURES)
+    }
+
+    /// Как `connect`, но предлагает серверу только возможности `features`
+    /// (`FEATURE_*`); итог согласования -- `negotiated_features()`.
+    pub fn connect_with_features(name: &str, timeout: Duration, features: u32) -> Result<Self> {
+        Self::connect_in_with_features(name, Namespace::Session, timeout, features)
+    }
+
+    /// Подключение к серверу, запущенному в `namespace`
+    /// (`ServerOptions::namespace`): например, процесс пользователя к
+    /// сервису сессии 0 через [`Namespace::Global`].
+    pub fn connect_in(name: &str, namespace: Namespace, timeout: Duration) -> Result<Self> {
+        Self::connect_in_with_features(name, namespace, timeout, DEFAULT_FEATURES)
+    }
+
+    /// Построитель подключения к каналу `name`: `SharedClient::builder(name)
+    /// .namespace(..).timeout(..).connect()`.
+    pub fn builder(name: &str) -> ClientBuilder {
+        ClientBuilder {
+            name: name.to_owned(),
+            options: ClientOptions::default(),
+        }
+    }
+
+    /// Подключение с явными [`ClientOptions`].
+    pub fn connect_with_options(name: &str, options: &ClientOptions) -> Result<Self> {
+        ensure_ring_capacity(options.ring_capacity)?;
+        Self::connect_in_with_features(name, options.namespace, options.timeout, options.features)
+    }
+
+    /// Подключение с повторами по `policy`: пока секции канала нет (сервер
+    /// ещё не запущен) или сервер не ответил на handshake (`Timeout`),
+    /// попытки повторяются с растущей паузой. Прочие ошибки и ошибка
+    /// последней попытки возвращаются как есть. Опции подключения -- по
+    /// умолчанию; другие -- через [`ClientBuilder::connect_with_retry`].
+    pub fn connect_with_retry(name: &str, policy: &RetryPolicy) -> Result<Self> {
+        Self::builder(name).connect_with_retry(policy)
+    }
+
+    pub(crate) fn connect_in_with_features(
+        name: &str,
+        namespace: Namespace,
+        timeout: Duration,
+        features: u32,
+    ) -> Result<Self> {
+        naming::validate(name)?;
+        let name = &namespace.qualify(name);
         let map_name = mapping_name(name);
-        let mapping = Mapping::open(&map_name)?;
-        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+        let mut mapping = Mapping::open(&map_name)?;
+        let mut view = unsafe { SharedView::new(mapping.as_ptr()) };
 
         // Проверка magic и version для валидации shared memory
         let control = view.control_block();
         if control.magic != SHARED_MAGIC {
             return Err(ShmError::Corrupted);
         }
-        if control.version != SHARED_VERSION {
-            return Err(ShmError::HandshakeFailed);
+        if !version_compatible(control.version) {
+            return Err(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: control.version,
+            });
         }
 
-        let events = SharedEvents::open(name)?;
+        // Секция с управляющими кольцами больше штатной: переоткрываем её
+        // полным размером (open_sized проверяет, что секция не меньше).
+        view.check_layout()?;
+        let flags = control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+        if flags & LAYOUT_FLAG_CONTROL_RINGS != 0 {
+            mapping = Mapping::open_sized(&map_name, dual_mapping_size())?;
+            view = unsafe { SharedView::with_control_rings(mapping.as_ptr()) };
+        }
 
-        view.control_block()
-            .client_state
-            .store(HANDSHAKE_CLIENT_HELLO, Ordering::Release);
+        let events = SharedEvents::open(name)?;
+        // Сервер старой версии блок состояния не создаёт.
+        let state = SharedState::open(name).ok();
 
-        unsafe {
-            (&*view.ring_header_a())
-                .handshake_state
-                .store(HANDSHAKE_CLIENT_HELLO, Ordering::Release);
-            (&*view.ring_header_b())
-                .handshake_state
-                .store(HANDSHAKE_CLIENT_HELLO, Ordering::Release);
-        }
+        let reserved = &view.control_block().reserved;
+        reserved[RESERVED_CLIENT_PID_INDEX].store(std::process::id(), Ordering::Release);
+        reserved[RESERVED_CLIENT_VERSION_INDEX].store(SHARED_VERSION, Ordering::Release);
+        reserved[RESERVED_CLIENT_FEATURES_INDEX]
+            .store(features & SUPPORTED_FEATURES, Ordering::Release);
+        set_handshake_state(&view, HANDSHAKE_CLIENT_HELLO);
 
         events.connect_req.set()?;
 
         if !events.connect_ack.wait(Some(timeout))? {
-            view.control_block()
-                .client_state
-                .store(HANDSHAKE_IDLE, Ordering::Release);
-            unsafe {
-                (&*view.ring_header_a())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-                (&*view.ring_header_b())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-            }
+            set_handshake_state(&view, HANDSHAKE_IDLE);
             return Err(ShmError::Timeout);
         }
 
         if view.control_block().server_state.load(Ordering::Acquire) != HANDSHAKE_SERVER_READY {
-            view.control_block()
-                .client_state
-                .store(HANDSHAKE_IDLE, Ordering::Release);
-            unsafe {
-                (&*view.ring_header_a())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-                (&*view.ring_header_b())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-            }
+            set_handshake_state(&view, HANDSHAKE_IDLE);
             return Err(ShmError::HandshakeFailed);
         }
 
+        Ok(Self::attach(
+            name.to_owned(),
+            mapping,
+            view,
+            Some(events),
+            state,
+        ))
+    }
+
+    /// Подключение к anonymous каналу (`SharedServer::start_anonymous`) по
+    /// handle'у его секции: `section_handle()` сервера, переданный в этот
+    /// процесс (`DuplicateHandle`, наследование, fd через `SCM_RIGHTS`) или
+    /// в kernel driver. Handle переходит во владение клиента и закрывается
+    /// вместе с ним, в том числе при ошибке. Событий у такого канала нет:
+    /// handshake и ожидание данных идут опросом (сервер --
+    /// `wait_for_client_noevent`), `poll_server` не ждёт, а только
+    /// проверяет буфер.
+    pub fn from_section_handle(handle: isize, timeout: Duration) -> Result<Self> {
+        let mapping = Mapping::from_handle(handle, shared_mapping_size())?;
+        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+
+        let control = view.control_block();
+        if control.magic != SHARED_MAGIC {
+            return Err(ShmError::Corrupted);
+        }
+        if !version_compatible(control.version) {
+            return Err(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: control.version,
+            });
+        }
+        view.check_layout()?;
+        // Anonymous сервер управляющих колец не создаёт.
+        let flags = control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+        if flags & LAYOUT_FLAG_CONTROL_RINGS != 0 {
+            return Err(ShmError::Unsupported(
+                "control rings need a named channel (SharedClient::connect)",
+            ));
+        }
+
+        let reserved = &control.reserved;
+        reserved[RESERVED_CLIENT_PID_INDEX].store(std::process::id(), Ordering::Release);
+        reserved[RESERVED_CLIENT_VERSION_INDEX].store(SHARED_VERSION, Ordering::Release);
+        reserved[RESERVED_CLIENT_FEATURES_INDEX]
+            .store(DEFAULT_FEATURES & SUPPORTED_FEATURES, Ordering::Release);
+        set_handshake_state(&view, HANDSHAKE_CLIENT_HELLO);
+
+        // Сервер отвечает, записывая SERVER_READY поверх нашего HELLO.
+        let start = Instant::now();
+        while control.client_state.load(Ordering::Acquire) != HANDSHAKE_SERVER_READY {
+            if start.elapsed() >= timeout {
+                set_handshake_state(&view, HANDSHAKE_IDLE);
+                return Err(ShmError::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        Ok(Self::attach(String::new(), mapping, view, None, None))
+    }
+
+    /// Завершение handshake: сервер ответил SERVER_READY.
+    fn attach(
+        name: String,
+        mapping: Mapping,
+        view: SharedView,
+        events: Option<SharedEvents>,
+        state: Option<SharedState>,
+    ) -> Self {
+        // Сервер старой версии не согласует возможности: там 0.
+        let negotiated_features = view.control_block().reserved[RESERVED_NEGOTIATED_FEATURES_INDEX]
+            .load(Ordering::Acquire);
         let generation = view.control_block().generation.load(Ordering::Acquire);
         unsafe {
             (&*view.ring_header_a())
@@ -103,26 +309,120 @@ impl SharedClient {
 
         let ring_tx = unsafe { RingBuffer::new(view.ring_header_b(), view.ring_buffer_b()) };
         let ring_rx = unsafe { RingBuffer::new(view.ring_header_a(), view.ring_buffer_a()) };
+        let control = view.control_rings(false);
 
-        let client = Self {
-            _name: name.to_owned(),
+        Self {
+            backend: Backend::of(&name),
+            _name: name,
             _mapping: mapping,
             view,
             events,
             ring_tx,
             ring_rx,
+            control,
+            state,
+            negotiated_features,
+            connection_gen: generation,
             connected: true,
-        };
-
-        Ok(client)
+        }
     }
 
     pub fn is_connected(&self) -> bool {
         self.connected
     }
 
-    pub(crate) fn events(&self) -> &SharedEvents {
-        &self.events
+    /// Номер соединения, одинаковый с сервером (см.
+    /// `SharedServer::connection_epoch`); `None` после `disconnect`.
+    pub fn connection_epoch(&self) -> Option<u32> {
+        self.connected.then_some(self.connection_gen)
+    }
+
+    /// Отмечает, что клиент жив (см. `SharedServer::heartbeat`).
+    pub fn heartbeat(&self) {
+        self.view.stamp_heartbeat(RESERVED_CLIENT_HEARTBEAT_INDEX);
+    }
+
+    /// Сколько прошло с последнего heartbeat сервера; `None` -- сервер
+    /// heartbeat не ведёт.
+    pub fn peer_heartbeat_age(&self) -> Option<Duration> {
+        self.view.heartbeat_age(RESERVED_SERVER_HEARTBEAT_INDEX)
+    }
+
+    /// Сервер отмечал heartbeat не дольше `max_age` назад (см.
+    /// `SharedServer::is_peer_responsive`).
+    pub fn is_peer_responsive(&self, max_age: Duration) -> bool {
+        self.peer_heartbeat_age().is_none_or(|age| age <= max_age)
+    }
+
+    /// PID процесса сервера (`RESERVED_SERVER_PID_INDEX`); `None` -- сервер
+    /// его не опубликовал (старая версия библиотеки).
+    pub fn peer_pid(&self) -> Option<u32> {
+        let pid =
+            self.view.control_block().reserved[RESERVED_SERVER_PID_INDEX].load(Ordering::Acquire);
+        (pid != 0).then_some(pid)
+    }
+
+    /// Жив ли процесс сервера (см. `SharedServer::is_peer_process_alive`).
+    pub fn is_peer_process_alive(&self) -> bool {
+        self.peer_pid().is_none_or(platform::is_process_alive)
+    }
+
+    /// Дешёвая проверка сервера без ожидания (см.
+    /// `SharedServer::is_peer_alive`).
+    pub fn is_peer_alive(&self) -> bool {
+        self.ensure_connected().is_ok()
+            && self.view.handshake_ready()
+            && self.is_peer_process_alive()
+    }
+
+    /// Делит соединение на независимые половины: приём в одном потоке,
+    /// отправка в другом (см. [`SendHalf`]). Собрать обратно --
+    /// [`SendHalf::reunite`].
+    pub fn split(self) -> (SendHalf<Self>, RecvHalf<Self>) {
+        split::split(self)
+    }
+
+    /// Отключается от сервера, оставив ему причину (его
+    /// `peer_disconnect_reason`, `AutoHandler::on_disconnect_with_reason`).
+    /// Дальнейшие операции -- `ShmError::NotConnected`; drop отключается с
+    /// `DisconnectReason::Graceful`.
+    pub fn disconnect(&mut self, reason: DisconnectReason) {
+        // После сброса канала control block и кольца принадлежат новому
+        // соединению -- устаревший клиент их не трогает.
+        if self.connected && self.ensure_connected().is_ok() {
+            self.view
+                .store_disconnect_reason(RESERVED_CLIENT_DISCONNECT_REASON_INDEX, reason);
+            set_handshake_state(&self.view, HANDSHAKE_IDLE);
+            if let Some(events) = &self.events {
+                let _ = events.disconnect.set();
+            }
+        }
+        self.connected = false;
+    }
+
+    /// Причина, которую оставил сервер (`SharedServer::disconnect`,
+    /// `Graceful` при drop); `Unknown` -- сервер не отключался или упал.
+    pub fn peer_disconnect_reason(&self) -> DisconnectReason {
+        self.view
+            .disconnect_reason(RESERVED_SERVER_DISCONNECT_REASON_INDEX)
+    }
+
+    /// Возможности, которые поддерживают обе стороны (0 -- сервер старой
+    /// версии без согласования).
+    pub fn negotiated_features(&self) -> u32 {
+        self.negotiated_features
+    }
+
+    /// Снимок control block и обоих колец для отчёта об ошибке (см.
+    /// `xshm::diagnostics`).
+    pub fn diagnostic_dump(&self) -> ChannelDump {
+        diagnostics::dump_view(&self.view)
+    }
+
+    /// События канала; `None` -- клиент подключён по handle'у секции
+    /// (`from_section_handle`) и работает опросом.
+    pub(crate) fn events(&self) -> Option<&SharedEvents> {
+        self.events.as_ref()
     }
 
     pub(crate) fn mark_disconnected(&mut self) {
@@ -132,55 +432,503 @@ impl SharedClient {
     fn ensure_connected(&self) -> Result<()> {
         if !self.connected {
             Err(ShmError::NotConnected)
+        } else if self.view.control_block().generation.load(Ordering::Acquire)
+            != self.connection_gen
+        {
+            Err(ShmError::ConnectionReset)
         } else {
             Ok(())
         }
     }
 
+    /// Включает CRC-32 трейлер для сообщений серверу. Входящие сообщения с
+    /// трейлером проверяются всегда; битые отбрасываются с
+    /// `ShmError::ChecksumMismatch`.
+    pub fn set_checksum(&self, enabled: bool) {
+        self.ring_tx.set_checksum(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_checksum(enabled);
+        }
+    }
+
+    /// Что делает отправка серверу, когда bulk-кольцо полно (см.
+    /// [`WritePolicy`]): вытесняет старые сообщения (по умолчанию),
+    /// возвращает `ShmError::QueueFull` или ждёт, пока сервер освободит
+    /// место. Ждут `send_to_server` и `send_to_server_with_ttl`, остальные
+    /// отправки при `Block` -- как при `Fail`. Управляющие кольца
+    /// вытесняют всегда.
+    pub fn set_write_policy(&self, policy: WritePolicy) {
+        self.ring_tx.set_write_policy(policy);
+    }
+
+    pub fn write_policy(&self) -> WritePolicy {
+        self.ring_tx.write_policy()
+    }
+
+    /// Метка времени записи на каждое сообщение серверу (см.
+    /// `xshm::latency`): по ней получатель считает задержку доставки.
+    pub fn set_timestamps(&self, enabled: bool) {
+        self.ring_tx.set_timestamps(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_timestamps(enabled);
+        }
+    }
+
+    /// Номер на каждое сообщение серверу через bulk-кольцо
+    /// (`MSG_FLAG_SEQUENCE`): по разрывам в номерах сервер видит
+    /// вытесненные сообщения (`receive_message_from_client`). Управляющие
+    /// кольца не нумеруются.
+    pub fn set_sequence_numbers(&self, enabled: bool) {
+        self.ring_tx.set_sequence_numbers(enabled);
+    }
+
+    /// Отправки серверу из нескольких потоков (MPSC): `send_to_server*`, пачки
+    /// и управляющие сообщения можно звать через общий `&SharedClient` (например,
+    /// из `Arc`) без своей блокировки. Писатели занимают место в кольце
+    /// CAS'ом и публикуют сообщения в порядке занятия; без режима
+    /// отправлять можно только из одного потока. `reserve_to_server` и так
+    /// требует `&mut self`, поэтому работает в обоих режимах.
+    pub fn set_multi_producer(&mut self, enabled: bool) {
+        self.ring_tx.set_multi_producer(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_multi_producer(enabled);
+        }
+    }
+
+    /// Гистограмма задержки сообщений от сервера с меткой времени;
+    /// `None` -- не считать.
+    pub fn set_latency_histogram(&mut self, histogram: Option<Arc<LatencyHistogram>>) {
+        if let Some((_, control_rx)) = &mut self.control {
+            control_rx.set_latency_histogram(histogram.clone());
+        }
+        self.ring_rx.set_latency_histogram(histogram);
+    }
+
+    /// Сколько сообщений от сервера отброшено из-за несовпадения CRC-32.
+    pub fn checksum_errors(&self) -> u32 {
+        self.ring_rx.checksum_errors()
+    }
+
+    /// Срок годности сообщений серверу по умолчанию (см.
+    /// `SharedServer::set_default_ttl`).
+    pub fn set_default_ttl(&self, ttl: Option<Duration>) {
+        self.ring_tx.set_default_ttl(ttl);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_default_ttl(ttl);
+        }
+    }
+
+    /// Порог space-события для сообщений от сервера (см.
+    /// `SharedServer::set_space_threshold`).
+    pub fn set_space_threshold(&self, percent: Option<u8>) -> Result<()> {
+        self.ring_rx.set_space_threshold(percent)
+    }
+
+    /// Сколько просроченных сообщений от сервера пропущено или убрано.
+    pub fn expired_messages(&self) -> u32 {
+        self.ring_rx.expired_count()
+    }
+
+    /// Метка времени последнего прочитанного от сервера кадра (см.
+    /// `RingBuffer::take_timestamp`); забирать после каждого чтения.
+    pub(crate) fn take_rx_timestamp(&self) -> Option<u64> {
+        let control = self
+            .control
+            .as_ref()
+            .and_then(|(_, control_rx)| control_rx.take_timestamp());
+        let bulk = self.ring_rx.take_timestamp();
+        control.or(bulk)
+    }
+
+    /// Непрочитанные кадры (от сервера, к серверу) во всех кольцах, включая
+    /// управляющие.
+    pub(crate) fn ring_depths(&self) -> (u32, u32) {
+        let (control_rx, control_tx) = self
+            .control
+            .as_ref()
+            .map_or((0, 0), |(tx, rx)| (rx.message_count(), tx.message_count()));
+        (
+            self.ring_rx.message_count() + control_rx,
+            self.ring_tx.message_count() + control_tx,
+        )
+    }
+
+    /// Сколько недописанных кадров сервера пропущено (см.
+    /// `SharedServer::torn_messages`).
+    pub fn torn_messages(&self) -> u32 {
+        self.ring_rx.torn_records()
+    }
+
+    /// Кредитный режим для сообщений от сервера (см.
+    /// `SharedServer::set_credit_window`). Действует до конца соединения.
+    pub fn set_credit_window(&self, window: Option<CreditWindow>) -> Result<()> {
+        self.ring_rx.set_credit_window(window)
+    }
+
+    /// Занятость bulk-кольца сообщений серверу (см. [`RingStatus`]).
+    pub fn tx_status(&self) -> RingStatus {
+        self.ring_tx.status()
+    }
+
+    /// Занятость bulk-кольца сообщений от сервера.
+    pub fn rx_status(&self) -> RingStatus {
+        self.ring_rx.status()
+    }
+
+    /// Отправленные, принятые, вытесненные и отброшенные сообщения и
+    /// глубина очереди обоих bulk-колец (см. [`ChannelStats`]).
+    pub fn stats(&self) -> ChannelStats {
+        ChannelStats {
+            tx: self.ring_tx.stats(),
+            rx: self.ring_rx.stats(),
+        }
+    }
+
+    /// Сбрасывает пики занятости обоих колец.
+    pub fn reset_high_water(&self) {
+        self.ring_tx.reset_high_water();
+        self.ring_rx.reset_high_water();
+    }
+
+    /// Свободные кредиты на отправку серверу; `None`, если сервер не
+    /// включил кредитный режим.
+    pub fn send_credits(&self) -> Option<CreditWindow> {
+        self.ring_tx.available_credits()
+    }
+
     pub fn send_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.send_to_server_with_flags(payload, 0)
+    }
+
+    /// Как `send_to_server`, плюс флаги приложения в заголовке кадра (биты
+    /// `MSG_USER_FLAGS_MASK`, иначе `ShmError::InvalidConfig`).
+    pub fn send_to_server_with_flags(&self, payload: &[u8], flags: u16) -> Result<WriteOutcome> {
+        ensure_config(
+            flags & !MSG_USER_FLAGS_MASK == 0,
+            "message flags must fit MSG_USER_FLAGS_MASK",
+        )?;
+        let result = self.ring_tx.write_blocking(
+            self.events.as_ref().map(|events| &events.c2s.space),
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.write_frame(payload, flags)
+            },
+        )?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Отправляет серверу, дожидаясь места не дольше `timeout` (см.
+    /// `SharedServer::send_blocking`).
+    pub fn send_blocking(&self, payload: &[u8], timeout: Duration) -> Result<WriteOutcome> {
+        let result = self.ring_tx.write_within(
+            self.events.as_ref().map(|events| &events.c2s.space),
+            timeout,
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.try_write_message(payload)
+            },
+        )?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Как `send_to_server`, но без ожидания и без вытеснения (см.
+    /// `SharedServer::try_send_to_client`).
+    pub fn try_send_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.try_write_message(payload)?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Как `send_to_server`, но со своим сроком годности вместо срока по
+    /// умолчанию (`None` -- бессрочно).
+    pub fn send_to_server_with_ttl(
+        &self,
+        payload: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<WriteOutcome> {
+        let result = self.ring_tx.write_blocking(
+            self.events.as_ref().map(|events| &events.c2s.space),
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.write_message_ttl(payload, ttl)
+            },
+        )?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Кусок большого сообщения auto-режима (см. `chunking`).
+    pub(crate) fn send_chunk_to_server(&self, chunk: &[u8], flags: u16) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.write_chunk(chunk, flags)?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Отправляет `payloads` серверу за один проход, выставляя событие
+    /// данных не больше одного раза -- на высоком темпе сообщений оно
+    /// дороже самой записи. Каждое сообщение -- как в `send_to_server`
+    /// (вытесняет старые при переполнении); ошибка прерывает проход, а
+    /// записанное до неё уже отправлено. Всё-или-ничего --
+    /// `send_batch_atomic_to_server`.
+    pub fn send_batch_to_server(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        self.ring_tx.write_messages(payloads, || {
+            self.signal_tx_data();
+        })
+    }
+
+    /// Отправляет `payloads` серверу одной публикацией: сервер увидит либо
+    /// всю пачку, либо ничего (например, тройку begin/update/commit). Пачка
+    /// не вытесняет непрочитанное: не хватает места -- `ShmError::QueueFull`
+    /// и не отправлено ничего.
+    pub fn send_batch_atomic_to_server(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.write_batch(payloads)?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Место под сообщение серверу прямо в разделяемой памяти: payload
+    /// пишется в guard (`&mut [u8]`, содержимое не обнулено), `commit()`
+    /// отправляет его. Брошенный guard не отправляет ничего. `&mut self` --
+    /// пока guard жив, других отправок нет.
+    pub fn reserve_to_server(&mut self, len: usize) -> Result<WriteGuard<'_>> {
+        self.ensure_connected()?;
+        Ok(self
+            .ring_tx
+            .reserve(len)?
+            .signal_on_commit(self.events.as_ref().map(|events| &events.c2s.data)))
+    }
+
+    /// Срочное сообщение серверу через управляющее кольцо (см.
+    /// `SharedServer::start_with_control_rings`). Без control-колец --
+    /// `ShmError::Unsupported`.
+    pub fn send_control_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let (control_tx, _) = self
+            .control
+            .as_ref()
+            .ok_or(ShmError::Unsupported("channel has no control rings"))?;
+        let result = control_tx.write_message(payload)?;
+        if result.was_empty {
+            self.signal_tx_data();
+        }
+        Ok(result)
+    }
+
+    /// Блок состояния канала (см. `xshm::state`); `None`, если сервер его
+    /// не создал.
+    pub fn state(&self) -> Option<&SharedState> {
+        self.state.as_ref()
+    }
+
+    pub fn has_control_rings(&self) -> bool {
+        self.view.has_control_rings()
+    }
+
+    /// Передаёт handle серверу (см. `SharedServer::send_handle_to_client`).
+    pub fn send_handle_to_server(&self, handle: isize) -> Result<WriteOutcome> {
         self.ensure_connected()?;
-        let result = self.ring_tx.write_message(payload)?;
+        let pid = self.view.control_block().reserved[RESERVED_SERVER_PID_INDEX]
+            .load(Ordering::Acquire);
+        let result = handles::write_handle(&self.ring_tx, self.backend, handle, pid)?;
         if result.was_empty {
-            let _ = self.events.c2s.data.set();
+            self.signal_tx_data();
         }
         Ok(result)
     }
 
+    /// Handle-кадры, пришедшие сюда, закрываются и пропускаются.
+    /// Управляющее кольцо (если есть) выбирается раньше bulk-кольца.
     pub fn receive_from_server(&self, buffer: &mut Vec<u8>) -> Result<usize> {
         self.ensure_connected()?;
-        let len = self.ring_rx.read_message(buffer)?;
-        if self.ring_rx.message_count() == 0 {
-            let _ = self.events.s2c.space.set();
+        let result = handles::read_message(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// `poll_server` и `receive_from_server` одним вызовом (см.
+    /// `SharedServer::receive_from_client_timeout`).
+    pub fn receive_from_server_timeout(
+        &self,
+        buffer: &mut Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match self.receive_from_server(buffer) {
+                Err(ShmError::QueueEmpty) => {}
+                result => return result,
+            }
+            let left = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if left.is_some_and(|left| left.is_zero()) {
+                return Err(ShmError::Timeout);
+            }
+            if self.events.is_some() {
+                // Ложное пробуждение -- ещё одна попытка чтения.
+                self.poll_server(left)?;
+            } else {
+                std::thread::sleep(left.map_or(Duration::from_millis(1), |left| {
+                    left.min(Duration::from_millis(1))
+                }));
+            }
+        }
+    }
+
+    /// Как `receive_from_server`, вместе с номером кадра (см.
+    /// `SharedServer::receive_message_from_client`).
+    pub fn receive_message_from_server(&self, buffer: &mut Vec<u8>) -> Result<ReceivedMessage> {
+        self.ensure_connected()?;
+        let result = handles::read_message_info(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result.map(|frame| ReceivedMessage {
+            len: frame.len,
+            seq: frame.sequence,
+        })
+    }
+
+    /// Как `receive_from_server`, вместе с флагами заголовка кадра (куски
+    /// auto-режима помечены `MSG_FLAG_CHUNK`).
+    pub(crate) fn receive_frame_from_server(&self, buffer: &mut Vec<u8>) -> Result<(usize, u16)> {
+        self.ensure_connected()?;
+        let result = handles::read_message_frame(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Приём без копии в `Vec` (см. `SharedServer::receive_with`).
+    pub fn receive_with<T>(&self, f: impl FnMut(&[u8]) -> T) -> Result<T> {
+        self.ensure_connected()?;
+        let result = handles::read_message_with(self.rx_lane(), self.backend, f);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Следующее сообщение без изъятия: копируется в `buffer`, а
+    /// следующий приём вернёт его же -- можно посмотреть на заголовок и
+    /// решить, забирать ли. Handle-кадры перед ним закрываются и
+    /// пропускаются, как в `receive_from_server`.
+    pub fn peek_message(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.ensure_connected()?;
+        let result = handles::peek_message(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Забирает все сообщения, что есть в очереди на момент вызова,
+    /// отдавая их `f` без копии (как `receive_with`), и возвращает их
+    /// число. Space-событие выставляется один раз в конце, а не на каждое
+    /// сообщение. Ошибка прерывает приём; уже отданное `f` забрано.
+    pub fn drain_into(&self, f: &mut impl FnMut(&[u8])) -> Result<usize> {
+        self.ensure_connected()?;
+        let queued = self.ring_rx.message_count()
+            + self
+                .control
+                .as_ref()
+                .map_or(0, |(_, control_rx)| control_rx.message_count());
+        let result = handles::drain_into(|| self.rx_lane(), self.backend, queued, f);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Приём сообщения или handle'а от сервера.
+    pub fn receive_any_from_server(&self, buffer: &mut Vec<u8>) -> Result<Received> {
+        self.ensure_connected()?;
+        let result = handles::read_any(self.rx_lane(), buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Кольцо, из которого читать следующим: непустое управляющее, иначе
+    /// bulk.
+    fn rx_lane(&self) -> &RingBuffer {
+        match &self.control {
+            Some((_, control_rx)) if !control_rx.is_empty() => control_rx,
+            _ => &self.ring_rx,
+        }
+    }
+
+    fn signal_tx_data(&self) {
+        if let Some(events) = &self.events {
+            let _ = events.c2s.data.set();
+        }
+    }
+
+    fn signal_rx_space(&self) {
+        // take_credit_stall первым: флаг сбрасывается при любом исходе.
+        if self.ring_rx.take_credit_stall() || self.ring_rx.space_available() {
+            if let Some(events) = &self.events {
+                let _ = events.s2c.space.set();
+            }
         }
-        Ok(len)
     }
 
     pub fn poll_server(&self, timeout: Option<Duration>) -> Result<bool> {
         self.ensure_connected()?;
-        if !self.ring_rx.is_empty() {
+        if !self.rx_lane().is_empty() {
             return Ok(true);
         }
-        self.events.s2c.data.wait(timeout)
+        // Без событий (клиент по handle'у) -- только проверка буфера.
+        let Some(events) = &self.events else {
+            return Ok(false);
+        };
+        events.s2c.data.wait(timeout)
+    }
+
+    /// Данные сервера, место в кольце к нему или его отключение (см.
+    /// `SharedServer::poll_events`). У клиента по handle'у секции событий
+    /// нет -- опрос без ожидания.
+    pub fn poll_events(&self, timeout: Option<Duration>) -> Result<PollResult> {
+        self.ensure_connected()?;
+        let handles = self.events.as_ref().map(|events| {
+            [
+                events.disconnect.raw_handle(),
+                events.s2c.data.raw_handle(),
+                events.c2s.space.raw_handle(),
+            ]
+        });
+        poll::poll_events(
+            handles,
+            timeout,
+            || !self.rx_lane().is_empty(),
+            || self.is_peer_alive(),
+        )
+    }
+}
+
+/// Состояние handshake клиента: в control block и в заголовках обоих колец.
+fn set_handshake_state(view: &SharedView, state: u32) {
+    view.control_block()
+        .client_state
+        .store(state, Ordering::Release);
+    unsafe {
+        (&*view.ring_header_a())
+            .handshake_state
+            .store(state, Ordering::Release);
+        (&*view.ring_header_b())
+            .handshake_state
+            .store(state, Ordering::Release);
     }
 }
 
 impl Drop for SharedClient {
     fn drop(&mut self) {
-        if self.connected {
-            let control = self.view.control_block();
-            control
-                .client_state
-                .store(HANDSHAKE_IDLE, Ordering::Release);
-            unsafe {
-                (&*self.view.ring_header_a())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-                (&*self.view.ring_header_b())
-                    .handshake_state
-                    .store(HANDSHAKE_IDLE, Ordering::Release);
-            }
-            let _ = self.events.disconnect.set();
-            self.connected = false;
-        }
+        self.disconnect(DisconnectReason::Graceful);
     }
 }
@@ -0,0 +1,259 @@
+//! Типизированные сообщения поверх байтовых каналов.
+//!
+//! [`Codec`] переводит значение в payload и обратно; ошибки всегда
+//! приходят как `ShmError::EncodeFailed` / `ShmError::DecodeFailed`.
+//! Готовые serde-кодеки: [`Postcard`] (feature `postcard`) и [`Bincode`]
+//! (feature `bincode`); собственный формат -- реализация `Codec` вручную.
+//!
+//! [`TypedSender`] работает поверх любого [`SendEndpoint`], [`TypedReceiver`]
+//! -- поверх [`ReceiveEndpoint`] (`SharedServer`/`SharedClient`). В
+//! `AutoHandler::on_message` payload разбирается тем же кодеком напрямую:
+//! `codec.decode(payload)`.
+//!
+//! ```ignore
+//! use xshm::codec::{Postcard, TypedReceiver, TypedSender};
+//!
+//! let tx = TypedSender::new(&client, Postcard);
+//! tx.send(&(42u32, "hello".to_owned()))?;
+//!
+//! let mut rx = TypedReceiver::<_, (u32, String), _>::new(&server, Postcard);
+//! let (id, text) = rx.receive()?;
+//! ```
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::client::SharedClient;
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::Result;
+#[cfg(any(feature = "postcard", feature = "bincode"))]
+use crate::error::ShmError;
+pub use crate::record::SendEndpoint;
+use crate::server::SharedServer;
+
+/// Формат сериализации значений типа `T`.
+pub trait Codec<T> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>>;
+    fn decode(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// Принимающая сторона канала с синхронным чтением.
+pub trait ReceiveEndpoint {
+    fn receive_payload(&self, buffer: &mut Vec<u8>) -> Result<usize>;
+
+    /// Ждёт входящих данных (`poll_client`/`poll_server`).
+    fn poll_payload(&self, timeout: Option<Duration>) -> Result<bool>;
+}
+
+impl ReceiveEndpoint for SharedServer {
+    fn receive_payload(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.receive_from_client(buffer)
+    }
+
+    fn poll_payload(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.poll_client(timeout)
+    }
+}
+
+impl ReceiveEndpoint for SharedClient {
+    fn receive_payload(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.receive_from_server(buffer)
+    }
+
+    fn poll_payload(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.poll_server(timeout)
+    }
+}
+
+impl<E: ReceiveEndpoint> ReceiveEndpoint for &E {
+    fn receive_payload(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        (**self).receive_payload(buffer)
+    }
+
+    fn poll_payload(&self, timeout: Option<Duration>) -> Result<bool> {
+        (**self).poll_payload(timeout)
+    }
+}
+
+/// Отправка значений `T`, закодированных `C`.
+pub struct TypedSender<E, T, C> {
+    endpoint: E,
+    codec: C,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<E: SendEndpoint, T, C: Codec<T>> TypedSender<E, T, C> {
+    pub fn new(endpoint: E, codec: C) -> Self {
+        Self {
+            endpoint,
+            codec,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn send(&self, value: &T) -> Result<()> {
+        let payload = self.codec.encode(value)?;
+        self.endpoint.send_payload(&payload)
+    }
+
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    pub fn into_inner(self) -> E {
+        self.endpoint
+    }
+}
+
+/// Приём значений `T`, закодированных `C`.
+pub struct TypedReceiver<E, T, C> {
+    endpoint: E,
+    codec: C,
+    buffer: Vec<u8>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<E: ReceiveEndpoint, T, C: Codec<T>> TypedReceiver<E, T, C> {
+    pub fn new(endpoint: E, codec: C) -> Self {
+        Self {
+            endpoint,
+            codec,
+            buffer: Vec::with_capacity(MAX_MESSAGE_SIZE),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Следующее сообщение; `ShmError::QueueEmpty`, если его нет.
+    pub fn receive(&mut self) -> Result<T> {
+        let len = self.endpoint.receive_payload(&mut self.buffer)?;
+        self.codec.decode(&self.buffer[..len])
+    }
+
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    pub fn into_inner(self) -> E {
+        self.endpoint
+    }
+}
+
+/// [postcard](https://docs.rs/postcard): компактный varint-формат.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Postcard {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        postcard::to_stdvec(value).map_err(|err| ShmError::EncodeFailed(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T> {
+        postcard::from_bytes(bytes).map_err(|err| ShmError::DecodeFailed(err.to_string()))
+    }
+}
+
+/// [bincode](https://docs.rs/bincode) 2 со стандартной конфигурацией.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Codec<T> for Bincode {
+    fn encode(&self, value: &T) -> Result<Vec<u8>> {
+        bincode::serde::encode_to_vec(value, bincode::config::standard())
+            .map_err(|err| ShmError::EncodeFailed(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<T> {
+        let (value, used) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map_err(|err| ShmError::DecodeFailed(err.to_string()))?;
+        if used != bytes.len() {
+            return Err(ShmError::DecodeFailed(format!(
+                "{} trailing bytes",
+                bytes.len() - used
+            )));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ShmError;
+
+    /// Ручной кодек: u32 little-endian ровно в 4 байтах.
+    struct LeU32;
+
+    impl Codec<u32> for LeU32 {
+        fn encode(&self, value: &u32) -> Result<Vec<u8>> {
+            Ok(value.to_le_bytes().to_vec())
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<u32> {
+            let bytes: [u8; 4] = bytes.try_into().map_err(|_| {
+                ShmError::DecodeFailed(format!("expected 4 bytes, got {}", bytes.len()))
+            })?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+    }
+
+    fn connected_pair(tag: &str) -> (SharedServer, SharedClient) {
+        let name = format!("XSHM_CODEC_{tag}_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        (server, connector.join().unwrap())
+    }
+
+    #[test]
+    fn typed_roundtrip_and_decode_error() {
+        let (server, client) = connected_pair("MANUAL");
+        let tx = TypedSender::new(&client, LeU32);
+        let mut rx = TypedReceiver::new(&server, LeU32);
+
+        tx.send(&0xDEAD_BEEF).unwrap();
+        assert_eq!(rx.receive().unwrap(), 0xDEAD_BEEF);
+        assert_eq!(rx.receive(), Err(ShmError::QueueEmpty));
+
+        client.send_to_server(b"xyz").unwrap();
+        assert!(matches!(rx.receive(), Err(ShmError::DecodeFailed(_))));
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn postcard_roundtrip() {
+        let (server, client) = connected_pair("POSTCARD");
+        let tx = TypedSender::new(&server, Postcard);
+        let mut rx = TypedReceiver::<_, (u32, String, Vec<u16>), _>::new(&client, Postcard);
+        let value = (7, "seven".to_owned(), vec![1, 2, 3]);
+        tx.send(&value).unwrap();
+        assert_eq!(rx.receive().unwrap(), value);
+
+        let bad: Result<String> = Postcard.decode(&[0xFF]);
+        assert!(matches!(bad, Err(ShmError::DecodeFailed(_))));
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_roundtrip() {
+        let (server, client) = connected_pair("BINCODE");
+        let tx = TypedSender::new(&client, Bincode);
+        let mut rx = TypedReceiver::<_, (u64, Option<String>), _>::new(&server, Bincode);
+        let value = (u64::MAX, Some("max".to_owned()));
+        tx.send(&value).unwrap();
+        assert_eq!(rx.receive().unwrap(), value);
+
+        let mut trailing = Bincode.encode(&1u8).unwrap();
+        trailing.push(0);
+        let bad: Result<u8> = Bincode.decode(&trailing);
+        assert!(matches!(bad, Err(ShmError::DecodeFailed(_))));
+    }
+}
@@ -0,0 +1,409 @@
+//! Опциональное аутентифицированное шифрование payload'ов канала
+//! (feature `encryption`).
+//!
+//! Секции и события создаются с NULL DACL, поэтому любой процесс в сессии,
+//! знающий имя канала, может открыть секцию и читать/портить кольца. Этот
+//! модуль закрывает содержимое сообщений:
+//!
+//! - **Key agreement**: X25519 на эфемерных ключах, свежих на КАЖДОЕ
+//!   соединение (после reconnect ключи новые).
+//! - **Шифрование**: ChaCha20-Poly1305 на каждое сообщение; ключи направлений
+//!   (S2C/C2S) разные, выводятся из общего секрета через HKDF-SHA256.
+//! - **Nonce**: монотонный 64-битный счётчик отправителя. Получатель
+//!   отвергает повторы и откаты счётчика (replay), но допускает пропуски —
+//!   кольцо может вытеснить сообщения при переполнении.
+//!
+//! Публичные ключи передаются ВНУТРИ канала первыми кадрами каждого
+//! направления (см. `KEY_FRAME_MAGIC`), а не через `ControlBlock::reserved`:
+//! там всего 44 байта, а двум 32-байтным ключам нужно 64.
+//!
+//! # Модель угроз
+//!
+//! Кольца, по которым идут кадры с ключами, доступны на запись любому, кто
+//! может открыть секцию, поэтому без pre-shared key защита -- только от
+//! пассивного чтения: наблюдатель, видящий оба публичных ключа, сессионных
+//! ключей не получит. Против активного процесса с доступом к секции обмен
+//! ключами не защищён вовсе: ему не нужно опережать пира -- он может в
+//! любой момент до чтения переписать или подменить кадр с ключом в кольце
+//! и встать посередине, а испортив кадр -- сорвать соединение
+//! (`ShmError::HandshakeFailed`). Подмена сообщений уже установленной
+//! сессии без её ключей обнаруживается (`ShmError::AuthenticationFailed`).
+//!
+//! `EncryptionOptions::psk` подмешивается в HKDF -- при несовпадении PSK
+//! стороны получают разные ключи и первый же кадр не проходит
+//! аутентификацию: с PSK подмена ключа сводится к отказу в обслуживании.
+//! От самого отказа (и от чтения метаданных: размеров, флагов, меток
+//! времени кадров) закрывают только права на объекты канала (`Security`).
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::{Result, ShmError};
+
+/// Магия кадра обмена ключами: 'XKEY'.
+const KEY_FRAME_MAGIC: u32 = 0x5859_454b;
+/// Версия кадра обмена ключами.
+const KEY_FRAME_VERSION: u8 = 1;
+/// Размер кадра обмена ключами: magic(4) + version(1) + public key(32).
+const KEY_FRAME_SIZE: usize = 4 + 1 + 32;
+
+/// Размер префикса nonce-счётчика в зашифрованном кадре.
+const COUNTER_SIZE: usize = 8;
+/// Размер тега Poly1305.
+const TAG_SIZE: usize = 16;
+
+/// Накладные расходы шифрования на одно сообщение (счётчик + тег).
+pub const SEAL_OVERHEAD: usize = COUNTER_SIZE + TAG_SIZE;
+
+/// Максимальный размер открытого текста, который ещё помещается в одно
+/// сообщение кольца после шифрования.
+pub const MAX_PLAINTEXT_SIZE: usize = MAX_MESSAGE_SIZE - SEAL_OVERHEAD;
+
+const HKDF_INFO_S2C: &[u8] = b"xshm v1 s2c";
+const HKDF_INFO_C2S: &[u8] = b"xshm v1 c2s";
+
+/// Настройки шифрования канала.
+#[derive(Clone, Default)]
+pub struct EncryptionOptions {
+    /// Pre-shared key, подмешиваемый в вывод ключей (см. «Модель угроз» в
+    /// документации модуля). `None` -- только эфемерный X25519.
+    pub psk: Option<[u8; 32]>,
+}
+
+/// Сторона соединения -- определяет, какой из ключей направлений
+/// используется для отправки, а какой для приёма.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+/// Эфемерная половина обмена ключами одного соединения.
+pub struct KeyExchange {
+    secret: EphemeralSecret,
+    public: PublicKey,
+    role: Role,
+}
+
+impl KeyExchange {
+    /// Генерирует эфемерную пару ключей (ОС-энтропия через `getrandom`).
+    pub fn new(role: Role) -> Self {
+        let secret = EphemeralSecret::random();
+        let public = PublicKey::from(&secret);
+        Self {
+            secret,
+            public,
+            role,
+        }
+    }
+
+    /// Публичный ключ этой стороны.
+    pub fn public_key(&self) -> [u8; 32] {
+        *self.public.as_bytes()
+    }
+
+    /// Кадр обмена ключами для отправки пиру первым сообщением.
+    pub fn key_frame(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(KEY_FRAME_SIZE);
+        frame.extend_from_slice(&KEY_FRAME_MAGIC.to_le_bytes());
+        frame.push(KEY_FRAME_VERSION);
+        frame.extend_from_slice(self.public.as_bytes());
+        frame
+    }
+
+    /// Завершает обмен по публичному ключу пира и выводит ключи сессии.
+    pub fn finish(
+        self,
+        peer_public: &[u8; 32],
+        options: &EncryptionOptions,
+    ) -> Result<SecureSession> {
+        let shared = self.secret.diffie_hellman(&PublicKey::from(*peer_public));
+        // Нулевой общий секрет -- пир прислал точку малого порядка.
+        if !shared.was_contributory() {
+            return Err(ShmError::AuthenticationFailed);
+        }
+
+        let hkdf = Hkdf::<Sha256>::new(
+            options.psk.as_ref().map(|k| k.as_slice()),
+            shared.as_bytes(),
+        );
+        let mut s2c = [0u8; 32];
+        let mut c2s = [0u8; 32];
+        hkdf.expand(HKDF_INFO_S2C, &mut s2c)
+            .map_err(|_| ShmError::AuthenticationFailed)?;
+        hkdf.expand(HKDF_INFO_C2S, &mut c2s)
+            .map_err(|_| ShmError::AuthenticationFailed)?;
+
+        let (tx_key, rx_key) = match self.role {
+            Role::Server => (s2c, c2s),
+            Role::Client => (c2s, s2c),
+        };
+
+        Ok(SecureSession {
+            tx: ChaCha20Poly1305::new(Key::from_slice(&tx_key)),
+            rx: ChaCha20Poly1305::new(Key::from_slice(&rx_key)),
+            tx_counter: 0,
+            rx_next: 0,
+        })
+    }
+}
+
+/// Разбирает кадр обмена ключами. `None` -- это не кадр обмена ключами.
+pub fn parse_key_frame(frame: &[u8]) -> Option<[u8; 32]> {
+    if frame.len() != KEY_FRAME_SIZE {
+        return None;
+    }
+    let magic = u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]);
+    if magic != KEY_FRAME_MAGIC || frame[4] != KEY_FRAME_VERSION {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&frame[5..]);
+    Some(key)
+}
+
+/// Установленная зашифрованная сессия одного соединения.
+///
+/// Layout зашифрованного кадра:
+/// ```text
+/// [0..8]   counter: u64 LE (nonce = 4 нулевых байта + counter)
+/// [8..]    ciphertext || tag(16)
+/// ```
+/// Счётчик входит в AEAD как associated data, поэтому подмена счётчика
+/// без ключа тоже обнаруживается.
+pub struct SecureSession {
+    tx: ChaCha20Poly1305,
+    rx: ChaCha20Poly1305,
+    tx_counter: u64,
+    rx_next: u64,
+}
+
+fn nonce_for(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    *Nonce::from_slice(&nonce)
+}
+
+impl SecureSession {
+    /// Шифрует сообщение для отправки пиру.
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        if plaintext.len() > MAX_PLAINTEXT_SIZE {
+            return Err(ShmError::MessageTooLarge);
+        }
+        let counter = self.tx_counter;
+        // Исчерпание 2^64 сообщений недостижимо на практике, но повтор
+        // nonce фатален для ChaCha20-Poly1305 -- не допускаем его даже в теории.
+        self.tx_counter = counter
+            .checked_add(1)
+            .ok_or(ShmError::AuthenticationFailed)?;
+
+        let aad = counter.to_le_bytes();
+        let ciphertext = self
+            .tx
+            .encrypt(
+                &nonce_for(counter),
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| ShmError::AuthenticationFailed)?;
+
+        let mut frame = Vec::with_capacity(COUNTER_SIZE + ciphertext.len());
+        frame.extend_from_slice(&aad);
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    /// Расшифровывает и аутентифицирует кадр от пира.
+    ///
+    /// Отвергает повторно проигранные и старые кадры (счётчик меньше
+    /// ожидаемого); пропуск счётчиков допустим (overwrite в кольце).
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        if frame.len() < SEAL_OVERHEAD {
+            return Err(ShmError::AuthenticationFailed);
+        }
+        let mut counter_bytes = [0u8; COUNTER_SIZE];
+        counter_bytes.copy_from_slice(&frame[..COUNTER_SIZE]);
+        let counter = u64::from_le_bytes(counter_bytes);
+        if counter < self.rx_next {
+            return Err(ShmError::AuthenticationFailed);
+        }
+
+        let plaintext = self
+            .rx
+            .decrypt(
+                &nonce_for(counter),
+                Payload {
+                    msg: &frame[COUNTER_SIZE..],
+                    aad: &counter_bytes,
+                },
+            )
+            .map_err(|_| ShmError::AuthenticationFailed)?;
+
+        // Продвигаем окно ТОЛЬКО после успешной аутентификации -- иначе
+        // подделанный кадр с большим счётчиком «съел» бы легитимные.
+        self.rx_next = counter.saturating_add(1);
+        Ok(plaintext)
+    }
+}
+
+/// Шифрование одного соединения в auto-mode: ожидание ключа пира, затем
+/// прозрачный seal/open.
+pub(crate) enum ChannelCipher {
+    /// Свой ключ отправлен, ждём кадр с ключом пира.
+    Pending(KeyExchange, EncryptionOptions),
+    /// Сессия установлена.
+    Ready(SecureSession),
+    /// Обмен ключами оборвался на ключе пира; соединение сбрасывается
+    /// вызывающим кодом, а новое начинается с `start`.
+    Failed,
+}
+
+impl ChannelCipher {
+    /// Начинает обмен ключами. Возвращает состояние и кадр, который нужно
+    /// отправить пиру ПЕРВЫМ сообщением соединения.
+    pub(crate) fn start(role: Role, options: &EncryptionOptions) -> (Self, Vec<u8>) {
+        let exchange = KeyExchange::new(role);
+        let frame = exchange.key_frame();
+        (ChannelCipher::Pending(exchange, options.clone()), frame)
+    }
+
+    pub(crate) fn is_ready(&self) -> bool {
+        matches!(self, ChannelCipher::Ready(_))
+    }
+
+    pub(crate) fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            ChannelCipher::Ready(session) => session.seal(plaintext),
+            ChannelCipher::Pending(..) => Err(ShmError::NotReady),
+            ChannelCipher::Failed => Err(ShmError::HandshakeFailed),
+        }
+    }
+
+    /// Обрабатывает входящий кадр. `Ok(None)` -- кадр был ключом пира и
+    /// поглощён (сессия теперь установлена); `Ok(Some(..))` -- открытый текст.
+    pub(crate) fn open(&mut self, frame: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self {
+            ChannelCipher::Ready(session) => session.open(frame).map(Some),
+            ChannelCipher::Pending(..) => {
+                let peer = parse_key_frame(frame).ok_or(ShmError::HandshakeFailed)?;
+                let ChannelCipher::Pending(exchange, options) =
+                    std::mem::replace(self, ChannelCipher::Failed)
+                else {
+                    unreachable!("checked by the outer match");
+                };
+                *self = ChannelCipher::Ready(exchange.finish(&peer, &options)?);
+                Ok(None)
+            }
+            ChannelCipher::Failed => Err(ShmError::HandshakeFailed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(
+        server_psk: Option<[u8; 32]>,
+        client_psk: Option<[u8; 32]>,
+    ) -> (SecureSession, SecureSession) {
+        let server = KeyExchange::new(Role::Server);
+        let client = KeyExchange::new(Role::Client);
+        let server_pub = server.public_key();
+        let client_pub = client.public_key();
+        let s = server
+            .finish(&client_pub, &EncryptionOptions { psk: server_psk })
+            .unwrap();
+        let c = client
+            .finish(&server_pub, &EncryptionOptions { psk: client_psk })
+            .unwrap();
+        (s, c)
+    }
+
+    #[test]
+    fn seal_open_roundtrip_both_directions() {
+        let (mut server, mut client) = pair(None, None);
+
+        let frame = server.seal(b"ping").unwrap();
+        assert_eq!(frame.len(), 4 + SEAL_OVERHEAD);
+        assert_eq!(client.open(&frame).unwrap(), b"ping");
+
+        let frame = client.seal(b"pong").unwrap();
+        assert_eq!(server.open(&frame).unwrap(), b"pong");
+    }
+
+    #[test]
+    fn ciphertext_does_not_contain_plaintext() {
+        let (mut server, _client) = pair(None, None);
+        let plaintext = b"very secret payload";
+        let frame = server.seal(plaintext).unwrap();
+        assert!(!frame.windows(plaintext.len()).any(|w| w == plaintext));
+    }
+
+    #[test]
+    fn tampered_frame_is_rejected() {
+        let (mut server, mut client) = pair(None, None);
+        let mut frame = server.seal(b"payload").unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 0x01;
+        assert_eq!(client.open(&frame), Err(ShmError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn replayed_frame_is_rejected_but_gaps_are_allowed() {
+        let (mut server, mut client) = pair(None, None);
+        let first = server.seal(b"one").unwrap();
+        let _dropped = server.seal(b"two").unwrap(); // «вытеснено» кольцом
+        let third = server.seal(b"three").unwrap();
+
+        assert_eq!(client.open(&first).unwrap(), b"one");
+        assert_eq!(client.open(&third).unwrap(), b"three");
+        assert_eq!(client.open(&first), Err(ShmError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn psk_mismatch_fails_authentication() {
+        let (mut server, mut client) = pair(Some([1; 32]), Some([2; 32]));
+        let frame = server.seal(b"payload").unwrap();
+        assert_eq!(client.open(&frame), Err(ShmError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn channel_cipher_consumes_key_frame_then_decrypts() {
+        let options = EncryptionOptions::default();
+        let (mut server, server_frame) = ChannelCipher::start(Role::Server, &options);
+        let (mut client, client_frame) = ChannelCipher::start(Role::Client, &options);
+
+        assert_eq!(server.seal(b"early"), Err(ShmError::NotReady));
+        assert_eq!(server.open(&client_frame).unwrap(), None);
+        assert_eq!(client.open(&server_frame).unwrap(), None);
+        assert!(server.is_ready() && client.is_ready());
+
+        let frame = server.seal(b"hello").unwrap();
+        assert_eq!(client.open(&frame).unwrap(), Some(b"hello".to_vec()));
+    }
+
+    #[test]
+    fn failed_key_exchange_stays_failed() {
+        let (mut cipher, _) = ChannelCipher::start(Role::Server, &EncryptionOptions::default());
+        let mut low_order = KEY_FRAME_MAGIC.to_le_bytes().to_vec();
+        low_order.push(KEY_FRAME_VERSION);
+        low_order.extend_from_slice(&[0; 32]);
+        assert_eq!(cipher.open(&low_order), Err(ShmError::AuthenticationFailed));
+        assert!(!cipher.is_ready());
+        assert_eq!(cipher.seal(b"data"), Err(ShmError::HandshakeFailed));
+        assert_eq!(cipher.open(&low_order), Err(ShmError::HandshakeFailed));
+    }
+
+    #[test]
+    fn non_key_frame_during_exchange_is_a_handshake_error() {
+        let (mut cipher, _) = ChannelCipher::start(Role::Server, &EncryptionOptions::default());
+        assert_eq!(cipher.open(b"plain data"), Err(ShmError::HandshakeFailed));
+    }
+}
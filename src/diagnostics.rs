@@ -0,0 +1,441 @@
+//! Снимок состояния канала для postmortem-отладки.
+//!
+//! [`ChannelDump`] содержит control block, оба заголовка колец и сырые
+//! байты вокруг позиций чтения/записи; `Display` даёт текстовый отчёт,
+//! который можно приложить к тикету. Снимок берётся без блокировок и без
+//! участия пира: поля читаются по одному и могут быть взаимно
+//! несогласованы, если канал в этот момент активен.
+//!
+//! ```no_run
+//! // канал чужого процесса: секция открывается без handshake, только чтение
+//! let dump = xshm::diagnostics::dump_by_name("MyService")?;
+//! eprintln!("{dump}");
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+//!
+//! [`ChannelTap`] -- живой просмотр трафика тем же способом: свой курсор
+//! по каждому кольцу, позиции чтения и записи не меняются.
+
+use std::fmt;
+use std::sync::atomic::Ordering;
+
+use crate::auto::ChannelKind;
+use crate::constants::{
+    CHECKSUM_SIZE, CONTROL_RESERVED_SLOTS, DEADLINE_SIZE, HANDSHAKE_CLIENT_HELLO, HANDSHAKE_IDLE,
+    HANDSHAKE_SERVER_READY, MAX_MESSAGE_SIZE, MESSAGE_HEADER_SIZE, MIN_MESSAGE_SIZE,
+    MSG_FLAG_CHECKSUM, MSG_FLAG_DEADLINE, MSG_FLAG_SEQUENCE, MSG_FLAG_TIMESTAMP, RING_CAPACITY,
+    RING_MASK, SEQUENCE_SIZE, TIMESTAMP_SIZE,
+};
+use crate::error::Result;
+use crate::layout::RingHeader;
+use crate::naming::mapping_name;
+use crate::platform::{Mapping, PlatformMapping};
+use crate::shared::SharedView;
+
+/// Сколько байт кольца захватывается после `read_pos` и перед `write_pos`.
+pub const DUMP_WINDOW: usize = 64;
+
+/// Состояние одного кольца.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingDump {
+    pub direction: ChannelKind,
+    pub write_pos: u32,
+    pub read_pos: u32,
+    pub message_count: u32,
+    pub drop_count: u32,
+    pub sequence: u32,
+    pub connection_gen: u32,
+    pub handshake_state: u32,
+    pub checksum_errors: u32,
+    /// `DUMP_WINDOW` байт, начиная с `read_pos` (следующее сообщение).
+    pub bytes_at_read: Vec<u8>,
+    /// `DUMP_WINDOW` байт, заканчивающихся на `write_pos` (последнее
+    /// записанное).
+    pub bytes_before_write: Vec<u8>,
+}
+
+/// Снимок канала целиком.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelDump {
+    pub magic: u32,
+    pub version: u32,
+    pub generation: u32,
+    pub server_state: u32,
+    pub client_state: u32,
+    pub reserved: [u32; CONTROL_RESERVED_SLOTS],
+    /// Кольцо A (сервер -> клиент).
+    pub server_to_client: RingDump,
+    /// Кольцо B (клиент -> сервер).
+    pub client_to_server: RingDump,
+}
+
+fn copy_wrapped(data: *const u8, start: u32, len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|offset| {
+            let index = (start.wrapping_add(offset) & RING_MASK) as usize;
+            // SAFETY: index < RING_CAPACITY, data -- начало кольца.
+            unsafe { data.add(index).read_volatile() }
+        })
+        .collect()
+}
+
+fn copy_window(data: *const u8, start: u32) -> Vec<u8> {
+    copy_wrapped(data, start, DUMP_WINDOW)
+}
+
+/// # Safety
+/// `header`/`data` -- заголовок и данные кольца живого отображения.
+unsafe fn dump_ring(
+    direction: ChannelKind,
+    header: *const RingHeader,
+    data: *const u8,
+) -> RingDump {
+    // SAFETY: контракт функции.
+    let header = unsafe { &*header };
+    let write_pos = header.write_pos.load(Ordering::Acquire);
+    let read_pos = header.read_pos.load(Ordering::Acquire);
+    RingDump {
+        direction,
+        write_pos,
+        read_pos,
+        message_count: header.message_count.load(Ordering::Acquire),
+        drop_count: header.drop_count.load(Ordering::Relaxed),
+        sequence: header.sequence.load(Ordering::Relaxed),
+        connection_gen: header.connection_gen.load(Ordering::Relaxed),
+        handshake_state: header.handshake_state.load(Ordering::Relaxed),
+        checksum_errors: header.checksum_errors.load(Ordering::Relaxed),
+        bytes_at_read: copy_window(data, read_pos),
+        bytes_before_write: copy_window(data, write_pos.wrapping_sub(DUMP_WINDOW as u32)),
+    }
+}
+
+pub(crate) fn dump_view(view: &SharedView) -> ChannelDump {
+    let control = view.control_block();
+    // SAFETY: view указывает на живое отображение канала.
+    let (server_to_client, client_to_server) = unsafe {
+        (
+            dump_ring(
+                ChannelKind::ServerToClient,
+                view.ring_header_a(),
+                view.ring_buffer_a(),
+            ),
+            dump_ring(
+                ChannelKind::ClientToServer,
+                view.ring_header_b(),
+                view.ring_buffer_b(),
+            ),
+        )
+    };
+    ChannelDump {
+        magic: control.magic,
+        version: control.version,
+        generation: control.generation.load(Ordering::Acquire),
+        server_state: control.server_state.load(Ordering::Acquire),
+        client_state: control.client_state.load(Ordering::Acquire),
+        reserved: std::array::from_fn(|i| control.reserved[i].load(Ordering::Relaxed)),
+        server_to_client,
+        client_to_server,
+    }
+}
+
+/// Снимок канала `name` без подключения к нему: секция только
+/// открывается и читается, состояние handshake не меняется.
+pub fn dump_by_name(name: &str) -> Result<ChannelDump> {
+    let mapping = Mapping::open(&mapping_name(name))?;
+    // SAFETY: отображение размером shared_mapping_size() живёт до конца
+    // функции, снимок копирует всё нужное.
+    let view = unsafe { SharedView::new(mapping.as_ptr()) };
+    Ok(dump_view(&view))
+}
+
+/// Кадр, увиденный [`ChannelTap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TappedFrame {
+    pub direction: ChannelKind,
+    /// Флаги кадра (`MSG_FLAG_*`).
+    pub flags: u16,
+    pub payload: Vec<u8>,
+}
+
+/// Наблюдатель трафика канала без подключения к нему.
+///
+/// Курсор каждого кольца стартует с текущего `write_pos`: видны только
+/// кадры, записанные после `open`. Кадр копируется оптимистично и
+/// проверяется по `write_pos` после копирования; если писатель успел
+/// перезаписать его (наблюдатель отстал на целое кольцо), курсор
+/// переносится на `read_pos`, а пропуск считается в [`lost`](Self::lost).
+/// Кадры, которые получатель уже забрал, наблюдатель всё равно видит,
+/// пока они не перезаписаны.
+pub struct ChannelTap {
+    _mapping: Mapping,
+    view: SharedView,
+    /// Курсоры колец A (сервер -> клиент) и B (клиент -> сервер).
+    cursors: [u32; 2],
+    lost: u64,
+}
+
+fn tapped_frame_size(len: usize, flags: u16) -> usize {
+    let trailer = |flag, size| if flags & flag != 0 { size } else { 0 };
+    MESSAGE_HEADER_SIZE
+        + len
+        + trailer(MSG_FLAG_TIMESTAMP, TIMESTAMP_SIZE)
+        + trailer(MSG_FLAG_DEADLINE, DEADLINE_SIZE)
+        + trailer(MSG_FLAG_SEQUENCE, SEQUENCE_SIZE)
+        + trailer(MSG_FLAG_CHECKSUM, CHECKSUM_SIZE)
+}
+
+impl ChannelTap {
+    /// Открывает секцию канала `name` только для чтения позиций и данных.
+    pub fn open(name: &str) -> Result<Self> {
+        let mapping = Mapping::open(&mapping_name(name))?;
+        // SAFETY: отображение размером shared_mapping_size() хранится в
+        // self вместе с view.
+        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+        let mut tap = Self {
+            _mapping: mapping,
+            view,
+            cursors: [0; 2],
+            lost: 0,
+        };
+        for ring in 0..2 {
+            let (header, _) = tap.ring(ring);
+            // SAFETY: заголовок кольца внутри живого отображения.
+            tap.cursors[ring] = unsafe { &*header }.write_pos.load(Ordering::Acquire);
+        }
+        Ok(tap)
+    }
+
+    fn ring(&self, ring: usize) -> (*const RingHeader, *const u8) {
+        if ring == 0 {
+            (self.view.ring_header_a(), self.view.ring_buffer_a())
+        } else {
+            (self.view.ring_header_b(), self.view.ring_buffer_b())
+        }
+    }
+
+    /// Сколько раз наблюдатель отставал на целое кольцо и терял кадры.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Следующий новый кадр любого направления (сначала сервер -> клиент)
+    /// или `None`, если новых кадров нет.
+    pub fn next_frame(&mut self) -> Option<TappedFrame> {
+        (0..2).find_map(|ring| self.next_in(ring))
+    }
+
+    fn next_in(&mut self, ring: usize) -> Option<TappedFrame> {
+        let (header, data) = self.ring(ring);
+        // SAFETY: заголовок кольца внутри отображения, которое живёт в self.
+        let header = unsafe { &*header };
+        let capacity = RING_CAPACITY as u32;
+        loop {
+            let cursor = self.cursors[ring];
+            let write = header.write_pos.load(Ordering::Acquire);
+            if write == cursor {
+                return None;
+            }
+            let resync = |tap: &mut Self| {
+                tap.cursors[ring] = header.read_pos.load(Ordering::Acquire);
+                tap.lost += 1;
+            };
+            // Отстали на кольцо или кольцо сброшено (reconnect).
+            if write.wrapping_sub(cursor) > capacity {
+                resync(self);
+                continue;
+            }
+            let prefix = copy_wrapped(data, cursor, MESSAGE_HEADER_SIZE);
+            let len = u16::from_le_bytes([prefix[0], prefix[1]]) as usize;
+            let flags = u16::from_le_bytes([prefix[2], prefix[3]]);
+            let total = tapped_frame_size(len, flags) as u32;
+            let payload = copy_wrapped(data, cursor.wrapping_add(MESSAGE_HEADER_SIZE as u32), len);
+            let after = header.write_pos.load(Ordering::Acquire);
+            let intact = after.wrapping_sub(cursor) <= capacity
+                && (MIN_MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&len)
+                && total <= write.wrapping_sub(cursor);
+            if !intact {
+                resync(self);
+                if self.cursors[ring] == cursor {
+                    // read_pos стоит на том же кадре: он действительно битый.
+                    self.cursors[ring] = write;
+                }
+                continue;
+            }
+            self.cursors[ring] = cursor.wrapping_add(total);
+            return Some(TappedFrame {
+                direction: if ring == 0 {
+                    ChannelKind::ServerToClient
+                } else {
+                    ChannelKind::ClientToServer
+                },
+                flags,
+                payload,
+            });
+        }
+    }
+}
+
+fn handshake_name(state: u32) -> &'static str {
+    match state {
+        HANDSHAKE_IDLE => "IDLE",
+        HANDSHAKE_CLIENT_HELLO => "CLIENT_HELLO",
+        HANDSHAKE_SERVER_READY => "SERVER_READY",
+        _ => "UNKNOWN",
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, base: u32, bytes: &[u8]) -> fmt::Result {
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let offset = base.wrapping_add(line as u32 * 16) & RING_MASK;
+        write!(f, "    {offset:08x}:")?;
+        for byte in chunk {
+            write!(f, " {byte:02x}")?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for RingDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let used = self.write_pos.wrapping_sub(self.read_pos);
+        writeln!(f, "ring {:?}:", self.direction)?;
+        writeln!(
+            f,
+            "  write_pos={} read_pos={} used={}/{} messages={}",
+            self.write_pos, self.read_pos, used, RING_CAPACITY, self.message_count
+        )?;
+        writeln!(
+            f,
+            "  drops={} checksum_errors={} sequence={} connection_gen={} handshake={} ({})",
+            self.drop_count,
+            self.checksum_errors,
+            self.sequence,
+            self.connection_gen,
+            handshake_name(self.handshake_state),
+            self.handshake_state
+        )?;
+        writeln!(f, "  bytes at read_pos:")?;
+        write_hex(f, self.read_pos, &self.bytes_at_read)?;
+        writeln!(f, "  bytes before write_pos:")?;
+        write_hex(
+            f,
+            self.write_pos.wrapping_sub(DUMP_WINDOW as u32),
+            &self.bytes_before_write,
+        )
+    }
+}
+
+impl fmt::Display for ChannelDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "xshm channel dump")?;
+        writeln!(
+            f,
+            "control: magic={:#010x} version={:#010x} generation={}",
+            self.magic, self.version, self.generation
+        )?;
+        writeln!(
+            f,
+            "  server_state={} ({}) client_state={} ({})",
+            handshake_name(self.server_state),
+            self.server_state,
+            handshake_name(self.client_state),
+            self.client_state
+        )?;
+        writeln!(f, "  reserved={:?}", self.reserved)?;
+        write!(f, "{}", self.server_to_client)?;
+        write!(f, "{}", self.client_to_server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MSG_FLAG_COMMIT, SHARED_MAGIC};
+    use crate::{SharedClient, SharedServer};
+    use std::time::Duration;
+
+    #[test]
+    fn dump_reflects_handshake_and_ring_contents() {
+        let name = format!("XSHM_DUMP_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        server.send_to_client(b"dump-me").unwrap();
+
+        let dump = server.diagnostic_dump();
+        assert_eq!(dump.magic, SHARED_MAGIC);
+        assert_eq!(dump.server_state, HANDSHAKE_SERVER_READY);
+        assert_eq!(dump.server_to_client.message_count, 1);
+        assert_eq!(dump.server_to_client.read_pos, 0);
+        // Заголовок кадра (длина 7, только метка фиксации круга 0), затем
+        // payload.
+        let flags = MSG_FLAG_COMMIT.to_le_bytes();
+        assert_eq!(
+            &dump.server_to_client.bytes_at_read[..4],
+            &[7, 0, flags[0], flags[1]]
+        );
+        assert_eq!(&dump.server_to_client.bytes_at_read[4..11], b"dump-me");
+        assert_eq!(
+            &dump.server_to_client.bytes_before_write[DUMP_WINDOW - 7..],
+            b"dump-me"
+        );
+
+        // Снимок по имени и снимок клиента видят ту же секцию.
+        assert_eq!(dump_by_name(&name).unwrap(), dump);
+        assert_eq!(client.diagnostic_dump(), dump);
+
+        let report = dump.to_string();
+        assert!(report.contains("server_state=SERVER_READY"));
+        assert!(report.contains("ring ServerToClient"));
+    }
+
+    #[test]
+    fn tap_sees_new_frames_without_consuming_them() {
+        let name = format!("XSHM_TAP_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        server.send_to_client(b"before tap").unwrap();
+
+        let mut tap = ChannelTap::open(&name).unwrap();
+        assert_eq!(tap.next_frame(), None);
+        server.set_timestamps(true);
+        server.send_to_client(b"to client").unwrap();
+        client.send_to_server(b"to server").unwrap();
+
+        let first = tap.next_frame().unwrap();
+        assert_eq!(first.direction, ChannelKind::ServerToClient);
+        assert_eq!(first.payload, b"to client");
+        assert_ne!(first.flags & MSG_FLAG_TIMESTAMP, 0);
+        let second = tap.next_frame().unwrap();
+        assert_eq!(second.direction, ChannelKind::ClientToServer);
+        assert_eq!(second.payload, b"to server");
+        assert_eq!(tap.next_frame(), None);
+        assert_eq!(tap.lost(), 0);
+
+        // Наблюдатель ничего не забрал у получателей.
+        let mut buffer = Vec::new();
+        client.receive_from_server(&mut buffer).unwrap();
+        assert_eq!(buffer, b"before tap");
+        server.receive_from_client(&mut buffer).unwrap();
+        assert_eq!(buffer, b"to server");
+    }
+
+    #[test]
+    fn dump_of_missing_channel_fails() {
+        assert!(dump_by_name(&format!("XSHM_DUMP_MISSING_{}", std::process::id())).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Причина отключения, которую уходящая сторона оставляет пиру.
+
+use crate::constants::{
+    DISCONNECT_REASON_GRACEFUL, DISCONNECT_REASON_KICKED, DISCONNECT_REASON_PROTOCOL_ERROR,
+    DISCONNECT_REASON_UNKNOWN, DISCONNECT_REASON_UPGRADING,
+};
+
+/// Почему пир отключился (`RESERVED_*_DISCONNECT_REASON_INDEX` control
+/// block). Пишется перед событием disconnect:
+/// `SharedServer::disconnect`/`SharedClient::disconnect` -- с указанной
+/// причиной, drop -- `Graceful`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DisconnectReason {
+    /// Причина не указана: пир упал, не отключившись, или собран старой
+    /// версией.
+    #[default]
+    Unknown,
+    /// Штатное закрытие.
+    Graceful,
+    /// Пир нарушил протокол (битый кусок сообщения, чужой ключ).
+    ProtocolError,
+    /// Сервер отключил клиента по своей инициативе.
+    Kicked,
+    /// Сторона уходит на обновление и скоро вернётся.
+    Upgrading,
+    /// Код, которого эта сборка не знает (пир новее).
+    Other(u32),
+}
+
+impl DisconnectReason {
+    /// Причина по коду `DISCONNECT_REASON_*`.
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            DISCONNECT_REASON_UNKNOWN => Self::Unknown,
+            DISCONNECT_REASON_GRACEFUL => Self::Graceful,
+            DISCONNECT_REASON_PROTOCOL_ERROR => Self::ProtocolError,
+            DISCONNECT_REASON_KICKED => Self::Kicked,
+            DISCONNECT_REASON_UPGRADING => Self::Upgrading,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Код `DISCONNECT_REASON_*` для control block.
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Unknown => DISCONNECT_REASON_UNKNOWN,
+            Self::Graceful => DISCONNECT_REASON_GRACEFUL,
+            Self::ProtocolError => DISCONNECT_REASON_PROTOCOL_ERROR,
+            Self::Kicked => DISCONNECT_REASON_KICKED,
+            Self::Upgrading => DISCONNECT_REASON_UPGRADING,
+            Self::Other(code) => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for reason in [
+            DisconnectReason::Unknown,
+            DisconnectReason::Graceful,
+            DisconnectReason::ProtocolError,
+            DisconnectReason::Kicked,
+            DisconnectReason::Upgrading,
+            DisconnectReason::Other(0x100),
+        ] {
+            assert_eq!(DisconnectReason::from_code(reason.code()), reason);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Обнаружение каналов по именам секций.
+//!
+//! [`discover`] перечисляет именованные секции, видимые процессу
+//! (session-local и `Global\` каталоги объектов на Windows, `/dev/shm` на
+//! Linux), оставляет те, чьё базовое имя начинается с префикса, и
+//! открывает каждую только для чтения control block: секции без
+//! `SHARED_MAGIC` (arena, блоки состояния, чужие объекты) отбрасываются.
+//! В отличие от [`registry`](crate::registry) серверу ничего запускать не
+//! нужно -- виден любой канал, в том числе `SharedServer` без auto-слоя.
+//! Другие Unix перечислять shm-объекты не умеют: там список пуст.
+//!
+//! ```no_run
+//! for channel in xshm::discover("Billing") {
+//!     println!("{} v{:#x} {:?}", channel.name, channel.version, channel.owner);
+//! }
+//! ```
+
+use std::sync::atomic::Ordering;
+
+use crate::constants::{
+    version_compatible, HANDSHAKE_SERVER_READY, LAYOUT_FLAG_CONTROL_RINGS,
+    RESERVED_LAYOUT_FLAGS_INDEX, SHARED_MAGIC,
+};
+use crate::naming::{self, Namespace};
+use crate::platform::{self, Mapping, PlatformMapping};
+use crate::reclaim::{self, ChannelOwner};
+use crate::shared::SharedView;
+
+/// Канал, найденный [`discover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelInfo {
+    /// Базовое имя -- то, что передаётся в `SharedClient::connect`.
+    pub name: String,
+    /// Namespace секции (подключаться через `SharedClient::connect_in`).
+    pub namespace: Namespace,
+    /// Версия протокола сервера (`SHARED_VERSION` его сборки).
+    pub version: u32,
+    /// Версия и раскладка совместимы с этой сборкой: `connect` не
+    /// отвергнет канал из-за `VersionMismatch`/`LayoutMismatch`.
+    pub compatible: bool,
+    /// Процесс сервера по PID из control block.
+    pub owner: ChannelOwner,
+    /// Клиент подключён (handshake завершён с обеих сторон).
+    pub client_connected: bool,
+    /// Канал с управляющими кольцами.
+    pub control_rings: bool,
+}
+
+/// Каналы, базовое имя которых начинается с `prefix` (`""` -- все),
+/// отсортированные по имени. Перечислить секции не удалось -- пустой
+/// список.
+pub fn discover(prefix: &str) -> Vec<ChannelInfo> {
+    let mut channels: Vec<ChannelInfo> = platform::list_sections()
+        .iter()
+        .filter_map(|section| probe(section, prefix))
+        .collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    channels
+}
+
+fn probe(section: &str, prefix: &str) -> Option<ChannelInfo> {
+    let (namespace, name) = match section.strip_prefix("Global\\") {
+        Some(name) => (Namespace::Global, name),
+        #[cfg(feature = "mock")]
+        None if section.starts_with(naming::MOCK_PREFIX) => {
+            (Namespace::Mock, &section[naming::MOCK_PREFIX.len()..])
+        }
+        None => (
+            Namespace::Session,
+            section.strip_prefix("Local\\").unwrap_or(section),
+        ),
+    };
+    if !name.starts_with(prefix) || naming::validate(name).is_err() {
+        return None;
+    }
+    // Секция меньше shared_mapping_size() (события на Unix, блоки
+    // состояния) не открывается.
+    let mapping = Mapping::open(section).ok()?;
+    // SAFETY: отображение размером shared_mapping_size() живёт до конца
+    // функции, дольше view.
+    let view = unsafe { SharedView::new(mapping.as_ptr()) };
+    let control = view.control_block();
+    if control.magic != SHARED_MAGIC {
+        return None;
+    }
+    let flags = control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+    Some(ChannelInfo {
+        name: name.to_owned(),
+        namespace,
+        version: control.version,
+        compatible: version_compatible(control.version) && view.check_layout().is_ok(),
+        owner: reclaim::owner_of(&view),
+        client_connected: control.server_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY
+            && control.client_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY,
+        control_rings: flags & LAYOUT_FLAG_CONTROL_RINGS != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::{SharedClient, SharedServer};
+
+    #[test]
+    fn lists_channels_by_prefix() {
+        let prefix = format!("XSHM_DISCOVER_{}_", std::process::id());
+        let idle_name = format!("{prefix}IDLE");
+        let busy_name = format!("{prefix}BUSY");
+        let _idle = SharedServer::start(&idle_name).unwrap();
+        let mut busy = SharedServer::start_with_control_rings(&busy_name).unwrap();
+        let connector = std::thread::spawn({
+            let name = busy_name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        busy.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let _client = connector.join().unwrap();
+
+        let found = discover(&prefix);
+        if cfg!(all(unix, not(target_os = "linux"))) {
+            assert!(found.is_empty());
+            return;
+        }
+        let names: Vec<&str> = found.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, [busy_name.as_str(), idle_name.as_str()]);
+
+        let pid = ChannelOwner::Alive(std::process::id());
+        let (busy_info, idle_info) = (&found[0], &found[1]);
+        assert!(busy_info.client_connected && busy_info.control_rings);
+        assert!(!idle_info.client_connected && !idle_info.control_rings);
+        for info in &found {
+            assert_eq!(info.owner, pid);
+            assert_eq!(info.namespace, Namespace::Session);
+            assert!(info.compatible);
+        }
+    }
+}
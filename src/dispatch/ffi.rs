@@ -6,11 +6,10 @@ use std::ffi::CStr;
 use std::os::raw::{c_char, c_void};
 use std::ptr::null_mut;
 use std::sync::Arc;
-use std::time::Duration;
 
 use crate::constants::MAX_MESSAGE_SIZE;
 use crate::error::ShmError;
-use crate::ffi::shm_error_t;
+use crate::ffi::{ffi_count_or, ffi_millis_or, shm_error_t};
 
 use super::{
     ClientRegistration, DispatchClient, DispatchClientHandler, DispatchClientOptions,
@@ -60,7 +59,7 @@ pub struct shm_dispatch_client_callbacks_t {
     pub user_data: *mut c_void,
 }
 
-/// Настройки сервера.
+/// Настройки сервера; нулевые поля -- значения по умолчанию.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct shm_dispatch_options_t {
@@ -81,7 +80,7 @@ impl Default for shm_dispatch_options_t {
     }
 }
 
-/// Настройки клиента.
+/// Настройки клиента; нулевые поля -- значения по умолчанию.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct shm_dispatch_client_options_t {
@@ -241,15 +240,20 @@ fn to_c_string_lossy(s: &str) -> std::ffi::CString {
 /// # Safety
 /// `ptr` обязан быть валидным указателем на `shm_dispatch_options_t` либо null.
 unsafe fn to_dispatch_options(ptr: *const shm_dispatch_options_t) -> DispatchOptions {
+    let defaults = DispatchOptions::default();
     if ptr.is_null() {
-        return DispatchOptions::default();
+        return defaults;
     }
     let opts = unsafe { *ptr };
     DispatchOptions {
-        lobby_timeout: Duration::from_millis(opts.lobby_timeout_ms as u64),
-        channel_connect_timeout: Duration::from_millis(opts.channel_connect_timeout_ms as u64),
-        poll_timeout: Duration::from_millis(opts.poll_timeout_ms as u64),
-        recv_batch: opts.recv_batch as usize,
+        lobby_timeout: ffi_millis_or(opts.lobby_timeout_ms, defaults.lobby_timeout),
+        channel_connect_timeout: ffi_millis_or(
+            opts.channel_connect_timeout_ms,
+            defaults.channel_connect_timeout,
+        ),
+        poll_timeout: ffi_millis_or(opts.poll_timeout_ms, defaults.poll_timeout),
+        recv_batch: ffi_count_or(opts.recv_batch, defaults.recv_batch),
+        ..defaults
     }
 }
 
@@ -258,17 +262,19 @@ unsafe fn to_dispatch_options(ptr: *const shm_dispatch_options_t) -> DispatchOpt
 unsafe fn to_dispatch_client_options(
     ptr: *const shm_dispatch_client_options_t,
 ) -> DispatchClientOptions {
+    let defaults = DispatchClientOptions::default();
     if ptr.is_null() {
-        return DispatchClientOptions::default();
+        return defaults;
     }
     let opts = unsafe { *ptr };
     DispatchClientOptions {
-        lobby_timeout: Duration::from_millis(opts.lobby_timeout_ms as u64),
-        response_timeout: Duration::from_millis(opts.response_timeout_ms as u64),
-        channel_timeout: Duration::from_millis(opts.channel_timeout_ms as u64),
-        poll_timeout: Duration::from_millis(opts.poll_timeout_ms as u64),
-        recv_batch: opts.recv_batch as usize,
-        max_send_queue: opts.max_send_queue as usize,
+        lobby_timeout: ffi_millis_or(opts.lobby_timeout_ms, defaults.lobby_timeout),
+        response_timeout: ffi_millis_or(opts.response_timeout_ms, defaults.response_timeout),
+        channel_timeout: ffi_millis_or(opts.channel_timeout_ms, defaults.channel_timeout),
+        poll_timeout: ffi_millis_or(opts.poll_timeout_ms, defaults.poll_timeout),
+        recv_batch: ffi_count_or(opts.recv_batch, defaults.recv_batch),
+        max_send_queue: ffi_count_or(opts.max_send_queue, defaults.max_send_queue),
+        ..defaults
     }
 }
 
@@ -14,21 +14,31 @@
 //! Обмен 1:1 на выделенном канале
 //! ```
 
+#[cfg(feature = "ffi")]
 pub mod ffi;
 pub mod protocol;
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-use crate::auto::{AutoClient, AutoHandler, AutoOptions, AutoServer, ChannelKind};
+use crate::auto::{
+    ensure_send_queue_bytes, AutoClient, AutoHandler, AutoOptions, AutoServer, AutoStatsSnapshot,
+    ChannelKind, MessageFilter,
+};
+use crate::cancel::CancellationToken;
 use crate::client::SharedClient;
 use crate::constants::MAX_MESSAGE_SIZE;
-use crate::error::{Result, ShmError};
+use crate::disconnect::DisconnectReason;
+use crate::error::{ensure_config, Result, ShmError};
+use crate::health::{ChannelHealth, LinkState};
+use crate::metrics::{self, ChannelRole, MetricsSource};
+use crate::naming::Namespace;
+use crate::platform::{self, PlatformEvent};
+use crate::registry::{self, ServiceKind, ServiceSource};
 use crate::server::SharedServer;
-use crate::wait_delay;
 
 pub use protocol::{RegistrationRequest, RegistrationResponse};
 
@@ -50,6 +60,16 @@ pub trait DispatchHandler: Send + Sync + 'static {
     /// Вызывается при отключении клиента от выделенного канала.
     fn on_client_disconnect(&self, client_id: u32);
 
+    /// Отключение клиента с причиной: что оставил клиент (`Unknown` --
+    /// клиент упал), `Kicked` -- `disconnect_client`, `Graceful` --
+    /// остановка сервера, `ProtocolError` -- канал сброшен из-за нарушения
+    /// протокола клиентом. По умолчанию передаёт вызов в
+    /// `on_client_disconnect`.
+    fn on_client_disconnect_with_reason(&self, client_id: u32, reason: DisconnectReason) {
+        let _ = reason;
+        self.on_client_disconnect(client_id);
+    }
+
     /// Вызывается при получении сообщения от клиента по выделенному каналу.
     fn on_message(&self, client_id: u32, data: &[u8]);
 
@@ -87,6 +107,20 @@ pub struct DispatchOptions {
     pub poll_timeout: Duration,
     /// Количество сообщений за один цикл на каждом клиентском канале.
     pub recv_batch: usize,
+    /// CRC-32 на сообщениях выделенных каналов (см. `AutoOptions::checksum`).
+    pub checksum: bool,
+    /// Шифрование выделенных каналов (см. `AutoOptions::encryption`).
+    #[cfg(feature = "encryption")]
+    pub encryption: Option<crate::crypto::EncryptionOptions>,
+    /// Внешний токен отмены лобби и всех выделенных каналов (см.
+    /// `AutoOptions::cancel`).
+    pub cancel: Option<CancellationToken>,
+    /// Фильтр входящих сообщений всех выделенных каналов (см.
+    /// `AutoOptions::filter`).
+    pub filter: Option<MessageFilter>,
+    /// Namespace лобби и выделенных каналов (см. `AutoOptions::namespace`);
+    /// у клиентов (`DispatchClientOptions::namespace`) должен совпадать.
+    pub namespace: Namespace,
 }
 
 impl Default for DispatchOptions {
@@ -96,10 +130,101 @@ impl Default for DispatchOptions {
             channel_connect_timeout: Duration::from_secs(30),
             poll_timeout: Duration::from_millis(50),
             recv_batch: 32,
+            checksum: false,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            cancel: None,
+            filter: None,
+            namespace: Namespace::Session,
         }
     }
 }
 
+impl DispatchOptions {
+    /// Построитель с проверкой значений в [`DispatchOptionsBuilder::build`].
+    pub fn builder() -> DispatchOptionsBuilder {
+        DispatchOptionsBuilder::default()
+    }
+
+    /// Отбрасывает нулевые таймауты и пачку. Вызывается и в
+    /// `DispatchServer::start`.
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(
+            !self.lobby_timeout.is_zero(),
+            "lobby_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.channel_connect_timeout.is_zero(),
+            "channel_connect_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.poll_timeout.is_zero(),
+            "poll_timeout must be non-zero",
+        )?;
+        ensure_config(self.recv_batch > 0, "recv_batch must be non-zero")
+    }
+}
+
+/// Построитель [`DispatchOptions`]; незаданные поля -- из `Default`.
+#[derive(Clone, Default)]
+pub struct DispatchOptionsBuilder {
+    options: DispatchOptions,
+}
+
+impl DispatchOptionsBuilder {
+    pub fn lobby_timeout(mut self, lobby_timeout: Duration) -> Self {
+        self.options.lobby_timeout = lobby_timeout;
+        self
+    }
+
+    pub fn channel_connect_timeout(mut self, channel_connect_timeout: Duration) -> Self {
+        self.options.channel_connect_timeout = channel_connect_timeout;
+        self
+    }
+
+    pub fn poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.options.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn recv_batch(mut self, recv_batch: usize) -> Self {
+        self.options.recv_batch = recv_batch;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, encryption: Option<crate::crypto::EncryptionOptions>) -> Self {
+        self.options.encryption = encryption;
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.options.cancel = cancel;
+        self
+    }
+
+    pub fn filter(mut self, filter: Option<MessageFilter>) -> Self {
+        self.options.filter = filter;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    /// Проверяет значения (см. [`DispatchOptions::validate`]).
+    pub fn build(self) -> Result<DispatchOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
 /// Настройки DispatchClient.
 #[derive(Clone)]
 pub struct DispatchClientOptions {
@@ -115,6 +240,19 @@ pub struct DispatchClientOptions {
     pub recv_batch: usize,
     /// Максимум сообщений в очереди перед сбросом самого старого.
     pub max_send_queue: usize,
+    /// Бюджет очереди в байтах (см. `AutoOptions::max_send_queue_bytes`).
+    pub max_send_queue_bytes: Option<usize>,
+    /// CRC-32 на сообщениях выделенного канала (см. `AutoOptions::checksum`).
+    pub checksum: bool,
+    /// Шифрование выделенного канала (см. `AutoOptions::encryption`).
+    #[cfg(feature = "encryption")]
+    pub encryption: Option<crate::crypto::EncryptionOptions>,
+    /// Внешний токен отмены выделенного канала (см. `AutoOptions::cancel`).
+    pub cancel: Option<CancellationToken>,
+    /// Сколько `Drop` ждёт отправки очереди (см. `AutoOptions::flush_on_drop`).
+    pub flush_on_drop: Option<Duration>,
+    /// Namespace лобби и выделенного канала (`DispatchOptions::namespace`).
+    pub namespace: Namespace,
 }
 
 impl Default for DispatchClientOptions {
@@ -126,10 +264,123 @@ impl Default for DispatchClientOptions {
             poll_timeout: Duration::from_millis(50),
             recv_batch: 32,
             max_send_queue: 256,
+            max_send_queue_bytes: None,
+            checksum: false,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            cancel: None,
+            flush_on_drop: None,
+            namespace: Namespace::Session,
         }
     }
 }
 
+impl DispatchClientOptions {
+    /// Построитель с проверкой значений в [`DispatchClientOptionsBuilder::build`].
+    pub fn builder() -> DispatchClientOptionsBuilder {
+        DispatchClientOptionsBuilder::default()
+    }
+
+    /// Отбрасывает нулевые таймауты, пачку и очередь отправки. Вызывается
+    /// и в `DispatchClient::connect`.
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(
+            !self.lobby_timeout.is_zero(),
+            "lobby_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.response_timeout.is_zero(),
+            "response_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.channel_timeout.is_zero(),
+            "channel_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.poll_timeout.is_zero(),
+            "poll_timeout must be non-zero",
+        )?;
+        ensure_config(self.recv_batch > 0, "recv_batch must be non-zero")?;
+        ensure_config(self.max_send_queue > 0, "max_send_queue must be non-zero")?;
+        ensure_send_queue_bytes(self.max_send_queue_bytes)
+    }
+}
+
+/// Построитель [`DispatchClientOptions`]; незаданные поля -- из `Default`.
+#[derive(Clone, Default)]
+pub struct DispatchClientOptionsBuilder {
+    options: DispatchClientOptions,
+}
+
+impl DispatchClientOptionsBuilder {
+    pub fn lobby_timeout(mut self, lobby_timeout: Duration) -> Self {
+        self.options.lobby_timeout = lobby_timeout;
+        self
+    }
+
+    pub fn response_timeout(mut self, response_timeout: Duration) -> Self {
+        self.options.response_timeout = response_timeout;
+        self
+    }
+
+    pub fn channel_timeout(mut self, channel_timeout: Duration) -> Self {
+        self.options.channel_timeout = channel_timeout;
+        self
+    }
+
+    pub fn poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.options.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn recv_batch(mut self, recv_batch: usize) -> Self {
+        self.options.recv_batch = recv_batch;
+        self
+    }
+
+    pub fn max_send_queue(mut self, max_send_queue: usize) -> Self {
+        self.options.max_send_queue = max_send_queue;
+        self
+    }
+
+    pub fn max_send_queue_bytes(mut self, max_send_queue_bytes: Option<usize>) -> Self {
+        self.options.max_send_queue_bytes = max_send_queue_bytes;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, encryption: Option<crate::crypto::EncryptionOptions>) -> Self {
+        self.options.encryption = encryption;
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.options.cancel = cancel;
+        self
+    }
+
+    pub fn flush_on_drop(mut self, flush_on_drop: Option<Duration>) -> Self {
+        self.options.flush_on_drop = flush_on_drop;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    /// Проверяет значения (см. [`DispatchClientOptions::validate`]).
+    pub fn build(self) -> Result<DispatchClientOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
 // ─── DispatchServer ──────────────────────────────────────────────────────────
 
 /// Активный клиент на выделенном канале.
@@ -144,11 +395,22 @@ struct DispatchedClient {
 /// Общая карта клиентов, доступная и серверу, и proxy-обработчикам.
 type ClientMap = Arc<RwLock<HashMap<u32, DispatchedClient>>>;
 
+/// В `metrics` dispatch-сервер -- сумма по выделенным каналам клиентов.
+impl MetricsSource for RwLock<HashMap<u32, DispatchedClient>> {
+    fn snapshot(&self) -> AutoStatsSnapshot {
+        let mut total = AutoStatsSnapshot::default();
+        for client in self.read().unwrap().values() {
+            total.accumulate(&client.server.stats());
+        }
+        total
+    }
+}
+
 /// Центральный dispatch-сервер — одно лобби, динамические каналы на клиента.
 pub struct DispatchServer {
     base_name: String,
     clients: ClientMap,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
     next_client_id: Arc<AtomicU32>,
     worker_handle: Mutex<Option<JoinHandle<()>>>,
     /// Потоки, ожидающие подключения клиента к выделенному каналу (см.
@@ -158,6 +420,7 @@ pub struct DispatchServer {
     pending_connects: Mutex<Vec<JoinHandle<()>>>,
     handler: Arc<dyn DispatchHandler>,
     options: DispatchOptions,
+    _metrics: metrics::Registration,
 }
 
 impl DispatchServer {
@@ -167,18 +430,24 @@ impl DispatchServer {
         handler: Arc<dyn DispatchHandler>,
         options: DispatchOptions,
     ) -> Result<Arc<Self>> {
-        let running = Arc::new(AtomicBool::new(true));
+        options.validate()?;
+        let stop = CancellationToken::for_worker(options.cancel.as_ref())?;
 
+        let clients: ClientMap = Arc::new(RwLock::new(HashMap::new()));
+        let metrics_source: Weak<dyn MetricsSource> = Arc::downgrade(&clients) as Weak<_>;
         let server = Arc::new(Self {
             base_name: name.to_owned(),
-            clients: Arc::new(RwLock::new(HashMap::new())),
-            running,
+            clients,
+            stop,
             next_client_id: Arc::new(AtomicU32::new(1)),
             worker_handle: Mutex::new(None),
             pending_connects: Mutex::new(Vec::new()),
             handler,
             options,
+            _metrics: metrics::register(name, ChannelRole::Server, metrics_source),
         });
+        let weak: Weak<Self> = Arc::downgrade(&server);
+        registry::register(name, ServiceKind::Dispatch, weak);
 
         let server_clone = server.clone();
         let name_owned = name.to_owned();
@@ -193,7 +462,12 @@ impl DispatchServer {
             builder = builder.name(format!("xsd-{name}"));
         }
         let handle = builder
-            .spawn(move || server_clone.worker_loop(&name_owned))
+            .spawn(move || {
+                server_clone.stop.run_worker(
+                    || server_clone.worker_loop(&name_owned),
+                    |err| server_clone.handler.on_error(None, err),
+                )
+            })
             .map_err(|e| ShmError::WindowsError {
                 code: e.raw_os_error().unwrap_or(-1) as u32,
                 context: "spawn dispatch worker",
@@ -230,7 +504,8 @@ impl DispatchServer {
             // Помечаем как отключённого, чтобы AutoProxyHandler не уведомил повторно
             client.disconnected.store(true, Ordering::Release);
             client.server.stop();
-            self.handler.on_client_disconnect(client_id);
+            self.handler
+                .on_client_disconnect_with_reason(client_id, DisconnectReason::Kicked);
             Ok(())
         } else {
             Err(ShmError::NotConnected)
@@ -252,6 +527,16 @@ impl DispatchServer {
         self.clients.read().unwrap().contains_key(&client_id)
     }
 
+    /// Состояние выделенного канала клиента (см. `xshm::health`); `None`
+    /// -- клиент не подключён.
+    pub fn health(&self, client_id: u32) -> Option<ChannelHealth> {
+        self.clients
+            .read()
+            .unwrap()
+            .get(&client_id)
+            .map(|c| c.server.health())
+    }
+
     /// Возвращает данные регистрации клиента.
     pub fn client_info(&self, client_id: u32) -> Option<ClientRegistration> {
         self.clients
@@ -280,7 +565,7 @@ impl DispatchServer {
     /// освободить `user_data` сразу после возврата. Идемпотентна (повторный
     /// вызов — no-op, обе очереди handle-ов уже опустошены).
     pub fn stop(&self) {
-        self.running.store(false, Ordering::Release);
+        self.stop.cancel();
         // Джойнится потоком-владельцем Self (не самим worker'ом — stop()
         // не вызывается изнутри worker_loop), поэтому не self-join. Тот же
         // фикс, что и для MultiServer::stop() (аудит 2026-07-10): worker
@@ -291,14 +576,20 @@ impl DispatchServer {
         }
         // Lobby worker уже остановлен -> новых pending-connect потоков не
         // появится, можно безопасно забрать и заджойнить все существующие.
-        // Каждый из них проверяет `running` и завершится быстро (не будет
-        // ждать полный channel_connect_timeout), т.к. running уже false.
+        // Каждый из них проверяет токен остановки и завершится быстро (не
+        // будет ждать полный channel_connect_timeout), т.к. он уже выставлен.
         let pending: Vec<_> = self.pending_connects.lock().unwrap().drain(..).collect();
         for handle in pending {
             let _ = handle.join();
         }
     }
 
+    /// Worker остановлен: `stop()`, отмена токена из опций или паника
+    /// (о ней сообщает `on_error` с `ShmError::WorkerPanicked`).
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
     /// Базовое имя dispatch-сервера.
     pub fn base_name(&self) -> &str {
         &self.base_name
@@ -343,13 +634,14 @@ impl DispatchServer {
     fn worker_loop(&self, base_name: &str) {
         let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
 
-        while self.running.load(Ordering::Acquire) {
+        while !self.stop.is_cancelled() {
             // Создаём лобби (или пересоздаём при ошибке)
-            let mut lobby_server = match SharedServer::start(base_name) {
+            let lobby = SharedServer::builder(base_name).namespace(self.options.namespace);
+            let mut lobby_server = match lobby.start() {
                 Ok(s) => s,
                 Err(err) => {
                     self.handler.on_error(None, err);
-                    if !wait_delay(&self.running, self.options.poll_timeout) {
+                    if !self.stop.sleep(self.options.poll_timeout) {
                         break;
                     }
                     continue;
@@ -357,8 +649,9 @@ impl DispatchServer {
             };
 
             // Внутренний цикл: последовательный приём клиентов через лобби
-            while self.running.load(Ordering::Acquire) {
-                match lobby_server.wait_for_client(Some(self.options.poll_timeout)) {
+            while !self.stop.is_cancelled() {
+                let timeout = Some(self.options.poll_timeout);
+                match lobby_server.wait_for_client_or_cancel(timeout, &self.stop) {
                     Ok(()) => {
                         // Клиент подключился — обрабатываем регистрацию
                         self.handle_lobby_client(&mut lobby_server, &mut buffer);
@@ -366,10 +659,11 @@ impl DispatchServer {
                         // Сбрасываем лобби для следующего клиента
                         lobby_server.mark_disconnected();
                     }
-                    Err(ShmError::Timeout) => continue,
                     Err(ShmError::AlreadyConnected) => {
                         lobby_server.mark_disconnected();
                     }
+                    Err(ShmError::Cancelled) => break,
+                    Err(err) if err.is_retryable() => continue,
                     Err(err) => {
                         self.handler.on_error(None, err);
                         break; // Пересоздаём лобби при серьёзной ошибке
@@ -383,7 +677,8 @@ impl DispatchServer {
         for (id, client) in clients.drain() {
             client.disconnected.store(true, Ordering::Release);
             client.server.stop();
-            self.handler.on_client_disconnect(id);
+            self.handler
+                .on_client_disconnect_with_reason(id, DisconnectReason::Graceful);
         }
     }
 
@@ -413,7 +708,7 @@ impl DispatchServer {
                 return;
             }
 
-            if !self.running.load(Ordering::Acquire) {
+            if self.stop.is_cancelled() {
                 return;
             }
 
@@ -427,9 +722,11 @@ impl DispatchServer {
                     }
                 },
                 Err(ShmError::QueueEmpty) => {
-                    // Блокируемся на событии — просыпаемся, когда клиент запишет данные
+                    // Блокируемся на событии — просыпаемся, когда клиент запишет
+                    // данные или сервер остановят
                     let wait_time = remaining.min(self.options.poll_timeout);
-                    let _ = events.c2s.data.wait(Some(wait_time));
+                    let handles = [events.c2s.data.raw_handle(), self.stop.raw_handle()];
+                    let _ = platform::wait_any(&handles, Some(wait_time));
                     continue;
                 }
                 Err(err) => {
@@ -462,10 +759,18 @@ impl DispatchServer {
             connect_timeout: self.options.channel_connect_timeout,
             poll_timeout: self.options.poll_timeout,
             recv_batch: self.options.recv_batch,
+            checksum: self.options.checksum,
+            #[cfg(feature = "encryption")]
+            encryption: self.options.encryption.clone(),
+            // Выделенные каналы -- ветви токена сервера: его отмена (в том
+            // числе от внешнего родителя) гасит их сразу.
+            cancel: Some(self.stop.clone()),
+            filter: self.options.filter.clone(),
+            namespace: self.options.namespace,
             ..AutoOptions::default()
         };
 
-        let auto_server = match AutoServer::start(&channel_name, proxy_handler, auto_options) {
+        let auto_server = match AutoServer::start_unlisted(&channel_name, proxy_handler, auto_options) {
             Ok(s) => s,
             Err(err) => {
                 self.handler.on_error(None, err);
@@ -505,7 +810,7 @@ impl DispatchServer {
         // сразу после этого — лобби готово к следующему клиенту немедленно.
         let handler = self.handler.clone();
         let clients_map = Arc::clone(&self.clients);
-        let running = Arc::clone(&self.running);
+        let stop = self.stop.clone();
         let channel_connect_timeout = self.options.channel_connect_timeout;
         let poll_timeout = self.options.poll_timeout;
         let join_handle = thread::spawn(move || {
@@ -519,7 +824,7 @@ impl DispatchServer {
             // проверки (аудит 2026-07-10: тот же класс UAF-гонки, что и в
             // shm_multi_server_stop/shm_dispatch_server_stop).
             let client_connected = loop {
-                if !running.load(Ordering::Acquire) {
+                if stop.is_cancelled() {
                     break false;
                 }
                 let remaining = deadline.saturating_duration_since(std::time::Instant::now());
@@ -537,7 +842,7 @@ impl DispatchServer {
                 drop(guard);
             };
 
-            if !client_connected || !running.load(Ordering::Acquire) {
+            if !client_connected || stop.is_cancelled() {
                 auto_server.stop();
                 return;
             }
@@ -564,9 +869,15 @@ impl DispatchServer {
     }
 }
 
+impl ServiceSource for DispatchServer {
+    fn is_running(&self) -> bool {
+        !self.stop.is_cancelled()
+    }
+}
+
 impl Drop for DispatchServer {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::Release);
+        self.stop.cancel();
         if let Some(handle) = self.worker_handle.lock().unwrap().take() {
             let _ = handle.join();
         }
@@ -595,12 +906,12 @@ impl AutoHandler for AutoProxyHandler {
         cvar.notify_one();
     }
 
-    fn on_disconnect(&self) {
+    fn on_disconnect_with_reason(&self, reason: DisconnectReason) {
         // Проверяем, не обработано ли уже (например, через disconnect_client())
         //
         // ВАЖНО: `clients.remove(...)` результат обязательно привязывается к
         // переменной (`removed`), а не дропается тут же внутри блока с
-        // write-логом. `on_disconnect` вызывается СИНХРОННО из worker-потока
+        // write-логом. `on_disconnect_with_reason` вызывается СИНХРОННО из worker-потока
         // САМОГО AutoServer'а этого клиента; удаляемый `DispatchedClient`
         // содержит этот же `AutoServer` (поле `server`), а его `Drop`
         // синхронно джойнит свой `worker_handle` -- т.е. текущий поток. Дропни
@@ -629,7 +940,8 @@ impl AutoHandler for AutoProxyHandler {
             // поток -- он не является worker-потоком этого AutoServer, поэтому
             // join там безопасен и не self-join'ится.
             thread::spawn(move || drop(dispatched_client));
-            self.handler.on_client_disconnect(self.client_id);
+            self.handler
+                .on_client_disconnect_with_reason(self.client_id, reason);
         }
     }
 
@@ -655,6 +967,8 @@ pub struct DispatchClient {
     running: Arc<AtomicBool>,
     client_id: u32,
     channel_name: String,
+    /// Счётчики выделенного канала в `metrics` под именем лобби.
+    _metrics: metrics::Registration,
 }
 
 impl DispatchClient {
@@ -668,6 +982,7 @@ impl DispatchClient {
         handler: Arc<dyn DispatchClientHandler>,
         options: DispatchClientOptions,
     ) -> Result<Self> {
+        options.validate()?;
         // Фаза 1: подключение к лобби и регистрация (блокирующая)
         let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
         let (assigned_id, assigned_channel) =
@@ -684,11 +999,20 @@ impl DispatchClient {
             connect_timeout: options.channel_timeout,
             poll_timeout: options.poll_timeout,
             max_send_queue: options.max_send_queue,
+            max_send_queue_bytes: options.max_send_queue_bytes,
             recv_batch: options.recv_batch,
+            checksum: options.checksum,
+            #[cfg(feature = "encryption")]
+            encryption: options.encryption.clone(),
+            cancel: options.cancel.clone(),
+            flush_on_drop: options.flush_on_drop,
+            namespace: options.namespace,
             ..AutoOptions::default()
         };
 
-        let auto_client = AutoClient::connect(&assigned_channel, client_handler, auto_options)?;
+        let auto_client =
+            AutoClient::connect_unlisted(&assigned_channel, client_handler, auto_options)?;
+        let metrics = metrics::register(name, ChannelRole::Client, auto_client.metrics_source());
 
         handler.on_connect(assigned_id, &assigned_channel);
 
@@ -697,6 +1021,7 @@ impl DispatchClient {
             running,
             client_id: assigned_id,
             channel_name: assigned_channel,
+            _metrics: metrics,
         })
     }
 
@@ -735,15 +1060,42 @@ impl DispatchClient {
             client.stop();
         }
     }
+
+    /// Клиент остановлен: `stop()`, отмена токена из опций или паника
+    /// worker'а выделенного канала.
+    pub fn is_stopped(&self) -> bool {
+        !self.running.load(Ordering::Acquire)
+            || self
+                .auto_client
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_none_or(AutoClient::is_stopped)
+    }
+
+    /// Состояние выделенного канала (см. `xshm::health`).
+    pub fn health(&self) -> ChannelHealth {
+        let mut health = self
+            .auto_client
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or_else(ChannelHealth::stopped, AutoClient::health);
+        if !self.running.load(Ordering::Acquire) {
+            health.state = LinkState::Stopped;
+        }
+        health
+    }
 }
 
+/// Новые `send` отклоняются сразу; уже принятые сообщения дописываются
+/// drop'ом `AutoClient` (см. `DispatchClientOptions::flush_on_drop`).
 impl Drop for DispatchClient {
     fn drop(&mut self) {
         self.running.store(false, Ordering::Release);
-        let mut guard = self.auto_client.lock().unwrap();
-        if let Some(client) = guard.take() {
-            client.stop();
-        }
+        // `stop()` здесь оборвал бы flush; ждём вне lock-а.
+        let client = self.auto_client.lock().unwrap().take();
+        drop(client);
     }
 }
 
@@ -756,7 +1108,7 @@ fn lobby_register(
     options: &DispatchClientOptions,
     buffer: &mut Vec<u8>,
 ) -> Result<(u32, String)> {
-    let client = SharedClient::connect(base_name, options.lobby_timeout)?;
+    let client = SharedClient::connect_in(base_name, options.namespace, options.lobby_timeout)?;
 
     // Отправляем запрос на регистрацию
     let request = protocol::encode_request(&RegistrationRequest {
@@ -766,11 +1118,13 @@ fn lobby_register(
     });
     client.send_to_server(&request)?;
 
+    // Клиент, подключённый по имени, всегда с named events
+    let events = client.events().expect("named client always has events");
+
     // Сигналим серверу о наличии данных через событие
-    let _ = client.events().c2s.data.set();
+    let _ = events.c2s.data.set();
 
     // Ожидаем ответ через событие s2c.data (по событию, без опроса)
-    let events = client.events();
     let start = std::time::Instant::now();
     loop {
         let remaining = options.response_timeout.saturating_sub(start.elapsed());
@@ -833,6 +1187,7 @@ mod tests {
         disconnects: AtomicU32,
         messages: AtomicU32,
         last_pid: AtomicU32,
+        last_reason: Mutex<Option<DisconnectReason>>,
     }
 
     impl TestServerHandler {
@@ -842,6 +1197,7 @@ mod tests {
                 disconnects: AtomicU32::new(0),
                 messages: AtomicU32::new(0),
                 last_pid: AtomicU32::new(0),
+                last_reason: Mutex::new(None),
             }
         }
     }
@@ -854,6 +1210,10 @@ mod tests {
         fn on_client_disconnect(&self, _client_id: u32) {
             self.disconnects.fetch_add(1, Ordering::Relaxed);
         }
+        fn on_client_disconnect_with_reason(&self, client_id: u32, reason: DisconnectReason) {
+            *self.last_reason.lock().unwrap() = Some(reason);
+            self.on_client_disconnect(client_id);
+        }
         fn on_message(&self, _client_id: u32, _data: &[u8]) {
             self.messages.fetch_add(1, Ordering::Relaxed);
         }
@@ -1019,6 +1379,10 @@ mod tests {
 
         // Должно быть ровно 1 отключение (не двойное)
         assert_eq!(server_handler.disconnects.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            *server_handler.last_reason.lock().unwrap(),
+            Some(DisconnectReason::Kicked)
+        );
 
         client.stop();
         server.stop();
@@ -1088,7 +1452,7 @@ mod tests {
     }
 
     /// Регрессия на self-join deadlock (аудит 2026-07-10): когда клиент
-    /// отключается, `AutoProxyHandler::on_disconnect` (вызывается ИЗ
+    /// отключается, `AutoProxyHandler::on_disconnect_with_reason` (вызывается ИЗ
     /// собственного worker-потока AutoServer этого канала) раньше делал
     /// `clients.remove(&id)` без привязки результата к переменной -- временное
     /// значение `DispatchedClient` (содержащее `AutoServer`) дропалось тут же,
@@ -1130,7 +1494,7 @@ mod tests {
         assert_eq!(server.client_count(), 1);
 
         // Клиент отключается сам (не через disconnect_client) -- именно этот
-        // путь триггерит AutoProxyHandler::on_disconnect ИЗНУТРИ AutoServer'а.
+        // путь триггерит AutoProxyHandler::on_disconnect_with_reason ИЗНУТРИ AutoServer'а.
         client.stop();
         thread::sleep(Duration::from_millis(300));
 
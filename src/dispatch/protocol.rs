@@ -86,6 +86,10 @@ pub fn decode_request(data: &[u8]) -> Result<RegistrationRequest> {
     let pid = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
     let revision = u16::from_le_bytes([data[10], data[11]]);
     let name_len = data[12] as usize;
+    // encode_request никогда не пишет больше MAX_NAME_LEN.
+    if name_len > MAX_NAME_LEN {
+        return Err(ShmError::Corrupted);
+    }
 
     if data.len() < 13 + name_len {
         return Err(ShmError::MessageTooSmall);
@@ -163,6 +167,9 @@ pub fn decode_response(data: &[u8]) -> Result<RegistrationResponse> {
     let status = data[6];
     let client_id = u32::from_le_bytes([data[7], data[8], data[9], data[10]]);
     let name_len = data[11] as usize;
+    if name_len > MAX_CHANNEL_NAME_LEN {
+        return Err(ShmError::Corrupted);
+    }
 
     if data.len() < 12 + name_len {
         return Err(ShmError::MessageTooSmall);
@@ -235,6 +242,18 @@ mod tests {
         assert_eq!(decoded.channel_name, "NxT_a7f3b2c1");
     }
 
+    #[test]
+    fn oversized_name_len_is_rejected() {
+        let mut data = encode_request(&RegistrationRequest {
+            pid: 1,
+            revision: 1,
+            name: String::new(),
+        });
+        data[12] = (MAX_NAME_LEN + 1) as u8;
+        data.resize(13 + MAX_NAME_LEN + 1, b'A');
+        assert_eq!(decode_request(&data).unwrap_err(), ShmError::Corrupted);
+    }
+
     #[test]
     fn request_too_short() {
         assert!(decode_request(&[0; 10]).is_err());
@@ -1,8 +1,19 @@
+use std::fmt;
+use std::io;
+
+use crate::metrics::ChannelRole;
+
 /// Удобный тип результата для библиотеки.
 pub type Result<T> = std::result::Result<T, ShmError>;
 
 /// Ошибки, которые может возвращать библиотека.
+///
+/// Перечисление пополняется, поэтому `#[non_exhaustive]`: решать «повторить
+/// или сдаться» лучше через [`ShmError::is_retryable`],
+/// [`ShmError::is_disconnect`] и [`ShmError::category`], а не перебором
+/// вариантов.
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ShmError {
     /// Запрошенная операция недоступна, так как соединение отсутствует.
     #[error("endpoint is not connected")]
@@ -10,6 +21,10 @@ pub enum ShmError {
     /// Ресурс ожидает завершения другой операции (например, handshake).
     #[error("endpoint is not ready yet")]
     NotReady,
+    /// Канал сброшен после подключения (сменился generation в control
+    /// block): кольца принадлежат новому соединению, нужно переподключиться.
+    #[error("connection was reset (channel generation changed)")]
+    ConnectionReset,
     /// Половина соединения уже активна; повторное подключение невозможно.
     #[error("endpoint is already connected")]
     AlreadyConnected,
@@ -31,9 +46,33 @@ pub enum ShmError {
     /// Формат данных в буфере повреждён или некорректен.
     #[error("shared ring buffer is corrupted")]
     Corrupted,
+    /// Контрольная сумма сообщения не совпала: сообщение отброшено.
+    #[error("message checksum mismatch")]
+    ChecksumMismatch,
     /// Не удалось выполнить handshake между участниками.
     #[error("handshake failed")]
     HandshakeFailed,
+    /// Версия протокола пира вне совместимого диапазона.
+    #[error("protocol version mismatch: local {local:#010x}, peer {remote:#010x}")]
+    VersionMismatch {
+        /// Версия этой сборки (`SHARED_VERSION`).
+        local: u32,
+        /// Версия секции или клиента.
+        remote: u32,
+    },
+    /// Секция создана сборкой с другой геометрией колец или раскладкой
+    /// заголовков (другой cargo-профиль `ring-*` или версия xshm-core):
+    /// открыть её, не испортив память, нельзя.
+    #[error("section layout mismatch: {what} is {remote} in the section, {local} in this build")]
+    LayoutMismatch {
+        /// Что расходится: `"layout version"`, `"ring capacity"` или
+        /// `"max messages"`.
+        what: &'static str,
+        /// Значение этой сборки.
+        local: u32,
+        /// Значение из control block секции.
+        remote: u32,
+    },
     /// Системная ошибка Windows (NTSTATUS или Win32 код).
     #[error("windows error {code:#x} while {context}")]
     WindowsError {
@@ -42,10 +81,374 @@ pub enum ShmError {
         /// Контекст операции.
         context: &'static str,
     },
+    /// Ошибка ввода-вывода вне shared memory: сокет TCP-моста, файл
+    /// записи `record`.
+    #[error("i/o error ({kind}) while {context}")]
+    Io {
+        kind: io::ErrorKind,
+        /// Контекст операции.
+        context: &'static str,
+    },
     /// Нет свободных слотов на мультиклиентном сервере.
     #[error("no free slots available on multi-client server")]
     NoFreeSlot,
     /// Некорректная конфигурация (например, недопустимое число клиентов).
     #[error("invalid configuration: {0}")]
     InvalidConfig(&'static str),
+    /// Имя канала или объекта недопустимо (пустое, с NUL, слишком длинное).
+    #[error("invalid object name: {0}")]
+    InvalidName(&'static str),
+    /// Операция прервана: endpoint закрыт или остановлен, пока она ждала.
+    #[error("operation cancelled")]
+    Cancelled,
+    /// Нет прав на объект (например, `Global\` без
+    /// `SeCreateGlobalPrivilege` или чужой shm-объект на Unix).
+    #[error("insufficient privileges while {0}")]
+    PrivilegeRequired(&'static str),
+    /// Сообщение не прошло аутентификацию (подмена, повтор или чужой ключ).
+    #[error("message authentication failed")]
+    AuthenticationFailed,
+    /// Операция не поддерживается платформой или пиром.
+    #[error("unsupported: {0}")]
+    Unsupported(&'static str),
+    /// В arena нет свободных слотов.
+    #[error("shared-memory arena is full")]
+    ArenaFull,
+    /// В блоке состояния нет свободных ячеек под новый ключ.
+    #[error("shared state block is full")]
+    StateFull,
+    /// Handle arena устарел (слот уже освобождён/переиспользован) или
+    /// указывает за пределы arena.
+    #[error("stale or invalid arena handle")]
+    StaleHandle,
+    /// Значение не удалось сериализовать (`codec`).
+    #[error("failed to encode message: {0}")]
+    EncodeFailed(String),
+    /// Сообщение не удалось разобрать в ожидаемый тип (`codec`).
+    #[error("failed to decode message: {0}")]
+    DecodeFailed(String),
+    /// Worker-поток канала запаниковал (обычно в callback'е handler'а или
+    /// на отравленном mutex'е); endpoint остановлен, его `is_stopped()`
+    /// возвращает `true`. Внутри -- текст паники. Только при
+    /// `panic = "unwind"` (по умолчанию): сборка с `panic = "abort"`
+    /// завершает процесс раньше, чем ошибку можно сообщить.
+    #[error("worker thread panicked: {0}")]
+    WorkerPanicked(String),
+    /// Хеш схемы пира не совпал с локальным (`protobuf`).
+    #[error("schema mismatch: local {local:#010x}, peer {remote:#010x}")]
+    SchemaMismatch {
+        /// Хеш локальной схемы.
+        local: u32,
+        /// Хеш, присланный пиром.
+        remote: u32,
+    },
+}
+
+/// Крупная группа ошибки, см. [`ShmError::category`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// Временное состояние: очередь пуста/полна, тайм-аут, handshake не
+    /// завершён.
+    Transient,
+    /// Нет соединения или место уже занято другим пиром.
+    Connection,
+    /// Исчерпаны слоты, arena или ячейки блока состояния.
+    Capacity,
+    /// Одно сообщение не прошло проверку (checksum, аутентификация).
+    Integrity,
+    /// Нарушен протокол или повреждена секция: соединению нельзя доверять.
+    Protocol,
+    /// Неверные аргументы, имя или конфигурация вызывающего.
+    InvalidInput,
+    /// Ошибка ОС, нехватка прав или упавший worker-поток.
+    System,
+    /// Не поддерживается платформой или пиром.
+    Unsupported,
+    /// Операция отменена.
+    Cancelled,
+}
+
+impl ErrorCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Transient => "transient",
+            Self::Connection => "connection",
+            Self::Capacity => "capacity",
+            Self::Integrity => "integrity",
+            Self::Protocol => "protocol",
+            Self::InvalidInput => "invalid_input",
+            Self::System => "system",
+            Self::Unsupported => "unsupported",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl ShmError {
+    /// Группа ошибки.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::NotReady | Self::Timeout | Self::QueueEmpty | Self::QueueFull => {
+                ErrorCategory::Transient
+            }
+            Self::NotConnected | Self::ConnectionReset | Self::AlreadyConnected => {
+                ErrorCategory::Connection
+            }
+            Self::Io { kind, .. } if is_io_disconnect(*kind) => ErrorCategory::Connection,
+            Self::Io {
+                kind:
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted,
+                ..
+            } => ErrorCategory::Transient,
+            Self::NoFreeSlot | Self::ArenaFull | Self::StateFull => ErrorCategory::Capacity,
+            Self::ChecksumMismatch | Self::AuthenticationFailed => ErrorCategory::Integrity,
+            Self::Corrupted
+            | Self::HandshakeFailed
+            | Self::VersionMismatch { .. }
+            | Self::LayoutMismatch { .. }
+            | Self::DecodeFailed(_)
+            | Self::SchemaMismatch { .. } => ErrorCategory::Protocol,
+            Self::MessageTooSmall
+            | Self::MessageTooLarge
+            | Self::InvalidConfig(_)
+            | Self::InvalidName(_)
+            | Self::StaleHandle
+            | Self::EncodeFailed(_) => ErrorCategory::InvalidInput,
+            Self::WindowsError { .. }
+            | Self::Io { .. }
+            | Self::PrivilegeRequired(_)
+            | Self::WorkerPanicked(_) => ErrorCategory::System,
+            Self::Unsupported(_) => ErrorCategory::Unsupported,
+            Self::Cancelled => ErrorCategory::Cancelled,
+        }
+    }
+
+    /// Повтор той же операции позже (после переподключения, когда
+    /// освободится место или слот) может пройти. Отбитое по checksum
+    /// сообщение уже изъято из кольца -- следующее чтение вернёт
+    /// следующее. `Corrupted`, ошибки протокола и аргументов повтором не
+    /// лечатся.
+    pub fn is_retryable(&self) -> bool {
+        match self.category() {
+            ErrorCategory::Transient | ErrorCategory::Connection => true,
+            // Ключи блока состояния не удаляются -- место не освободится.
+            ErrorCategory::Capacity => *self != Self::StateFull,
+            ErrorCategory::Integrity => *self == Self::ChecksumMismatch,
+            _ => false,
+        }
+    }
+
+    /// Соединение с пиром потеряно или его состоянию нельзя доверять:
+    /// текущее соединение надо сбросить (и, если нужно, установить заново).
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            Self::Io { kind, .. } => is_io_disconnect(*kind),
+            _ => matches!(
+                self,
+                Self::NotConnected
+                    | Self::ConnectionReset
+                    | Self::Corrupted
+                    | Self::HandshakeFailed
+                    | Self::VersionMismatch { .. }
+                    | Self::LayoutMismatch { .. }
+                    | Self::SchemaMismatch { .. }
+            ),
+        }
+    }
+}
+
+/// Пир закрыл или сбросил сокет.
+fn is_io_disconnect(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+/// `ShmError::Io` из `io::Error` (TCP-мост, файлы `record`).
+pub(crate) fn io_error(err: io::Error, context: &'static str) -> ShmError {
+    ShmError::Io {
+        kind: err.kind(),
+        context,
+    }
+}
+
+/// `Ok(())`, если `valid`, иначе `ShmError::InvalidConfig(message)`.
+pub(crate) fn ensure_config(valid: bool, message: &'static str) -> Result<()> {
+    if valid {
+        Ok(())
+    } else {
+        Err(ShmError::InvalidConfig(message))
+    }
+}
+
+/// Ошибка с контекстом: канал, сторона и операция.
+///
+/// `ShmError` сам по себе не знает, где случился: при десятке каналов
+/// «handshake failed» ничего не говорит. `ErrorContext` добавляет это в
+/// `Display` (`server 'Telemetry': wait_for_client: handshake failed`),
+/// а исходная ошибка остаётся в `error` для `match`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Имя канала (то же, что передано в `start`/`connect`).
+    pub channel: String,
+    pub role: ChannelRole,
+    /// Операция, например `"connect"` или `"send_to_client"`.
+    pub operation: &'static str,
+    pub error: ShmError,
+}
+
+impl ErrorContext {
+    pub fn new(
+        channel: impl Into<String>,
+        role: ChannelRole,
+        operation: &'static str,
+        error: ShmError,
+    ) -> Self {
+        Self {
+            channel: channel.into(),
+            role,
+            operation,
+            error,
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} '{}': {}: {}",
+            self.role.as_str(),
+            self.channel,
+            self.operation,
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<ErrorContext> for ShmError {
+    fn from(context: ErrorContext) -> Self {
+        context.error
+    }
+}
+
+/// Добавление [`ErrorContext`] к `Result<T>` библиотеки.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use xshm::metrics::ChannelRole;
+/// use xshm::{ResultExt, SharedClient};
+///
+/// let client = SharedClient::connect("Telemetry", Duration::from_secs(1))
+///     .context("Telemetry", ChannelRole::Client, "connect");
+/// if let Err(err) = &client {
+///     eprintln!("{err}"); // client 'Telemetry': connect: operation timed out
+/// }
+/// ```
+pub trait ResultExt<T> {
+    fn context(
+        self,
+        channel: &str,
+        role: ChannelRole,
+        operation: &'static str,
+    ) -> std::result::Result<T, ErrorContext>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn context(
+        self,
+        channel: &str,
+        role: ChannelRole,
+        operation: &'static str,
+    ) -> std::result::Result<T, ErrorContext> {
+        self.map_err(|error| ErrorContext::new(channel, role, operation, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taxonomy_separates_retry_from_abort() {
+        for err in [
+            ShmError::Timeout,
+            ShmError::QueueFull,
+            ShmError::NotConnected,
+            ShmError::NoFreeSlot,
+            ShmError::ChecksumMismatch,
+        ] {
+            assert!(err.is_retryable(), "{err:?}");
+        }
+        for err in [
+            ShmError::Corrupted,
+            ShmError::HandshakeFailed,
+            ShmError::StateFull,
+            ShmError::AuthenticationFailed,
+            ShmError::InvalidName("empty"),
+            ShmError::PrivilegeRequired("creating section"),
+            ShmError::Cancelled,
+            ShmError::WorkerPanicked("handler".to_owned()),
+        ] {
+            assert!(!err.is_retryable(), "{err:?}");
+        }
+
+        assert!(ShmError::Corrupted.is_disconnect());
+        assert!(ShmError::NotConnected.is_disconnect());
+        assert!(ShmError::ConnectionReset.is_disconnect());
+        assert!(ShmError::ConnectionReset.is_retryable());
+        assert!(!ShmError::ChecksumMismatch.is_disconnect());
+        assert!(!ShmError::Timeout.is_disconnect());
+
+        assert_eq!(ShmError::Corrupted.category(), ErrorCategory::Protocol);
+        assert_eq!(
+            ShmError::InvalidName("x").category(),
+            ErrorCategory::InvalidInput
+        );
+        assert_eq!(
+            ShmError::PrivilegeRequired("x").category(),
+            ErrorCategory::System
+        );
+        assert_eq!(ShmError::Cancelled.category().as_str(), "cancelled");
+    }
+
+    #[test]
+    fn io_errors_separate_peer_loss_from_os_failures() {
+        for kind in [io::ErrorKind::ConnectionReset, io::ErrorKind::BrokenPipe] {
+            let err = io_error(kind.into(), "bridge read");
+            assert!(err.is_disconnect(), "{err:?}");
+            assert!(err.is_retryable(), "{err:?}");
+            assert_eq!(err.category(), ErrorCategory::Connection);
+        }
+        let eof = io_error(io::ErrorKind::UnexpectedEof.into(), "read record");
+        assert!(eof.is_disconnect());
+
+        let denied = io_error(io::ErrorKind::PermissionDenied.into(), "create record file");
+        assert!(!denied.is_disconnect());
+        assert_eq!(denied.category(), ErrorCategory::System);
+        assert_eq!(
+            denied.to_string(),
+            "i/o error (permission denied) while create record file"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn empty_channel_name_is_invalid_name() {
+        assert!(matches!(
+            crate::SharedServer::start(""),
+            Err(ShmError::InvalidName(_))
+        ));
+    }
 }
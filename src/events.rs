@@ -1,10 +1,13 @@
+use std::time::Duration;
+
 use crate::constants::{
     EVENT_CONNECT_REQ_SUFFIX, EVENT_CONNECT_SUFFIX, EVENT_DATA_SUFFIX, EVENT_DISCONNECT_SUFFIX,
     EVENT_SPACE_SUFFIX,
 };
 use crate::error::Result;
 use crate::naming::{event_name, Direction};
-use crate::win::EventHandle;
+use crate::platform::{self, EventHandle, PlatformEvent};
+use crate::security::Security;
 
 pub struct ChannelEvents {
     pub data: EventHandle,
@@ -36,46 +39,44 @@ pub struct EventHandles {
 
 impl SharedEvents {
     pub fn create(base: &str) -> Result<Self> {
+        Self::create_secured(base, Security::Everyone)
+    }
+
+    /// Как `create`, но с правами доступа `security`.
+    pub(crate) fn create_secured(base: &str, security: Security) -> Result<Self> {
         Ok(Self {
             s2c: ChannelEvents {
-                data: EventHandle::create(&event_name(
-                    base,
-                    Direction::ServerToClient,
-                    EVENT_DATA_SUFFIX,
-                ))?,
-                space: EventHandle::create(&event_name(
-                    base,
-                    Direction::ServerToClient,
-                    EVENT_SPACE_SUFFIX,
-                ))?,
+                data: EventHandle::create_secured(
+                    &event_name(base, Direction::ServerToClient, EVENT_DATA_SUFFIX),
+                    security,
+                )?,
+                space: EventHandle::create_secured(
+                    &event_name(base, Direction::ServerToClient, EVENT_SPACE_SUFFIX),
+                    security,
+                )?,
             },
             c2s: ChannelEvents {
-                data: EventHandle::create(&event_name(
-                    base,
-                    Direction::ClientToServer,
-                    EVENT_DATA_SUFFIX,
-                ))?,
-                space: EventHandle::create(&event_name(
-                    base,
-                    Direction::ClientToServer,
-                    EVENT_SPACE_SUFFIX,
-                ))?,
+                data: EventHandle::create_secured(
+                    &event_name(base, Direction::ClientToServer, EVENT_DATA_SUFFIX),
+                    security,
+                )?,
+                space: EventHandle::create_secured(
+                    &event_name(base, Direction::ClientToServer, EVENT_SPACE_SUFFIX),
+                    security,
+                )?,
             },
-            connect_ack: EventHandle::create(&event_name(
-                base,
-                Direction::ServerToClient,
-                EVENT_CONNECT_SUFFIX,
-            ))?,
-            connect_req: EventHandle::create(&event_name(
-                base,
-                Direction::ClientToServer,
-                EVENT_CONNECT_REQ_SUFFIX,
-            ))?,
-            disconnect: EventHandle::create(&event_name(
-                base,
-                Direction::ServerToClient,
-                EVENT_DISCONNECT_SUFFIX,
-            ))?,
+            connect_ack: EventHandle::create_secured(
+                &event_name(base, Direction::ServerToClient, EVENT_CONNECT_SUFFIX),
+                security,
+            )?,
+            connect_req: EventHandle::create_secured(
+                &event_name(base, Direction::ClientToServer, EVENT_CONNECT_REQ_SUFFIX),
+                security,
+            )?,
+            disconnect: EventHandle::create_secured(
+                &event_name(base, Direction::ServerToClient, EVENT_DISCONNECT_SUFFIX),
+                security,
+            )?,
         })
     }
 
@@ -136,4 +137,54 @@ impl SharedEvents {
             ))?,
         })
     }
+
+    /// Удалять ли имена событий при Drop (см. `PlatformEvent::set_owns_name`).
+    pub(crate) fn set_owns_names(&mut self, owns: bool) {
+        for event in [
+            &mut self.s2c.data,
+            &mut self.s2c.space,
+            &mut self.c2s.data,
+            &mut self.c2s.space,
+            &mut self.connect_ack,
+            &mut self.connect_req,
+            &mut self.disconnect,
+        ] {
+            event.set_owns_name(owns);
+        }
+    }
+
+    /// Снимает имена событий канала `base`, брошенного упавшим сервером
+    /// (см. `platform::unlink`).
+    pub(crate) fn unlink(base: &str) {
+        for (direction, suffix) in [
+            (Direction::ServerToClient, EVENT_DATA_SUFFIX),
+            (Direction::ServerToClient, EVENT_SPACE_SUFFIX),
+            (Direction::ClientToServer, EVENT_DATA_SUFFIX),
+            (Direction::ClientToServer, EVENT_SPACE_SUFFIX),
+            (Direction::ServerToClient, EVENT_CONNECT_SUFFIX),
+            (Direction::ClientToServer, EVENT_CONNECT_REQ_SUFFIX),
+            (Direction::ServerToClient, EVENT_DISCONNECT_SUFFIX),
+        ] {
+            platform::unlink(&event_name(base, direction, suffix));
+        }
+    }
+
+    /// Занимает события брошенного канала (см. `reclaim`): создаёт их,
+    /// а если они ещё живы -- открывает и снимает оставшиеся сигналы,
+    /// чтобы новый сервер не принял их за запросы нового клиента.
+    pub(crate) fn adopt(base: &str, security: Security) -> Result<Self> {
+        let events = match Self::create_secured(base, security) {
+            Ok(events) => events,
+            Err(_) => Self::open(base)?,
+        };
+        for event in [
+            &events.connect_req,
+            &events.connect_ack,
+            &events.c2s.data,
+            &events.s2c.space,
+        ] {
+            while event.wait(Some(Duration::ZERO))? {}
+        }
+        Ok(events)
+    }
 }
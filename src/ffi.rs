@@ -1,9 +1,17 @@
 //! FFI-интерфейс совместимый с существующей C-библиотекой.
 //!
+//! Совместимость -- на уровне API (имена, сигнатуры, коды ошибок), не
+//! на уровне секции: раскладка (`ControlBlock`, `RingHeader`, magic) и
+//! имена событий свои, поэтому обе стороны канала должны быть собраны с
+//! xshm. Пересобрать C-клиентов с `xshm.h` достаточно, код менять не нужно.
+//! Режима с раскладкой исходной библиотеки (`legacy-layout`) нет: её
+//! смещений и имён событий не с чем сверить.
+//!
 //! All public functions in this module accept raw pointers from C callers.
 //! The `not_unsafe_ptr_arg_deref` lint is suppressed at the module level
 //! because every FFI function validates its pointer arguments before use.
 
+use std::cell::RefCell;
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_void};
 use std::ptr::null_mut;
@@ -15,7 +23,10 @@ use crate::auto::{
 };
 use crate::client::SharedClient;
 use crate::constants::MAX_MESSAGE_SIZE;
-use crate::error::{Result, ShmError};
+use crate::error::{ErrorContext, Result, ShmError};
+use crate::handles::Received;
+use crate::metrics::ChannelRole;
+use crate::ring::CreditWindow;
 use crate::server::SharedServer;
 
 #[repr(C)]
@@ -60,6 +71,8 @@ pub enum shm_error_t {
     SHM_ERROR_PROTOCOL = -9,
     SHM_ERROR_FULL = -10,
     SHM_ERROR_NO_SLOT = -11,
+    SHM_ERROR_CHECKSUM = -12,
+    SHM_ERROR_WORKER_PANICKED = -13,
 }
 
 impl From<ShmError> for shm_error_t {
@@ -72,12 +85,30 @@ impl From<ShmError> for shm_error_t {
             ShmError::QueueFull => shm_error_t::SHM_ERROR_FULL,
             ShmError::Timeout => shm_error_t::SHM_ERROR_TIMEOUT,
             ShmError::NotReady => shm_error_t::SHM_ERROR_NOT_READY,
-            ShmError::NotConnected => shm_error_t::SHM_ERROR_NOT_FOUND,
+            ShmError::NotConnected | ShmError::ConnectionReset => shm_error_t::SHM_ERROR_NOT_FOUND,
             ShmError::AlreadyConnected => shm_error_t::SHM_ERROR_EXISTS,
-            ShmError::HandshakeFailed | ShmError::Corrupted => shm_error_t::SHM_ERROR_PROTOCOL,
+            ShmError::HandshakeFailed | ShmError::Corrupted | ShmError::AuthenticationFailed => {
+                shm_error_t::SHM_ERROR_PROTOCOL
+            }
             ShmError::WindowsError { .. } => shm_error_t::SHM_ERROR_ACCESS,
-            ShmError::InvalidConfig(_) => shm_error_t::SHM_ERROR_INVALID_PARAM,
+            ShmError::Io { .. } if value.is_disconnect() => shm_error_t::SHM_ERROR_NOT_FOUND,
+            ShmError::Io { .. } => shm_error_t::SHM_ERROR_ACCESS,
+            ShmError::InvalidConfig(_) | ShmError::InvalidName(_) => {
+                shm_error_t::SHM_ERROR_INVALID_PARAM
+            }
+            ShmError::PrivilegeRequired(_) => shm_error_t::SHM_ERROR_ACCESS,
+            ShmError::Cancelled => shm_error_t::SHM_ERROR_NOT_FOUND,
             ShmError::NoFreeSlot => shm_error_t::SHM_ERROR_NO_SLOT,
+            ShmError::ChecksumMismatch => shm_error_t::SHM_ERROR_CHECKSUM,
+            ShmError::WorkerPanicked(_) => shm_error_t::SHM_ERROR_WORKER_PANICKED,
+            ShmError::Unsupported(_) => shm_error_t::SHM_ERROR_INVALID_PARAM,
+            ShmError::ArenaFull | ShmError::StateFull => shm_error_t::SHM_ERROR_FULL,
+            ShmError::StaleHandle => shm_error_t::SHM_ERROR_NOT_FOUND,
+            ShmError::EncodeFailed(_) => shm_error_t::SHM_ERROR_INVALID_PARAM,
+            ShmError::DecodeFailed(_)
+            | ShmError::SchemaMismatch { .. }
+            | ShmError::VersionMismatch { .. }
+            | ShmError::LayoutMismatch { .. } => shm_error_t::SHM_ERROR_PROTOCOL,
         }
     }
 }
@@ -97,6 +128,69 @@ mod error_mapping_tests {
         assert_eq!(not_connected, shm_error_t::SHM_ERROR_NOT_FOUND);
         assert_eq!(not_ready, shm_error_t::SHM_ERROR_NOT_READY);
     }
+
+    #[test]
+    fn last_error_names_channel_and_operation() {
+        use std::ffi::{CStr, CString};
+
+        let name = format!("XSHM_FFI_LASTERR_{}", std::process::id());
+        let name_c = CString::new(name.clone()).unwrap();
+        let config = shm_endpoint_config_t {
+            name: name_c.as_ptr(),
+        };
+        shm_clear_last_error();
+        assert_eq!(shm_last_error(null_mut(), 0), 0);
+
+        let client = shm_client_connect(&config, std::ptr::null(), 20);
+        assert!(client.is_null());
+        let expected = format!("client '{name}': connect: ");
+        let len = shm_last_error(null_mut(), 0) as usize;
+        assert!(len > expected.len());
+        assert_ne!(shm_last_error_code(), shm_error_t::SHM_SUCCESS);
+
+        let mut buffer = vec![0 as c_char; len + 1];
+        assert_eq!(
+            shm_last_error(buffer.as_mut_ptr(), buffer.len() as u32) as usize,
+            len
+        );
+        let text = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap();
+        assert!(text.starts_with(&expected), "{text}");
+
+        // Короткий буфер: текст обрезан, но завершён NUL.
+        let mut short = [1 as c_char; 8];
+        assert_eq!(shm_last_error(short.as_mut_ptr(), 8) as usize, len);
+        let text = unsafe { CStr::from_ptr(short.as_ptr()) }.to_str().unwrap();
+        assert_eq!(text, "client ");
+
+        shm_clear_last_error();
+        assert_eq!(shm_last_error_code(), shm_error_t::SHM_SUCCESS);
+    }
+}
+
+#[cfg(test)]
+mod options_tests {
+    use super::*;
+
+    #[test]
+    fn zeroed_auto_options_fall_back_to_defaults() {
+        let zeroed = shm_auto_options_t {
+            poll_timeout_ms: 0,
+            reconnect_delay_ms: 0,
+            connect_timeout_ms: 0,
+            max_send_queue: 0,
+            recv_batch: 7,
+            checksum: true,
+        };
+        let options = ffi_auto_options(&zeroed);
+        let defaults = AutoOptions::default();
+        assert_eq!(options.poll_timeout, defaults.poll_timeout);
+        assert_eq!(options.reconnect_delay, defaults.reconnect_delay);
+        assert_eq!(options.connect_timeout, defaults.connect_timeout);
+        assert_eq!(options.max_send_queue, defaults.max_send_queue);
+        assert_eq!(options.recv_batch, 7);
+        assert!(options.checksum);
+        assert_eq!(options.validate(), Ok(()));
+    }
 }
 
 #[cfg(test)]
@@ -204,7 +298,8 @@ mod recv_cache_tests {
     #[test]
     fn concurrent_send_and_receive_on_same_handle_do_not_conflict() {
         let name = unique_name("CONCURRENT");
-        const ITERATIONS: usize = 200;
+        // Клиент читает только после отправки: всё должно уместиться в очередь.
+        const ITERATIONS: usize = crate::constants::MAX_MESSAGES as usize / 2;
 
         let server_thread = {
             let name = name.clone();
@@ -293,6 +388,8 @@ pub enum shm_direction_t {
     SHM_DIR_CLIENT_TO_SERVER = 1,
 }
 
+/// Настройки auto-режима; нулевые таймауты и размеры -- значения по
+/// умолчанию.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct shm_auto_options_t {
@@ -301,6 +398,7 @@ pub struct shm_auto_options_t {
     pub connect_timeout_ms: u32,
     pub max_send_queue: u32,
     pub recv_batch: u32,
+    pub checksum: bool,
 }
 
 impl Default for shm_auto_options_t {
@@ -311,6 +409,7 @@ impl Default for shm_auto_options_t {
             connect_timeout_ms: 2000,
             max_send_queue: 256,
             recv_batch: 32,
+            checksum: false,
         }
     }
 }
@@ -322,6 +421,7 @@ pub struct shm_auto_stats_t {
     pub send_overflows: u64,
     pub received_messages: u64,
     pub receive_overflows: u64,
+    pub checksum_errors: u64,
 }
 
 fn to_rust_str(ptr: *const c_char) -> Result<String> {
@@ -332,6 +432,69 @@ fn to_rust_str(ptr: *const c_char) -> Result<String> {
     Ok(cstr.to_string_lossy().into_owned())
 }
 
+thread_local! {
+    static LAST_ERROR: RefCell<Option<ErrorContext>> = const { RefCell::new(None) };
+}
+
+/// Запоминает ошибку с контекстом для `shm_last_error` текущего потока и
+/// возвращает её код.
+fn record_error(
+    name: &str,
+    role: ChannelRole,
+    operation: &'static str,
+    err: ShmError,
+) -> shm_error_t {
+    let code = err.clone().into();
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = Some(ErrorContext::new(name, role, operation, err));
+    });
+    code
+}
+
+/// Текст последней ошибки текущего потока, например
+/// `client 'Telemetry': connect: operation timed out`.
+///
+/// Пишет в `buffer` не больше `capacity - 1` байт и завершающий NUL;
+/// возвращает полную длину текста без NUL (больше `capacity - 1` -- текст
+/// обрезан). 0 -- ошибок не было. `buffer` может быть NULL, чтобы узнать
+/// длину.
+///
+/// Запоминаются ошибки канала; неверные аргументы
+/// (`SHM_ERROR_INVALID_PARAM` до обращения к каналу) и успешные вызовы
+/// последнюю ошибку не меняют.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_last_error(buffer: *mut c_char, capacity: u32) -> u32 {
+    let Some(text) = LAST_ERROR.with(|last| last.borrow().as_ref().map(ToString::to_string)) else {
+        return 0;
+    };
+    if !buffer.is_null() && capacity > 0 {
+        let len = text.len().min(capacity as usize - 1);
+        unsafe {
+            std::ptr::copy_nonoverlapping(text.as_ptr(), buffer.cast::<u8>(), len);
+            *buffer.add(len) = 0;
+        }
+    }
+    text.len() as u32
+}
+
+/// Код последней ошибки текущего потока (`SHM_SUCCESS`, если её нет).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_last_error_code() -> shm_error_t {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(shm_error_t::SHM_SUCCESS, |context| {
+                context.error.clone().into()
+            })
+    })
+}
+
+/// Сбрасывает последнюю ошибку текущего потока.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_clear_last_error() {
+    LAST_ERROR.with(|last| last.borrow_mut().take());
+}
+
 /// Буфер приёма с кэшем недоставленного сообщения.
 ///
 /// Если сообщение вычитано из ring buffer, но буфер C-вызывающего оказался
@@ -356,12 +519,14 @@ impl RecvCache {
 
 struct ServerState {
     inner: SharedServer,
+    name: String,
     callbacks: Option<shm_callbacks_t>,
     recv_cache: Mutex<RecvCache>,
 }
 
 struct ClientState {
     inner: SharedClient,
+    name: String,
     recv_cache: Mutex<RecvCache>,
 }
 
@@ -372,19 +537,35 @@ pub type AutoClientHandle = c_void;
 
 struct AutoServerState {
     inner: AutoServer,
+    name: String,
     _callbacks: Option<shm_callbacks_t>,
     _handler: Arc<FfiHandler>,
 }
 
 struct AutoClientState {
     inner: AutoClient,
+    name: String,
     _callbacks: Option<shm_callbacks_t>,
     _handler: Arc<FfiHandler>,
 }
 
+impl ServerState {
+    fn fail(&self, operation: &'static str, err: ShmError) -> shm_error_t {
+        record_error(&self.name, ChannelRole::Server, operation, err)
+    }
+}
+
+impl ClientState {
+    fn fail(&self, operation: &'static str, err: ShmError) -> shm_error_t {
+        record_error(&self.name, ChannelRole::Client, operation, err)
+    }
+}
+
 #[derive(Clone)]
 struct FfiHandler {
     callbacks: shm_callbacks_t,
+    name: String,
+    role: ChannelRole,
 }
 
 unsafe impl Send for FfiHandler {}
@@ -472,8 +653,19 @@ impl AutoHandler for FfiHandler {
     }
 
     fn on_error(&self, err: ShmError) {
+        // Вызывается из рабочего потока: `shm_last_error` внутри колбэка
+        // вернёт именно эту ошибку.
+        let code = record_error(&self.name, self.role, "worker", err);
         if let Some(cb) = self.callbacks.on_error {
-            cb(err.into(), self.callbacks.user_data);
+            cb(code, self.callbacks.user_data);
+        }
+    }
+
+    fn on_error_with_context(&self, error: ErrorContext) {
+        // Операцию называет сам worker, канал и сторона те же.
+        let code = record_error(&self.name, self.role, error.operation, error.error);
+        if let Some(cb) = self.callbacks.on_error {
+            cb(code, self.callbacks.user_data);
         }
     }
 }
@@ -487,20 +679,81 @@ impl From<ChannelKind> for shm_direction_t {
     }
 }
 
+/// Таймаут из структуры опций C API: 0 (поле не заполнено, структура
+/// обнулена) -- `default`, а не busy loop.
+pub(crate) fn ffi_millis_or(ms: u32, default: Duration) -> Duration {
+    if ms == 0 {
+        default
+    } else {
+        Duration::from_millis(ms as u64)
+    }
+}
+
+/// Размер пачки или очереди из структуры опций C API: 0 -- `default`.
+pub(crate) fn ffi_count_or(count: u32, default: usize) -> usize {
+    if count == 0 {
+        default
+    } else {
+        count as usize
+    }
+}
+
 fn ffi_auto_options(ptr: *const shm_auto_options_t) -> AutoOptions {
+    let defaults = AutoOptions::default();
     if ptr.is_null() {
-        return AutoOptions::default();
+        return defaults;
     }
     let opts = unsafe { *ptr };
     AutoOptions {
-        poll_timeout: Duration::from_millis(opts.poll_timeout_ms as u64),
-        reconnect_delay: Duration::from_millis(opts.reconnect_delay_ms as u64),
-        connect_timeout: Duration::from_millis(opts.connect_timeout_ms as u64),
-        max_send_queue: opts.max_send_queue as usize,
-        recv_batch: opts.recv_batch as usize,
+        poll_timeout: ffi_millis_or(opts.poll_timeout_ms, defaults.poll_timeout),
+        reconnect_delay: ffi_millis_or(opts.reconnect_delay_ms, defaults.reconnect_delay),
+        connect_timeout: ffi_millis_or(opts.connect_timeout_ms, defaults.connect_timeout),
+        max_send_queue: ffi_count_or(opts.max_send_queue, defaults.max_send_queue),
+        recv_batch: ffi_count_or(opts.recv_batch, defaults.recv_batch),
+        checksum: opts.checksum,
+        ..defaults
     }
 }
 
+fn ffi_credit_window(messages: u32, bytes: u32) -> Option<CreditWindow> {
+    (messages != 0).then_some(CreditWindow { messages, bytes })
+}
+
+fn ffi_ttl(ttl_ms: u32) -> Option<Duration> {
+    (ttl_ms != 0).then(|| Duration::from_millis(ttl_ms as u64))
+}
+
+/// Пачка для `shm_*_send_batch_atomic`: `count` указателей и размеров.
+/// `None` -- нулевой указатель или размер вне `1..=MAX_MESSAGE_SIZE`.
+///
+/// # Safety
+/// При `count > 0` `messages` и `sizes` указывают на `count` элементов, а
+/// каждое сообщение -- на `sizes[i]` байт.
+unsafe fn ffi_batch<'a>(
+    messages: *const *const c_void,
+    sizes: *const u32,
+    count: u32,
+) -> Option<Vec<&'a [u8]>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    if messages.is_null() || sizes.is_null() {
+        return None;
+    }
+    let messages = unsafe { std::slice::from_raw_parts(messages, count as usize) };
+    let sizes = unsafe { std::slice::from_raw_parts(sizes, count as usize) };
+    messages
+        .iter()
+        .zip(sizes)
+        .map(|(&data, &size)| {
+            if data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+                return None;
+            }
+            Some(unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) })
+        })
+        .collect()
+}
+
 fn write_stats(dst: *mut shm_auto_stats_t, stats: AutoStatsSnapshot) -> bool {
     if dst.is_null() {
         return false;
@@ -511,6 +764,7 @@ fn write_stats(dst: *mut shm_auto_stats_t, stats: AutoStatsSnapshot) -> bool {
             send_overflows: stats.send_overflows,
             received_messages: stats.received_messages,
             receive_overflows: stats.receive_overflows,
+            checksum_errors: stats.checksum_errors,
         };
     }
     true
@@ -542,17 +796,21 @@ pub extern "C" fn shm_server_start_auto(
     };
     let handler = Arc::new(FfiHandler {
         callbacks: callbacks_val,
+        name: name.clone(),
+        role: ChannelRole::Server,
     });
     let opts = ffi_auto_options(options);
     match AutoServer::start(&name, handler.clone(), opts) {
         Ok(inner) => Box::into_raw(Box::new(AutoServerState {
             inner,
+            name,
             _callbacks: Some(callbacks_val),
             _handler: handler,
         })) as *mut AutoServerHandle,
         Err(err) => {
+            let code = record_error(&name, ChannelRole::Server, "start", err);
             if let Some(cb) = callbacks_val.on_error {
-                cb(err.into(), callbacks_val.user_data);
+                cb(code, callbacks_val.user_data);
             }
             null_mut()
         }
@@ -572,7 +830,7 @@ pub extern "C" fn shm_server_send_auto(
     let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
     match state.inner.send(slice) {
         Ok(_) => shm_error_t::SHM_SUCCESS,
-        Err(err) => err.into(),
+        Err(err) => record_error(&state.name, ChannelRole::Server, "send", err),
     }
 }
 
@@ -619,17 +877,21 @@ pub extern "C" fn shm_client_connect_auto(
     };
     let handler = Arc::new(FfiHandler {
         callbacks: callbacks_val,
+        name: name.clone(),
+        role: ChannelRole::Client,
     });
     let opts = ffi_auto_options(options);
     match AutoClient::connect(&name, handler.clone(), opts) {
         Ok(inner) => Box::into_raw(Box::new(AutoClientState {
             inner,
+            name,
             _callbacks: Some(callbacks_val),
             _handler: handler,
         })) as *mut AutoClientHandle,
         Err(err) => {
+            let code = record_error(&name, ChannelRole::Client, "connect", err);
             if let Some(cb) = callbacks_val.on_error {
-                cb(err.into(), callbacks_val.user_data);
+                cb(code, callbacks_val.user_data);
             }
             null_mut()
         }
@@ -649,7 +911,7 @@ pub extern "C" fn shm_client_send_auto(
     let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
     match state.inner.send(slice) {
         Ok(_) => shm_error_t::SHM_SUCCESS,
-        Err(err) => err.into(),
+        Err(err) => record_error(&state.name, ChannelRole::Client, "send", err),
     }
 }
 
@@ -679,6 +941,25 @@ pub extern "C" fn shm_client_disconnect_auto(handle: *mut AutoClientHandle) {
 pub extern "C" fn shm_server_start(
     config: *const shm_endpoint_config_t,
     callbacks: *const shm_callbacks_t,
+) -> *mut ServerHandle {
+    server_start_with(config, callbacks, SharedServer::start)
+}
+
+/// Как `shm_server_start`, но с управляющими кольцами: срочные сообщения
+/// (`shm_server_send_control`/`shm_client_send_control`) читаются раньше
+/// накопленных bulk-кадров. Клиент подключается обычным `shm_client_connect`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_start_control_rings(
+    config: *const shm_endpoint_config_t,
+    callbacks: *const shm_callbacks_t,
+) -> *mut ServerHandle {
+    server_start_with(config, callbacks, SharedServer::start_with_control_rings)
+}
+
+fn server_start_with(
+    config: *const shm_endpoint_config_t,
+    callbacks: *const shm_callbacks_t,
+    start: fn(&str) -> Result<SharedServer>,
 ) -> *mut ServerHandle {
     if config.is_null() {
         return null_mut();
@@ -694,14 +975,15 @@ pub extern "C" fn shm_server_start(
         Some(unsafe { *callbacks })
     };
 
-    match SharedServer::start(&name) {
+    match start(&name) {
         Ok(server) => Box::into_raw(Box::new(ServerState {
             inner: server,
+            name,
             callbacks,
             recv_cache: Mutex::new(RecvCache::new()),
         })) as *mut ServerHandle,
         Err(err) => {
-            let code: shm_error_t = err.into();
+            let code = record_error(&name, ChannelRole::Server, "start", err);
             if let Some(cb) = callbacks {
                 if let Some(on_error) = cb.on_error {
                     on_error(code, cb.user_data);
@@ -736,7 +1018,7 @@ pub extern "C" fn shm_server_wait_for_client(
             shm_error_t::SHM_SUCCESS
         }
         Err(err) => {
-            let code: shm_error_t = err.clone().into();
+            let code = state.fail("wait_for_client", err);
             if let Some(cb) = state.callbacks.as_ref() {
                 if let Some(on_error) = cb.on_error {
                     on_error(code, cb.user_data);
@@ -747,6 +1029,101 @@ pub extern "C" fn shm_server_wait_for_client(
     }
 }
 
+/// Как `shm_server_wait_for_client`, но сначала отпускает прежнего клиента
+/// (см. `SharedServer::accept_next_client`): сервер принимает клиентов по
+/// очереди без пересоздания.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_accept_next_client(
+    handle: *mut ServerHandle,
+    timeout_ms: u32,
+) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &mut *server_state_from(handle) };
+    let timeout = if timeout_ms == u32::MAX {
+        None
+    } else {
+        Some(Duration::from_millis(timeout_ms as u64))
+    };
+    match state.inner.accept_next_client(timeout) {
+        Ok(_) => {
+            if let Some(cb) = state.callbacks.as_ref() {
+                if let Some(on_connect) = cb.on_connect {
+                    on_connect(cb.user_data);
+                }
+            }
+            shm_error_t::SHM_SUCCESS
+        }
+        Err(err) => {
+            let code = state.fail("accept_next_client", err);
+            if let Some(cb) = state.callbacks.as_ref() {
+                if let Some(on_error) = cb.on_error {
+                    on_error(code, cb.user_data);
+                }
+            }
+            code
+        }
+    }
+}
+
+/// PID процесса клиента из handshake; 0 -- неизвестен.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_peer_pid(handle: *mut ServerHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.peer_pid().unwrap_or(0)
+}
+
+/// Жив ли процесс клиента; без PID и при любой неясности -- `true`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_is_peer_process_alive(handle: *mut ServerHandle) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.is_peer_process_alive()
+}
+
+/// Жив ли клиент: соединение не сброшено, клиент не отключился и его
+/// процесс жив (см. `SharedServer::is_peer_alive`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_is_peer_alive(handle: *mut ServerHandle) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.is_peer_alive()
+}
+
+/// Отмечает heartbeat сервера (см. `SharedServer::heartbeat`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_heartbeat(handle: *mut ServerHandle) {
+    if handle.is_null() {
+        return;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.heartbeat();
+}
+
+/// Клиент отмечал heartbeat не дольше `max_age_ms` назад; клиент без
+/// heartbeat считается отзывчивым.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_is_peer_responsive(
+    handle: *mut ServerHandle,
+    max_age_ms: u32,
+) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state
+        .inner
+        .is_peer_responsive(Duration::from_millis(max_age_ms as u64))
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn shm_server_stop(handle: *mut ServerHandle) {
     if handle.is_null() {
@@ -775,7 +1152,91 @@ pub extern "C" fn shm_server_send(
     let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
     match state.inner.send_to_client(slice) {
         Ok(_) => shm_error_t::SHM_SUCCESS,
-        Err(err) => err.into(),
+        Err(err) => state.fail("send", err),
+    }
+}
+
+/// Как `shm_server_send`, но не ждёт и не вытесняет непрочитанное при любой
+/// политике записи: нет места -- `SHM_ERROR_FULL`, ничего не отправлено.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_try_send(
+    handle: *mut ServerHandle,
+    data: *const c_void,
+    size: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state.inner.try_send_to_client(slice) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("try_send", err),
+    }
+}
+
+/// Отправляет, дожидаясь места не дольше `timeout_ms`: не дождался --
+/// `SHM_ERROR_TIMEOUT`. Непрочитанное не вытесняется.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_send_blocking(
+    handle: *mut ServerHandle,
+    data: *const c_void,
+    size: u32,
+    timeout_ms: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state
+        .inner
+        .send_blocking(slice, Duration::from_millis(timeout_ms as u64))
+    {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_blocking", err),
+    }
+}
+
+/// Отправляет `count` сообщений одной публикацией: клиент увидит либо все,
+/// либо ни одного. Не хватает места -- `SHM_ERROR_FULL`, ничего не
+/// отправлено.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_send_batch_atomic(
+    handle: *mut ServerHandle,
+    messages: *const *const c_void,
+    sizes: *const u32,
+    count: u32,
+) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let Some(batch) = (unsafe { ffi_batch(messages, sizes, count) }) else {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    };
+    let state = unsafe { &*server_state_from(handle) };
+    match state.inner.send_batch_atomic_to_client(&batch) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_batch_atomic_to_client", err),
+    }
+}
+
+/// Срочное сообщение через управляющее кольцо. Канал без control-колец --
+/// `SHM_ERROR_INVALID_PARAM`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_send_control(
+    handle: *mut ServerHandle,
+    data: *const c_void,
+    size: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state.inner.send_control_to_client(slice) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_control_to_client", err),
     }
 }
 
@@ -799,7 +1260,7 @@ pub extern "C" fn shm_server_receive(
         Some(pending_len) => pending_len,
         None => match state.inner.receive_from_client(&mut cache.buffer) {
             Ok(len) => len,
-            Err(err) => return err.into(),
+            Err(err) => return state.fail("receive_from_client", err),
         },
     };
     if len > capacity {
@@ -813,6 +1274,48 @@ pub extern "C" fn shm_server_receive(
     shm_error_t::SHM_SUCCESS
 }
 
+/// Передать handle (секция, событие, файл) клиенту: handle дублируется в
+/// процесс клиента, клиент получает новое значение через
+/// `shm_client_receive_any`. Исходный handle остаётся за вызывающим.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_send_handle(
+    handle: *mut ServerHandle,
+    object: *mut c_void,
+) -> shm_error_t {
+    if handle.is_null() || object.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    match state.inner.send_handle_to_client(object as isize) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("receive_from_client", err),
+    }
+}
+
+/// Как `shm_server_receive`, но handle-кадры не пропускаются: для них
+/// `*received_handle` -- handle в текущем процессе (закрывает вызывающий),
+/// `*size` = 0. Для обычного сообщения `*received_handle` = NULL.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_receive_any(
+    handle: *mut ServerHandle,
+    buffer: *mut c_void,
+    size: *mut u32,
+    received_handle: *mut *mut c_void,
+) -> shm_error_t {
+    if handle.is_null() || received_handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    receive_any_into(
+        &state.recv_cache,
+        |buf| state.inner.receive_any_from_client(buf),
+        |err| state.fail("receive_any_from_client", err),
+        buffer,
+        size,
+        received_handle,
+    )
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn shm_server_poll(handle: *mut ServerHandle, timeout_ms: u32) -> shm_error_t {
     if handle.is_null() {
@@ -827,10 +1330,85 @@ pub extern "C" fn shm_server_poll(handle: *mut ServerHandle, timeout_ms: u32) ->
     match state.inner.poll_client(timeout) {
         Ok(true) => shm_error_t::SHM_SUCCESS,
         Ok(false) => shm_error_t::SHM_ERROR_TIMEOUT,
-        Err(err) => err.into(),
+        Err(err) => state.fail("poll_client", err),
     }
 }
 
+/// Включить/выключить CRC-32 трейлер для сообщений клиенту.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_set_checksum(handle: *mut ServerHandle, enabled: bool) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.set_checksum(enabled);
+    shm_error_t::SHM_SUCCESS
+}
+
+/// Кредитный режим для сообщений от клиента: не больше `messages`
+/// непрочитанных сообщений и `bytes` байт. `messages == 0` -- выключить.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_set_credit_window(
+    handle: *mut ServerHandle,
+    messages: u32,
+    bytes: u32,
+) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &mut *server_state_from(handle) };
+    match state
+        .inner
+        .set_credit_window(ffi_credit_window(messages, bytes))
+    {
+        Ok(()) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("set_credit_window", err),
+    }
+}
+
+/// Число сообщений от клиента, отброшенных из-за несовпадения CRC-32.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_checksum_errors(handle: *mut ServerHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.checksum_errors()
+}
+
+/// Срок годности сообщений клиенту по умолчанию в миллисекундах; 0 --
+/// бессрочно.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_set_ttl(handle: *mut ServerHandle, ttl_ms: u32) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.set_default_ttl(ffi_ttl(ttl_ms));
+    shm_error_t::SHM_SUCCESS
+}
+
+/// Число просроченных сообщений от клиента, пропущенных или убранных.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_expired_messages(handle: *mut ServerHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.expired_messages()
+}
+
+/// Возможности (`FEATURE_*`), согласованные с текущим клиентом; 0 -- нет
+/// соединения или клиент старой версии.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_negotiated_features(handle: *mut ServerHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*server_state_from(handle) };
+    state.inner.negotiated_features()
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn shm_client_connect(
     config: *const shm_endpoint_config_t,
@@ -856,13 +1434,14 @@ pub extern "C" fn shm_client_connect(
             }
             Box::into_raw(Box::new(ClientState {
                 inner: client,
+                name,
                 recv_cache: Mutex::new(RecvCache::new()),
             })) as *mut ClientHandle
         }
         Err(err) => {
+            let code = record_error(&name, ChannelRole::Client, "connect", err);
             if !callbacks.is_null() {
                 let cb = unsafe { *callbacks };
-                let code: shm_error_t = err.clone().into();
                 if let Some(on_error) = cb.on_error {
                     on_error(code, cb.user_data);
                 }
@@ -882,6 +1461,61 @@ pub extern "C" fn shm_client_disconnect(handle: *mut ClientHandle) {
     }
 }
 
+/// PID процесса сервера; 0 -- неизвестен.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_peer_pid(handle: *mut ClientHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.peer_pid().unwrap_or(0)
+}
+
+/// См. `shm_server_is_peer_process_alive`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_is_peer_process_alive(handle: *mut ClientHandle) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.is_peer_process_alive()
+}
+
+/// См. `shm_server_is_peer_alive`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_is_peer_alive(handle: *mut ClientHandle) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.is_peer_alive()
+}
+
+/// См. `shm_server_heartbeat`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_heartbeat(handle: *mut ClientHandle) {
+    if handle.is_null() {
+        return;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.heartbeat();
+}
+
+/// См. `shm_server_is_peer_responsive`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_is_peer_responsive(
+    handle: *mut ClientHandle,
+    max_age_ms: u32,
+) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state
+        .inner
+        .is_peer_responsive(Duration::from_millis(max_age_ms as u64))
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn shm_client_is_connected(handle: *const ClientHandle) -> bool {
     if handle.is_null() {
@@ -904,7 +1538,86 @@ pub extern "C" fn shm_client_send(
     let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
     match state.inner.send_to_server(slice) {
         Ok(_) => shm_error_t::SHM_SUCCESS,
-        Err(err) => err.into(),
+        Err(err) => state.fail("send_to_server", err),
+    }
+}
+
+/// См. `shm_server_try_send`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_try_send(
+    handle: *mut ClientHandle,
+    data: *const c_void,
+    size: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state.inner.try_send_to_server(slice) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("try_send_to_server", err),
+    }
+}
+
+/// См. `shm_server_send_blocking`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_send_blocking(
+    handle: *mut ClientHandle,
+    data: *const c_void,
+    size: u32,
+    timeout_ms: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state
+        .inner
+        .send_blocking(slice, Duration::from_millis(timeout_ms as u64))
+    {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_blocking", err),
+    }
+}
+
+/// См. `shm_server_send_batch_atomic`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_send_batch_atomic(
+    handle: *mut ClientHandle,
+    messages: *const *const c_void,
+    sizes: *const u32,
+    count: u32,
+) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let Some(batch) = (unsafe { ffi_batch(messages, sizes, count) }) else {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    };
+    let state = unsafe { &*client_state_from(handle) };
+    match state.inner.send_batch_atomic_to_server(&batch) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_batch_atomic_to_server", err),
+    }
+}
+
+/// См. `shm_server_send_control`.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_send_control(
+    handle: *mut ClientHandle,
+    data: *const c_void,
+    size: u32,
+) -> shm_error_t {
+    if handle.is_null() || data.is_null() || size == 0 || size as usize > MAX_MESSAGE_SIZE {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    let slice = unsafe { std::slice::from_raw_parts(data as *const u8, size as usize) };
+    match state.inner.send_control_to_server(slice) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("send_control_to_server", err),
     }
 }
 
@@ -928,7 +1641,89 @@ pub extern "C" fn shm_client_receive(
         Some(pending_len) => pending_len,
         None => match state.inner.receive_from_server(&mut cache.buffer) {
             Ok(len) => len,
-            Err(err) => return err.into(),
+            Err(err) => return state.fail("receive_from_server", err),
+        },
+    };
+    if len > capacity {
+        cache.pending_len = Some(len);
+        return shm_error_t::SHM_ERROR_MEMORY;
+    }
+    unsafe {
+        std::ptr::copy_nonoverlapping(cache.buffer.as_ptr(), buffer as *mut u8, len);
+        *size = len as u32;
+    }
+    shm_error_t::SHM_SUCCESS
+}
+
+/// Передать handle серверу (см. `shm_server_send_handle`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_send_handle(
+    handle: *mut ClientHandle,
+    object: *mut c_void,
+) -> shm_error_t {
+    if handle.is_null() || object.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    match state.inner.send_handle_to_server(object as isize) {
+        Ok(_) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("receive_from_server", err),
+    }
+}
+
+/// Приём сообщения или handle'а от сервера (см. `shm_server_receive_any`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_receive_any(
+    handle: *mut ClientHandle,
+    buffer: *mut c_void,
+    size: *mut u32,
+    received_handle: *mut *mut c_void,
+) -> shm_error_t {
+    if handle.is_null() || received_handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    receive_any_into(
+        &state.recv_cache,
+        |buf| state.inner.receive_any_from_server(buf),
+        |err| state.fail("receive_any_from_server", err),
+        buffer,
+        size,
+        received_handle,
+    )
+}
+
+/// Общая часть `shm_*_receive_any`: сообщение, не поместившееся в буфер,
+/// остаётся в кэше (как в `shm_*_receive`); handle-кадр отдаётся сразу.
+fn receive_any_into(
+    cache: &Mutex<RecvCache>,
+    fetch: impl FnOnce(&mut Vec<u8>) -> Result<Received>,
+    fail: impl FnOnce(ShmError) -> shm_error_t,
+    buffer: *mut c_void,
+    size: *mut u32,
+    received_handle: *mut *mut c_void,
+) -> shm_error_t {
+    if buffer.is_null() || size.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let capacity = unsafe { *size } as usize;
+    if capacity == 0 {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+
+    let mut cache = cache.lock().unwrap();
+    let len = match cache.pending_len.take() {
+        Some(pending_len) => pending_len,
+        None => match fetch(&mut cache.buffer) {
+            Ok(Received::Message(len)) => len,
+            Ok(Received::Handle(object)) => {
+                unsafe {
+                    *received_handle = object as *mut c_void;
+                    *size = 0;
+                }
+                return shm_error_t::SHM_SUCCESS;
+            }
+            Err(err) => return fail(err),
         },
     };
     if len > capacity {
@@ -938,6 +1733,7 @@ pub extern "C" fn shm_client_receive(
     unsafe {
         std::ptr::copy_nonoverlapping(cache.buffer.as_ptr(), buffer as *mut u8, len);
         *size = len as u32;
+        *received_handle = null_mut();
     }
     shm_error_t::SHM_SUCCESS
 }
@@ -956,8 +1752,82 @@ pub extern "C" fn shm_client_poll(handle: *mut ClientHandle, timeout_ms: u32) ->
     match state.inner.poll_server(timeout) {
         Ok(true) => shm_error_t::SHM_SUCCESS,
         Ok(false) => shm_error_t::SHM_ERROR_TIMEOUT,
-        Err(err) => err.into(),
+        Err(err) => state.fail("poll_server", err),
+    }
+}
+
+/// Включить/выключить CRC-32 трейлер для сообщений серверу.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_set_checksum(handle: *mut ClientHandle, enabled: bool) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.set_checksum(enabled);
+    shm_error_t::SHM_SUCCESS
+}
+
+/// Кредитный режим для сообщений от сервера (см.
+/// `shm_server_set_credit_window`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_set_credit_window(
+    handle: *mut ClientHandle,
+    messages: u32,
+    bytes: u32,
+) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
     }
+    let state = unsafe { &*client_state_from(handle) };
+    match state
+        .inner
+        .set_credit_window(ffi_credit_window(messages, bytes))
+    {
+        Ok(()) => shm_error_t::SHM_SUCCESS,
+        Err(err) => state.fail("set_credit_window", err),
+    }
+}
+
+/// Число сообщений от сервера, отброшенных из-за несовпадения CRC-32.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_checksum_errors(handle: *mut ClientHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.checksum_errors()
+}
+
+/// Срок годности сообщений серверу по умолчанию (см. `shm_server_set_ttl`).
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_set_ttl(handle: *mut ClientHandle, ttl_ms: u32) -> shm_error_t {
+    if handle.is_null() {
+        return shm_error_t::SHM_ERROR_INVALID_PARAM;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.set_default_ttl(ffi_ttl(ttl_ms));
+    shm_error_t::SHM_SUCCESS
+}
+
+/// Число просроченных сообщений от сервера, пропущенных или убранных.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_expired_messages(handle: *mut ClientHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.expired_messages()
+}
+
+/// Возможности (`FEATURE_*`), согласованные с сервером; 0 -- сервер
+/// старой версии.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_negotiated_features(handle: *mut ClientHandle) -> u32 {
+    if handle.is_null() {
+        return 0;
+    }
+    let state = unsafe { &*client_state_from(handle) };
+    state.inner.negotiated_features()
 }
 
 /// Получить event handles для передачи в kernel driver
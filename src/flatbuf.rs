@@ -0,0 +1,135 @@
+//! Flatbuffers поверх каналов и arena (feature `flatbuffers`).
+//!
+//! [`root`] проверяет буфер (`flatbuffers::Verifier`) и отдаёт корневую
+//! таблицу, ссылающуюся прямо на переданные байты, без десериализации.
+//! Источник байтов -- любой:
+//!
+//! - payload в `AutoHandler::on_message`: корень живёт до конца callback'а;
+//! - [`ArenaGuard`](crate::arena::ArenaGuard): корень ссылается на память
+//!   arena и живёт, пока жив guard -- полностью zero-copy путь для
+//!   высокочастотной телеметрии (в кольце идёт только 12-байтовый
+//!   `ArenaHandle`, см. [`put_finished`]).
+//!
+//! Из самого кольца zero-copy чтения нет: writer при переполнении
+//! вытесняет непрочитанные кадры (`discard_oldest`), поэтому ссылку на
+//! байты кольца нельзя держать дольше одного чтения -- payload копируется
+//! в буфер worker'а. Большие сообщения стоит передавать через arena.
+//!
+//! ```ignore
+//! // отправитель
+//! let handle = xshm::flatbuf::put_finished(&arena, &builder)?;
+//! client.send(&handle.to_bytes())?;
+//!
+//! // получатель (on_message)
+//! let guard = arena.take(ArenaHandle::from_bytes(payload).unwrap())?;
+//! let sample = xshm::flatbuf::root::<telemetry::Sample>(&guard)?;
+//! ```
+
+use flatbuffers::{FlatBufferBuilder, Follow, Verifiable};
+
+use crate::arena::{Arena, ArenaHandle};
+use crate::error::{Result, ShmError};
+
+/// Проверенный корень flatbuffer'а `T` поверх `bytes` (без копирования).
+/// Некорректный буфер -- `ShmError::DecodeFailed`.
+pub fn root<'a, T>(bytes: &'a [u8]) -> Result<T::Inner>
+where
+    T: 'a + Follow<'a> + Verifiable,
+{
+    flatbuffers::root::<T>(bytes).map_err(|err| ShmError::DecodeFailed(err.to_string()))
+}
+
+/// Кладёт готовый (`finish`) буфер builder'а в arena.
+pub fn put_finished(arena: &Arena, builder: &FlatBufferBuilder<'_>) -> Result<ArenaHandle> {
+    arena.put_bytes(builder.finished_data())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaOptions;
+    use flatbuffers::{ForwardsUOffset, InvalidFlatbuffer, Table, VOffsetT, Verifier};
+
+    /// То, что flatc сгенерировал бы для
+    /// `table Sample { id: uint; name: string; }`.
+    struct Sample<'a> {
+        tab: Table<'a>,
+    }
+
+    impl<'a> Follow<'a> for Sample<'a> {
+        type Inner = Sample<'a>;
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+            Sample {
+                tab: unsafe { Table::new(buf, loc) },
+            }
+        }
+    }
+
+    impl<'a> Sample<'a> {
+        const VT_ID: VOffsetT = 4;
+        const VT_NAME: VOffsetT = 6;
+
+        fn id(&self) -> u32 {
+            // SAFETY: таблица проверена verifier'ом.
+            unsafe { self.tab.get::<u32>(Self::VT_ID, Some(0)).unwrap() }
+        }
+
+        fn name(&self) -> Option<&'a str> {
+            // SAFETY: см. id.
+            unsafe { self.tab.get::<ForwardsUOffset<&str>>(Self::VT_NAME, None) }
+        }
+    }
+
+    impl Verifiable for Sample<'_> {
+        fn run_verifier(
+            v: &mut Verifier<'_, '_>,
+            pos: usize,
+        ) -> std::result::Result<(), InvalidFlatbuffer> {
+            v.visit_table(pos)?
+                .visit_field::<u32>("id", Self::VT_ID, false)?
+                .visit_field::<ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
+                .finish();
+            Ok(())
+        }
+    }
+
+    fn build(id: u32, name: &str) -> FlatBufferBuilder<'static> {
+        let mut builder = FlatBufferBuilder::new();
+        let name = builder.create_string(name);
+        let start = builder.start_table();
+        builder.push_slot::<u32>(Sample::VT_ID, id, 0);
+        builder.push_slot_always(Sample::VT_NAME, name);
+        let table = builder.end_table(start);
+        builder.finish_minimal(table);
+        builder
+    }
+
+    #[test]
+    fn root_is_verified() {
+        let builder = build(42, "sensor");
+        let sample = root::<Sample>(builder.finished_data()).unwrap();
+        assert_eq!(sample.id(), 42);
+        assert_eq!(sample.name(), Some("sensor"));
+
+        let truncated = &builder.finished_data()[..6];
+        assert!(matches!(
+            root::<Sample>(truncated).err(),
+            Some(ShmError::DecodeFailed(_))
+        ));
+    }
+
+    #[test]
+    fn root_references_arena_memory() {
+        let name = format!("XSHM_FLATBUF_{}", std::process::id());
+        let arena = Arena::create(&name, ArenaOptions::default()).unwrap();
+        let handle = put_finished(&arena, &build(7, "telemetry")).unwrap();
+
+        let peer = Arena::open(&name).unwrap();
+        let guard = peer.take(handle).unwrap();
+        let sample = root::<Sample>(&guard).unwrap();
+        assert_eq!(sample.id(), 7);
+        let text = sample.name().unwrap();
+        assert!(guard.as_ptr_range().contains(&text.as_ptr()));
+    }
+}
@@ -0,0 +1,312 @@
+//! Async-API без привязки к runtime (feature `futures`).
+//!
+//! [`AsyncBridge`] -- `AutoHandler`, который складывает входящие сообщения
+//! в очередь и будит сохранённые `Waker`'ы прямо из worker-потока канала.
+//! Поверх него -- [`MessageStream`] (`futures_core::Stream`) и
+//! [`SendFuture`]. Ни executor, ни reactor не нужны: работает под tokio,
+//! smol, async-std или самописным `block_on`.
+//!
+//! ```ignore
+//! let bridge = AsyncBridge::new(1024);
+//! let client = AutoClient::connect("MyService", bridge.clone(), AutoOptions::default())?;
+//!
+//! bridge.send(&client, b"request").await?;
+//! let mut messages = bridge.stream();
+//! while let Some(message) = messages.recv().await {
+//!     handle(&message);
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::auto::{AutoHandler, ChannelKind};
+use crate::error::{Result, ShmError};
+use crate::record::SendEndpoint;
+
+#[derive(Default)]
+struct BridgeState {
+    queue: VecDeque<Vec<u8>>,
+    dropped: u64,
+    connected: bool,
+    closed: bool,
+    recv_waker: Option<Waker>,
+    send_wakers: Vec<Waker>,
+}
+
+/// Мост между callback'ами канала и `Waker`'ами async-кода. Передаётся
+/// каналу как handler (`bridge.clone()`).
+pub struct AsyncBridge {
+    state: Mutex<BridgeState>,
+    capacity: usize,
+}
+
+impl AsyncBridge {
+    /// `capacity` -- сколько непрочитанных сообщений держать; при
+    /// переполнении вытесняется самое старое (см. [`dropped`](Self::dropped)).
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(BridgeState::default()),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// Поток входящих сообщений. Потребитель должен быть один: будится
+    /// только последний опросивший.
+    pub fn stream(self: &Arc<Self>) -> MessageStream {
+        MessageStream {
+            bridge: self.clone(),
+        }
+    }
+
+    /// Отправка, которая при `ShmError::QueueFull` ждёт освобождения места
+    /// (`on_space_available`) вместо ошибки.
+    pub fn send<'a, E: SendEndpoint>(
+        &'a self,
+        endpoint: &'a E,
+        payload: &'a [u8],
+    ) -> SendFuture<'a, E> {
+        SendFuture {
+            bridge: self,
+            endpoint,
+            payload,
+        }
+    }
+
+    /// Завершает поток: после выборки оставшихся сообщений он вернёт
+    /// `None`, ожидающие отправки получат `ShmError::Cancelled`.
+    pub fn close(&self) {
+        let (recv, send) = {
+            let mut state = self.state.lock().unwrap();
+            state.closed = true;
+            (
+                state.recv_waker.take(),
+                std::mem::take(&mut state.send_wakers),
+            )
+        };
+        recv.into_iter().chain(send).for_each(Waker::wake);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().connected
+    }
+
+    /// Сколько сообщений вытеснено из переполненной очереди.
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+
+    fn wake_senders(&self) {
+        let wakers = std::mem::take(&mut self.state.lock().unwrap().send_wakers);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl AutoHandler for AsyncBridge {
+    fn on_connect(&self) {
+        self.state.lock().unwrap().connected = true;
+    }
+
+    fn on_disconnect(&self) {
+        self.state.lock().unwrap().connected = false;
+    }
+
+    fn on_message(&self, _direction: ChannelKind, payload: &[u8]) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            if state.queue.len() >= self.capacity {
+                state.queue.pop_front();
+                state.dropped += 1;
+            }
+            state.queue.push_back(payload.to_vec());
+            state.recv_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn on_space_available(&self, _direction: ChannelKind) {
+        self.wake_senders();
+    }
+}
+
+/// Входящие сообщения канала.
+pub struct MessageStream {
+    bridge: Arc<AsyncBridge>,
+}
+
+impl MessageStream {
+    /// Следующее сообщение (`None` после [`AsyncBridge::close`]).
+    pub fn recv(&mut self) -> Recv<'_> {
+        Recv { stream: self }
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        let mut state = self.bridge.state.lock().unwrap();
+        if let Some(message) = state.queue.pop_front() {
+            return Poll::Ready(Some(message));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.recv_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Future из [`MessageStream::recv`].
+pub struct Recv<'a> {
+    stream: &'a mut MessageStream,
+}
+
+impl Future for Recv<'_> {
+    type Output = Option<Vec<u8>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+/// Future из [`AsyncBridge::send`].
+pub struct SendFuture<'a, E> {
+    bridge: &'a AsyncBridge,
+    endpoint: &'a E,
+    payload: &'a [u8],
+}
+
+impl<E: SendEndpoint> Future for SendFuture<'_, E> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // Waker регистрируется ДО попытки: место, освободившееся между
+        // неудачной записью и регистрацией, иначе не разбудило бы нас.
+        {
+            let mut state = self.bridge.state.lock().unwrap();
+            if state.closed {
+                return Poll::Ready(Err(ShmError::Cancelled));
+            }
+            if !state.send_wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                state.send_wakers.push(cx.waker().clone());
+            }
+        }
+        match self.endpoint.send_payload(self.payload) {
+            Err(ShmError::QueueFull) => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::{AutoClient, AutoOptions, AutoServer};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Минимальный executor: async-API не должен требовать runtime.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park_timeout(Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn stream_yields_messages_in_order() {
+        let name = format!("XSHM_FUTURES_{}", std::process::id());
+        let bridge = AsyncBridge::new(16);
+        let _server = AutoServer::start(&name, bridge.clone(), AutoOptions::default()).unwrap();
+        let client_bridge = AsyncBridge::new(16);
+        let client =
+            AutoClient::connect(&name, client_bridge.clone(), AutoOptions::default()).unwrap();
+
+        for payload in [b"one", b"two", b"six"] {
+            block_on(client_bridge.send(&client, payload)).unwrap();
+        }
+        let mut stream = bridge.stream();
+        for expected in [b"one", b"two", b"six"] {
+            assert_eq!(block_on(stream.recv()).unwrap(), expected);
+        }
+
+        bridge.close();
+        assert_eq!(block_on(stream.recv()), None);
+    }
+
+    /// Endpoint, который отвечает `QueueFull`, пока не разрешат запись.
+    struct Gate {
+        open: AtomicU32,
+    }
+
+    impl SendEndpoint for Gate {
+        const DIRECTION: ChannelKind = ChannelKind::ClientToServer;
+
+        fn send_payload(&self, _payload: &[u8]) -> Result<()> {
+            match self.open.load(Ordering::Acquire) {
+                0 => Err(ShmError::QueueFull),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn send_waits_for_space() {
+        let bridge = AsyncBridge::new(1);
+        let gate = Arc::new(Gate {
+            open: AtomicU32::new(0),
+        });
+        let opener = thread::spawn({
+            let bridge = bridge.clone();
+            let gate = gate.clone();
+            move || {
+                thread::sleep(Duration::from_millis(50));
+                gate.open.store(1, Ordering::Release);
+                bridge.on_space_available(ChannelKind::ClientToServer);
+            }
+        });
+        block_on(bridge.send(&*gate, b"payload")).unwrap();
+        opener.join().unwrap();
+
+        bridge.close();
+        assert_eq!(
+            block_on(bridge.send(&*gate, b"payload")),
+            Err(ShmError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn overflow_drops_oldest() {
+        let bridge = AsyncBridge::new(2);
+        for payload in [b"a1", b"b2", b"c3"] {
+            bridge.on_message(ChannelKind::ClientToServer, payload);
+        }
+        assert_eq!(bridge.dropped(), 1);
+        let mut stream = bridge.stream();
+        assert_eq!(block_on(stream.recv()).unwrap(), b"b2");
+        assert_eq!(block_on(stream.recv()).unwrap(), b"c3");
+    }
+}
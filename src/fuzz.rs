@@ -0,0 +1,48 @@
+//! Точки входа для cargo-fuzz (`fuzz/`). Собираются только под
+//! `--cfg fuzzing`, который выставляет `cargo fuzz`: внутренности кольца
+//! наружу в обычной сборке не торчат.
+
+use std::sync::atomic::Ordering;
+
+use crate::constants::{MAX_MESSAGES, RING_CAPACITY, RING_MASK};
+use crate::layout::RingHeader;
+use crate::ring::RingBuffer;
+
+/// Поля заголовка, которые берутся из входа: write_pos, read_pos,
+/// message_count (по 4 байта LE) и байт режима.
+const HEADER_INPUT: usize = 13;
+
+/// Враждебный пир записал в shared memory что угодно: заголовок кольца и
+/// байты в районе `read_pos` берутся из `input`. Чтение и запись обязаны
+/// вернуть ошибку, а не паниковать, зависать или выходить за кольцо.
+pub fn ring_with_hostile_header(input: &[u8]) {
+    if input.len() < HEADER_INPUT {
+        return;
+    }
+    let word = |at: usize| u32::from_le_bytes(input[at..at + 4].try_into().unwrap());
+
+    let mut header = Box::<RingHeader>::default();
+    let mut data = vec![0u8; RING_CAPACITY];
+    header.write_pos.store(word(0), Ordering::Relaxed);
+    header.read_pos.store(word(4), Ordering::Relaxed);
+    header.message_count.store(word(8), Ordering::Relaxed);
+
+    // Мусор кладём туда, откуда reader начнёт разбор (с переносом).
+    let start = (word(4) & RING_MASK) as usize;
+    for (offset, &byte) in input[HEADER_INPUT..].iter().enumerate() {
+        data[(start + offset) % RING_CAPACITY] = byte;
+    }
+
+    // SAFETY: header и data живут до конца функции, дольше ring.
+    let ring = unsafe { RingBuffer::new(&mut *header, data.as_mut_ptr()) };
+    ring.set_checksum(input[12] & 1 != 0);
+
+    let mut out = Vec::new();
+    for _ in 0..=MAX_MESSAGES {
+        if ring.read_message(&mut out).is_err() {
+            break;
+        }
+    }
+    let _ = ring.write_message(&input[..2]);
+    let _ = ring.read_message(&mut out);
+}
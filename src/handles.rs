@@ -0,0 +1,168 @@
+//! Передача handle'ов (секции, события, файлы) между сторонами канала.
+//!
+//! Отправитель дублирует handle в процесс пира (PID из handshake, см.
+//! `RESERVED_*_PID_INDEX`) и кладёт новое значение в кольцо управляющим
+//! кадром с `MSG_FLAG_HANDLE`. Получатель видит его как
+//! [`Received::Handle`] и владеет handle'ом: закрыть его -- его забота.
+//! Обычные `receive_from_*` handle-кадры закрывают и пропускают, так что
+//! код, не знающий о handle'ах, их не копит.
+
+use xshm_core::ring::FrameInfo;
+
+use crate::constants::{HANDLE_FRAME_SIZE, MSG_FLAG_HANDLE};
+use crate::error::{Result, ShmError};
+use crate::platform::Backend;
+use crate::ring::{RingBuffer, WriteOutcome};
+
+/// Что пришло из кольца.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    /// Обычное сообщение указанной длины (в буфере вызывающего).
+    Message(usize),
+    /// Handle, уже продублированный в текущий процесс. Закрывается
+    /// получателем (`CloseHandle`/`NtClose`).
+    Handle(isize),
+}
+
+/// Сообщение вместе с номером кадра (см. `receive_message_from_*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceivedMessage {
+    /// Длина сообщения (в буфере вызывающего).
+    pub len: usize,
+    /// Номер кадра (`MSG_FLAG_SEQUENCE`), `None` -- отправитель не
+    /// нумерует кадры. Номера идут подряд по модулю 2^32; разрыв --
+    /// столько кадров не дошло (вытеснены или это пропущенные
+    /// handle-кадры).
+    pub seq: Option<u32>,
+}
+
+/// Дублирует `handle` в процесс `peer_pid` средствами `backend` канала и
+/// пишет handle-кадр. Если кадр записать не удалось, копия в процессе пира
+/// закрывается.
+pub(crate) fn write_handle(
+    ring: &RingBuffer,
+    backend: Backend,
+    handle: isize,
+    peer_pid: u32,
+) -> Result<WriteOutcome> {
+    if peer_pid == 0 {
+        return Err(ShmError::Unsupported("peer did not publish its PID"));
+    }
+    let remote = backend.duplicate_handle_into(handle, peer_pid)?;
+    ring.write_frame(&(remote as i64).to_le_bytes(), MSG_FLAG_HANDLE)
+        .inspect_err(|_| backend.close_remote_handle(remote, peer_pid))
+}
+
+/// Читает следующий кадр и разбирает handle-кадры.
+pub(crate) fn read_any(ring: &RingBuffer, buffer: &mut Vec<u8>) -> Result<Received> {
+    let (len, flags) = ring.read_frame(buffer)?;
+    if flags & MSG_FLAG_HANDLE == 0 {
+        return Ok(Received::Message(len));
+    }
+    let bytes: [u8; HANDLE_FRAME_SIZE] =
+        buffer[..len].try_into().map_err(|_| ShmError::Corrupted)?;
+    buffer.clear();
+    Ok(Received::Handle(i64::from_le_bytes(bytes) as isize))
+}
+
+/// Как `read_message`, но payload отдаётся `f` без копии (см.
+/// `RingBuffer::read_frame_with`).
+pub(crate) fn read_message_with<T>(
+    ring: &RingBuffer,
+    backend: Backend,
+    mut f: impl FnMut(&[u8]) -> T,
+) -> Result<T> {
+    loop {
+        let frame = ring.read_frame_with(|payload, flags| {
+            if flags & MSG_FLAG_HANDLE == 0 {
+                Ok(f(payload))
+            } else {
+                Err(<[u8; HANDLE_FRAME_SIZE]>::try_from(payload).ok())
+            }
+        })?;
+        match frame {
+            Ok(value) => return Ok(value),
+            Err(Some(bytes)) => backend.close_handle(i64::from_le_bytes(bytes) as isize),
+            Err(None) => return Err(ShmError::Corrupted),
+        }
+    }
+}
+
+/// Отдаёт `f` без копии сообщения из `lane()` (кольцо выбирается заново
+/// перед каждым), пока они есть, но не больше `limit` -- очереди на момент
+/// вызова, чтобы быстрый писатель не держал вызывающего вечно.
+pub(crate) fn drain_into<'a>(
+    mut lane: impl FnMut() -> &'a RingBuffer,
+    backend: Backend,
+    limit: u32,
+    f: &mut impl FnMut(&[u8]),
+) -> Result<usize> {
+    let mut drained = 0;
+    while drained < limit as usize {
+        match read_message_with(lane(), backend, &mut *f) {
+            Ok(()) => drained += 1,
+            Err(ShmError::QueueEmpty) => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(drained)
+}
+
+/// Как `read_message`, но сообщение остаётся в кольце (см.
+/// `RingBuffer::peek_frame_with`); handle-кадры перед ним закрываются и
+/// забираются.
+pub(crate) fn peek_message(ring: &RingBuffer, backend: Backend, buffer: &mut Vec<u8>) -> Result<usize> {
+    loop {
+        let frame = ring.peek_frame_with(
+            |flags| flags & MSG_FLAG_HANDLE == 0,
+            |payload, flags| {
+                if flags & MSG_FLAG_HANDLE == 0 {
+                    buffer.clear();
+                    buffer.extend_from_slice(payload);
+                    Ok(payload.len())
+                } else {
+                    Err(<[u8; HANDLE_FRAME_SIZE]>::try_from(payload).ok())
+                }
+            },
+        )?;
+        match frame {
+            Ok(len) => return Ok(len),
+            Err(Some(bytes)) => backend.close_handle(i64::from_le_bytes(bytes) as isize),
+            Err(None) => return Err(ShmError::Corrupted),
+        }
+    }
+}
+
+/// Как `read_any`, но handle-кадры закрываются и пропускаются.
+pub(crate) fn read_message(ring: &RingBuffer, backend: Backend, buffer: &mut Vec<u8>) -> Result<usize> {
+    read_message_frame(ring, backend, buffer).map(|(len, _)| len)
+}
+
+/// Как `read_message`, вместе с флагами заголовка (`MSG_FLAG_CHUNK`).
+pub(crate) fn read_message_frame(
+    ring: &RingBuffer,
+    backend: Backend,
+    buffer: &mut Vec<u8>,
+) -> Result<(usize, u16)> {
+    read_message_info(ring, backend, buffer).map(|frame| (frame.len, frame.flags))
+}
+
+/// Как `read_message`, со всем заголовком кадра (номер для
+/// `ReceivedMessage`).
+pub(crate) fn read_message_info(
+    ring: &RingBuffer,
+    backend: Backend,
+    buffer: &mut Vec<u8>,
+) -> Result<FrameInfo> {
+    loop {
+        let frame = ring.read_frame_info(buffer)?;
+        if frame.flags & MSG_FLAG_HANDLE == 0 {
+            return Ok(frame);
+        }
+        let bytes: [u8; HANDLE_FRAME_SIZE] = buffer[..frame.len]
+            .try_into()
+            .map_err(|_| ShmError::Corrupted)?;
+        buffer.clear();
+        backend.close_handle(i64::from_le_bytes(bytes) as isize);
+    }
+}
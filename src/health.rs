@@ -0,0 +1,128 @@
+//! Состояние IPC-связей для liveness-проб.
+//!
+//! `health()` есть у `AutoServer`/`AutoClient`, `DispatchClient`, а у
+//! `MultiServer`/`DispatchServer` -- по клиенту (`health(client_id)`).
+//! Все возвращают один и тот же [`ChannelHealth`], так что проба хоста
+//! обходит связи любого режима одинаково.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use xshm::health::LinkState;
+//! use xshm::{AutoHandler, AutoOptions, AutoServer};
+//!
+//! struct Quiet;
+//! impl AutoHandler for Quiet {}
+//!
+//! let server = AutoServer::start("app", Arc::new(Quiet), AutoOptions::default())?;
+//! let health = server.health();
+//! let stale = health.last_rx_age.map_or(true, |age| age > Duration::from_secs(30));
+//! if health.state != LinkState::Connected || stale {
+//!     eprintln!("app link unhealthy: {health:?}");
+//! }
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::ShmError;
+use crate::platform;
+
+/// Состояние связи.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// Пира нет: сервер ждёт клиента, клиент переподключается.
+    Waiting,
+    Connected,
+    /// `stop()`, отмена токена или паника worker'а.
+    Stopped,
+}
+
+/// Глубины очередей на момент последнего прохода worker'а (у `multi` --
+/// на момент вызова).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueDepths {
+    /// Сообщения в очереди отправки auto worker'а (у `multi` её нет -- 0).
+    pub send_queue: usize,
+    pub send_queue_bytes: usize,
+    /// Непрочитанные входящие кадры в кольцах.
+    pub rx_ring: u32,
+    /// Исходящие кадры, ещё не прочитанные пиром.
+    pub tx_ring: u32,
+}
+
+/// Снимок состояния одной связи.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelHealth {
+    pub state: LinkState,
+    /// Сколько прошло с последнего принятого сообщения; `None` -- не было.
+    pub last_rx_age: Option<Duration>,
+    /// Сколько прошло с последнего отправленного сообщения; `None` -- не было.
+    pub last_tx_age: Option<Duration>,
+    pub queue_depths: QueueDepths,
+    /// Переподключения: соединения после первого.
+    pub reconnects: u64,
+    /// Последняя ошибка, о которой сообщил handler'у worker.
+    pub last_error: Option<ShmError>,
+}
+
+impl ChannelHealth {
+    /// Связь, от которой ничего не осталось (остановленный endpoint).
+    pub(crate) fn stopped() -> Self {
+        LinkActivity::default().health(LinkState::Stopped, QueueDepths::default(), 0)
+    }
+}
+
+/// Время последнего приёма/отправки и последняя ошибка связи.
+#[derive(Default)]
+pub(crate) struct LinkActivity {
+    /// `platform::monotonic_ns()`, 0 -- ещё не было.
+    last_rx_ns: AtomicU64,
+    last_tx_ns: AtomicU64,
+    last_error: Mutex<Option<ShmError>>,
+}
+
+impl LinkActivity {
+    pub(crate) fn touch_rx(&self) {
+        self.last_rx_ns.store(now_ns(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn touch_tx(&self) {
+        self.last_tx_ns.store(now_ns(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_error(&self, err: &ShmError) {
+        *self.last_error.lock().unwrap() = Some(err.clone());
+    }
+
+    /// Собирает снимок; `state`, глубины и переподключения знает владелец.
+    pub(crate) fn health(
+        &self,
+        state: LinkState,
+        queue_depths: QueueDepths,
+        reconnects: u64,
+    ) -> ChannelHealth {
+        ChannelHealth {
+            state,
+            last_rx_age: age(&self.last_rx_ns),
+            last_tx_age: age(&self.last_tx_ns),
+            queue_depths,
+            reconnects,
+            last_error: self.last_error.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn now_ns() -> u64 {
+    // 0 занят под «не было».
+    platform::monotonic_ns().max(1)
+}
+
+fn age(stamp: &AtomicU64) -> Option<Duration> {
+    match stamp.load(Ordering::Relaxed) {
+        0 => None,
+        ns => Some(Duration::from_nanos(now_ns().saturating_sub(ns))),
+    }
+}
@@ -0,0 +1,249 @@
+//! Гистограмма задержки доставки сообщений.
+//!
+//! Отправитель с включёнными метками (`set_timestamps` /
+//! `AutoOptions::latency`) дописывает к каждому кадру время записи
+//! (`MSG_FLAG_TIMESTAMP`, монотонные часы машины, общие для процессов).
+//! Получатель с подключённой гистограммой (`set_latency_histogram`)
+//! записывает в неё «сейчас минус метка» -- путь enqueue→dequeue целиком,
+//! включая время ожидания в кольце.
+//!
+//! Гистограмма -- log-linear в духе HDR: 16 линейных корзин на каждую
+//! степень двойки, т.е. относительная погрешность не больше 1/16
+//! (6.25%). Корзины -- атомики: запись lock-free, снимок можно брать из
+//! любого потока на ходу.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use xshm::latency::LatencyHistogram;
+//! # fn demo(server: &mut xshm::SharedServer) {
+//! let histogram = Arc::new(LatencyHistogram::new());
+//! server.set_latency_histogram(Some(histogram.clone()));
+//! // ... клиент шлёт с `set_timestamps(true)` ...
+//! let snapshot = histogram.snapshot();
+//! println!("p50={}ns p99={}ns p999={}ns", snapshot.p50_ns, snapshot.p99_ns, snapshot.p999_ns);
+//! # }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::platform;
+
+/// Линейных корзин на степень двойки (log2).
+const SUB_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BITS;
+/// Старшая различимая степень двойки: 2^40 нс ≈ 18 минут. Всё дольше
+/// попадает в последнюю корзину.
+const MAX_EXPONENT: u32 = 40;
+const BUCKETS: usize = (MAX_EXPONENT - SUB_BITS + 2) as usize * SUB_BUCKETS;
+
+/// Текущее время монотонных часов машины в наносекундах (та же шкала,
+/// что у меток `MSG_FLAG_TIMESTAMP`).
+pub fn timestamp_ns() -> u64 {
+    platform::monotonic_ns()
+}
+
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS as u64 {
+        return value as usize;
+    }
+    let exponent = (63 - value.leading_zeros()).min(MAX_EXPONENT);
+    if exponent == MAX_EXPONENT && value >> MAX_EXPONENT > 1 {
+        return BUCKETS - 1;
+    }
+    let sub = (value >> (exponent - SUB_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exponent - SUB_BITS + 1) as usize * SUB_BUCKETS + sub
+}
+
+/// Наибольшее значение, попадающее в корзину `index`.
+fn bucket_upper(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let exponent = (index / SUB_BUCKETS) as u32 + SUB_BITS - 1;
+    let sub = (index % SUB_BUCKETS) as u64;
+    let width = 1u64 << (exponent - SUB_BITS);
+    (1u64 << exponent) + (sub + 1) * width - 1
+}
+
+/// Гистограмма задержек в наносекундах.
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+    last: AtomicU64,
+}
+
+/// Сводка гистограммы. Перцентили -- верхняя граница корзины (не больше
+/// `max_ns`); пустая гистограмма -- все поля 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySnapshot {
+    pub count: u64,
+    pub min_ns: u64,
+    pub max_ns: u64,
+    pub mean_ns: u64,
+    pub p50_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    /// Последняя записанная задержка. Это путь в одну сторону (метка
+    /// отправителя → чтение получателем), а не round-trip: осмысленна,
+    /// только пока у сторон общие часы.
+    pub last_ns: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        self.record_ns(latency.as_nanos().min(u64::MAX as u128) as u64);
+    }
+
+    pub fn record_ns(&self, nanos: u64) {
+        self.buckets[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(nanos, Ordering::Relaxed);
+        self.min.fetch_min(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+        self.last.store(nanos, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Задержка кадра с меткой `timestamp` (см. [`timestamp_ns`]).
+    pub(crate) fn record_since(&self, timestamp: u64) {
+        self.record_ns(timestamp_ns().saturating_sub(timestamp));
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Значение, не меньше которого `quantile` (0.0..=1.0) записанных
+    /// задержек; 0 для пустой гистограммы.
+    pub fn percentile(&self, quantile: f64) -> u64 {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        let max = self.max.load(Ordering::Relaxed);
+        percentile_of(&counts, quantile, max)
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        // Корзины и счётчики читаются не атомарно вместе: при записи на
+        // ходу count может на пару единиц отличаться от суммы корзин.
+        let count: u64 = counts.iter().sum();
+        if count == 0 {
+            return LatencySnapshot::default();
+        }
+        let max = self.max.load(Ordering::Relaxed);
+        LatencySnapshot {
+            count,
+            min_ns: self.min.load(Ordering::Relaxed).min(max),
+            max_ns: max,
+            mean_ns: self.sum.load(Ordering::Relaxed) / self.count().max(1),
+            p50_ns: percentile_of(&counts, 0.5, max),
+            p99_ns: percentile_of(&counts, 0.99, max),
+            p999_ns: percentile_of(&counts, 0.999, max),
+            last_ns: self.last.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Обнуляет гистограмму (например, после прогрева).
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+        self.last.store(0, Ordering::Relaxed);
+    }
+}
+
+fn percentile_of(counts: &[u64], quantile: f64, max: u64) -> u64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+    let rank = ((quantile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (index, &count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return bucket_upper(index).min(max);
+        }
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_cover_values_within_one_sixteenth() {
+        for value in (0..20).chain([17, 100, 1_000, 65_535, 1 << 20, 123_456_789, 1 << 40]) {
+            let index = bucket_index(value);
+            assert!(index < BUCKETS);
+            let upper = bucket_upper(index);
+            assert!(upper >= value, "{value} above its bucket {upper}");
+            assert!(upper - value <= value / 16, "{value} -> {upper}");
+        }
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+        // Корзины идут подряд: верхняя граница следующей строго больше.
+        for index in 1..BUCKETS {
+            assert!(bucket_upper(index) > bucket_upper(index - 1));
+        }
+    }
+
+    #[test]
+    fn percentiles_follow_recorded_distribution() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.snapshot(), LatencySnapshot::default());
+
+        for nanos in 1..=1000u64 {
+            histogram.record_ns(nanos * 1_000);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 1000);
+        assert_eq!(snapshot.min_ns, 1_000);
+        assert_eq!(snapshot.max_ns, 1_000_000);
+        assert_eq!(snapshot.mean_ns, 500_500);
+        assert_eq!(snapshot.last_ns, 1_000_000);
+        for (value, expected) in [
+            (snapshot.p50_ns, 500_000),
+            (snapshot.p99_ns, 990_000),
+            (snapshot.p999_ns, 999_000),
+        ] {
+            assert!(value >= expected && value - expected <= expected / 16);
+        }
+        assert_eq!(histogram.percentile(1.0), 1_000_000);
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.percentile(0.5), 0);
+    }
+}
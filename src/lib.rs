@@ -1,58 +1,120 @@
 #![forbid(unsafe_op_in_unsafe_fn)]
 
+mod cancel;
+mod channel;
+mod chunking;
 mod client;
-mod constants;
+mod disconnect;
 mod error;
 pub mod events;
-mod layout;
-mod naming;
+mod handles;
+mod platform;
+mod poll;
+mod retry;
 mod ring;
+mod security;
 mod server;
 mod shared;
-mod win;
+mod split;
 
+// Раскладка секции, константы и кольцо -- общие с kernel-mode стороной.
+use xshm_core::{constants, layout};
+
+pub mod arena;
 pub mod auto;
+pub mod bridge;
+pub mod broadcast;
+pub mod codec;
+#[cfg(feature = "encryption")]
+pub mod crypto;
+pub mod diagnostics;
+pub mod discovery;
 pub mod dispatch;
+#[cfg(feature = "ffi")]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub mod ffi;
+#[cfg(feature = "flatbuffers")]
+pub mod flatbuf;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzz;
+#[cfg(feature = "futures")]
+pub mod futures;
+pub mod health;
+pub mod latency;
+pub mod mailbox;
+pub mod metrics;
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub mod multi;
+pub mod naming;
+#[cfg(feature = "ffi")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub mod pinvoke;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod reclaim;
+pub mod record;
+pub mod registry;
+pub mod reliable;
+pub mod state;
+pub mod sync;
+pub mod upgrade;
+pub mod watchdog;
+#[cfg(windows)]
+pub mod win;
 
 // Внутренний модуль - не экспортируется в C API
+#[cfg(windows)]
 pub(crate) mod ntapi;
 
-pub use auto::{AutoClient, AutoHandler, AutoOptions, AutoServer, AutoStatsSnapshot, ChannelKind};
-pub use client::SharedClient;
+/// Сырые NT-типы и вызовы ntdll (feature `ntapi-pub`), вне semver-гарантий.
+// Отдельный реэкспорт, а не второе объявление `ntapi`: cbindgen не смотрит
+// на `cfg` и разобрал бы модуль дважды.
+#[cfg(all(windows, feature = "ntapi-pub"))]
+pub mod nt {
+    pub use crate::ntapi::*;
+}
+
+pub use auto::{
+    AutoClient, AutoHandler, AutoHandlerFn, AutoOptions, AutoOptionsBuilder, AutoServer,
+    AutoStatsSnapshot, ChannelKind, ConnectionState, MessageFilter, PooledMessage, QueuePolicy,
+    SendTicket, TapHandler,
+};
+pub use cancel::CancellationToken;
+pub use channel::{ChannelServer, AUTO_PEER_ID};
+pub use client::{ClientBuilder, ClientOptions, SharedClient};
+pub use constants::{
+    DEFAULT_FEATURES, FEATURE_CHECKSUM, FEATURE_CHUNKING, FEATURE_CONTROL_RINGS, FEATURE_CREDITS,
+    FEATURE_HANDLES, FEATURE_SEQUENCE, FEATURE_TIMESTAMPS, FEATURE_TTL, MAX_CHUNKED_MESSAGE_SIZE,
+    MAX_MESSAGES, MAX_MESSAGE_SIZE, MSG_USER_FLAGS_MASK, RING_CAPACITY, SHARED_VERSION,
+    SUPPORTED_FEATURES,
+};
+#[cfg(feature = "encryption")]
+pub use crypto::EncryptionOptions;
+pub use disconnect::DisconnectReason;
+pub use discovery::{discover, ChannelInfo};
 pub use dispatch::{
     ClientRegistration, DispatchClient, DispatchClientHandler, DispatchClientOptions,
-    DispatchHandler, DispatchOptions, DispatchServer,
+    DispatchClientOptionsBuilder, DispatchHandler, DispatchOptions, DispatchOptionsBuilder,
+    DispatchServer,
 };
-pub use error::{Result, ShmError};
+pub use error::{ErrorCategory, ErrorContext, Result, ResultExt, ShmError};
 pub use events::EventHandles;
+pub use handles::{Received, ReceivedMessage};
 pub use multi::{
-    MultiClient, MultiClientHandler, MultiClientOptions, MultiHandler, MultiOptions, MultiServer,
+    MultiClient, MultiClientHandler, MultiClientOptions, MultiClientOptionsBuilder, MultiHandler,
+    MultiOptions, MultiOptionsBuilder, MultiServer,
 };
-pub use ring::WriteOutcome;
-pub use server::SharedServer;
-
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
-
-/// Ожидание с периодической проверкой флага остановки.
-/// Возвращает `false` если `running` стал `false` (сигнал остановки).
-pub(crate) fn wait_delay(running: &AtomicBool, delay: Duration) -> bool {
-    if delay.is_zero() {
-        return running.load(Ordering::Acquire);
-    }
-    let deadline = Instant::now() + delay;
-    while Instant::now() < deadline {
-        if !running.load(Ordering::Acquire) {
-            return false;
-        }
-        std::thread::sleep(Duration::from_millis(10));
-    }
-    running.load(Ordering::Acquire)
-}
+pub use naming::Namespace;
+pub use poll::PollResult;
+pub use retry::{BackoffFn, BackoffStrategy, RetryPolicy};
+pub use ring::{
+    ChannelStats, CreditWindow, DirectionStats, RingStatus, WriteGuard, WriteOutcome, WritePolicy,
+};
+pub use security::Security;
+pub use server::{RemoteHandles, ServerBuilder, ServerOptions, SharedServer};
+pub use split::{RecvHalf, SendHalf};
+pub use state::SharedState;
 
 #[cfg(test)]
 mod tests {
@@ -108,13 +170,64 @@ mod tests {
         assert!(server_result.is_ok());
     }
 
+    /// Handle секции сервера дублируется в процесс клиента (здесь -- в
+    /// тот же процесс) и приходит отдельным значением.
+    #[cfg(windows)]
+    #[test]
+    fn handle_is_duplicated_to_peer() {
+        let name = format!("HANDLE_UNITTEST_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let client = connector.join().unwrap();
+
+        server.send_handle_to_client(server.section_handle()).unwrap();
+        let mut buffer = Vec::new();
+        match client.receive_any_from_server(&mut buffer).unwrap() {
+            Received::Handle(handle) => {
+                assert_ne!(handle, server.section_handle());
+                platform::Backend::Native.close_handle(handle);
+            }
+            other => panic!("expected handle, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handle_passing_is_unsupported_on_unix() {
+        let name = format!("HANDLE_UNITTEST_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let client = connector.join().unwrap();
+
+        assert!(matches!(
+            client.send_handle_to_server(0),
+            Err(ShmError::Unsupported(_))
+        ));
+        // Кадр не записан: кольцо пусто.
+        let mut buffer = Vec::new();
+        assert_eq!(
+            server.receive_from_client(&mut buffer),
+            Err(ShmError::QueueEmpty)
+        );
+    }
+
+    type Captured = Arc<(Mutex<Vec<Vec<u8>>>, Condvar)>;
+
     #[derive(Clone)]
     struct CaptureHandler {
-        buffer: Arc<(Mutex<Vec<Vec<u8>>>, Condvar)>,
+        buffer: Captured,
     }
 
     impl CaptureHandler {
-        fn new() -> (Self, Arc<(Mutex<Vec<Vec<u8>>>, Condvar)>) {
+        fn new() -> (Self, Captured) {
             let shared = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
             (
                 CaptureHandler {
@@ -124,7 +237,7 @@ mod tests {
             )
         }
 
-        fn wait_for(shared: &Arc<(Mutex<Vec<Vec<u8>>>, Condvar)>, expected: &[u8]) {
+        fn wait_for(shared: &Captured, expected: &[u8]) {
             let (lock, cv) = &**shared;
             let mut guard = lock.lock().unwrap();
             const TIMEOUT: Duration = Duration::from_secs(2);
@@ -0,0 +1,327 @@
+//! Mailbox: одно значение, которое writer целиком заменяет.
+//!
+//! Для периодической публикации состояния (позиция, телеметрия, конфиг),
+//! где каждое промежуточное обновление в очереди -- лишняя работа:
+//! читатель всегда получает самый свежий снимок, а не историю.
+//!
+//! Секция (`Local\{name}_MBOX`) хранит два буфера. Writer пишет в тот,
+//! что сейчас не опубликован, и затем публикует его номер `seq`
+//! (растёт с 1 на каждую запись). Читатель копирует опубликованный буфер
+//! и перепроверяет его `seq`: если writer успел начать писать в этот же
+//! буфер (через одну запись), копия отбрасывается и чтение повторяется.
+//! Writer никогда не ждёт читателей.
+//!
+//! ```no_run
+//! use xshm::mailbox::{MailboxOptions, MailboxReader, MailboxWriter};
+//!
+//! let mut writer = MailboxWriter::create("POSE", MailboxOptions::default())?;
+//! writer.write(b"x=1.0 y=2.0")?;
+//!
+//! let reader = MailboxReader::open("POSE")?;
+//! let mut buffer = Vec::new();
+//! let seq = reader.read(&mut buffer)?;
+//! assert_eq!(buffer, b"x=1.0 y=2.0");
+//! // Следующее чтение -- только если значение обновилось.
+//! assert_eq!(reader.read_newer(seq, &mut buffer)?, None);
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, ShmError};
+use crate::naming::mailbox_name;
+use crate::platform::{Mapping, PlatformMapping};
+
+/// 'XMBX'
+const MAILBOX_MAGIC: u32 = 0x584D_4258;
+const MAILBOX_VERSION: u32 = 1;
+
+/// `seq` буфера, в который writer сейчас пишет.
+const SEQ_WRITING: u64 = u64::MAX;
+
+#[repr(C, align(64))]
+struct MailboxHeader {
+    magic: u32,
+    version: u32,
+    /// Размер каждого из двух буферов.
+    capacity: u32,
+    reserved0: u32,
+    /// `seq` последнего опубликованного значения, 0 -- ещё ничего.
+    latest: AtomicU64,
+    reserved: [u32; 10],
+}
+
+#[repr(C, align(64))]
+struct BufferHeader {
+    seq: AtomicU64,
+    len: AtomicU32,
+    reserved: [u32; 13],
+}
+
+/// Параметры mailbox. Задаются writer'ом, читатели берут их из секции.
+#[derive(Debug, Clone)]
+pub struct MailboxOptions {
+    /// Максимальный размер значения.
+    pub capacity: usize,
+}
+
+impl Default for MailboxOptions {
+    fn default() -> Self {
+        Self { capacity: 4096 }
+    }
+}
+
+struct Slots {
+    mapping: Mapping,
+    capacity: usize,
+}
+
+fn buffer_stride(capacity: usize) -> usize {
+    std::mem::size_of::<BufferHeader>() + capacity.next_multiple_of(64)
+}
+
+fn section_size(capacity: usize) -> usize {
+    std::mem::size_of::<MailboxHeader>() + buffer_stride(capacity) * 2
+}
+
+impl Slots {
+    fn header(&self) -> &MailboxHeader {
+        // SAFETY: секция начинается с заголовка и живёт не меньше self.
+        unsafe { &*(self.mapping.as_ptr() as *const MailboxHeader) }
+    }
+
+    fn buffer(&self, seq: u64) -> (&BufferHeader, *mut u8) {
+        let offset = std::mem::size_of::<MailboxHeader>()
+            + (seq & 1) as usize * buffer_stride(self.capacity);
+        // SAFETY: оба буфера (заголовок + capacity байт) внутри секции
+        // размером section_size(capacity).
+        unsafe {
+            let base = self.mapping.as_ptr().add(offset);
+            (
+                &*(base as *const BufferHeader),
+                base.add(std::mem::size_of::<BufferHeader>()),
+            )
+        }
+    }
+}
+
+/// Единственный writer mailbox.
+pub struct MailboxWriter {
+    slots: Slots,
+}
+
+impl MailboxWriter {
+    /// Создаёт mailbox `name` (секция `Local\{name}_MBOX`).
+    pub fn create(name: &str, options: MailboxOptions) -> Result<Self> {
+        if options.capacity == 0 || options.capacity > u32::MAX as usize / 4 {
+            return Err(ShmError::InvalidConfig(
+                "mailbox capacity must be non-zero and below 1 GiB",
+            ));
+        }
+        let capacity = options.capacity;
+        let mapping = Mapping::create_sized(&mailbox_name(name), section_size(capacity))?;
+        let slots = Slots { mapping, capacity };
+        // SAFETY: секция только что создана нами и вмещает заголовок.
+        unsafe {
+            let header = slots.mapping.as_ptr() as *mut MailboxHeader;
+            (*header).magic = MAILBOX_MAGIC;
+            (*header).version = MAILBOX_VERSION;
+            (*header).capacity = capacity as u32;
+        }
+        for seq in 0..2 {
+            let (buffer, _) = slots.buffer(seq);
+            buffer.seq.store(0, Ordering::Relaxed);
+            buffer.len.store(0, Ordering::Relaxed);
+        }
+        slots.header().latest.store(0, Ordering::Release);
+        Ok(Self { slots })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity
+    }
+
+    /// Заменяет значение; возвращает его `seq`.
+    pub fn write(&mut self, value: &[u8]) -> Result<u64> {
+        if value.len() > self.slots.capacity {
+            return Err(ShmError::MessageTooLarge);
+        }
+        let header = self.slots.header();
+        let seq = header.latest.load(Ordering::Relaxed) + 1;
+        let (buffer, data) = self.slots.buffer(seq);
+
+        // Seqlock: отметка «пишется» видна раньше любых байт значения.
+        buffer.seq.store(SEQ_WRITING, Ordering::Relaxed);
+        fence(Ordering::Release);
+        // SAFETY: value.len() <= capacity -- размер буфера.
+        unsafe { std::ptr::copy_nonoverlapping(value.as_ptr(), data, value.len()) };
+        buffer.len.store(value.len() as u32, Ordering::Relaxed);
+        buffer.seq.store(seq, Ordering::Release);
+
+        header.latest.store(seq, Ordering::Release);
+        Ok(seq)
+    }
+}
+
+/// Читатель mailbox. Читателей может быть сколько угодно.
+pub struct MailboxReader {
+    slots: Slots,
+}
+
+impl MailboxReader {
+    pub fn open(name: &str) -> Result<Self> {
+        let section = mailbox_name(name);
+        let capacity = {
+            let probe = Mapping::open_sized(&section, std::mem::size_of::<MailboxHeader>())?;
+            // SAFETY: отображено не меньше заголовка.
+            let header = unsafe { &*(probe.as_ptr() as *const MailboxHeader) };
+            if header.magic != MAILBOX_MAGIC {
+                return Err(ShmError::Corrupted);
+            }
+            if header.version != MAILBOX_VERSION {
+                return Err(ShmError::HandshakeFailed);
+            }
+            header.capacity as usize
+        };
+        if capacity == 0 {
+            return Err(ShmError::Corrupted);
+        }
+        Ok(Self {
+            slots: Slots {
+                mapping: Mapping::open_sized(&section, section_size(capacity))?,
+                capacity,
+            },
+        })
+    }
+
+    /// `seq` последнего опубликованного значения (0 -- ещё ничего).
+    pub fn sequence(&self) -> u64 {
+        self.slots.header().latest.load(Ordering::Acquire)
+    }
+
+    /// Копирует самое свежее значение в `buffer`, возвращает его `seq`.
+    /// `ShmError::QueueEmpty`, если writer ещё ничего не записал.
+    pub fn read(&self, buffer: &mut Vec<u8>) -> Result<u64> {
+        loop {
+            let seq = self.sequence();
+            if seq == 0 {
+                return Err(ShmError::QueueEmpty);
+            }
+            let (slot, data) = self.slots.buffer(seq);
+            if slot.seq.load(Ordering::Acquire) != seq {
+                // Writer уже пишет в этот буфер: latest вот-вот сменится.
+                std::hint::spin_loop();
+                continue;
+            }
+            let len = (slot.len.load(Ordering::Relaxed) as usize).min(self.slots.capacity);
+            buffer.resize(len, 0);
+            // SAFETY: len <= capacity; порванная копия отбрасывается ниже.
+            unsafe { std::ptr::copy_nonoverlapping(data, buffer.as_mut_ptr(), len) };
+            fence(Ordering::Acquire);
+            if slot.seq.load(Ordering::Relaxed) == seq {
+                return Ok(seq);
+            }
+        }
+    }
+
+    /// Как `read`, но только если значение новее `since`; иначе `None`.
+    pub fn read_newer(&self, since: u64, buffer: &mut Vec<u8>) -> Result<Option<u64>> {
+        if self.sequence() <= since {
+            return Ok(None);
+        }
+        self.read(buffer).map(Some)
+    }
+
+    /// Ждёт значения новее `since` не дольше `timeout` (`None` --
+    /// бесконечно), опрашивая `seq`. Событий нет: обновление ждут любые
+    /// читатели, а auto-reset событие разбудило бы только одного.
+    pub fn wait_newer(&self, since: u64, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut spins = 0u32;
+        loop {
+            if self.sequence() > since {
+                return true;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return false;
+            }
+            if spins < 1000 {
+                spins += 1;
+                std::hint::spin_loop();
+            } else {
+                std::thread::sleep(Duration::from_micros(100));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_MBOX_{tag}_{}", std::process::id())
+    }
+
+    #[test]
+    fn reader_sees_only_the_latest_value() {
+        let name = unique("LATEST");
+        let mut writer = MailboxWriter::create(&name, MailboxOptions::default()).unwrap();
+        let reader = MailboxReader::open(&name).unwrap();
+
+        let mut buffer = Vec::new();
+        assert_eq!(reader.read(&mut buffer), Err(ShmError::QueueEmpty));
+
+        for value in [&b"one"[..], b"two", b"three"] {
+            writer.write(value).unwrap();
+        }
+        assert_eq!(reader.read(&mut buffer).unwrap(), 3);
+        assert_eq!(buffer, b"three");
+        assert_eq!(reader.read_newer(3, &mut buffer).unwrap(), None);
+        assert!(!reader.wait_newer(3, Some(Duration::from_millis(1))));
+
+        writer.write(b"").unwrap();
+        assert_eq!(reader.read_newer(3, &mut buffer).unwrap(), Some(4));
+        assert!(buffer.is_empty());
+
+        assert_eq!(
+            writer.write(&vec![0; writer.capacity() + 1]),
+            Err(ShmError::MessageTooLarge)
+        );
+    }
+
+    #[test]
+    fn concurrent_reads_are_never_torn() {
+        let name = unique("TORN");
+        let mut writer = MailboxWriter::create(&name, MailboxOptions { capacity: 1024 }).unwrap();
+        writer.write(&[0; 1024]).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+
+        let reader_thread = std::thread::spawn({
+            let name = name.clone();
+            let done = done.clone();
+            move || {
+                let reader = MailboxReader::open(&name).unwrap();
+                let mut buffer = Vec::new();
+                let mut last = 0;
+                while !done.load(Ordering::Acquire) {
+                    let seq = reader.read(&mut buffer).unwrap();
+                    assert!(seq >= last);
+                    last = seq;
+                    // Значение -- 1024 одинаковых байта: порванная копия
+                    // смешала бы две записи.
+                    assert!(buffer.iter().all(|&byte| byte == buffer[0]));
+                }
+            }
+        });
+
+        for round in 0..20_000u32 {
+            writer.write(&[round as u8; 1024]).unwrap();
+        }
+        done.store(true, Ordering::Release);
+        reader_thread.join().unwrap();
+    }
+}
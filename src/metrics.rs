@@ -0,0 +1,429 @@
+//! Метрики всех активных каналов процесса.
+//!
+//! `AutoServer`/`AutoClient`, `MultiServer`/`MultiClient` и
+//! `DispatchServer`/`DispatchClient` регистрируются в глобальном реестре в
+//! конструкторе (под именем, переданным в `start`/`connect`) и снимаются с
+//! учёта в `Drop`. У multi-сервера в выгрузке сумма по слотам, у
+//! dispatch-сервера -- по выделенным каналам клиентов; сами выделенные
+//! каналы отдельно не регистрируются. Реестр держит только `Weak`-ссылки на
+//! счётчики, поэтому и запись, которую не успели снять, не переживает
+//! источник.
+//!
+//! ```no_run
+//! use xshm::metrics::{self, PrometheusFormatter};
+//!
+//! let mut prom = PrometheusFormatter::new();
+//! metrics::export(&mut prom);
+//! let body = prom.into_string(); // ответ на GET /metrics
+//! ```
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Weak};
+
+use crate::auto::AutoStatsSnapshot;
+
+/// Сторона канала в выгрузке метрик.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelRole {
+    Server,
+    Client,
+}
+
+impl ChannelRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelRole::Server => "server",
+            ChannelRole::Client => "client",
+        }
+    }
+}
+
+/// Снимок счётчиков одного канала.
+#[derive(Debug, Clone)]
+pub struct ChannelSample {
+    /// Имя канала (то же, что передано в `start`/`connect`).
+    pub name: String,
+    pub role: ChannelRole,
+    pub stats: AutoStatsSnapshot,
+}
+
+/// Приёмник выгрузки метрик (Prometheus, StatsD, лог и т.п.).
+pub trait MetricsSink {
+    /// Вызывается один раз на выгрузку со снимками всех живых каналов.
+    fn export(&mut self, samples: &[ChannelSample]);
+}
+
+/// Источник счётчиков, который можно зарегистрировать в реестре.
+pub(crate) trait MetricsSource: Send + Sync {
+    fn snapshot(&self) -> AutoStatsSnapshot;
+}
+
+struct Entry {
+    id: u64,
+    name: String,
+    role: ChannelRole,
+    source: Weak<dyn MetricsSource>,
+}
+
+static REGISTRY: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Запись в реестре; уничтожение снимает её. Владелец канала хранит её в
+/// поле, поэтому канал исчезает из выгрузки в своём `Drop`.
+pub(crate) struct Registration(u64);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        REGISTRY.lock().unwrap().retain(|entry| entry.id != self.0);
+    }
+}
+
+/// Регистрирует источник счётчиков. Мёртвые записи вычищаются попутно.
+pub(crate) fn register(
+    name: &str,
+    role: ChannelRole,
+    source: Weak<dyn MetricsSource>,
+) -> Registration {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|entry| entry.source.strong_count() > 0);
+    registry.push(Entry {
+        id,
+        name: name.to_owned(),
+        role,
+        source,
+    });
+    Registration(id)
+}
+
+/// Снимки всех живых каналов процесса в порядке регистрации.
+pub fn collect() -> Vec<ChannelSample> {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|entry| entry.source.strong_count() > 0);
+    registry
+        .iter()
+        .filter_map(|entry| {
+            entry.source.upgrade().map(|source| ChannelSample {
+                name: entry.name.clone(),
+                role: entry.role,
+                stats: source.snapshot(),
+            })
+        })
+        .collect()
+}
+
+/// Собирает снимки и передаёт их в `sink`.
+pub fn export(sink: &mut dyn MetricsSink) {
+    let samples = collect();
+    sink.export(&samples);
+}
+
+/// Формирует выгрузку в текстовом формате Prometheus (exposition format
+/// 0.0.4). Метрики помечены лейблами `channel` и `role`.
+#[derive(Default)]
+pub struct PrometheusFormatter {
+    out: String,
+}
+
+type Field = fn(&AutoStatsSnapshot) -> u64;
+
+const FAMILIES: &[(&str, &str, Field)] = &[
+    (
+        "xshm_sent_messages_total",
+        "Messages written to the outgoing ring.",
+        |s| s.sent_messages,
+    ),
+    (
+        "xshm_sent_bytes_total",
+        "Payload bytes written to the outgoing ring.",
+        |s| s.sent_bytes,
+    ),
+    (
+        "xshm_send_overflows_total",
+        "Unread messages overwritten in the outgoing ring.",
+        |s| s.send_overflows,
+    ),
+    (
+        "xshm_send_queue_drops_total",
+        "Queued messages evicted by the max_send_queue count limit.",
+        |s| s.send_queue_drops,
+    ),
+    (
+        "xshm_send_queue_byte_drops_total",
+        "Queued messages evicted by the max_send_queue_bytes budget.",
+        |s| s.send_queue_byte_drops,
+    ),
+    (
+        "xshm_send_expired_total",
+        "Messages dropped from the send queue after their send_with_deadline deadline.",
+        |s| s.send_expired,
+    ),
+    (
+        "xshm_received_messages_total",
+        "Messages delivered to the handler.",
+        |s| s.received_messages,
+    ),
+    (
+        "xshm_received_bytes_total",
+        "Payload bytes delivered to the handler.",
+        |s| s.received_bytes,
+    ),
+    (
+        "xshm_filtered_messages_total",
+        "Incoming messages rejected by the receive filter.",
+        |s| s.filtered_messages,
+    ),
+    (
+        "xshm_receive_overflows_total",
+        "Incoming messages lost to overwrite.",
+        |s| s.receive_overflows,
+    ),
+    (
+        "xshm_checksum_errors_total",
+        "Incoming messages dropped on CRC-32 mismatch.",
+        |s| s.checksum_errors,
+    ),
+    (
+        "xshm_connects_total",
+        "Established connections (the first one plus every reconnect).",
+        |s| s.connects,
+    ),
+    (
+        "xshm_disconnects_total",
+        "Connections lost (peer exit, disconnect event or fatal error).",
+        |s| s.disconnects,
+    ),
+];
+
+/// Мгновенные значения, а не накопительные счётчики.
+const GAUGES: &[(&str, &str, Field)] = &[
+    (
+        "xshm_send_queue_depth",
+        "Messages waiting in the send queue.",
+        |s| s.send_queue_depth as u64,
+    ),
+    (
+        "xshm_send_queue_bytes",
+        "Payload bytes waiting in the send queue.",
+        |s| s.send_queue_bytes as u64,
+    ),
+];
+
+impl PrometheusFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Готовый текст выгрузки.
+    pub fn into_string(self) -> String {
+        self.out
+    }
+}
+
+fn escape_label(value: &str, out: &mut String) {
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// `channel="..",role=".."` без фигурных скобок.
+fn write_labels(sample: &ChannelSample, out: &mut String) {
+    out.push_str("channel=\"");
+    escape_label(&sample.name, out);
+    let _ = write!(out, "\",role=\"{}\"", sample.role.as_str());
+}
+
+const LATENCY_FAMILY: &str = "xshm_receive_latency_seconds";
+const LAST_LATENCY_FAMILY: &str = "xshm_last_receive_latency_seconds";
+
+impl MetricsSink for PrometheusFormatter {
+    fn export(&mut self, samples: &[ChannelSample]) {
+        let families = FAMILIES.iter().map(|family| (family, "counter"));
+        let gauges = GAUGES.iter().map(|family| (family, "gauge"));
+        for ((name, help, field), kind) in families.chain(gauges) {
+            let _ = writeln!(self.out, "# HELP {name} {help}");
+            let _ = writeln!(self.out, "# TYPE {name} {kind}");
+            for sample in samples {
+                self.out.push_str(name);
+                self.out.push('{');
+                write_labels(sample, &mut self.out);
+                let _ = writeln!(self.out, "}} {}", field(&sample.stats));
+            }
+        }
+
+        // Задержка -- summary; каналы без меток времени (count == 0) не
+        // выводятся.
+        let _ = writeln!(
+            self.out,
+            "# HELP {LATENCY_FAMILY} Delivery latency of incoming timestamped messages."
+        );
+        let _ = writeln!(self.out, "# TYPE {LATENCY_FAMILY} summary");
+        for sample in samples {
+            let latency = &sample.stats.latency;
+            if latency.count == 0 {
+                continue;
+            }
+            for (quantile, nanos) in [
+                ("0.5", latency.p50_ns),
+                ("0.99", latency.p99_ns),
+                ("0.999", latency.p999_ns),
+            ] {
+                self.out.push_str(LATENCY_FAMILY);
+                self.out.push('{');
+                write_labels(sample, &mut self.out);
+                let _ = writeln!(self.out, ",quantile=\"{quantile}\"}} {}", seconds(nanos));
+            }
+            for (suffix, value) in [
+                (
+                    "_sum",
+                    seconds(latency.mean_ns.saturating_mul(latency.count)),
+                ),
+                ("_count", latency.count.to_string()),
+            ] {
+                let _ = write!(self.out, "{LATENCY_FAMILY}{suffix}{{");
+                write_labels(sample, &mut self.out);
+                let _ = writeln!(self.out, "}} {value}");
+            }
+        }
+
+        let _ = writeln!(
+            self.out,
+            "# HELP {LAST_LATENCY_FAMILY} One-way delivery latency (sender write to local receipt) of the most recent timestamped message."
+        );
+        let _ = writeln!(self.out, "# TYPE {LAST_LATENCY_FAMILY} gauge");
+        for sample in samples {
+            if sample.stats.latency.count == 0 {
+                continue;
+            }
+            self.out.push_str(LAST_LATENCY_FAMILY);
+            self.out.push('{');
+            write_labels(sample, &mut self.out);
+            let _ = writeln!(self.out, "}} {}", seconds(sample.stats.latency.last_ns));
+        }
+    }
+}
+
+fn seconds(nanos: u64) -> String {
+    format!("{:.9}", nanos as f64 / 1e9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    struct Fixed(AutoStatsSnapshot);
+
+    impl MetricsSource for Fixed {
+        fn snapshot(&self) -> AutoStatsSnapshot {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn registry_drops_closed_channels() {
+        let name = format!("METRICS_REG_{}", std::process::id());
+        let source: Arc<dyn MetricsSource> = Arc::new(Fixed(AutoStatsSnapshot {
+            sent_messages: 7,
+            ..Default::default()
+        }));
+        let _registration = register(&name, ChannelRole::Server, Arc::downgrade(&source));
+
+        let found = collect().into_iter().find(|s| s.name == name).unwrap();
+        assert_eq!(found.role, ChannelRole::Server);
+        assert_eq!(found.stats.sent_messages, 7);
+
+        drop(source);
+        assert!(collect().iter().all(|s| s.name != name));
+    }
+
+    #[test]
+    fn dropped_registration_removes_live_source() {
+        let name = format!("METRICS_DEREG_{}", std::process::id());
+        let source: Arc<dyn MetricsSource> = Arc::new(Fixed(AutoStatsSnapshot::default()));
+        let registration = register(&name, ChannelRole::Client, Arc::downgrade(&source));
+        assert!(collect().iter().any(|s| s.name == name));
+
+        drop(registration);
+        assert!(collect().iter().all(|s| s.name != name));
+    }
+
+    #[test]
+    fn prometheus_output_groups_families_and_escapes_labels() {
+        let samples = vec![
+            ChannelSample {
+                name: "a\"b".to_owned(),
+                role: ChannelRole::Server,
+                stats: AutoStatsSnapshot {
+                    sent_messages: 3,
+                    ..Default::default()
+                },
+            },
+            ChannelSample {
+                name: "c".to_owned(),
+                role: ChannelRole::Client,
+                stats: AutoStatsSnapshot {
+                    connects: 2,
+                    send_queue_depth: 4,
+                    ..Default::default()
+                },
+            },
+        ];
+        let mut prom = PrometheusFormatter::new();
+        prom.export(&samples);
+        let text = prom.into_string();
+
+        assert_eq!(
+            text.matches("# TYPE xshm_sent_messages_total counter")
+                .count(),
+            1
+        );
+        assert!(text.contains("xshm_sent_messages_total{channel=\"a\\\"b\",role=\"server\"} 3\n"));
+        assert!(text.contains("xshm_connects_total{channel=\"c\",role=\"client\"} 2\n"));
+        assert!(text.contains("# TYPE xshm_send_queue_depth gauge\n"));
+        assert!(text.contains("xshm_send_queue_depth{channel=\"c\",role=\"client\"} 4\n"));
+    }
+
+    #[test]
+    fn prometheus_output_reports_latency_quantiles() {
+        let samples = vec![ChannelSample {
+            name: "lat".to_owned(),
+            role: ChannelRole::Client,
+            stats: AutoStatsSnapshot {
+                latency: crate::latency::LatencySnapshot {
+                    count: 4,
+                    mean_ns: 2_000,
+                    p50_ns: 1_500,
+                    p99_ns: 3_000,
+                    p999_ns: 3_000,
+                    last_ns: 2_500,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }];
+        let mut prom = PrometheusFormatter::new();
+        prom.export(&samples);
+        let text = prom.into_string();
+
+        assert!(text.contains("# TYPE xshm_receive_latency_seconds summary\n"));
+        assert!(text.contains(
+            "xshm_receive_latency_seconds{channel=\"lat\",role=\"client\",quantile=\"0.5\"} 0.000001500\n"
+        ));
+        assert!(text.contains(
+            "xshm_receive_latency_seconds_sum{channel=\"lat\",role=\"client\"} 0.000008000\n"
+        ));
+        assert!(text.contains(
+            "xshm_receive_latency_seconds_count{channel=\"lat\",role=\"client\"} 4\n"
+        ));
+        assert!(text.contains(
+            "xshm_last_receive_latency_seconds{channel=\"lat\",role=\"client\"} 0.000002500\n"
+        ));
+    }
+}
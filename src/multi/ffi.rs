@@ -6,14 +6,13 @@ use std::ffi::CStr;
 use std::os::raw::{c_char, c_void};
 use std::ptr::null_mut;
 use std::sync::Arc;
-use std::time::Duration;
 
 use crate::constants::MAX_MESSAGE_SIZE;
 use crate::error::ShmError;
-use crate::ffi::shm_error_t;
+use crate::ffi::{ffi_count_or, ffi_millis_or, shm_error_t};
 use crate::multi::{MultiHandler, MultiOptions, MultiServer, DEFAULT_MAX_CLIENTS};
 
-/// Опции для мультиклиентного сервера
+/// Опции для мультиклиентного сервера (нулевые поля -- по умолчанию)
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct shm_multi_options_t {
@@ -167,14 +166,20 @@ pub extern "C" fn shm_multi_server_start(
         unsafe { *callbacks }
     };
 
+    let defaults = MultiOptions::default();
     let opts = if options.is_null() {
-        MultiOptions::default()
+        defaults
     } else {
         let o = unsafe { *options };
         MultiOptions {
-            max_clients: o.max_clients,
-            poll_timeout: Duration::from_millis(o.poll_timeout_ms as u64),
-            recv_batch: o.recv_batch as usize,
+            max_clients: if o.max_clients == 0 {
+                defaults.max_clients
+            } else {
+                o.max_clients
+            },
+            poll_timeout: ffi_millis_or(o.poll_timeout_ms, defaults.poll_timeout),
+            recv_batch: ffi_count_or(o.recv_batch, defaults.recv_batch),
+            ..defaults
         }
     };
 
@@ -394,7 +399,7 @@ pub extern "C" fn shm_multi_server_channel_name(
             if !buffer.is_null() && buffer_size > 0 {
                 let copy_len = std::cmp::min(len, (buffer_size - 1) as usize);
                 unsafe {
-                    std::ptr::copy_nonoverlapping(name_bytes.as_ptr(), buffer as *mut u8, copy_len);
+                    std::ptr::copy_nonoverlapping(name_bytes.as_ptr(), buffer.cast::<u8>(), copy_len);
                     *buffer.add(copy_len) = 0; // null-terminator
                 }
             }
@@ -429,7 +434,7 @@ pub extern "C" fn shm_multi_server_stop(handle: *mut MultiServerHandle) {
 use crate::constants::SLOT_ID_NO_SLOT;
 use crate::multi::{MultiClient, MultiClientHandler, MultiClientOptions};
 
-/// Опции для мультиклиента
+/// Опции для мультиклиента (нулевые поля -- по умолчанию)
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct shm_multi_client_options_t {
@@ -582,15 +587,17 @@ pub extern "C" fn shm_multi_client_connect(
         unsafe { *callbacks }
     };
 
+    let defaults = MultiClientOptions::default();
     let opts = if options.is_null() {
-        MultiClientOptions::default()
+        defaults
     } else {
         let o = unsafe { *options };
         MultiClientOptions {
-            slot_timeout: Duration::from_millis(o.slot_timeout_ms as u64),
-            poll_timeout: Duration::from_millis(o.poll_timeout_ms as u64),
-            recv_batch: o.recv_batch as usize,
-            max_send_queue: o.max_send_queue as usize,
+            slot_timeout: ffi_millis_or(o.slot_timeout_ms, defaults.slot_timeout),
+            poll_timeout: ffi_millis_or(o.poll_timeout_ms, defaults.poll_timeout),
+            recv_batch: ffi_count_or(o.recv_batch, defaults.recv_batch),
+            max_send_queue: ffi_count_or(o.max_send_queue, defaults.max_send_queue),
+            ..defaults
         }
     };
 
@@ -24,26 +24,34 @@
 //! N клиентов подключаются ПОЛНОСТЬЮ КОНКУРЕНТНО: CAS на разной памяти,
 //! без общего состояния, без coalescing событий, без коллизий слотов.
 
+#[cfg(feature = "ffi")]
 mod ffi;
 
+#[cfg(feature = "ffi")]
 pub use ffi::*;
 
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
+use crate::auto::AutoStatsSnapshot;
+use crate::cancel::CancellationToken;
 use crate::client::SharedClient;
 use crate::constants::{
-    CLAIM_FREE, HANDSHAKE_CLIENT_HELLO, HANDSHAKE_SERVER_READY, MAX_MESSAGE_SIZE,
-    RESERVED_CLAIM_INDEX, RESERVED_OWNER_PID_INDEX, SHARED_MAGIC, SHARED_VERSION, SLOT_ID_NO_SLOT,
+    version_compatible, CLAIM_FREE, HANDSHAKE_CLIENT_HELLO, HANDSHAKE_SERVER_READY,
+    MAX_MESSAGE_SIZE, RESERVED_CLAIM_INDEX, RESERVED_OWNER_PID_INDEX, SHARED_MAGIC,
+    SLOT_ID_NO_SLOT,
 };
-use crate::error::{Result, ShmError};
-use crate::naming::mapping_name;
+use crate::error::{ensure_config, Result, ShmError};
+use crate::health::{ChannelHealth, LinkActivity, LinkState, QueueDepths};
+use crate::metrics::{self, ChannelRole, MetricsSource};
+use crate::naming::{mapping_name, Namespace};
+use crate::registry::{self, ServiceKind, ServiceSource};
+use crate::ring::WriteOutcome;
 use crate::server::SharedServer;
 use crate::shared::SharedView;
-use crate::wait_delay;
-use crate::win::{self, Mapping};
+use crate::platform::{self, Mapping, PlatformEvent, PlatformMapping};
 
 /// Максимальное количество клиентов по умолчанию
 pub const DEFAULT_MAX_CLIENTS: u32 = 20;
@@ -119,6 +127,11 @@ pub struct MultiOptions {
     pub poll_timeout: Duration,
     /// Количество сообщений для обработки за один цикл
     pub recv_batch: usize,
+    /// Внешний токен отмены (см. `AutoOptions::cancel`)
+    pub cancel: Option<CancellationToken>,
+    /// Namespace слотов (см. `AutoOptions::namespace`); у клиентов
+    /// (`MultiClientOptions::namespace`) должен совпадать.
+    pub namespace: Namespace,
 }
 
 impl Default for MultiOptions {
@@ -127,10 +140,72 @@ impl Default for MultiOptions {
             max_clients: DEFAULT_MAX_CLIENTS,
             poll_timeout: Duration::from_millis(50),
             recv_batch: 32,
+            cancel: None,
+            namespace: Namespace::Session,
         }
     }
 }
 
+impl MultiOptions {
+    /// Построитель с проверкой значений в [`MultiOptionsBuilder::build`].
+    pub fn builder() -> MultiOptionsBuilder {
+        MultiOptionsBuilder::default()
+    }
+
+    /// Проверяет число клиентов и отбрасывает нулевые таймаут и пачку
+    /// (worker крутился бы вхолостую). Вызывается и в `MultiServer::start`.
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(
+            (1..=MAX_MULTI_CLIENTS).contains(&self.max_clients),
+            "max_clients must be in 1..=31 (NtWaitForMultipleObjects limit)",
+        )?;
+        ensure_config(
+            !self.poll_timeout.is_zero(),
+            "poll_timeout must be non-zero",
+        )?;
+        ensure_config(self.recv_batch > 0, "recv_batch must be non-zero")
+    }
+}
+
+/// Построитель [`MultiOptions`]; незаданные поля -- из `Default`.
+#[derive(Clone, Default)]
+pub struct MultiOptionsBuilder {
+    options: MultiOptions,
+}
+
+impl MultiOptionsBuilder {
+    pub fn max_clients(mut self, max_clients: u32) -> Self {
+        self.options.max_clients = max_clients;
+        self
+    }
+
+    pub fn poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.options.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn recv_batch(mut self, recv_batch: usize) -> Self {
+        self.options.recv_batch = recv_batch;
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.options.cancel = cancel;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    /// Проверяет значения (см. [`MultiOptions::validate`]).
+    pub fn build(self) -> Result<MultiOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
 /// Опции для MultiClient
 #[derive(Clone)]
 pub struct MultiClientOptions {
@@ -144,6 +219,10 @@ pub struct MultiClientOptions {
     /// самого старого (overwrite-семантика, как у `AutoOptions.max_send_queue`).
     /// Без этого предела очередь росла бы неограниченно, если пир завис/тормозит.
     pub max_send_queue: usize,
+    /// Внешний токен отмены (см. `AutoOptions::cancel`)
+    pub cancel: Option<CancellationToken>,
+    /// Namespace слотов сервера (`MultiOptions::namespace`).
+    pub namespace: Namespace,
 }
 
 impl Default for MultiClientOptions {
@@ -153,10 +232,78 @@ impl Default for MultiClientOptions {
             poll_timeout: Duration::from_millis(50),
             recv_batch: 32,
             max_send_queue: 256,
+            cancel: None,
+            namespace: Namespace::Session,
         }
     }
 }
 
+impl MultiClientOptions {
+    /// Построитель с проверкой значений в [`MultiClientOptionsBuilder::build`].
+    pub fn builder() -> MultiClientOptionsBuilder {
+        MultiClientOptionsBuilder::default()
+    }
+
+    /// Отбрасывает нулевые таймауты, пачку и очередь отправки. Вызывается
+    /// и в `MultiClient::connect`.
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(
+            !self.slot_timeout.is_zero(),
+            "slot_timeout must be non-zero",
+        )?;
+        ensure_config(
+            !self.poll_timeout.is_zero(),
+            "poll_timeout must be non-zero",
+        )?;
+        ensure_config(self.recv_batch > 0, "recv_batch must be non-zero")?;
+        ensure_config(self.max_send_queue > 0, "max_send_queue must be non-zero")
+    }
+}
+
+/// Построитель [`MultiClientOptions`]; незаданные поля -- из `Default`.
+#[derive(Clone, Default)]
+pub struct MultiClientOptionsBuilder {
+    options: MultiClientOptions,
+}
+
+impl MultiClientOptionsBuilder {
+    pub fn slot_timeout(mut self, slot_timeout: Duration) -> Self {
+        self.options.slot_timeout = slot_timeout;
+        self
+    }
+
+    pub fn poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.options.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn recv_batch(mut self, recv_batch: usize) -> Self {
+        self.options.recv_batch = recv_batch;
+        self
+    }
+
+    pub fn max_send_queue(mut self, max_send_queue: usize) -> Self {
+        self.options.max_send_queue = max_send_queue;
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.options.cancel = cancel;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    /// Проверяет значения (см. [`MultiClientOptions::validate`]).
+    pub fn build(self) -> Result<MultiClientOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
 /// Состояние одного клиентского слота
 struct ClientSlot {
     id: u32,
@@ -171,6 +318,58 @@ struct ClientSlot {
     ///   (throttle, чтобы не дёргать `NtOpenProcess` на каждой итерации
     ///   worker loop — см. `LIVENESS_CHECK_INTERVAL`).
     claim_seen_at: Option<Instant>,
+    /// Для `health()`: обмен и ошибки слота, подключения к нему.
+    activity: LinkActivity,
+    connects: u64,
+}
+
+/// Счётчики multi-канала для `metrics`: у сервера -- сумма по слотам, у
+/// клиента -- по всем переподключениям. Задержку не измеряют.
+#[derive(Default)]
+struct MultiStats {
+    sent_messages: AtomicU64,
+    sent_bytes: AtomicU64,
+    send_overflows: AtomicU64,
+    send_queue_drops: AtomicU64,
+    received_messages: AtomicU64,
+    received_bytes: AtomicU64,
+    connects: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+impl MultiStats {
+    fn record_sent(&self, len: usize, outcome: &WriteOutcome) {
+        self.sent_messages.fetch_add(1, Ordering::Relaxed);
+        self.sent_bytes.fetch_add(len as u64, Ordering::Relaxed);
+        self.send_overflows
+            .fetch_add(outcome.overwritten as u64, Ordering::Relaxed);
+    }
+
+    fn record_received(&self, len: usize) {
+        self.received_messages.fetch_add(1, Ordering::Relaxed);
+        self.received_bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
+impl MetricsSource for MultiStats {
+    fn snapshot(&self) -> AutoStatsSnapshot {
+        AutoStatsSnapshot {
+            sent_messages: self.sent_messages.load(Ordering::Relaxed),
+            sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
+            send_overflows: self.send_overflows.load(Ordering::Relaxed),
+            send_queue_drops: self.send_queue_drops.load(Ordering::Relaxed),
+            received_messages: self.received_messages.load(Ordering::Relaxed),
+            received_bytes: self.received_bytes.load(Ordering::Relaxed),
+            connects: self.connects.load(Ordering::Relaxed),
+            disconnects: self.disconnects.load(Ordering::Relaxed),
+            ..AutoStatsSnapshot::default()
+        }
+    }
+}
+
+fn weak_source(stats: &Arc<MultiStats>) -> Weak<dyn MetricsSource> {
+    let weak: Weak<MultiStats> = Arc::downgrade(stats);
+    weak
 }
 
 /// Мультиклиентный сервер.
@@ -187,10 +386,12 @@ pub struct MultiServer {
     base_name: String,
     slots: RwLock<Vec<Mutex<ClientSlot>>>,
     max_clients: u32,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
     worker_handle: Mutex<Option<JoinHandle<()>>>,
     handler: Arc<dyn MultiHandler>,
     options: MultiOptions,
+    stats: Arc<MultiStats>,
+    _metrics: metrics::Registration,
 }
 
 impl MultiServer {
@@ -200,13 +401,9 @@ impl MultiServer {
         handler: Arc<dyn MultiHandler>,
         options: MultiOptions,
     ) -> Result<Arc<Self>> {
-        if options.max_clients == 0 || options.max_clients > MAX_MULTI_CLIENTS {
-            return Err(ShmError::InvalidConfig(
-                "max_clients must be in 1..=31 (NtWaitForMultipleObjects limit)",
-            ));
-        }
+        options.validate()?;
 
-        let running = Arc::new(AtomicBool::new(true));
+        let stop = CancellationToken::for_worker(options.cancel.as_ref())?;
 
         // Создаём N независимых сегментов-слотов. Lobby не нужен — клиенты
         // захватывают слоты сами через атомарный claim (см. doc MultiServer).
@@ -215,31 +412,46 @@ impl MultiServer {
             let mut slots_guard = slots.write().unwrap();
             for slot_id in 0..options.max_clients {
                 let channel_name = format!("{}_{}", base_name, slot_id);
-                let server = SharedServer::start(&channel_name)?;
+                let server = SharedServer::builder(&channel_name)
+                    .namespace(options.namespace)
+                    .start()?;
                 slots_guard.push(Mutex::new(ClientSlot {
                     id: slot_id,
                     server,
                     connected: false,
                     claim_seen_at: None,
+                    activity: LinkActivity::default(),
+                    connects: 0,
                 }));
             }
         }
 
+        let stats = Arc::new(MultiStats::default());
+        let metrics = metrics::register(base_name, ChannelRole::Server, weak_source(&stats));
         let server = Arc::new(Self {
             base_name: base_name.to_owned(),
             slots,
             max_clients: options.max_clients,
-            running,
+            stop,
             worker_handle: Mutex::new(None),
             handler,
             options,
+            stats,
+            _metrics: metrics,
         });
+        let weak: Weak<Self> = Arc::downgrade(&server);
+        registry::register(base_name, ServiceKind::Multi, weak);
 
         // Запускаем worker thread
         let server_clone = server.clone();
         let handle = thread::Builder::new()
             .name(format!("xshm-multi-{}", base_name))
-            .spawn(move || server_clone.worker_loop())
+            .spawn(move || {
+                server_clone.stop.run_worker(
+                    || server_clone.worker_loop(),
+                    |err| server_clone.handler.on_error(None, err),
+                )
+            })
             .map_err(|e| ShmError::WindowsError {
                 code: e.raw_os_error().unwrap_or(-1) as u32,
                 context: "spawn multi worker",
@@ -262,7 +474,9 @@ impl MultiServer {
             return Err(ShmError::NotConnected);
         }
 
-        slot.server.send_to_client(data)?;
+        let outcome = slot.server.send_to_client(data)?;
+        self.stats.record_sent(data.len(), &outcome);
+        slot.activity.touch_tx();
         Ok(())
     }
 
@@ -273,7 +487,12 @@ impl MultiServer {
 
         for slot_mutex in slots.iter() {
             let slot = slot_mutex.lock().unwrap();
-            if slot.connected && slot.server.send_to_client(data).is_ok() {
+            if !slot.connected {
+                continue;
+            }
+            if let Ok(outcome) = slot.server.send_to_client(data) {
+                self.stats.record_sent(data.len(), &outcome);
+                slot.activity.touch_tx();
                 sent_count += 1;
             }
         }
@@ -300,6 +519,7 @@ impl MultiServer {
             slot.server.mark_disconnected();
             Self::release_slot_claim(&slot);
             drop(slot);
+            self.stats.disconnects.fetch_add(1, Ordering::Relaxed);
             self.handler.on_client_disconnect(client_id);
         }
 
@@ -331,6 +551,28 @@ impl MultiServer {
             .count() as u32
     }
 
+    /// Состояние слота `client_id` (см. `xshm::health`); `None` -- нет
+    /// такого слота.
+    pub fn health(&self, client_id: u32) -> Option<ChannelHealth> {
+        let slots = self.slots.read().unwrap();
+        let slot = slots.get(client_id as usize)?.lock().unwrap();
+        let state = if self.stop.is_cancelled() {
+            LinkState::Stopped
+        } else if slot.connected {
+            LinkState::Connected
+        } else {
+            LinkState::Waiting
+        };
+        let (rx_ring, tx_ring) = slot.server.ring_depths();
+        let depths = QueueDepths {
+            rx_ring,
+            tx_ring,
+            ..QueueDepths::default()
+        };
+        let reconnects = slot.connects.saturating_sub(1);
+        Some(slot.activity.health(state, depths, reconnects))
+    }
+
     /// Проверка подключения конкретного клиента
     pub fn is_client_connected(&self, client_id: u32) -> bool {
         let slots = self.slots.read().unwrap();
@@ -348,7 +590,7 @@ impl MultiServer {
     /// сразу освободить `user_data`/callback-структуры сразу после возврата.
     /// Идемпотентна: повторный вызов — no-op (`worker_handle` уже `None`).
     pub fn stop(&self) {
-        self.running.store(false, Ordering::Release);
+        self.stop.cancel();
         // `.join()` вызывается потоком-владельцем handle (не worker-потоком —
         // stop() никогда не вызывается изнутри worker_loop), поэтому это не
         // self-join. К моменту, когда Drop for MultiServer возьмёт тот же
@@ -359,6 +601,12 @@ impl MultiServer {
         }
     }
 
+    /// Worker остановлен: `stop()`, отмена токена из опций или паника
+    /// (о ней сообщает `on_error` с `ShmError::WorkerPanicked`).
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
     /// Базовое имя канала
     pub fn base_name(&self) -> &str {
         &self.base_name
@@ -434,7 +682,7 @@ impl MultiServer {
                     let owner_pid = slot.server.view().control_block().reserved
                         [RESERVED_OWNER_PID_INDEX]
                         .load(Ordering::Acquire);
-                    if !win::is_process_alive(owner_pid) {
+                    if !platform::is_process_alive(owner_pid) {
                         // Подтверждено: процесс-владелец завершился, claim
                         // (ещё) не FREE -- ожидаем именно текущее значение.
                         orphaned.push((slot.id, claim));
@@ -464,7 +712,7 @@ impl MultiServer {
     fn worker_loop(&self) {
         let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
 
-        while self.running.load(Ordering::Acquire) {
+        while !self.stop.is_cancelled() {
             // Освобождаем «зависшие» захваты и осиротевшие слоты в начале каждой
             // итерации. Отключение осиротевших — вне блокировок (handler без lock-а).
             for (slot_id, expected_claim) in self.reclaim_stale_claims() {
@@ -501,9 +749,11 @@ impl MultiServer {
                     }
                 }
             }
+            // Последним -- токен остановки: его индекс вне handle_to_event.
+            wait_handles.push(self.stop.raw_handle());
 
             // Ожидаем любое событие
-            match win::wait_any(&wait_handles, Some(self.options.poll_timeout)) {
+            match platform::wait_any(&wait_handles, Some(self.options.poll_timeout)) {
                 Ok(Some(index)) => {
                     if index < handle_to_event.len() {
                         self.handle_event(&handle_to_event[index], &mut buffer);
@@ -545,6 +795,8 @@ impl MultiServer {
                 Ok(()) => {
                     slot.connected = true;
                     slot.claim_seen_at = None;
+                    slot.connects += 1;
+                    self.stats.connects.fetch_add(1, Ordering::Relaxed);
                     let id = slot.id;
                     drop(slot);
                     drop(slots);
@@ -635,6 +887,7 @@ impl MultiServer {
         };
 
         if was_connected {
+            self.stats.disconnects.fetch_add(1, Ordering::Relaxed);
             self.handler.on_client_disconnect(slot_id);
         }
     }
@@ -684,6 +937,7 @@ impl MultiServer {
         };
 
         if was_connected {
+            self.stats.disconnects.fetch_add(1, Ordering::Relaxed);
             self.handler.on_client_disconnect(slot_id);
         }
     }
@@ -708,22 +962,38 @@ impl MultiServer {
                             messages.push(buffer[..len].to_vec());
                         }
                         Err(ShmError::QueueEmpty) => break,
+                        // Битое сообщение уже изъято из кольца -- читаем дальше.
+                        Err(err @ ShmError::ChecksumMismatch) => {
+                            slot.activity.record_error(&err);
+                            self.handler.on_error(Some(slot_id), err);
+                        }
                         Err(err) => {
+                            slot.activity.record_error(&err);
                             error = Some(err);
                             break;
                         }
                     }
                 }
+                if !messages.is_empty() {
+                    slot.activity.touch_rx();
+                }
             }
         }
 
         // Отдаём handler-у без lock-а
         for data in &messages {
+            self.stats.record_received(data.len());
             self.handler.on_message(slot_id, data);
         }
 
         if let Some(err) = error {
+            // NotConnected сообщит событие disconnect; испорченному слоту
+            // доверять нельзя -- отключаем клиента сразу.
+            let reset = err.is_disconnect() && err != ShmError::NotConnected;
             self.handler.on_error(Some(slot_id), err);
+            if reset {
+                self.handle_slot_disconnect(slot_id);
+            }
         }
     }
 
@@ -750,9 +1020,15 @@ impl MultiServer {
     }
 }
 
+impl ServiceSource for MultiServer {
+    fn is_running(&self) -> bool {
+        !self.stop.is_cancelled()
+    }
+}
+
 impl Drop for MultiServer {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::Release);
+        self.stop.cancel();
         if let Some(handle) = self.worker_handle.lock().unwrap().take() {
             let _ = handle.join();
         }
@@ -784,8 +1060,9 @@ enum ClientCommand {
 pub struct MultiClient {
     cmd_tx: Sender<ClientCommand>,
     join: Mutex<Option<JoinHandle<()>>>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
     slot_id: Arc<AtomicU32>,
+    _metrics: metrics::Registration,
 }
 
 impl MultiClient {
@@ -800,18 +1077,29 @@ impl MultiClient {
         handler: Arc<dyn MultiClientHandler>,
         options: MultiClientOptions,
     ) -> Result<Self> {
+        options.validate()?;
         let (tx, rx) = mpsc::channel();
-        let running = Arc::new(AtomicBool::new(true));
+        let stop = CancellationToken::for_worker(options.cancel.as_ref())?;
         let slot_id = Arc::new(AtomicU32::new(SLOT_ID_NO_SLOT));
 
-        let running_clone = running.clone();
+        let stats = Arc::new(MultiStats::default());
+        let metrics = metrics::register(base_name, ChannelRole::Client, weak_source(&stats));
+
+        let stop_clone = stop.clone();
         let slot_id_clone = slot_id.clone();
         let name = base_name.to_owned();
 
         let handle = thread::Builder::new()
             .name(format!("xshm-multi-client-{}", base_name))
             .spawn(move || {
-                client_worker(&name, handler, options, rx, running_clone, slot_id_clone);
+                let guard = stop_clone.clone();
+                let report = handler.clone();
+                guard.run_worker(
+                    || {
+                        client_worker(&name, handler, options, rx, stop_clone, slot_id_clone, &stats)
+                    },
+                    |err| report.on_error(err),
+                );
             })
             .map_err(|e| ShmError::WindowsError {
                 code: e.raw_os_error().unwrap_or(-1) as u32,
@@ -821,14 +1109,15 @@ impl MultiClient {
         Ok(Self {
             cmd_tx: tx,
             join: Mutex::new(Some(handle)),
-            running,
+            stop,
             slot_id,
+            _metrics: metrics,
         })
     }
 
     /// Отправка сообщения серверу
     pub fn send(&self, data: &[u8]) -> Result<()> {
-        if !self.running.load(Ordering::Acquire) {
+        if self.stop.is_cancelled() {
             return Err(ShmError::NotReady);
         }
         self.cmd_tx
@@ -846,15 +1135,22 @@ impl MultiClient {
         self.slot_id.load(Ordering::Acquire) != SLOT_ID_NO_SLOT
     }
 
-    /// Остановка клиента
+    /// Остановка клиента (прерывает и паузу между попытками захвата слота)
     pub fn stop(&self) {
         let _ = self.cmd_tx.send(ClientCommand::Shutdown);
+        self.stop.cancel();
+    }
+
+    /// Worker остановлен: `stop()`, отмена токена из опций или паника
+    /// (о ней сообщает `on_error` с `ShmError::WorkerPanicked`).
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
     }
 }
 
 impl Drop for MultiClient {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::Release);
+        self.stop.cancel();
         let _ = self.cmd_tx.send(ClientCommand::Shutdown);
         if let Some(handle) = self.join.lock().unwrap().take() {
             let _ = handle.join();
@@ -891,20 +1187,21 @@ fn client_worker(
     handler: Arc<dyn MultiClientHandler>,
     options: MultiClientOptions,
     cmd_rx: Receiver<ClientCommand>,
-    running: Arc<AtomicBool>,
+    stop: CancellationToken,
     slot_id_out: Arc<AtomicU32>,
+    stats: &MultiStats,
 ) {
     let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
 
     let effective_slot_timeout = clamp_slot_timeout(options.slot_timeout);
 
-    while running.load(Ordering::Acquire) {
+    while !stop.is_cancelled() {
         // Шаг 1: атомарно захватываем свободный слот (без централизованного lobby).
-        let (slot_id, slot_name, token) = match claim_free_slot(base_name) {
+        let (slot_id, slot_name, token) = match claim_free_slot(base_name, options.namespace) {
             Ok(v) => v,
             Err(err) => {
                 handler.on_error(err);
-                if !wait_delay(&running, options.poll_timeout) {
+                if !stop.sleep(options.poll_timeout) {
                     break;
                 }
                 continue;
@@ -912,14 +1209,14 @@ fn client_worker(
         };
 
         // Шаг 2: подключаемся к захваченному слоту обычным handshake.
-        let client = match SharedClient::connect(&slot_name, effective_slot_timeout) {
+        let client = match SharedClient::connect_in(&slot_name, options.namespace, effective_slot_timeout) {
             Ok(c) => c,
             Err(err) => {
                 // Не подключились — освобождаем захваченный слот (best-effort;
                 // иначе сервер вернёт его в оборот по RESERVE_TIMEOUT).
-                release_claim(&slot_name, token);
+                release_claim(&slot_name, options.namespace, token);
                 handler.on_error(err);
-                if !wait_delay(&running, options.poll_timeout) {
+                if !stop.sleep(options.poll_timeout) {
                     break;
                 }
                 continue;
@@ -927,20 +1224,22 @@ fn client_worker(
         };
 
         slot_id_out.store(slot_id, Ordering::Release);
+        stats.connects.fetch_add(1, Ordering::Relaxed);
         handler.on_connect(slot_id);
 
         // Шаг 3: Работаем с данными
-        // SharedClient всегда использует named events (не anonymous)
-        let client_events = client.events();
+        // Клиент, подключённый по имени, всегда с named events
+        let client_events = client.events().expect("named client always has events");
         let handles = [
             client_events.disconnect.raw_handle(),
             client_events.s2c.data.raw_handle(),
+            stop.raw_handle(),
         ];
 
         let mut send_queue: VecDeque<Vec<u8>> = VecDeque::new();
 
         loop {
-            if !running.load(Ordering::Acquire) {
+            if stop.is_cancelled() {
                 break;
             }
 
@@ -949,11 +1248,12 @@ fn client_worker(
                 match cmd {
                     ClientCommand::Send(data) => {
                         if push_with_cap(&mut send_queue, data, options.max_send_queue) {
+                            stats.send_queue_drops.fetch_add(1, Ordering::Relaxed);
                             handler.on_overflow(1);
                         }
                     }
                     ClientCommand::Shutdown => {
-                        running.store(false, Ordering::Release);
+                        stop.cancel();
                     }
                 }
             }
@@ -961,34 +1261,53 @@ fn client_worker(
             // Отправляем данные
             while let Some(data) = send_queue.front() {
                 match client.send_to_server(data) {
-                    Ok(_) => {
+                    Ok(outcome) => {
+                        stats.record_sent(data.len(), &outcome);
                         send_queue.pop_front();
                     }
                     Err(ShmError::QueueFull) => break,
-                    Err(err) => {
+                    Err(err) if err.is_retryable() || err.is_disconnect() => {
                         handler.on_error(err);
                         break;
                     }
+                    Err(err) => {
+                        // Повтор этого сообщения не поможет -- отбрасываем.
+                        send_queue.pop_front();
+                        handler.on_error(err);
+                    }
                 }
             }
 
             // Получаем данные
+            let mut reset = false;
             loop {
                 match client.receive_from_server(&mut buffer) {
-                    Ok(len) => handler.on_message(&buffer[..len]),
+                    Ok(len) => {
+                        stats.record_received(len);
+                        handler.on_message(&buffer[..len]);
+                    }
                     Err(ShmError::QueueEmpty) => break,
+                    Err(err @ ShmError::ChecksumMismatch) => handler.on_error(err),
                     Err(err) => {
+                        reset = err.is_disconnect() && err != ShmError::NotConnected;
                         handler.on_error(err);
                         break;
                     }
                 }
             }
+            if reset {
+                slot_id_out.store(SLOT_ID_NO_SLOT, Ordering::Release);
+                stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                handler.on_disconnect();
+                break;
+            }
 
             // Ожидаем события
-            match win::wait_any(&handles, Some(options.poll_timeout)) {
+            match platform::wait_any(&handles, Some(options.poll_timeout)) {
                 Ok(Some(0)) => {
                     // Disconnect
                     slot_id_out.store(SLOT_ID_NO_SLOT, Ordering::Release);
+                    stats.disconnects.fetch_add(1, Ordering::Relaxed);
                     handler.on_disconnect();
                     break;
                 }
@@ -999,6 +1318,7 @@ fn client_worker(
                 Err(err) => {
                     handler.on_error(err);
                     slot_id_out.store(SLOT_ID_NO_SLOT, Ordering::Release);
+                    stats.disconnects.fetch_add(1, Ordering::Relaxed);
                     handler.on_disconnect();
                     break;
                 }
@@ -1009,9 +1329,9 @@ fn client_worker(
         // вернулся в оборот. CAS token->FREE сработает, только если claim ещё наш
         // (если сервер уже отнял слот по таймауту/force-disconnect — это no-op).
         drop(client);
-        release_claim(&slot_name, token);
+        release_claim(&slot_name, options.namespace, token);
 
-        if !wait_delay(&running, options.poll_timeout) {
+        if !stop.sleep(options.poll_timeout) {
             break;
         }
     }
@@ -1053,14 +1373,17 @@ fn next_claim_token() -> u32 {
 /// Пытается атомарно захватить конкретный слот через `compare_exchange`.
 /// `Ok(true)` — захвачено нами; `Ok(false)` — слот занят/невалиден;
 /// `Err(_)` — слота с таким именем не существует (сегмент не открылся).
-fn try_claim_slot(slot_name: &str, token: u32) -> Result<bool> {
+fn try_claim_slot(slot_name: &str, namespace: Namespace, token: u32) -> Result<bool> {
     // Сервер создаёт секцию под именем mapping_name(slot_name) — открываем так же.
-    let mapping = Mapping::open(&mapping_name(slot_name))?; // Err => слота нет
+    let mapping = Mapping::open(&mapping_name(&namespace.qualify(slot_name)))?; // Err => слота нет
     let view = unsafe { SharedView::new(mapping.as_ptr()) };
     let control = view.control_block();
-    if control.magic != SHARED_MAGIC || control.version != SHARED_VERSION {
+    if control.magic != SHARED_MAGIC || !version_compatible(control.version) {
         return Ok(false); // чужой/повреждённый сегмент — пропускаем
     }
+    if view.check_layout().is_err() {
+        return Ok(false); // сервер собран с другим профилем колец
+    }
     let claimed = control.reserved[RESERVED_CLAIM_INDEX]
         .compare_exchange(CLAIM_FREE, token, Ordering::AcqRel, Ordering::Acquire)
         .is_ok();
@@ -1077,8 +1400,8 @@ fn try_claim_slot(slot_name: &str, token: u32) -> Result<bool> {
 }
 
 /// Снять собственный claim со слота (CAS token -> FREE), если он всё ещё наш.
-fn release_claim(slot_name: &str, token: u32) {
-    if let Ok(mapping) = Mapping::open(&mapping_name(slot_name)) {
+fn release_claim(slot_name: &str, namespace: Namespace, token: u32) {
+    if let Ok(mapping) = Mapping::open(&mapping_name(&namespace.qualify(slot_name))) {
         let view = unsafe { SharedView::new(mapping.as_ptr()) };
         let _ = view.control_block().reserved[RESERVED_CLAIM_INDEX].compare_exchange(
             token,
@@ -1091,12 +1414,12 @@ fn release_claim(slot_name: &str, token: u32) {
 
 /// Пробегает слоты `base_name_0..` и атомарно захватывает первый свободный.
 /// Конкурентные клиенты захватывают РАЗНЫЕ слоты (CAS на разной памяти).
-fn claim_free_slot(base_name: &str) -> Result<(u32, String, u32)> {
+fn claim_free_slot(base_name: &str, namespace: Namespace) -> Result<(u32, String, u32)> {
     let token = next_claim_token();
     let mut saw_slot = false;
     for slot_id in 0..MAX_MULTI_CLIENTS {
         let slot_name = format!("{}_{}", base_name, slot_id);
-        match try_claim_slot(&slot_name, token) {
+        match try_claim_slot(&slot_name, namespace, token) {
             Ok(true) => return Ok((slot_id, slot_name, token)),
             Ok(false) => {
                 saw_slot = true;
@@ -1215,6 +1538,7 @@ mod tests {
         assert!(server.is_ok());
         let server = server.unwrap();
         assert_eq!(server.client_count(), 0);
+        server.stop();
     }
 
     /// `stop()` обязан синхронно дождаться выхода worker-потока: после
@@ -1263,10 +1587,7 @@ mod tests {
         }
 
         // Реальный завершившийся процесс -- гарантированно мёртвый PID.
-        let mut child = std::process::Command::new("cmd")
-            .args(["/C", "exit", "0"])
-            .spawn()
-            .expect("spawn short-lived child process");
+        let mut child = platform::spawn_exiting_child();
         let dead_pid = child.id();
         child.wait().expect("wait for child exit");
 
@@ -1282,7 +1603,7 @@ mod tests {
         }
 
         // is_process_alive может не сразу увидеть завершение (см. запас в
-        // win::tests::exited_process_is_detected_as_dead) -- ретраим.
+        // platform::tests::exited_process_is_detected_as_dead) -- ретраим.
         let mut orphaned = Vec::new();
         for _ in 0..50 {
             orphaned = server.reclaim_stale_claims();
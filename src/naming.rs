@@ -1,5 +1,125 @@
+//! Имена каналов.
+//!
+//! Библиотека строит из базового имени канала имена объектов ОС -- секции
+//! (`Local\{name}`), событий (`Local\{name}_C2S_DATA` и т.п.), слотов
+//! multi (`{name}_{slot}`). [`validate`] проверяет базовое имя по тем же
+//! правилам, что `SharedServer::start`/`SharedClient::connect`, так что
+//! имя из конфигурации можно отвергнуть при загрузке, а не глубоко внутри
+//! создания секции. [`normalize`] вдобавок снимает префикс `Local\`.
+//! Namespace объектов задаёт не имя, а [`Namespace`] в опциях сервера и
+//! клиента.
+//!
+//! ```
+//! use xshm::naming;
+//!
+//! assert_eq!(naming::normalize("Local\\billing")?, "billing");
+//! assert!(naming::validate("billing/eu").is_err());
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use crate::error::{Result, ShmError};
+
+/// Максимальная длина базового имени в байтах. Запас до POSIX `NAME_MAX`
+/// (255) -- под суффиксы событий (`_C2S_CONNECT_REQ`) и слотов multi.
+pub const MAX_NAME_LEN: usize = 200;
+
+/// Проверяет базовое имя канала: непустое, не длиннее [`MAX_NAME_LEN`],
+/// без префикса namespace'а, разделителей пути и управляющих символов.
+pub fn validate(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(ShmError::InvalidName("channel name is empty"));
+    }
+    if name.starts_with("Global\\") {
+        return Err(ShmError::InvalidName(
+            "Global\\ prefix is not part of the name; choose naming::Namespace::Global instead",
+        ));
+    }
+    if name.starts_with("Local\\") {
+        return Err(ShmError::InvalidName(
+            "Local\\ prefix is added by the library; pass the bare name (see naming::normalize)",
+        ));
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(ShmError::InvalidName(
+            "channel name is longer than naming::MAX_NAME_LEN bytes",
+        ));
+    }
+    if name.contains(['\\', '/']) {
+        return Err(ShmError::InvalidName(
+            "channel name contains a path separator ('\\' or '/')",
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(ShmError::InvalidName(
+            "channel name contains a control character",
+        ));
+    }
+    Ok(())
+}
+
+/// Снимает префикс `Local\` (его добавляет сама библиотека) и проверяет
+/// результат через [`validate`].
+pub fn normalize(name: &str) -> Result<String> {
+    let bare = name.strip_prefix("Local\\").unwrap_or(name);
+    validate(bare)?;
+    Ok(bare.to_owned())
+}
+
+/// Префикс имён объектов in-memory backend'а ([`Namespace::Mock`]).
+#[cfg(feature = "mock")]
+pub(crate) const MOCK_PREFIX: &str = "Mock\\";
+
+/// Namespace объектов канала на Windows.
+///
+/// По умолчанию объекты session-local (`Local\`): сервис в сессии 0 и
+/// процесс пользователя в интерактивной сессии друг друга не видят. С
+/// `Global` обе стороны работают в `\BaseNamedObjects`; создание из
+/// сессии != 0 требует SeCreateGlobalPrivilege (библиотека включает её,
+/// если она выдана; иначе `ShmError::PrivilegeRequired`). Стороны канала
+/// обязаны выбрать один namespace. На Unix namespace'ов нет -- значение
+/// ни на что не влияет (кроме `Mock`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    #[default]
+    Session,
+    Global,
+    /// In-memory объекты этого процесса (feature `mock`) на любой ОС:
+    /// kernel-объекты не создаются, другие процессы канал не видят. Для
+    /// unit-тестов handler'ов; настоящие каналы в той же сборке работают
+    /// как обычно.
+    #[cfg(feature = "mock")]
+    Mock,
+}
+
+impl Namespace {
+    /// Базовое имя канала в этом namespace'е: для `Global` и `Mock` -- с
+    /// префиксом, который сохраняют все имена объектов (см. `qualified`).
+    pub(crate) fn qualify(self, name: &str) -> String {
+        match self {
+            Namespace::Session => name.to_owned(),
+            Namespace::Global => format!("Global\\{name}"),
+            #[cfg(feature = "mock")]
+            Namespace::Mock => format!("{MOCK_PREFIX}{name}"),
+        }
+    }
+}
+
+/// Имя объекта ОС для базового имени: `Global\`- и `Mock\`-имя (см.
+/// [`Namespace::qualify`]) остаётся как есть, остальные уходят в `Local\`.
+fn qualified(base: &str) -> String {
+    #[cfg(feature = "mock")]
+    if base.starts_with(MOCK_PREFIX) {
+        return base.to_owned();
+    }
+    if base.starts_with("Global\\") {
+        base.to_owned()
+    } else {
+        format!("Local\\{base}")
+    }
+}
+
 #[derive(Clone, Copy)]
-pub enum Direction {
+pub(crate) enum Direction {
     ServerToClient,
     ClientToServer,
 }
@@ -17,13 +137,89 @@ impl Direction {
 // пространство имён полностью контролирует встраивающий код. Вызывающая сторона
 // должна сама гарантировать, что base не конфликтует с другими объектами Local\ в сессии.
 fn event_prefix(base: &str) -> String {
-    format!("Local\\{base}_")
+    format!("{}_", qualified(base))
+}
+
+pub(crate) fn mapping_name(base: &str) -> String {
+    qualified(base)
+}
+
+/// Секция arena (`xshm::arena`), отдельная от секции канала.
+pub(crate) fn arena_name(base: &str) -> String {
+    format!("{}_ARENA", qualified(base))
+}
+
+/// Блок состояния канала (`xshm::state`).
+pub(crate) fn state_name(base: &str) -> String {
+    format!("{}_STATE", qualified(base))
+}
+
+/// Секция mailbox (`xshm::mailbox`).
+pub(crate) fn mailbox_name(base: &str) -> String {
+    format!("{}_MBOX", qualified(base))
+}
+
+/// Секция broadcast-шины (`xshm::broadcast`).
+pub(crate) fn broadcast_name(base: &str) -> String {
+    format!("{}_BCAST", qualified(base))
 }
 
-pub fn mapping_name(base: &str) -> String {
-    format!("Local\\{base}")
+/// Секция примитива синхронизации (`xshm::sync`).
+pub(crate) fn sync_name(base: &str) -> String {
+    format!("{}_SYNC", qualified(base))
 }
 
-pub fn event_name(base: &str, direction: Direction, suffix: &str) -> String {
+/// Событие пробуждения ждущих примитива синхронизации.
+pub(crate) fn sync_event_name(base: &str) -> String {
+    format!("{}_SYNC_EVT", qualified(base))
+}
+
+pub(crate) fn event_name(base: &str, direction: Direction, suffix: &str) -> String {
     format!("{}{}_{}", event_prefix(base), direction.as_str(), suffix)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_checked_like_the_library_does() {
+        assert_eq!(validate("billing_eu-1"), Ok(()));
+        assert_eq!(validate(&"a".repeat(MAX_NAME_LEN)), Ok(()));
+        for bad in [
+            "",
+            "Global\\billing",
+            "Local\\billing",
+            "billing\\eu",
+            "billing/eu",
+            "bill\0ing",
+            "bill\ning",
+        ] {
+            assert!(
+                matches!(validate(bad), Err(ShmError::InvalidName(_))),
+                "{bad:?}"
+            );
+        }
+        assert!(validate(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+
+        assert_eq!(normalize("Local\\billing").as_deref(), Ok("billing"));
+        assert_eq!(normalize("billing").as_deref(), Ok("billing"));
+        assert!(normalize("Local\\").is_err());
+        assert!(normalize("Global\\billing").is_err());
+    }
+
+    #[test]
+    fn global_namespace_prefixes_every_object() {
+        let base = Namespace::Global.qualify("billing");
+        assert_eq!(mapping_name(&base), "Global\\billing");
+        assert_eq!(state_name(&base), "Global\\billing_STATE");
+        assert_eq!(
+            event_name(&base, Direction::ClientToServer, "DATA"),
+            "Global\\billing_C2S_DATA"
+        );
+
+        let base = Namespace::Session.qualify("billing");
+        assert_eq!(mapping_name(&base), "Local\\billing");
+        assert_eq!(sync_event_name(&base), "Local\\billing_SYNC_EVT");
+    }
+}
@@ -37,6 +37,20 @@ extern "system" {
         ClientId: *mut CLIENT_ID,
     ) -> NTSTATUS;
 
+    /// Дублирование handle между процессами.
+    ///
+    /// SourceProcessHandle: NT_CURRENT_PROCESS для своего handle;
+    /// TargetProcessHandle требует PROCESS_DUP_HANDLE.
+    pub fn NtDuplicateObject(
+        SourceProcessHandle: HANDLE,
+        SourceHandle: HANDLE,
+        TargetProcessHandle: HANDLE,
+        TargetHandle: *mut HANDLE,
+        DesiredAccess: ACCESS_MASK,
+        HandleAttributes: ULONG,
+        Options: ULONG,
+    ) -> NTSTATUS;
+
     // ========================================================================
     // Event operations
     // ========================================================================
@@ -89,6 +103,12 @@ extern "system" {
         Timeout: *const i64,
     ) -> NTSTATUS;
 
+    /// Счётчик производительности (QPC) и его частота (тиков в секунду).
+    pub fn NtQueryPerformanceCounter(
+        PerformanceCounter: *mut i64,
+        PerformanceFrequency: *mut i64,
+    ) -> NTSTATUS;
+
     // ========================================================================
     // Section operations (Shared Memory)
     // ========================================================================
@@ -131,6 +151,30 @@ extern "system" {
     /// Размаппинг секции
     pub fn NtUnmapViewOfSection(ProcessHandle: HANDLE, BaseAddress: PVOID) -> NTSTATUS;
 
+    // ========================================================================
+    // Object directory operations
+    // ========================================================================
+
+    /// Открытие каталога объектов (`\BaseNamedObjects` и т.п.)
+    pub fn NtOpenDirectoryObject(
+        DirectoryHandle: *mut HANDLE,
+        DesiredAccess: ACCESS_MASK,
+        ObjectAttributes: *mut OBJECT_ATTRIBUTES,
+    ) -> NTSTATUS;
+
+    /// Перечисление каталога: в `Buffer` -- массив
+    /// OBJECT_DIRECTORY_INFORMATION (одна запись при ReturnSingleEntry),
+    /// позиция перечисления -- в `Context`.
+    pub fn NtQueryDirectoryObject(
+        DirectoryHandle: HANDLE,
+        Buffer: PVOID,
+        Length: ULONG,
+        ReturnSingleEntry: BOOLEAN,
+        RestartScan: BOOLEAN,
+        Context: *mut ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+
     // ========================================================================
     // Security Descriptor helpers (Rtl* functions)
     // ========================================================================
@@ -148,6 +192,15 @@ extern "system" {
         Dacl: PVOID,
         DaclDefaulted: BOOLEAN,
     ) -> NTSTATUS;
+
+    /// Включение/выключение привилегии в токене процесса (или потока при
+    /// CurrentThread = TRUE); прежнее состояние -- в `Enabled`.
+    pub fn RtlAdjustPrivilege(
+        Privilege: ULONG,
+        Enable: BOOLEAN,
+        CurrentThread: BOOLEAN,
+        Enabled: *mut BOOLEAN,
+    ) -> NTSTATUS;
 }
 
 // ============================================================================
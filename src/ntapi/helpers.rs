@@ -47,6 +47,11 @@ fn session_local_path(name: &str) -> String {
     }
 }
 
+/// NT путь каталога session-local объектов (`Local\`) текущего процесса.
+pub fn session_local_directory() -> String {
+    session_local_path("").trim_end_matches('\\').to_owned()
+}
+
 /// Преобразование Win32 имени в NT путь.
 ///
 /// - `"Global\\X"`  -> `\BaseNamedObjects\X` (глобальный namespace; требует
@@ -113,7 +118,7 @@ impl NtName {
         let units = wide.len() - 1; // без null-терминатора
 
         if units > MAX_NT_NAME_UNITS {
-            return Err(ShmError::InvalidConfig(
+            return Err(ShmError::InvalidName(
                 "object name too long for UNICODE_STRING (max ~32766 UTF-16 units)",
             ));
         }
@@ -243,8 +248,8 @@ mod tests {
     fn nt_name_rejects_pathologically_long_name() {
         let huge_name = "A".repeat(MAX_NT_NAME_UNITS + 100);
         match NtName::new(&huge_name) {
-            Err(ShmError::InvalidConfig(_)) => {}
-            _ => panic!("must reject overlong name with InvalidConfig"),
+            Err(ShmError::InvalidName(_)) => {}
+            _ => panic!("must reject overlong name with InvalidName"),
         }
     }
 
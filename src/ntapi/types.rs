@@ -195,6 +195,14 @@ pub struct CLIENT_ID {
 pub const PROCESS_QUERY_LIMITED_INFORMATION: ACCESS_MASK = 0x1000;
 /// Право на ожидание сигнального состояния процесса (завершение).
 pub const PROCESS_SYNCHRONIZE: ACCESS_MASK = 0x0010_0000;
+/// Право дублировать handle'ы в процесс (NtDuplicateObject, target).
+pub const PROCESS_DUP_HANDLE: ACCESS_MASK = 0x0040;
+
+/// Опции NtDuplicateObject: закрыть исходный handle; права и атрибуты
+/// как у исходного handle.
+pub const DUPLICATE_CLOSE_SOURCE: ULONG = 0x0000_0001;
+pub const DUPLICATE_SAME_ACCESS: ULONG = 0x0000_0002;
+pub const DUPLICATE_SAME_ATTRIBUTES: ULONG = 0x0000_0004;
 
 // ============================================================================
 // Константы NTSTATUS
@@ -202,7 +210,14 @@ pub const PROCESS_SYNCHRONIZE: ACCESS_MASK = 0x0010_0000;
 
 pub const STATUS_SUCCESS: NTSTATUS = 0;
 pub const STATUS_TIMEOUT: NTSTATUS = 0x00000102;
+/// В буфере часть записей каталога, есть ещё (NtQueryDirectoryObject).
+pub const STATUS_MORE_ENTRIES: NTSTATUS = 0x00000105;
 pub const STATUS_WAIT_0: NTSTATUS = 0;
+pub const STATUS_SECTION_TOO_BIG: NTSTATUS = 0xC0000040u32 as i32;
+pub const STATUS_ACCESS_DENIED: NTSTATUS = 0xC0000022u32 as i32;
+pub const STATUS_PRIVILEGE_NOT_HELD: NTSTATUS = 0xC0000061u32 as i32;
+pub const STATUS_OBJECT_NAME_NOT_FOUND: NTSTATUS = 0xC0000034u32 as i32;
+pub const STATUS_OBJECT_PATH_NOT_FOUND: NTSTATUS = 0xC000003Au32 as i32;
 
 // ============================================================================
 // Константы OBJECT_ATTRIBUTES
@@ -217,10 +232,36 @@ pub const OBJ_CASE_INSENSITIVE: ULONG = 0x00000040;
 pub const SECTION_ALL_ACCESS: ACCESS_MASK = 0x000F001F;
 pub const PAGE_READWRITE: ULONG = 0x04;
 pub const SEC_COMMIT: ULONG = 0x08000000;
+/// Секция на больших страницах: только вместе с SEC_COMMIT, размер кратен
+/// `LARGE_PAGE_SIZE`, нужна SeLockMemoryPrivilege.
+pub const SEC_LARGE_PAGES: ULONG = 0x80000000;
+/// AllocationType для NtMapViewOfSection: view на больших страницах.
+pub const MEM_LARGE_PAGES: ULONG = 0x20000000;
+/// Минимальный размер большой страницы на x64 и ARM64 (`GetLargePageMinimum`).
+pub const LARGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+/// SE_LOCK_MEMORY_PRIVILEGE для RtlAdjustPrivilege.
+pub const SE_LOCK_MEMORY_PRIVILEGE: ULONG = 4;
+/// SE_CREATE_GLOBAL_PRIVILEGE: объекты в `\BaseNamedObjects` из сессии != 0.
+pub const SE_CREATE_GLOBAL_PRIVILEGE: ULONG = 30;
 
 /// ViewUnmap - секция будет размаппена при закрытии handle
 pub const VIEW_UNMAP: ULONG = 2;
 
+// ============================================================================
+// Каталоги объектов
+// ============================================================================
+
+/// DIRECTORY_QUERY - перечисление каталога объектов
+pub const DIRECTORY_QUERY: ACCESS_MASK = 0x0001;
+
+/// Запись NtQueryDirectoryObject: имя объекта и имя его типа
+/// (`"Section"`, `"Event"`, ...). Строки лежат в том же буфере.
+#[repr(C)]
+pub struct OBJECT_DIRECTORY_INFORMATION {
+    pub Name: UNICODE_STRING,
+    pub TypeName: UNICODE_STRING,
+}
+
 // ============================================================================
 // Константы для Event
 // ============================================================================
@@ -0,0 +1,322 @@
+//! Профиль C API для P/Invoke (.NET).
+//!
+//! Всё здесь -- надстройка над `crate::ffi`, `crate::multi::ffi` и
+//! `crate::dispatch::ffi`, отдельного состояния нет:
+//!
+//! - `*_w`-варианты функций, принимающих имя, берут NUL-terminated UTF-16
+//!   (`[MarshalAs(UnmanagedType.LPWStr)] string` в C# -- без перекодировки
+//!   и ручного pinning'а). Невалидный UTF-16 (непарные суррогаты) -- NULL.
+//! - [`shm_abi_layout`] отдаёт размеры всех структур C API: C#-сторона
+//!   сверяет их с `Marshal.SizeOf<T>()` при загрузке, а не ловит порчу
+//!   памяти при первом вызове. Все структуры -- `LayoutKind.Sequential`,
+//!   `bool` -- 1 байт (`MarshalAs(UnmanagedType.U1)`), callbacks -- `cdecl`.
+//!
+//! Модель регистрации callbacks: в структурах callbacks лежат голые
+//! указатели на функции, а `user_data` -- `GCHandle.ToIntPtr` (не
+//! pinned-handle: объект может двигаться, handle -- нет). Делегаты и
+//! `GCHandle` должны жить, пока возможен вызов, то есть до возврата из
+//! `shm_*_stop`/`shm_*_disconnect`: остановка синхронна, после неё
+//! callbacks больше не вызываются. Сгенерированный `include/xshm.cs`
+//! (feature `csharp`) содержит `CallbackRegistration`, которая держит
+//! и то, и другое.
+
+use std::ffi::CString;
+use std::mem::size_of;
+use std::os::raw::c_char;
+
+use crate::dispatch::ffi::{
+    shm_dispatch_callbacks_t, shm_dispatch_client_callbacks_t, shm_dispatch_client_connect,
+    shm_dispatch_client_options_t, shm_dispatch_options_t, shm_dispatch_registration_t,
+    shm_dispatch_server_start, DispatchClientHandle, DispatchServerHandle,
+};
+use crate::ffi::{
+    shm_auto_options_t, shm_auto_stats_t, shm_callbacks_t, shm_client_connect,
+    shm_client_connect_auto, shm_endpoint_config_t, shm_server_start, shm_server_start_auto,
+    AutoClientHandle, AutoServerHandle, ClientHandle, ServerHandle,
+};
+use crate::multi::{
+    shm_multi_callbacks_t, shm_multi_client_callbacks_t, shm_multi_client_connect,
+    shm_multi_client_options_t, shm_multi_options_t, shm_multi_server_start, MultiClientHandle,
+    MultiServerHandle,
+};
+
+/// Версия раскладки структур C API; растёт при любом её изменении.
+pub const SHM_ABI_VERSION: u32 = 1;
+
+/// Размеры структур C API в байтах (для сверки с `Marshal.SizeOf`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct shm_abi_layout_t {
+    pub abi_version: u32,
+    pub pointer_size: u32,
+    pub endpoint_config_size: u32,
+    pub callbacks_size: u32,
+    pub auto_options_size: u32,
+    pub auto_stats_size: u32,
+    pub multi_options_size: u32,
+    pub multi_callbacks_size: u32,
+    pub multi_client_options_size: u32,
+    pub multi_client_callbacks_size: u32,
+    pub dispatch_registration_size: u32,
+    pub dispatch_callbacks_size: u32,
+    pub dispatch_client_callbacks_size: u32,
+    pub dispatch_options_size: u32,
+    pub dispatch_client_options_size: u32,
+}
+
+// Раскладка зафиксирована: изменение любой строки ниже ломает уже
+// сгенерированные биндинги и требует поднять SHM_ABI_VERSION.
+const PTR: usize = size_of::<usize>();
+const _: () = {
+    assert!(size_of::<shm_endpoint_config_t>() == PTR);
+    assert!(size_of::<shm_callbacks_t>() == 8 * PTR);
+    assert!(size_of::<shm_auto_options_t>() == 24);
+    assert!(size_of::<shm_auto_stats_t>() == 40);
+    assert!(size_of::<shm_multi_options_t>() == 12);
+    assert!(size_of::<shm_multi_callbacks_t>() == 5 * PTR);
+    assert!(size_of::<shm_multi_client_options_t>() == 16);
+    assert!(size_of::<shm_multi_client_callbacks_t>() == 6 * PTR);
+    assert!(size_of::<shm_dispatch_registration_t>() == 8 + PTR);
+    assert!(size_of::<shm_dispatch_callbacks_t>() == 5 * PTR);
+    assert!(size_of::<shm_dispatch_client_callbacks_t>() == 5 * PTR);
+    assert!(size_of::<shm_dispatch_options_t>() == 16);
+    assert!(size_of::<shm_dispatch_client_options_t>() == 24);
+};
+
+/// Фактическая раскладка структур в этой сборке библиотеки.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_abi_layout() -> shm_abi_layout_t {
+    shm_abi_layout_t {
+        abi_version: SHM_ABI_VERSION,
+        pointer_size: PTR as u32,
+        endpoint_config_size: size_of::<shm_endpoint_config_t>() as u32,
+        callbacks_size: size_of::<shm_callbacks_t>() as u32,
+        auto_options_size: size_of::<shm_auto_options_t>() as u32,
+        auto_stats_size: size_of::<shm_auto_stats_t>() as u32,
+        multi_options_size: size_of::<shm_multi_options_t>() as u32,
+        multi_callbacks_size: size_of::<shm_multi_callbacks_t>() as u32,
+        multi_client_options_size: size_of::<shm_multi_client_options_t>() as u32,
+        multi_client_callbacks_size: size_of::<shm_multi_client_callbacks_t>() as u32,
+        dispatch_registration_size: size_of::<shm_dispatch_registration_t>() as u32,
+        dispatch_callbacks_size: size_of::<shm_dispatch_callbacks_t>() as u32,
+        dispatch_client_callbacks_size: size_of::<shm_dispatch_client_callbacks_t>() as u32,
+        dispatch_options_size: size_of::<shm_dispatch_options_t>() as u32,
+        dispatch_client_options_size: size_of::<shm_dispatch_client_options_t>() as u32,
+    }
+}
+
+/// NUL-terminated UTF-16 -> `CString` (UTF-8). `None` для NULL и
+/// невалидного UTF-16: подменять символы в имени объекта нельзя -- это
+/// было бы подключение к чужому каналу.
+fn from_wide(ptr: *const u16) -> Option<CString> {
+    if ptr.is_null() {
+        return None;
+    }
+    let mut len = 0;
+    // SAFETY: вызывающий передаёт NUL-terminated строку.
+    while unsafe { *ptr.add(len) } != 0 {
+        len += 1;
+    }
+    let units = unsafe { std::slice::from_raw_parts(ptr, len) };
+    let name = String::from_utf16(units).ok()?;
+    // Внутренних NUL нет: сканирование остановилось на первом.
+    CString::new(name).ok()
+}
+
+fn wide_config(name: *const u16) -> Option<(CString, shm_endpoint_config_t)> {
+    let name = from_wide(name)?;
+    let config = shm_endpoint_config_t {
+        name: name.as_ptr() as *const c_char,
+    };
+    Some((name, config))
+}
+
+/// `shm_server_start` с именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_start_w(
+    name: *const u16,
+    callbacks: *const shm_callbacks_t,
+) -> *mut ServerHandle {
+    match wide_config(name) {
+        Some((_name, config)) => shm_server_start(&config, callbacks),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_client_connect` с именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_connect_w(
+    name: *const u16,
+    callbacks: *const shm_callbacks_t,
+    timeout_ms: u32,
+) -> *mut ClientHandle {
+    match wide_config(name) {
+        Some((_name, config)) => shm_client_connect(&config, callbacks, timeout_ms),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_server_start_auto` с именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_server_start_auto_w(
+    name: *const u16,
+    callbacks: *const shm_callbacks_t,
+    options: *const shm_auto_options_t,
+) -> *mut AutoServerHandle {
+    match wide_config(name) {
+        Some((_name, config)) => shm_server_start_auto(&config, callbacks, options),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_client_connect_auto` с именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_client_connect_auto_w(
+    name: *const u16,
+    callbacks: *const shm_callbacks_t,
+    options: *const shm_auto_options_t,
+) -> *mut AutoClientHandle {
+    match wide_config(name) {
+        Some((_name, config)) => shm_client_connect_auto(&config, callbacks, options),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_multi_server_start` с базовым именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_multi_server_start_w(
+    base_name: *const u16,
+    callbacks: *const shm_multi_callbacks_t,
+    options: *const shm_multi_options_t,
+) -> *mut MultiServerHandle {
+    match from_wide(base_name) {
+        Some(name) => shm_multi_server_start(name.as_ptr(), callbacks, options),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_multi_client_connect` с базовым именем в UTF-16.
+#[unsafe(no_mangle)]
+pub extern "C" fn shm_multi_client_connect_w(
+    base_name: *const u16,
+    callbacks: *const shm_multi_client_callbacks_t,
+    options: *const shm_multi_client_options_t,
+) -> *mut MultiClientHandle {
+    match from_wide(base_name) {
+        Some(name) => shm_multi_client_connect(name.as_ptr(), callbacks, options),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_dispatch_server_start` с именем в UTF-16.
+///
+/// # Safety
+/// `name` обязан быть валидной NUL-terminated UTF-16 строкой либо null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn shm_dispatch_server_start_w(
+    name: *const u16,
+    callbacks: *const shm_dispatch_callbacks_t,
+    options: *const shm_dispatch_options_t,
+) -> *mut DispatchServerHandle {
+    match from_wide(name) {
+        Some(name) => unsafe { shm_dispatch_server_start(name.as_ptr(), callbacks, options) },
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `shm_dispatch_client_connect` с именем lobby в UTF-16 (`reg.name`
+/// остаётся UTF-8).
+///
+/// # Safety
+/// Как у `shm_dispatch_client_connect`; `name` -- NUL-terminated UTF-16.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn shm_dispatch_client_connect_w(
+    name: *const u16,
+    reg: *const shm_dispatch_registration_t,
+    callbacks: *const shm_dispatch_client_callbacks_t,
+    options: *const shm_dispatch_client_options_t,
+) -> *mut DispatchClientHandle {
+    match from_wide(name) {
+        Some(name) => unsafe {
+            shm_dispatch_client_connect(name.as_ptr(), reg, callbacks, options)
+        },
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::{
+        shm_client_disconnect_auto, shm_direction_t, shm_server_send_auto, shm_server_stop_auto,
+    };
+    use std::os::raw::c_void;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain([0]).collect()
+    }
+
+    #[test]
+    fn wide_names_are_decoded() {
+        let name = wide("Канал_😀");
+        assert_eq!(
+            from_wide(name.as_ptr()).unwrap().to_str().unwrap(),
+            "Канал_😀"
+        );
+        assert!(from_wide(std::ptr::null()).is_none());
+        // Непарный суррогат.
+        assert!(from_wide([0xD800, 0x41, 0].as_ptr()).is_none());
+        assert!(
+            shm_server_start_auto_w([0xDC00, 0].as_ptr(), std::ptr::null(), std::ptr::null())
+                .is_null()
+        );
+    }
+
+    #[test]
+    fn layout_matches_rust_types() {
+        let layout = shm_abi_layout();
+        assert_eq!(layout.abi_version, SHM_ABI_VERSION);
+        assert_eq!(layout.pointer_size as usize, size_of::<*const c_void>());
+        assert_eq!(layout.auto_options_size, 24);
+        assert_eq!(layout.callbacks_size as usize, size_of::<shm_callbacks_t>());
+    }
+
+    extern "C" fn count_message(
+        _direction: shm_direction_t,
+        _data: *const c_void,
+        _size: u32,
+        user_data: *mut c_void,
+    ) {
+        let counter = unsafe { &*(user_data as *const AtomicU32) };
+        counter.fetch_add(1, Ordering::AcqRel);
+    }
+
+    #[test]
+    fn wide_auto_roundtrip() {
+        let name = wide(&format!("XSHM_PINVOKE_Ω_{}", std::process::id()));
+        let server = shm_server_start_auto_w(name.as_ptr(), std::ptr::null(), std::ptr::null());
+        assert!(!server.is_null());
+
+        let received = AtomicU32::new(0);
+        let callbacks = shm_callbacks_t {
+            on_message: Some(count_message),
+            user_data: &received as *const AtomicU32 as *mut c_void,
+            ..Default::default()
+        };
+        let client = shm_client_connect_auto_w(name.as_ptr(), &callbacks, std::ptr::null());
+        assert!(!client.is_null());
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while received.load(Ordering::Acquire) == 0 && Instant::now() < deadline {
+            let _ = shm_server_send_auto(server, b"ping".as_ptr() as *const c_void, 4);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // После возврата disconnect callbacks больше не вызываются, и
+        // `received` можно отпускать.
+        shm_client_disconnect_auto(client);
+        shm_server_stop_auto(server);
+        assert!(received.load(Ordering::Acquire) > 0);
+    }
+}
@@ -0,0 +1,359 @@
+//! Mock backend (feature `mock`) - всё в памяти процесса.
+//!
+//! Секции и события живут в глобальных реестрах по имени, поэтому
+//! `SharedServer`/`SharedClient`, auto, multi и dispatch в namespace'е
+//! `Namespace::Mock` работают без kernel-объектов: handler'ы можно гонять
+//! в unit-тестах на CI без прав и на любой ОС, рядом с настоящими каналами
+//! (выбор backend'а -- `routed.rs`). Межпроцессного обмена, разумеется, нет
+//! -- обе стороны канала должны жить в одном процессе.
+//!
+//! Семантика повторяет Windows backend: объект живёт, пока открыт хотя бы
+//! один handle; `create` существующего имени -- ошибка
+//! `STATUS_OBJECT_NAME_COLLISION`, `open` отсутствующего --
+//! `STATUS_OBJECT_NAME_NOT_FOUND`. Секция создаётся обнулённой.
+//!
+//! Liveness процессов делегируется нативному backend'у: PID в ControlBlock
+//! всё равно настоящие. Handle'ы mock-объектов -- отрицательные адреса
+//! (см. [`is_mock_handle`]), чтобы `wait_any` отличал их от нативных.
+
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use super::{Platform, PlatformEvent, PlatformMapping};
+use crate::error::{Result, ShmError};
+use crate::layout::shared_mapping_size;
+
+const STATUS_OBJECT_NAME_NOT_FOUND: u32 = 0xC000_0034;
+const STATUS_OBJECT_NAME_COLLISION: u32 = 0xC000_0035;
+const STATUS_SECTION_TOO_BIG: u32 = 0xC000_0040;
+
+/// Выравнивание секции: как у страницы, с запасом для `align(64)` layout'а.
+const SECTION_ALIGN: usize = 4096;
+
+/// Реестр именованных объектов одного типа (`Weak` -- объект умирает
+/// вместе с последним handle, как в NT).
+struct Registry<T> {
+    objects: Mutex<Option<HashMap<String, Weak<T>>>>,
+}
+
+impl<T> Registry<T> {
+    const fn new() -> Self {
+        Registry {
+            objects: Mutex::new(None),
+        }
+    }
+
+    fn create(&self, name: &str, make: impl FnOnce() -> Result<T>) -> Result<Arc<T>> {
+        let mut guard = self.objects.lock().unwrap();
+        let objects = guard.get_or_insert_with(HashMap::new);
+        objects.retain(|_, object| object.strong_count() > 0);
+        if objects.contains_key(name) {
+            return Err(ShmError::WindowsError {
+                code: STATUS_OBJECT_NAME_COLLISION,
+                context: "mock create",
+            });
+        }
+        let object = Arc::new(make()?);
+        objects.insert(name.to_owned(), Arc::downgrade(&object));
+        Ok(object)
+    }
+
+    /// Регистрирует уже созданный объект (ключ известен только после
+    /// создания).
+    fn register(&self, name: String, object: &Arc<T>) {
+        let mut guard = self.objects.lock().unwrap();
+        let objects = guard.get_or_insert_with(HashMap::new);
+        objects.retain(|_, object| object.strong_count() > 0);
+        objects.insert(name, Arc::downgrade(object));
+    }
+
+    /// Имена живых объектов.
+    fn names(&self) -> Vec<String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .filter(|(_, object)| object.strong_count() > 0)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn open(&self, name: &str) -> Result<Arc<T>> {
+        self.objects
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|objects| objects.get(name))
+            .and_then(Weak::upgrade)
+            .ok_or(ShmError::WindowsError {
+                code: STATUS_OBJECT_NAME_NOT_FOUND,
+                context: "mock open",
+            })
+    }
+}
+
+// ============================================================================
+// Mapping
+// ============================================================================
+
+struct Section {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+unsafe impl Send for Section {}
+unsafe impl Sync for Section {}
+
+impl Section {
+    fn new(size: usize) -> Result<Self> {
+        let layout = Layout::from_size_align(size, SECTION_ALIGN)
+            .map_err(|_| ShmError::InvalidConfig("mock section layout"))?;
+        if size == 0 {
+            return Err(ShmError::InvalidConfig("mock section size is zero"));
+        }
+        // SAFETY: размер ненулевой.
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return Err(ShmError::WindowsError {
+                code: 0xC000_0017, // STATUS_NO_MEMORY
+                context: "mock section alloc",
+            });
+        }
+        Ok(Section { ptr, layout })
+    }
+}
+
+impl Drop for Section {
+    fn drop(&mut self) {
+        // SAFETY: выделено в Section::new с тем же layout.
+        unsafe { dealloc(self.ptr, self.layout) };
+    }
+}
+
+static SECTIONS: Registry<Section> = Registry::new();
+/// Анонимные секции по `section_handle` -- для `from_handle`.
+static ANONYMOUS: Registry<Section> = Registry::new();
+
+pub struct Mapping {
+    section: Arc<Section>,
+}
+
+impl PlatformMapping for Mapping {
+    fn create_sized(name: &str, size: usize) -> Result<Self> {
+        SECTIONS
+            .create(name, || Section::new(size))
+            .map(|section| Mapping { section })
+    }
+
+    fn open_sized(name: &str, size: usize) -> Result<Self> {
+        Mapping::with_size(SECTIONS.open(name)?, size)
+    }
+
+    fn create_anonymous() -> Result<Self> {
+        let section = Arc::new(Section::new(shared_mapping_size())?);
+        ANONYMOUS.register(anonymous_key(mock_handle(section.ptr)), &section);
+        Ok(Mapping { section })
+    }
+
+    /// Handle -- значение `section_handle` живой анонимной секции.
+    fn from_handle(handle: isize, size: usize) -> Result<Self> {
+        Mapping::with_size(ANONYMOUS.open(&anonymous_key(handle))?, size)
+    }
+
+    fn as_ptr(&self) -> *mut u8 {
+        self.section.ptr
+    }
+
+    /// Адрес секции (см. [`mock_handle`]): настоящего handle'а нет, значение
+    /// годится только как непрозрачный идентификатор.
+    fn section_handle(&self) -> isize {
+        mock_handle(self.section.ptr)
+    }
+}
+
+impl Mapping {
+    fn with_size(section: Arc<Section>, size: usize) -> Result<Self> {
+        if section.layout.size() < size {
+            return Err(ShmError::WindowsError {
+                code: STATUS_SECTION_TOO_BIG,
+                context: "mock open (section smaller than requested)",
+            });
+        }
+        Ok(Mapping { section })
+    }
+}
+
+fn anonymous_key(handle: isize) -> String {
+    format!("{handle:#x}")
+}
+
+/// Handle mock-объекта: адрес со знаком минус. Нативные handle'ы, которые
+/// библиотека ждёт или передаёт (fd, NT HANDLE), неотрицательны.
+fn mock_handle<T>(object: *const T) -> isize {
+    -(object as isize)
+}
+
+pub(crate) fn is_mock_handle(handle: isize) -> bool {
+    handle < 0
+}
+
+// ============================================================================
+// EventHandle
+// ============================================================================
+
+/// Один condvar на все события: `wait_any` ждёт сразу несколько флагов.
+static SIGNAL: (Mutex<()>, Condvar) = (Mutex::new(()), Condvar::new());
+
+struct Event {
+    signaled: AtomicBool,
+}
+
+static EVENTS: Registry<Event> = Registry::new();
+
+pub struct EventHandle {
+    event: Arc<Event>,
+}
+
+fn new_event() -> Result<Event> {
+    Ok(Event {
+        signaled: AtomicBool::new(false),
+    })
+}
+
+impl PlatformEvent for EventHandle {
+    fn create(name: &str) -> Result<Self> {
+        EVENTS
+            .create(name, new_event)
+            .map(|event| EventHandle { event })
+    }
+
+    fn open(name: &str) -> Result<Self> {
+        EVENTS.open(name).map(|event| EventHandle { event })
+    }
+
+    fn create_local() -> Result<Self> {
+        new_event().map(|event| EventHandle {
+            event: Arc::new(event),
+        })
+    }
+
+    fn set(&self) -> Result<()> {
+        self.event.signaled.store(true, Ordering::SeqCst);
+        let _guard = SIGNAL.0.lock().unwrap();
+        SIGNAL.1.notify_all();
+        Ok(())
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        wait_events(&[&self.event], timeout).map(|index| index.is_some())
+    }
+
+    /// Адрес `Event` (значение для `wait_any`, см. [`mock_handle`]).
+    fn raw_handle(&self) -> isize {
+        mock_handle(Arc::as_ptr(&self.event))
+    }
+}
+
+fn wait_events(events: &[&Event], timeout: Option<Duration>) -> Result<Option<usize>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut guard = SIGNAL.0.lock().unwrap();
+    loop {
+        // Проверка под mutex'ом: set() публикует флаг ДО захвата mutex'а,
+        // поэтому сигнал не теряется между проверкой и засыпанием.
+        for (index, event) in events.iter().enumerate() {
+            // swap забирает сигнал -- auto-reset, будим ровно одного.
+            if event.signaled.swap(false, Ordering::SeqCst) {
+                return Ok(Some(index));
+            }
+        }
+        guard = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                SIGNAL.1.wait_timeout(guard, deadline - now).unwrap().0
+            }
+            None => SIGNAL.1.wait(guard).unwrap(),
+        };
+    }
+}
+
+// ============================================================================
+// Platform
+// ============================================================================
+
+pub(crate) struct Mock;
+
+impl Platform for Mock {
+    type Mapping = Mapping;
+    type Event = EventHandle;
+
+    fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+        // SAFETY: единственный источник mock-значений в крейте --
+        // raw_handle() живых EventHandle, т.е. указатели на Event внутри Arc
+        // со знаком минус.
+        let events: Vec<&Event> = handles
+            .iter()
+            .map(|&handle| unsafe { &*(-handle as *const Event) })
+            .collect();
+        wait_events(&events, timeout)
+    }
+
+    fn is_process_alive(pid: u32) -> bool {
+        super::NativeOs::is_process_alive(pid)
+    }
+
+    /// Обе стороны в одном процессе: handle передаётся как есть.
+    fn duplicate_handle_into(handle: isize, _pid: u32) -> Result<isize> {
+        Ok(handle)
+    }
+
+    /// Handle не дублировался -- закрывать нечего (им владеет отправитель).
+    fn close_handle(_handle: isize) {}
+
+    fn close_remote_handle(_handle: isize, _pid: u32) {}
+
+    fn monotonic_ns() -> u64 {
+        super::NativeOs::monotonic_ns()
+    }
+
+    fn list_sections() -> Vec<String> {
+        SECTIONS.names()
+    }
+
+    fn is_not_found(error: &ShmError) -> bool {
+        matches!(
+            error,
+            ShmError::WindowsError {
+                code: STATUS_OBJECT_NAME_NOT_FOUND,
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_collides_while_alive_and_frees_name_on_drop() {
+        let name = "Local\\XSHM_MOCK_COLLISION";
+        let first = EventHandle::create(name).unwrap();
+        assert!(matches!(
+            EventHandle::create(name),
+            Err(ShmError::WindowsError {
+                code: STATUS_OBJECT_NAME_COLLISION,
+                ..
+            })
+        ));
+        drop(first);
+        assert!(EventHandle::open(name).is_err());
+        assert!(EventHandle::create(name).is_ok());
+    }
+}
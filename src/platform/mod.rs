@@ -0,0 +1,369 @@
+//! Platform layer: shared memory секция, именованные auto-reset события и
+//! ожидание нескольких событий сразу.
+//!
+//! Остальной код библиотеки (`SharedServer`/`SharedClient`, auto, multi,
+//! dispatch) работает только через трейты этого модуля, поэтому один и тот
+//! же API собирается на:
+//! - **Windows** (`windows.rs`) -- прямые вызовы NT API через ntdll.dll;
+//! - **Unix** (`unix.rs`) -- `shm_open`/`mmap`, события как futex-слово в
+//!   отдельном shm-сегменте (на не-Linux системах -- polling);
+//! - **mock** (`mock.rs`, feature `mock`) -- in-memory объекты одного
+//!   процесса для unit-тестов без kernel-объектов. Не заменяет нативный
+//!   backend: объекты с именами в `Namespace::Mock` и их handle'ы
+//!   `routed.rs` отправляет в mock, остальные -- в нативный.
+//!
+//! Архитектуры: x86, x86_64 и aarch64 (Windows on ARM, Linux). Порядок
+//! доступа к памяти кольца задан атомиками и барьерами модели C11 (см.
+//! `xshm_core::ring`) и проверен loom-тестами, а не выведен из x86 TSO;
+//! остальные архитектуры не проверялись.
+
+// Compile-time проверка архитектуры
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+compile_error!("xShm поддерживает только x86, x86_64 и aarch64 архитектуры!");
+
+use std::time::Duration;
+
+use crate::error::{Result, ShmError};
+use crate::layout::shared_mapping_size;
+use crate::security::Security;
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+mod routed;
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+/// Разделяемая секция, отображённая в адресное пространство процесса.
+/// Отображение живёт, пока жив объект. Каналы используют размер
+/// `shared_mapping_size()`, arena -- свой.
+pub(crate) trait PlatformMapping: Sized + Send + Sync {
+    /// Создание именованной секции размером `size` байт (обнулённой).
+    fn create_sized(name: &str, size: usize) -> Result<Self>;
+    /// Открытие существующей именованной секции размером не менее `size`.
+    fn open_sized(name: &str, size: usize) -> Result<Self>;
+    /// Создание секции канала без имени (доступна только через
+    /// `section_handle`).
+    fn create_anonymous() -> Result<Self>;
+    /// Отображение секции по её handle'у (`section_handle` другой стороны,
+    /// переданный в этот процесс); размер -- не менее `size`. Handle
+    /// переходит во владение отображения и закрывается вместе с ним, в
+    /// том числе при ошибке.
+    fn from_handle(handle: isize, size: usize) -> Result<Self>;
+    /// Начало отображения.
+    fn as_ptr(&self) -> *mut u8;
+    /// Raw handle секции (NT HANDLE на Windows, fd на Unix).
+    fn section_handle(&self) -> isize;
+    /// Удалять ли имя секции при Drop. На Unix имя удаляет его владелец
+    /// (по умолчанию -- создатель); на Windows и в mock объект живёт, пока
+    /// открыт хоть один handle, и флаг ничего не меняет.
+    fn set_owns_name(&mut self, _owns: bool) {}
+    /// Создание именованной секции размером не менее `size` на больших
+    /// страницах (меньше TLB-промахов на многомегабайтных кольцах). Если
+    /// ОС их не даёт -- нет привилегии, поддержки или свободных больших
+    /// страниц, -- создаётся обычная секция; какая получилась, говорит
+    /// `large_pages`.
+    fn create_large_pages(name: &str, size: usize) -> Result<Self> {
+        Self::create_sized(name, size)
+    }
+    /// Создание именованной секции с правами `security` (см. [`Security`]);
+    /// `large_pages` -- как в `create_large_pages`. Backend без прав доступа
+    /// (mock) значение игнорирует.
+    fn create_secured(
+        name: &str,
+        size: usize,
+        large_pages: bool,
+        security: Security,
+    ) -> Result<Self> {
+        let _ = security;
+        if large_pages {
+            Self::create_large_pages(name, size)
+        } else {
+            Self::create_sized(name, size)
+        }
+    }
+    /// Секция отображена на больших страницах.
+    fn large_pages(&self) -> bool {
+        false
+    }
+
+    /// Открытие существующей именованной секции канала.
+    fn open(name: &str) -> Result<Self> {
+        Self::open_sized(name, shared_mapping_size())
+    }
+}
+
+
+/// Именованное auto-reset событие: `set` будит ровно одного ожидающего,
+/// после чего событие снова несигнальное.
+pub(crate) trait PlatformEvent: Sized + Send + Sync {
+    fn create(name: &str) -> Result<Self>;
+    /// Создание с правами `security` (см. `PlatformMapping::create_secured`).
+    fn create_secured(name: &str, security: Security) -> Result<Self> {
+        let _ = security;
+        Self::create(name)
+    }
+    fn open(name: &str) -> Result<Self>;
+    /// Событие без имени, видимое только этому процессу (см. `CancellationToken`).
+    fn create_local() -> Result<Self>;
+    fn set(&self) -> Result<()>;
+    /// `Ok(true)` -- событие сработало, `Ok(false)` -- таймаут.
+    fn wait(&self, timeout: Option<Duration>) -> Result<bool>;
+    /// Значение для `wait_any` (и для передачи в kernel driver на Windows).
+    fn raw_handle(&self) -> isize;
+    /// См. `PlatformMapping::set_owns_name`.
+    fn set_owns_name(&mut self, _owns: bool) {}
+}
+
+/// Набор примитивов одной платформы.
+pub(crate) trait Platform {
+    type Mapping: PlatformMapping;
+    type Event: PlatformEvent;
+
+    /// Ожидание любого из событий (`raw_handle()`); возвращает индекс
+    /// сработавшего (наименьший, если несколько) или `None` по таймауту.
+    fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>>;
+
+    /// Жив ли процесс с данным PID. При любой двусмысленности -- `true`.
+    fn is_process_alive(pid: u32) -> bool;
+
+    /// Дублирует handle текущего процесса в процесс `pid`; возвращает
+    /// значение нового handle'а в целевом процессе. Исходный handle
+    /// остаётся за вызывающим.
+    fn duplicate_handle_into(handle: isize, pid: u32) -> Result<isize>;
+
+    /// Закрывает handle, полученный от пира и никому не отданный.
+    fn close_handle(handle: isize);
+
+    /// Закрывает handle, ранее продублированный в процесс `pid`, но так и
+    /// не доставленный пиру.
+    fn close_remote_handle(handle: isize, pid: u32);
+
+    /// Монотонные часы в наносекундах, общие для всех процессов машины
+    /// (метки времени сообщений сравниваются между процессами).
+    fn monotonic_ns() -> u64;
+
+    /// Имена именованных секций, видимых процессу, в том виде, который
+    /// принимает `Mapping::open`. Перечислить не удалось -- пустой список.
+    fn list_sections() -> Vec<String>;
+
+    /// Ошибка открытия означает «объекта с таким именем нет» (сервер ещё
+    /// не создал канал или уже закрыл его).
+    fn is_not_found(error: &ShmError) -> bool;
+
+    /// Удаляет имя объекта, владелец которого умер, не закрыв его: там, где
+    /// имена переживают процесс (Unix). На Windows и в mock объект исчезает
+    /// вместе с последним handle'ом -- удалять нечего.
+    fn unlink(_name: &str) {}
+}
+
+#[cfg(windows)]
+type NativeOs = windows::Windows;
+#[cfg(unix)]
+type NativeOs = unix::Unix;
+
+#[cfg(all(windows, not(feature = "mock")))]
+pub(crate) use windows::{EventHandle, Mapping};
+#[cfg(all(unix, not(feature = "mock")))]
+pub(crate) use unix::{EventHandle, Mapping};
+
+#[cfg(feature = "mock")]
+pub(crate) use routed::{is_not_found, list_sections, unlink, wait_any, EventHandle, Mapping};
+
+/// Чьи объекты у канала. Handle'ы, которые стороны передают друг другу
+/// (`send_handle_*`), дублирует и закрывает backend канала, а не значение
+/// handle'а: у mock-канала они проходят как есть.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Backend {
+    #[default]
+    Native,
+    /// In-memory объекты (`Namespace::Mock`, feature `mock`).
+    #[cfg(feature = "mock")]
+    Mock,
+}
+
+impl Backend {
+    /// Backend объектов канала с именем объекта ОС `name`.
+    pub(crate) fn of(name: &str) -> Self {
+        #[cfg(feature = "mock")]
+        if routed::is_mock_name(name) {
+            return Backend::Mock;
+        }
+        let _ = name;
+        Backend::Native
+    }
+
+    pub(crate) fn duplicate_handle_into(self, handle: isize, pid: u32) -> Result<isize> {
+        match self {
+            Backend::Native => NativeOs::duplicate_handle_into(handle, pid),
+            #[cfg(feature = "mock")]
+            Backend::Mock => mock::Mock::duplicate_handle_into(handle, pid),
+        }
+    }
+
+    pub(crate) fn close_handle(self, handle: isize) {
+        match self {
+            Backend::Native => NativeOs::close_handle(handle),
+            #[cfg(feature = "mock")]
+            Backend::Mock => mock::Mock::close_handle(handle),
+        }
+    }
+
+    pub(crate) fn close_remote_handle(self, handle: isize, pid: u32) {
+        match self {
+            Backend::Native => NativeOs::close_remote_handle(handle, pid),
+            #[cfg(feature = "mock")]
+            Backend::Mock => mock::Mock::close_remote_handle(handle, pid),
+        }
+    }
+}
+
+#[cfg(not(feature = "mock"))]
+pub(crate) fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+    NativeOs::wait_any(handles, timeout)
+}
+
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    NativeOs::is_process_alive(pid)
+}
+
+pub(crate) fn monotonic_ns() -> u64 {
+    NativeOs::monotonic_ns()
+}
+
+#[cfg(not(feature = "mock"))]
+pub(crate) fn list_sections() -> Vec<String> {
+    NativeOs::list_sections()
+}
+
+#[cfg(not(feature = "mock"))]
+pub(crate) fn is_not_found(error: &ShmError) -> bool {
+    NativeOs::is_not_found(error)
+}
+
+#[cfg(not(feature = "mock"))]
+pub(crate) fn unlink(name: &str) {
+    NativeOs::unlink(name)
+}
+
+/// Короткоживущий дочерний процесс, который сразу завершается (для
+/// liveness-тестов).
+#[cfg(test)]
+pub(crate) fn spawn_exiting_child() -> std::process::Child {
+    #[cfg(windows)]
+    let mut command = {
+        let mut c = std::process::Command::new("cmd");
+        c.args(["/C", "exit", "0"]);
+        c
+    };
+    #[cfg(unix)]
+    let mut command = std::process::Command::new("true");
+    command.spawn().expect("spawn short-lived child process")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_process_is_alive() {
+        assert!(is_process_alive(std::process::id()));
+    }
+
+    #[test]
+    fn pid_zero_is_conservatively_alive() {
+        // 0 не бывает PID пользовательского процесса — не должен читаться
+        // как "подтверждённо мёртв".
+        assert!(is_process_alive(0));
+    }
+
+    /// Регрессия (аудит 2026-07-10, orphan-слот bug): для РЕАЛЬНО завершённого
+    /// процесса `is_process_alive` обязана вернуть `false` — иначе
+    /// liveness-детекция никогда не сработает.
+    #[test]
+    fn exited_process_is_detected_as_dead() {
+        let mut child = spawn_exiting_child();
+        let pid = child.id();
+        child.wait().expect("wait for child exit");
+
+        // Небольшой запас: на некоторых системах PID-объект остаётся
+        // открываемым ещё короткое время после выхода, пока wait() полностью
+        // не разрешит завершение с точки зрения ядра.
+        for _ in 0..50 {
+            if !is_process_alive(pid) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("is_process_alive(pid={pid}) должен был вернуть false после child.wait()");
+    }
+
+    #[test]
+    fn event_is_auto_reset_and_visible_through_open() {
+        let name = format!("Local\\XSHM_EVT_TEST_{}", std::process::id());
+        let created = EventHandle::create(&name).unwrap();
+        let opened = EventHandle::open(&name).unwrap();
+
+        assert!(!opened.wait(Some(Duration::ZERO)).unwrap());
+        created.set().unwrap();
+        assert!(opened.wait(Some(Duration::from_millis(100))).unwrap());
+        // auto-reset: второй wait уже не срабатывает
+        assert!(!opened.wait(Some(Duration::from_millis(10))).unwrap());
+    }
+
+    #[test]
+    fn wait_any_reports_signalled_index_and_times_out() {
+        let base = format!("Local\\XSHM_WAITANY_TEST_{}", std::process::id());
+        let a = EventHandle::create(&format!("{base}_A")).unwrap();
+        let b = EventHandle::create(&format!("{base}_B")).unwrap();
+        let handles = [a.raw_handle(), b.raw_handle()];
+
+        assert_eq!(
+            wait_any(&handles, Some(Duration::from_millis(10))).unwrap(),
+            None
+        );
+
+        let setter = std::thread::spawn({
+            let b = EventHandle::open(&format!("{base}_B")).unwrap();
+            move || {
+                std::thread::sleep(Duration::from_millis(20));
+                b.set().unwrap();
+            }
+        });
+        assert_eq!(
+            wait_any(&handles, Some(Duration::from_secs(2))).unwrap(),
+            Some(1)
+        );
+        setter.join().unwrap();
+    }
+
+    #[test]
+    fn mapping_is_shared_between_create_and_open() {
+        let name = format!("Local\\XSHM_MAP_TEST_{}", std::process::id());
+        let created = Mapping::create_sized(&name, shared_mapping_size()).unwrap();
+        let opened = Mapping::open(&name).unwrap();
+        // SAFETY: оба отображения одной секции размером shared_mapping_size().
+        unsafe {
+            *created.as_ptr().add(100) = 0x5A;
+            assert_eq!(*opened.as_ptr().add(100), 0x5A);
+        }
+        assert!(Mapping::open(&format!("{name}_MISSING")).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn owner_only_mapping_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let name = format!("XSHM_OWNER_TEST_{}", std::process::id());
+        let _mapping =
+            Mapping::create_secured(&name, shared_mapping_size(), false, Security::Owner).unwrap();
+        let mode = std::fs::metadata(format!("/dev/shm/{name}"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
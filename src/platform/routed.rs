@@ -0,0 +1,253 @@
+//! Выбор backend'а по объекту (feature `mock`).
+//!
+//! Имена в namespace'е `Mock\` (см. `naming::Namespace::Mock`) и handle'ы
+//! их объектов уходят в in-memory backend (`mock.rs`), всё остальное -- в
+//! нативный. Так mock-каналы работают рядом с настоящими в одной сборке:
+//! feature ничего не заменяет, а только добавляет namespace.
+
+use std::time::{Duration, Instant};
+
+use super::mock::{self, Mock};
+use super::{NativeOs, Platform, PlatformEvent, PlatformMapping};
+use crate::error::{Result, ShmError};
+use crate::naming::MOCK_PREFIX;
+use crate::security::Security;
+
+#[cfg(unix)]
+use super::unix::{EventHandle as NativeEvent, Mapping as NativeMapping};
+#[cfg(windows)]
+use super::windows::{EventHandle as NativeEvent, Mapping as NativeMapping};
+
+/// Срез опроса в `wait_any` по смешанному набору handle'ов.
+const MIXED_WAIT_SLICE: Duration = Duration::from_millis(1);
+
+pub(crate) fn is_mock_name(name: &str) -> bool {
+    name.starts_with(MOCK_PREFIX)
+}
+
+pub enum Mapping {
+    Native(NativeMapping),
+    Mock(mock::Mapping),
+}
+
+impl Mapping {
+    fn by_name(
+        name: &str,
+        native: impl FnOnce() -> Result<NativeMapping>,
+        mock: impl FnOnce() -> Result<mock::Mapping>,
+    ) -> Result<Self> {
+        if is_mock_name(name) {
+            mock().map(Mapping::Mock)
+        } else {
+            native().map(Mapping::Native)
+        }
+    }
+}
+
+impl PlatformMapping for Mapping {
+    fn create_sized(name: &str, size: usize) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeMapping::create_sized(name, size),
+            || mock::Mapping::create_sized(name, size),
+        )
+    }
+
+    fn open_sized(name: &str, size: usize) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeMapping::open_sized(name, size),
+            || mock::Mapping::open_sized(name, size),
+        )
+    }
+
+    /// Безымянная секция -- всегда нативная: mock-канал выбирается именем.
+    fn create_anonymous() -> Result<Self> {
+        NativeMapping::create_anonymous().map(Mapping::Native)
+    }
+
+    fn from_handle(handle: isize, size: usize) -> Result<Self> {
+        if mock::is_mock_handle(handle) {
+            mock::Mapping::from_handle(handle, size).map(Mapping::Mock)
+        } else {
+            NativeMapping::from_handle(handle, size).map(Mapping::Native)
+        }
+    }
+
+    fn as_ptr(&self) -> *mut u8 {
+        match self {
+            Mapping::Native(mapping) => mapping.as_ptr(),
+            Mapping::Mock(mapping) => mapping.as_ptr(),
+        }
+    }
+
+    fn section_handle(&self) -> isize {
+        match self {
+            Mapping::Native(mapping) => mapping.section_handle(),
+            Mapping::Mock(mapping) => mapping.section_handle(),
+        }
+    }
+
+    fn set_owns_name(&mut self, owns: bool) {
+        match self {
+            Mapping::Native(mapping) => mapping.set_owns_name(owns),
+            Mapping::Mock(mapping) => mapping.set_owns_name(owns),
+        }
+    }
+
+    fn create_large_pages(name: &str, size: usize) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeMapping::create_large_pages(name, size),
+            || mock::Mapping::create_large_pages(name, size),
+        )
+    }
+
+    fn create_secured(
+        name: &str,
+        size: usize,
+        large_pages: bool,
+        security: Security,
+    ) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeMapping::create_secured(name, size, large_pages, security),
+            || mock::Mapping::create_secured(name, size, large_pages, security),
+        )
+    }
+
+    fn large_pages(&self) -> bool {
+        match self {
+            Mapping::Native(mapping) => mapping.large_pages(),
+            Mapping::Mock(mapping) => mapping.large_pages(),
+        }
+    }
+}
+
+pub enum EventHandle {
+    Native(NativeEvent),
+    Mock(mock::EventHandle),
+}
+
+impl EventHandle {
+    fn by_name(
+        name: &str,
+        native: impl FnOnce() -> Result<NativeEvent>,
+        mock: impl FnOnce() -> Result<mock::EventHandle>,
+    ) -> Result<Self> {
+        if is_mock_name(name) {
+            mock().map(EventHandle::Mock)
+        } else {
+            native().map(EventHandle::Native)
+        }
+    }
+}
+
+impl PlatformEvent for EventHandle {
+    fn create(name: &str) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeEvent::create(name),
+            || mock::EventHandle::create(name),
+        )
+    }
+
+    fn create_secured(name: &str, security: Security) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeEvent::create_secured(name, security),
+            || mock::EventHandle::create_secured(name, security),
+        )
+    }
+
+    fn open(name: &str) -> Result<Self> {
+        Self::by_name(
+            name,
+            || NativeEvent::open(name),
+            || mock::EventHandle::open(name),
+        )
+    }
+
+    /// Локальное событие (токен отмены) -- нативное: его ждут вместе с
+    /// событиями каналов обоих backend'ов (см. [`wait_any`]).
+    fn create_local() -> Result<Self> {
+        NativeEvent::create_local().map(EventHandle::Native)
+    }
+
+    fn set(&self) -> Result<()> {
+        match self {
+            EventHandle::Native(event) => event.set(),
+            EventHandle::Mock(event) => event.set(),
+        }
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        match self {
+            EventHandle::Native(event) => event.wait(timeout),
+            EventHandle::Mock(event) => event.wait(timeout),
+        }
+    }
+
+    fn raw_handle(&self) -> isize {
+        match self {
+            EventHandle::Native(event) => event.raw_handle(),
+            EventHandle::Mock(event) => event.raw_handle(),
+        }
+    }
+
+    fn set_owns_name(&mut self, owns: bool) {
+        match self {
+            EventHandle::Native(event) => event.set_owns_name(owns),
+            EventHandle::Mock(event) => event.set_owns_name(owns),
+        }
+    }
+}
+
+/// Ожидание событий обоих backend'ов. Набор одного backend'а ждёт он сам;
+/// смешанный (mock-канал и нативный токен отмены) опрашивается срезами
+/// [`MIXED_WAIT_SLICE`]: нативная половина -- без ожидания, mock -- срез.
+pub(crate) fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+    let (mocked, native): (Vec<usize>, Vec<usize>) =
+        (0..handles.len()).partition(|&index| mock::is_mock_handle(handles[index]));
+    if mocked.is_empty() {
+        return NativeOs::wait_any(handles, timeout);
+    }
+    if native.is_empty() {
+        return Mock::wait_any(handles, timeout);
+    }
+    let mock_handles: Vec<isize> = mocked.iter().map(|&index| handles[index]).collect();
+    let native_handles: Vec<isize> = native.iter().map(|&index| handles[index]).collect();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if let Some(index) = NativeOs::wait_any(&native_handles, Some(Duration::ZERO))? {
+            return Ok(Some(native[index]));
+        }
+        let slice = match deadline {
+            Some(deadline) => MIXED_WAIT_SLICE.min(deadline.saturating_duration_since(Instant::now())),
+            None => MIXED_WAIT_SLICE,
+        };
+        if let Some(index) = Mock::wait_any(&mock_handles, Some(slice))? {
+            return Ok(Some(mocked[index]));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(None);
+        }
+    }
+}
+
+pub(crate) fn list_sections() -> Vec<String> {
+    let mut sections = NativeOs::list_sections();
+    sections.extend(Mock::list_sections());
+    sections
+}
+
+pub(crate) fn is_not_found(error: &ShmError) -> bool {
+    NativeOs::is_not_found(error) || Mock::is_not_found(error)
+}
+
+/// У mock-объектов имя умирает вместе с последним handle'ом.
+pub(crate) fn unlink(name: &str) {
+    if !is_mock_name(name) {
+        NativeOs::unlink(name);
+    }
+}
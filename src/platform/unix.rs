@@ -0,0 +1,658 @@
+//! Unix backend - POSIX shared memory (`shm_open`/`mmap`).
+//!
+//! Имена объектов в стиле Windows (`Local\name`) отображаются на POSIX shm
+//! имена: префикс `Local\`/`Global\` отбрасывается (сессионных namespace'ов
+//! на Unix нет), разделители пути заменяются на `_` -- `/name`.
+//!
+//! Событие -- 4-байтовое слово в собственном shm-сегменте (1 = сигнальное).
+//! `set` публикует 1 и будит ожидающих через futex (Linux, НЕ private --
+//! работает между процессами); ожидающий забирает сигнал атомарным
+//! `swap(0)`, что и даёт семантику auto-reset. На Unix без futex ожидание
+//! сводится к polling с шагом `POLL_STEP`.
+//!
+//! Создание -- `O_EXCL`, как на Windows: имя, которое уже занято, даёт
+//! ошибку коллизии (`STATUS_OBJECT_NAME_COLLISION`), а не пересоздание
+//! чужого сегмента. Создатель объекта (сервер) удаляет имя (`shm_unlink`)
+//! при Drop; уже открытые пирами отображения при этом остаются валидными.
+//! Сервер, передающий канал новой версии процесса (`SharedServer::detach`),
+//! оставляет имена, и удалять их при Drop будет уже новый владелец. Имена
+//! упавшего процесса переживают его -- их снимает `reclaim`.
+
+use std::ffi::CString;
+use std::io;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use super::{Platform, PlatformEvent, PlatformMapping};
+use crate::error::{Result, ShmError};
+use crate::layout::shared_mapping_size;
+use crate::security::Security;
+
+/// Шаг polling'а там, где нет futex.
+const POLL_STEP: Duration = Duration::from_millis(1);
+
+/// Размер сегмента события (одно futex-слово).
+const EVENT_SEGMENT_SIZE: usize = std::mem::size_of::<AtomicU32>();
+
+/// Код ошибки занятого имени -- тот же, что у Windows backend'а.
+const STATUS_OBJECT_NAME_COLLISION: u32 = 0xC000_0035;
+
+fn os_error(context: &'static str) -> ShmError {
+    match io::Error::last_os_error().raw_os_error().unwrap_or(0) {
+        libc::EACCES | libc::EPERM => ShmError::PrivilegeRequired(context),
+        libc::EEXIST => ShmError::WindowsError {
+            code: STATUS_OBJECT_NAME_COLLISION,
+            context,
+        },
+        code => ShmError::WindowsError {
+            code: code as u32,
+            context,
+        },
+    }
+}
+
+fn shm_name(name: &str) -> Result<CString> {
+    let bare = name
+        .strip_prefix("Local\\")
+        .or_else(|| name.strip_prefix("Global\\"))
+        .unwrap_or(name);
+    if bare.is_empty() {
+        return Err(ShmError::InvalidName("object name is empty"));
+    }
+    let mut posix = String::with_capacity(bare.len() + 1);
+    posix.push('/');
+    posix.extend(
+        bare.chars()
+            .map(|c| if c == '/' || c == '\\' { '_' } else { c }),
+    );
+    CString::new(posix).map_err(|_| ShmError::InvalidName("object name contains NUL"))
+}
+
+// ============================================================================
+// Segment - отображённый shm-объект
+// ============================================================================
+
+struct Segment {
+    fd: libc::c_int,
+    ptr: *mut u8,
+    len: usize,
+    /// Имя объекта (у anonymous -- нет).
+    name: Option<CString>,
+    /// Удалить имя (`shm_unlink`) при Drop: по умолчанию -- у создателя.
+    owns_name: bool,
+}
+
+impl Segment {
+    fn create(name: &str, len: usize, security: Security) -> Result<Self> {
+        let cname = shm_name(name)?;
+        let mode = match security {
+            Security::Everyone => 0o666,
+            Security::Owner => 0o600,
+        } as libc::mode_t;
+        let fd = unsafe {
+            libc::shm_open(
+                cname.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR | libc::O_CLOEXEC,
+                mode,
+            )
+        };
+        if fd < 0 {
+            return Err(os_error("shm_open"));
+        }
+        // Права -- как DACL на Windows (см. `Security`): umask их не урезает.
+        unsafe { libc::fchmod(fd, mode) };
+        Self::map(fd, len, true, Some(cname), true)
+    }
+
+    fn open(name: &str, len: usize) -> Result<Self> {
+        let cname = shm_name(name)?;
+        let fd = unsafe { libc::shm_open(cname.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(os_error("shm_open"));
+        }
+        Self::adopt(fd, len, Some(cname))
+    }
+
+    /// Отображение уже открытого `fd` (закрывается при ошибке).
+    fn adopt(fd: libc::c_int, len: usize, name: Option<CString>) -> Result<Self> {
+        // Создатель мог ещё не успеть выставить размер: mmap за концом файла
+        // дал бы SIGBUS при первом обращении, поэтому проверяем заранее.
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+            let err = os_error("fstat");
+            unsafe { libc::close(fd) };
+            return Err(err);
+        }
+        if (stat.st_size as u64) < len as u64 {
+            unsafe { libc::close(fd) };
+            return Err(ShmError::WindowsError {
+                code: libc::ENOENT as u32,
+                context: "shm_open (segment not initialized)",
+            });
+        }
+        Self::map(fd, len, false, name, false)
+    }
+
+    fn anonymous(len: usize) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        let fd = unsafe { libc::memfd_create(c"xshm".as_ptr(), libc::MFD_CLOEXEC) };
+        #[cfg(not(target_os = "linux"))]
+        let fd = {
+            use std::sync::atomic::AtomicU64;
+            static COUNTER: AtomicU64 = AtomicU64::new(0);
+            let unique = format!(
+                "/xshm-anon-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let cname = CString::new(unique).expect("no NUL in generated name");
+            let fd = unsafe {
+                libc::shm_open(
+                    cname.as_ptr(),
+                    libc::O_CREAT | libc::O_EXCL | libc::O_RDWR | libc::O_CLOEXEC,
+                    0o600 as libc::mode_t,
+                )
+            };
+            if fd >= 0 {
+                // Имя сразу удаляем -- объект доступен только через fd.
+                unsafe { libc::shm_unlink(cname.as_ptr()) };
+            }
+            fd
+        };
+        if fd < 0 {
+            return Err(os_error("memfd_create"));
+        }
+        Self::map(fd, len, true, None, false)
+    }
+
+    fn map(
+        fd: libc::c_int,
+        len: usize,
+        truncate: bool,
+        name: Option<CString>,
+        owns_name: bool,
+    ) -> Result<Self> {
+        let fail = |context: &'static str| {
+            let err = os_error(context);
+            unsafe { libc::close(fd) };
+            if let Some(name) = name.as_ref().filter(|_| owns_name) {
+                unsafe { libc::shm_unlink(name.as_ptr()) };
+            }
+            err
+        };
+
+        if truncate && unsafe { libc::ftruncate(fd, len as libc::off_t) } != 0 {
+            return Err(fail("ftruncate"));
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(fail("mmap"));
+        }
+
+        Ok(Segment {
+            fd,
+            ptr: ptr as *mut u8,
+            len,
+            name,
+            owns_name,
+        })
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+            libc::close(self.fd);
+            if let Some(name) = self.name.as_ref().filter(|_| self.owns_name) {
+                libc::shm_unlink(name.as_ptr());
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Mapping
+// ============================================================================
+
+/// Размер transparent huge page на x86_64 и aarch64 (4K-гранула).
+#[cfg(target_os = "linux")]
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+pub struct Mapping {
+    segment: Segment,
+    /// `create_large_pages` и ядро приняло `MADV_HUGEPAGE`.
+    large_pages: bool,
+}
+
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn small_pages(segment: Segment) -> Self {
+        Mapping {
+            segment,
+            large_pages: false,
+        }
+    }
+
+    /// См. `create_large_pages`.
+    #[cfg(target_os = "linux")]
+    fn huge_pages(name: &str, size: usize, security: Security) -> Result<Self> {
+        let segment = Segment::create(name, size.next_multiple_of(HUGE_PAGE_SIZE), security)?;
+        let advised = unsafe {
+            libc::madvise(
+                segment.ptr as *mut libc::c_void,
+                segment.len,
+                libc::MADV_HUGEPAGE,
+            )
+        };
+        Ok(Mapping {
+            segment,
+            large_pages: advised == 0,
+        })
+    }
+}
+
+impl PlatformMapping for Mapping {
+    fn create_sized(name: &str, size: usize) -> Result<Self> {
+        Segment::create(name, size, Security::Everyone).map(Mapping::small_pages)
+    }
+
+    fn create_secured(
+        name: &str,
+        size: usize,
+        large_pages: bool,
+        security: Security,
+    ) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        if large_pages {
+            return Mapping::huge_pages(name, size, security);
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = large_pages;
+        Segment::create(name, size, security).map(Mapping::small_pages)
+    }
+
+    fn open_sized(name: &str, size: usize) -> Result<Self> {
+        Segment::open(name, size).map(Mapping::small_pages)
+    }
+
+    /// Секция без имени (memfd на Linux): передаётся пиру только как fd.
+    fn create_anonymous() -> Result<Self> {
+        Segment::anonymous(shared_mapping_size()).map(Mapping::small_pages)
+    }
+
+    /// `handle` -- fd секции (например, полученный через SCM_RIGHTS).
+    fn from_handle(handle: isize, size: usize) -> Result<Self> {
+        let fd = libc::c_int::try_from(handle)
+            .map_err(|_| ShmError::InvalidConfig("section handle is not a file descriptor"))?;
+        Segment::adopt(fd, size, None).map(Mapping::small_pages)
+    }
+
+    /// Linux: shm-сегмент, округлённый до `HUGE_PAGE_SIZE`, с
+    /// `madvise(MADV_HUGEPAGE)` -- transparent huge pages для shmem
+    /// (действует при `shmem_enabled` = `advise` или `always`). Ядро без
+    /// THP отвергает совет, и сегмент остаётся на обычных страницах.
+    #[cfg(target_os = "linux")]
+    fn create_large_pages(name: &str, size: usize) -> Result<Self> {
+        Mapping::huge_pages(name, size, Security::Everyone)
+    }
+
+    fn large_pages(&self) -> bool {
+        self.large_pages
+    }
+
+    fn as_ptr(&self) -> *mut u8 {
+        self.segment.ptr
+    }
+
+    fn section_handle(&self) -> isize {
+        self.segment.fd as isize
+    }
+
+    fn set_owns_name(&mut self, owns: bool) {
+        self.segment.owns_name = owns;
+    }
+}
+
+// ============================================================================
+// EventHandle - futex-слово в shm
+// ============================================================================
+
+pub struct EventHandle {
+    segment: Segment,
+}
+
+unsafe impl Send for EventHandle {}
+unsafe impl Sync for EventHandle {}
+
+impl EventHandle {
+    fn word(&self) -> &AtomicU32 {
+        // SAFETY: сегмент отображён (минимум страница), выровнен на страницу
+        // и живёт не меньше self.
+        unsafe { &*(self.segment.ptr as *const AtomicU32) }
+    }
+}
+
+impl PlatformEvent for EventHandle {
+    fn create(name: &str) -> Result<Self> {
+        Self::create_secured(name, Security::Everyone)
+    }
+
+    fn create_secured(name: &str, security: Security) -> Result<Self> {
+        let event = EventHandle {
+            segment: Segment::create(name, EVENT_SEGMENT_SIZE, security)?,
+        };
+        // InitialState = FALSE (новый сегмент после ftruncate и так нулевой)
+        event.word().store(0, Ordering::Release);
+        Ok(event)
+    }
+
+    fn open(name: &str) -> Result<Self> {
+        Ok(EventHandle {
+            segment: Segment::open(name, EVENT_SEGMENT_SIZE)?,
+        })
+    }
+
+    /// Futex-слово в anonymous сегменте (memfd после ftruncate -- нули).
+    fn create_local() -> Result<Self> {
+        Ok(EventHandle {
+            segment: Segment::anonymous(EVENT_SEGMENT_SIZE)?,
+        })
+    }
+
+    fn set(&self) -> Result<()> {
+        self.word().store(1, Ordering::Release);
+        futex::wake(self.word());
+        Ok(())
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        wait_words(&[self.word()], timeout).map(|index| index.is_some())
+    }
+
+    /// Адрес futex-слова в этом процессе (значение для `wait_any`).
+    fn raw_handle(&self) -> isize {
+        self.segment.ptr as isize
+    }
+
+    fn set_owns_name(&mut self, owns: bool) {
+        self.segment.owns_name = owns;
+    }
+}
+
+// ============================================================================
+// Ожидание
+// ============================================================================
+
+fn wait_words(words: &[&AtomicU32], timeout: Option<Duration>) -> Result<Option<usize>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        for (index, word) in words.iter().enumerate() {
+            // swap забирает сигнал -- auto-reset, будим ровно одного.
+            if word.swap(0, Ordering::AcqRel) == 1 {
+                return Ok(Some(index));
+            }
+        }
+        let remaining = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                Some(deadline - now)
+            }
+            None => None,
+        };
+        // Пробуждения бывают ложными, а сигнал мог прийти между проверкой и
+        // засыпанием (futex тогда вернётся сразу) -- всегда перепроверяем.
+        futex::wait_any(words, remaining);
+    }
+}
+
+fn poll_sleep(timeout: Option<Duration>) {
+    std::thread::sleep(timeout.map_or(POLL_STEP, |t| t.min(POLL_STEP)));
+}
+
+#[cfg(target_os = "linux")]
+mod futex {
+    use std::ptr::null;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::time::Duration;
+
+    /// `futex_waitv` появился в Linux 5.16; на старых ядрах -- polling.
+    static WAITV_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+    /// FUTEX2_SIZE_U32 (без FUTEX_PRIVATE_FLAG -- futex разделяемый).
+    const FUTEX2_SIZE_U32: u32 = 0x02;
+
+    #[repr(C)]
+    struct FutexWaitv {
+        val: u64,
+        uaddr: u64,
+        flags: u32,
+        reserved: u32,
+    }
+
+    fn timespec(d: Duration) -> libc::timespec {
+        libc::timespec {
+            tv_sec: d.as_secs() as libc::time_t,
+            tv_nsec: d.subsec_nanos() as libc::c_long,
+        }
+    }
+
+    pub(super) fn wake(word: &AtomicU32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAKE,
+                i32::MAX,
+                null::<libc::timespec>(),
+            );
+        }
+    }
+
+    /// Засыпает, пока любое слово равно 0. Результат не важен: вызывающий
+    /// код всё равно перепроверяет слова.
+    pub(super) fn wait_any(words: &[&AtomicU32], timeout: Option<Duration>) {
+        if let [word] = words {
+            let ts = timeout.map(timespec);
+            let ts_ptr = ts.as_ref().map_or(null(), |t| t as *const libc::timespec);
+            unsafe {
+                libc::syscall(
+                    libc::SYS_futex,
+                    word.as_ptr(),
+                    libc::FUTEX_WAIT,
+                    0u32,
+                    ts_ptr,
+                );
+            }
+            return;
+        }
+
+        if WAITV_UNSUPPORTED.load(Ordering::Relaxed) {
+            super::poll_sleep(timeout);
+            return;
+        }
+
+        let waiters: Vec<FutexWaitv> = words
+            .iter()
+            .map(|word| FutexWaitv {
+                val: 0,
+                uaddr: word.as_ptr() as u64,
+                flags: FUTEX2_SIZE_U32,
+                reserved: 0,
+            })
+            .collect();
+
+        // futex_waitv принимает АБСОЛЮТНЫЙ таймаут по указанным часам.
+        let deadline = timeout.map(|t| {
+            let mut now = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+            let now = Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+            timespec(now + t)
+        });
+        let deadline_ptr = deadline
+            .as_ref()
+            .map_or(null(), |t| t as *const libc::timespec);
+
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_futex_waitv,
+                waiters.as_ptr(),
+                waiters.len() as libc::c_uint,
+                0u32,
+                deadline_ptr,
+                libc::CLOCK_MONOTONIC,
+            )
+        };
+        if rc < 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::ENOSYS) {
+            WAITV_UNSUPPORTED.store(true, Ordering::Relaxed);
+            super::poll_sleep(timeout);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod futex {
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    pub(super) fn wake(_word: &AtomicU32) {}
+
+    pub(super) fn wait_any(_words: &[&AtomicU32], timeout: Option<Duration>) {
+        super::poll_sleep(timeout);
+    }
+}
+
+// ============================================================================
+// Platform
+// ============================================================================
+
+pub(crate) struct Unix;
+
+impl Platform for Unix {
+    type Mapping = Mapping;
+    type Event = EventHandle;
+
+    fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+        if handles.is_empty() {
+            return Ok(None);
+        }
+        // SAFETY: единственный источник значений в крейте -- raw_handle()
+        // живых EventHandle, т.е. адреса отображённых futex-слов.
+        let words: Vec<&AtomicU32> = handles
+            .iter()
+            .map(|&handle| unsafe { &*(handle as *const AtomicU32) })
+            .collect();
+        wait_words(&words, timeout)
+    }
+
+    /// `kill(pid, 0)`: `ESRCH` -- процесса нет; всё остальное (включая
+    /// `EPERM` -- процесс чужой, но жив) консервативно считается «жив».
+    fn is_process_alive(pid: u32) -> bool {
+        if pid == 0 || pid > i32::MAX as u32 {
+            // 0 и «отрицательные» PID адресуют группы процессов, а не процесс.
+            return true;
+        }
+        if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 {
+            return true;
+        }
+        io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+    }
+
+    /// fd нельзя «вставить» в чужой процесс без Unix-сокета (SCM_RIGHTS),
+    /// которого у канала нет.
+    fn duplicate_handle_into(_handle: isize, _pid: u32) -> Result<isize> {
+        Err(ShmError::Unsupported("handle passing is only available on Windows"))
+    }
+
+    fn close_handle(handle: isize) {
+        unsafe { libc::close(handle as libc::c_int) };
+    }
+
+    fn close_remote_handle(_handle: isize, _pid: u32) {}
+
+    /// `CLOCK_MONOTONIC` -- общий для процессов (отсчёт от загрузки).
+    fn monotonic_ns() -> u64 {
+        let mut now = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+        now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64
+    }
+
+    /// Linux: файлы `/dev/shm` (туда `shm_open` кладёт объекты). Прочие
+    /// Unix перечислять shm-объекты не умеют -- пустой список.
+    fn list_sections() -> Vec<String> {
+        if !cfg!(target_os = "linux") {
+            return Vec::new();
+        }
+        let Ok(entries) = std::fs::read_dir("/dev/shm") else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+
+    fn is_not_found(error: &ShmError) -> bool {
+        matches!(error, ShmError::WindowsError { code, .. } if *code == libc::ENOENT as u32)
+    }
+
+    /// POSIX-имя живёт до `shm_unlink`, а не до закрытия последнего fd.
+    fn unlink(name: &str) {
+        if let Ok(cname) = shm_name(name) {
+            unsafe { libc::shm_unlink(cname.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_style_names_map_to_posix_names() {
+        assert_eq!(shm_name("Local\\CHAN").unwrap().to_str().unwrap(), "/CHAN");
+        assert_eq!(shm_name("Global\\A_B").unwrap().to_str().unwrap(), "/A_B");
+        assert_eq!(shm_name("a/b\\c").unwrap().to_str().unwrap(), "/a_b_c");
+    }
+
+    #[test]
+    fn creating_a_live_name_collides_instead_of_truncating() {
+        let name = format!("Local\\XSHM_EXCL_TEST_{}", std::process::id());
+        let created = Mapping::create_sized(&name, shared_mapping_size()).unwrap();
+        unsafe { *created.as_ptr().add(100) = 0x5A };
+
+        let second = Mapping::create_sized(&name, shared_mapping_size());
+        assert!(matches!(
+            second,
+            Err(ShmError::WindowsError {
+                code: STATUS_OBJECT_NAME_COLLISION,
+                ..
+            })
+        ));
+        // Первый сегмент не пересоздан и не обрезан.
+        unsafe { assert_eq!(*created.as_ptr().add(100), 0x5A) };
+    }
+}
@@ -0,0 +1,863 @@
+//! Windows backend - прямые вызовы NT API через ntdll.dll
+//!
+//! Использует статическую линковку с ntdll.dll.
+//! Никаких внешних зависимостей, никакого TLS.
+
+use std::ptr::{null, null_mut};
+use std::time::Duration;
+
+use super::{Platform, PlatformEvent, PlatformMapping};
+use crate::error::{Result, ShmError};
+use crate::layout::shared_mapping_size;
+use crate::ntapi::{
+    duration_to_nt_timeout,
+    // Functions
+    NtClose,
+    NtCreateEvent,
+    NtCreateSection,
+    NtDuplicateObject,
+    NtMapViewOfSection,
+    // Helpers
+    NtName,
+    NtOpenDirectoryObject,
+    NtOpenEvent,
+    NtOpenProcess,
+    NtOpenSection,
+    NtQueryDirectoryObject,
+    NtQueryPerformanceCounter,
+    NtSetEvent,
+    NtUnmapViewOfSection,
+    NtWaitForMultipleObjects,
+    NtWaitForSingleObject,
+    NullDaclSecurityDescriptor,
+    RtlAdjustPrivilege,
+    session_local_directory,
+    // Types
+    CLIENT_ID,
+    DIRECTORY_QUERY,
+    DUPLICATE_CLOSE_SOURCE,
+    DUPLICATE_SAME_ACCESS,
+    DUPLICATE_SAME_ATTRIBUTES,
+    EVENT_ALL_ACCESS,
+    HANDLE,
+    LARGE_INTEGER,
+    LARGE_PAGE_SIZE,
+    MEM_LARGE_PAGES,
+    NTSTATUS,
+    NT_CURRENT_PROCESS,
+    OBJECT_ATTRIBUTES,
+    OBJECT_DIRECTORY_INFORMATION,
+    OBJ_CASE_INSENSITIVE,
+    PAGE_READWRITE,
+    PROCESS_DUP_HANDLE,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+    PROCESS_SYNCHRONIZE,
+    PVOID,
+    SECTION_ALL_ACCESS,
+    SEC_COMMIT,
+    SEC_LARGE_PAGES,
+    SE_CREATE_GLOBAL_PRIVILEGE,
+    SE_LOCK_MEMORY_PRIVILEGE,
+    // Constants
+    STATUS_ACCESS_DENIED,
+    STATUS_MORE_ENTRIES,
+    STATUS_OBJECT_NAME_NOT_FOUND,
+    STATUS_OBJECT_PATH_NOT_FOUND,
+    STATUS_PRIVILEGE_NOT_HELD,
+    STATUS_SECTION_TOO_BIG,
+    STATUS_SUCCESS,
+    STATUS_TIMEOUT,
+    STATUS_WAIT_0,
+    SYNCHRONIZATION_EVENT,
+    ULONG,
+    UNICODE_STRING,
+    VIEW_UNMAP,
+    WAIT_ANY,
+};
+use crate::security::Security;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+const INVALID_HANDLE_VALUE: isize = -1;
+
+// ============================================================================
+// Handle wrapper
+// ============================================================================
+
+#[derive(Debug)]
+pub struct Handle(HANDLE);
+
+impl Handle {
+    pub fn raw(&self) -> HANDLE {
+        self.0
+    }
+
+    pub fn as_isize(&self) -> isize {
+        self.0 as isize
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        if !self.0.is_null() && self.0 as isize != INVALID_HANDLE_VALUE {
+            unsafe {
+                let _ = NtClose(self.0);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+/// SECURITY_DESCRIPTOR для OBJECT_ATTRIBUTES: NULL DACL у `Everyone`,
+/// NULL (DACL по умолчанию из токена процесса) у `Owner`. `sd` должен
+/// жить до вызова NtCreate*.
+fn security_descriptor(sd: &mut NullDaclSecurityDescriptor, security: Security) -> PVOID {
+    match security {
+        Security::Everyone => sd.as_ptr(),
+        Security::Owner => null_mut(),
+    }
+}
+
+fn status_to_error(status: NTSTATUS, context: &'static str) -> ShmError {
+    match status {
+        STATUS_ACCESS_DENIED | STATUS_PRIVILEGE_NOT_HELD => ShmError::PrivilegeRequired(context),
+        _ => ShmError::WindowsError {
+            code: status as u32,
+            context,
+        },
+    }
+}
+
+// ============================================================================
+// EventHandle - NT Event через ntdll.dll
+// ============================================================================
+
+pub struct EventHandle {
+    handle: Handle,
+    _name: String,
+}
+
+unsafe impl Send for EventHandle {}
+unsafe impl Sync for EventHandle {}
+
+impl PlatformEvent for EventHandle {
+    /// Создание события через NtCreateEvent с NULL DACL
+    fn create(name: &str) -> Result<Self> {
+        Self::create_secured(name, Security::Everyone)
+    }
+
+    fn create_secured(name: &str, security: Security) -> Result<Self> {
+        enable_create_global_privilege(name);
+        let mut nt_name = NtName::new(name)?;
+        let mut sd = NullDaclSecurityDescriptor::new();
+        let mut obj_attr = OBJECT_ATTRIBUTES::new(
+            nt_name.as_ptr(),
+            OBJ_CASE_INSENSITIVE,
+            security_descriptor(&mut sd, security),
+        );
+
+        let mut handle: HANDLE = null_mut();
+
+        let status = unsafe {
+            NtCreateEvent(
+                &mut handle,
+                EVENT_ALL_ACCESS,
+                &mut obj_attr,
+                SYNCHRONIZATION_EVENT,
+                0, // InitialState = FALSE
+            )
+        };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtCreateEvent"));
+        }
+
+        Ok(EventHandle {
+            handle: Handle(handle),
+            _name: name.to_owned(),
+        })
+    }
+
+    /// Безымянное событие: OBJECT_ATTRIBUTES без ObjectName.
+    fn create_local() -> Result<Self> {
+        let mut obj_attr = OBJECT_ATTRIBUTES::new(null_mut(), 0, null_mut());
+        let mut handle: HANDLE = null_mut();
+
+        let status = unsafe {
+            NtCreateEvent(
+                &mut handle,
+                EVENT_ALL_ACCESS,
+                &mut obj_attr,
+                SYNCHRONIZATION_EVENT,
+                0, // InitialState = FALSE
+            )
+        };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtCreateEvent (local)"));
+        }
+
+        Ok(EventHandle {
+            handle: Handle(handle),
+            _name: String::new(),
+        })
+    }
+
+    /// Открытие события через NtOpenEvent
+    fn open(name: &str) -> Result<Self> {
+        let mut nt_name = NtName::new(name)?;
+        let mut obj_attr =
+            OBJECT_ATTRIBUTES::new(nt_name.as_ptr(), OBJ_CASE_INSENSITIVE, null_mut());
+
+        let mut handle: HANDLE = null_mut();
+
+        let status = unsafe { NtOpenEvent(&mut handle, EVENT_ALL_ACCESS, &mut obj_attr) };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtOpenEvent"));
+        }
+
+        Ok(EventHandle {
+            handle: Handle(handle),
+            _name: name.to_owned(),
+        })
+    }
+
+    /// Сигнализация через NtSetEvent
+    fn set(&self) -> Result<()> {
+        let mut previous_state: i32 = 0;
+        let status = unsafe { NtSetEvent(self.handle.raw(), &mut previous_state) };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtSetEvent"));
+        }
+        Ok(())
+    }
+
+    /// Ожидание через NtWaitForSingleObject
+    fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        let timeout_value: i64 = match timeout {
+            Some(d) => duration_to_nt_timeout(d),
+            None => 0,
+        };
+
+        let timeout_ptr = if timeout.is_some() {
+            &timeout_value as *const i64
+        } else {
+            null()
+        };
+
+        let status = unsafe {
+            NtWaitForSingleObject(
+                self.handle.raw(),
+                0, // Alertable = FALSE
+                timeout_ptr,
+            )
+        };
+
+        match status {
+            STATUS_SUCCESS => Ok(true),
+            STATUS_TIMEOUT => Ok(false),
+            _ => Err(status_to_error(status, "NtWaitForSingleObject")),
+        }
+    }
+
+    fn raw_handle(&self) -> isize {
+        self.handle.as_isize()
+    }
+}
+
+// ============================================================================
+// Mapping - NT Section через ntdll.dll
+// ============================================================================
+
+#[derive(Debug)]
+pub struct Mapping {
+    _handle: Handle,
+    view: *mut u8,
+    _size: usize,
+    _name: String,
+    large_pages: bool,
+}
+
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    /// Внутренний метод создания секции (общая логика для named и anonymous)
+    fn create_internal(
+        object_name: *mut UNICODE_STRING,
+        name_for_storage: String,
+        size: usize,
+        large_pages: bool,
+        security: Security,
+    ) -> Result<Self> {
+        let mut sd = NullDaclSecurityDescriptor::new();
+        let mut obj_attr = OBJECT_ATTRIBUTES::new(
+            object_name,
+            OBJ_CASE_INSENSITIVE,
+            security_descriptor(&mut sd, security),
+        );
+
+        let mut section_handle: HANDLE = null_mut();
+        let mut max_size = LARGE_INTEGER {
+            QuadPart: size as i64,
+        };
+
+        let status = unsafe {
+            NtCreateSection(
+                &mut section_handle,
+                SECTION_ALL_ACCESS,
+                &mut obj_attr,
+                &mut max_size,
+                PAGE_READWRITE,
+                if large_pages {
+                    SEC_COMMIT | SEC_LARGE_PAGES
+                } else {
+                    SEC_COMMIT
+                },
+                null_mut(), // FileHandle = NULL
+            )
+        };
+
+        if status != STATUS_SUCCESS {
+            let context = if object_name.is_null() {
+                "NtCreateSection (anonymous)"
+            } else {
+                "NtCreateSection"
+            };
+            return Err(status_to_error(status, context));
+        }
+
+        // Проверка: Handle не должен быть NULL
+        if section_handle.is_null() {
+            return Err(status_to_error(
+                0xC0000008u32 as i32,
+                "NtCreateSection returned NULL handle",
+            ));
+        }
+
+        let handle = Handle(section_handle);
+
+        // Map view
+        let mut base_address: PVOID = null_mut();
+        let mut view_size: usize = 0;
+
+        let status = unsafe {
+            NtMapViewOfSection(
+                handle.raw(),
+                NT_CURRENT_PROCESS,
+                &mut base_address,
+                0,
+                0,
+                null_mut(), // SectionOffset
+                &mut view_size,
+                VIEW_UNMAP,
+                if large_pages { MEM_LARGE_PAGES } else { 0 },
+                PAGE_READWRITE,
+            )
+        };
+
+        if status != STATUS_SUCCESS {
+            let context = if object_name.is_null() {
+                "NtMapViewOfSection (anonymous)"
+            } else {
+                "NtMapViewOfSection"
+            };
+            return Err(status_to_error(status, context));
+        }
+
+        Ok(Mapping {
+            _handle: handle,
+            view: base_address as *mut u8,
+            _size: size,
+            _name: name_for_storage,
+            large_pages,
+        })
+    }
+
+    /// Отображает секцию целиком по открытому handle'у; секция меньше
+    /// `size` -- ошибка (handle закрывается вместе с отображением).
+    fn map_existing(handle: Handle, name: String, size: usize) -> Result<Self> {
+        // Map view
+        let mut base_address: PVOID = null_mut();
+        let mut view_size: usize = 0;
+
+        let status = unsafe {
+            NtMapViewOfSection(
+                handle.raw(),
+                NT_CURRENT_PROCESS,
+                &mut base_address,
+                0,
+                0,
+                null_mut(),
+                &mut view_size,
+                VIEW_UNMAP,
+                0,
+                PAGE_READWRITE,
+            )
+        };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtMapViewOfSection"));
+        }
+
+        // Drop размапит view и закроет handle, если размер не подошёл.
+        let mapping = Mapping {
+            _handle: handle,
+            view: base_address as *mut u8,
+            _size: view_size,
+            _name: name,
+            large_pages: false,
+        };
+        if view_size < size {
+            return Err(status_to_error(
+                STATUS_SECTION_TOO_BIG,
+                "NtMapViewOfSection (section smaller than requested)",
+            ));
+        }
+        Ok(mapping)
+    }
+}
+
+/// Включает SeLockMemoryPrivilege в токене процесса: без неё
+/// NtCreateSection с SEC_LARGE_PAGES возвращает STATUS_PRIVILEGE_NOT_HELD.
+/// Привилегию выдаёт политика "Lock pages in memory"; включить можно
+/// только уже выданную.
+fn enable_lock_memory_privilege() -> Result<()> {
+    let mut was_enabled = 0;
+    let status = unsafe { RtlAdjustPrivilege(SE_LOCK_MEMORY_PRIVILEGE, 1, 0, &mut was_enabled) };
+    if status != STATUS_SUCCESS {
+        return Err(status_to_error(
+            status,
+            "RtlAdjustPrivilege (SeLockMemoryPrivilege)",
+        ));
+    }
+    Ok(())
+}
+
+/// Перед созданием объекта `Global\` включает SeCreateGlobalPrivilege:
+/// из сессии != 0 без неё создание в `\BaseNamedObjects` отклоняется.
+/// Привилегия не выдана -- ошибку вернёт само создание объекта.
+fn enable_create_global_privilege(name: &str) {
+    if !name.starts_with("Global\\") {
+        return;
+    }
+    let mut was_enabled = 0;
+    unsafe { RtlAdjustPrivilege(SE_CREATE_GLOBAL_PRIVILEGE, 1, 0, &mut was_enabled) };
+}
+
+impl PlatformMapping for Mapping {
+    /// Создание секции через NtCreateSection с NULL DACL
+    fn create_sized(name: &str, size: usize) -> Result<Self> {
+        Self::create_secured(name, size, false, Security::Everyone)
+    }
+
+    /// SEC_LARGE_PAGES-секция, размер округлён вверх до `LARGE_PAGE_SIZE`.
+    /// Без привилегии или при нехватке непрерывной физической памяти --
+    /// обычная секция.
+    fn create_large_pages(name: &str, size: usize) -> Result<Self> {
+        Self::create_secured(name, size, true, Security::Everyone)
+    }
+
+    fn create_secured(
+        name: &str,
+        size: usize,
+        large_pages: bool,
+        security: Security,
+    ) -> Result<Self> {
+        enable_create_global_privilege(name);
+        let mut nt_name = NtName::new(name)?;
+        if large_pages && enable_lock_memory_privilege().is_ok() {
+            let rounded = size.next_multiple_of(LARGE_PAGE_SIZE);
+            if let Ok(mapping) =
+                Self::create_internal(nt_name.as_ptr(), name.to_owned(), rounded, true, security)
+            {
+                return Ok(mapping);
+            }
+        }
+        Self::create_internal(nt_name.as_ptr(), name.to_owned(), size, false, security)
+    }
+
+    fn large_pages(&self) -> bool {
+        self.large_pages
+    }
+
+    /// Создание anonymous секции без имени (только через handle)
+    ///
+    /// Anonymous section не имеет имени в глобальном namespace и доступна
+    /// только через handle. Идеально для передачи handle в kernel driver.
+    ///
+    /// **ВАЖНО:** Это НЕ то же самое, что `create("")` (пустая строка).
+    ///
+    /// **Технические детали:**
+    /// - `create("")` → `NtName::new("")` → `to_nt_path("")` → `"\\BaseNamedObjects\\"`
+    ///   → создается `UNICODE_STRING` с валидным указателем → **именованная секция**
+    /// - `create_anonymous()` → передает `null_mut()` в `OBJECT_ATTRIBUTES.ObjectName`
+    ///   → Windows NT API видит `ObjectName = NULL` → **anonymous section**
+    ///
+    /// Windows NT API интерпретирует только `ObjectName = NULL` в `OBJECT_ATTRIBUTES`
+    /// как anonymous (unnamed) объект. Пустой `UNICODE_STRING` (даже с `Length = 0`)
+    /// все равно является указателем на структуру, а не NULL, поэтому создаст
+    /// именованную секцию (которая, вероятно, завершится ошибкой из-за невалидного имени).
+    fn create_anonymous() -> Result<Self> {
+        Self::create_internal(
+            null_mut(),
+            String::new(),
+            shared_mapping_size(),
+            false,
+            Security::Everyone,
+        )
+    }
+
+    /// Открытие секции через NtOpenSection. Отображается секция целиком;
+    /// если она меньше `size`, открытие не удаётся.
+    fn open_sized(name: &str, size: usize) -> Result<Self> {
+        let mut nt_name = NtName::new(name)?;
+        let mut obj_attr =
+            OBJECT_ATTRIBUTES::new(nt_name.as_ptr(), OBJ_CASE_INSENSITIVE, null_mut());
+
+        let mut section_handle: HANDLE = null_mut();
+
+        let status =
+            unsafe { NtOpenSection(&mut section_handle, SECTION_ALL_ACCESS, &mut obj_attr) };
+
+        if status != STATUS_SUCCESS {
+            return Err(status_to_error(status, "NtOpenSection"));
+        }
+
+        Self::map_existing(Handle(section_handle), name.to_owned(), size)
+    }
+
+    fn from_handle(handle: isize, size: usize) -> Result<Self> {
+        Self::map_existing(Handle(handle as HANDLE), String::new(), size)
+    }
+
+    fn as_ptr(&self) -> *mut u8 {
+        self.view
+    }
+
+    /// Получить raw HANDLE секции (для передачи в kernel driver)
+    fn section_handle(&self) -> isize {
+        self._handle.as_isize()
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if !self.view.is_null() {
+            unsafe {
+                let _ = NtUnmapViewOfSection(NT_CURRENT_PROCESS, self.view as PVOID);
+            }
+            self.view = null_mut();
+        }
+    }
+}
+
+// ============================================================================
+// Platform
+// ============================================================================
+
+pub(crate) struct Windows;
+
+impl Platform for Windows {
+    type Mapping = Mapping;
+    type Event = EventHandle;
+
+    fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+        wait_any(handles, timeout)
+    }
+
+    fn is_process_alive(pid: u32) -> bool {
+        is_process_alive(pid)
+    }
+
+    fn duplicate_handle_into(handle: isize, pid: u32) -> Result<isize> {
+        duplicate_handle_into(handle, pid)
+    }
+
+    fn close_handle(handle: isize) {
+        drop(Handle(handle as HANDLE));
+    }
+
+    fn close_remote_handle(handle: isize, pid: u32) {
+        close_remote_handle(handle, pid)
+    }
+
+    /// QPC (`NtQueryPerformanceCounter`) -- общий для процессов.
+    fn monotonic_ns() -> u64 {
+        let mut counter = 0i64;
+        let mut frequency = 0i64;
+        unsafe { NtQueryPerformanceCounter(&mut counter, &mut frequency) };
+        if frequency <= 0 {
+            return 0;
+        }
+        (counter as u128 * 1_000_000_000 / frequency as u128) as u64
+    }
+
+    /// Секции session-local каталога (`Local\X`) и, вне сессии 0,
+    /// глобального (`Global\X`); в сессии 0 это один и тот же каталог.
+    fn list_sections() -> Vec<String> {
+        let local = session_local_directory();
+        let mut names = directory_sections(&local, "Local\\");
+        if local != GLOBAL_DIRECTORY {
+            names.extend(directory_sections(GLOBAL_DIRECTORY, "Global\\"));
+        }
+        names
+    }
+
+    /// Нет объекта (`Local\X`) или каталога (`Global\X` без сессии).
+    fn is_not_found(error: &ShmError) -> bool {
+        matches!(
+            error,
+            ShmError::WindowsError { code, .. }
+                if *code == STATUS_OBJECT_NAME_NOT_FOUND as u32
+                    || *code == STATUS_OBJECT_PATH_NOT_FOUND as u32
+        )
+    }
+}
+
+// ============================================================================
+// list_sections - NtQueryDirectoryObject
+// ============================================================================
+
+const GLOBAL_DIRECTORY: &str = "\\BaseNamedObjects";
+
+/// Буфер одной записи каталога: заголовок и обе строки (имя объекта не
+/// длиннее `MAX_NT_NAME_UNITS`).
+const DIRECTORY_ENTRY_BUFFER: usize = 64 * 1024;
+
+fn unicode_to_string(value: &UNICODE_STRING) -> String {
+    if value.Buffer.is_null() {
+        return String::new();
+    }
+    // SAFETY: строка из буфера NtQueryDirectoryObject, Length -- в байтах.
+    let units = unsafe { std::slice::from_raw_parts(value.Buffer, value.Length as usize / 2) };
+    String::from_utf16_lossy(units)
+}
+
+/// Секции каталога `directory` с префиксом `prefix` перед именем.
+fn directory_sections(directory: &str, prefix: &str) -> Vec<String> {
+    let Ok(mut nt_name) = NtName::new(directory) else {
+        return Vec::new();
+    };
+    let mut obj_attr = OBJECT_ATTRIBUTES::new(nt_name.as_ptr(), OBJ_CASE_INSENSITIVE, null_mut());
+    let mut raw_handle: HANDLE = null_mut();
+    let status = unsafe { NtOpenDirectoryObject(&mut raw_handle, DIRECTORY_QUERY, &mut obj_attr) };
+    if status != STATUS_SUCCESS {
+        return Vec::new();
+    }
+    let directory = Handle(raw_handle);
+
+    // u64 -- выравнивание под указатели в OBJECT_DIRECTORY_INFORMATION.
+    let mut buffer = vec![0u64; DIRECTORY_ENTRY_BUFFER / 8];
+    let mut context: ULONG = 0;
+    let mut names = Vec::new();
+    let mut restart = 1;
+    loop {
+        let mut returned: ULONG = 0;
+        let status = unsafe {
+            NtQueryDirectoryObject(
+                directory.raw(),
+                buffer.as_mut_ptr() as PVOID,
+                DIRECTORY_ENTRY_BUFFER as ULONG,
+                1,
+                restart,
+                &mut context,
+                &mut returned,
+            )
+        };
+        // STATUS_NO_MORE_ENTRIES -- конец каталога; прочие ошибки тоже
+        // обрывают перечисление: вернуть, что успели.
+        if status != STATUS_SUCCESS && status != STATUS_MORE_ENTRIES {
+            break;
+        }
+        restart = 0;
+        // SAFETY: при успехе в начале буфера -- одна запись.
+        let entry = unsafe { &*(buffer.as_ptr() as *const OBJECT_DIRECTORY_INFORMATION) };
+        if unicode_to_string(&entry.TypeName) == "Section" {
+            names.push(format!("{prefix}{}", unicode_to_string(&entry.Name)));
+        }
+    }
+    names
+}
+
+// ============================================================================
+// wait_any - NtWaitForMultipleObjects
+// ============================================================================
+
+fn wait_any(handles: &[isize], timeout: Option<Duration>) -> Result<Option<usize>> {
+    if handles.is_empty() {
+        return Ok(None);
+    }
+
+    let timeout_value: i64 = match timeout {
+        Some(d) => duration_to_nt_timeout(d),
+        None => 0,
+    };
+
+    let timeout_ptr = if timeout.is_some() {
+        &timeout_value as *const i64
+    } else {
+        null()
+    };
+
+    let status = unsafe {
+        NtWaitForMultipleObjects(
+            handles.len() as u32,
+            handles.as_ptr() as *const HANDLE,
+            WAIT_ANY,
+            0, // Alertable = FALSE
+            timeout_ptr,
+        )
+    };
+
+    // STATUS_TIMEOUT (0x102) проверяем ДО диапазона валидных индексов: это
+    // значение >= 0 и по чистой случайности совпало бы с индексом 258,
+    // если бы handles.len() когда-нибудь превысил этот порог. Сейчас это
+    // не достижимо (максимум 63 хендла: MAX_MULTI_CLIENTS = 31 по два плюс
+    // токен остановки), но порядок веток не должен полагаться на этот
+    // внешний инвариант.
+    match status {
+        STATUS_TIMEOUT => Ok(None),
+        s if s >= 0 && (s as usize) < handles.len() => Ok(Some(s as usize)),
+        _ => Err(status_to_error(status, "NtWaitForMultipleObjects")),
+    }
+}
+
+// ============================================================================
+// is_process_alive - liveness-проверка процесса по PID (NtOpenProcess)
+// ============================================================================
+
+/// Проверяет, жив ли процесс с данным PID.
+///
+/// Используется multi-client сервером для liveness-детекции connected-слотов,
+/// чей клиент мог упасть ПОСЛЕ завершения handshake, не освободив claim (в
+/// этом случае никаких событий от мёртвого процесса не придёт, и слот иначе
+/// был бы потерян навсегда — см. `RESERVED_OWNER_PID_INDEX`).
+///
+/// Консервативна по конструкции: при любой двусмысленности (PID уже
+/// переиспользован под другой процесс, недостаточно прав, иная ошибка NT)
+/// возвращает `true` ("жив") — чтобы не отключить силой ещё легитимно
+/// работающего клиента. Возвращает `false` ТОЛЬКО при однозначном
+/// подтверждении: handle открыт и находится в сигнальном состоянии
+/// (WaitForSingleObject с нулевым таймаутом вернул STATUS_WAIT_0 — процесс
+/// завершился).
+fn is_process_alive(pid: u32) -> bool {
+    if pid == 0 {
+        return true; // 0 не бывает PID пользовательского процесса
+    }
+
+    let mut client_id = CLIENT_ID {
+        UniqueProcess: pid as usize as HANDLE,
+        UniqueThread: null_mut(),
+    };
+    // ObjectName = NULL: процессы не именованные объекты BaseNamedObjects,
+    // идентифицируются исключительно через ClientId.
+    let mut obj_attr = OBJECT_ATTRIBUTES::new(null_mut(), 0, null_mut());
+    let mut raw_handle: HANDLE = null_mut();
+
+    let open_status = unsafe {
+        NtOpenProcess(
+            &mut raw_handle,
+            PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_SYNCHRONIZE,
+            &mut obj_attr,
+            &mut client_id,
+        )
+    };
+
+    if open_status != STATUS_SUCCESS {
+        // Не удалось открыть — PID переиспользован, отказано в доступе или
+        // иная ошибка. Ни один из этих случаев не является подтверждением
+        // смерти процесса-владельца claim, поэтому НЕ считаем его мёртвым.
+        return true;
+    }
+
+    // RAII: handle закроется через NtClose при выходе из функции.
+    let handle = Handle(raw_handle);
+
+    let zero_timeout: i64 = 0; // мгновенный опрос, не блокируем worker
+    let wait_status = unsafe { NtWaitForSingleObject(handle.raw(), 0, &zero_timeout) };
+
+    match wait_status {
+        // Объект-процесс сигнален => процесс завершился.
+        STATUS_WAIT_0 => false,
+        // Таймаут (объект не сигнален) => процесс всё ещё выполняется.
+        STATUS_TIMEOUT => true,
+        // Любой иной статус — двусмысленность, консервативно трактуем как "жив".
+        _ => true,
+    }
+}
+
+// ============================================================================
+// duplicate_handle_into - передача handle'а в процесс пира (NtDuplicateObject)
+// ============================================================================
+
+/// Открывает процесс `pid` с правом дублирования handle'ов в него/из него.
+fn open_process_for_dup(pid: u32) -> Result<Handle> {
+    let mut client_id = CLIENT_ID {
+        UniqueProcess: pid as usize as HANDLE,
+        UniqueThread: null_mut(),
+    };
+    let mut obj_attr = OBJECT_ATTRIBUTES::new(null_mut(), 0, null_mut());
+    let mut raw_process: HANDLE = null_mut();
+
+    let status = unsafe {
+        NtOpenProcess(
+            &mut raw_process,
+            PROCESS_DUP_HANDLE,
+            &mut obj_attr,
+            &mut client_id,
+        )
+    };
+    if status != STATUS_SUCCESS {
+        return Err(status_to_error(status, "NtOpenProcess(PROCESS_DUP_HANDLE)"));
+    }
+    Ok(Handle(raw_process))
+}
+
+/// Дублирует `handle` текущего процесса в процесс `pid` с теми же правами и
+/// атрибутами. Возвращённое значение валидно только в целевом процессе;
+/// закрыть его там -- ответственность получателя.
+fn duplicate_handle_into(handle: isize, pid: u32) -> Result<isize> {
+    let process = open_process_for_dup(pid)?;
+    let mut target: HANDLE = null_mut();
+    let status = unsafe {
+        NtDuplicateObject(
+            NT_CURRENT_PROCESS,
+            handle as HANDLE,
+            process.raw(),
+            &mut target,
+            0,
+            0,
+            DUPLICATE_SAME_ACCESS | DUPLICATE_SAME_ATTRIBUTES,
+        )
+    };
+    if status != STATUS_SUCCESS {
+        return Err(status_to_error(status, "NtDuplicateObject"));
+    }
+    Ok(target as isize)
+}
+
+/// Закрывает handle в процессе `pid` (DUPLICATE_CLOSE_SOURCE без целевого
+/// процесса). Best-effort: пир мог уже завершиться.
+fn close_remote_handle(handle: isize, pid: u32) {
+    if let Ok(process) = open_process_for_dup(pid) {
+        unsafe {
+            let _ = NtDuplicateObject(
+                process.raw(),
+                handle as HANDLE,
+                null_mut(),
+                null_mut(),
+                0,
+                0,
+                DUPLICATE_CLOSE_SOURCE,
+            );
+        }
+    }
+}
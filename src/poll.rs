@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::platform;
+
+/// Итог `SharedServer::poll_events` / `SharedClient::poll_events`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PollResult {
+    /// Во входящем кольце есть кадр (или сработало событие данных).
+    DataAvailable,
+    /// Пир освободил место в исходящем кольце.
+    SpaceAvailable,
+    /// Пир отключился или его процесс умер: ответа ждать не от кого.
+    PeerDisconnected,
+    /// За `timeout` ничего не произошло.
+    Timeout,
+}
+
+/// Общая часть `poll_events` сервера и клиента. `handles` -- события
+/// disconnect, данных входящего и места исходящего направления (`None` у
+/// канала без событий: только проверка без ожидания); `has_data` --
+/// во входящем кольце есть кадр; `peer_alive` -- `is_peer_alive` стороны.
+pub(crate) fn poll_events(
+    handles: Option<[isize; 3]>,
+    timeout: Option<Duration>,
+    has_data: impl Fn() -> bool,
+    peer_alive: impl Fn() -> bool,
+) -> Result<PollResult> {
+    if has_data() {
+        return Ok(PollResult::DataAvailable);
+    }
+    // Событие disconnect мог забрать кто-то другой (auto-reset); уход пира
+    // виден и по handshake-состоянию и процессу.
+    if !peer_alive() {
+        return Ok(PollResult::PeerDisconnected);
+    }
+    let Some(handles) = handles else {
+        return Ok(PollResult::Timeout);
+    };
+    Ok(match platform::wait_any(&handles, timeout)? {
+        // Кадры, записанные пиром перед уходом, отдаются первыми: уход
+        // увидит следующий опрос по handshake-состоянию.
+        Some(0) if has_data() => PollResult::DataAvailable,
+        Some(0) => PollResult::PeerDisconnected,
+        Some(1) => PollResult::DataAvailable,
+        Some(_) => PollResult::SpaceAvailable,
+        None if peer_alive() => PollResult::Timeout,
+        None => PollResult::PeerDisconnected,
+    })
+}
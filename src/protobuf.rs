@@ -0,0 +1,217 @@
+//! Protobuf-сообщения (prost) поверх каналов xshm (feature `prost`).
+//!
+//! Кольцо уже разделяет сообщения, поэтому payload -- ровно одно
+//! закодированное `prost::Message`, без varint-префикса длины.
+//!
+//! [`ProstChannel::establish`] при подключении обменивается со пиром хешем
+//! схемы, чтобы расхождение `.proto` ловилось сразу, а не мусором при
+//! разборе. Первый кадр каждой стороны -- 8 байт: `"XPB1"` и CRC-32
+//! (IEEE, как `crc32()` из zlib) дескриптора схемы little-endian; C++-пир
+//! отправляет и проверяет такой же кадр. Хеш считается по
+//! сериализованному `FileDescriptorSet` (`prost-build`
+//! `file_descriptor_set_path`, `protoc --descriptor_set_out`), см.
+//! [`descriptor_hash`].
+//!
+//! ```ignore
+//! let hash = xshm::protobuf::descriptor_hash(include_bytes!(concat!(env!("OUT_DIR"), "/api.bin")));
+//! let mut channel = ProstChannel::establish(client, hash, Duration::from_secs(1))?;
+//! channel.send(&api::Ping { id: 1 })?;
+//! let pong: api::Pong = channel.receive()?;
+//! ```
+
+use std::time::{Duration, Instant};
+
+use prost::Message;
+use xshm_core::checksum::Crc32;
+
+use crate::codec::{Codec, ReceiveEndpoint, SendEndpoint};
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::{Result, ShmError};
+
+/// Сигнатура кадра обмена хешем схемы.
+pub const SCHEMA_FRAME_MAGIC: [u8; 4] = *b"XPB1";
+const SCHEMA_FRAME_SIZE: usize = 8;
+
+/// CRC-32 сериализованного дескриптора схемы.
+pub fn descriptor_hash(descriptor: &[u8]) -> u32 {
+    Crc32::new().update(descriptor).finish()
+}
+
+/// [`Codec`] для `prost::Message` (для `TypedSender`/`TypedReceiver`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Prost;
+
+impl<M: Message + Default> Codec<M> for Prost {
+    fn encode(&self, value: &M) -> Result<Vec<u8>> {
+        Ok(value.encode_to_vec())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<M> {
+        M::decode(bytes).map_err(|err| ShmError::DecodeFailed(err.to_string()))
+    }
+}
+
+/// Канал protobuf-сообщений с проверенной при подключении схемой.
+pub struct ProstChannel<E> {
+    endpoint: E,
+    schema_hash: u32,
+    buffer: Vec<u8>,
+}
+
+impl<E: SendEndpoint + ReceiveEndpoint> ProstChannel<E> {
+    /// Отправляет свой хеш схемы и ждёт хеш пира (не дольше `timeout`).
+    /// Оба вызова должны прийти до обмена сообщениями: первый кадр в
+    /// каждом направлении -- кадр схемы. Несовпадение --
+    /// `ShmError::SchemaMismatch`.
+    pub fn establish(endpoint: E, schema_hash: u32, timeout: Duration) -> Result<Self> {
+        let mut frame = [0u8; SCHEMA_FRAME_SIZE];
+        frame[..4].copy_from_slice(&SCHEMA_FRAME_MAGIC);
+        frame[4..].copy_from_slice(&schema_hash.to_le_bytes());
+        endpoint.send_payload(&frame)?;
+
+        let mut channel = Self {
+            endpoint,
+            schema_hash,
+            buffer: Vec::with_capacity(MAX_MESSAGE_SIZE),
+        };
+        let deadline = Instant::now() + timeout;
+        let len = loop {
+            match channel.endpoint.receive_payload(&mut channel.buffer) {
+                Ok(len) => break len,
+                Err(ShmError::QueueEmpty) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(ShmError::Timeout);
+                    }
+                    channel.endpoint.poll_payload(Some(left))?;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        let peer = &channel.buffer[..len];
+        if len != SCHEMA_FRAME_SIZE || peer[..4] != SCHEMA_FRAME_MAGIC {
+            return Err(ShmError::HandshakeFailed);
+        }
+        let remote = u32::from_le_bytes(peer[4..].try_into().unwrap());
+        if remote != schema_hash {
+            return Err(ShmError::SchemaMismatch {
+                local: schema_hash,
+                remote,
+            });
+        }
+        Ok(channel)
+    }
+
+    pub fn send<M: Message>(&self, message: &M) -> Result<()> {
+        let len = message.encoded_len();
+        if len > MAX_MESSAGE_SIZE {
+            return Err(ShmError::MessageTooLarge);
+        }
+        self.endpoint.send_payload(&message.encode_to_vec())
+    }
+
+    /// Следующее сообщение; `ShmError::QueueEmpty`, если его нет.
+    pub fn receive<M: Message + Default>(&mut self) -> Result<M> {
+        let len = self.endpoint.receive_payload(&mut self.buffer)?;
+        Prost.decode(&self.buffer[..len])
+    }
+
+    pub fn schema_hash(&self) -> u32 {
+        self.schema_hash
+    }
+
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    pub fn into_inner(self) -> E {
+        self.endpoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedClient, SharedServer};
+    use std::thread;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Ping {
+        #[prost(uint32, tag = "1")]
+        id: u32,
+        #[prost(string, tag = "2")]
+        text: String,
+    }
+
+    fn connected_pair(tag: &str) -> (SharedServer, SharedClient) {
+        let name = format!("XSHM_PROST_{tag}_{}", std::process::id());
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        (server, connector.join().unwrap())
+    }
+
+    #[test]
+    fn matching_schemas_exchange_messages() {
+        let (server, client) = connected_pair("OK");
+        let hash = descriptor_hash(b"package test; message Ping {}");
+        let peer = thread::spawn(move || {
+            let mut channel =
+                ProstChannel::establish(client, hash, Duration::from_secs(2)).unwrap();
+            let deadline = Instant::now() + Duration::from_secs(2);
+            loop {
+                match channel.receive::<Ping>() {
+                    Ok(ping) => break ping,
+                    Err(ShmError::QueueEmpty) if Instant::now() < deadline => {
+                        thread::sleep(Duration::from_millis(5));
+                    }
+                    Err(err) => panic!("{err}"),
+                }
+            }
+        });
+
+        let channel = ProstChannel::establish(server, hash, Duration::from_secs(2)).unwrap();
+        let ping = Ping {
+            id: 7,
+            text: "seven".into(),
+        };
+        channel.send(&ping).unwrap();
+        assert_eq!(peer.join().unwrap(), ping);
+    }
+
+    #[test]
+    fn schema_drift_is_reported() {
+        let (server, client) = connected_pair("DRIFT");
+        let peer =
+            thread::spawn(move || ProstChannel::establish(client, 1, Duration::from_secs(2)).err());
+        let err = ProstChannel::establish(server, 2, Duration::from_secs(2)).err();
+        assert_eq!(
+            err,
+            Some(ShmError::SchemaMismatch {
+                local: 2,
+                remote: 1
+            })
+        );
+        assert_eq!(
+            peer.join().unwrap(),
+            Some(ShmError::SchemaMismatch {
+                local: 1,
+                remote: 2
+            })
+        );
+    }
+
+    #[test]
+    fn non_schema_first_frame_fails_handshake() {
+        let (server, client) = connected_pair("RAW");
+        client.send_to_server(b"not a schema frame").unwrap();
+        let err = ProstChannel::establish(server, 1, Duration::from_secs(1)).err();
+        assert_eq!(err, Some(ShmError::HandshakeFailed));
+    }
+}
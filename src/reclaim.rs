@@ -0,0 +1,325 @@
+//! Подбор каналов, брошенных упавшим сервером.
+//!
+//! Если процесс сервера умер, не закрыв канал, его секция и события живут,
+//! пока открыт хоть один handle (клиент, монитор), а новый сервер с тем же
+//! именем получает ошибку коллизии или полуинициализированное состояние.
+//! Сервер пишет свой PID в control block (`RESERVED_SERVER_PID_INDEX`):
+//! если этот процесс мёртв, канал считается брошенным -- control block и
+//! кольца принудительно сбрасываются, и канал можно занять заново.
+//!
+//! На Unix имена shm-объектов переживают процесс: у брошенного канала они
+//! ещё и снимаются (`shm_unlink`), так что новый сервер создаёт свежие
+//! объекты, а не открывает старые. Снимаются и каналы мёртвого сервера с
+//! другой раскладкой колец (другой профиль размеров), которые сбросить
+//! нельзя. Все брошенные каналы с префиксом подбирает [`reclaim_dead`].
+//!
+//! `SharedServer::start*` делает это сам перед созданием секции; для
+//! явной уборки при старте есть [`reclaim_stale`]:
+//!
+//! ```no_run
+//! let report = xshm::reclaim::reclaim_stale(["CHAN_A", "CHAN_B"]);
+//! println!("reclaimed {} of {} channels", report.reclaimed.len(), report.checked);
+//! ```
+
+use std::sync::atomic::Ordering;
+
+use crate::constants::{
+    HANDSHAKE_IDLE, LAYOUT_FLAG_CONTROL_RINGS, RESERVED_CLIENT_PID_INDEX,
+    RESERVED_LAYOUT_FLAGS_INDEX, RESERVED_SERVER_PID_INDEX, RESERVED_UPGRADE_TOKEN_INDEX,
+    SHARED_MAGIC,
+};
+use crate::discovery;
+use crate::error::{Result, ShmError};
+use crate::events::SharedEvents;
+use crate::layout::{dual_mapping_size, ControlBlock};
+use crate::naming::mapping_name;
+use crate::platform::{self, Mapping, PlatformMapping};
+use crate::shared::SharedView;
+use crate::state::SharedState;
+
+/// Владелец именованного канала по данным control block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelOwner {
+    /// Секции нет (или её не удалось открыть).
+    Missing,
+    /// Процесс сервера жив.
+    Alive(u32),
+    /// Процесс сервера завершился, канал брошен.
+    Dead(u32),
+    /// PID не записан (старый сервер или anonymous) -- не трогаем.
+    Unknown,
+}
+
+/// Итог [`reclaim_stale`].
+#[derive(Debug, Default)]
+pub struct ReclaimReport {
+    /// Сколько имён проверено.
+    pub checked: usize,
+    /// Подобранные каналы и PID их мёртвого сервера.
+    pub reclaimed: Vec<(String, u32)>,
+    /// Каналы, которые не удалось проверить или сбросить.
+    pub failed: Vec<(String, ShmError)>,
+}
+
+/// Только control block канала `name` -- без проверки раскладки колец.
+fn open_control(name: &str) -> Result<Option<Mapping>> {
+    let Ok(mapping) = Mapping::open_sized(&mapping_name(name), std::mem::size_of::<ControlBlock>()) else {
+        return Ok(None);
+    };
+    // SAFETY: отображение не меньше ControlBlock, который лежит в начале.
+    let control = unsafe { &*(mapping.as_ptr() as *const ControlBlock) };
+    if control.magic != SHARED_MAGIC {
+        return Err(ShmError::Corrupted);
+    }
+    Ok(Some(mapping))
+}
+
+fn control_of(mapping: &Mapping) -> &ControlBlock {
+    // SAFETY: см. `open_control`; ссылка живёт не дольше отображения.
+    unsafe { &*(mapping.as_ptr() as *const ControlBlock) }
+}
+
+fn open_view(name: &str) -> Result<Option<(Mapping, SharedView)>> {
+    let map_name = mapping_name(name);
+    let Ok(mapping) = Mapping::open(&map_name) else {
+        return Ok(None);
+    };
+    // SAFETY: отображение размером shared_mapping_size() живёт вместе с view.
+    let view = unsafe { SharedView::new(mapping.as_ptr()) };
+    let control = view.control_block();
+    if control.magic != SHARED_MAGIC {
+        return Err(ShmError::Corrupted);
+    }
+    // Сброс чужой раскладки затёр бы память не там, где её кольца.
+    view.check_layout()?;
+    let flags = control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+    if flags & LAYOUT_FLAG_CONTROL_RINGS == 0 {
+        return Ok(Some((mapping, view)));
+    }
+    // Раскладка с управляющими кольцами: переоткрываем целиком, чтобы
+    // сбросить и их.
+    let mapping = Mapping::open_sized(&map_name, dual_mapping_size())?;
+    // SAFETY: отображение размером dual_mapping_size() живёт вместе с view.
+    let view = unsafe { SharedView::with_control_rings(mapping.as_ptr()) };
+    Ok(Some((mapping, view)))
+}
+
+pub(crate) fn owner_of(view: &SharedView) -> ChannelOwner {
+    owner_of_control(view.control_block())
+}
+
+fn owner_of_control(control: &ControlBlock) -> ChannelOwner {
+    match control.reserved[RESERVED_SERVER_PID_INDEX].load(Ordering::Acquire) {
+        0 => ChannelOwner::Unknown,
+        pid if platform::is_process_alive(pid) => ChannelOwner::Alive(pid),
+        pid => ChannelOwner::Dead(pid),
+    }
+}
+
+/// Владелец канала `name`.
+pub fn owner(name: &str) -> Result<ChannelOwner> {
+    Ok(match open_control(name)? {
+        Some(mapping) => owner_of_control(control_of(&mapping)),
+        None => ChannelOwner::Missing,
+    })
+}
+
+/// Сбрасывает канал `name`, если его сервер мёртв; возвращает PID
+/// мёртвого сервера. Живой, неизвестный или отсутствующий владелец --
+/// `Ok(None)`, канал не трогается.
+///
+/// Control block переходит в IDLE с новым generation (клиенты упавшего
+/// сервера увидят смену поколения), кольца очищаются, PID сторон
+/// обнуляются. Раскладка (флаги) сохраняется. Канал с чужой раскладкой
+/// колец не сбрасывается. На Unix после этого имена секции, событий и
+/// блока состояния канала снимаются.
+pub fn reclaim(name: &str) -> Result<Option<u32>> {
+    let Some(probe) = open_control(name)? else {
+        return Ok(None);
+    };
+    let ChannelOwner::Dead(pid) = owner_of_control(control_of(&probe)) else {
+        return Ok(None);
+    };
+    // Снимаем PID мёртвого сервера атомарно: из нескольких одновременно
+    // стартующих процессов сбрасывает канал только один.
+    if control_of(&probe).reserved[RESERVED_SERVER_PID_INDEX]
+        .compare_exchange(pid, 0, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        return Ok(None);
+    }
+    if let Ok(Some((_mapping, view))) = open_view(name) {
+        reset(&view);
+    }
+    platform::unlink(&mapping_name(name));
+    SharedEvents::unlink(name);
+    SharedState::unlink(name);
+    Ok(Some(pid))
+}
+
+fn reset(view: &SharedView) {
+    let control = view.control_block();
+    control
+        .server_state
+        .store(HANDSHAKE_IDLE, Ordering::Release);
+    control
+        .client_state
+        .store(HANDSHAKE_IDLE, Ordering::Release);
+    control.reserved[RESERVED_CLIENT_PID_INDEX].store(0, Ordering::Relaxed);
+    control.reserved[RESERVED_UPGRADE_TOKEN_INDEX].store(0, Ordering::Relaxed);
+
+    let generation = control.generation.load(Ordering::Acquire).wrapping_add(1);
+    // SAFETY: заголовки колец внутри отображения (инвариант SharedView).
+    unsafe {
+        (*view.ring_header_a()).reset(generation);
+        (*view.ring_header_b()).reset(generation);
+    }
+    view.reset_control_rings(generation);
+    control.generation.store(generation, Ordering::Release);
+}
+
+/// Проверяет каналы `names` и подбирает брошенные (см. [`reclaim`]).
+pub fn reclaim_stale<I, S>(names: I) -> ReclaimReport
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut report = ReclaimReport::default();
+    for name in names {
+        let name = name.as_ref();
+        report.checked += 1;
+        match reclaim(name) {
+            Ok(Some(pid)) => report.reclaimed.push((name.to_owned(), pid)),
+            Ok(None) => {}
+            Err(err) => report.failed.push((name.to_owned(), err)),
+        }
+    }
+    report
+}
+
+/// Подбирает все брошенные каналы, базовое имя которых начинается с
+/// `prefix` (`""` -- все), среди видимых [`discover`](crate::discover).
+pub fn reclaim_dead(prefix: &str) -> ReclaimReport {
+    reclaim_stale(
+        discovery::discover(prefix)
+            .into_iter()
+            .filter(|channel| matches!(channel.owner, ChannelOwner::Dead(_)))
+            .map(|channel| channel.namespace.qualify(&channel.name)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SharedServer;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_RECLAIM_{tag}_{}", std::process::id())
+    }
+
+    fn dead_pid() -> u32 {
+        let mut child = platform::spawn_exiting_child();
+        let pid = child.id();
+        child.wait().expect("wait for child exit");
+        for _ in 0..50 {
+            if !platform::is_process_alive(pid) {
+                return pid;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("child {pid} still reported alive");
+    }
+
+    /// Подменяет PID сервера в секции, как будто его процесс упал.
+    fn orphan(name: &str, pid: u32) {
+        let (_mapping, view) = open_view(name).unwrap().unwrap();
+        let control = view.control_block();
+        control.reserved[RESERVED_SERVER_PID_INDEX].store(pid, Ordering::Release);
+        control
+            .client_state
+            .store(crate::constants::HANDSHAKE_CLIENT_HELLO, Ordering::Release);
+    }
+
+    #[test]
+    fn stale_channels_are_counted_and_reset() {
+        let alive = unique("ALIVE");
+        let stale = unique("STALE");
+        let _alive_server = SharedServer::start(&alive).unwrap();
+        let _stale_server = SharedServer::start(&stale).unwrap();
+        let pid = dead_pid();
+        orphan(&stale, pid);
+
+        assert_eq!(
+            owner(&alive).unwrap(),
+            ChannelOwner::Alive(std::process::id())
+        );
+        assert_eq!(owner(&stale).unwrap(), ChannelOwner::Dead(pid));
+        assert_eq!(owner(&unique("MISSING")).unwrap(), ChannelOwner::Missing);
+
+        // Секцию держим сами: на Unix имя после сброса снимается.
+        let (_mapping, view) = open_view(&stale).unwrap().unwrap();
+        let report = reclaim_stale([&alive, &stale, &unique("MISSING")]);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.reclaimed, vec![(stale.clone(), pid)]);
+        assert!(report.failed.is_empty());
+
+        let control = view.control_block();
+        assert_eq!(control.client_state.load(Ordering::Acquire), HANDSHAKE_IDLE);
+        assert_eq!(control.generation.load(Ordering::Acquire), 2);
+        assert!(matches!(
+            owner(&stale).unwrap(),
+            ChannelOwner::Unknown | ChannelOwner::Missing
+        ));
+        // Повторный проход ничего не находит.
+        assert!(reclaim_stale([&stale]).reclaimed.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dead_server_names_are_unlinked_even_with_foreign_layout() {
+        use crate::constants::RESERVED_RING_GEOMETRY_INDEX;
+
+        let name = unique("UNLINK");
+        let old = SharedServer::start(&name).unwrap();
+        let pid = dead_pid();
+        orphan(&name, pid);
+        // Канал другого профиля размеров: сбросить его нельзя.
+        control_of(&open_control(&name).unwrap().unwrap()).reserved
+            [RESERVED_RING_GEOMETRY_INDEX]
+            .store(1, Ordering::Release);
+        assert!(matches!(
+            open_view(&name),
+            Err(ShmError::LayoutMismatch { .. })
+        ));
+        assert_eq!(owner(&name).unwrap(), ChannelOwner::Dead(pid));
+
+        // Имена сняты, новый сервер создаёт свежие объекты.
+        let server = SharedServer::start(&name).unwrap();
+        assert_eq!(server.reclaimed_from(), Some(pid));
+        assert_eq!(
+            owner(&name).unwrap(),
+            ChannelOwner::Alive(std::process::id())
+        );
+        drop(server);
+        assert_eq!(owner(&name).unwrap(), ChannelOwner::Missing);
+        drop(old);
+    }
+
+    #[test]
+    fn server_takes_over_channel_of_dead_server() {
+        let name = unique("TAKEOVER");
+        let old = SharedServer::start_with_control_rings(&name).unwrap();
+        let pid = dead_pid();
+        orphan(&name, pid);
+
+        // Секция и события ещё открыты (handles «упавшего» сервера живы).
+        let server = SharedServer::start_with_control_rings(&name).unwrap();
+        assert_eq!(server.reclaimed_from(), Some(pid));
+        assert_eq!(
+            owner(&name).unwrap(),
+            ChannelOwner::Alive(std::process::id())
+        );
+        drop(server);
+        drop(old);
+    }
+}
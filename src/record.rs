@@ -0,0 +1,654 @@
+//! Запись и воспроизведение трафика канала.
+//!
+//! [`Recorder`] пишет бинарный лог с метками времени: входящие сообщения
+//! снимает [`RecordingHandler`] (обёртка над любым `AutoHandler`),
+//! исходящие -- [`Tap`] (обёртка над любым endpoint'ом). Записанную сессию
+//! [`RecordReader`] отдаёт обратно в `AutoHandler`
+//! ([`replay_into_handler`]) или в живой канал ([`replay_into`]) в
+//! исходном темпе или ускоренно -- для воспроизведения проблем с поля.
+//!
+//! Формат (всё little-endian):
+//! ```text
+//! "XREC" | version: u16 | reserved: u16
+//! { timestamp_ns: u64 | direction: u8 | len: u32 | payload[len] }*
+//! ```
+//! `timestamp_ns` -- от создания `Recorder`; `direction`: 0 --
+//! `ServerToClient`, 1 -- `ClientToServer`.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use xshm::record::{self, Recorder, RecordingHandler, ReplayOptions};
+//! # struct App;
+//! # impl xshm::AutoHandler for App {}
+//!
+//! // запись
+//! let recorder = Arc::new(Recorder::create("session.xrec")?);
+//! let handler = Arc::new(RecordingHandler::new(Arc::new(App), recorder.clone()));
+//! let server = xshm::AutoServer::start("CHAN", handler, xshm::AutoOptions::default())?;
+//!
+//! // воспроизведение в 10 раз быстрее
+//! let reader = record::open("session.xrec")?;
+//! let options = ReplayOptions { speed: 10.0, ..ReplayOptions::default() };
+//! record::replay_into_handler(reader, &App, &options)?;
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::auto::{AutoClient, AutoHandler, AutoServer, ChannelKind, PooledMessage};
+use crate::client::SharedClient;
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::disconnect::DisconnectReason;
+use crate::error::{io_error, ErrorContext, Result, ShmError};
+use crate::server::SharedServer;
+
+const RECORD_MAGIC: [u8; 4] = *b"XREC";
+const RECORD_VERSION: u16 = 1;
+const FILE_HEADER_SIZE: usize = 8;
+const RECORD_HEADER_SIZE: usize = 13;
+
+fn direction_code(direction: ChannelKind) -> u8 {
+    match direction {
+        ChannelKind::ServerToClient => 0,
+        ChannelKind::ClientToServer => 1,
+    }
+}
+
+struct RecorderState {
+    writer: Box<dyn Write + Send>,
+    start: Instant,
+}
+
+/// Потокобезопасный писатель лога. Один `Recorder` можно делить между
+/// `RecordingHandler` и `Tap` одного канала.
+pub struct Recorder {
+    state: Mutex<RecorderState>,
+}
+
+impl Recorder {
+    /// Лог в произвольный `Write` (заголовок пишется сразу).
+    pub fn new(mut writer: impl Write + Send + 'static) -> Result<Self> {
+        let mut header = [0u8; FILE_HEADER_SIZE];
+        header[..4].copy_from_slice(&RECORD_MAGIC);
+        header[4..6].copy_from_slice(&RECORD_VERSION.to_le_bytes());
+        writer
+            .write_all(&header)
+            .map_err(|err| io_error(err, "write record header"))?;
+        Ok(Self {
+            state: Mutex::new(RecorderState {
+                writer: Box::new(writer),
+                start: Instant::now(),
+            }),
+        })
+    }
+
+    /// Лог в файл (создаётся/перезаписывается, буферизован).
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).map_err(|err| io_error(err, "create record file"))?;
+        Self::new(BufWriter::new(file))
+    }
+
+    /// Дописывает одно сообщение с текущей меткой времени.
+    pub fn record(&self, direction: ChannelKind, payload: &[u8]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let timestamp = state.start.elapsed().as_nanos() as u64;
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        header[..8].copy_from_slice(&timestamp.to_le_bytes());
+        header[8] = direction_code(direction);
+        header[9..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        state
+            .writer
+            .write_all(&header)
+            .and_then(|()| state.writer.write_all(payload))
+            .map_err(|err| io_error(err, "write record"))
+    }
+
+    /// Сбрасывает буфер писателя (лог также сбрасывается при drop).
+    pub fn flush(&self) -> Result<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .writer
+            .flush()
+            .map_err(|err| io_error(err, "flush record"))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Ok(state) = self.state.get_mut() {
+            let _ = state.writer.flush();
+        }
+    }
+}
+
+/// `AutoHandler`, записывающий каждое входящее сообщение перед передачей
+/// обёрнутому handler'у. Ошибки записи уходят в `on_error` обёрнутого.
+pub struct RecordingHandler<H: AutoHandler> {
+    inner: Arc<H>,
+    recorder: Arc<Recorder>,
+}
+
+impl<H: AutoHandler> RecordingHandler<H> {
+    pub fn new(inner: Arc<H>, recorder: Arc<Recorder>) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+impl<H: AutoHandler> AutoHandler for RecordingHandler<H> {
+    fn on_connect(&self) {
+        self.inner.on_connect();
+    }
+
+    fn on_connect_with_epoch(&self, epoch: u32) {
+        self.inner.on_connect_with_epoch(epoch);
+    }
+
+    fn on_disconnect(&self) {
+        self.inner.on_disconnect();
+    }
+
+    fn on_disconnect_with_reason(&self, reason: DisconnectReason) {
+        self.inner.on_disconnect_with_reason(reason);
+    }
+
+    fn on_reconnect_scheduled(&self, delay: Duration, attempt: u32) {
+        self.inner.on_reconnect_scheduled(delay, attempt);
+    }
+
+    fn on_reconnect(&self, attempt: u32, last_error: &ShmError) {
+        self.inner.on_reconnect(attempt, last_error);
+    }
+
+    fn on_tick(&self, elapsed: Duration) {
+        self.inner.on_tick(elapsed);
+    }
+
+    fn on_message(&self, direction: ChannelKind, payload: &[u8]) {
+        if let Err(err) = self.recorder.record(direction, payload) {
+            self.inner.on_error(err);
+        }
+        self.inner.on_message(direction, payload);
+    }
+
+    fn on_message_with_flags(&self, direction: ChannelKind, payload: &[u8], flags: u16) {
+        if let Err(err) = self.recorder.record(direction, payload) {
+            self.inner.on_error(err);
+        }
+        self.inner.on_message_with_flags(direction, payload, flags);
+    }
+
+    fn on_message_owned(&self, direction: ChannelKind, message: PooledMessage, flags: u16) {
+        if let Err(err) = self.recorder.record(direction, &message) {
+            self.inner.on_error(err);
+        }
+        self.inner.on_message_owned(direction, message, flags);
+    }
+
+    fn on_overflow(&self, direction: ChannelKind, count: u32) {
+        self.inner.on_overflow(direction, count);
+    }
+
+    fn on_space_available(&self, direction: ChannelKind) {
+        self.inner.on_space_available(direction);
+    }
+
+    fn on_expired(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_expired(direction, payload);
+    }
+
+    fn on_error(&self, err: ShmError) {
+        self.inner.on_error(err);
+    }
+
+    fn on_error_with_context(&self, error: ErrorContext) {
+        self.inner.on_error_with_context(error);
+    }
+
+    fn on_before_send(&self, direction: ChannelKind, payload: &[u8]) {
+        self.inner.on_before_send(direction, payload);
+    }
+
+    fn on_after_receive(
+        &self,
+        direction: ChannelKind,
+        payload: &[u8],
+        enqueue_latency: Option<Duration>,
+    ) {
+        self.inner
+            .on_after_receive(direction, payload, enqueue_latency);
+    }
+}
+
+/// Отправляющая сторона канала: цель для [`Tap`] и [`replay_into`].
+pub trait SendEndpoint {
+    /// Направление исходящих сообщений этой стороны.
+    const DIRECTION: ChannelKind;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()>;
+}
+
+impl SendEndpoint for AutoServer {
+    const DIRECTION: ChannelKind = ChannelKind::ServerToClient;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()> {
+        self.send(payload)
+    }
+}
+
+impl SendEndpoint for AutoClient {
+    const DIRECTION: ChannelKind = ChannelKind::ClientToServer;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()> {
+        self.send(payload)
+    }
+}
+
+impl SendEndpoint for SharedServer {
+    const DIRECTION: ChannelKind = ChannelKind::ServerToClient;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()> {
+        self.send_to_client(payload).map(drop)
+    }
+}
+
+impl SendEndpoint for SharedClient {
+    const DIRECTION: ChannelKind = ChannelKind::ClientToServer;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()> {
+        self.send_to_server(payload).map(drop)
+    }
+}
+
+impl<E: SendEndpoint> SendEndpoint for &E {
+    const DIRECTION: ChannelKind = E::DIRECTION;
+
+    fn send_payload(&self, payload: &[u8]) -> Result<()> {
+        (**self).send_payload(payload)
+    }
+}
+
+/// Endpoint, записывающий каждое успешно отправленное сообщение.
+pub struct Tap<E: SendEndpoint> {
+    endpoint: E,
+    recorder: Arc<Recorder>,
+}
+
+impl<E: SendEndpoint> Tap<E> {
+    pub fn new(endpoint: E, recorder: Arc<Recorder>) -> Self {
+        Self { endpoint, recorder }
+    }
+
+    pub fn send(&self, payload: &[u8]) -> Result<()> {
+        self.endpoint.send_payload(payload)?;
+        self.recorder.record(E::DIRECTION, payload)
+    }
+
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    pub fn into_inner(self) -> E {
+        self.endpoint
+    }
+}
+
+/// Одно записанное сообщение.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Время от начала записи.
+    pub at: Duration,
+    pub direction: ChannelKind,
+    pub payload: Vec<u8>,
+}
+
+/// Последовательное чтение лога. Итерирует `Result<Record>`; обрезанный
+/// или повреждённый хвост даёт `ShmError::Corrupted`.
+pub struct RecordReader<R: Read> {
+    reader: R,
+    failed: bool,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; FILE_HEADER_SIZE];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| ShmError::Corrupted)?;
+        if header[..4] != RECORD_MAGIC {
+            return Err(ShmError::Corrupted);
+        }
+        if u16::from_le_bytes([header[4], header[5]]) != RECORD_VERSION {
+            return Err(ShmError::HandshakeFailed);
+        }
+        Ok(Self {
+            reader,
+            failed: false,
+        })
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>> {
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        // Чистый конец файла возможен только на границе записи.
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(ShmError::Corrupted),
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(io_error(err, "read record")),
+            }
+        }
+
+        let at = Duration::from_nanos(u64::from_le_bytes(header[..8].try_into().unwrap()));
+        let direction = match header[8] {
+            0 => ChannelKind::ServerToClient,
+            1 => ChannelKind::ClientToServer,
+            _ => return Err(ShmError::Corrupted),
+        };
+        let len = u32::from_le_bytes(header[9..].try_into().unwrap()) as usize;
+        if len > MAX_MESSAGE_SIZE {
+            return Err(ShmError::Corrupted);
+        }
+        let mut payload = vec![0u8; len];
+        self.reader
+            .read_exact(&mut payload)
+            .map_err(|_| ShmError::Corrupted)?;
+        Ok(Some(Record {
+            at,
+            direction,
+            payload,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = self.read_record().transpose();
+        self.failed = matches!(item, Some(Err(_)));
+        item
+    }
+}
+
+/// Открывает лог, записанный `Recorder::create`.
+pub fn open(path: impl AsRef<Path>) -> Result<RecordReader<BufReader<File>>> {
+    let file = File::open(path).map_err(|err| io_error(err, "open record file"))?;
+    RecordReader::new(BufReader::new(file))
+}
+
+/// Параметры воспроизведения.
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Множитель скорости: 1.0 -- исходный темп, 10.0 -- в 10 раз быстрее,
+    /// 0.0 -- без пауз.
+    pub speed: f64,
+    /// Воспроизводить только одно направление (`None` -- все). Для
+    /// `replay_into` по умолчанию берётся направление endpoint'а.
+    pub direction: Option<ChannelKind>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            direction: None,
+        }
+    }
+}
+
+/// Общий цикл: выдерживает паузы между записями и передаёт подходящие
+/// в `deliver`. Возвращает число переданных.
+fn replay<R: Read>(
+    reader: RecordReader<R>,
+    direction: Option<ChannelKind>,
+    speed: f64,
+    mut deliver: impl FnMut(&Record) -> Result<()>,
+) -> Result<usize> {
+    if !(speed >= 0.0 && speed.is_finite()) {
+        return Err(ShmError::InvalidConfig(
+            "replay speed must be finite and >= 0",
+        ));
+    }
+    let start = Instant::now();
+    let mut delivered = 0;
+    for record in reader {
+        let record = record?;
+        if direction.is_some_and(|d| d != record.direction) {
+            continue;
+        }
+        if speed > 0.0 {
+            let due = start + record.at.div_f64(speed);
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+        deliver(&record)?;
+        delivered += 1;
+    }
+    Ok(delivered)
+}
+
+/// Передаёт записанные сообщения в `handler.on_message` (с исходным
+/// направлением).
+pub fn replay_into_handler<R: Read, H: AutoHandler + ?Sized>(
+    reader: RecordReader<R>,
+    handler: &H,
+    options: &ReplayOptions,
+) -> Result<usize> {
+    replay(reader, options.direction, options.speed, |record| {
+        handler.on_message(record.direction, &record.payload);
+        Ok(())
+    })
+}
+
+/// Отправляет записанные сообщения в живой канал. Без явного
+/// `options.direction` берутся сообщения того же направления, что и у
+/// `endpoint` (сессия сервера переигрывается через сервер).
+pub fn replay_into<R: Read, E: SendEndpoint>(
+    reader: RecordReader<R>,
+    endpoint: &E,
+    options: &ReplayOptions,
+) -> Result<usize> {
+    let direction = options.direction.unwrap_or(E::DIRECTION);
+    replay(reader, Some(direction), options.speed, |record| {
+        endpoint.send_payload(&record.payload)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Write` в общий буфер, чтобы прочитать лог обратно.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<(ChannelKind, Vec<u8>)>>);
+
+    impl AutoHandler for Collect {
+        fn on_message(&self, direction: ChannelKind, payload: &[u8]) {
+            self.0.lock().unwrap().push((direction, payload.to_vec()));
+        }
+    }
+
+    #[derive(Default)]
+    struct Expired(Mutex<Vec<Vec<u8>>>);
+
+    impl AutoHandler for Expired {
+        fn on_expired(&self, _direction: ChannelKind, payload: &[u8]) {
+            self.0.lock().unwrap().push(payload.to_vec());
+        }
+    }
+
+    fn recorded(messages: &[(ChannelKind, &[u8])]) -> Vec<u8> {
+        let buf = SharedBuf::default();
+        let recorder = Recorder::new(buf.clone()).unwrap();
+        for (direction, payload) in messages {
+            recorder.record(*direction, payload).unwrap();
+        }
+        drop(recorder);
+        let bytes = buf.0.lock().unwrap().clone();
+        bytes
+    }
+
+    #[test]
+    fn handler_tap_records_and_forwards() {
+        let buf = SharedBuf::default();
+        let recorder = Arc::new(Recorder::new(buf.clone()).unwrap());
+        let inner = Arc::new(Collect::default());
+        let handler = RecordingHandler::new(inner.clone(), recorder);
+        handler.on_message(ChannelKind::ClientToServer, b"hello");
+        assert_eq!(inner.0.lock().unwrap().len(), 1);
+
+        let bytes = buf.0.lock().unwrap().clone();
+        let records: Vec<Record> = RecordReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].direction, ChannelKind::ClientToServer);
+        assert_eq!(records[0].payload, b"hello");
+    }
+
+    #[derive(Default)]
+    struct Traced(Mutex<Vec<&'static str>>);
+
+    impl AutoHandler for Traced {
+        fn on_before_send(&self, _direction: ChannelKind, _payload: &[u8]) {
+            self.0.lock().unwrap().push("before_send");
+        }
+
+        fn on_after_receive(&self, _: ChannelKind, _: &[u8], _: Option<Duration>) {
+            self.0.lock().unwrap().push("after_receive");
+        }
+    }
+
+    #[test]
+    fn wrapped_handler_sees_tracing_hooks() {
+        let recorder = Arc::new(Recorder::new(SharedBuf::default()).unwrap());
+        let inner = Arc::new(Traced::default());
+        let handler = RecordingHandler::new(inner.clone(), recorder);
+        handler.on_before_send(ChannelKind::ClientToServer, b"out");
+        handler.on_after_receive(ChannelKind::ServerToClient, b"in", None);
+        assert_eq!(*inner.0.lock().unwrap(), ["before_send", "after_receive"]);
+    }
+
+    #[test]
+    fn wrapped_handler_sees_expired_sends() {
+        let name = format!("TEST_RECORD_EXPIRED_{}", std::process::id());
+        let server = AutoServer::start(&name, Arc::new(Collect::default()), Default::default())
+            .unwrap();
+        let recorder = Arc::new(Recorder::new(SharedBuf::default()).unwrap());
+        let inner = Arc::new(Expired::default());
+        let handler = Arc::new(RecordingHandler::new(inner.clone(), recorder));
+        let client = AutoClient::connect(&name, handler, Default::default()).unwrap();
+
+        client.send_with_deadline(b"stale", Instant::now()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while inner.0.lock().unwrap().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*inner.0.lock().unwrap(), [b"stale".to_vec()]);
+        client.stop();
+        server.stop();
+    }
+
+    #[test]
+    fn replay_filters_direction_and_keeps_order() {
+        let bytes = recorded(&[
+            (ChannelKind::ServerToClient, b"s1"),
+            (ChannelKind::ClientToServer, b"c1"),
+            (ChannelKind::ServerToClient, b"s2"),
+        ]);
+
+        let all = Collect::default();
+        let reader = RecordReader::new(bytes.as_slice()).unwrap();
+        let options = ReplayOptions {
+            speed: 0.0,
+            ..ReplayOptions::default()
+        };
+        assert_eq!(replay_into_handler(reader, &all, &options).unwrap(), 3);
+
+        let s2c = Collect::default();
+        let reader = RecordReader::new(bytes.as_slice()).unwrap();
+        let options = ReplayOptions {
+            speed: 0.0,
+            direction: Some(ChannelKind::ServerToClient),
+        };
+        assert_eq!(replay_into_handler(reader, &s2c, &options).unwrap(), 2);
+        let payloads: Vec<Vec<u8>> = s2c.0.lock().unwrap().iter().map(|m| m.1.clone()).collect();
+        assert_eq!(payloads, vec![b"s1".to_vec(), b"s2".to_vec()]);
+    }
+
+    #[test]
+    fn replay_keeps_original_pacing_scaled_by_speed() {
+        let buf = SharedBuf::default();
+        let recorder = Recorder::new(buf.clone()).unwrap();
+        recorder.record(ChannelKind::ServerToClient, b"a").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        recorder.record(ChannelKind::ServerToClient, b"b").unwrap();
+        drop(recorder);
+        let bytes = buf.0.lock().unwrap().clone();
+
+        let started = Instant::now();
+        let reader = RecordReader::new(bytes.as_slice()).unwrap();
+        let options = ReplayOptions {
+            speed: 2.0,
+            ..ReplayOptions::default()
+        };
+        replay_into_handler(reader, &Collect::default(), &options).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn truncated_or_foreign_logs_are_rejected() {
+        let bytes = recorded(&[(ChannelKind::ServerToClient, b"payload")]);
+        let truncated = &bytes[..bytes.len() - 2];
+        let mut reader = RecordReader::new(truncated).unwrap();
+        assert_eq!(reader.next(), Some(Err(ShmError::Corrupted)));
+        assert_eq!(reader.next(), None);
+
+        assert!(matches!(
+            RecordReader::new(&b"NOPE\x01\x00\x00\x00"[..]),
+            Err(ShmError::Corrupted)
+        ));
+        assert!(matches!(
+            RecordReader::new(&b"XREC\x09\x00\x00\x00"[..]),
+            Err(ShmError::HandshakeFailed)
+        ));
+        assert!(matches!(
+            replay_into_handler(
+                RecordReader::new(bytes.as_slice()).unwrap(),
+                &Collect::default(),
+                &ReplayOptions {
+                    speed: f64::NAN,
+                    direction: None
+                }
+            ),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+//! Реестр xshm-сервисов процесса и служба обнаружения.
+//!
+//! Каждый `AutoServer`, `MultiServer` и `DispatchServer` при старте сам
+//! регистрируется здесь (выделенные каналы клиентов `dispatch` -- деталь
+//! реализации и в реестр не попадают). Как и в `metrics`, реестр держит
+//! только `Weak`-ссылки: освобождённый сервер исчезает из [`list`] сам.
+//!
+//! [`serve`] дополнительно поднимает канал с общеизвестным именем
+//! ([`REGISTRY_CHANNEL`]), через который другой процесс получает список
+//! сервисов хоста вызовом [`query`], не зная их имён заранее.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use xshm::registry;
+//!
+//! // процесс-хост
+//! let _registry = registry::serve(registry::REGISTRY_CHANNEL)?;
+//!
+//! // любой другой процесс
+//! for service in registry::query(registry::REGISTRY_CHANNEL, Duration::from_secs(1))? {
+//!     println!("{} ({}) pid={}", service.name, service.kind.as_str(), service.pid);
+//! }
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::auto::AutoStatsSnapshot;
+use crate::client::SharedClient;
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::{Result, ShmError};
+use crate::platform::PlatformEvent;
+use crate::server::SharedServer;
+
+/// Общеизвестное имя канала обнаружения.
+pub const REGISTRY_CHANNEL: &str = "XSHM_REGISTRY";
+
+/// Сколько служба ждёт отключения клиента после ответа, прежде чем
+/// перейти к следующему.
+const REPLY_LINGER: Duration = Duration::from_secs(1);
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+const STATS_FIELDS: usize = 7;
+
+/// Тип сервиса.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceKind {
+    Auto,
+    Multi,
+    Dispatch,
+}
+
+impl ServiceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServiceKind::Auto => "auto",
+            ServiceKind::Multi => "multi",
+            ServiceKind::Dispatch => "dispatch",
+        }
+    }
+
+    fn to_wire(self) -> u8 {
+        match self {
+            ServiceKind::Auto => 0,
+            ServiceKind::Multi => 1,
+            ServiceKind::Dispatch => 2,
+        }
+    }
+
+    fn from_wire(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ServiceKind::Auto),
+            1 => Some(ServiceKind::Multi),
+            2 => Some(ServiceKind::Dispatch),
+            _ => None,
+        }
+    }
+}
+
+/// Описание одного сервиса.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    /// Имя, переданное в `start` (для multi/dispatch -- базовое имя).
+    pub name: String,
+    pub kind: ServiceKind,
+    /// PID процесса-хоста.
+    pub pid: u32,
+    /// Счётчики канала (только у `Auto`).
+    pub stats: Option<AutoStatsSnapshot>,
+}
+
+/// Сервер, который можно зарегистрировать в реестре.
+pub(crate) trait ServiceSource: Send + Sync {
+    /// `false` после `stop()`: остановленный сервер в список не попадает,
+    /// даже если его ещё держат.
+    fn is_running(&self) -> bool {
+        true
+    }
+
+    fn stats(&self) -> Option<AutoStatsSnapshot> {
+        None
+    }
+}
+
+struct Entry {
+    name: String,
+    kind: ServiceKind,
+    source: Weak<dyn ServiceSource>,
+}
+
+static REGISTRY: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// Регистрирует сервис. Мёртвые записи вычищаются попутно.
+pub(crate) fn register(name: &str, kind: ServiceKind, source: Weak<dyn ServiceSource>) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|entry| entry.source.strong_count() > 0);
+    registry.push(Entry {
+        name: name.to_owned(),
+        kind,
+        source,
+    });
+}
+
+/// Живые сервисы текущего процесса в порядке регистрации.
+pub fn list() -> Vec<ServiceInfo> {
+    let pid = std::process::id();
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.retain(|entry| entry.source.strong_count() > 0);
+    registry
+        .iter()
+        .filter_map(|entry| {
+            let source = entry.source.upgrade()?;
+            source.is_running().then(|| ServiceInfo {
+                name: entry.name.clone(),
+                kind: entry.kind,
+                pid,
+                stats: source.stats(),
+            })
+        })
+        .collect()
+}
+
+fn stats_fields(stats: &AutoStatsSnapshot) -> [u64; STATS_FIELDS] {
+    [
+        stats.sent_messages,
+        stats.send_overflows,
+        stats.received_messages,
+        stats.receive_overflows,
+        stats.checksum_errors,
+        stats.connects,
+        stats.disconnects,
+    ]
+}
+
+/// Кодирует список в одно сообщение:
+/// `[count: u16]` и на каждый сервис
+/// `[kind: u8][pid: u32][name_len: u16][name][has_stats: u8][stats: 7 x u64]`.
+/// Не влезающие в `MAX_MESSAGE_SIZE` записи отбрасываются.
+fn encode(services: &[ServiceInfo]) -> Vec<u8> {
+    let mut out = vec![0u8; 2];
+    let mut count: u16 = 0;
+    for service in services {
+        let name = service.name.as_bytes();
+        let stats_len = if service.stats.is_some() {
+            STATS_FIELDS * 8
+        } else {
+            0
+        };
+        let entry_len = 1 + 4 + 2 + name.len() + 1 + stats_len;
+        if name.len() > u16::MAX as usize
+            || out.len() + entry_len > MAX_MESSAGE_SIZE
+            || count == u16::MAX
+        {
+            continue;
+        }
+        out.push(service.kind.to_wire());
+        out.extend_from_slice(&service.pid.to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(name);
+        match &service.stats {
+            Some(stats) => {
+                out.push(1);
+                for field in stats_fields(stats) {
+                    out.extend_from_slice(&field.to_le_bytes());
+                }
+            }
+            None => out.push(0),
+        }
+        count += 1;
+    }
+    out[..2].copy_from_slice(&count.to_le_bytes());
+    out
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(ShmError::Corrupted);
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+fn take_u64(data: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(take(data, 8)?.try_into().unwrap()))
+}
+
+fn decode(mut data: &[u8]) -> Result<Vec<ServiceInfo>> {
+    let data = &mut data;
+    let count = u16::from_le_bytes(take(data, 2)?.try_into().unwrap());
+    let mut services = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let kind = ServiceKind::from_wire(take(data, 1)?[0]).ok_or(ShmError::Corrupted)?;
+        let pid = u32::from_le_bytes(take(data, 4)?.try_into().unwrap());
+        let name_len = u16::from_le_bytes(take(data, 2)?.try_into().unwrap());
+        let name = std::str::from_utf8(take(data, name_len as usize)?)
+            .map_err(|_| ShmError::Corrupted)?
+            .to_owned();
+        let stats = match take(data, 1)?[0] {
+            0 => None,
+            1 => Some(AutoStatsSnapshot {
+                sent_messages: take_u64(data)?,
+                send_overflows: take_u64(data)?,
+                received_messages: take_u64(data)?,
+                receive_overflows: take_u64(data)?,
+                checksum_errors: take_u64(data)?,
+                connects: take_u64(data)?,
+                disconnects: take_u64(data)?,
+                // Гистограмма задержки по сети обнаружения не передаётся.
+                ..AutoStatsSnapshot::default()
+            }),
+            _ => return Err(ShmError::Corrupted),
+        };
+        services.push(ServiceInfo {
+            name,
+            kind,
+            pid,
+            stats,
+        });
+    }
+    if !data.is_empty() {
+        return Err(ShmError::Corrupted);
+    }
+    Ok(services)
+}
+
+/// Служба обнаружения, запущенная [`serve`]. Останавливается при drop.
+pub struct RegistryService {
+    running: Arc<AtomicBool>,
+    join: Option<JoinHandle<()>>,
+}
+
+impl RegistryService {
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(join) = self.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+impl Drop for RegistryService {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Поднимает канал обнаружения `name`. Каждому подключившемуся клиенту
+/// отправляется актуальный [`list`], после чего служба ждёт его отключения
+/// (не дольше `REPLY_LINGER`) и принимает следующего.
+pub fn serve(name: &str) -> Result<RegistryService> {
+    let mut server = SharedServer::start(name)?;
+    let running = Arc::new(AtomicBool::new(true));
+    let worker_running = running.clone();
+    #[cfg_attr(not(debug_assertions), allow(unused_mut))]
+    let mut builder = thread::Builder::new();
+    #[cfg(debug_assertions)]
+    {
+        builder = builder.name(format!("xsr-{name}"));
+    }
+    let join = builder
+        .spawn(move || serve_loop(&mut server, &worker_running))
+        .map_err(|err| ShmError::WindowsError {
+            code: err.raw_os_error().unwrap_or(-1) as u32,
+            context: "spawn registry worker",
+        })?;
+    Ok(RegistryService {
+        running,
+        join: Some(join),
+    })
+}
+
+fn serve_loop(server: &mut SharedServer, running: &AtomicBool) {
+    while running.load(Ordering::Acquire) {
+        match server.wait_for_client(Some(POLL_TIMEOUT)) {
+            Ok(()) => {}
+            Err(ShmError::Timeout) => continue,
+            Err(_) => {
+                server.mark_disconnected();
+                continue;
+            }
+        }
+        let _ = server.send_to_client(&encode(&list()));
+        if let Some(events) = server.events() {
+            let _ = events.disconnect.wait(Some(REPLY_LINGER));
+        }
+        server.mark_disconnected();
+    }
+}
+
+/// Запрашивает список сервисов у службы обнаружения `name` (обычно
+/// [`REGISTRY_CHANNEL`]). `timeout` ограничивает и подключение, и ответ.
+pub fn query(name: &str, timeout: Duration) -> Result<Vec<ServiceInfo>> {
+    let deadline = Instant::now() + timeout;
+    let client = SharedClient::connect(name, timeout)?;
+    let mut buffer = Vec::with_capacity(MAX_MESSAGE_SIZE);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !client.poll_server(Some(remaining))? {
+            return Err(ShmError::Timeout);
+        }
+        // Событие данных могло остаться взведённым от прошлого запроса:
+        // пустое кольцо -- ждём дальше.
+        match client.receive_from_server(&mut buffer) {
+            Ok(len) => return decode(&buffer[..len]),
+            Err(ShmError::QueueEmpty) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto::{AutoHandler, AutoOptions, AutoServer};
+
+    struct Noop;
+    impl AutoHandler for Noop {}
+
+    fn unique(prefix: &str) -> String {
+        format!("{prefix}_{}", std::process::id())
+    }
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let services = vec![
+            ServiceInfo {
+                name: "alpha".into(),
+                kind: ServiceKind::Auto,
+                pid: 42,
+                stats: Some(AutoStatsSnapshot {
+                    sent_messages: 1,
+                    connects: 2,
+                    ..Default::default()
+                }),
+            },
+            ServiceInfo {
+                name: "beta".into(),
+                kind: ServiceKind::Dispatch,
+                pid: 42,
+                stats: None,
+            },
+        ];
+        assert_eq!(decode(&encode(&services)).unwrap(), services);
+        assert_eq!(decode(&[1, 0, 9]), Err(ShmError::Corrupted));
+    }
+
+    #[test]
+    fn servers_register_and_vanish_on_drop() {
+        let name = unique("XSHM_REG_AUTO");
+        let server = AutoServer::start(&name, Arc::new(Noop), AutoOptions::default()).unwrap();
+        let listed = list();
+        let entry = listed.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(entry.kind, ServiceKind::Auto);
+        assert_eq!(entry.pid, std::process::id());
+        assert!(entry.stats.is_some());
+
+        drop(server);
+        assert!(list().iter().all(|s| s.name != name));
+    }
+
+    struct NoopMulti;
+    impl crate::multi::MultiHandler for NoopMulti {
+        fn on_client_connect(&self, _client_id: u32) {}
+        fn on_client_disconnect(&self, _client_id: u32) {}
+        fn on_message(&self, _client_id: u32, _data: &[u8]) {}
+    }
+
+    #[test]
+    fn stopped_multi_server_is_not_listed() {
+        let name = unique("XSHM_REG_MULTI");
+        let options = crate::multi::MultiOptions {
+            max_clients: 1,
+            ..Default::default()
+        };
+        let server = crate::multi::MultiServer::start(&name, Arc::new(NoopMulti), options).unwrap();
+        let entry = list().into_iter().find(|s| s.name == name).unwrap();
+        assert_eq!(entry.kind, ServiceKind::Multi);
+        assert_eq!(entry.stats, None);
+
+        server.stop();
+        assert!(list().iter().all(|s| s.name != name));
+    }
+
+    #[test]
+    fn query_returns_services_of_serving_process() {
+        let service_name = unique("XSHM_REG_QUERY_SVC");
+        let registry_name = unique("XSHM_REG_QUERY");
+        let _server =
+            AutoServer::start(&service_name, Arc::new(Noop), AutoOptions::default()).unwrap();
+        let _registry = serve(&registry_name).unwrap();
+
+        // Два запроса подряд: служба отвечает каждому новому клиенту.
+        for _ in 0..2 {
+            let services = query(&registry_name, Duration::from_secs(2)).unwrap();
+            assert!(services
+                .iter()
+                .any(|s| s.name == service_name && s.kind == ServiceKind::Auto));
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! Подтверждаемая доставка с повторной отправкой (at-least-once).
+//!
+//! Кольцо теряет сообщения при переполнении (`Overwrite`) и вместе с
+//! упавшим процессом. Для событий, которые терять нельзя (биллинг),
+//! поверх любого канала включается надёжный режим:
+//!
+//! - [`ReliableSender`] нумерует сообщения (`seq` с 1) и держит каждое
+//!   у себя, пока получатель его не подтвердит;
+//! - [`ReliableReceiver`] отбрасывает дубликаты и шлёт в обратном
+//!   направлении подтверждения -- диапазоны принятых `seq`;
+//! - неподтверждённое отправляется повторно: по таймауту
+//!   ([`ReliableSender::retransmit`]) или целиком после переподключения
+//!   ([`ReliableSender::resend_all`]).
+//!
+//! Состояние живёт в самих объектах, а не в канале: отправитель
+//! переживает перезапуск сервера -- после переподключения тот же
+//! `ReliableSender` досылает всё неподтверждённое новому каналу. Новый
+//! получатель ничего не знает о прошлом, поэтому сообщение, принятое, но
+//! не подтверждённое до падения, может прийти второй раз (at-least-once).
+//! Каждый отправитель получает случайный `session`: перезапущенный
+//! отправитель начинает нумерацию заново, и получатель сбрасывает свои
+//! диапазоны вместо того, чтобы принять новые сообщения за дубликаты.
+//!
+//! Кадр данных: `[0xA1][session u64][seq u64][payload]`, подтверждение:
+//! `[0xA2][session u64][count u8][(first u64, last u64) * count]`, всё
+//! little-endian. Оба типа кадров могут идти по одному направлению --
+//! их различает [`Frame::parse`].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use xshm::reliable::{ReliableOptions, ReliableReceiver, ReliableSender};
+//! # fn demo(server: &xshm::SharedServer, client: &xshm::SharedClient) -> xshm::Result<()> {
+//! let mut tx = ReliableSender::new(ReliableOptions::default());
+//! tx.send(client, b"charge:42")?;
+//!
+//! let mut rx = ReliableReceiver::new();
+//! let mut buffer = Vec::new();
+//! let len = server.receive_from_client(&mut buffer)?;
+//! if let Some(payload) = rx.accept(&buffer[..len])? {
+//!     println!("event {payload:?}");
+//! }
+//! rx.send_ack(server)?;
+//!
+//! let len = client.receive_from_server(&mut buffer)?;
+//! tx.handle_ack(&buffer[..len])?;
+//! assert_eq!(tx.pending(), 0);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::constants::MAX_MESSAGE_SIZE;
+use crate::error::{Result, ShmError};
+use crate::record::SendEndpoint;
+
+const TAG_DATA: u8 = 0xA1;
+const TAG_ACK: u8 = 0xA2;
+
+/// Заголовок кадра данных: тег, session, seq.
+pub const RELIABLE_HEADER_SIZE: usize = 1 + 8 + 8;
+/// Наибольший payload в надёжном режиме.
+pub const MAX_RELIABLE_PAYLOAD: usize = MAX_MESSAGE_SIZE - RELIABLE_HEADER_SIZE;
+/// Сколько диапазонов помнит получатель (и шлёт в одном подтверждении).
+/// При большем числе разрывов старейшие забываются: их повтор будет
+/// принят как новое сообщение.
+pub const MAX_ACK_RANGES: usize = 32;
+
+/// Параметры отправителя.
+#[derive(Debug, Clone)]
+pub struct ReliableOptions {
+    /// Через сколько неподтверждённое сообщение отправляется повторно.
+    pub retransmit_after: Duration,
+    /// Сколько неподтверждённых сообщений держать; дальше `send`
+    /// возвращает `ShmError::QueueFull`.
+    pub max_pending: usize,
+}
+
+impl Default for ReliableOptions {
+    fn default() -> Self {
+        Self {
+            retransmit_after: Duration::from_millis(200),
+            max_pending: 4096,
+        }
+    }
+}
+
+/// Разобранный кадр надёжного режима.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame<'a> {
+    Data {
+        session: u64,
+        seq: u64,
+        payload: &'a [u8],
+    },
+    Ack {
+        session: u64,
+        ranges: Vec<(u64, u64)>,
+    },
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|raw| u64::from_le_bytes(raw.try_into().unwrap()))
+        .ok_or_else(|| ShmError::DecodeFailed("truncated reliable frame".to_owned()))
+}
+
+impl<'a> Frame<'a> {
+    /// Разбирает кадр; чужой тег или обрезанный кадр --
+    /// `ShmError::DecodeFailed`.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let session = read_u64(bytes, 1)?;
+        match bytes[0] {
+            TAG_DATA => Ok(Frame::Data {
+                session,
+                seq: read_u64(bytes, 9)?,
+                payload: &bytes[RELIABLE_HEADER_SIZE..],
+            }),
+            TAG_ACK => {
+                let count = *bytes
+                    .get(9)
+                    .ok_or_else(|| ShmError::DecodeFailed("truncated ack".to_owned()))?;
+                let ranges = (0..count as usize)
+                    .map(|index| {
+                        let offset = 10 + index * 16;
+                        Ok((read_u64(bytes, offset)?, read_u64(bytes, offset + 8)?))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Frame::Ack { session, ranges })
+            }
+            tag => Err(ShmError::DecodeFailed(format!(
+                "unknown reliable frame tag {tag:#04x}"
+            ))),
+        }
+    }
+}
+
+fn new_session() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    // Время + PID: два отправителя, стартовавшие в одну наносекунду,
+    // всё равно различаются.
+    nanos ^ (u64::from(std::process::id()) << 40) ^ crate::platform::monotonic_ns()
+}
+
+struct Pending {
+    frame: Vec<u8>,
+    sent_at: Option<Instant>,
+}
+
+/// Отправляющая сторона надёжного режима.
+///
+/// Endpoint передаётся в каждый вызов, а не хранится: после
+/// переподключения тот же отправитель работает с новым каналом.
+pub struct ReliableSender {
+    options: ReliableOptions,
+    session: u64,
+    next_seq: u64,
+    pending: BTreeMap<u64, Pending>,
+}
+
+impl ReliableSender {
+    pub fn new(options: ReliableOptions) -> Self {
+        Self {
+            options,
+            session: new_session(),
+            next_seq: 1,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn session(&self) -> u64 {
+        self.session
+    }
+
+    /// Нумерует, запоминает и отправляет `payload`; возвращает его `seq`.
+    ///
+    /// Ошибка канала (нет клиента, кольцо полно) сообщение не теряет: оно
+    /// остаётся неподтверждённым и уйдёт при `retransmit`/`resend_all`.
+    /// Ошибки -- только `MessageTooLarge` и `QueueFull` (набралось
+    /// `max_pending` неподтверждённых).
+    pub fn send<E: SendEndpoint>(&mut self, endpoint: &E, payload: &[u8]) -> Result<u64> {
+        if payload.len() > MAX_RELIABLE_PAYLOAD {
+            return Err(ShmError::MessageTooLarge);
+        }
+        if self.pending.len() >= self.options.max_pending {
+            return Err(ShmError::QueueFull);
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let mut frame = Vec::with_capacity(RELIABLE_HEADER_SIZE + payload.len());
+        frame.push(TAG_DATA);
+        frame.extend_from_slice(&self.session.to_le_bytes());
+        frame.extend_from_slice(&seq.to_le_bytes());
+        frame.extend_from_slice(payload);
+        let sent_at = endpoint.send_payload(&frame).ok().map(|()| Instant::now());
+        self.pending.insert(seq, Pending { frame, sent_at });
+        Ok(seq)
+    }
+
+    /// Снимает подтверждённые сообщения; возвращает, сколько снято.
+    /// Подтверждение чужой сессии (от прошлого отправителя) игнорируется.
+    pub fn handle_ack(&mut self, frame: &[u8]) -> Result<usize> {
+        let Frame::Ack { session, ranges } = Frame::parse(frame)? else {
+            return Err(ShmError::DecodeFailed("expected reliable ack".to_owned()));
+        };
+        if session != self.session {
+            return Ok(0);
+        }
+        let before = self.pending.len();
+        for (first, last) in ranges {
+            let acked: Vec<u64> = self
+                .pending
+                .range(first..=last)
+                .map(|(&seq, _)| seq)
+                .collect();
+            for seq in acked {
+                self.pending.remove(&seq);
+            }
+        }
+        Ok(before - self.pending.len())
+    }
+
+    /// Повторно отправляет сообщения, не подтверждённые дольше
+    /// `retransmit_after` (и те, что не удалось отправить); возвращает,
+    /// сколько ушло. Останавливается на первой ошибке канала.
+    pub fn retransmit<E: SendEndpoint>(&mut self, endpoint: &E) -> Result<usize> {
+        let now = Instant::now();
+        let after = self.options.retransmit_after;
+        self.resend(endpoint, |sent_at| {
+            sent_at.is_none_or(|sent_at| now.duration_since(sent_at) >= after)
+        })
+    }
+
+    /// Отправляет все неподтверждённые сообщения заново -- после
+    /// переподключения к (перезапущенному) серверу.
+    pub fn resend_all<E: SendEndpoint>(&mut self, endpoint: &E) -> Result<usize> {
+        self.resend(endpoint, |_| true)
+    }
+
+    fn resend<E: SendEndpoint>(
+        &mut self,
+        endpoint: &E,
+        due: impl Fn(Option<Instant>) -> bool,
+    ) -> Result<usize> {
+        let mut sent = 0;
+        for pending in self.pending.values_mut() {
+            if !due(pending.sent_at) {
+                continue;
+            }
+            endpoint.send_payload(&pending.frame)?;
+            pending.sent_at = Some(Instant::now());
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Число неподтверждённых сообщений.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Наименьший неподтверждённый `seq`.
+    pub fn oldest_pending(&self) -> Option<u64> {
+        self.pending.keys().next().copied()
+    }
+}
+
+/// Принимающая сторона надёжного режима.
+#[derive(Debug, Default)]
+pub struct ReliableReceiver {
+    session: Option<u64>,
+    /// Принятые `seq`: непересекающиеся диапазоны по возрастанию.
+    ranges: Vec<(u64, u64)>,
+    duplicates: u64,
+}
+
+impl ReliableReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Разбирает кадр данных: `Some(payload)` для нового сообщения,
+    /// `None` для дубликата. Кадр другой сессии сбрасывает историю --
+    /// отправитель перезапущен.
+    pub fn accept<'a>(&mut self, frame: &'a [u8]) -> Result<Option<&'a [u8]>> {
+        let Frame::Data {
+            session,
+            seq,
+            payload,
+        } = Frame::parse(frame)?
+        else {
+            return Err(ShmError::DecodeFailed("expected reliable data".to_owned()));
+        };
+        if self.session != Some(session) {
+            self.session = Some(session);
+            self.ranges.clear();
+        }
+        if self.insert(seq) {
+            Ok(Some(payload))
+        } else {
+            self.duplicates += 1;
+            Ok(None)
+        }
+    }
+
+    /// Добавляет `seq` в диапазоны; `false`, если он уже был.
+    fn insert(&mut self, seq: u64) -> bool {
+        let index = self.ranges.partition_point(|&(_, last)| last < seq);
+        if self
+            .ranges
+            .get(index)
+            .is_some_and(|&(first, _)| first <= seq)
+        {
+            return false;
+        }
+        let joins_prev = index > 0 && self.ranges[index - 1].1 + 1 == seq;
+        let joins_next = self
+            .ranges
+            .get(index)
+            .is_some_and(|&(first, _)| first == seq + 1);
+        match (joins_prev, joins_next) {
+            (true, true) => {
+                self.ranges[index - 1].1 = self.ranges[index].1;
+                self.ranges.remove(index);
+            }
+            (true, false) => self.ranges[index - 1].1 = seq,
+            (false, true) => self.ranges[index].0 = seq,
+            (false, false) => {
+                self.ranges.insert(index, (seq, seq));
+                if self.ranges.len() > MAX_ACK_RANGES {
+                    self.ranges.remove(0);
+                }
+            }
+        }
+        true
+    }
+
+    /// Кадр подтверждения всех принятых диапазонов; `None`, пока не
+    /// принято ни одного сообщения.
+    pub fn ack_frame(&self) -> Option<Vec<u8>> {
+        let session = self.session?;
+        let mut frame = Vec::with_capacity(10 + self.ranges.len() * 16);
+        frame.push(TAG_ACK);
+        frame.extend_from_slice(&session.to_le_bytes());
+        frame.push(self.ranges.len() as u8);
+        for &(first, last) in &self.ranges {
+            frame.extend_from_slice(&first.to_le_bytes());
+            frame.extend_from_slice(&last.to_le_bytes());
+        }
+        Some(frame)
+    }
+
+    /// Отправляет подтверждение в обратном направлении. Подтверждения
+    /// кумулятивны: потерянное восполнится следующим.
+    pub fn send_ack<E: SendEndpoint>(&self, endpoint: &E) -> Result<()> {
+        match self.ack_frame() {
+            Some(frame) => endpoint.send_payload(&frame),
+            None => Ok(()),
+        }
+    }
+
+    /// Принятые диапазоны `seq` текущей сессии.
+    pub fn ranges(&self) -> &[(u64, u64)] {
+        &self.ranges
+    }
+
+    /// Сколько дубликатов отброшено.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedClient, SharedServer};
+
+    fn connected_pair(name: &str) -> (SharedServer, SharedClient) {
+        let mut server = SharedServer::start(name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.to_owned();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        (server, connector.join().unwrap())
+    }
+
+    fn drain(server: &SharedServer, rx: &mut ReliableReceiver) -> Vec<Vec<u8>> {
+        let mut buffer = Vec::new();
+        let mut delivered = Vec::new();
+        while let Ok(len) = server.receive_from_client(&mut buffer) {
+            if let Some(payload) = rx.accept(&buffer[..len]).unwrap() {
+                delivered.push(payload.to_vec());
+            }
+        }
+        delivered
+    }
+
+    fn deliver_ack(
+        server: &SharedServer,
+        client: &SharedClient,
+        tx: &mut ReliableSender,
+        rx: &ReliableReceiver,
+    ) -> usize {
+        rx.send_ack(server).unwrap();
+        let mut buffer = Vec::new();
+        let len = client.receive_from_server(&mut buffer).unwrap();
+        tx.handle_ack(&buffer[..len]).unwrap()
+    }
+
+    /// Endpoint без соединения: любая отправка -- `NotConnected`.
+    fn disconnected() -> impl SendEndpoint {
+        struct Down;
+        impl SendEndpoint for Down {
+            const DIRECTION: crate::ChannelKind = crate::ChannelKind::ClientToServer;
+
+            fn send_payload(&self, _payload: &[u8]) -> Result<()> {
+                Err(ShmError::NotConnected)
+            }
+        }
+        Down
+    }
+
+    #[test]
+    fn receiver_merges_ranges_and_drops_duplicates() {
+        let mut tx = ReliableSender::new(ReliableOptions::default());
+        let frames: Vec<Vec<u8>> = (0..5)
+            .map(|index| {
+                let seq = tx.next_seq;
+                tx.next_seq += 1;
+                let mut frame = vec![TAG_DATA];
+                frame.extend_from_slice(&tx.session.to_le_bytes());
+                frame.extend_from_slice(&seq.to_le_bytes());
+                frame.push(index);
+                frame
+            })
+            .collect();
+
+        let mut rx = ReliableReceiver::new();
+        for index in [0, 2, 4, 2] {
+            rx.accept(&frames[index]).unwrap();
+        }
+        assert_eq!(rx.ranges(), &[(1, 1), (3, 3), (5, 5)]);
+        assert_eq!(rx.duplicates(), 1);
+        assert_eq!(rx.accept(&frames[1]).unwrap(), Some(&[1u8][..]));
+        assert_eq!(rx.accept(&frames[3]).unwrap(), Some(&[3u8][..]));
+        assert_eq!(rx.ranges(), &[(1, 5)]);
+        assert_eq!(rx.accept(&frames[0]).unwrap(), None);
+
+        // Новая сессия (перезапущенный отправитель) начинает заново.
+        let restarted = ReliableSender::new(ReliableOptions::default());
+        let mut frame = vec![TAG_DATA];
+        frame.extend_from_slice(&restarted.session().to_le_bytes());
+        frame.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(rx.accept(&frame).unwrap(), Some(&[][..]));
+        assert_eq!(rx.ranges(), &[(1, 1)]);
+
+        assert!(matches!(
+            rx.accept(&rx.ack_frame().unwrap()),
+            Err(ShmError::DecodeFailed(_))
+        ));
+        assert!(matches!(
+            Frame::parse(&[0xA1, 1]),
+            Err(ShmError::DecodeFailed(_))
+        ));
+    }
+
+    #[test]
+    fn unacked_messages_survive_server_restart() {
+        let name = format!("XSHM_RELIABLE_RESTART_{}", std::process::id());
+        let mut tx = ReliableSender::new(ReliableOptions {
+            retransmit_after: Duration::ZERO,
+            ..ReliableOptions::default()
+        });
+
+        let (server, client) = connected_pair(&name);
+        let mut rx = ReliableReceiver::new();
+        for payload in [&b"a"[..], b"b", b"c"] {
+            tx.send(&client, payload).unwrap();
+        }
+        assert_eq!(
+            drain(&server, &mut rx),
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(deliver_ack(&server, &client, &mut tx, &rx), 3);
+
+        // Сервер принимает "d" и падает, не успев подтвердить.
+        tx.send(&client, b"d").unwrap();
+        assert_eq!(drain(&server, &mut rx), vec![b"d".to_vec()]);
+        drop(client);
+        drop(server);
+        // Пока сервера нет, "e" не уходит, но и не теряется.
+        tx.send(&disconnected(), b"e").unwrap();
+        assert_eq!(tx.pending(), 2);
+
+        let (server, client) = connected_pair(&name);
+        let mut rx = ReliableReceiver::new();
+        assert_eq!(tx.resend_all(&client).unwrap(), 2);
+        assert_eq!(drain(&server, &mut rx), vec![b"d".to_vec(), b"e".to_vec()]);
+        // Повтор по таймауту приходит дубликатом и отбрасывается.
+        assert_eq!(tx.retransmit(&client).unwrap(), 2);
+        assert!(drain(&server, &mut rx).is_empty());
+        assert_eq!(rx.duplicates(), 2);
+
+        assert_eq!(deliver_ack(&server, &client, &mut tx, &rx), 2);
+        assert_eq!(tx.pending(), 0);
+        assert_eq!(tx.oldest_pending(), None);
+    }
+}
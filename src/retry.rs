@@ -0,0 +1,272 @@
+//! Паузы между попытками подключения: [`RetryPolicy`] для
+//! `SharedClient::connect_with_retry` и [`BackoffStrategy`] для
+//! переподключений Auto-клиента.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{ensure_config, Result, ShmError};
+use crate::platform;
+
+/// Сколько раз и с какими паузами повторять подключение
+/// ([`SharedClient::connect_with_retry`](crate::SharedClient::connect_with_retry)).
+///
+/// Пауза перед попыткой `n` (с нуля, после неудачной `n - 1`) --
+/// `initial_delay * multiplier^(n - 1)`, но не больше `max_delay`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    /// Всего попыток, включая первую; не меньше 1.
+    pub max_attempts: u32,
+    /// Пауза после первой неудачи.
+    pub initial_delay: Duration,
+    /// Потолок паузы.
+    pub max_delay: Duration,
+    /// Во сколько раз растёт пауза после каждой неудачи; не меньше 1.
+    pub multiplier: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            multiplier: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<()> {
+        ensure_config(self.max_attempts > 0, "max_attempts must be non-zero")?;
+        ensure_config(self.multiplier > 0, "multiplier must be non-zero")?;
+        ensure_config(
+            self.initial_delay <= self.max_delay,
+            "initial_delay must not exceed max_delay",
+        )
+    }
+
+    /// Пауза после неудачной попытки `failed` (с нуля).
+    pub fn delay(&self, failed: u32) -> Duration {
+        exponential(self.initial_delay, self.max_delay, self.multiplier, failed)
+    }
+
+    /// Выполняет `attempt`, пока она не удастся, не вернёт ошибку, которую
+    /// повторять бессмысленно, или не кончатся попытки (тогда -- последняя
+    /// ошибка). Повторяются «канала ещё нет» и `Timeout` handshake'а.
+    pub(crate) fn run<T>(&self, mut attempt: impl FnMut() -> Result<T>) -> Result<T> {
+        self.validate()?;
+        let mut failed = 0;
+        loop {
+            match attempt() {
+                Err(error) if failed + 1 < self.max_attempts && should_retry(&error) => {
+                    std::thread::sleep(self.delay(failed));
+                    failed += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn should_retry(error: &ShmError) -> bool {
+    matches!(error, ShmError::Timeout) || platform::is_not_found(error)
+}
+
+/// `initial * multiplier^step`, не больше `max`.
+fn exponential(initial: Duration, max: Duration, multiplier: u32, step: u32) -> Duration {
+    let factor = multiplier.checked_pow(step).unwrap_or(u32::MAX);
+    initial
+        .checked_mul(factor)
+        .map_or(max, |delay| delay.min(max))
+}
+
+/// Пауза перед переподключением по номеру неудачной попытки подряд (с 1).
+pub type BackoffFn = Arc<dyn Fn(u32) -> Duration + Send + Sync>;
+
+/// Пауза Auto-клиента между попытками подключения
+/// (`AutoOptions::backoff`). Счёт попыток начинается заново после
+/// каждого установленного соединения.
+#[derive(Clone)]
+pub enum BackoffStrategy {
+    /// Всегда одна и та же пауза.
+    Constant(Duration),
+    /// `initial * multiplier^(attempt - 1)`, не больше `max`. С `jitter`
+    /// пауза случайно укорачивается до половины, чтобы клиенты, потерявшие
+    /// один сервер, не переподключались строем.
+    Exponential {
+        initial: Duration,
+        max: Duration,
+        multiplier: u32,
+        jitter: bool,
+    },
+    /// Пауза от приложения; нулевая -- следующая попытка сразу.
+    Custom(BackoffFn),
+}
+
+impl BackoffStrategy {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Constant(delay) => {
+                ensure_config(!delay.is_zero(), "backoff delay must be non-zero")
+            }
+            Self::Exponential {
+                initial,
+                max,
+                multiplier,
+                ..
+            } => {
+                ensure_config(!initial.is_zero(), "backoff initial must be non-zero")?;
+                ensure_config(initial <= max, "backoff initial must not exceed max")?;
+                ensure_config(*multiplier > 0, "backoff multiplier must be non-zero")
+            }
+            Self::Custom(_) => Ok(()),
+        }
+    }
+
+    /// Пауза после неудачной попытки `attempt` (с 1) подряд.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match self {
+            Self::Constant(delay) => *delay,
+            Self::Exponential {
+                initial,
+                max,
+                multiplier,
+                jitter,
+            } => {
+                let delay = exponential(*initial, *max, *multiplier, attempt.saturating_sub(1));
+                if *jitter {
+                    let half = delay / 2;
+                    half + random_up_to(delay - half)
+                } else {
+                    delay
+                }
+            }
+            Self::Custom(delay) => delay(attempt),
+        }
+    }
+}
+
+/// Случайная длительность в `[0, limit]` (ключ `RandomState` -- из
+/// системного источника случайности).
+fn random_up_to(limit: Duration) -> Duration {
+    let nanos = limit.as_nanos() as u64;
+    if nanos == 0 {
+        return Duration::ZERO;
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(platform::monotonic_ns());
+    Duration::from_nanos(hasher.finish() % (nanos + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_exponentially_up_to_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            multiplier: 2,
+        };
+        let delays: Vec<_> = (0..5).map(|failed| policy.delay(failed)).collect();
+        assert_eq!(
+            delays,
+            [10, 20, 40, 50, 50].map(Duration::from_millis).to_vec()
+        );
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn backoff_strategies_compute_delays() {
+        let ms = Duration::from_millis;
+        assert_eq!(BackoffStrategy::Constant(ms(7)).delay(42), ms(7));
+
+        let exponential = BackoffStrategy::Exponential {
+            initial: ms(10),
+            max: ms(100),
+            multiplier: 3,
+            jitter: false,
+        };
+        let delays: Vec<_> = (1..=4).map(|attempt| exponential.delay(attempt)).collect();
+        assert_eq!(delays, [ms(10), ms(30), ms(90), ms(100)]);
+
+        let jittered = BackoffStrategy::Exponential {
+            initial: ms(10),
+            max: ms(100),
+            multiplier: 3,
+            jitter: true,
+        };
+        for attempt in 1..=4 {
+            let delay = jittered.delay(attempt);
+            let full = exponential.delay(attempt);
+            assert!(delay >= full / 2 && delay <= full, "{delay:?} vs {full:?}");
+        }
+
+        let custom =
+            BackoffStrategy::Custom(Arc::new(|attempt| Duration::from_millis(attempt as u64)));
+        assert_eq!(custom.delay(5), ms(5));
+
+        assert!(BackoffStrategy::Constant(Duration::ZERO)
+            .validate()
+            .is_err());
+        assert!(BackoffStrategy::Exponential {
+            initial: ms(10),
+            max: ms(5),
+            multiplier: 2,
+            jitter: false,
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn run_stops_on_success_fatal_error_or_attempt_limit() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            multiplier: 2,
+        };
+
+        let mut calls = 0;
+        let result = policy.run(|| {
+            calls += 1;
+            if calls < 2 {
+                Err(ShmError::Timeout)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(ShmError::Timeout)
+        });
+        assert!(matches!(result, Err(ShmError::Timeout)));
+        assert_eq!(calls, 3);
+
+        calls = 0;
+        let result: Result<()> = policy.run(|| {
+            calls += 1;
+            Err(ShmError::Corrupted)
+        });
+        assert!(matches!(result, Err(ShmError::Corrupted)));
+        assert_eq!(calls, 1);
+
+        let invalid = RetryPolicy {
+            max_attempts: 0,
+            ..policy
+        };
+        assert!(matches!(
+            invalid.run(|| Ok(())),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+}
@@ -1,465 +1,1018 @@
-//! Lock-free SPSC кольцевой буфер для shared memory IPC.
+//! Кольцо из `xshm-core` с ошибками `ShmError` и чтением в `Vec`.
 //!
-//! ВАЖНО: Код оптимизирован для x86/x86_64 с TSO (Total Store Order).
-//! На этих архитектурах stores видны в порядке программы, что упрощает
-//! синхронизацию. НЕ портировать на ARM/RISC-V без доработки!
+//! Сам алгоритм (и его loom-тесты) живёт в `xshm_core::ring`: тот же код
+//! собирается в kernel-mode драйвер, поэтому не аллоцирует. Часы для
+//! меток времени и сроков годности, гистограмма задержки -- здесь.
 
-use std::ptr::NonNull;
-use std::sync::atomic::{compiler_fence, Ordering};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::constants::*;
+use xshm_core::ring::{FrameInfo, Reservation, RingBuffer as CoreRing, RingError};
+
+use crate::constants::{
+    CHECKSUM_SIZE, DEADLINE_SIZE, MAX_MESSAGES, MAX_MESSAGE_SIZE, MESSAGE_HEADER_SIZE,
+    MSG_FLAG_CHUNK, MSG_FLAG_HANDLE, RING_CAPACITY, SEQUENCE_SIZE, TIMESTAMP_SIZE,
+};
 use crate::error::{Result, ShmError};
+use crate::latency::LatencyHistogram;
 use crate::layout::RingHeader;
+use crate::platform::{self, EventHandle, PlatformEvent};
+
+pub use xshm_core::ring::WriteOutcome;
+
+impl From<RingError> for ShmError {
+    fn from(err: RingError) -> Self {
+        match err {
+            RingError::QueueEmpty => ShmError::QueueEmpty,
+            RingError::QueueFull => ShmError::QueueFull,
+            RingError::MessageTooSmall => ShmError::MessageTooSmall,
+            RingError::MessageTooLarge => ShmError::MessageTooLarge,
+            RingError::Corrupted => ShmError::Corrupted,
+            RingError::ChecksumMismatch => ShmError::ChecksumMismatch,
+            // Чтение в Vec расширяет буфер само (см. read_frame).
+            RingError::BufferTooSmall { .. } => ShmError::MessageTooLarge,
+        }
+    }
+}
 
-#[derive(Debug, Clone, Copy)]
-pub struct WriteOutcome {
+/// Окно кредитного режима: сколько непрочитанных кадров и байт читатель
+/// разрешает держать в кольце. Writer, исчерпавший кредиты, получает
+/// `ShmError::QueueFull` вместо вытеснения старых кадров и ждёт
+/// space-события, которое читатель выставляет, вернув кредиты.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreditWindow {
+    /// 1..=`MAX_MESSAGES`.
+    pub messages: u32,
+    /// Не меньше кадра максимального сообщения (с трейлерами) и не
+    /// больше `RING_CAPACITY`.
+    pub bytes: u32,
+}
+
+impl CreditWindow {
+    /// Минимальное окно в байтах: один кадр `MAX_MESSAGE_SIZE` с трейлерами.
+    pub const MIN_BYTES: u32 = (MESSAGE_HEADER_SIZE
+        + MAX_MESSAGE_SIZE
+        + TIMESTAMP_SIZE
+        + DEADLINE_SIZE
+        + SEQUENCE_SIZE
+        + CHECKSUM_SIZE) as u32;
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !(1..=MAX_MESSAGES).contains(&self.messages) {
+            return Err(ShmError::InvalidConfig(
+                "credit window must allow 1..=MAX_MESSAGES messages",
+            ));
+        }
+        if !(Self::MIN_BYTES..=RING_CAPACITY as u32).contains(&self.bytes) {
+            return Err(ShmError::InvalidConfig(
+                "credit window bytes must fit a max-size frame and the ring",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Порог space-события -- доля ёмкости в процентах, 1..=100.
+pub(crate) fn ensure_space_threshold(percent: Option<u8>) -> Result<()> {
+    if percent.is_some_and(|percent| !(1..=100).contains(&percent)) {
+        return Err(ShmError::InvalidConfig(
+            "space threshold must be 1..=100 percent of the ring",
+        ));
+    }
+    Ok(())
+}
+
+/// Всё кольцо: кредиты только отменяют вытеснение.
+impl Default for CreditWindow {
+    fn default() -> Self {
+        Self {
+            messages: MAX_MESSAGES,
+            bytes: RING_CAPACITY as u32,
+        }
+    }
+}
+
+/// Что делает отправка, когда под сообщение в кольце нет места.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WritePolicy {
+    /// Вытеснить старейшие непрочитанные сообщения (счётчик `drop_count`).
+    #[default]
+    Overwrite,
+    /// Ничего не вытеснять, вернуть `ShmError::QueueFull`.
+    Fail,
+    /// Ничего не вытеснять, ждать space-события, пока пир не освободит
+    /// место, но не дольше таймаута; затем `ShmError::QueueFull`.
+    Block(Duration),
+}
+
+/// Занятость кольца на момент вызова (поля читаются по одному и у
+/// активного канала могут слегка расходиться) и её пик.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingStatus {
+    pub capacity: u32,
+    pub used_bytes: u32,
+    pub free_bytes: u32,
+    pub message_count: u32,
+    /// Наибольшая занятость, которую видела эта сторона (отправитель --
+    /// после своих записей, получатель -- перед приёмом) с создания
+    /// endpoint'а или `reset_high_water`.
+    pub high_water_bytes: u32,
+    pub high_water_messages: u32,
+}
+
+/// Счётчики сообщений одного кольца с последнего подключения (по модулю
+/// 2^32). Хранятся в секции, поэтому обе стороны видят одни и те же
+/// значения; поля читаются по одному, как у [`RingStatus`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectionStats {
+    /// Записанные отправителем сообщения.
+    pub sent: u32,
+    /// Сообщения, выданные получателю.
+    pub received: u32,
+    /// Вытесненные отправителем до прочтения (полное кольцо,
+    /// `WritePolicy::Overwrite`).
     pub overwritten: u32,
-    pub was_empty: bool,
+    /// Отброшенные без выдачи: несовпадение CRC-32 и истёкший срок годности.
+    pub dropped: u32,
+    /// Сообщения в кольце сейчас.
+    pub queue_depth: u32,
+}
+
+/// Счётчики обоих направлений endpoint'а (`SharedServer::stats`,
+/// `SharedClient::stats`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Исходящее bulk-кольцо.
+    pub tx: DirectionStats,
+    /// Входящее bulk-кольцо.
+    pub rx: DirectionStats,
 }
 
 pub struct RingBuffer {
-    header: NonNull<RingHeader>,
-    storage: NonNull<u8>,
-    capacity: u32,
+    inner: CoreRing,
+    /// Таймаут `WritePolicy::Block` (нс), 0 -- не ждать.
+    block_ns: AtomicU64,
+    /// Дописывать метку времени (`MSG_FLAG_TIMESTAMP`) к исходящим кадрам.
+    timestamps: AtomicBool,
+    /// Куда записывать задержку входящих кадров с меткой.
+    latency: Option<Arc<LatencyHistogram>>,
+    /// Срок годности исходящих кадров по умолчанию (нс), 0 -- бессрочно.
+    default_ttl_ns: AtomicU64,
+    /// Метка последнего прочитанного кадра, 0 -- без метки или уже забрана.
+    last_timestamp: AtomicU64,
+    /// Порог space-события читателя: свободных байт не меньше -- сигнал;
+    /// 0 -- только опустевшее кольцо.
+    space_threshold: AtomicU32,
+    /// Читатель видел свободного места меньше порога и ещё не сигналил.
+    space_armed: AtomicBool,
 }
 
-unsafe impl Send for RingBuffer {}
-unsafe impl Sync for RingBuffer {}
+fn ttl_nanos(ttl: Duration) -> u64 {
+    // Нулевой срок -- «истекает сразу», а не «бессрочно».
+    (ttl.as_nanos().min(u64::MAX as u128) as u64).max(1)
+}
+
+/// Зарезервированное в кольце сообщение: payload пишется прямо в
+/// разделяемую память через `&mut [u8]` (`DerefMut`), [`commit`](Self::commit)
+/// публикует его. Guard, брошенный без `commit`, ничего не публикует.
+///
+/// Payload, который переходит через конец кольца, собирается во
+/// временном буфере и копируется в кольцо при `commit`.
+pub struct WriteGuard<'a> {
+    ring: &'a RingBuffer,
+    reservation: Reservation,
+    payload: GuardPayload<'a>,
+    /// Событие данных пира: выставляется, если кольцо было пустым.
+    data_event: Option<&'a EventHandle>,
+}
+
+enum GuardPayload<'a> {
+    InPlace(&'a mut [u8]),
+    Wrapped(Vec<u8>),
+}
+
+impl<'a> WriteGuard<'a> {
+    pub(crate) fn signal_on_commit(mut self, event: Option<&'a EventHandle>) -> Self {
+        self.data_event = event;
+        self
+    }
+
+    /// Публикует сообщение.
+    pub fn commit(self) -> WriteOutcome {
+        let WriteGuard {
+            ring,
+            reservation,
+            payload,
+            data_event,
+        } = self;
+        // Срез в кольце отпускаем до фиксации: commit сам читает payload
+        // под CRC.
+        if let GuardPayload::Wrapped(buffer) = payload {
+            // SAFETY: срезы живут только до конца блока, других записей в
+            // кольцо нет (см. RingBuffer::reserve).
+            let (head, tail) = unsafe { ring.inner.reserved_payload(&reservation) };
+            let (first, rest) = buffer.split_at(head.len());
+            head.copy_from_slice(first);
+            tail.copy_from_slice(rest);
+        }
+        let outcome = ring.inner.commit(reservation);
+        if let Some(event) = data_event.filter(|_| outcome.was_empty) {
+            let _ = event.set();
+        }
+        outcome
+    }
+}
+
+impl Deref for WriteGuard<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.payload {
+            GuardPayload::InPlace(payload) => payload,
+            GuardPayload::Wrapped(buffer) => buffer,
+        }
+    }
+}
+
+impl DerefMut for WriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.payload {
+            GuardPayload::InPlace(payload) => payload,
+            GuardPayload::Wrapped(buffer) => buffer,
+        }
+    }
+}
 
 impl RingBuffer {
+    /// # Safety
+    /// См. `xshm_core::ring::RingBuffer::new`.
     pub unsafe fn new(header: *mut RingHeader, data: *mut u8) -> Self {
+        // SAFETY: требования переданы вызывающему.
+        Self::wrap(unsafe { CoreRing::new(header, data) })
+    }
+
+    /// # Safety
+    /// См. `xshm_core::ring::RingBuffer::with_capacity`.
+    pub unsafe fn with_capacity(header: *mut RingHeader, data: *mut u8, capacity: u32) -> Self {
+        // SAFETY: требования переданы вызывающему.
+        Self::wrap(unsafe { CoreRing::with_capacity(header, data, capacity) })
+    }
+
+    fn wrap(mut inner: CoreRing) -> Self {
+        // Писатель в очереди на публикацию уступает ядро тому, чья очередь.
+        inner.set_turn_backoff(std::thread::yield_now);
         RingBuffer {
-            header: NonNull::new(header).expect("header pointer must be valid"),
-            storage: NonNull::new(data).expect("ring buffer pointer must be valid"),
-            capacity: RING_CAPACITY as u32,
+            inner,
+            block_ns: AtomicU64::new(0),
+            timestamps: AtomicBool::new(false),
+            latency: None,
+            default_ttl_ns: AtomicU64::new(0),
+            last_timestamp: AtomicU64::new(0),
+            space_threshold: AtomicU32::new(0),
+            space_armed: AtomicBool::new(false),
         }
     }
 
-    fn header(&self) -> &RingHeader {
-        unsafe { self.header.as_ref() }
+    /// Включает/выключает CRC-32 трейлер для последующих записей.
+    pub fn set_checksum(&self, enabled: bool) {
+        self.inner.set_checksum(enabled);
     }
 
-    fn data_ptr(&self) -> *mut u8 {
-        self.storage.as_ptr()
+    /// Включает/выключает метку времени записи для последующих записей.
+    pub fn set_timestamps(&self, enabled: bool) {
+        self.timestamps.store(enabled, Ordering::Relaxed);
     }
 
-    #[allow(dead_code)]
-    pub fn capacity(&self) -> u32 {
-        self.capacity
+    /// Включает/выключает номер кадра для последующих записей (см.
+    /// `xshm_core::ring::RingBuffer::set_sequence_numbers`).
+    pub fn set_sequence_numbers(&self, enabled: bool) {
+        self.inner.set_sequence_numbers(enabled);
     }
 
-    #[allow(dead_code)]
-    pub fn reset(&self, generation: u32) {
-        self.header().reset(generation);
+    /// Включает/выключает запись из нескольких потоков (см.
+    /// `xshm_core::ring::RingBuffer::set_multi_producer`).
+    pub fn set_multi_producer(&self, enabled: bool) {
+        self.inner.set_multi_producer(enabled);
     }
 
-    fn available_bytes(&self, write: u32, read: u32) -> i64 {
-        let used = write.wrapping_sub(read);
-        self.capacity as i64 - used as i64
+    /// Гистограмма для задержки входящих кадров с меткой; `None` -- не
+    /// считать.
+    pub fn set_latency_histogram(&mut self, histogram: Option<Arc<LatencyHistogram>>) {
+        self.latency = histogram;
     }
 
-    fn mask_index(&self, pos: u32) -> usize {
-        (pos & RING_MASK) as usize
+    /// Срок годности (`MSG_FLAG_DEADLINE`) для последующих записей без
+    /// явного срока; `None` -- бессрочно. Handle-кадры всегда бессрочны.
+    pub fn set_default_ttl(&self, ttl: Option<Duration>) {
+        self.default_ttl_ns
+            .store(ttl.map_or(0, ttl_nanos), Ordering::Relaxed);
     }
 
-    /// # Safety
-    /// `index + data.len() <= capacity` (вызывающий код обязан гарантировать
-    /// отсутствие выхода за пределы `storage`; сам `copy_into` этого не
-    /// проверяет -- проверки границ выполняются в `copy_into_wrapped` через
-    /// модульную арифметику до вызова).
-    unsafe fn copy_into(&self, index: usize, data: &[u8]) {
-        // SAFETY: storage валиден на всё время жизни self (гарантия
-        // конструктора RingBuffer::new); index+data.len() <= capacity --
-        // инвариант вызывающей стороны (см. doc выше).
-        unsafe {
-            let ptr = self.data_ptr().add(index);
-            ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
+    /// Порог space-события в процентах ёмкости (см.
+    /// `SharedServer::set_space_threshold`); `None` -- только опустевшее
+    /// кольцо.
+    pub fn set_space_threshold(&self, percent: Option<u8>) -> Result<()> {
+        ensure_space_threshold(percent)?;
+        let bytes = percent.map_or(0, |percent| {
+            (self.inner.capacity() as u64 * percent as u64 / 100) as u32
+        });
+        self.space_threshold.store(bytes, Ordering::Relaxed);
+        self.space_armed.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Звать читателю после изъятия кадра: `true` -- пора будить writer'а.
+    /// Кольцо опустело или свободное место поднялось до порога после того,
+    /// как читатель видел его ниже (один сигнал на пересечение, а не на
+    /// каждый кадр).
+    pub fn space_available(&self) -> bool {
+        if self.inner.message_count() == 0 {
+            self.space_armed.store(false, Ordering::Relaxed);
+            return true;
+        }
+        let threshold = self.space_threshold.load(Ordering::Relaxed);
+        if threshold == 0 {
+            return false;
+        }
+        if self.inner.free_bytes() < threshold {
+            self.space_armed.store(true, Ordering::Relaxed);
+            false
+        } else {
+            self.space_armed.swap(false, Ordering::Relaxed)
         }
     }
 
-    /// # Safety
-    /// `index + dst.len() <= capacity` (см. `copy_into`).
-    unsafe fn copy_from(&self, index: usize, dst: &mut [u8]) {
-        // SAFETY: storage валиден на всё время жизни self; index+dst.len()
-        // <= capacity -- инвариант вызывающей стороны (см. doc выше).
-        unsafe {
-            let ptr = self.data_ptr().add(index);
-            dst.copy_from_slice(std::slice::from_raw_parts(ptr, dst.len()));
+    pub fn default_ttl(&self) -> Option<Duration> {
+        match self.default_ttl_ns.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
         }
     }
 
-    /// # Safety
-    /// `index < capacity` (читает 2 байта начиная с `index`, с wrap-around
-    /// через `copy_from_wrapped`, поэтому сам `index` не обязан оставлять
-    /// место под оба байта без переноса).
-    unsafe fn read_u16(&self, index: usize) -> u16 {
-        let mut buf = [0u8; 2];
-        // SAFETY: copy_from_wrapped сам обеспечивает wrap-around в пределах
-        // capacity -- единственное требование к index описано в doc выше.
-        unsafe { self.copy_from_wrapped(index, &mut buf) };
-        u16::from_le_bytes(buf)
+    /// Политика при нехватке места для последующих записей. Вытеснение
+    /// выключают и `Fail`, и `Block`; ждёт только
+    /// [`write_blocking`](Self::write_blocking), у которой есть событие.
+    pub fn set_write_policy(&self, policy: WritePolicy) {
+        let block_ns = match policy {
+            // Нулевой таймаут -- «не ждать», но всё же `Block`.
+            WritePolicy::Block(timeout) => ttl_nanos(timeout),
+            _ => 0,
+        };
+        self.block_ns.store(block_ns, Ordering::Relaxed);
+        self.inner.set_evict(policy == WritePolicy::Overwrite);
     }
 
-    /// # Safety
-    /// `data.len() <= capacity` (иначе один и тот же байт будет записан
-    /// дважды при переносе через границу кольца; вызывающий код -- ring.rs
-    /// сам, всегда после проверки `total_required <= self.capacity` в
-    /// `write_message`).
-    unsafe fn copy_into_wrapped(&self, start: usize, data: &[u8]) {
-        let capacity = self.capacity as usize;
-        let start = start % capacity;
-        let first = capacity - start;
-        if data.len() <= first {
-            // SAFETY: start+data.len() <= capacity -- проверено веткой if.
-            unsafe { self.copy_into(start, data) };
-        } else {
-            // SAFETY: обе части (`first` и остаток) укладываются в
-            // [0, capacity) по построению (start+first == capacity).
-            unsafe { self.copy_into(start, &data[..first]) };
-            unsafe { self.copy_into(0, &data[first..]) };
+    pub fn write_policy(&self) -> WritePolicy {
+        match self.block_ns.load(Ordering::Relaxed) {
+            0 if self.inner.evicts() => WritePolicy::Overwrite,
+            0 => WritePolicy::Fail,
+            nanos => WritePolicy::Block(Duration::from_nanos(nanos)),
         }
     }
 
-    /// # Safety
-    /// `dst.len() <= capacity` (см. `copy_into_wrapped`).
-    unsafe fn copy_from_wrapped(&self, start: usize, dst: &mut [u8]) {
-        let capacity = self.capacity as usize;
-        let start = start % capacity;
-        let first = capacity - start;
-        if dst.len() <= first {
-            // SAFETY: start+dst.len() <= capacity -- проверено веткой if.
-            unsafe { self.copy_from(start, dst) };
-        } else {
-            // SAFETY: обе части укладываются в [0, capacity) по построению
-            // (start+first == capacity), как и в copy_into_wrapped.
-            unsafe { self.copy_from(start, &mut dst[..first]) };
-            unsafe { self.copy_from(0, &mut dst[first..]) };
+    /// Повторяет `write`, пока она упирается в `QueueFull`, ожидая
+    /// `space` -- по `WritePolicy::Block` до таймаута, иначе один вызов.
+    /// Без события (anonymous-канал) не ждёт: `Block` -- как `Fail`.
+    pub fn write_blocking(
+        &self,
+        space: Option<&EventHandle>,
+        mut write: impl FnMut() -> Result<WriteOutcome>,
+    ) -> Result<WriteOutcome> {
+        let (WritePolicy::Block(timeout), Some(space)) = (self.write_policy(), space) else {
+            return write();
+        };
+        let deadline = Instant::now() + timeout;
+        loop {
+            match write() {
+                Err(ShmError::QueueFull) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    // Событие могло остаться выставленным с прошлого раза --
+                    // тогда пробуждение ложное, и запись просто повторится.
+                    if left.is_zero() || !space.wait(Some(left))? {
+                        return write();
+                    }
+                }
+                result => return result,
+            }
         }
     }
 
-    fn discard_oldest(&self) -> Result<()> {
-        let header = self.header();
-
+    /// Повторяет `write`, пока она упирается в `QueueFull`, ожидая `space`
+    /// не дольше `timeout` при любой `WritePolicy`; не дождался места --
+    /// `ShmError::Timeout`. Без события (anonymous-канал) опрашивает кольцо
+    /// раз в миллисекунду.
+    pub fn write_within(
+        &self,
+        space: Option<&EventHandle>,
+        timeout: Duration,
+        mut write: impl FnMut() -> Result<WriteOutcome>,
+    ) -> Result<WriteOutcome> {
+        let deadline = Instant::now() + timeout;
         loop {
-            let read = header.read_pos.load(Ordering::Acquire);
-            let write = header.write_pos.load(Ordering::Acquire);
-            if read == write {
-                return Err(ShmError::QueueEmpty);
+            match write() {
+                Err(ShmError::QueueFull) => {}
+                result => return result,
             }
-
-            let idx = self.mask_index(read);
-            // SAFETY: idx = read & RING_MASK всегда < capacity (mask_index).
-            let msg_len = unsafe { self.read_u16(idx) } as usize;
-            if !(MIN_MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&msg_len) {
-                // Повреждённая длина в слоте. Не трогаем общий message_count
-                // деструктивно (его двигает и reader). Сигналим Corrupted —
-                // вызывающий код решает (auto-mode трактует как fatal -> reconnect,
-                // что сбросит буферы через handshake/generation).
-                return Err(ShmError::Corrupted);
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(ShmError::Timeout);
             }
-            let total = MESSAGE_HEADER_SIZE + msg_len;
-            let new_read = read.wrapping_add(total as u32);
-
-            // CAS to avoid racing with read_message on the reader side
-            if header
-                .read_pos
-                .compare_exchange(read, new_read, Ordering::AcqRel, Ordering::Acquire)
-                .is_ok()
-            {
-                header.message_count.fetch_sub(1, Ordering::AcqRel);
-                header.drop_count.fetch_add(1, Ordering::Relaxed);
-                return Ok(());
+            // Ложное пробуждение (событие осталось с прошлого раза) -- просто
+            // ещё одна попытка записи.
+            match space {
+                Some(space) => {
+                    space.wait(Some(left))?;
+                }
+                None => std::thread::sleep(left.min(Duration::from_millis(1))),
             }
-            // CAS failed — reader moved read_pos, retry with fresh values
         }
     }
 
-    pub fn write_message(&self, payload: &[u8]) -> Result<WriteOutcome> {
-        if payload.len() < MIN_MESSAGE_SIZE {
-            return Err(ShmError::MessageTooSmall);
-        }
-        if payload.len() > MAX_MESSAGE_SIZE {
-            return Err(ShmError::MessageTooLarge);
-        }
+    #[allow(dead_code)]
+    pub fn capacity(&self) -> u32 {
+        self.inner.capacity()
+    }
 
-        let total_required = (MESSAGE_HEADER_SIZE + payload.len()) as u32;
-        if total_required > self.capacity {
-            return Err(ShmError::MessageTooLarge);
-        }
+    #[allow(dead_code)]
+    pub fn reset(&self, generation: u32) {
+        self.inner.reset(generation);
+    }
 
-        let header = self.header();
-        let mut overwritten = 0u32;
+    pub fn write_message(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.write_frame(payload, 0)
+    }
 
-        loop {
-            let write = header.write_pos.load(Ordering::Acquire);
-            let read = header.read_pos.load(Ordering::Acquire);
-            let available = self.available_bytes(write, read);
-            let count = header.message_count.load(Ordering::Acquire);
-
-            if available < total_required as i64 || count >= MAX_MESSAGES {
-                if count == 0 {
-                    // нет сообщений, но не хватает места — значит сообщение больше буфера
-                    return Err(ShmError::MessageTooLarge);
-                }
-                self.discard_oldest()?;
-                overwritten += 1;
-                continue;
-            }
+    /// Запись с библиотечными флагами заголовка (`MSG_FLAG_HANDLE`);
+    /// `MSG_FLAG_CHECKSUM`, `MSG_FLAG_TIMESTAMP`, `MSG_FLAG_DEADLINE` и
+    /// `MSG_FLAG_SEQUENCE` добавляются по настройке кольца.
+    pub fn write_frame(&self, payload: &[u8], extra_flags: u16) -> Result<WriteOutcome> {
+        let ttl = if extra_flags & MSG_FLAG_HANDLE != 0 {
+            None
+        } else {
+            self.default_ttl()
+        };
+        self.write_frame_ttl(payload, extra_flags, ttl)
+    }
 
-            let idx = self.mask_index(write);
-            let len_le = (payload.len() as u16).to_le_bytes();
-            let flags = 0u16.to_le_bytes();
-            // SAFETY: каждый вызов copy_into_wrapped пишет <= capacity байт
-            // (len_le/flags -- по 2 байта, payload -- не более MAX_MESSAGE_SIZE,
-            // и total_required = MESSAGE_HEADER_SIZE+payload.len() уже
-            // проверен против self.capacity веткой availability-проверки выше).
-            unsafe {
-                self.copy_into_wrapped(idx, &len_le);
-                self.copy_into_wrapped((idx + 2) & (RING_MASK as usize), &flags);
-                self.copy_into_wrapped((idx + MESSAGE_HEADER_SIZE) & (RING_MASK as usize), payload);
-            }
+    /// Запись, которая никогда не вытесняет непрочитанное, какой бы ни была
+    /// `WritePolicy`: не хватает места -- сразу `QueueFull`, ничего не
+    /// записано и не потеряно.
+    pub fn try_write_message(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        let (timestamp, deadline) = self.stamps(self.default_ttl());
+        Ok(self
+            .inner
+            .try_write_frame_timed(payload, 0, timestamp, deadline)?)
+    }
 
-            // ВАЖНО: сначала увеличиваем message_count, потом обновляем write_pos
-            // Это гарантирует, что reader увидит count > 0 когда видит новый write_pos
-            // На x86/x64 TSO это безопасно, но порядок операций всё равно важен
-            let prev_count = header.message_count.fetch_add(1, Ordering::AcqRel);
+    /// Запись со своим сроком годности вместо срока по умолчанию; `None`
+    /// -- бессрочно.
+    pub fn write_message_ttl(&self, payload: &[u8], ttl: Option<Duration>) -> Result<WriteOutcome> {
+        self.write_frame_ttl(payload, 0, ttl)
+    }
 
-            let new_write = write.wrapping_add(total_required);
-            header.write_pos.store(new_write, Ordering::Release);
+    fn write_frame_ttl(
+        &self,
+        payload: &[u8],
+        extra_flags: u16,
+        ttl: Option<Duration>,
+    ) -> Result<WriteOutcome> {
+        let (timestamp, deadline) = self.stamps(ttl);
+        Ok(self
+            .inner
+            .write_frame_timed(payload, extra_flags, timestamp, deadline)?)
+    }
 
-            if prev_count == 0 {
-                header.sequence.fetch_add(1, Ordering::Relaxed);
-            }
+    /// Метка времени (если включена) и срок годности для записи сейчас.
+    fn stamps(&self, ttl: Option<Duration>) -> (Option<u64>, Option<u64>) {
+        let stamped = self.timestamps.load(Ordering::Relaxed);
+        let now = (stamped || ttl.is_some()).then(platform::monotonic_ns);
+        let deadline = ttl.zip(now).map(|(ttl, now)| {
+            // Пока пишем, заодно убираем просроченное из головы очереди:
+            // читатель, который стоит, не должен держать место под мусор.
+            self.inner.purge_expired(now);
+            now.saturating_add(ttl_nanos(ttl))
+        });
+        (now.filter(|_| stamped), deadline)
+    }
 
-            return Ok(WriteOutcome {
-                overwritten,
-                was_empty: prev_count == 0,
-            });
+    /// Сообщения по одному, как `write_message` (по `WritePolicy`, без ожидания),
+    /// но пира будят один раз: `wake` зовётся в конце, если хоть одна
+    /// запись застала кольцо пустым. Ошибка на очередном сообщении
+    /// прерывает проход; записанное до неё остаётся, и `wake` для него
+    /// всё равно вызывается.
+    pub fn write_messages(&self, payloads: &[&[u8]], wake: impl FnOnce()) -> Result<WriteOutcome> {
+        let mut total = WriteOutcome {
+            overwritten: 0,
+            was_empty: false,
+            free_bytes_after: self.inner.free_bytes(),
+            queue_len_after: self.inner.message_count(),
+        };
+        let result = payloads.iter().try_for_each(|payload| {
+            let outcome = self.write_message(payload)?;
+            total.overwritten += outcome.overwritten;
+            total.was_empty |= outcome.was_empty;
+            total.free_bytes_after = outcome.free_bytes_after;
+            total.queue_len_after = outcome.queue_len_after;
+            Ok(())
+        });
+        if total.was_empty {
+            wake();
         }
+        result.map(|()| total)
     }
 
+    /// Пачка кадров одной публикацией (см. `xshm_core::ring::RingBuffer::write_batch`):
+    /// читатель не увидит её частично. Метка времени и срок по умолчанию --
+    /// общие на пачку.
+    pub fn write_batch(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        let (timestamp, deadline) = self.stamps(self.default_ttl());
+        Ok(self.inner.write_batch(payloads, timestamp, deadline)?)
+    }
+
+    /// Место под сообщение длины `len` прямо в кольце (см. [`WriteGuard`]).
+    /// Место освобождается сразу, как при `write_message`; метка времени и
+    /// срок по умолчанию -- на момент резервации.
+    pub fn reserve(&self, len: usize) -> Result<WriteGuard<'_>> {
+        let (timestamp, deadline) = self.stamps(self.default_ttl());
+        let reservation = self.inner.reserve(len, 0, timestamp, deadline)?;
+        // SAFETY: WriteGuard держит &self, а пишущие endpoint'ы отдают его
+        // только из `&mut self` -- пока срез жив, других записей нет.
+        let (head, tail) = unsafe { self.inner.reserved_payload(&reservation) };
+        let payload = if tail.is_empty() {
+            GuardPayload::InPlace(head)
+        } else {
+            GuardPayload::Wrapped(vec![0; len])
+        };
+        Ok(WriteGuard {
+            ring: self,
+            reservation,
+            payload,
+            data_event: None,
+        })
+    }
+
+    /// Кусок большого сообщения (`MSG_FLAG_CHUNK` плюс флаги сообщения
+    /// `flags`, см. `chunking`). Как кадр пачки, не вытесняет старые: места
+    /// нет -- `ShmError::QueueFull`.
+    pub fn write_chunk(&self, chunk: &[u8], flags: u16) -> Result<WriteOutcome> {
+        let (timestamp, deadline) = self.stamps(self.default_ttl());
+        Ok(self
+            .inner
+            .write_batch_flagged(&[chunk], MSG_FLAG_CHUNK | flags, timestamp, deadline)?)
+    }
+
+    #[allow(dead_code)]
     pub fn read_message(&self, out: &mut Vec<u8>) -> Result<usize> {
-        let header = self.header();
+        self.read_frame(out).map(|(len, _)| len)
+    }
 
+    /// Чтение вместе с флагами заголовка (для различения handle-кадров).
+    /// `out` заменяется payload'ом; ёмкость растёт до размера кадра.
+    pub fn read_frame(&self, out: &mut Vec<u8>) -> Result<(usize, u16)> {
+        self.read_frame_info(out)
+            .map(|frame| (frame.len, frame.flags))
+    }
+
+    /// [`read_frame`](Self::read_frame) со всем заголовком кадра (метка
+    /// времени, срок, номер).
+    pub(crate) fn read_frame_info(&self, out: &mut Vec<u8>) -> Result<FrameInfo> {
+        out.clear();
         loop {
-            let count = header.message_count.load(Ordering::Acquire);
-            if count == 0 {
-                return Err(ShmError::QueueEmpty);
+            match self
+                .inner
+                .read_live_frame_uninit(out.spare_capacity_mut(), platform::monotonic_ns)
+            {
+                Ok(frame) => {
+                    // SAFETY: read_frame_info_uninit инициализировал первые
+                    // len байт.
+                    unsafe { out.set_len(frame.len) };
+                    self.note_frame(&frame);
+                    return Ok(frame);
+                }
+                Err(RingError::BufferTooSmall { required }) => out.reserve(required),
+                Err(err) => return Err(err.into()),
             }
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn read_message_with<T>(&self, mut f: impl FnMut(&[u8]) -> T) -> Result<T> {
+        self.read_frame_with(|payload, _| f(payload))
+    }
 
-            let read = header.read_pos.load(Ordering::Acquire);
-            let idx = self.mask_index(read);
-            // SAFETY: idx = read & RING_MASK всегда < capacity (mask_index).
-            let msg_len = unsafe { self.read_u16(idx) } as usize;
-            if !(MIN_MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&msg_len) {
-                // Длина могла быть «порвана» перезаписью producer-а. Если read_pos
-                // уже сдвинулся — это гонка перезаписи, повторяем. Иначе буфер
-                // действительно повреждён.
-                if header.read_pos.load(Ordering::Acquire) != read {
-                    continue;
+    /// Чтение без копии: `f` получает payload прямо из разделяемой памяти
+    /// и флаги заголовка. Только кадр через конец кольца собирается во
+    /// временный буфер. `f` может быть вызван повторно, если писатель
+    /// вытеснил кадр во время вызова (см.
+    /// `xshm_core::ring::RingBuffer::read_live_frame_with`).
+    pub fn read_frame_with<T>(&self, mut f: impl FnMut(&[u8], u16) -> T) -> Result<T> {
+        let mut scratch = Vec::new();
+        loop {
+            match self.inner.read_live_frame_with(
+                scratch.spare_capacity_mut(),
+                platform::monotonic_ns,
+                &mut f,
+            ) {
+                Ok((value, frame)) => {
+                    self.note_frame(&frame);
+                    return Ok(value);
                 }
-                return Err(ShmError::Corrupted);
+                Err(RingError::BufferTooSmall { required }) => scratch.reserve(required),
+                Err(err) => return Err(err.into()),
             }
+        }
+    }
 
-            let total = MESSAGE_HEADER_SIZE + msg_len;
-            let new_read = read.wrapping_add(total as u32);
-
-            // ОПТИМИСТИЧНОЕ копирование ДО фиксации read_pos (seqlock-паттерн).
-            // Если producer перезапишет слот во время копирования, CAS ниже
-            // провалится, и мы отбросим эту (потенциально битую) копию.
-            // SAFETY: clear + reserve гарантируют capacity >= msg_len; copy_from_wrapped
-            // читает строго в пределах кольца (wrap по модулю capacity).
-            out.clear();
-            out.reserve(msg_len);
-            debug_assert!(out.capacity() >= msg_len);
-            unsafe {
-                out.set_len(msg_len);
-                self.copy_from_wrapped(
-                    (idx + MESSAGE_HEADER_SIZE) & (RING_MASK as usize),
-                    out.as_mut_slice(),
-                );
-            }
+    /// Следующее сообщение без изъятия: копируется в `out`, `read_pos` не
+    /// двигается, и следующее чтение вернёт его же. Просроченные кадры по
+    /// дороге забираются, как при чтении.
+    #[allow(dead_code)]
+    pub fn peek_message(&self, out: &mut Vec<u8>) -> Result<usize> {
+        self.peek_frame_with(
+            |_| true,
+            |payload, _| {
+                out.clear();
+                out.extend_from_slice(payload);
+                payload.len()
+            },
+        )
+    }
 
-            // Барьер компилятора: копирование не должно «переехать» НИЖЕ CAS,
-            // иначе валидация теряет смысл. На x86 успешный lock cmpxchg также
-            // даёт аппаратный барьер.
-            compiler_fence(Ordering::Release);
-
-            // Фиксация: атомарно забираем слот. Провал => producer сдвинул read_pos
-            // (перезапись/конкурентный discard) => скопированные байты невалидны,
-            // повторяем с актуальными значениями.
-            if header
-                .read_pos
-                .compare_exchange(read, new_read, Ordering::AcqRel, Ordering::Acquire)
-                .is_err()
-            {
-                continue;
+    /// Как [`read_frame_with`](Self::read_frame_with), но кадр, флаги
+    /// которого `keep` оставляет, остаётся в кольце (см.
+    /// `xshm_core::ring::RingBuffer::peek_live_frame_with`).
+    pub fn peek_frame_with<T>(
+        &self,
+        mut keep: impl FnMut(u16) -> bool,
+        mut f: impl FnMut(&[u8], u16) -> T,
+    ) -> Result<T> {
+        let mut scratch = Vec::new();
+        loop {
+            match self.inner.peek_live_frame_with(
+                scratch.spare_capacity_mut(),
+                platform::monotonic_ns,
+                &mut keep,
+                &mut f,
+            ) {
+                Ok((value, frame)) => {
+                    // Задержку считает чтение, которое кадр заберёт.
+                    if !keep(frame.flags) {
+                        self.note_frame(&frame);
+                    }
+                    return Ok(value);
+                }
+                Err(RingError::BufferTooSmall { required }) => scratch.reserve(required),
+                Err(err) => return Err(err.into()),
             }
+        }
+    }
 
-            let prev_count = header.message_count.fetch_sub(1, Ordering::AcqRel);
-            if prev_count <= 1 {
-                header.sequence.fetch_add(1, Ordering::Relaxed);
-            }
+    /// Задержка и метка времени прочитанного кадра.
+    fn note_frame(&self, frame: &FrameInfo) {
+        if let (Some(latency), Some(timestamp)) = (&self.latency, frame.timestamp) {
+            latency.record_since(timestamp);
+        }
+        self.last_timestamp
+            .store(frame.timestamp.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Метка времени (`MSG_FLAG_TIMESTAMP`) последнего прочитанного кадра;
+    /// забирается один раз -- до следующего чтения дальше `None`.
+    pub fn take_timestamp(&self) -> Option<u64> {
+        match self.last_timestamp.swap(0, Ordering::Relaxed) {
+            0 => None,
+            timestamp => Some(timestamp),
+        }
+    }
 
-            return Ok(msg_len);
+    /// Кредитный режим для этого кольца (вызывает читатель); `None`
+    /// выключает его.
+    pub fn set_credit_window(&self, window: Option<CreditWindow>) -> Result<()> {
+        match window {
+            Some(window) => {
+                window.validate()?;
+                self.inner.set_credit_window(window.messages, window.bytes);
+            }
+            None => self.inner.set_credit_window(0, 0),
         }
+        Ok(())
+    }
+
+    /// Текущее окно кредитного режима (из заголовка кольца).
+    pub fn credit_window(&self) -> Option<CreditWindow> {
+        self.inner
+            .credit_window()
+            .map(|(messages, bytes)| CreditWindow { messages, bytes })
+    }
+
+    /// Свободные кредиты writer'а; `None` -- кредитный режим выключен.
+    pub fn available_credits(&self) -> Option<CreditWindow> {
+        self.inner
+            .available_credits()
+            .map(|(messages, bytes)| CreditWindow { messages, bytes })
+    }
+
+    /// `true` -- writer ждёт кредитов (см. `xshm_core::ring`).
+    pub fn take_credit_stall(&self) -> bool {
+        self.inner.take_credit_stall()
     }
 
     pub fn message_count(&self) -> u32 {
-        self.header().message_count.load(Ordering::Acquire)
+        self.inner.message_count()
     }
 
     #[allow(dead_code)]
     pub fn drop_count(&self) -> u32 {
-        self.header().drop_count.load(Ordering::Acquire)
+        self.inner.drop_count()
+    }
+
+    pub fn stats(&self) -> DirectionStats {
+        let overwritten = self.inner.drop_count();
+        let dropped = self
+            .inner
+            .checksum_errors()
+            .wrapping_add(self.inner.expired_count());
+        DirectionStats {
+            sent: self.inner.sent_messages(),
+            // Изъятые кадры -- выданные плюс вытесненные и отброшенные.
+            received: self
+                .inner
+                .consumed_messages()
+                .wrapping_sub(overwritten)
+                .wrapping_sub(dropped),
+            overwritten,
+            dropped,
+            queue_depth: self.inner.message_count(),
+        }
+    }
+
+    /// Сколько сообщений отброшено читателем этого кольца из-за CRC-32.
+    pub fn checksum_errors(&self) -> u32 {
+        self.inner.checksum_errors()
+    }
+
+    /// Сколько просроченных сообщений этого кольца пропущено читателем или
+    /// убрано writer'ом.
+    pub fn expired_count(&self) -> u32 {
+        self.inner.expired_count()
+    }
+
+    /// Сколько обещанных счётчиком, но не записанных кадров пропустил
+    /// читатель этого кольца.
+    pub fn torn_records(&self) -> u32 {
+        self.inner.torn_records()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.message_count() == 0
+        self.inner.is_empty()
+    }
+
+    pub fn status(&self) -> RingStatus {
+        let used_bytes = self.inner.used_bytes();
+        let (high_water_bytes, high_water_messages) = self.inner.high_water();
+        RingStatus {
+            capacity: self.inner.capacity(),
+            used_bytes,
+            free_bytes: self.inner.capacity() - used_bytes,
+            message_count: self.inner.message_count(),
+            high_water_bytes,
+            high_water_messages,
+        }
+    }
+
+    pub fn reset_high_water(&self) {
+        self.inner.reset_high_water();
     }
 }
 
 #[cfg(test)]
-mod overflow_race_tests {
+mod tests {
     use super::*;
-    use crate::layout::RingHeader;
-    use std::alloc::{alloc_zeroed, dealloc, Layout};
-    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as O};
-    use std::sync::Arc;
-    use std::thread;
-
-    /// Владелец сырой выровненной памяти под один RingHeader + RING_CAPACITY.
-    struct RingMem {
-        ptr: *mut u8,
-        layout: Layout,
-    }
-    unsafe impl Send for RingMem {}
-    unsafe impl Sync for RingMem {}
-    impl Drop for RingMem {
-        fn drop(&mut self) {
-            // SAFETY: ptr/layout получены из alloc_zeroed в make_ring.
-            unsafe { dealloc(self.ptr, self.layout) };
+    use crate::constants::{MAX_MESSAGES, MAX_MESSAGE_SIZE, RING_CAPACITY};
+
+    fn make_ring() -> (RingBuffer, Box<RingHeader>, Vec<u8>) {
+        let mut header = Box::<RingHeader>::default();
+        let mut data = vec![0u8; RING_CAPACITY];
+        // SAFETY: header и data возвращаются вместе с кольцом и живут дольше.
+        let ring = unsafe { RingBuffer::new(&mut *header, data.as_mut_ptr()) };
+        (ring, header, data)
+    }
+
+    #[test]
+    fn vec_read_grows_buffer_to_frame() {
+        let (ring, _header, _data) = make_ring();
+        let big = vec![0x5A; MAX_MESSAGE_SIZE.min(40_000)];
+        ring.write_message(&big).unwrap();
+        ring.write_message(b"small").unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(ring.read_message(&mut out), Ok(big.len()));
+        assert_eq!(out, big);
+        assert_eq!(ring.read_message(&mut out), Ok(5));
+        assert_eq!(out, b"small");
+        assert_eq!(ring.read_message(&mut out), Err(ShmError::QueueEmpty));
+    }
+
+    #[test]
+    fn core_errors_map_to_shm_errors() {
+        let (ring, _header, _data) = make_ring();
+        assert_eq!(
+            ring.write_message(b"x").err(),
+            Some(ShmError::MessageTooSmall)
+        );
+        for _ in 0..=MAX_MESSAGES {
+            ring.write_message(b"fill").unwrap();
         }
+        assert_eq!(ring.drop_count(), 1);
     }
 
-    fn make_ring() -> (RingBuffer, RingMem) {
-        let header_size = std::mem::size_of::<RingHeader>();
-        let total = header_size + RING_CAPACITY;
-        let layout = Layout::from_size_align(total, 64).unwrap();
-        // SAFETY: ненулевой размер; зануление валидно для AtomicU32 полей.
-        let ptr = unsafe { alloc_zeroed(layout) };
-        assert!(!ptr.is_null(), "alloc failed");
-        let header = ptr as *mut RingHeader;
-        // SAFETY: ptr выровнен на 64 и указывает на зануленный RingHeader.
-        unsafe { (*header).reset(1) };
-        // SAFETY: data сразу за заголовком, в пределах выделения.
-        let data = unsafe { ptr.add(header_size) };
-        // SAFETY: header и data валидны, не пересекаются, живут пока жив RingMem.
-        let ring = unsafe { RingBuffer::new(header, data) };
-        (ring, RingMem { ptr, layout })
-    }
-
-    // Большие сообщения: ~34 сообщения заполняют 2 МБ кольца ПО БАЙТАМ
-    // (а не по счётчику MAX_MESSAGES=500). Torn-read возможен только в
-    // байт-заполненном режиме, где write_pos & MASK == read_pos & MASK и
-    // producer физически перезаписывает слот, который читает consumer.
-    // С маленькими сообщениями переполнение наступает по счётчику задолго
-    // до байтового, write далеко впереди read, и гонка не открывается.
-    const PAYLOAD: usize = 60_000;
-    // seq-маркеры в трёх точках сообщения. Если producer перезапишет слот
-    // в середине копирования, маркеры начала/середины/конца разойдутся.
-    const MARK0: usize = 0;
-    const MARK1: usize = PAYLOAD / 2;
-    const MARK2: usize = PAYLOAD - 4;
-
-    /// Проставить seq в три маркера переиспользуемого буфера (без аллокаций
-    /// в горячем цикле — producer должен быть быстрым, чтобы успевать
-    /// перезаписывать слот во время копирования consumer-ом).
-    fn stamp(buf: &mut [u8], seq: u32) {
-        let s = seq.to_le_bytes();
-        buf[MARK0..MARK0 + 4].copy_from_slice(&s);
-        buf[MARK1..MARK1 + 4].copy_from_slice(&s);
-        buf[MARK2..MARK2 + 4].copy_from_slice(&s);
-    }
-
-    /// Err, если сообщение «порвано»: маркеры начала/середины/конца не совпали.
-    // Примечание: `use super::*` втягивает crate-овый `Result<T>` (ошибка = ShmError),
-    // поэтому здесь используем полностью квалифицированный std-Result для String-ошибки.
-    fn check(msg: &[u8]) -> std::result::Result<(), String> {
-        if msg.len() != PAYLOAD {
-            return Err(format!("bad len {}", msg.len()));
+    #[test]
+    fn space_threshold_signals_once_per_crossing() {
+        let (ring, _header, _data) = make_ring();
+        assert_eq!(
+            ring.set_space_threshold(Some(0)),
+            Err(ShmError::InvalidConfig(
+                "space threshold must be 1..=100 percent of the ring"
+            ))
+        );
+        ring.set_space_threshold(Some(50)).unwrap();
+        let frame = vec![7u8; MAX_MESSAGE_SIZE.min(RING_CAPACITY / 8)];
+        while ring.status().free_bytes > RING_CAPACITY as u32 / 4 {
+            ring.write_message(&frame).unwrap();
         }
-        let m0 = u32::from_le_bytes([msg[MARK0], msg[MARK0 + 1], msg[MARK0 + 2], msg[MARK0 + 3]]);
-        let m1 = u32::from_le_bytes([msg[MARK1], msg[MARK1 + 1], msg[MARK1 + 2], msg[MARK1 + 3]]);
-        let m2 = u32::from_le_bytes([msg[MARK2], msg[MARK2 + 1], msg[MARK2 + 2], msg[MARK2 + 3]]);
-        if m0 != m1 || m0 != m2 {
-            return Err(format!("torn: m0={m0} m1={m1} m2={m2}"));
+
+        let mut out = Vec::new();
+        let mut signals = Vec::new();
+        while ring.read_message(&mut out).is_ok() {
+            signals.push(ring.space_available());
         }
-        Ok(())
+        // Один сигнал, когда свободна половина, и ещё один -- пустое кольцо.
+        let crossing = signals.iter().position(|&signal| signal).unwrap();
+        assert!(crossing > 0);
+        assert_eq!(signals.iter().filter(|&&signal| signal).count(), 2);
+        assert_eq!(signals.last(), Some(&true));
+
+        // Без порога -- только пустое кольцо.
+        ring.set_space_threshold(None).unwrap();
+        ring.write_message(&frame).unwrap();
+        ring.write_message(&frame).unwrap();
+        ring.read_message(&mut out).unwrap();
+        assert!(!ring.space_available());
+        ring.read_message(&mut out).unwrap();
+        assert!(ring.space_available());
     }
 
     #[test]
-    fn overflow_does_not_tear_messages() {
-        let (ring, _mem) = make_ring();
-        let ring = Arc::new(ring);
-        let stop = Arc::new(AtomicBool::new(false));
-        let torn = Arc::new(AtomicU64::new(0));
-        let reads = Arc::new(AtomicU64::new(0));
-
-        let producer = {
-            let ring = ring.clone();
-            let stop = stop.clone();
-            thread::spawn(move || {
-                // Переиспользуемый буфер: producer на полной скорости держит
-                // кольцо байт-заполненным, постоянно перезаписывая старое.
-                let mut buf = vec![0u8; PAYLOAD];
-                let mut seq: u32 = 1;
-                while !stop.load(O::Acquire) {
-                    stamp(&mut buf, seq);
-                    let _ = ring.write_message(&buf); // overwrite разрешён
-                    seq = seq.wrapping_add(1);
-                }
-            })
-        };
+    fn timestamped_frames_feed_latency_histogram() {
+        let (mut ring, _header, _data) = make_ring();
+        let histogram = Arc::new(LatencyHistogram::new());
+        ring.set_latency_histogram(Some(histogram.clone()));
+        ring.set_checksum(true);
+
+        ring.write_message(b"plain").unwrap();
+        ring.set_timestamps(true);
+        ring.write_message(b"stamped").unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(ring.read_message(&mut out), Ok(5));
+        assert_eq!(histogram.count(), 0);
+        // Трейлер метки не попадает в payload и покрыт CRC.
+        assert_eq!(ring.read_message(&mut out), Ok(7));
+        assert_eq!(out, b"stamped");
+        assert_eq!(histogram.count(), 1);
+        assert_eq!(ring.checksum_errors(), 0);
+    }
 
-        let consumer = {
-            let ring = ring.clone();
-            let stop = stop.clone();
-            let torn = torn.clone();
-            let reads = reads.clone();
-            thread::spawn(move || {
-                let mut out = Vec::with_capacity(PAYLOAD);
-                while !stop.load(O::Acquire) {
-                    match ring.read_message(&mut out) {
-                        Ok(_) => {
-                            if check(&out).is_err() {
-                                torn.fetch_add(1, O::AcqRel);
-                            }
-                            reads.fetch_add(1, O::AcqRel);
-                            // Лёгкая задержка: consumer чуть медленнее producer-а
-                            // -> кольцо остаётся заполненным -> producer пишет
-                            // ровно в слот, который мы читаем.
-                            for _ in 0..400 {
-                                std::hint::spin_loop();
-                            }
-                        }
-                        Err(_) => thread::yield_now(),
-                    }
-                }
-            })
-        };
+    #[test]
+    fn expired_messages_are_skipped_by_reader() {
+        let (ring, _header, _data) = make_ring();
+        ring.set_default_ttl(Some(Duration::from_millis(20)));
+        ring.write_message(b"stale").unwrap();
+        // Явный срок перекрывает срок по умолчанию.
+        ring.write_message_ttl(b"forever", None).unwrap();
+        ring.write_message_ttl(b"long", Some(Duration::from_secs(60)))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+
+        let mut out = Vec::new();
+        assert_eq!(ring.read_message(&mut out), Ok(7));
+        assert_eq!(out, b"forever");
+        assert_eq!(ring.expired_count(), 1);
+        assert_eq!(ring.read_message(&mut out), Ok(4));
+        assert_eq!(ring.read_message(&mut out), Err(ShmError::QueueEmpty));
+
+        // Просроченная голова убирается следующей записью.
+        ring.write_message(b"old").unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        ring.write_message(b"new").unwrap();
+        assert_eq!(ring.message_count(), 1);
+        assert_eq!(ring.expired_count(), 2);
+        assert_eq!(ring.drop_count(), 0);
+    }
 
-        thread::sleep(std::time::Duration::from_millis(800));
-        stop.store(true, O::Release);
-        producer.join().unwrap();
-        consumer.join().unwrap();
+    #[test]
+    fn credit_mode_refuses_instead_of_overwriting() {
+        let (ring, _header, _data) = make_ring();
+        let window = CreditWindow {
+            messages: 2,
+            bytes: CreditWindow::MIN_BYTES,
+        };
+        ring.set_credit_window(Some(window)).unwrap();
+
+        ring.write_message(b"one").unwrap();
+        ring.write_message(b"two").unwrap();
+        assert!(matches!(
+            ring.write_message(b"three"),
+            Err(ShmError::QueueFull)
+        ));
+        assert_eq!(ring.drop_count(), 0);
+        assert_eq!(ring.available_credits().unwrap().messages, 0);
+        assert!(ring.take_credit_stall());
+        assert!(!ring.take_credit_stall());
+
+        let mut out = Vec::new();
+        assert_eq!(ring.read_message(&mut out), Ok(3));
+        assert_eq!(ring.available_credits().unwrap().messages, 1);
+        ring.write_message(b"three").unwrap();
+
+        ring.set_credit_window(None).unwrap();
+        assert_eq!(ring.available_credits(), None);
+        assert!(matches!(
+            ring.set_credit_window(Some(CreditWindow {
+                messages: 0,
+                ..window
+            })),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
 
-        let reads = reads.load(O::Acquire);
-        let torn = torn.load(O::Acquire);
-        assert!(reads > 0, "consumer не прочитал ни одного сообщения");
+    #[test]
+    fn write_messages_wakes_once_for_written_part() {
+        let (ring, _header, _data) = make_ring();
+        let mut wakes = 0;
+        let outcome = ring
+            .write_messages(&[b"one", b"two", b"three"], || wakes += 1)
+            .unwrap();
+        assert!(outcome.was_empty);
+        assert_eq!((wakes, ring.message_count()), (1, 3));
+
+        // Кольцо не пустое -- будить некого.
+        ring.write_messages(&[b"four"], || wakes += 1).unwrap();
+        assert_eq!(wakes, 1);
+
+        let mut out = Vec::new();
+        while ring.read_message(&mut out).is_ok() {}
         assert_eq!(
-            torn, 0,
-            "обнаружены порванные сообщения: {torn} (из {reads} прочитанных)"
+            ring.write_messages(&[b"five", b"x", b"six"], || wakes += 1)
+                .err(),
+            Some(ShmError::MessageTooSmall)
         );
+        assert_eq!((wakes, ring.message_count()), (2, 1));
+    }
+
+    #[test]
+    fn peek_does_not_consume() {
+        let (ring, _header, _data) = make_ring();
+        ring.set_timestamps(true);
+        ring.write_message(b"\x01first").unwrap();
+        ring.write_message(b"second").unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(ring.peek_message(&mut out), Ok(6));
+        assert_eq!(out[0], 1);
+        assert_eq!(ring.take_timestamp(), None);
+        assert_eq!(ring.peek_message(&mut out), Ok(6));
+        assert_eq!(ring.message_count(), 2);
+        assert_eq!(ring.read_message(&mut out), Ok(6));
+        assert_eq!(out, b"\x01first");
+        assert!(ring.take_timestamp().is_some());
+        assert_eq!(ring.peek_message(&mut out), Ok(6));
+        assert_eq!(out, b"second");
+    }
+
+    #[test]
+    fn reservation_across_ring_end_is_copied_on_commit() {
+        let (ring, _header, data) = make_ring();
+        ring.set_checksum(true);
+        let big = vec![0x5A; MAX_MESSAGE_SIZE * 15 / 16];
+        let mut out = Vec::new();
+        // Кадр не делит ёмкость кольца: за круг payload одной из
+        // резерваций перейдёт через его конец.
+        let mut wrapped = false;
+        for _ in 0..RING_CAPACITY / big.len() + 2 {
+            let mut guard = ring.reserve(big.len()).unwrap();
+            guard.copy_from_slice(&big);
+            wrapped = !data.as_ptr_range().contains(&guard.as_ptr());
+            guard.commit();
+            assert_eq!(ring.read_message(&mut out), Ok(big.len()));
+            assert_eq!(out, big);
+            if wrapped {
+                break;
+            }
+        }
+        assert!(wrapped);
+        assert_eq!(ring.checksum_errors(), 0);
     }
 }
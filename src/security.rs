@@ -0,0 +1,15 @@
+/// Кто может открыть объекты канала, созданные сервером: секцию, события
+/// и блок состояния.
+///
+/// По умолчанию -- `Everyone`: NULL DACL на Windows и права `0o666` на
+/// Unix, так что подключается процесс любого пользователя (например,
+/// процесс пользователя к сервису через `Namespace::Global`). `Owner`
+/// оставляет доступ создателю: DACL по умолчанию из токена процесса
+/// (владелец, SYSTEM, администраторы) на Windows и `0o600` на Unix.
+/// Клиент объектов не создаёт, и его опции права не задают.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Security {
+    #[default]
+    Everyone,
+    Owner,
+}
@@ -1,35 +1,235 @@
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::constants::{HANDSHAKE_CLIENT_HELLO, HANDSHAKE_IDLE, HANDSHAKE_SERVER_READY};
-use crate::error::{Result, ShmError};
-use crate::events::SharedEvents;
-use crate::naming::mapping_name;
-use crate::ring::{RingBuffer, WriteOutcome};
+use crate::cancel::CancellationToken;
+use crate::constants::{
+    layout_ring_flags, version_compatible, DEFAULT_FEATURES, FEATURE_CONTROL_RINGS,
+    HANDSHAKE_CLIENT_HELLO, HANDSHAKE_IDLE, HANDSHAKE_SERVER_READY, LAYOUT_FLAG_CONTROL_RINGS,
+    MSG_USER_FLAGS_MASK, RESERVED_CLIENT_DISCONNECT_REASON_INDEX, RESERVED_CLIENT_FEATURES_INDEX,
+    RESERVED_CLIENT_HEARTBEAT_INDEX, RESERVED_CLIENT_PID_INDEX, RESERVED_CLIENT_VERSION_INDEX,
+    RESERVED_LAYOUT_FLAGS_INDEX, RESERVED_NEGOTIATED_FEATURES_INDEX,
+    RESERVED_SERVER_DISCONNECT_REASON_INDEX, RESERVED_SERVER_FEATURES_INDEX,
+    RESERVED_SERVER_HEARTBEAT_INDEX, RESERVED_SERVER_PID_INDEX, RESERVED_UPGRADE_TOKEN_INDEX,
+    RING_CAPACITY, SHARED_MAGIC, SHARED_VERSION, SUPPORTED_FEATURES,
+};
+use crate::diagnostics::{self, ChannelDump};
+use crate::disconnect::DisconnectReason;
+use crate::error::{ensure_config, Result, ShmError};
+use crate::events::{EventHandles, SharedEvents};
+use crate::handles::{self, Received, ReceivedMessage};
+use crate::latency::LatencyHistogram;
+use crate::layout::{dual_mapping_size, shared_mapping_size};
+use crate::naming::{self, mapping_name, Namespace};
+use crate::poll::{self, PollResult};
+use crate::reclaim;
+use crate::ring::{
+    ChannelStats, CreditWindow, RingBuffer, RingStatus, WriteGuard, WriteOutcome, WritePolicy,
+};
+use crate::security::Security;
 use crate::shared::SharedView;
-use crate::win::Mapping;
+use crate::split::{self, RecvHalf, SendHalf};
+use crate::state::SharedState;
+use crate::upgrade::UpgradeToken;
+use crate::platform::{self, Backend, Mapping, PlatformEvent, PlatformMapping};
+
+/// Опции [`SharedServer::start_with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct ServerOptions {
+    /// Управляющие кольца (см. [`SharedServer::start_with_control_rings`]).
+    pub control_rings: bool,
+    /// Секция на больших страницах (`SEC_LARGE_PAGES` на Windows,
+    /// transparent huge pages на Linux): меньше TLB-промахов на
+    /// многомегабайтных кольцах. Без SeLockMemoryPrivilege или поддержки
+    /// ОС сервер стартует на обычных страницах, см.
+    /// [`SharedServer::large_pages`]. Клиенту ничего включать не нужно.
+    pub large_pages: bool,
+    /// Namespace объектов канала (см. [`Namespace`]); клиент подключается
+    /// через [`SharedClient::connect_in`](crate::SharedClient::connect_in)
+    /// с тем же значением.
+    pub namespace: Namespace,
+    /// Права доступа к объектам канала (см. [`Security`]).
+    pub security: Security,
+    /// Ожидаемый размер кольца в байтах. Размер задаётся при сборке
+    /// (`RING_CAPACITY`, features `ring-*`); другое значение --
+    /// `ShmError::InvalidConfig` вместо канала, к которому не подключится
+    /// пир с нужным профилем. `None` -- без проверки.
+    pub ring_capacity: Option<usize>,
+}
+
+impl ServerOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        ensure_ring_capacity(self.ring_capacity)
+    }
+}
+
+/// Размер кольца нельзя выбрать в рантайме: допустим только профиль сборки.
+pub(crate) fn ensure_ring_capacity(ring_capacity: Option<usize>) -> Result<()> {
+    ensure_config(
+        ring_capacity.is_none_or(|capacity| capacity == RING_CAPACITY),
+        "ring_capacity must match RING_CAPACITY of the build (ring-* features)",
+    )
+}
+
+/// Построитель именованного канала ([`SharedServer::builder`]);
+/// незаданные поля -- из `ServerOptions::default()`.
+#[derive(Clone, Debug)]
+pub struct ServerBuilder {
+    name: String,
+    options: ServerOptions,
+}
+
+impl ServerBuilder {
+    pub fn control_rings(mut self, control_rings: bool) -> Self {
+        self.options.control_rings = control_rings;
+        self
+    }
+
+    pub fn large_pages(mut self, large_pages: bool) -> Self {
+        self.options.large_pages = large_pages;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Namespace) -> Self {
+        self.options.namespace = namespace;
+        self
+    }
+
+    pub fn security(mut self, security: Security) -> Self {
+        self.options.security = security;
+        self
+    }
+
+    pub fn ring_capacity(mut self, ring_capacity: usize) -> Self {
+        self.options.ring_capacity = Some(ring_capacity);
+        self
+    }
+
+    /// Собранные опции (например, для `AutoOptions` или хранения в конфиге).
+    pub fn options(&self) -> &ServerOptions {
+        &self.options
+    }
+
+    pub fn start(self) -> Result<SharedServer> {
+        SharedServer::start_with_options(&self.name, &self.options)
+    }
+}
+
+/// Handle'ы канала, продублированные в другой процесс
+/// ([`SharedServer::share_with_process`]). Значения валидны только в
+/// целевом процессе; закрывает их получатель.
+#[derive(Clone, Copy, Debug)]
+pub struct RemoteHandles {
+    /// Handle секции (для `SharedClient::from_section_handle`).
+    pub section: isize,
+    /// Handle'ы событий данных; `None` у anonymous канала.
+    pub events: Option<EventHandles>,
+}
 
 pub struct SharedServer {
     _name: String,
+    /// Backend объектов канала: им дублируются и закрываются handle'ы.
+    backend: Backend,
     _mapping: Mapping,
     view: SharedView,
     events: Option<SharedEvents>, // None для anonymous режима
     ring_tx: RingBuffer,
     ring_rx: RingBuffer,
+    /// Управляющие кольца `(tx, rx)` (`start_with_control_rings`).
+    control: Option<(RingBuffer, RingBuffer)>,
+    state: Option<SharedState>, // None для anonymous режима
+    /// Окно кредитов для входящего кольца; переустанавливается после
+    /// сброса колец при каждом подключении.
+    credit_window: Option<CreditWindow>,
+    reclaimed_from: Option<u32>,
+    /// Предлагаемые клиентам возможности (`FEATURE_*`).
+    features: u32,
+    /// Возможности, согласованные с текущим клиентом.
+    negotiated_features: u32,
+    /// Generation control block текущего соединения (см.
+    /// `SharedClient`): расхождение -- `ShmError::ConnectionReset`.
+    connection_gen: u32,
     connected: bool,
+    /// Канал передан новой версии (`detach`): Drop его не трогает.
+    detached: bool,
 }
 
 unsafe impl Send for SharedServer {}
 
 impl SharedServer {
     pub fn start(name: &str) -> Result<Self> {
+        Self::start_with_options(name, &ServerOptions::default())
+    }
+
+    /// Канал с управляющими кольцами: кроме bulk-кольца (2 МБ) в каждом
+    /// направлении есть маленькое (`CONTROL_RING_CAPACITY`) кольцо для
+    /// срочных команд (`send_control_to_client`/`send_control_to_server`).
+    /// Приём всегда сначала выбирает управляющее кольцо, поэтому команда не
+    /// ждёт за уже записанными 64 КБ bulk-кадрами. Клиент узнаёт раскладку
+    /// из control block сам, `SharedClient::connect` не меняется.
+    pub fn start_with_control_rings(name: &str) -> Result<Self> {
+        Self::start_with_options(
+            name,
+            &ServerOptions {
+                control_rings: true,
+                ..ServerOptions::default()
+            },
+        )
+    }
+
+    /// Построитель канала `name`: `SharedServer::builder(name)
+    /// .namespace(..).security(..).start()`.
+    pub fn builder(name: &str) -> ServerBuilder {
+        ServerBuilder {
+            name: name.to_owned(),
+            options: ServerOptions::default(),
+        }
+    }
+
+    /// Именованный канал с явными [`ServerOptions`].
+    pub fn start_with_options(name: &str, options: &ServerOptions) -> Result<Self> {
+        let control_rings = options.control_rings;
+        options.validate()?;
+        naming::validate(name)?;
+        let name = &options.namespace.qualify(name);
         let map_name = mapping_name(name);
-        let mapping = Mapping::create(&map_name)?;
-        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+        // Канал упавшего сервера сбрасываем и занимаем: его объекты ещё
+        // живы, пока их держит кто-то другой (см. `reclaim`).
+        let reclaimed_from = reclaim::reclaim(name)?;
+        let size = if control_rings {
+            dual_mapping_size()
+        } else {
+            shared_mapping_size()
+        };
+        let created =
+            Mapping::create_secured(&map_name, size, options.large_pages, options.security);
+        let mapping = match created {
+            // Объект ещё держат (Windows): открываем уже сброшенную секцию.
+            Err(_) if reclaimed_from.is_some() => Mapping::open_sized(&map_name, size)?,
+            created => created?,
+        };
+        let view = if control_rings {
+            unsafe { SharedView::with_control_rings(mapping.as_ptr()) }
+        } else {
+            unsafe { SharedView::new(mapping.as_ptr()) }
+        };
 
         // SAFETY: единственный владелец на этапе инициализации, алиасинга нет
         let control = unsafe { &mut *view.control_block_ptr() };
         control.reset();
+        control.reserved[RESERVED_SERVER_PID_INDEX].store(std::process::id(), Ordering::Relaxed);
+        let layout_flags = if control_rings {
+            layout_ring_flags() | LAYOUT_FLAG_CONTROL_RINGS
+        } else {
+            layout_ring_flags()
+        };
+        control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].store(layout_flags, Ordering::Relaxed);
+        let features = if control_rings {
+            DEFAULT_FEATURES
+        } else {
+            DEFAULT_FEATURES & !FEATURE_CONTROL_RINGS
+        };
+        control.reserved[RESERVED_SERVER_FEATURES_INDEX].store(features, Ordering::Relaxed);
         let generation = control.generation.load(Ordering::Relaxed);
 
         unsafe {
@@ -38,23 +238,147 @@ impl SharedServer {
             let header_b = &*view.ring_header_b();
             header_b.reset(generation);
         }
+        view.reset_control_rings(generation);
 
-        let events = SharedEvents::create(name)?;
+        let (events, state) = match reclaimed_from {
+            None => (
+                SharedEvents::create_secured(name, options.security)?,
+                SharedState::create_secured(name, options.security)?,
+            ),
+            Some(_) => (
+                SharedEvents::adopt(name, options.security)?,
+                SharedState::adopt(name, options.security)?,
+            ),
+        };
 
         let ring_tx = unsafe { RingBuffer::new(view.ring_header_a(), view.ring_buffer_a()) };
         let ring_rx = unsafe { RingBuffer::new(view.ring_header_b(), view.ring_buffer_b()) };
+        let control = view.control_rings(true);
 
         Ok(Self {
             _name: name.to_owned(),
+            backend: Backend::of(name),
             _mapping: mapping,
             view,
             events: Some(events),
             ring_tx,
             ring_rx,
+            control,
+            state: Some(state),
+            credit_window: None,
+            reclaimed_from,
+            features,
+            negotiated_features: 0,
+            connection_gen: 0,
             connected: false,
+            detached: false,
+        })
+    }
+
+    /// Забирает канал, отпущенный сервером прошлой версии (`detach`, см.
+    /// `xshm::upgrade`): секция, события и состояние открываются как есть,
+    /// соединение с клиентом и непрочитанные сообщения сохраняются.
+    ///
+    /// Токен не совпадает (подделан, уже использован) или generation
+    /// сменился (канал сбросили после `detach`) -- `ShmError::HandshakeFailed`.
+    pub fn adopt(token: &UpgradeToken) -> Result<Self> {
+        let map_name = mapping_name(&token.name);
+        let probe = Mapping::open(&map_name)?;
+        // SAFETY: отображение размером shared_mapping_size() живёт вместе с view.
+        let probe_view = unsafe { SharedView::new(probe.as_ptr()) };
+        let control = probe_view.control_block();
+        if control.magic != SHARED_MAGIC {
+            return Err(ShmError::Corrupted);
+        }
+        if !version_compatible(control.version) {
+            return Err(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: control.version,
+            });
+        }
+        probe_view.check_layout()?;
+        let layout_flags = control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+        let control_rings = layout_flags & LAYOUT_FLAG_CONTROL_RINGS != 0;
+        let mut mapping = if control_rings {
+            Mapping::open_sized(&map_name, dual_mapping_size())?
+        } else {
+            probe
+        };
+        let view = if control_rings {
+            unsafe { SharedView::with_control_rings(mapping.as_ptr()) }
+        } else {
+            unsafe { SharedView::new(mapping.as_ptr()) }
+        };
+
+        let control = view.control_block();
+        if control.generation.load(Ordering::Acquire) != token.generation {
+            return Err(ShmError::HandshakeFailed);
+        }
+        // Токен одноразовый: из нескольких претендентов канал достаётся
+        // одному.
+        if control.reserved[RESERVED_UPGRADE_TOKEN_INDEX]
+            .compare_exchange(token.nonce, 0, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(ShmError::HandshakeFailed);
+        }
+
+        let mut events = SharedEvents::open(&token.name)?;
+        let mut state = SharedState::open(&token.name)?;
+        mapping.set_owns_name(true);
+        events.set_owns_names(true);
+        state.set_owns_name(true);
+        control.reserved[RESERVED_SERVER_PID_INDEX].store(std::process::id(), Ordering::Release);
+
+        let connected = control.server_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY
+            && control.client_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY;
+        let ring_tx = unsafe { RingBuffer::new(view.ring_header_a(), view.ring_buffer_a()) };
+        let ring_rx = unsafe { RingBuffer::new(view.ring_header_b(), view.ring_buffer_b()) };
+        let control_rings = view.control_rings(true);
+
+        Ok(Self {
+            _name: token.name.clone(),
+            backend: Backend::of(&token.name),
+            credit_window: ring_rx.credit_window(),
+            features: control.reserved[RESERVED_SERVER_FEATURES_INDEX].load(Ordering::Acquire),
+            negotiated_features: control.reserved[RESERVED_NEGOTIATED_FEATURES_INDEX]
+                .load(Ordering::Acquire),
+            _mapping: mapping,
+            view,
+            events: Some(events),
+            ring_tx,
+            ring_rx,
+            control: control_rings,
+            state: Some(state),
+            reclaimed_from: None,
+            connection_gen: token.generation,
+            connected,
+            detached: false,
         })
     }
 
+    /// Отпускает канал для новой версии процесса (см. `xshm::upgrade`):
+    /// секция, кольца и события остаются как есть, клиенту ничего не
+    /// сигналится. Возвращает одноразовый токен для [`SharedServer::adopt`].
+    /// Anonymous-канал передать нельзя -- `ShmError::Unsupported`.
+    pub fn detach(mut self) -> Result<UpgradeToken> {
+        let Some(events) = self.events.as_mut() else {
+            return Err(ShmError::Unsupported(
+                "anonymous channel cannot be detached",
+            ));
+        };
+        events.set_owns_names(false);
+        if let Some(state) = self.state.as_mut() {
+            state.set_owns_name(false);
+        }
+        self._mapping.set_owns_name(false);
+        let control = self.view.control_block();
+        let token = UpgradeToken::new(&self._name, control.generation.load(Ordering::Acquire));
+        control.reserved[RESERVED_UPGRADE_TOKEN_INDEX].store(token.nonce, Ordering::Release);
+        self.detached = true;
+        Ok(token)
+    }
+
     /// Создание anonymous сервера без имени (только через handle)
     ///
     /// Anonymous сервер создает section без имени в глобальном namespace.
@@ -74,6 +398,10 @@ impl SharedServer {
         // SAFETY: единственный владелец на этапе инициализации, алиасинга нет
         let control = unsafe { &mut *view.control_block_ptr() };
         control.reset();
+        control.reserved[RESERVED_SERVER_PID_INDEX].store(std::process::id(), Ordering::Relaxed);
+        control.reserved[RESERVED_LAYOUT_FLAGS_INDEX].store(layout_ring_flags(), Ordering::Relaxed);
+        let features = DEFAULT_FEATURES & !FEATURE_CONTROL_RINGS;
+        control.reserved[RESERVED_SERVER_FEATURES_INDEX].store(features, Ordering::Relaxed);
         let generation = control.generation.load(Ordering::Relaxed);
 
         unsafe {
@@ -90,15 +418,131 @@ impl SharedServer {
 
         Ok(Self {
             _name: String::new(), // Anonymous - нет имени
+            backend: Backend::Native,
             _mapping: mapping,
             view,
             events: None, // No events for anonymous mode
             ring_tx,
             ring_rx,
+            control: None,
+            state: None,
+            credit_window: None,
+            reclaimed_from: None,
+            features,
+            negotiated_features: 0,
+            connection_gen: 0,
             connected: false,
+            detached: false,
         })
     }
 
+    /// Ограничивает возможности (`FEATURE_*`), предлагаемые следующим
+    /// клиентам; неизвестные этой сборке биты отбрасываются. Текущее
+    /// соединение не меняется.
+    pub fn set_features(&mut self, features: u32) {
+        let mut features = features & SUPPORTED_FEATURES;
+        if !self.view.has_control_rings() {
+            features &= !FEATURE_CONTROL_RINGS;
+        }
+        self.features = features;
+        self.view.control_block().reserved[RESERVED_SERVER_FEATURES_INDEX]
+            .store(features, Ordering::Release);
+    }
+
+    /// Возможности, предлагаемые клиентам.
+    pub fn features(&self) -> u32 {
+        self.features
+    }
+
+    /// Возможности, которые поддерживают обе стороны текущего соединения
+    /// (0 -- клиент старой версии без согласования).
+    pub fn negotiated_features(&self) -> u32 {
+        self.negotiated_features
+    }
+
+    /// PID процесса клиента из handshake (`RESERVED_CLIENT_PID_INDEX`);
+    /// `None` -- клиент его не опубликовал (старая версия библиотеки).
+    pub fn peer_pid(&self) -> Option<u32> {
+        let pid =
+            self.view.control_block().reserved[RESERVED_CLIENT_PID_INDEX].load(Ordering::Acquire);
+        (pid != 0).then_some(pid)
+    }
+
+    /// Жив ли процесс клиента (`NtOpenProcess` + ожидание его handle'а).
+    /// Ловит клиента, упавшего без disconnect. Консервативна: без PID и при
+    /// любой двусмысленности (нет прав, PID переиспользован) -- `true`.
+    pub fn is_peer_process_alive(&self) -> bool {
+        self.peer_pid().is_none_or(platform::is_process_alive)
+    }
+
+    /// Дешёвая проверка клиента без ожидания: соединение не сброшено
+    /// (generation), клиент не отключился (handshake-состояние) и его
+    /// процесс жив (`is_peer_process_alive`). `false` -- ответа ждать не
+    /// от кого, запрос можно провалить сразу, не выжидая таймаут.
+    pub fn is_peer_alive(&self) -> bool {
+        self.ensure_connected().is_ok()
+            && self.view.handshake_ready()
+            && self.is_peer_process_alive()
+    }
+
+    /// Делит установленное соединение на независимые половины (см.
+    /// [`SendHalf`]); принять следующего клиента половины не могут --
+    /// для этого сервер собирают обратно ([`SendHalf::reunite`]).
+    pub fn split(self) -> (SendHalf<Self>, RecvHalf<Self>) {
+        split::split(self)
+    }
+
+    /// Отключает текущего клиента, оставив ему причину (его
+    /// `peer_disconnect_reason`, `AutoHandler::on_disconnect_with_reason`),
+    /// и готовит сервер к следующему (`wait_for_client`). Без клиента --
+    /// ничего не делает.
+    pub fn disconnect(&mut self, reason: DisconnectReason) {
+        if !self.connected {
+            return;
+        }
+        // Канал сброшен -- control block уже принадлежит новому соединению.
+        if self.ensure_connected().is_ok() {
+            self.view
+                .store_disconnect_reason(RESERVED_SERVER_DISCONNECT_REASON_INDEX, reason);
+            if let Some(events) = &self.events {
+                let _ = events.disconnect.set();
+            }
+        }
+        self.mark_disconnected();
+    }
+
+    /// Выселяет текущего клиента: `disconnect(reason)` и сразу новый
+    /// generation, как в `accept_next_client`. Клиент, пропустивший
+    /// событие disconnect, всё равно получает `ShmError::ConnectionReset`
+    /// на следующей операции и не пишет в кольца. Сервер готов к
+    /// `wait_for_client`; без клиента -- `ShmError::NotConnected`.
+    pub fn kick_client(&mut self, reason: DisconnectReason) -> Result<()> {
+        if !self.connected {
+            return Err(ShmError::NotConnected);
+        }
+        self.disconnect(reason);
+        let control = self.view.control_block();
+        self.connection_gen = control
+            .generation
+            .fetch_add(1, Ordering::AcqRel)
+            .wrapping_add(1);
+        Ok(())
+    }
+
+    /// Причина, которую оставил отключившийся клиент
+    /// (`SharedClient::disconnect`, `Graceful` при drop); `Unknown` --
+    /// клиент не отключался или упал.
+    pub fn peer_disconnect_reason(&self) -> DisconnectReason {
+        self.view
+            .disconnect_reason(RESERVED_CLIENT_DISCONNECT_REASON_INDEX)
+    }
+
+    /// PID упавшего сервера, чей брошенный канал был сброшен и занят при
+    /// старте; `None` -- канал создан заново.
+    pub fn reclaimed_from(&self) -> Option<u32> {
+        self.reclaimed_from
+    }
+
     /// Получить handles событий для передачи в kernel driver
     ///
     /// Возвращает `None` если сервер создан в anonymous режиме (без событий).
@@ -120,12 +564,58 @@ impl SharedServer {
         if !self.events.as_ref().unwrap().connect_req.wait(timeout)? {
             return Err(ShmError::Timeout);
         }
+        self.accept_hello()
+    }
+
+    /// Принимает следующего клиента на той же секции, не пересоздавая
+    /// сервер: сбрасывает состояние рукопожатия, сдвигает generation и
+    /// ждёт `connect_req`, как `wait_for_client`. Прежний клиент, если он
+    /// ещё жив, получает `ShmError::ConnectionReset` на любой операции.
+    pub fn accept_next_client(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.mark_disconnected();
+        // Новый generation сразу, а не только в accept_hello: прежний клиент
+        // не должен писать в кольца, пока следующий ещё не пришёл.
+        let control = self.view.control_block();
+        self.connection_gen = control.generation.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+        self.wait_for_client(timeout)
+    }
+
+    /// `wait_for_client`, прерываемый токеном: отмена -- `ShmError::Cancelled`.
+    pub(crate) fn wait_for_client_or_cancel(
+        &mut self,
+        timeout: Option<Duration>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        if self.connected {
+            return Err(ShmError::AlreadyConnected);
+        }
+        let Some(events) = &self.events else {
+            return self.wait_for_client_noevent(timeout);
+        };
+        let handles = [events.connect_req.raw_handle(), cancel.raw_handle()];
+        match platform::wait_any(&handles, timeout)? {
+            Some(0) => self.accept_hello(),
+            Some(_) => Err(ShmError::Cancelled),
+            None => Err(ShmError::Timeout),
+        }
+    }
 
+    /// Ответ на CLIENT_HELLO после сработавшего `connect_req`.
+    fn accept_hello(&mut self) -> Result<()> {
         let control = self.view.control_block();
         let client_state = control.client_state.load(Ordering::Acquire);
         if client_state != HANDSHAKE_CLIENT_HELLO {
             return Err(ShmError::HandshakeFailed);
         }
+        if let Err(err) = self.negotiate() {
+            // Отказ: клиент проснётся по ack, увидит server_state != READY
+            // и вернёт HandshakeFailed, не дожидаясь своего таймаута.
+            if let Some(events) = &self.events {
+                events.connect_ack.set()?;
+            }
+            return Err(err);
+        }
+        let control = self.view.control_block();
 
         // ВАЖНО: сначала сбрасываем буферы, потом обновляем generation
         // Это гарантирует, что клиент увидит чистые буферы когда прочитает новый generation
@@ -136,9 +626,17 @@ impl SharedServer {
             (&*self.view.ring_header_a()).reset(new_generation);
             (&*self.view.ring_header_b()).reset(new_generation);
         }
+        self.view.reset_control_rings(new_generation);
+        self.apply_credit_window();
+        // Heartbeat и причины отключения прежнего клиента к новому
+        // отношения не имеют.
+        control.reserved[RESERVED_CLIENT_HEARTBEAT_INDEX].store(0, Ordering::Relaxed);
+        control.reserved[RESERVED_SERVER_DISCONNECT_REASON_INDEX].store(0, Ordering::Relaxed);
+        control.reserved[RESERVED_CLIENT_DISCONNECT_REASON_INDEX].store(0, Ordering::Relaxed);
 
         // Теперь атомарно публикуем новый generation
         control.generation.store(new_generation, Ordering::Release);
+        self.connection_gen = new_generation;
 
         let header_a = unsafe { &*self.view.ring_header_a() };
         let header_b = unsafe { &*self.view.ring_header_b() };
@@ -189,6 +687,8 @@ impl SharedServer {
 
             std::thread::sleep(Duration::from_millis(1));
         }
+        self.negotiate()?;
+        let control = self.view.control_block();
 
         // ВАЖНО: сначала сбрасываем буферы, потом обновляем generation
         let current_gen = control.generation.load(Ordering::Acquire);
@@ -198,9 +698,17 @@ impl SharedServer {
             (&*self.view.ring_header_a()).reset(new_generation);
             (&*self.view.ring_header_b()).reset(new_generation);
         }
+        self.view.reset_control_rings(new_generation);
+        self.apply_credit_window();
+        // Heartbeat и причины отключения прежнего клиента к новому
+        // отношения не имеют.
+        control.reserved[RESERVED_CLIENT_HEARTBEAT_INDEX].store(0, Ordering::Relaxed);
+        control.reserved[RESERVED_SERVER_DISCONNECT_REASON_INDEX].store(0, Ordering::Relaxed);
+        control.reserved[RESERVED_CLIENT_DISCONNECT_REASON_INDEX].store(0, Ordering::Relaxed);
 
         // Теперь атомарно публикуем новый generation
         control.generation.store(new_generation, Ordering::Release);
+        self.connection_gen = new_generation;
 
         let header_a = unsafe { &*self.view.ring_header_a() };
         let header_b = unsafe { &*self.view.ring_header_b() };
@@ -227,6 +735,36 @@ impl SharedServer {
         self.connected
     }
 
+    /// Номер текущего соединения (generation control block): растёт с
+    /// каждым подключением клиента и одинаков у обеих сторон. Другой номер
+    /// между двумя сообщениями -- клиент переподключился, и состояние
+    /// прежней сессии можно выбросить. `None` -- клиента нет.
+    pub fn connection_epoch(&self) -> Option<u32> {
+        self.connected.then_some(self.connection_gen)
+    }
+
+    /// Отмечает, что сервер жив (`RESERVED_SERVER_HEARTBEAT_INDEX`). Heartbeat
+    /// необязателен: его ведёт приложение, вызывая метод из своего цикла
+    /// чаще, чем `max_age`, с которым клиент проверяет
+    /// `is_peer_responsive`.
+    pub fn heartbeat(&self) {
+        self.view.stamp_heartbeat(RESERVED_SERVER_HEARTBEAT_INDEX);
+    }
+
+    /// Сколько прошло с последнего heartbeat клиента; `None` -- клиент
+    /// heartbeat не ведёт.
+    pub fn peer_heartbeat_age(&self) -> Option<Duration> {
+        self.view.heartbeat_age(RESERVED_CLIENT_HEARTBEAT_INDEX)
+    }
+
+    /// Клиент отмечал heartbeat не дольше `max_age` назад. Убитый без
+    /// disconnect клиент перестаёт отмечаться, и через `max_age` метод
+    /// вернёт `false`. Клиент, который heartbeat не ведёт, считается
+    /// отзывчивым.
+    pub fn is_peer_responsive(&self, max_age: Duration) -> bool {
+        self.peer_heartbeat_age().is_none_or(|age| age <= max_age)
+    }
+
     /// Доступ к событиям сервера (для внутреннего использования)
     ///
     /// ВАЖНО: Для anonymous режима возвращает None - события не создаются.
@@ -240,12 +778,57 @@ impl SharedServer {
         self.events.is_none()
     }
 
+    /// Секция канала на больших страницах (`ServerOptions::large_pages`
+    /// и ОС их дала).
+    pub fn large_pages(&self) -> bool {
+        self._mapping.large_pages()
+    }
+
     /// Получить raw HANDLE секции для передачи в kernel driver
     /// ВАЖНО: Handle принадлежит Mapping, не закрывать вручную!
     pub fn section_handle(&self) -> isize {
         self._mapping.section_handle()
     }
 
+    /// Дублирует handle секции и, у именованного канала, handle'ы событий
+    /// данных (`get_event_handles`) в процесс `pid` -- например, для
+    /// anonymous канала, к которому тот процесс подключится через
+    /// `SharedClient::from_section_handle`. Значения передаются ему по
+    /// другому каналу. Ошибка на любом handle'е закрывает уже
+    /// продублированные. Только Windows: на Unix -- `ShmError::Unsupported`.
+    pub fn share_with_process(&self, pid: u32) -> Result<RemoteHandles> {
+        let events = self.get_event_handles();
+        let mut local = vec![self.section_handle()];
+        if let Some(events) = events {
+            local.extend([events.s2c_data, events.c2s_data]);
+        }
+        let mut remote = Vec::with_capacity(local.len());
+        for handle in local {
+            match self.backend.duplicate_handle_into(handle, pid) {
+                Ok(duplicated) => remote.push(duplicated),
+                Err(err) => {
+                    for duplicated in remote {
+                        self.backend.close_remote_handle(duplicated, pid);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(RemoteHandles {
+            section: remote[0],
+            events: events.map(|_| EventHandles {
+                s2c_data: remote[1],
+                c2s_data: remote[2],
+            }),
+        })
+    }
+
+    /// Снимок control block и обоих колец для отчёта об ошибке (см.
+    /// `xshm::diagnostics`).
+    pub fn diagnostic_dump(&self) -> ChannelDump {
+        diagnostics::dump_view(&self.view)
+    }
+
     /// Доступ к shared view (для внутреннего использования)
     pub(crate) fn view(&self) -> &SharedView {
         &self.view
@@ -254,6 +837,36 @@ impl SharedServer {
     /// Установка состояния подключения (для внутреннего использования)
     pub(crate) fn set_connected(&mut self, connected: bool) {
         self.connected = connected;
+        if connected {
+            self.connection_gen = self.view.control_block().generation.load(Ordering::Acquire);
+        }
+    }
+
+    /// Проверка версии клиента, приславшего HELLO, и публикация
+    /// согласованных возможностей. При несовместимой версии клиент
+    /// отклоняется (`client_state` -> IDLE).
+    fn negotiate(&mut self) -> Result<()> {
+        let control = self.view.control_block();
+        // Забираем версию и маску: клиент старой версии их не пишет и не
+        // должен унаследовать значения предыдущего клиента.
+        let version = control.reserved[RESERVED_CLIENT_VERSION_INDEX].swap(0, Ordering::AcqRel);
+        let offered = control.reserved[RESERVED_CLIENT_FEATURES_INDEX].swap(0, Ordering::AcqRel);
+        if version != 0 && !version_compatible(version) {
+            control
+                .server_state
+                .store(HANDSHAKE_IDLE, Ordering::Release);
+            control
+                .client_state
+                .store(HANDSHAKE_IDLE, Ordering::Release);
+            return Err(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: version,
+            });
+        }
+        self.negotiated_features = self.features & offered;
+        control.reserved[RESERVED_NEGOTIATED_FEATURES_INDEX]
+            .store(self.negotiated_features, Ordering::Release);
+        Ok(())
     }
 
     pub(crate) fn mark_disconnected(&mut self) {
@@ -264,9 +877,14 @@ impl SharedServer {
         control
             .server_state
             .store(HANDSHAKE_IDLE, Ordering::Release);
-        control
+        // Следующий клиент мог уже успеть выставить HELLO (и connect_req) --
+        // безусловный сброс затёр бы его, и wait_for_client ответил бы ему
+        // HandshakeFailed.
+        let _ = control
             .client_state
-            .store(HANDSHAKE_IDLE, Ordering::Release);
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                (state != HANDSHAKE_CLIENT_HELLO).then_some(HANDSHAKE_IDLE)
+            });
 
         unsafe {
             (&*self.view.ring_header_a())
@@ -281,14 +899,208 @@ impl SharedServer {
     fn ensure_connected(&self) -> Result<()> {
         if !self.connected {
             Err(ShmError::NotConnected)
+        } else if self.view.control_block().generation.load(Ordering::Acquire)
+            != self.connection_gen
+        {
+            Err(ShmError::ConnectionReset)
         } else {
             Ok(())
         }
     }
 
+    /// Включает CRC-32 трейлер для сообщений клиенту. Входящие сообщения с
+    /// трейлером проверяются всегда; битые отбрасываются с
+    /// `ShmError::ChecksumMismatch`.
+    pub fn set_checksum(&self, enabled: bool) {
+        self.ring_tx.set_checksum(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_checksum(enabled);
+        }
+    }
+
+    /// Что делает отправка клиенту, когда bulk-кольцо полно (см.
+    /// [`WritePolicy`]): вытесняет старые сообщения (по умолчанию),
+    /// возвращает `ShmError::QueueFull` или ждёт, пока клиент освободит
+    /// место. Ждут `send_to_client` и `send_to_client_with_ttl`, остальные
+    /// отправки при `Block` -- как при `Fail`. Управляющие кольца
+    /// вытесняют всегда.
+    pub fn set_write_policy(&self, policy: WritePolicy) {
+        self.ring_tx.set_write_policy(policy);
+    }
+
+    pub fn write_policy(&self) -> WritePolicy {
+        self.ring_tx.write_policy()
+    }
+
+    /// Метка времени записи на каждое сообщение клиенту (см.
+    /// `xshm::latency`): по ней получатель считает задержку доставки.
+    pub fn set_timestamps(&self, enabled: bool) {
+        self.ring_tx.set_timestamps(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_timestamps(enabled);
+        }
+    }
+
+    /// Номер на каждое сообщение клиенту через bulk-кольцо
+    /// (`MSG_FLAG_SEQUENCE`): по разрывам в номерах клиент видит
+    /// вытесненные сообщения (`receive_message_from_server`). Управляющие
+    /// кольца не нумеруются.
+    pub fn set_sequence_numbers(&self, enabled: bool) {
+        self.ring_tx.set_sequence_numbers(enabled);
+    }
+
+    /// Отправки клиенту из нескольких потоков (MPSC): `send_to_client*`, пачки
+    /// и управляющие сообщения можно звать через общий `&SharedServer` (например,
+    /// из `Arc`) без своей блокировки. Писатели занимают место в кольце
+    /// CAS'ом и публикуют сообщения в порядке занятия; без режима
+    /// отправлять можно только из одного потока. `reserve_to_client` и так
+    /// требует `&mut self`, поэтому работает в обоих режимах.
+    pub fn set_multi_producer(&mut self, enabled: bool) {
+        self.ring_tx.set_multi_producer(enabled);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_multi_producer(enabled);
+        }
+    }
+
+    /// Гистограмма задержки сообщений от клиента с меткой времени;
+    /// `None` -- не считать.
+    pub fn set_latency_histogram(&mut self, histogram: Option<Arc<LatencyHistogram>>) {
+        if let Some((_, control_rx)) = &mut self.control {
+            control_rx.set_latency_histogram(histogram.clone());
+        }
+        self.ring_rx.set_latency_histogram(histogram);
+    }
+
+    /// Сколько сообщений от клиента отброшено из-за несовпадения CRC-32
+    /// (с начала текущего соединения).
+    pub fn checksum_errors(&self) -> u32 {
+        self.ring_rx.checksum_errors()
+    }
+
+    /// Срок годности сообщений клиенту по умолчанию (`None` -- бессрочно).
+    /// Сообщение, не прочитанное клиентом за `ttl`, пропускается при чтении
+    /// и убирается из очереди при следующей записи -- устаревшие команды не
+    /// выполняются, когда застрявший клиент наконец до них доберётся.
+    pub fn set_default_ttl(&self, ttl: Option<Duration>) {
+        self.ring_tx.set_default_ttl(ttl);
+        if let Some((control_tx, _)) = &self.control {
+            control_tx.set_default_ttl(ttl);
+        }
+    }
+
+    /// Порог space-события для сообщений от клиента: `receive_from_client`
+    /// будит ждущего отправителя, когда свободно не меньше `percent`
+    /// процентов кольца (один раз на пересечение порога снизу), а не только
+    /// когда кольцо опустело -- writer с `WritePolicy::Block` или auto
+    /// worker продолжает, не дожидаясь полного опустошения. `None` --
+    /// только пустое кольцо. Вне 1..=100 -- `ShmError::InvalidConfig`.
+    pub fn set_space_threshold(&self, percent: Option<u8>) -> Result<()> {
+        self.ring_rx.set_space_threshold(percent)
+    }
+
+    /// Сколько просроченных сообщений от клиента пропущено или убрано (с
+    /// начала текущего соединения).
+    pub fn expired_messages(&self) -> u32 {
+        self.ring_rx.expired_count()
+    }
+
+    /// Метка времени последнего прочитанного от клиента кадра (см.
+    /// `RingBuffer::take_timestamp`); забирать после каждого чтения.
+    pub(crate) fn take_rx_timestamp(&self) -> Option<u64> {
+        let control = self
+            .control
+            .as_ref()
+            .and_then(|(_, control_rx)| control_rx.take_timestamp());
+        let bulk = self.ring_rx.take_timestamp();
+        control.or(bulk)
+    }
+
+    /// Непрочитанные кадры (от клиента, к клиенту) во всех кольцах, включая
+    /// управляющие.
+    pub(crate) fn ring_depths(&self) -> (u32, u32) {
+        let (control_rx, control_tx) = self
+            .control
+            .as_ref()
+            .map_or((0, 0), |(tx, rx)| (rx.message_count(), tx.message_count()));
+        (
+            self.ring_rx.message_count() + control_rx,
+            self.ring_tx.message_count() + control_tx,
+        )
+    }
+
+    /// Сколько раз за `write_pos` нашёлся кадр, учтённый клиентом в
+    /// счётчике, но не записанный (клиент умер посреди записи или заголовок
+    /// кольца сбит); такие кадры не выдаются.
+    pub fn torn_messages(&self) -> u32 {
+        self.ring_rx.torn_records()
+    }
+
+    /// Кредитный режим для сообщений от клиента: клиент может держать в
+    /// bulk-кольце не больше `window` непрочитанных сообщений/байт, сверх
+    /// этого `send_to_server` возвращает `ShmError::QueueFull` вместо
+    /// вытеснения старых сообщений. Кредиты возвращаются по мере приёма,
+    /// ожидающий клиент будится space-событием. `None` -- обычный режим.
+    /// Управляющие кольца кредитов не используют.
+    pub fn set_credit_window(&mut self, window: Option<CreditWindow>) -> Result<()> {
+        self.ring_rx.set_credit_window(window)?;
+        self.credit_window = window;
+        Ok(())
+    }
+
+    /// Занятость bulk-кольца сообщений клиенту (см. [`RingStatus`]).
+    pub fn tx_status(&self) -> RingStatus {
+        self.ring_tx.status()
+    }
+
+    /// Занятость bulk-кольца сообщений от клиента.
+    pub fn rx_status(&self) -> RingStatus {
+        self.ring_rx.status()
+    }
+
+    /// Отправленные, принятые, вытесненные и отброшенные сообщения и
+    /// глубина очереди обоих bulk-колец (см. [`ChannelStats`]).
+    pub fn stats(&self) -> ChannelStats {
+        ChannelStats {
+            tx: self.ring_tx.stats(),
+            rx: self.ring_rx.stats(),
+        }
+    }
+
+    /// Сбрасывает пики занятости обоих колец.
+    pub fn reset_high_water(&self) {
+        self.ring_tx.reset_high_water();
+        self.ring_rx.reset_high_water();
+    }
+
+    /// Свободные кредиты на отправку клиенту; `None`, если клиент не
+    /// включил кредитный режим.
+    pub fn send_credits(&self) -> Option<CreditWindow> {
+        self.ring_tx.available_credits()
+    }
+
+    fn apply_credit_window(&self) {
+        // Окно уже проверено в set_credit_window.
+        let _ = self.ring_rx.set_credit_window(self.credit_window);
+    }
+
     pub fn send_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
-        self.ensure_connected()?;
-        let result = self.ring_tx.write_message(payload)?;
+        self.send_to_client_with_flags(payload, 0)
+    }
+
+    /// Как `send_to_client`, плюс флаги приложения в заголовке кадра (биты
+    /// `MSG_USER_FLAGS_MASK`, иначе `ShmError::InvalidConfig`).
+    pub fn send_to_client_with_flags(&self, payload: &[u8], flags: u16) -> Result<WriteOutcome> {
+        ensure_config(
+            flags & !MSG_USER_FLAGS_MASK == 0,
+            "message flags must fit MSG_USER_FLAGS_MASK",
+        )?;
+        let result = self.ring_tx.write_blocking(
+            self.events.as_ref().map(|events| &events.s2c.space),
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.write_frame(payload, flags)
+            },
+        )?;
         // Сигнализируем только если events доступны
         if let Some(ref events) = self.events {
             if result.was_empty {
@@ -298,21 +1110,294 @@ impl SharedServer {
         Ok(result)
     }
 
+    /// Отправляет клиенту, дожидаясь места не дольше `timeout`: кольцо
+    /// полно -- ждёт события `space` и повторяет, пока не истечёт срок
+    /// (`ShmError::Timeout`). Непрочитанное не вытесняется, какой бы ни была
+    /// `WritePolicy`.
+    pub fn send_blocking(&self, payload: &[u8], timeout: Duration) -> Result<WriteOutcome> {
+        let result = self.ring_tx.write_within(
+            self.events.as_ref().map(|events| &events.s2c.space),
+            timeout,
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.try_write_message(payload)
+            },
+        )?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Как `send_to_client`, но без ожидания и без вытеснения, при любой
+    /// `WritePolicy`: нет места -- сразу `ShmError::QueueFull`, а уже
+    /// поставленные в очередь сообщения остаются на месте. Для тех, кто
+    /// повторит позже и не может терять данные.
+    pub fn try_send_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.try_write_message(payload)?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Как `send_to_client`, но со своим сроком годности вместо срока по
+    /// умолчанию (`None` -- бессрочно).
+    pub fn send_to_client_with_ttl(
+        &self,
+        payload: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<WriteOutcome> {
+        let result = self.ring_tx.write_blocking(
+            self.events.as_ref().map(|events| &events.s2c.space),
+            || {
+                self.ensure_connected()?;
+                self.ring_tx.write_message_ttl(payload, ttl)
+            },
+        )?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Кусок большого сообщения auto-режима (см. `chunking`).
+    pub(crate) fn send_chunk_to_client(&self, chunk: &[u8], flags: u16) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.write_chunk(chunk, flags)?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Отправляет `payloads` клиенту за один проход, выставляя событие
+    /// данных не больше одного раза -- на высоком темпе сообщений оно
+    /// дороже самой записи. Каждое сообщение -- как в `send_to_client`
+    /// (вытесняет старые при переполнении); ошибка прерывает проход, а
+    /// записанное до неё уже отправлено. Всё-или-ничего --
+    /// `send_batch_atomic_to_client`.
+    pub fn send_batch_to_client(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        self.ring_tx.write_messages(payloads, || {
+            if let Some(ref events) = self.events {
+                let _ = events.s2c.data.set();
+            }
+        })
+    }
+
+    /// Отправляет `payloads` клиенту одной публикацией: клиент увидит либо
+    /// всю пачку, либо ничего (например, тройку begin/update/commit). Пачка
+    /// не вытесняет непрочитанное: не хватает места -- `ShmError::QueueFull`
+    /// и не отправлено ничего.
+    pub fn send_batch_atomic_to_client(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let result = self.ring_tx.write_batch(payloads)?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Место под сообщение клиенту прямо в разделяемой памяти: payload
+    /// пишется в guard (`&mut [u8]`, содержимое не обнулено), `commit()`
+    /// отправляет его. Брошенный guard не отправляет ничего. `&mut self` --
+    /// пока guard жив, других отправок нет.
+    pub fn reserve_to_client(&mut self, len: usize) -> Result<WriteGuard<'_>> {
+        self.ensure_connected()?;
+        Ok(self
+            .ring_tx
+            .reserve(len)?
+            .signal_on_commit(self.events.as_ref().map(|events| &events.s2c.data)))
+    }
+
+    /// Срочное сообщение клиенту через управляющее кольцо (см.
+    /// `start_with_control_rings`). Без control-колец --
+    /// `ShmError::Unsupported`.
+    pub fn send_control_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let (control_tx, _) = self
+            .control
+            .as_ref()
+            .ok_or(ShmError::Unsupported("channel has no control rings"))?;
+        let result = control_tx.write_message(payload)?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Блок состояния канала (см. `xshm::state`); `None` в anonymous
+    /// режиме. Переживает переподключения клиентов.
+    pub fn state(&self) -> Option<&SharedState> {
+        self.state.as_ref()
+    }
+
+    pub fn has_control_rings(&self) -> bool {
+        self.control.is_some()
+    }
+
+    /// Передаёт handle клиенту: handle дублируется в процесс клиента
+    /// (`NtDuplicateObject`), клиент получает новое значение через
+    /// `receive_any_from_server` как `Received::Handle`. Исходный handle
+    /// остаётся за вызывающим. Непрочитанный handle-кадр не вытесняется при
+    /// overflow -- вместо этого запись возвращает `ShmError::QueueFull`.
+    pub fn send_handle_to_client(&self, handle: isize) -> Result<WriteOutcome> {
+        self.ensure_connected()?;
+        let pid = self.view.control_block().reserved[RESERVED_CLIENT_PID_INDEX]
+            .load(Ordering::Acquire);
+        let result = handles::write_handle(&self.ring_tx, self.backend, handle, pid)?;
+        if let Some(ref events) = self.events {
+            if result.was_empty {
+                let _ = events.s2c.data.set();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Handle-кадры, пришедшие сюда, закрываются и пропускаются.
+    /// Управляющее кольцо (если есть) выбирается раньше bulk-кольца.
     pub fn receive_from_client(&self, buffer: &mut Vec<u8>) -> Result<usize> {
         self.ensure_connected()?;
-        let len = self.ring_rx.read_message(buffer)?;
+        let result = handles::read_message(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// `poll_client` и `receive_from_client` одним вызовом: ждёт сообщения
+    /// не дольше `timeout` (`None` -- без срока) и читает его; не
+    /// дождался -- `ShmError::Timeout`. Anonymous-сервер опрашивает кольцо
+    /// раз в миллисекунду.
+    pub fn receive_from_client_timeout(
+        &self,
+        buffer: &mut Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match self.receive_from_client(buffer) {
+                Err(ShmError::QueueEmpty) => {}
+                result => return result,
+            }
+            let left = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if left.is_some_and(|left| left.is_zero()) {
+                return Err(ShmError::Timeout);
+            }
+            if self.events.is_some() {
+                // Ложное пробуждение -- ещё одна попытка чтения.
+                self.poll_client(left)?;
+            } else {
+                std::thread::sleep(left.map_or(Duration::from_millis(1), |left| {
+                    left.min(Duration::from_millis(1))
+                }));
+            }
+        }
+    }
+
+    /// Как `receive_from_client`, вместе с номером кадра: разрыв в
+    /// номерах -- сообщения, вытесненные до приёма. Номер есть, только если
+    /// клиент включил `set_sequence_numbers`, и только у сообщений
+    /// bulk-кольца.
+    pub fn receive_message_from_client(&self, buffer: &mut Vec<u8>) -> Result<ReceivedMessage> {
+        self.ensure_connected()?;
+        let result = handles::read_message_info(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result.map(|frame| ReceivedMessage {
+            len: frame.len,
+            seq: frame.sequence,
+        })
+    }
+
+    /// Как `receive_from_client`, вместе с флагами заголовка кадра (куски
+    /// auto-режима помечены `MSG_FLAG_CHUNK`).
+    pub(crate) fn receive_frame_from_client(&self, buffer: &mut Vec<u8>) -> Result<(usize, u16)> {
+        self.ensure_connected()?;
+        let result = handles::read_message_frame(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Приём без копии в `Vec`: `f` разбирает payload прямо из
+    /// разделяемой памяти и может быть вызван повторно, если кадр вытеснили
+    /// во время вызова (см. `RingBuffer::read_frame_with`). Handle-кадры
+    /// закрываются и пропускаются, как в `receive_from_client`.
+    pub fn receive_with<T>(&self, f: impl FnMut(&[u8]) -> T) -> Result<T> {
+        self.ensure_connected()?;
+        let result = handles::read_message_with(self.rx_lane(), self.backend, f);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Следующее сообщение без изъятия: копируется в `buffer`, а
+    /// следующий приём вернёт его же -- можно посмотреть на заголовок и
+    /// решить, забирать ли. Handle-кадры перед ним закрываются и
+    /// пропускаются, как в `receive_from_client`.
+    pub fn peek_message(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.ensure_connected()?;
+        let result = handles::peek_message(self.rx_lane(), self.backend, buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Забирает все сообщения, что есть в очереди на момент вызова,
+    /// отдавая их `f` без копии (как `receive_with`), и возвращает их
+    /// число. Space-событие выставляется один раз в конце, а не на каждое
+    /// сообщение. Ошибка прерывает приём; уже отданное `f` забрано.
+    pub fn drain_into(&self, f: &mut impl FnMut(&[u8])) -> Result<usize> {
+        self.ensure_connected()?;
+        let queued = self.ring_rx.message_count()
+            + self
+                .control
+                .as_ref()
+                .map_or(0, |(_, control_rx)| control_rx.message_count());
+        let result = handles::drain_into(|| self.rx_lane(), self.backend, queued, f);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Приём сообщения или handle'а от клиента.
+    pub fn receive_any_from_client(&self, buffer: &mut Vec<u8>) -> Result<Received> {
+        self.ensure_connected()?;
+        let result = handles::read_any(self.rx_lane(), buffer);
+        self.signal_rx_space();
+        result
+    }
+
+    /// Кольцо, из которого читать следующим: непустое управляющее, иначе
+    /// bulk.
+    fn rx_lane(&self) -> &RingBuffer {
+        match &self.control {
+            Some((_, control_rx)) if !control_rx.is_empty() => control_rx,
+            _ => &self.ring_rx,
+        }
+    }
+
+    fn signal_rx_space(&self) {
         // Сигнализируем только если events доступны
         if let Some(ref events) = self.events {
-            if self.ring_rx.message_count() == 0 {
+            // take_credit_stall первым: флаг сбрасывается при любом исходе.
+            if self.ring_rx.take_credit_stall() || self.ring_rx.space_available() {
                 let _ = events.c2s.space.set();
             }
         }
-        Ok(len)
     }
 
     pub fn poll_client(&self, timeout: Option<Duration>) -> Result<bool> {
         self.ensure_connected()?;
-        if !self.ring_rx.is_empty() {
+        if !self.rx_lane().is_empty() {
             return Ok(true);
         }
         // Для anonymous режима просто проверяем буфер (polling)
@@ -321,10 +1406,36 @@ impl SharedServer {
         }
         self.events.as_ref().unwrap().c2s.data.wait(timeout)
     }
+
+    /// Как `poll_client`, но ждёт сразу данные клиента, место в кольце к
+    /// нему и его отключение ([`PollResult`]): ручной цикл событий не
+    /// пропустит disconnect, пока спит на данных. Клиент, ушедший без
+    /// события (процесс умер, событие забрал другой поток), тоже даёт
+    /// `PeerDisconnected`. У anonymous канала событий нет -- опрос без
+    /// ожидания.
+    pub fn poll_events(&self, timeout: Option<Duration>) -> Result<PollResult> {
+        self.ensure_connected()?;
+        let handles = self.events.as_ref().map(|events| {
+            [
+                events.disconnect.raw_handle(),
+                events.c2s.data.raw_handle(),
+                events.s2c.space.raw_handle(),
+            ]
+        });
+        poll::poll_events(
+            handles,
+            timeout,
+            || !self.rx_lane().is_empty(),
+            || self.is_peer_alive(),
+        )
+    }
 }
 
 impl Drop for SharedServer {
     fn drop(&mut self) {
+        if self.detached {
+            return;
+        }
         let control = self.view.control_block();
         control
             .server_state
@@ -341,9 +1452,1158 @@ impl Drop for SharedServer {
                 .store(HANDSHAKE_IDLE, Ordering::Release);
         }
         if self.connected {
+            self.view.store_disconnect_reason(
+                RESERVED_SERVER_DISCONNECT_REASON_INDEX,
+                DisconnectReason::Graceful,
+            );
             if let Some(ref events) = self.events {
                 let _ = events.disconnect.set();
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{
+        ring_geometry, FEATURE_CHECKSUM, GEOMETRY_LAYOUT_VERSION_OFFSET,
+        GEOMETRY_RING_SHIFT_OFFSET, LAYOUT_RING_SHIFT_OFFSET, MAX_MESSAGES,
+        RESERVED_RING_GEOMETRY_INDEX,
+    };
+    use crate::ring::DirectionStats;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_SERVER_{tag}_{}", std::process::id())
+    }
+
+    /// Имитирует HELLO клиента версии `version` с маской `features`.
+    fn hello(server: &SharedServer, version: u32, features: u32) {
+        let control = server.view.control_block();
+        control.reserved[RESERVED_CLIENT_VERSION_INDEX].store(version, Ordering::Release);
+        control.reserved[RESERVED_CLIENT_FEATURES_INDEX].store(features, Ordering::Release);
+        control
+            .client_state
+            .store(HANDSHAKE_CLIENT_HELLO, Ordering::Release);
+    }
+
+    #[test]
+    fn second_server_on_live_channel_is_refused() {
+        let name = unique("TWICE");
+        let mut server = SharedServer::start(&name).unwrap();
+        assert!(matches!(
+            SharedServer::start(&name),
+            Err(ShmError::WindowsError {
+                code: 0xC000_0035,
+                ..
+            })
+        ));
+        // Канал первого сервера не тронут: клиент подключается.
+        let client = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).map(|_| ())
+        });
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        client.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn incompatible_client_version_is_rejected() {
+        let mut server = SharedServer::start(&unique("VERSION")).unwrap();
+        hello(&server, 0x0003_0000, SUPPORTED_FEATURES);
+        assert_eq!(
+            server.wait_for_client_noevent(Some(Duration::from_secs(1))),
+            Err(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: 0x0003_0000,
+            })
+        );
+        let control = server.view.control_block();
+        assert_eq!(control.client_state.load(Ordering::Acquire), HANDSHAKE_IDLE);
+        assert!(!server.is_connected());
+
+        // Новый minor той же major-версии совместим.
+        hello(
+            &server,
+            SHARED_VERSION + 7,
+            FEATURE_CONTROL_RINGS | FEATURE_CHECKSUM,
+        );
+        server
+            .wait_for_client_noevent(Some(Duration::from_secs(1)))
+            .unwrap();
+        // Управляющих колец у канала нет -- их не согласовать.
+        assert_eq!(server.negotiated_features(), FEATURE_CHECKSUM);
+    }
+
+    #[test]
+    fn legacy_client_negotiates_no_features() {
+        let mut server = SharedServer::start(&unique("LEGACY")).unwrap();
+        // Клиент старой версии не пишет ни версию, ни маску.
+        hello(&server, 0, 0);
+        server
+            .wait_for_client_noevent(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(server.negotiated_features(), 0);
+        assert_eq!(
+            server.view.control_block().reserved[RESERVED_NEGOTIATED_FEATURES_INDEX]
+                .load(Ordering::Acquire),
+            0
+        );
+    }
+
+    #[test]
+    fn client_rejects_section_of_other_major_version() {
+        let name = unique("SECTION");
+        let server = SharedServer::start(&name).unwrap();
+        // SAFETY: клиентов ещё нет, запись не гонится с чтением.
+        unsafe { (*server.view.control_block_ptr()).version = 0x0003_0000 };
+        assert_eq!(
+            crate::SharedClient::connect(&name, Duration::from_millis(50)).err(),
+            Some(ShmError::VersionMismatch {
+                local: SHARED_VERSION,
+                remote: 0x0003_0000,
+            })
+        );
+    }
+
+    #[test]
+    fn client_rejects_section_of_other_ring_geometry() {
+        let name = unique("PROFILE");
+        let server = SharedServer::start(&name).unwrap();
+        let reserved = &server.view.control_block().reserved;
+        let geometry = &reserved[RESERVED_RING_GEOMETRY_INDEX];
+        assert_eq!(geometry.load(Ordering::Acquire), ring_geometry());
+        let connect = || crate::SharedClient::connect(&name, Duration::from_millis(50)).err();
+
+        // Сервер, собранный с другим MAX_MESSAGES при той же ёмкости.
+        geometry.store(ring_geometry() - 1, Ordering::Release);
+        assert_eq!(
+            connect(),
+            Some(ShmError::LayoutMismatch {
+                what: "max messages",
+                local: MAX_MESSAGES,
+                remote: MAX_MESSAGES - 1,
+            })
+        );
+        // Другая раскладка заголовков проверяется первой.
+        geometry.store(
+            (ring_geometry() + (1 << GEOMETRY_RING_SHIFT_OFFSET))
+                ^ (3 << GEOMETRY_LAYOUT_VERSION_OFFSET),
+            Ordering::Release,
+        );
+        assert!(matches!(
+            connect(),
+            Some(ShmError::LayoutMismatch {
+                what: "layout version",
+                ..
+            })
+        ));
+        // Сервер старой версии: только профиль во флагах раскладки.
+        geometry.store(0, Ordering::Release);
+        reserved[RESERVED_LAYOUT_FLAGS_INDEX].store(
+            layout_ring_flags() + (1 << LAYOUT_RING_SHIFT_OFFSET),
+            Ordering::Release,
+        );
+        assert_eq!(
+            connect(),
+            Some(ShmError::LayoutMismatch {
+                what: "ring capacity",
+                local: RING_CAPACITY as u32,
+                remote: 2 * RING_CAPACITY as u32,
+            })
+        );
+    }
+
+    #[test]
+    fn stale_client_sees_connection_reset() {
+        let name = unique("RESET");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connect = |name: &str| {
+            let name = name.to_owned();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+
+        let connector = connect(&name);
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let stale = connector.join().unwrap();
+
+        // Сервер счёл клиента пропавшим и принял другого: generation сменился.
+        server.mark_disconnected();
+        let connector = connect(&name);
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let fresh = connector.join().unwrap();
+
+        let mut buffer = Vec::new();
+        assert!(matches!(
+            stale.send_to_server(b"late"),
+            Err(ShmError::ConnectionReset)
+        ));
+        assert_eq!(
+            stale.receive_from_server(&mut buffer),
+            Err(ShmError::ConnectionReset)
+        );
+        // Уход устаревшего клиента не рвёт новое соединение.
+        drop(stale);
+
+        fresh.send_to_server(b"hello").unwrap();
+        server.receive_from_client(&mut buffer).unwrap();
+        assert_eq!(buffer, b"hello");
+        server.send_to_client(b"world").unwrap();
+        fresh.receive_from_server(&mut buffer).unwrap();
+        assert_eq!(buffer, b"world");
+    }
+
+    #[test]
+    fn accept_next_client_serves_clients_in_turn() {
+        let name = unique("NEXT");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connect = |name: &str| {
+            let name = name.to_owned();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+
+        let connector = connect(&name);
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let first = connector.join().unwrap();
+        first.send_to_server(b"first").unwrap();
+
+        // Пока следующий не пришёл, прежний клиент уже отрезан.
+        assert_eq!(
+            server.accept_next_client(Some(Duration::from_millis(50))),
+            Err(ShmError::Timeout)
+        );
+        assert_eq!(
+            first.send_to_server(b"late").err(),
+            Some(ShmError::ConnectionReset)
+        );
+        drop(first);
+
+        for round in 0..2u8 {
+            let connector = connect(&name);
+            if round > 0 {
+                server
+                    .accept_next_client(Some(Duration::from_secs(2)))
+                    .unwrap();
+            } else {
+                server
+                    .wait_for_client(Some(Duration::from_secs(2)))
+                    .unwrap();
+            }
+            let client = connector.join().unwrap();
+            let mut buffer = Vec::new();
+            client.send_to_server(&[round; 4]).unwrap();
+            server.receive_from_client(&mut buffer).unwrap();
+            assert_eq!(buffer, [round; 4]);
+        }
+    }
+
+    #[test]
+    fn peers_exchange_pids() {
+        let name = unique("PEERPID");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        assert_eq!(server.peer_pid(), Some(std::process::id()));
+        assert_eq!(client.peer_pid(), Some(std::process::id()));
+        assert!(server.is_peer_process_alive());
+        assert!(client.is_peer_process_alive());
+
+        let mut child = platform::spawn_exiting_child();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        server.view.control_block().reserved[RESERVED_CLIENT_PID_INDEX]
+            .store(dead_pid, Ordering::Release);
+        assert_eq!(server.peer_pid(), Some(dead_pid));
+        assert!(!server.is_peer_process_alive());
+        assert!(!server.is_peer_alive());
+    }
+
+    #[test]
+    fn global_namespace_channel_connects() {
+        let name = unique("GLOBALNS");
+        let options = ServerOptions {
+            namespace: Namespace::Global,
+            ..ServerOptions::default()
+        };
+        let mut server = SharedServer::start_with_options(&name, &options).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || {
+                crate::SharedClient::connect_in(&name, Namespace::Global, Duration::from_secs(2))
+                    .unwrap()
+            }
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        client.send_to_server(b"hello").unwrap();
+        let mut buffer = Vec::new();
+        let len = server
+            .receive_from_client_timeout(&mut buffer, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(&buffer[..len], b"hello");
+    }
+
+    #[test]
+    fn builders_connect_owner_only_channel() {
+        let name = unique("BUILDER");
+        assert!(matches!(
+            SharedServer::builder(&name)
+                .ring_capacity(RING_CAPACITY * 2)
+                .start(),
+            Err(ShmError::InvalidConfig(_))
+        ));
+        let mut server = SharedServer::builder(&name)
+            .security(Security::Owner)
+            .ring_capacity(RING_CAPACITY)
+            .start()
+            .unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || {
+                crate::SharedClient::builder(&name)
+                    .ring_capacity(RING_CAPACITY)
+                    .timeout(Duration::from_secs(2))
+                    .connect()
+                    .unwrap()
+            }
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        client.send_to_server(b"hello").unwrap();
+        let mut buffer = Vec::new();
+        let len = server
+            .receive_from_client_timeout(&mut buffer, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(&buffer[..len], b"hello");
+    }
+
+    #[test]
+    fn client_retries_until_server_starts() {
+        let name = unique("RETRY");
+        let policy = crate::RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            multiplier: 2,
+        };
+        assert!(matches!(
+            crate::SharedClient::connect_with_retry(&name, &policy),
+            Err(ref error) if platform::is_not_found(error)
+        ));
+
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || {
+                let policy = crate::RetryPolicy {
+                    max_attempts: 100,
+                    initial_delay: Duration::from_millis(5),
+                    max_delay: Duration::from_millis(20),
+                    multiplier: 2,
+                };
+                crate::SharedClient::connect_with_retry(&name, &policy).unwrap()
+            }
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        let mut server = SharedServer::start(&name).unwrap();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert!(client.is_connected());
+    }
+
+    #[test]
+    fn heartbeat_reveals_a_silent_peer() {
+        let name = unique("HEARTBEAT");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        // Без heartbeat судить не о чем: пир считается отзывчивым.
+        assert_eq!(server.peer_heartbeat_age(), None);
+        assert!(server.is_peer_responsive(Duration::ZERO));
+
+        client.heartbeat();
+        server.heartbeat();
+        assert!(server.is_peer_responsive(Duration::from_secs(1)));
+        assert!(client.is_peer_responsive(Duration::from_secs(1)));
+
+        // Клиент замолчал (как убитый процесс): через max_age он не отвечает.
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(server.peer_heartbeat_age().unwrap() >= Duration::from_millis(20));
+        assert!(!server.is_peer_responsive(Duration::from_millis(20)));
+        client.heartbeat();
+        assert!(server.is_peer_responsive(Duration::from_millis(20)));
+
+        // Heartbeat прежнего клиента не достаётся следующему.
+        drop(client);
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .accept_next_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert_eq!(server.peer_heartbeat_age(), None);
+        assert!(client.peer_heartbeat_age().is_some());
+    }
+
+    #[test]
+    fn receive_with_decodes_without_copy() {
+        let name = unique("BORROW");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        client.send_to_server(&7u32.to_le_bytes()).unwrap();
+        client.send_to_server(b"tail").unwrap();
+        let decode = |payload: &[u8]| u32::from_le_bytes(payload.try_into().unwrap());
+        assert_eq!(server.receive_with(decode), Ok(7));
+        assert_eq!(server.receive_with(<[u8]>::to_vec), Ok(b"tail".to_vec()));
+        assert_eq!(server.receive_with(|_| ()), Err(ShmError::QueueEmpty));
+
+        server.send_to_client(b"back").unwrap();
+        assert_eq!(client.receive_with(|payload| payload.len()), Ok(4));
+    }
+
+    /// Большие страницы -- только оптимизация: без привилегии сервер
+    /// стартует на обычных, клиент подключается одинаково.
+    #[test]
+    fn large_pages_server_serves_ordinary_client() {
+        let name = unique("LARGE");
+        let options = ServerOptions {
+            control_rings: true,
+            large_pages: true,
+            ..ServerOptions::default()
+        };
+        let mut server = SharedServer::start_with_options(&name, &options).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert!(client.has_control_rings());
+
+        client.send_to_server(b"huge").unwrap();
+        let mut buffer = Vec::new();
+        assert_eq!(server.receive_from_client(&mut buffer), Ok(4));
+        assert_eq!(buffer, b"huge");
+        assert!(!SharedServer::start(&unique("SMALL")).unwrap().large_pages());
+    }
+
+    #[test]
+    fn reserved_send_is_delivered_on_commit() {
+        let name = unique("RESERVE");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut client = connector.join().unwrap();
+
+        let mut guard = server.reserve_to_client(8).unwrap();
+        guard.copy_from_slice(&0xDEAD_BEEF_u64.to_le_bytes());
+        assert!(guard.commit().was_empty);
+        // Брошенный guard ничего не отправляет.
+        drop(server.reserve_to_client(4).unwrap());
+        let mut out = Vec::new();
+        assert_eq!(client.receive_from_server(&mut out), Ok(8));
+        assert_eq!(out, 0xDEAD_BEEF_u64.to_le_bytes());
+        assert_eq!(
+            client.receive_from_server(&mut out),
+            Err(ShmError::QueueEmpty)
+        );
+
+        let mut guard = client.reserve_to_server(5).unwrap();
+        guard.copy_from_slice(b"hello");
+        guard.commit();
+        assert_eq!(server.receive_with(<[u8]>::to_vec), Ok(b"hello".to_vec()));
+        assert_eq!(
+            client.reserve_to_server(1).err(),
+            Some(ShmError::MessageTooSmall)
+        );
+    }
+
+    #[test]
+    fn batch_send_delivers_every_message() {
+        let name = unique("SENDBATCH");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        assert!(
+            server
+                .send_batch_to_client(&[b"one", b"two", b"three"])
+                .unwrap()
+                .was_empty
+        );
+        client.send_batch_to_server(&[b"up", b"down"]).unwrap();
+        let mut out = Vec::new();
+        for expected in [&b"one"[..], b"two", b"three"] {
+            client.receive_from_server(&mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+        for expected in [&b"up"[..], b"down"] {
+            server.receive_from_client(&mut out).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn write_policy_fails_or_waits_instead_of_overwriting() {
+        let name = unique("WRITEPOLICY");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        server.set_write_policy(WritePolicy::Fail);
+        for _ in 0..crate::constants::MAX_MESSAGES {
+            server.send_to_client(b"fill").unwrap();
+        }
+        assert_eq!(server.send_to_client(b"more").err(), Some(ShmError::QueueFull));
+
+        server.set_write_policy(WritePolicy::Block(Duration::from_millis(20)));
+        assert_eq!(server.send_to_client(b"more").err(), Some(ShmError::QueueFull));
+
+        // Место освобождает приём клиента -- отправка дожидается его.
+        server.set_write_policy(WritePolicy::Block(Duration::from_secs(5)));
+        let start = std::time::Instant::now();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                client.receive_from_server(&mut Vec::new()).unwrap();
+            });
+            assert_eq!(server.send_to_client(b"more").unwrap().overwritten, 0);
+        });
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.ring_tx.drop_count(), 0);
+    }
+
+    #[test]
+    fn ring_status_reports_occupancy_and_peak() {
+        let name = unique("RINGSTATUS");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        let idle = server.tx_status();
+        assert_eq!((idle.used_bytes, idle.free_bytes), (0, idle.capacity));
+
+        for payload in [&b"one"[..], b"two", b"three"] {
+            server.send_to_client(payload).unwrap();
+        }
+        let tx = server.tx_status();
+        assert_eq!(tx.message_count, 3);
+        assert_eq!(tx.used_bytes + tx.free_bytes, tx.capacity);
+        assert_eq!(
+            (tx.high_water_bytes, tx.high_water_messages),
+            (tx.used_bytes, 3)
+        );
+        // Та же занятость с другой стороны; пик получателя -- до первого приёма.
+        assert_eq!(client.rx_status().used_bytes, tx.used_bytes);
+        assert_eq!(client.rx_status().high_water_messages, 0);
+
+        let mut out = Vec::new();
+        client.receive_from_server(&mut out).unwrap();
+        assert_eq!(client.rx_status().high_water_messages, 3);
+        let tx = server.tx_status();
+        assert_eq!((tx.message_count, tx.high_water_messages), (2, 3));
+        server.reset_high_water();
+        assert_eq!(server.tx_status().high_water_bytes, 0);
+    }
+
+    #[test]
+    fn stats_count_both_directions_and_overwrites() {
+        let name = unique("STATS");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert_eq!(server.stats(), ChannelStats::default());
+
+        // Кольцо вмещает MAX_MESSAGES сообщений: лишние вытесняют старые.
+        let extra = 5;
+        for i in 0..MAX_MESSAGES + extra {
+            server.send_to_client(&i.to_le_bytes()).unwrap();
+        }
+        client.send_to_server(b"up").unwrap();
+        let mut out = Vec::new();
+        client.receive_from_server(&mut out).unwrap();
+        assert_eq!(out, extra.to_le_bytes());
+        server.receive_from_client(&mut out).unwrap();
+
+        let stats = server.stats();
+        assert_eq!(
+            stats.tx,
+            DirectionStats {
+                sent: MAX_MESSAGES + extra,
+                received: 1,
+                overwritten: extra,
+                dropped: 0,
+                queue_depth: MAX_MESSAGES - 1,
+            }
+        );
+        assert_eq!(
+            (stats.rx.sent, stats.rx.received, stats.rx.queue_depth),
+            (1, 1, 0)
+        );
+        // Счётчики лежат в секции: клиент видит те же, направления наоборот.
+        let mirrored = client.stats();
+        assert_eq!((mirrored.rx, mirrored.tx), (stats.tx, stats.rx));
+    }
+
+    #[test]
+    fn try_send_never_overwrites_queued_messages() {
+        let name = unique("TRYSEND");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        // Политика по умолчанию вытесняет, try_send -- нет.
+        for i in 0..MAX_MESSAGES {
+            server.try_send_to_client(&i.to_le_bytes()).unwrap();
+        }
+        assert_eq!(
+            server.try_send_to_client(b"late").err(),
+            Some(ShmError::QueueFull)
+        );
+        let mut out = Vec::new();
+        client.receive_from_server(&mut out).unwrap();
+        assert_eq!(out, 0u32.to_le_bytes());
+        assert_eq!(server.stats().tx.overwritten, 0);
+        server.try_send_to_client(b"late").unwrap();
+
+        client.try_send_to_server(b"up").unwrap();
+        server.receive_from_client(&mut out).unwrap();
+        assert_eq!(out, b"up");
+    }
+
+    #[test]
+    fn send_blocking_waits_for_the_reader() {
+        let name = unique("SENDWAIT");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        for i in 0..MAX_MESSAGES {
+            server.send_to_client(&i.to_le_bytes()).unwrap();
+        }
+        assert_eq!(
+            server
+                .send_blocking(b"late", Duration::from_millis(20))
+                .err(),
+            Some(ShmError::Timeout)
+        );
+
+        let reader = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let mut out = Vec::new();
+            client.receive_from_server(&mut out).unwrap();
+            assert_eq!(out, 0u32.to_le_bytes());
+            client
+        });
+        server
+            .send_blocking(b"late", Duration::from_secs(2))
+            .unwrap();
+        let client = reader.join().unwrap();
+        assert_eq!(server.stats().tx.overwritten, 0);
+
+        client.send_blocking(b"up", Duration::from_secs(1)).unwrap();
+        let mut out = Vec::new();
+        server.receive_from_client(&mut out).unwrap();
+        assert_eq!(out, b"up");
+    }
+
+    #[test]
+    fn receive_timeout_waits_for_data() {
+        let name = unique("RECVWAIT");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(
+            server.receive_from_client_timeout(&mut out, Some(Duration::from_millis(20))),
+            Err(ShmError::Timeout)
+        );
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            client.send_to_server(b"ping").unwrap();
+            client
+        });
+        let len = server
+            .receive_from_client_timeout(&mut out, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(&out[..len], b"ping");
+
+        let client = sender.join().unwrap();
+        server.send_to_client(b"pong").unwrap();
+        let len = client.receive_from_server_timeout(&mut out, None).unwrap();
+        assert_eq!(&out[..len], b"pong");
+    }
+
+    #[test]
+    fn sequence_numbers_reveal_overwritten_messages() {
+        let name = unique("SEQUENCE");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        let mut out = Vec::new();
+        server.send_to_client(b"plain").unwrap();
+        let plain = client.receive_message_from_server(&mut out).unwrap();
+        assert_eq!(plain, ReceivedMessage { len: 5, seq: None });
+
+        server.set_sequence_numbers(true);
+        for _ in 0..MAX_MESSAGES + 2 {
+            server.send_to_client(b"fill").unwrap();
+        }
+        // Два старейших вытеснены: первый номер в очереди -- 1 + 2.
+        let first = client.receive_message_from_server(&mut out).unwrap();
+        assert_eq!((first.seq, &out[..]), (Some(3), &b"fill"[..]));
+        let next = client.receive_message_from_server(&mut out).unwrap();
+        assert_eq!(next.seq, Some(4));
+    }
+
+    #[test]
+    fn multi_producer_sends_arrive_whole_numbered_and_in_order() {
+        const THREADS: u8 = 4;
+        const PER_THREAD: u32 = 2_000;
+        let name = unique("MPSC");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        server.set_multi_producer(true);
+        server.set_sequence_numbers(true);
+        server.set_write_policy(WritePolicy::Block(Duration::from_secs(5)));
+
+        let total = u32::from(THREADS) * PER_THREAD;
+        std::thread::scope(|scope| {
+            for id in 0..THREADS {
+                let server = &server;
+                scope.spawn(move || {
+                    for n in 0..PER_THREAD {
+                        let mut payload = vec![id];
+                        payload.extend(n.to_le_bytes().repeat(8));
+                        server.send_to_client(&payload).unwrap();
+                    }
+                });
+            }
+
+            let mut last = [None::<u32>; THREADS as usize];
+            let mut out = Vec::new();
+            let mut received = 0;
+            while received < total {
+                let message = match client.receive_message_from_server(&mut out) {
+                    Ok(message) => message,
+                    Err(ShmError::QueueEmpty) => {
+                        std::thread::yield_now();
+                        continue;
+                    }
+                    Err(err) => panic!("receive failed: {err}"),
+                };
+                // Блокирующая отправка ничего не вытесняет: номера подряд.
+                assert_eq!(message.seq, Some(received));
+                let n = u32::from_le_bytes(out[1..5].try_into().unwrap());
+                assert_eq!(out[1..], n.to_le_bytes().repeat(8));
+                let last = &mut last[out[0] as usize];
+                assert!(last.is_none_or(|prev| prev + 1 == n), "{last:?} -> {n}");
+                *last = Some(n);
+                received += 1;
+            }
+        });
+        assert_eq!(
+            client.receive_message_from_server(&mut Vec::new()),
+            Err(ShmError::QueueEmpty)
+        );
+        assert_eq!(server.tx_status().message_count, 0);
+    }
+
+    #[test]
+    fn drain_takes_only_what_was_queued() {
+        let name = unique("DRAIN");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        client.send_batch_to_server(&[b"one", b"two"]).unwrap();
+        let mut seen = Vec::new();
+        // Дописанное во время приёма ждёт следующего вызова.
+        let drained = server
+            .drain_into(&mut |payload: &[u8]| {
+                seen.push(payload.to_vec());
+                client.send_to_server(b"late").unwrap();
+            })
+            .unwrap();
+        assert_eq!(drained, 2);
+        assert_eq!(seen, [b"one".to_vec(), b"two".to_vec()]);
+        assert_eq!(server.drain_into(&mut |_| {}), Ok(2));
+        assert_eq!(server.drain_into(&mut |_| {}), Ok(0));
+
+        server.send_to_client(b"back").unwrap();
+        assert_eq!(client.drain_into(&mut |_| {}), Ok(1));
+    }
+
+    #[test]
+    fn peeked_message_stays_for_receive() {
+        let name = unique("PEEK");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(server.peek_message(&mut out), Err(ShmError::QueueEmpty));
+        client.send_to_server(b"\x02routed").unwrap();
+        assert_eq!(server.peek_message(&mut out), Ok(7));
+        assert_eq!(out[0], 2);
+        assert_eq!(server.receive_from_client(&mut out), Ok(7));
+        assert_eq!(out, b"\x02routed");
+
+        server.send_to_client(b"back").unwrap();
+        assert_eq!(client.peek_message(&mut out), Ok(4));
+        assert_eq!(client.receive_from_server(&mut out), Ok(4));
+        assert_eq!(client.peek_message(&mut out), Err(ShmError::QueueEmpty));
+    }
+
+    /// Копия handle'а секции для `from_section_handle`, который её закроет.
+    fn owned_section_handle(server: &SharedServer) -> isize {
+        #[cfg(unix)]
+        let handle = unsafe { libc::dup(server.section_handle() as libc::c_int) } as isize;
+        #[cfg(windows)]
+        let handle = crate::platform::Backend::Native
+            .duplicate_handle_into(server.section_handle(), std::process::id())
+            .unwrap();
+        handle
+    }
+
+    #[test]
+    fn client_attaches_to_anonymous_section_by_handle() {
+        let mut server = SharedServer::start_anonymous().unwrap();
+        let handle = owned_section_handle(&server);
+        let connector = std::thread::spawn(move || {
+            crate::SharedClient::from_section_handle(handle, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client_noevent(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert_eq!(client.peer_pid(), Some(std::process::id()));
+
+        let mut out = Vec::new();
+        client.send_to_server(b"ping").unwrap();
+        assert_eq!(server.receive_from_client(&mut out), Ok(4));
+        server.send_to_client(b"pong").unwrap();
+        assert_eq!(client.poll_server(Some(Duration::ZERO)), Ok(true));
+        assert_eq!(
+            client.receive_from_server_timeout(&mut out, Some(Duration::from_secs(1))),
+            Ok(4)
+        );
+        assert_eq!(out, b"pong");
+        assert_eq!(client.poll_server(Some(Duration::from_secs(1))), Ok(false));
+    }
+
+    #[test]
+    fn section_handle_attach_times_out_without_server() {
+        let server = SharedServer::start_anonymous().unwrap();
+        let handle = owned_section_handle(&server);
+        assert!(matches!(
+            crate::SharedClient::from_section_handle(handle, Duration::from_millis(20)),
+            Err(ShmError::Timeout)
+        ));
+        let control = server.view.control_block();
+        assert_eq!(control.client_state.load(Ordering::Acquire), HANDSHAKE_IDLE);
+    }
+
+    #[test]
+    fn shared_handles_attach_client_in_target_process() {
+        let mut server = SharedServer::start_anonymous().unwrap();
+        let shared = server.share_with_process(std::process::id());
+        if cfg!(unix) {
+            assert!(matches!(shared, Err(ShmError::Unsupported(_))));
+            return;
+        }
+        let remote = shared.unwrap();
+        assert!(remote.events.is_none());
+        let connector = std::thread::spawn(move || {
+            crate::SharedClient::from_section_handle(remote.section, Duration::from_secs(2))
+                .unwrap()
+        });
+        server
+            .wait_for_client_noevent(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        client.send_to_server(b"hi").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(server.receive_from_client(&mut out), Ok(2));
+
+        let named = SharedServer::start(&unique("SHARE")).unwrap();
+        let remote = named.share_with_process(std::process::id()).unwrap();
+        let events = remote.events.expect("named channel shares its events");
+        for handle in [remote.section, events.s2c_data, events.c2s_data] {
+            crate::platform::Backend::Native.close_handle(handle);
+        }
+    }
+
+    #[test]
+    fn disconnect_leaves_reason_for_peer() {
+        let name = unique("REASON");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connect = || {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+        let connector = connect();
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let mut client = connector.join().unwrap();
+        assert_eq!(server.peer_disconnect_reason(), DisconnectReason::Unknown);
+        client.disconnect(DisconnectReason::Upgrading);
+        assert_eq!(server.peer_disconnect_reason(), DisconnectReason::Upgrading);
+        server.disconnect(DisconnectReason::Upgrading);
+        drop(client);
+
+        // Новое подключение начинается без причин прежнего.
+        let connector = connect();
+        server.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let client = connector.join().unwrap();
+        assert_eq!(server.peer_disconnect_reason(), DisconnectReason::Unknown);
+        assert_eq!(client.peer_disconnect_reason(), DisconnectReason::Unknown);
+        server.disconnect(DisconnectReason::Kicked);
+        assert!(!server.is_connected());
+        assert_eq!(client.peer_disconnect_reason(), DisconnectReason::Kicked);
+        drop(server);
+        assert_eq!(client.peer_disconnect_reason(), DisconnectReason::Kicked);
+    }
+
+    #[test]
+    fn kicked_client_is_reset_and_server_accepts_next() {
+        let name = unique("KICK");
+        let mut server = SharedServer::start(&name).unwrap();
+        assert_eq!(
+            server.kick_client(DisconnectReason::Kicked),
+            Err(ShmError::NotConnected)
+        );
+        let connect = || {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        let epoch = server.connection_epoch().unwrap();
+
+        server.kick_client(DisconnectReason::Kicked).unwrap();
+        assert!(!server.is_connected());
+        assert_eq!(client.peer_disconnect_reason(), DisconnectReason::Kicked);
+        assert!(!client.is_peer_alive());
+        assert_eq!(
+            client.send_to_server(b"late").err(),
+            Some(ShmError::ConnectionReset)
+        );
+        drop(client);
+
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert!(server.connection_epoch().unwrap() > epoch);
+        client.send_to_server(b"next").unwrap();
+        let mut buffer = Vec::new();
+        let len = server
+            .receive_from_client_timeout(&mut buffer, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(&buffer[..len], b"next");
+    }
+
+    #[test]
+    fn peer_alive_follows_disconnect_on_either_side() {
+        let name = unique("ALIVE");
+        let mut server = SharedServer::start(&name).unwrap();
+        assert!(!server.is_peer_alive());
+        let connect = || {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut client = connector.join().unwrap();
+        assert!(server.is_peer_alive());
+        assert!(client.is_peer_alive());
+
+        // Сервер ещё считает себя подключённым, но клиент уже ушёл.
+        client.disconnect(DisconnectReason::Graceful);
+        assert!(server.is_connected());
+        assert!(!server.is_peer_alive());
+        assert!(!client.is_peer_alive());
+        server.mark_disconnected();
+
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        assert!(client.is_peer_alive());
+        drop(server);
+        assert!(client.is_connected());
+        assert!(!client.is_peer_alive());
+    }
+
+    #[test]
+    fn poll_events_reports_data_before_disconnect() {
+        let name = unique("POLLEVENTS");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connect = || {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                crate::SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+            })
+        };
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut client = connector.join().unwrap();
+        let timeout = Some(Duration::from_secs(2));
+        assert_eq!(
+            server.poll_events(Some(Duration::from_millis(20))).unwrap(),
+            PollResult::Timeout
+        );
+
+        // Последний кадр ушедшего клиента не теряется за его disconnect.
+        client.send_to_server(b"last").unwrap();
+        client.disconnect(DisconnectReason::Graceful);
+        assert_eq!(
+            server.poll_events(timeout).unwrap(),
+            PollResult::DataAvailable
+        );
+        let mut buffer = Vec::new();
+        let len = server.receive_from_client(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"last");
+        assert_eq!(
+            server.poll_events(timeout).unwrap(),
+            PollResult::PeerDisconnected
+        );
+        server.mark_disconnected();
+        assert_eq!(server.poll_events(timeout), Err(ShmError::NotConnected));
+
+        let connector = connect();
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+        server.disconnect(DisconnectReason::Kicked);
+        assert_eq!(
+            client.poll_events(timeout).unwrap(),
+            PollResult::PeerDisconnected
+        );
+    }
+}
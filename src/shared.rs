@@ -1,9 +1,37 @@
 use std::ptr::NonNull;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use crate::constants::{
+    ring_profile_matches, CONTROL_RING_CAPACITY, GEOMETRY_LAYOUT_VERSION_MASK,
+    GEOMETRY_LAYOUT_VERSION_OFFSET, GEOMETRY_MAX_MESSAGES_MASK, GEOMETRY_RING_SHIFT_MASK,
+    GEOMETRY_RING_SHIFT_OFFSET, HANDSHAKE_SERVER_READY, LAYOUT_RING_SHIFT_MASK,
+    LAYOUT_RING_SHIFT_OFFSET, LAYOUT_VERSION, MAX_MESSAGES, RESERVED_LAYOUT_FLAGS_INDEX,
+    RESERVED_RING_GEOMETRY_INDEX, RING_CAPACITY,
+};
+use crate::disconnect::DisconnectReason;
+use crate::error::{Result, ShmError};
 use crate::layout::{ControlBlock, RingHeader};
+use crate::platform;
+use crate::ring::RingBuffer;
+
+/// Монотонные часы heartbeat'а в миллисекундах (по модулю 2^32).
+fn heartbeat_now() -> u32 {
+    (platform::monotonic_ns() / 1_000_000) as u32
+}
+
+/// Ёмкость кольца по log2 из флагов или слова геометрии; 0 -- сдвиг
+/// больше u32.
+fn capacity_of_shift(shift: u32) -> u32 {
+    1u32.checked_shl(shift).unwrap_or(0)
+}
 
 pub struct SharedView {
     base: NonNull<u8>,
+    /// Маппинг размером `dual_mapping_size()`: за кольцом B лежат
+    /// управляющие кольца. Фиксируется при создании view, а не читается
+    /// из общей памяти, чтобы пир не мог вывести смещения за маппинг.
+    control_rings: bool,
 }
 
 unsafe impl Send for SharedView {}
@@ -20,9 +48,24 @@ impl SharedView {
     pub unsafe fn new(base: *mut u8) -> Self {
         SharedView {
             base: NonNull::new(base).expect("shared mapping pointer must be valid"),
+            control_rings: false,
         }
     }
 
+    /// # Safety
+    /// Как у `new`, но маппинг размером не менее `dual_mapping_size()`
+    /// (`...+RingBuffer_B+ControlHeader_A+ControlRing_A+ControlHeader_B+ControlRing_B`).
+    pub unsafe fn with_control_rings(base: *mut u8) -> Self {
+        SharedView {
+            base: NonNull::new(base).expect("shared mapping pointer must be valid"),
+            control_rings: true,
+        }
+    }
+
+    pub fn has_control_rings(&self) -> bool {
+        self.control_rings
+    }
+
     pub fn control_block(&self) -> &ControlBlock {
         // SAFETY: base указывает на начало маппинга (инвариант конструктора
         // new), ControlBlock -- первое поле layout'а; маппинг живёт не
@@ -34,6 +77,98 @@ impl SharedView {
         self.base.as_ptr() as *mut ControlBlock
     }
 
+    /// Секция создана сборкой с той же раскладкой заголовков, ёмкостью
+    /// колец и `MAX_MESSAGES` (`RESERVED_RING_GEOMETRY_INDEX`), иначе
+    /// `ShmError::LayoutMismatch` с первым расходящимся параметром. У
+    /// секции сервера старой версии слова геометрии нет -- сверяется
+    /// только профиль колец из флагов раскладки.
+    pub fn check_layout(&self) -> Result<()> {
+        let reserved = &self.control_block().reserved;
+        let geometry = reserved[RESERVED_RING_GEOMETRY_INDEX].load(Ordering::Acquire);
+        if geometry == 0 {
+            let flags = reserved[RESERVED_LAYOUT_FLAGS_INDEX].load(Ordering::Acquire);
+            if ring_profile_matches(flags) {
+                return Ok(());
+            }
+            let remote = match (flags & LAYOUT_RING_SHIFT_MASK) >> LAYOUT_RING_SHIFT_OFFSET {
+                0 => 2 * 1024 * 1024,
+                shift => capacity_of_shift(shift),
+            };
+            return Err(ShmError::LayoutMismatch {
+                what: "ring capacity",
+                local: RING_CAPACITY as u32,
+                remote,
+            });
+        }
+        let fields = [
+            (
+                "layout version",
+                LAYOUT_VERSION,
+                (geometry & GEOMETRY_LAYOUT_VERSION_MASK) >> GEOMETRY_LAYOUT_VERSION_OFFSET,
+            ),
+            (
+                "ring capacity",
+                RING_CAPACITY as u32,
+                capacity_of_shift(
+                    (geometry & GEOMETRY_RING_SHIFT_MASK) >> GEOMETRY_RING_SHIFT_OFFSET,
+                ),
+            ),
+            (
+                "max messages",
+                MAX_MESSAGES,
+                geometry & GEOMETRY_MAX_MESSAGES_MASK,
+            ),
+        ];
+        match fields
+            .into_iter()
+            .find(|&(_, local, remote)| local != remote)
+        {
+            Some((what, local, remote)) => Err(ShmError::LayoutMismatch {
+                what,
+                local,
+                remote,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Отмечает heartbeat стороны в поле `index`
+    /// (`RESERVED_*_HEARTBEAT_INDEX`): миллисекунды монотонных часов, не 0.
+    pub(crate) fn stamp_heartbeat(&self, index: usize) {
+        let now = heartbeat_now().max(1);
+        self.control_block().reserved[index].store(now, Ordering::Release);
+    }
+
+    /// Сколько прошло с последнего heartbeat стороны в поле `index`;
+    /// `None` -- сторона heartbeat не ведёт.
+    pub(crate) fn heartbeat_age(&self, index: usize) -> Option<Duration> {
+        match self.control_block().reserved[index].load(Ordering::Acquire) {
+            0 => None,
+            stamp => Some(Duration::from_millis(
+                heartbeat_now().wrapping_sub(stamp) as u64
+            )),
+        }
+    }
+
+    /// Записывает причину отключения стороны в поле `index`
+    /// (`RESERVED_*_DISCONNECT_REASON_INDEX`).
+    pub(crate) fn store_disconnect_reason(&self, index: usize, reason: DisconnectReason) {
+        self.control_block().reserved[index].store(reason.code(), Ordering::Release);
+    }
+
+    /// Причина отключения стороны из поля `index`.
+    pub(crate) fn disconnect_reason(&self, index: usize) -> DisconnectReason {
+        DisconnectReason::from_code(self.control_block().reserved[index].load(Ordering::Acquire))
+    }
+
+    /// Обе стороны в состоянии соединения (`HANDSHAKE_SERVER_READY`):
+    /// отключаясь, каждая сбрасывает своё поле в IDLE.
+    pub(crate) fn handshake_ready(&self) -> bool {
+        let control = self.control_block();
+        control.server_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY
+            && control.client_state.load(Ordering::Acquire) == HANDSHAKE_SERVER_READY
+    }
+
     pub fn ring_header_a(&self) -> *mut RingHeader {
         // SAFETY: смещение на size_of::<ControlBlock>() остаётся внутри
         // маппинга -- следующее поле layout'а сразу после ControlBlock.
@@ -43,7 +178,7 @@ impl SharedView {
     pub fn ring_header_b(&self) -> *mut RingHeader {
         // SAFETY: ring_buffer_a() + RING_CAPACITY -- следующее поле layout'а
         // (RingHeader_B) сразу после RingBuffer_A, остаётся внутри маппинга.
-        unsafe { self.ring_buffer_a().add(crate::constants::RING_CAPACITY) as *mut RingHeader }
+        unsafe { self.ring_buffer_a().add(RING_CAPACITY) as *mut RingHeader }
     }
 
     pub fn ring_buffer_a(&self) -> *mut u8 {
@@ -58,4 +193,60 @@ impl SharedView {
         // (гарантировано размером, выделенным shared_mapping_size()).
         unsafe { (self.ring_header_b() as *mut u8).add(std::mem::size_of::<RingHeader>()) }
     }
+
+    /// Заголовок управляющего кольца сервер→клиент; `None` без control-колец.
+    pub fn control_header_a(&self) -> Option<*mut RingHeader> {
+        // SAFETY: control_rings => маппинг размером dual_mapping_size(),
+        // управляющие кольца начинаются сразу за RingBuffer_B.
+        self.control_rings
+            .then(|| unsafe { self.ring_buffer_b().add(RING_CAPACITY) as *mut RingHeader })
+    }
+
+    pub fn control_buffer_a(&self) -> Option<*mut u8> {
+        // SAFETY: следующее поле за ControlHeader_A, внутри маппинга.
+        self.control_header_a()
+            .map(|header| unsafe { (header as *mut u8).add(std::mem::size_of::<RingHeader>()) })
+    }
+
+    /// Заголовок управляющего кольца клиент→сервер.
+    pub fn control_header_b(&self) -> Option<*mut RingHeader> {
+        // SAFETY: ControlHeader_B сразу за ControlRing_A, внутри маппинга.
+        self.control_buffer_a()
+            .map(|data| unsafe { data.add(CONTROL_RING_CAPACITY) as *mut RingHeader })
+    }
+
+    pub fn control_buffer_b(&self) -> Option<*mut u8> {
+        // SAFETY: последнее поле раскладки dual_mapping_size().
+        self.control_header_b()
+            .map(|header| unsafe { (header as *mut u8).add(std::mem::size_of::<RingHeader>()) })
+    }
+
+    /// Сброс управляющих колец (если есть) вместе с bulk-кольцами при
+    /// новом соединении.
+    pub fn reset_control_rings(&self, generation: u32) {
+        if let (Some(a), Some(b)) = (self.control_header_a(), self.control_header_b()) {
+            // SAFETY: заголовки внутри маппинга (см. control_header_a/b).
+            unsafe {
+                (*a).reset(generation);
+                (*b).reset(generation);
+            }
+        }
+    }
+
+    /// Управляющие кольца стороны `(tx, rx)`: сервер пишет в A и читает B,
+    /// клиент -- наоборот. `None` без control-колец.
+    pub fn control_rings(&self, server: bool) -> Option<(RingBuffer, RingBuffer)> {
+        let a = (self.control_header_a()?, self.control_buffer_a()?);
+        let b = (self.control_header_b()?, self.control_buffer_b()?);
+        let (tx, rx) = if server { (a, b) } else { (b, a) };
+        let capacity = CONTROL_RING_CAPACITY as u32;
+        // SAFETY: заголовки и кольца внутри маппинга, который живёт не
+        // меньше view (и владельца колец -- SharedServer/SharedClient).
+        unsafe {
+            Some((
+                RingBuffer::with_capacity(tx.0, tx.1, capacity),
+                RingBuffer::with_capacity(rx.0, rx.1, capacity),
+            ))
+        }
+    }
 }
@@ -0,0 +1,346 @@
+//! Половины установленного соединения (`SharedClient::split`,
+//! `SharedServer::split`): [`SendHalf`] пишет только в исходящее кольцо,
+//! [`RecvHalf`] читает только входящее. Кольца однонаправленные (SPSC),
+//! поэтому один поток может ждать `poll_*`/`receive_*_timeout`, пока
+//! другой отправляет, -- без `Mutex` вокруг всего endpoint'а и без
+//! auto-worker'а.
+//!
+//! Каждая половина `Send`, но не `Sync` и не `Clone`: у кольца остаётся
+//! ровно один писатель и один читатель. Переподключения у половин нет;
+//! [`SendHalf::reunite`] возвращает endpoint целиком, а drop последней
+//! половины -- то же, что drop endpoint'а.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::SharedClient;
+use crate::error::{Result, ShmError};
+use crate::handles::{Received, ReceivedMessage};
+use crate::ring::{CreditWindow, RingStatus, WriteOutcome};
+use crate::server::SharedServer;
+
+/// Отправляющая половина endpoint'а `E` (`SharedClient` или `SharedServer`).
+pub struct SendHalf<E> {
+    endpoint: Arc<E>,
+}
+
+/// Принимающая половина endpoint'а `E` (`SharedClient` или `SharedServer`).
+pub struct RecvHalf<E> {
+    endpoint: Arc<E>,
+}
+
+// SAFETY: половины делят endpoint, но SendHalf трогает только исходящее
+// кольцо (сторона писателя), а RecvHalf -- только входящее (сторона
+// читателя); общие поля (события, control block) -- атомики и kernel-
+// объекты. Половины не Sync и не Clone: у каждой стороны кольца один поток.
+unsafe impl Send for SendHalf<SharedClient> {}
+unsafe impl Send for RecvHalf<SharedClient> {}
+unsafe impl Send for SendHalf<SharedServer> {}
+unsafe impl Send for RecvHalf<SharedServer> {}
+
+pub(crate) fn split<E>(endpoint: E) -> (SendHalf<E>, RecvHalf<E>) {
+    let endpoint = Arc::new(endpoint);
+    (
+        SendHalf {
+            endpoint: endpoint.clone(),
+        },
+        RecvHalf { endpoint },
+    )
+}
+
+impl<E> SendHalf<E> {
+    /// Собирает endpoint из двух половин одного `split`; половины разных
+    /// endpoint'ов -- `ShmError::InvalidConfig`.
+    pub fn reunite(self, recv: RecvHalf<E>) -> Result<E> {
+        if !Arc::ptr_eq(&self.endpoint, &recv.endpoint) {
+            return Err(ShmError::InvalidConfig(
+                "halves belong to different endpoints",
+            ));
+        }
+        drop(self);
+        // Других ссылок нет: половины не клонируются.
+        Ok(Arc::into_inner(recv.endpoint).expect("both halves of one split"))
+    }
+}
+
+impl SendHalf<SharedClient> {
+    pub fn send_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.send_to_server(payload)
+    }
+
+    pub fn send_to_server_with_flags(&self, payload: &[u8], flags: u16) -> Result<WriteOutcome> {
+        self.endpoint.send_to_server_with_flags(payload, flags)
+    }
+
+    pub fn send_to_server_with_ttl(
+        &self,
+        payload: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<WriteOutcome> {
+        self.endpoint.send_to_server_with_ttl(payload, ttl)
+    }
+
+    pub fn send_blocking(&self, payload: &[u8], timeout: Duration) -> Result<WriteOutcome> {
+        self.endpoint.send_blocking(payload, timeout)
+    }
+
+    pub fn try_send_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.try_send_to_server(payload)
+    }
+
+    pub fn send_batch_to_server(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.endpoint.send_batch_to_server(payloads)
+    }
+
+    pub fn send_batch_atomic_to_server(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.endpoint.send_batch_atomic_to_server(payloads)
+    }
+
+    pub fn send_control_to_server(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.send_control_to_server(payload)
+    }
+
+    pub fn send_handle_to_server(&self, handle: isize) -> Result<WriteOutcome> {
+        self.endpoint.send_handle_to_server(handle)
+    }
+
+    pub fn send_credits(&self) -> Option<CreditWindow> {
+        self.endpoint.send_credits()
+    }
+
+    pub fn tx_status(&self) -> RingStatus {
+        self.endpoint.tx_status()
+    }
+
+    pub fn is_peer_alive(&self) -> bool {
+        self.endpoint.is_peer_alive()
+    }
+}
+
+impl RecvHalf<SharedClient> {
+    pub fn receive_from_server(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.endpoint.receive_from_server(buffer)
+    }
+
+    pub fn receive_from_server_timeout(
+        &self,
+        buffer: &mut Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        self.endpoint.receive_from_server_timeout(buffer, timeout)
+    }
+
+    pub fn receive_message_from_server(&self, buffer: &mut Vec<u8>) -> Result<ReceivedMessage> {
+        self.endpoint.receive_message_from_server(buffer)
+    }
+
+    pub fn receive_any_from_server(&self, buffer: &mut Vec<u8>) -> Result<Received> {
+        self.endpoint.receive_any_from_server(buffer)
+    }
+
+    pub fn receive_with<T>(&self, f: impl FnMut(&[u8]) -> T) -> Result<T> {
+        self.endpoint.receive_with(f)
+    }
+
+    pub fn peek_message(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.endpoint.peek_message(buffer)
+    }
+
+    pub fn drain_into(&self, f: &mut impl FnMut(&[u8])) -> Result<usize> {
+        self.endpoint.drain_into(f)
+    }
+
+    pub fn poll_server(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.endpoint.poll_server(timeout)
+    }
+
+    pub fn rx_status(&self) -> RingStatus {
+        self.endpoint.rx_status()
+    }
+
+    pub fn is_peer_alive(&self) -> bool {
+        self.endpoint.is_peer_alive()
+    }
+}
+
+impl SendHalf<SharedServer> {
+    pub fn send_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.send_to_client(payload)
+    }
+
+    pub fn send_to_client_with_flags(&self, payload: &[u8], flags: u16) -> Result<WriteOutcome> {
+        self.endpoint.send_to_client_with_flags(payload, flags)
+    }
+
+    pub fn send_to_client_with_ttl(
+        &self,
+        payload: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<WriteOutcome> {
+        self.endpoint.send_to_client_with_ttl(payload, ttl)
+    }
+
+    pub fn send_blocking(&self, payload: &[u8], timeout: Duration) -> Result<WriteOutcome> {
+        self.endpoint.send_blocking(payload, timeout)
+    }
+
+    pub fn try_send_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.try_send_to_client(payload)
+    }
+
+    pub fn send_batch_to_client(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.endpoint.send_batch_to_client(payloads)
+    }
+
+    pub fn send_batch_atomic_to_client(&self, payloads: &[&[u8]]) -> Result<WriteOutcome> {
+        self.endpoint.send_batch_atomic_to_client(payloads)
+    }
+
+    pub fn send_control_to_client(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.endpoint.send_control_to_client(payload)
+    }
+
+    pub fn send_handle_to_client(&self, handle: isize) -> Result<WriteOutcome> {
+        self.endpoint.send_handle_to_client(handle)
+    }
+
+    pub fn send_credits(&self) -> Option<CreditWindow> {
+        self.endpoint.send_credits()
+    }
+
+    pub fn tx_status(&self) -> RingStatus {
+        self.endpoint.tx_status()
+    }
+
+    pub fn is_peer_alive(&self) -> bool {
+        self.endpoint.is_peer_alive()
+    }
+}
+
+impl RecvHalf<SharedServer> {
+    pub fn receive_from_client(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.endpoint.receive_from_client(buffer)
+    }
+
+    pub fn receive_from_client_timeout(
+        &self,
+        buffer: &mut Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        self.endpoint.receive_from_client_timeout(buffer, timeout)
+    }
+
+    pub fn receive_message_from_client(&self, buffer: &mut Vec<u8>) -> Result<ReceivedMessage> {
+        self.endpoint.receive_message_from_client(buffer)
+    }
+
+    pub fn receive_any_from_client(&self, buffer: &mut Vec<u8>) -> Result<Received> {
+        self.endpoint.receive_any_from_client(buffer)
+    }
+
+    pub fn receive_with<T>(&self, f: impl FnMut(&[u8]) -> T) -> Result<T> {
+        self.endpoint.receive_with(f)
+    }
+
+    pub fn peek_message(&self, buffer: &mut Vec<u8>) -> Result<usize> {
+        self.endpoint.peek_message(buffer)
+    }
+
+    pub fn drain_into(&self, f: &mut impl FnMut(&[u8])) -> Result<usize> {
+        self.endpoint.drain_into(f)
+    }
+
+    pub fn poll_client(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.endpoint.poll_client(timeout)
+    }
+
+    pub fn rx_status(&self) -> RingStatus {
+        self.endpoint.rx_status()
+    }
+
+    pub fn is_peer_alive(&self) -> bool {
+        self.endpoint.is_peer_alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn unique(tag: &str) -> String {
+        format!("SPLIT_{tag}_{}", std::process::id())
+    }
+
+    #[test]
+    fn halves_send_and_receive_from_separate_threads() {
+        let name = unique("PINGPONG");
+        let mut server = SharedServer::start(&name).unwrap();
+        let connector = thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        server
+            .wait_for_client(Some(Duration::from_secs(2)))
+            .unwrap();
+        let client = connector.join().unwrap();
+
+        let (client_tx, client_rx) = client.split();
+        let (server_tx, server_rx) = server.split();
+        // Эхо на стороне сервера: приём и отправка в разных потоках.
+        let (forward, forwarded) = std::sync::mpsc::channel::<Vec<u8>>();
+        let echo_rx = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            for _ in 0..3 {
+                let len = server_rx
+                    .receive_from_client_timeout(&mut buffer, Some(Duration::from_secs(2)))
+                    .unwrap();
+                forward.send(buffer[..len].to_vec()).unwrap();
+            }
+            server_rx
+        });
+        let echo_tx = thread::spawn(move || {
+            for payload in forwarded {
+                server_tx.send_to_client(&payload).unwrap();
+            }
+            server_tx
+        });
+
+        // Клиент ждёт ответов в одном потоке, пока другой отправляет.
+        let receiver = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let mut replies = Vec::new();
+            for _ in 0..3 {
+                let len = client_rx
+                    .receive_from_server_timeout(&mut buffer, Some(Duration::from_secs(2)))
+                    .unwrap();
+                replies.push(buffer[..len].to_vec());
+            }
+            (client_rx, replies)
+        });
+        for payload in [b"one", b"two", b"six"] {
+            client_tx.send_to_server(payload).unwrap();
+        }
+        let (client_rx, replies) = receiver.join().unwrap();
+        assert_eq!(replies, [b"one", b"two", b"six"]);
+
+        let server_rx = echo_rx.join().unwrap();
+        let server_tx = echo_tx.join().unwrap();
+        let server = server_tx.reunite(server_rx).unwrap();
+        assert!(server.is_connected());
+        let client = client_tx.reunite(client_rx).unwrap();
+        assert!(client.is_connected());
+    }
+
+    #[test]
+    fn reunite_rejects_halves_of_different_endpoints() {
+        let first = SharedServer::start(&unique("FIRST")).unwrap();
+        let second = SharedServer::start(&unique("SECOND")).unwrap();
+        let (first_tx, _first_rx) = first.split();
+        let (_second_tx, second_rx) = second.split();
+        assert!(matches!(
+            first_tx.reunite(second_rx),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+}
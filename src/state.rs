@@ -0,0 +1,462 @@
+//! Общий блок состояния канала: именованные атомарные ячейки.
+//!
+//! Блок -- отдельная маленькая секция (`Local\{name}_STATE`), которую
+//! `SharedServer::start` создаёт рядом с секцией колец, а
+//! `SharedClient::connect` открывает. Пиры публикуют в нём лёгкий статус
+//! (fps, флаги режима, прогресс) без сообщений в очереди и без
+//! ad-hoc использования `ControlBlock.reserved`.
+//!
+//! Два вида ячеек, ключ -- строка до `STATE_KEY_MAX` байт:
+//! - `u64`-ячейки (`STATE_CELLS` штук): `set_u64`/`get_u64`/`fetch_add_u64`,
+//!   `fetch_or_u64`/`fetch_and_u64` для флагов;
+//! - байтовые слоты (`STATE_SLOTS` штук по `STATE_SLOT_SIZE` байт):
+//!   `set_bytes`/`get_bytes`, чтение через seqlock без блокировки writer'а.
+//!
+//! Ячейка занимается при первой записи ключа и не освобождается до
+//! пересоздания сервера; когда свободных нет -- `ShmError::StateFull`.
+//! Писать в одну ячейку могут обе стороны.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use xshm::{SharedClient, SharedServer};
+//!
+//! let server = SharedServer::start("VIDEO")?;
+//! server.state().unwrap().set_u64("fps", 60)?;
+//!
+//! let client = SharedClient::connect("VIDEO", Duration::from_secs(1))?;
+//! assert_eq!(client.state().unwrap().get_u64("fps"), Some(60));
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+use crate::error::{Result, ShmError};
+use crate::naming::state_name;
+use crate::platform::{self, Mapping, PlatformMapping};
+use crate::security::Security;
+
+/// 'XSTA'
+const STATE_MAGIC: u32 = 0x5853_5441;
+const STATE_VERSION: u32 = 1;
+
+/// Число `u64`-ячеек.
+pub const STATE_CELLS: usize = 64;
+/// Число байтовых слотов.
+pub const STATE_SLOTS: usize = 16;
+/// Максимальная длина ключа (байты UTF-8).
+pub const STATE_KEY_MAX: usize = 40;
+/// Максимальный размер значения байтового слота.
+pub const STATE_SLOT_SIZE: usize = 192;
+
+const ENTRY_FREE: u32 = 0;
+const ENTRY_CLAIMING: u32 = 1;
+const ENTRY_READY: u32 = 2;
+
+#[repr(C, align(64))]
+struct StateHeader {
+    magic: u32,
+    version: u32,
+    cells: u32,
+    slots: u32,
+    reserved: [u32; 12],
+}
+
+/// Ключ ячейки/слота: занимается один раз (`FREE -> CLAIMING -> READY`),
+/// после READY не меняется.
+#[repr(C)]
+struct EntryKey {
+    state: AtomicU32,
+    len: u32,
+    bytes: [u8; STATE_KEY_MAX],
+}
+
+#[repr(C, align(64))]
+struct Cell {
+    key: EntryKey,
+    value: AtomicU64,
+    reserved: u64,
+}
+
+#[repr(C, align(64))]
+struct Slot {
+    key: EntryKey,
+    /// Seqlock: нечётное -- идёт запись.
+    seq: AtomicU32,
+    len: AtomicU32,
+    reserved: [u32; 2],
+    data: [u8; STATE_SLOT_SIZE],
+}
+
+const _: () = assert!(std::mem::size_of::<Cell>() == 64);
+const _: () = assert!(std::mem::size_of::<Slot>() == 256);
+
+const fn section_size() -> usize {
+    std::mem::size_of::<StateHeader>()
+        + std::mem::size_of::<Cell>() * STATE_CELLS
+        + std::mem::size_of::<Slot>() * STATE_SLOTS
+}
+
+impl EntryKey {
+    fn matches(&self, key: &[u8]) -> bool {
+        self.len as usize == key.len() && &self.bytes[..key.len()] == key
+    }
+}
+
+/// Отображённый блок состояния.
+pub struct SharedState {
+    mapping: Mapping,
+}
+
+impl SharedState {
+    /// Создаёт блок канала `name`.
+    pub fn create(name: &str) -> Result<Self> {
+        Self::create_secured(name, Security::Everyone)
+    }
+
+    /// Как `create`, но с правами доступа `security`.
+    pub(crate) fn create_secured(name: &str, security: Security) -> Result<Self> {
+        Ok(Self::initialize(Self {
+            mapping: Mapping::create_secured(&state_name(name), section_size(), false, security)?,
+        }))
+    }
+
+    /// Как `create`, но блок брошенного канала (см. `reclaim`), который
+    /// ещё жив, открывается и обнуляется.
+    pub(crate) fn adopt(name: &str, security: Security) -> Result<Self> {
+        let created = Mapping::create_secured(&state_name(name), section_size(), false, security);
+        let mapping = match created {
+            Ok(mapping) => mapping,
+            Err(_) => Mapping::open_sized(&state_name(name), section_size())?,
+        };
+        Ok(Self::initialize(Self { mapping }))
+    }
+
+    /// Снимает имя блока канала, брошенного упавшим сервером (см.
+    /// `platform::unlink`).
+    pub(crate) fn unlink(name: &str) {
+        platform::unlink(&state_name(name));
+    }
+
+    /// Удалять ли имя секции при Drop (см. `PlatformMapping::set_owns_name`).
+    pub(crate) fn set_owns_name(&mut self, owns: bool) {
+        self.mapping.set_owns_name(owns);
+    }
+
+    fn initialize(state: Self) -> Self {
+        // SAFETY: секция размером section_size() принадлежит нам; пиров
+        // ещё нет, поэтому обнуление не гонится с чтением.
+        unsafe {
+            std::ptr::write_bytes(state.mapping.as_ptr(), 0, section_size());
+            let header = state.mapping.as_ptr() as *mut StateHeader;
+            (*header).magic = STATE_MAGIC;
+            (*header).version = STATE_VERSION;
+            (*header).cells = STATE_CELLS as u32;
+            (*header).slots = STATE_SLOTS as u32;
+        }
+        fence(Ordering::Release);
+        state
+    }
+
+    /// Открывает блок, созданный сервером канала `name`.
+    pub fn open(name: &str) -> Result<Self> {
+        let state = Self {
+            mapping: Mapping::open_sized(&state_name(name), section_size())?,
+        };
+        // SAFETY: отображено section_size() байт, заголовок в начале.
+        let header = unsafe { &*(state.mapping.as_ptr() as *const StateHeader) };
+        if header.magic != STATE_MAGIC {
+            return Err(ShmError::Corrupted);
+        }
+        if header.version != STATE_VERSION
+            || header.cells != STATE_CELLS as u32
+            || header.slots != STATE_SLOTS as u32
+        {
+            return Err(ShmError::HandshakeFailed);
+        }
+        Ok(state)
+    }
+
+    fn cell(&self, index: usize) -> &Cell {
+        debug_assert!(index < STATE_CELLS);
+        // SAFETY: таблица ячеек сразу за заголовком, index < STATE_CELLS.
+        unsafe {
+            let table = self
+                .mapping
+                .as_ptr()
+                .add(std::mem::size_of::<StateHeader>());
+            &*(table as *const Cell).add(index)
+        }
+    }
+
+    fn slot_ptr(&self, index: usize) -> *mut Slot {
+        debug_assert!(index < STATE_SLOTS);
+        // SAFETY: слоты сразу за ячейками, index < STATE_SLOTS.
+        unsafe {
+            let table = self.mapping.as_ptr().add(
+                std::mem::size_of::<StateHeader>() + std::mem::size_of::<Cell>() * STATE_CELLS,
+            );
+            (table as *mut Slot).add(index)
+        }
+    }
+
+    fn slot_key(&self, index: usize) -> &EntryKey {
+        // SAFETY: ключ меняется только через атомарный state (см. claim).
+        unsafe { &(*self.slot_ptr(index)).key }
+    }
+
+    fn check_key(key: &str) -> Result<&[u8]> {
+        if key.is_empty() || key.len() > STATE_KEY_MAX {
+            return Err(ShmError::InvalidConfig(
+                "state key must be 1..=STATE_KEY_MAX bytes",
+            ));
+        }
+        Ok(key.as_bytes())
+    }
+
+    /// Ищет ключ среди `count` записей; при `create` занимает первую
+    /// свободную. Записи занимаются строго по порядку и не освобождаются,
+    /// поэтому два пира, одновременно создающие один ключ, сойдутся на
+    /// одной записи: проигравший CAS дождётся READY и сравнит ключ.
+    fn find<'a>(
+        count: usize,
+        entry: impl Fn(usize) -> &'a EntryKey,
+        key: &[u8],
+        create: bool,
+    ) -> Result<Option<usize>> {
+        for index in 0..count {
+            let entry = entry(index);
+            let mut state = entry.state.load(Ordering::Acquire);
+            if state == ENTRY_FREE {
+                if !create {
+                    return Ok(None);
+                }
+                match entry.state.compare_exchange(
+                    ENTRY_FREE,
+                    ENTRY_CLAIMING,
+                    Ordering::Acquire,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => {
+                        let raw = entry as *const EntryKey as *mut EntryKey;
+                        // SAFETY: CLAIMING даёт эксклюзивную запись ключа;
+                        // читатели трогают его только после READY (Release).
+                        unsafe {
+                            (*raw).len = key.len() as u32;
+                            let bytes = std::ptr::addr_of_mut!((*raw).bytes) as *mut u8;
+                            std::ptr::copy_nonoverlapping(key.as_ptr(), bytes, key.len());
+                        }
+                        entry.state.store(ENTRY_READY, Ordering::Release);
+                        return Ok(Some(index));
+                    }
+                    Err(current) => state = current,
+                }
+            }
+            while state == ENTRY_CLAIMING {
+                std::hint::spin_loop();
+                state = entry.state.load(Ordering::Acquire);
+            }
+            if entry.matches(key) {
+                return Ok(Some(index));
+            }
+        }
+        if create {
+            Err(ShmError::StateFull)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn find_cell(&self, key: &str, create: bool) -> Result<Option<&AtomicU64>> {
+        let key = Self::check_key(key)?;
+        let index = Self::find(STATE_CELLS, |index| &self.cell(index).key, key, create)?;
+        Ok(index.map(|index| &self.cell(index).value))
+    }
+
+    fn cell_or_create(&self, key: &str) -> Result<&AtomicU64> {
+        Ok(self
+            .find_cell(key, true)?
+            .expect("find with create returns a cell or an error"))
+    }
+
+    pub fn set_u64(&self, key: &str, value: u64) -> Result<()> {
+        self.cell_or_create(key)?.store(value, Ordering::Release);
+        Ok(())
+    }
+
+    /// `None`, если ключ ещё никто не записывал.
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.find_cell(key, false)
+            .ok()
+            .flatten()
+            .map(|cell| cell.load(Ordering::Acquire))
+    }
+
+    /// Атомарно прибавляет `delta`, возвращает прежнее значение.
+    pub fn fetch_add_u64(&self, key: &str, delta: u64) -> Result<u64> {
+        Ok(self.cell_or_create(key)?.fetch_add(delta, Ordering::AcqRel))
+    }
+
+    /// Атомарно выставляет биты `mask`, возвращает прежнее значение.
+    pub fn fetch_or_u64(&self, key: &str, mask: u64) -> Result<u64> {
+        Ok(self.cell_or_create(key)?.fetch_or(mask, Ordering::AcqRel))
+    }
+
+    /// Атомарно оставляет только биты `mask`, возвращает прежнее значение.
+    pub fn fetch_and_u64(&self, key: &str, mask: u64) -> Result<u64> {
+        Ok(self.cell_or_create(key)?.fetch_and(mask, Ordering::AcqRel))
+    }
+
+    /// Записывает значение байтового слота (до `STATE_SLOT_SIZE` байт).
+    /// Одновременные писатели одного слота сериализуются.
+    pub fn set_bytes(&self, key: &str, value: &[u8]) -> Result<()> {
+        if value.len() > STATE_SLOT_SIZE {
+            return Err(ShmError::MessageTooLarge);
+        }
+        let key = Self::check_key(key)?;
+        let index = Self::find(STATE_SLOTS, |index| self.slot_key(index), key, true)?
+            .expect("find with create returns a slot or an error");
+        let slot = self.slot_ptr(index);
+        // SAFETY: slot внутри секции; seq/len атомарны, data пишется только
+        // владельцем нечётного seq.
+        unsafe {
+            let seq = &(*slot).seq;
+            let mut current = seq.load(Ordering::Relaxed);
+            loop {
+                if current & 1 == 0 {
+                    match seq.compare_exchange_weak(
+                        current,
+                        current.wrapping_add(1),
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => break,
+                        Err(actual) => current = actual,
+                    }
+                } else {
+                    std::hint::spin_loop();
+                    current = seq.load(Ordering::Relaxed);
+                }
+            }
+            fence(Ordering::Release);
+            let data = std::ptr::addr_of_mut!((*slot).data) as *mut u8;
+            std::ptr::copy_nonoverlapping(value.as_ptr(), data, value.len());
+            (*slot).len.store(value.len() as u32, Ordering::Relaxed);
+            seq.store(current.wrapping_add(2), Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Читает байтовый слот в `buffer`, возвращает длину. `None`, если
+    /// ключ ещё никто не записывал.
+    pub fn get_bytes(&self, key: &str, buffer: &mut Vec<u8>) -> Option<usize> {
+        let key = Self::check_key(key).ok()?;
+        let index = Self::find(STATE_SLOTS, |index| self.slot_key(index), key, false).ok()??;
+        let slot = self.slot_ptr(index);
+        // SAFETY: slot внутри секции; данные копируются оптимистично и
+        // отбрасываются, если seq изменился (seqlock).
+        unsafe {
+            let seq = &(*slot).seq;
+            loop {
+                let before = seq.load(Ordering::Acquire);
+                if before & 1 != 0 {
+                    std::hint::spin_loop();
+                    continue;
+                }
+                let len = ((*slot).len.load(Ordering::Relaxed) as usize).min(STATE_SLOT_SIZE);
+                buffer.resize(len, 0);
+                let data = std::ptr::addr_of!((*slot).data) as *const u8;
+                std::ptr::copy_nonoverlapping(data, buffer.as_mut_ptr(), len);
+                fence(Ordering::Acquire);
+                if seq.load(Ordering::Relaxed) == before {
+                    return Some(len);
+                }
+            }
+        }
+    }
+
+    /// Ключи всех занятых `u64`-ячеек и байтовых слотов.
+    pub fn keys(&self) -> Vec<String> {
+        let cells = (0..STATE_CELLS).map(|index| &self.cell(index).key);
+        let slots = (0..STATE_SLOTS).map(|index| self.slot_key(index));
+        cells
+            .chain(slots)
+            .filter(|entry| entry.state.load(Ordering::Acquire) == ENTRY_READY)
+            .map(|entry| {
+                let len = (entry.len as usize).min(STATE_KEY_MAX);
+                String::from_utf8_lossy(&entry.bytes[..len]).into_owned()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_STATE_{tag}_{}", std::process::id())
+    }
+
+    #[test]
+    fn counters_are_shared_between_creator_and_peer() {
+        let name = unique("CELLS");
+        let server = SharedState::create(&name).unwrap();
+        let client = SharedState::open(&name).unwrap();
+
+        assert_eq!(client.get_u64("fps"), None);
+        server.set_u64("fps", 60).unwrap();
+        assert_eq!(client.get_u64("fps"), Some(60));
+
+        assert_eq!(client.fetch_add_u64("progress", 5).unwrap(), 0);
+        assert_eq!(server.fetch_add_u64("progress", 5).unwrap(), 5);
+        server.fetch_or_u64("mode", 0b101).unwrap();
+        client.fetch_and_u64("mode", 0b100).unwrap();
+        assert_eq!(server.get_u64("mode"), Some(0b100));
+
+        let mut keys = client.keys();
+        keys.sort();
+        assert_eq!(keys, ["fps", "mode", "progress"]);
+    }
+
+    #[test]
+    fn byte_slots_roundtrip() {
+        let name = unique("SLOTS");
+        let server = SharedState::create(&name).unwrap();
+        let client = SharedState::open(&name).unwrap();
+
+        let mut buffer = Vec::new();
+        assert_eq!(client.get_bytes("status", &mut buffer), None);
+        server.set_bytes("status", b"encoding 1080p").unwrap();
+        let len = client.get_bytes("status", &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"encoding 1080p");
+        client.set_bytes("status", b"idle").unwrap();
+        let len = server.get_bytes("status", &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"idle");
+
+        assert_eq!(
+            server.set_bytes("status", &[0; STATE_SLOT_SIZE + 1]),
+            Err(ShmError::MessageTooLarge)
+        );
+    }
+
+    #[test]
+    fn table_full_and_bad_keys_are_reported() {
+        let state = SharedState::create(&unique("FULL")).unwrap();
+        for index in 0..STATE_CELLS {
+            state.set_u64(&format!("k{index}"), index as u64).unwrap();
+        }
+        assert_eq!(state.set_u64("overflow", 1), Err(ShmError::StateFull));
+        // Существующие ключи по-прежнему пишутся.
+        state.set_u64("k0", 7).unwrap();
+        assert_eq!(state.get_u64("k0"), Some(7));
+
+        assert!(matches!(
+            state.set_u64("", 1),
+            Err(ShmError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            state.set_u64(&"x".repeat(STATE_KEY_MAX + 1), 1),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,377 @@
+//! Межпроцессные примитивы синхронизации: защёлка и барьер.
+//!
+//! Для согласованного старта/остановки нескольких процессов без
+//! ping-сообщений по каналам данных. Каждый объект -- маленькая секция
+//! (`Local\{name}_SYNC`) с одним атомарным словом состояния и событие
+//! (`Local\{name}_SYNC_EVT`) для пробуждения ждущих.
+//!
+//! - [`Latch`] -- одноразовая защёлка: создаётся со счётчиком `count`,
+//!   `count_down` уменьшает его, `wait` ждёт нуля.
+//! - [`Barrier`] -- многоразовый барьер на `parties` участников: `wait`
+//!   возвращается, когда до него дошли все, и барьер сразу готов к
+//!   следующему кругу.
+//!
+//! Событие auto-reset будит одного ждущего, поэтому проснувшийся сразу
+//! взводит его снова для следующего (эстафета). Ожидание дополнительно
+//! перепроверяет состояние каждые `POLL_SLICE`, так что потерянное
+//! пробуждение стоит не больше этого интервала.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use xshm::sync::Latch;
+//!
+//! // Координатор: ждём готовности трёх воркеров.
+//! let ready = Latch::create("WORKERS_READY", 3)?;
+//! assert!(ready.wait(Some(Duration::from_secs(10)))?);
+//!
+//! // Каждый воркер после инициализации:
+//! Latch::open("WORKERS_READY")?.count_down()?;
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, ShmError};
+use crate::naming::{sync_event_name, sync_name};
+use crate::platform::{EventHandle, Mapping, PlatformEvent, PlatformMapping};
+
+/// 'XSYN'
+const SYNC_MAGIC: u32 = 0x5853_594E;
+const SYNC_VERSION: u32 = 1;
+
+const KIND_LATCH: u32 = 1;
+const KIND_BARRIER: u32 = 2;
+
+/// Максимальный интервал между перепроверками состояния при ожидании.
+const POLL_SLICE: Duration = Duration::from_millis(5);
+
+#[repr(C, align(64))]
+struct SyncHeader {
+    magic: u32,
+    version: u32,
+    kind: u32,
+    /// Начальный счётчик защёлки / число участников барьера.
+    parties: u32,
+    /// Защёлка: младшие 32 бита -- остаток счётчика.
+    /// Барьер: старшие 32 бита -- номер круга, младшие -- сколько дошло.
+    state: AtomicU64,
+    reserved: [u32; 10],
+}
+
+const _: () = assert!(std::mem::size_of::<SyncHeader>() == 64);
+
+/// Секция и событие объекта синхронизации.
+struct SyncObject {
+    mapping: Mapping,
+    event: EventHandle,
+    parties: u32,
+}
+
+impl SyncObject {
+    fn create(name: &str, kind: u32, parties: u32, state: u64) -> Result<Self> {
+        let mapping = Mapping::create_sized(&sync_name(name), std::mem::size_of::<SyncHeader>())?;
+        // SAFETY: секция только что создана нами и вмещает заголовок.
+        unsafe {
+            let header = mapping.as_ptr() as *mut SyncHeader;
+            (*header).magic = SYNC_MAGIC;
+            (*header).version = SYNC_VERSION;
+            (*header).kind = kind;
+            (*header).parties = parties;
+        }
+        let object = Self {
+            mapping,
+            event: EventHandle::create(&sync_event_name(name))?,
+            parties,
+        };
+        object.header().state.store(state, Ordering::Release);
+        Ok(object)
+    }
+
+    fn open(name: &str, kind: u32, mismatch: &'static str) -> Result<Self> {
+        let mapping = Mapping::open_sized(&sync_name(name), std::mem::size_of::<SyncHeader>())?;
+        // SAFETY: отображено не меньше заголовка.
+        let header = unsafe { &*(mapping.as_ptr() as *const SyncHeader) };
+        if header.magic != SYNC_MAGIC {
+            return Err(ShmError::Corrupted);
+        }
+        if header.version != SYNC_VERSION {
+            return Err(ShmError::HandshakeFailed);
+        }
+        if header.kind != kind {
+            return Err(ShmError::InvalidConfig(mismatch));
+        }
+        let parties = header.parties;
+        if parties == 0 {
+            return Err(ShmError::Corrupted);
+        }
+        Ok(Self {
+            mapping,
+            event: EventHandle::open(&sync_event_name(name))?,
+            parties,
+        })
+    }
+
+    fn header(&self) -> &SyncHeader {
+        // SAFETY: секция начинается с заголовка и живёт не меньше self.
+        unsafe { &*(self.mapping.as_ptr() as *const SyncHeader) }
+    }
+
+    fn state(&self) -> &AtomicU64 {
+        &self.header().state
+    }
+
+    /// Ждёт `done(state)` не дольше `timeout` (`None` -- бесконечно).
+    fn wait_until(&self, timeout: Option<Duration>, done: impl Fn(u64) -> bool) -> Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut woken = false;
+        loop {
+            if done(self.state().load(Ordering::Acquire)) {
+                if woken {
+                    // Эстафета: будим следующего ждущего.
+                    self.event.set()?;
+                }
+                return Ok(true);
+            }
+            let slice = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Ok(false);
+                    }
+                    (deadline - now).min(POLL_SLICE)
+                }
+                None => POLL_SLICE,
+            };
+            woken |= self.event.wait(Some(slice))?;
+        }
+    }
+}
+
+/// Одноразовая защёлка со счётчиком.
+pub struct Latch {
+    object: SyncObject,
+}
+
+impl Latch {
+    /// Создаёт защёлку `name` со счётчиком `count` (не 0).
+    pub fn create(name: &str, count: u32) -> Result<Self> {
+        if count == 0 {
+            return Err(ShmError::InvalidConfig("latch count must be non-zero"));
+        }
+        Ok(Self {
+            object: SyncObject::create(name, KIND_LATCH, count, count as u64)?,
+        })
+    }
+
+    pub fn open(name: &str) -> Result<Self> {
+        Ok(Self {
+            object: SyncObject::open(name, KIND_LATCH, "sync object is not a latch")?,
+        })
+    }
+
+    /// Начальное значение счётчика.
+    pub fn initial_count(&self) -> u32 {
+        self.object.parties
+    }
+
+    /// Сколько `count_down` осталось до открытия.
+    pub fn count(&self) -> u32 {
+        self.object.state().load(Ordering::Acquire) as u32
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Уменьшает счётчик, возвращает остаток. У открытой защёлки
+    /// ничего не меняет и возвращает 0.
+    pub fn count_down(&self) -> Result<u32> {
+        let state = self.object.state();
+        let mut current = state.load(Ordering::Acquire);
+        loop {
+            if current == 0 {
+                return Ok(0);
+            }
+            match state.compare_exchange_weak(
+                current,
+                current - 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+        let remaining = (current - 1) as u32;
+        if remaining == 0 {
+            self.object.event.set()?;
+        }
+        Ok(remaining)
+    }
+
+    /// Ждёт открытия не дольше `timeout` (`None` -- бесконечно);
+    /// `false` -- таймаут.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        self.object.wait_until(timeout, |state| state == 0)
+    }
+}
+
+/// Многоразовый барьер на фиксированное число участников.
+///
+/// Одновременно в `wait` должно входить не больше `parties` участников:
+/// лишний попал бы в текущий круг и сбил счёт.
+pub struct Barrier {
+    object: SyncObject,
+}
+
+fn round_of(state: u64) -> u32 {
+    (state >> 32) as u32
+}
+
+fn arrived_of(state: u64) -> u32 {
+    state as u32
+}
+
+impl Barrier {
+    /// Создаёт барьер `name` на `parties` участников (не 0).
+    pub fn create(name: &str, parties: u32) -> Result<Self> {
+        if parties == 0 {
+            return Err(ShmError::InvalidConfig("barrier parties must be non-zero"));
+        }
+        Ok(Self {
+            object: SyncObject::create(name, KIND_BARRIER, parties, 0)?,
+        })
+    }
+
+    pub fn open(name: &str) -> Result<Self> {
+        Ok(Self {
+            object: SyncObject::open(name, KIND_BARRIER, "sync object is not a barrier")?,
+        })
+    }
+
+    pub fn parties(&self) -> u32 {
+        self.object.parties
+    }
+
+    /// Сколько участников уже ждут в текущем круге.
+    pub fn arrived(&self) -> u32 {
+        arrived_of(self.object.state().load(Ordering::Acquire))
+    }
+
+    /// Номер текущего круга (растёт на 1 при каждом открытии).
+    pub fn round(&self) -> u32 {
+        round_of(self.object.state().load(Ordering::Acquire))
+    }
+
+    /// Ждёт, пока до барьера дойдут все `parties` участников, не дольше
+    /// `timeout` (`None` -- бесконечно). `false` -- таймаут: участник
+    /// снимается с круга, остальные продолжают ждать.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        let state = self.object.state();
+        let previous = state.fetch_add(1, Ordering::AcqRel);
+        let round = round_of(previous);
+        if arrived_of(previous) + 1 == self.object.parties {
+            // Последний: открываем круг и сбрасываем счёт одним store.
+            state.store((round.wrapping_add(1) as u64) << 32, Ordering::Release);
+            self.object.event.set()?;
+            return Ok(true);
+        }
+        let parties = self.object.parties;
+        let released = |current: u64| round_of(current) != round || arrived_of(current) == parties;
+        if self.object.wait_until(timeout, released)? {
+            return Ok(true);
+        }
+        // Таймаут: снимаемся, если круг ещё не открыт.
+        let mut current = state.load(Ordering::Acquire);
+        loop {
+            if released(current) {
+                return Ok(true);
+            }
+            match state.compare_exchange_weak(
+                current,
+                current - 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(false),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_SYNC_{tag}_{}", std::process::id())
+    }
+
+    #[test]
+    fn latch_opens_after_count_downs() {
+        let name = unique("LATCH");
+        let latch = Latch::create(&name, 3).unwrap();
+        assert!(!latch.wait(Some(Duration::from_millis(1))).unwrap());
+
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let name = name.clone();
+                std::thread::spawn(move || Latch::open(&name).unwrap().count_down().unwrap())
+            })
+            .collect();
+        let mut remaining: Vec<u32> = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        remaining.sort_unstable();
+        assert_eq!(remaining, [0, 1, 2]);
+
+        assert!(latch.wait(Some(Duration::from_secs(1))).unwrap());
+        assert!(latch.is_released());
+        assert_eq!(latch.count_down().unwrap(), 0);
+        assert_eq!(latch.initial_count(), 3);
+
+        assert!(matches!(
+            Barrier::open(&name),
+            Err(ShmError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            Latch::create(&unique("ZERO"), 0),
+            Err(ShmError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn barrier_releases_every_round_and_drops_timed_out_parties() {
+        let name = unique("BARRIER");
+        let barrier = Arc::new(Barrier::create(&name, 3).unwrap());
+
+        // Один участник из трёх -- таймаут, и он снимается с круга.
+        assert!(!barrier.wait(Some(Duration::from_millis(10))).unwrap());
+        assert_eq!(barrier.arrived(), 0);
+
+        let others: Vec<_> = (0..2)
+            .map(|_| {
+                let name = name.clone();
+                std::thread::spawn(move || {
+                    let barrier = Barrier::open(&name).unwrap();
+                    for _ in 0..5 {
+                        assert!(barrier.wait(Some(Duration::from_secs(5))).unwrap());
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..5 {
+            assert!(barrier.wait(Some(Duration::from_secs(5))).unwrap());
+        }
+        for other in others {
+            other.join().unwrap();
+        }
+        assert_eq!(barrier.round(), 5);
+        assert_eq!(barrier.arrived(), 0);
+        assert_eq!(barrier.parties(), 3);
+    }
+}
@@ -0,0 +1,198 @@
+//! Обновление сервера без разрыва соединения (hot upgrade).
+//!
+//! Старый процесс отпускает канал [`SharedServer::detach`]: секция, кольца
+//! с непрочитанными сообщениями, события и блок состояния остаются как
+//! есть, клиенту ничего не сигналится, а в control block записывается
+//! одноразовый токен. [`UpgradeToken`] -- строка, которую старый процесс
+//! передаёт новому (аргумент, переменная окружения, pipe). Новая версия
+//! забирает канал [`SharedServer::adopt`]: открывает секцию и события,
+//! сверяет токен и generation (канал не сбрасывали и не передавали
+//! кому-то ещё) и продолжает с того же места -- клиент не замечает смены
+//! процесса.
+//!
+//! Локальные настройки колец (`set_checksum`, `set_timestamps`,
+//! `set_default_ttl`, гистограмма) в секции не хранятся -- новый процесс
+//! задаёт их заново. Окно кредитов и согласованные возможности берутся из
+//! секции.
+//!
+//! На Unix отпущенные имена не удаляются, пока канал не заберут; если
+//! новый процесс так и не пришёл, их уберёт следующий `SharedServer::start`
+//! после смерти старого процесса (см. `reclaim`). На Windows объекты
+//! живут, пока открыт хоть один handle: новый процесс должен забрать
+//! канал, пока старый (или клиент) ещё держит его.
+//!
+//! ```no_run
+//! use xshm::upgrade::UpgradeToken;
+//! use xshm::SharedServer;
+//! # fn spawn_new_version(_token: &str) {}
+//! // Старая версия:
+//! # let server = SharedServer::start("billing")?;
+//! let token = server.detach()?;
+//! spawn_new_version(&token.to_string());
+//!
+//! // Новая версия:
+//! # let arg = String::new();
+//! let token: UpgradeToken = arg.parse()?;
+//! let server = SharedServer::adopt(&token)?;
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ShmError;
+
+/// Префикс строкового вида токена.
+const TOKEN_PREFIX: &str = "xshm-upgrade-v1";
+
+/// Передаваемый новой версии сервера канал: имя, одноразовый `nonce` из
+/// control block и generation на момент `detach`.
+///
+/// Строковый вид -- `xshm-upgrade-v1:{nonce:08x}:{generation:08x}:{name}`
+/// (имя последним: может содержать `:`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeToken {
+    pub name: String,
+    pub nonce: u32,
+    pub generation: u32,
+}
+
+impl UpgradeToken {
+    pub(crate) fn new(name: &str, generation: u32) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                elapsed.subsec_nanos() ^ elapsed.as_secs() as u32
+            });
+        // 0 в control block -- «канал не передаётся».
+        let nonce = (nanos ^ std::process::id().rotate_left(16)).max(1);
+        Self {
+            name: name.to_owned(),
+            nonce,
+            generation,
+        }
+    }
+}
+
+impl fmt::Display for UpgradeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{TOKEN_PREFIX}:{:08x}:{:08x}:{}",
+            self.nonce, self.generation, self.name
+        )
+    }
+}
+
+impl FromStr for UpgradeToken {
+    type Err = ShmError;
+
+    fn from_str(text: &str) -> Result<Self, ShmError> {
+        let invalid = || ShmError::DecodeFailed(format!("invalid upgrade token: {text:?}"));
+        let mut parts = text.splitn(4, ':');
+        if parts.next() != Some(TOKEN_PREFIX) {
+            return Err(invalid());
+        }
+        let mut hex = || {
+            parts
+                .next()
+                .and_then(|part| u32::from_str_radix(part, 16).ok())
+                .ok_or_else(invalid)
+        };
+        let nonce = hex()?;
+        let generation = hex()?;
+        let name = parts
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(invalid)?;
+        if nonce == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            nonce,
+            generation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedClient, SharedServer};
+    use std::time::Duration;
+
+    fn unique(tag: &str) -> String {
+        format!("XSHM_UPGRADE_{tag}_{}", std::process::id())
+    }
+
+    #[test]
+    fn token_roundtrips_through_string() {
+        let token = UpgradeToken::new("svc:with:colons", 7);
+        assert_ne!(token.nonce, 0);
+        let text = token.to_string();
+        assert!(text.starts_with("xshm-upgrade-v1:"));
+        assert_eq!(text.parse::<UpgradeToken>().unwrap(), token);
+
+        for bad in [
+            "",
+            "xshm-upgrade-v2:00000001:00000001:svc",
+            "xshm-upgrade-v1:zz:00000001:svc",
+            "xshm-upgrade-v1:00000000:00000001:svc",
+            "xshm-upgrade-v1:00000001:00000001:",
+        ] {
+            assert!(
+                matches!(bad.parse::<UpgradeToken>(), Err(ShmError::DecodeFailed(_))),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn adopted_server_keeps_client_and_queued_messages() {
+        let name = unique("ADOPT");
+        let mut old = SharedServer::start_with_control_rings(&name).unwrap();
+        let connector = std::thread::spawn({
+            let name = name.clone();
+            move || SharedClient::connect(&name, Duration::from_secs(2)).unwrap()
+        });
+        old.wait_for_client(Some(Duration::from_secs(2))).unwrap();
+        let client = connector.join().unwrap();
+
+        client.send_to_server(b"before upgrade").unwrap();
+        let token = old.detach().unwrap();
+        // Пока канал ничей, клиент продолжает писать.
+        client.send_to_server(b"during upgrade").unwrap();
+
+        let forged = UpgradeToken {
+            nonce: token.nonce ^ 1,
+            ..token.clone()
+        };
+        assert!(matches!(
+            SharedServer::adopt(&forged),
+            Err(ShmError::HandshakeFailed)
+        ));
+
+        let token: UpgradeToken = token.to_string().parse().unwrap();
+        let new = SharedServer::adopt(&token).unwrap();
+        assert!(new.is_connected());
+        assert!(new.has_control_rings());
+        // Токен одноразовый.
+        assert!(matches!(
+            SharedServer::adopt(&token),
+            Err(ShmError::HandshakeFailed)
+        ));
+
+        let mut buffer = Vec::new();
+        for expected in [&b"before upgrade"[..], b"during upgrade"] {
+            new.receive_from_client(&mut buffer).unwrap();
+            assert_eq!(buffer, expected);
+        }
+        new.send_to_client(b"hello from v2").unwrap();
+        assert!(client.poll_server(Some(Duration::from_secs(1))).unwrap());
+        client.receive_from_server(&mut buffer).unwrap();
+        assert_eq!(buffer, b"hello from v2");
+        assert!(client.is_connected());
+    }
+}
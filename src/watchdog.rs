@@ -0,0 +1,515 @@
+//! Надзор за `AutoServer`-каналами.
+//!
+//! [`Watchdog`] раз в `check_interval` проверяет каждый канал, запущенный
+//! через [`Watchdog::supervise`]:
+//!
+//! - **завис worker** -- цикл worker'а не продвигается дольше
+//!   `stall_timeout` (обычно застрял callback handler'а);
+//! - **застрял handshake** -- клиент выставил HELLO, а сервер не ответил
+//!   дольше `handshake_timeout`;
+//! - **умер пир** -- соединение установлено, а процесс клиента (PID из
+//!   handshake) уже завершился, не выставив disconnect.
+//!
+//! На каждую неисправность [`WatchdogHandler::on_fault`] выбирает
+//! [`Action`]: разорвать соединение, пересоздать канал (новый `AutoServer`
+//! на том же имени) или ничего не делать. Поток, застрявший в callback'е,
+//! прервать нельзя: пересоздание ждёт, пока старый worker вернётся в цикл
+//! и освободит секцию.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use xshm::watchdog::{DefaultPolicy, Watchdog, WatchdogOptions};
+//! use xshm::{AutoHandler, AutoOptions};
+//!
+//! struct Handler;
+//! impl AutoHandler for Handler {}
+//!
+//! let watchdog = Watchdog::start(Arc::new(DefaultPolicy), WatchdogOptions::default())?;
+//! let server = watchdog.supervise("MyService", Arc::new(Handler), AutoOptions::default())?;
+//! server.send(b"hello")?;
+//! # Ok::<(), xshm::ShmError>(())
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::auto::{AutoHandler, AutoOptions, AutoServer, AutoStatsSnapshot};
+use crate::constants::{HANDSHAKE_CLIENT_HELLO, HANDSHAKE_SERVER_READY, RESERVED_CLIENT_PID_INDEX};
+use crate::diagnostics;
+use crate::error::{Result, ShmError};
+use crate::platform;
+
+/// Пороги и период проверок.
+#[derive(Debug, Clone)]
+pub struct WatchdogOptions {
+    pub check_interval: Duration,
+    /// Сколько цикл worker'а может не продвигаться.
+    pub stall_timeout: Duration,
+    /// Сколько HELLO клиента может оставаться без ответа.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_millis(500),
+            stall_timeout: Duration::from_secs(5),
+            handshake_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Обнаруженная неисправность.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    WorkerStalled,
+    HandshakeStuck,
+    PeerDead { pid: u32 },
+}
+
+/// Реакция на неисправность.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Ignore,
+    /// Разорвать текущее соединение; канал и worker остаются.
+    Disconnect,
+    /// Остановить worker и пересоздать канал на том же имени.
+    Restart,
+}
+
+impl Fault {
+    /// Политика по умолчанию: мёртвый пир -- разрыв соединения, остальное
+    /// -- пересоздание канала.
+    pub fn default_action(self) -> Action {
+        match self {
+            Fault::PeerDead { .. } => Action::Disconnect,
+            Fault::WorkerStalled | Fault::HandshakeStuck => Action::Restart,
+        }
+    }
+}
+
+/// Callback-интерфейс watchdog'а. Вызывается из потока watchdog'а.
+pub trait WatchdogHandler: Send + Sync + 'static {
+    fn on_fault(&self, _name: &str, fault: Fault) -> Action {
+        fault.default_action()
+    }
+
+    /// Результат пересоздания канала. Неудачная попытка повторяется на
+    /// следующей проверке.
+    fn on_restart(&self, _name: &str, _result: &Result<()>) {}
+}
+
+/// `WatchdogHandler` с политикой по умолчанию и без уведомлений.
+pub struct DefaultPolicy;
+
+impl WatchdogHandler for DefaultPolicy {}
+
+struct Supervised {
+    name: String,
+    handler: Arc<dyn AutoHandler>,
+    options: AutoOptions,
+    server: Mutex<Option<AutoServer>>,
+    /// Остановленный сервер, чей worker ещё не вернулся в цикл.
+    retiring: Mutex<Option<AutoServer>>,
+    restarts: AtomicU64,
+}
+
+/// Канал под надзором watchdog'а. Drop останавливает канал и снимает его
+/// с надзора.
+pub struct SupervisedServer {
+    inner: Arc<Supervised>,
+}
+
+impl SupervisedServer {
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Отправка через текущий экземпляр сервера. Пока канал пересоздаётся
+    /// -- `ShmError::NotReady`.
+    pub fn send(&self, data: &[u8]) -> Result<()> {
+        match self.inner.server.lock().unwrap().as_ref() {
+            Some(server) => server.send(data),
+            None => Err(ShmError::NotReady),
+        }
+    }
+
+    /// Счётчики текущего экземпляра (после пересоздания начинаются с нуля).
+    pub fn stats(&self) -> Option<AutoStatsSnapshot> {
+        self.inner
+            .server
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(AutoServer::stats)
+    }
+
+    /// Сколько раз канал был пересоздан.
+    pub fn restarts(&self) -> u64 {
+        self.inner.restarts.load(Ordering::Relaxed)
+    }
+}
+
+/// Состояние проверок одного канала (живёт в потоке watchdog'а).
+struct Probe {
+    channel: Weak<Supervised>,
+    heartbeat: u64,
+    heartbeat_at: Instant,
+    hello_since: Option<Instant>,
+    /// Generation соединения, о мёртвом пире которого уже сообщили.
+    dead_peer_generation: Option<u32>,
+}
+
+impl Probe {
+    fn new(channel: Weak<Supervised>) -> Self {
+        Self {
+            channel,
+            heartbeat: 0,
+            heartbeat_at: Instant::now(),
+            hello_since: None,
+            dead_peer_generation: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Probe::new(self.channel.clone());
+    }
+}
+
+struct Shared {
+    running: AtomicBool,
+    incoming: Mutex<Vec<Weak<Supervised>>>,
+    handler: Arc<dyn WatchdogHandler>,
+    options: WatchdogOptions,
+}
+
+/// Поток надзора. Drop останавливает надзор (сами каналы продолжают
+/// работать, пока живы их `SupervisedServer`).
+pub struct Watchdog {
+    shared: Arc<Shared>,
+    join: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub fn start(handler: Arc<dyn WatchdogHandler>, options: WatchdogOptions) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            incoming: Mutex::new(Vec::new()),
+            handler,
+            options,
+        });
+        let worker_shared = shared.clone();
+        #[cfg_attr(not(debug_assertions), allow(unused_mut))]
+        let mut builder = thread::Builder::new();
+        #[cfg(debug_assertions)]
+        {
+            builder = builder.name("xsw".to_owned());
+        }
+        let join = builder
+            .spawn(move || watch_loop(&worker_shared))
+            .map_err(|err| ShmError::WindowsError {
+                code: err.raw_os_error().unwrap_or(-1) as u32,
+                context: "spawn watchdog",
+            })?;
+        Ok(Self {
+            shared,
+            join: Some(join),
+        })
+    }
+
+    /// Запускает `AutoServer` на `name` и ставит его под надзор.
+    pub fn supervise(
+        &self,
+        name: &str,
+        handler: Arc<dyn AutoHandler>,
+        options: AutoOptions,
+    ) -> Result<SupervisedServer> {
+        let server = AutoServer::start(name, handler.clone(), options.clone())?;
+        let inner = Arc::new(Supervised {
+            name: name.to_owned(),
+            handler,
+            options,
+            server: Mutex::new(Some(server)),
+            retiring: Mutex::new(None),
+            restarts: AtomicU64::new(0),
+        });
+        self.shared
+            .incoming
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&inner));
+        Ok(SupervisedServer { inner })
+    }
+
+    pub fn stop(&mut self) {
+        self.shared.running.store(false, Ordering::Release);
+        if let Some(join) = self.join.take() {
+            let _ = join.join();
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn watch_loop(shared: &Shared) {
+    let mut probes: Vec<Probe> = Vec::new();
+    while shared.running.load(Ordering::Acquire) {
+        probes.extend(shared.incoming.lock().unwrap().drain(..).map(Probe::new));
+        probes.retain_mut(|probe| match probe.channel.upgrade() {
+            Some(channel) => {
+                check(shared, &channel, probe);
+                true
+            }
+            None => false,
+        });
+        thread::sleep(shared.options.check_interval);
+    }
+}
+
+fn check(shared: &Shared, channel: &Supervised, probe: &mut Probe) {
+    let fault = {
+        let server = channel.server.lock().unwrap();
+        match server.as_ref() {
+            Some(server) => detect(&shared.options, &channel.name, server, probe),
+            None => None,
+        }
+    };
+
+    if let Some(fault) = fault {
+        match shared.handler.on_fault(&channel.name, fault) {
+            Action::Ignore => {}
+            Action::Disconnect => {
+                if let Some(server) = channel.server.lock().unwrap().as_ref() {
+                    server.disconnect_peer();
+                }
+            }
+            Action::Restart => {
+                if let Some(server) = channel.server.lock().unwrap().take() {
+                    server.retire();
+                    *channel.retiring.lock().unwrap() = Some(server);
+                }
+            }
+        }
+    }
+
+    if channel.server.lock().unwrap().is_none() {
+        restart(shared, channel, probe);
+    }
+}
+
+fn detect(
+    options: &WatchdogOptions,
+    name: &str,
+    server: &AutoServer,
+    probe: &mut Probe,
+) -> Option<Fault> {
+    let now = Instant::now();
+    let heartbeat = server.heartbeat();
+    if heartbeat != probe.heartbeat {
+        probe.heartbeat = heartbeat;
+        probe.heartbeat_at = now;
+    } else if now.duration_since(probe.heartbeat_at) >= options.stall_timeout {
+        probe.heartbeat_at = now;
+        return Some(Fault::WorkerStalled);
+    }
+
+    let dump = diagnostics::dump_by_name(name).ok()?;
+    if dump.client_state == HANDSHAKE_CLIENT_HELLO && dump.server_state != HANDSHAKE_SERVER_READY {
+        let since = *probe.hello_since.get_or_insert(now);
+        if now.duration_since(since) >= options.handshake_timeout {
+            probe.hello_since = None;
+            return Some(Fault::HandshakeStuck);
+        }
+    } else {
+        probe.hello_since = None;
+    }
+
+    let pid = dump.reserved[RESERVED_CLIENT_PID_INDEX];
+    if dump.server_state == HANDSHAKE_SERVER_READY
+        && pid != 0
+        && probe.dead_peer_generation != Some(dump.generation)
+        && !platform::is_process_alive(pid)
+    {
+        probe.dead_peer_generation = Some(dump.generation);
+        return Some(Fault::PeerDead { pid });
+    }
+    None
+}
+
+fn restart(shared: &Shared, channel: &Supervised, probe: &mut Probe) {
+    {
+        let mut retiring = channel.retiring.lock().unwrap();
+        if retiring
+            .as_ref()
+            .is_some_and(|server| !server.is_worker_finished())
+        {
+            return;
+        }
+        retiring.take();
+    }
+
+    let result = AutoServer::start(
+        &channel.name,
+        channel.handler.clone(),
+        channel.options.clone(),
+    );
+    let report = result.as_ref().map(|_| ()).map_err(Clone::clone);
+    if let Ok(server) = result {
+        *channel.server.lock().unwrap() = Some(server);
+        channel.restarts.fetch_add(1, Ordering::Relaxed);
+        probe.reset();
+    }
+    shared.handler.on_restart(&channel.name, &report);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::SharedClient;
+    use crate::naming::mapping_name;
+    use crate::platform::{Mapping, PlatformMapping};
+    use crate::shared::SharedView;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::mpsc::{self, Sender};
+
+    fn fast_options() -> WatchdogOptions {
+        WatchdogOptions {
+            check_interval: Duration::from_millis(20),
+            stall_timeout: Duration::from_millis(300),
+            handshake_timeout: Duration::from_millis(300),
+        }
+    }
+
+    struct Reporter {
+        faults: Mutex<Sender<Fault>>,
+        restarts: Mutex<Sender<bool>>,
+    }
+
+    impl WatchdogHandler for Reporter {
+        fn on_fault(&self, _name: &str, fault: Fault) -> Action {
+            let _ = self.faults.lock().unwrap().send(fault);
+            fault.default_action()
+        }
+
+        fn on_restart(&self, _name: &str, result: &Result<()>) {
+            let _ = self.restarts.lock().unwrap().send(result.is_ok());
+        }
+    }
+
+    fn reporter() -> (Arc<Reporter>, mpsc::Receiver<Fault>, mpsc::Receiver<bool>) {
+        let (fault_tx, fault_rx) = mpsc::channel();
+        let (restart_tx, restart_rx) = mpsc::channel();
+        let reporter = Arc::new(Reporter {
+            faults: Mutex::new(fault_tx),
+            restarts: Mutex::new(restart_tx),
+        });
+        (reporter, fault_rx, restart_rx)
+    }
+
+    /// Блокирует worker в `on_message`, пока не открыт `gate`.
+    #[derive(Default)]
+    struct Blocking {
+        gate: AtomicBool,
+        disconnects: AtomicU32,
+    }
+
+    impl AutoHandler for Blocking {
+        fn on_message(&self, _direction: crate::ChannelKind, _payload: &[u8]) {
+            while !self.gate.load(Ordering::Acquire) {
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+
+        fn on_disconnect(&self) {
+            self.disconnects.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn stalled_worker_is_restarted_once_it_returns() {
+        let name = format!("XSHM_WD_STALL_{}", std::process::id());
+        let (reporter, faults, restarts) = reporter();
+        // Запас на загруженную машину: ложный stall до подключения клиента
+        // пересоздал бы канал раньше времени.
+        let options = WatchdogOptions {
+            stall_timeout: Duration::from_secs(1),
+            ..fast_options()
+        };
+        let watchdog = Watchdog::start(reporter, options).unwrap();
+        let handler = Arc::new(Blocking::default());
+        let server = watchdog
+            .supervise(&name, handler.clone(), AutoOptions::default())
+            .unwrap();
+
+        let client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        client.send_to_server(b"stall").unwrap();
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(faults.recv_timeout(timeout), Ok(Fault::WorkerStalled));
+        // `on_fault` сообщает до того, как watchdog заберёт сервер.
+        let deadline = Instant::now() + timeout;
+        while server.send(b"x").is_ok() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Worker всё ещё в callback'е -- пересоздание ждёт его.
+        assert_eq!(server.send(b"x"), Err(ShmError::NotReady));
+        assert_eq!(server.restarts(), 0);
+
+        drop(client);
+        handler.gate.store(true, Ordering::Release);
+        assert_eq!(restarts.recv_timeout(timeout), Ok(true));
+        assert_eq!(server.restarts(), 1);
+        assert!(server.send(b"after restart").is_ok());
+    }
+
+    #[test]
+    fn dead_peer_is_disconnected() {
+        let name = format!("XSHM_WD_PEER_{}", std::process::id());
+        let (reporter, faults, _restarts) = reporter();
+        let watchdog = Watchdog::start(reporter, fast_options()).unwrap();
+        let handler = Arc::new(Blocking {
+            gate: AtomicBool::new(true),
+            ..Default::default()
+        });
+        // Worker сам ловит мёртвого пира; здесь проверяется watchdog.
+        let options = AutoOptions::builder()
+            .check_peer_process(false)
+            .build()
+            .unwrap();
+        let server = watchdog.supervise(&name, handler.clone(), options).unwrap();
+
+        let client = SharedClient::connect(&name, Duration::from_secs(2)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while server.stats().unwrap().connects == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Имитируем падение клиента: disconnect не выставлен, а PID в
+        // control block принадлежит уже завершённому процессу.
+        let mut child = platform::spawn_exiting_child();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        let mapping = Mapping::open(&mapping_name(&name)).unwrap();
+        // SAFETY: отображение живо до конца теста.
+        let view = unsafe { SharedView::new(mapping.as_ptr()) };
+        view.control_block().reserved[RESERVED_CLIENT_PID_INDEX].store(dead_pid, Ordering::Release);
+        std::mem::forget(client);
+
+        assert_eq!(
+            faults.recv_timeout(Duration::from_secs(5)),
+            Ok(Fault::PeerDead { pid: dead_pid })
+        );
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while handler.disconnects.load(Ordering::Relaxed) == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handler.disconnects.load(Ordering::Relaxed), 1);
+        assert_eq!(server.restarts(), 0);
+    }
+}
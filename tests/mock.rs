@@ -0,0 +1,241 @@
+//! Тесты in-memory backend'а (feature `mock`): каналы в `Namespace::Mock`,
+//! обе стороны в одном процессе, без kernel-объектов. Нативные каналы той
+//! же сборки работают рядом с ними.
+#![cfg(feature = "mock")]
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use xshm::{
+    AutoClient, AutoHandler, AutoOptions, AutoServer, ChannelKind, ClientRegistration,
+    DispatchClient, DispatchClientHandler, DispatchClientOptions, DispatchHandler,
+    DispatchOptions, DispatchServer, MultiClient, MultiClientHandler, MultiClientOptions,
+    MultiHandler, MultiOptions, MultiServer, Namespace, SharedClient, SharedServer,
+};
+
+/// Собирает входящие сообщения и будит ожидающий тест.
+#[derive(Default)]
+struct Collector {
+    messages: Mutex<Vec<Vec<u8>>>,
+    signal: Condvar,
+}
+
+impl Collector {
+    fn push(&self, payload: &[u8]) {
+        self.messages.lock().unwrap().push(payload.to_vec());
+        self.signal.notify_all();
+    }
+
+    fn wait_for(&self, count: usize) -> Vec<Vec<u8>> {
+        let guard = self.messages.lock().unwrap();
+        let (guard, _) = self
+            .signal
+            .wait_timeout_while(guard, Duration::from_secs(5), |m| m.len() < count)
+            .unwrap();
+        guard.clone()
+    }
+}
+
+impl AutoHandler for Collector {
+    fn on_message(&self, _direction: ChannelKind, payload: &[u8]) {
+        self.push(payload);
+    }
+}
+
+impl MultiHandler for Collector {
+    fn on_client_connect(&self, _client_id: u32) {}
+    fn on_client_disconnect(&self, _client_id: u32) {}
+    fn on_message(&self, _client_id: u32, data: &[u8]) {
+        self.push(data);
+    }
+}
+
+impl MultiClientHandler for Collector {
+    fn on_connect(&self, _slot_id: u32) {}
+    fn on_disconnect(&self) {}
+    fn on_message(&self, data: &[u8]) {
+        self.push(data);
+    }
+}
+
+impl DispatchHandler for Collector {
+    fn on_client_connect(&self, _client_id: u32, _info: &ClientRegistration) {}
+    fn on_client_disconnect(&self, _client_id: u32) {}
+    fn on_message(&self, _client_id: u32, data: &[u8]) {
+        self.push(data);
+    }
+}
+
+impl DispatchClientHandler for Collector {
+    fn on_connect(&self, _client_id: u32, _channel_name: &str) {}
+    fn on_disconnect(&self) {}
+    fn on_message(&self, data: &[u8]) {
+        self.push(data);
+    }
+}
+
+fn mock_options() -> AutoOptions {
+    AutoOptions::builder()
+        .namespace(Namespace::Mock)
+        .build()
+        .unwrap()
+}
+
+/// Подключает клиента к серверу `name` в namespace'е `namespace`.
+fn connect_pair(name: &str, namespace: Namespace) -> (SharedServer, SharedClient) {
+    let mut server = SharedServer::builder(name)
+        .namespace(namespace)
+        .start()
+        .unwrap();
+    let connector = std::thread::spawn({
+        let name = name.to_owned();
+        move || SharedClient::connect_in(&name, namespace, Duration::from_secs(2)).unwrap()
+    });
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .unwrap();
+    (server, connector.join().unwrap())
+}
+
+#[test]
+fn auto_server_and_client_exchange_messages_in_memory() {
+    let name = format!("XSHM_MOCK_AUTO_{}", std::process::id());
+    let server_handler = Arc::new(Collector::default());
+    let client_handler = Arc::new(Collector::default());
+
+    let server = AutoServer::start(&name, server_handler.clone(), mock_options()).unwrap();
+    let client = AutoClient::connect(&name, client_handler.clone(), mock_options()).unwrap();
+
+    client.send(b"ping").unwrap();
+    assert_eq!(server_handler.wait_for(1), vec![b"ping".to_vec()]);
+
+    server.send(b"pong").unwrap();
+    assert_eq!(client_handler.wait_for(1), vec![b"pong".to_vec()]);
+
+    client.stop();
+    server.stop();
+}
+
+#[test]
+fn connect_to_unknown_channel_fails() {
+    let name = format!("XSHM_MOCK_MISSING_{}", std::process::id());
+    assert!(SharedClient::connect_in(&name, Namespace::Mock, Duration::from_millis(50)).is_err());
+}
+
+/// Mock-канал не виден нативному клиенту и наоборот; оба работают в одной
+/// сборке под одним базовым именем.
+#[test]
+fn mock_and_native_channels_coexist() {
+    let name = format!("XSHM_MOCK_COEXIST_{}", std::process::id());
+    let (mock_server, mock_client) = connect_pair(&name, Namespace::Mock);
+    assert!(SharedClient::connect(&name, Duration::from_millis(50)).is_err());
+
+    let (native_server, native_client) = connect_pair(&name, Namespace::Session);
+
+    let mut buffer = Vec::new();
+    mock_client.send_to_server(b"mock").unwrap();
+    native_client.send_to_server(b"native").unwrap();
+    let len = mock_server.receive_from_client(&mut buffer).unwrap();
+    assert_eq!(&buffer[..len], b"mock");
+    let len = native_server.receive_from_client(&mut buffer).unwrap();
+    assert_eq!(&buffer[..len], b"native");
+}
+
+#[test]
+fn handles_are_delivered_as_control_frames() {
+    use xshm::Received;
+
+    let name = format!("XSHM_MOCK_HANDLES_{}", std::process::id());
+    let (server, client) = connect_pair(&name, Namespace::Mock);
+
+    // В mock обе стороны в одном процессе: handle приходит как есть.
+    client.send_handle_to_server(0x1234).unwrap();
+    client.send_to_server(b"after").unwrap();
+    let mut buffer = Vec::new();
+    assert_eq!(
+        server.receive_any_from_client(&mut buffer).unwrap(),
+        Received::Handle(0x1234)
+    );
+    assert_eq!(
+        server.receive_any_from_client(&mut buffer).unwrap(),
+        Received::Message(5)
+    );
+
+    // Обычный приём пропускает handle-кадры.
+    server.send_handle_to_client(0x42).unwrap();
+    server.send_to_client(b"data").unwrap();
+    let len = client.receive_from_server(&mut buffer).unwrap();
+    assert_eq!(&buffer[..len], b"data");
+}
+
+#[test]
+fn multi_server_works_in_memory() {
+    let name = format!("XSHM_MOCK_MULTI_{}", std::process::id());
+    let server_handler = Arc::new(Collector::default());
+    let client_handler = Arc::new(Collector::default());
+
+    let server = MultiServer::start(
+        &name,
+        server_handler.clone(),
+        MultiOptions {
+            max_clients: 2,
+            namespace: Namespace::Mock,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let client = MultiClient::connect(
+        &name,
+        client_handler.clone(),
+        MultiClientOptions {
+            namespace: Namespace::Mock,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    client.send(b"hello").unwrap();
+    assert_eq!(server_handler.wait_for(1), vec![b"hello".to_vec()]);
+
+    client.stop();
+    server.stop();
+}
+
+#[test]
+fn dispatch_server_works_in_memory() {
+    let name = format!("XSHM_MOCK_DISPATCH_{}", std::process::id());
+    let server_handler = Arc::new(Collector::default());
+    let client_handler = Arc::new(Collector::default());
+
+    let server = DispatchServer::start(
+        &name,
+        server_handler.clone(),
+        DispatchOptions {
+            namespace: Namespace::Mock,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    // Лобби поднимает worker сервера.
+    std::thread::sleep(Duration::from_millis(100));
+    let client = DispatchClient::connect(
+        &name,
+        ClientRegistration {
+            pid: std::process::id(),
+            revision: 1,
+            name: "mock".to_owned(),
+        },
+        client_handler.clone(),
+        DispatchClientOptions {
+            namespace: Namespace::Mock,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    client.send(b"hello").unwrap();
+    assert_eq!(server_handler.wait_for(1), vec![b"hello".to_vec()]);
+
+    client.stop();
+    server.stop();
+}
@@ -8,6 +8,8 @@ use std::time::{Duration, Instant};
 use xshm::multi::{
     MultiClient, MultiClientHandler, MultiClientOptions, MultiHandler, MultiOptions, MultiServer,
 };
+use xshm::health::LinkState;
+use xshm::metrics::{self, PrometheusFormatter};
 use xshm::ShmError;
 
 fn unique_name(tag: &str) -> String {
@@ -215,6 +217,100 @@ fn test_multi_single_client_auto_slot() {
     );
 
     println!("[TEST] Single client auto-slot: PASSED");
+    // Worker держит Arc сервера: без stop() объекты слотов живут до
+    // выхода процесса, а на Unix их имена -- и после него.
+    server.stop();
+}
+
+#[test]
+fn test_multi_slot_health() {
+    let base_name = unique_name("HEALTH");
+    let server_handler = Arc::new(TestServerHandler::new());
+    let server = MultiServer::start(
+        &base_name,
+        server_handler.clone(),
+        MultiOptions {
+            max_clients: 2,
+            ..Default::default()
+        },
+    )
+    .expect("MultiServer start");
+    assert!(server.health(2).is_none());
+    let idle = server.health(0).expect("slot 0");
+    assert_eq!(idle.state, LinkState::Waiting);
+    assert_eq!((idle.last_rx_age, idle.last_tx_age), (None, None));
+
+    let client_handler = Arc::new(TestClientHandler::new());
+    let client = MultiClient::connect(
+        &base_name,
+        client_handler.clone(),
+        MultiClientOptions::default(),
+    )
+    .expect("MultiClient connect");
+    assert!(client_handler.wait_for_connect(Duration::from_secs(5)));
+    assert!(server_handler.wait_for_connects(1, Duration::from_secs(5)));
+    let slot_id = client_handler.slot_id.load(Ordering::Acquire);
+
+    client.send(b"ping").expect("Client send");
+    assert!(server_handler.wait_for_messages(1, Duration::from_secs(2)));
+    server.send_to(slot_id, b"pong").expect("Server send");
+
+    let health = server.health(slot_id).expect("client slot");
+    assert_eq!(health.state, LinkState::Connected);
+    assert!(health.last_rx_age.is_some() && health.last_tx_age.is_some());
+    assert_eq!(health.reconnects, 0);
+    assert_eq!(health.last_error, None);
+
+    server.stop();
+    assert_eq!(server.health(slot_id).unwrap().state, LinkState::Stopped);
+}
+
+fn prometheus_text() -> String {
+    let mut prom = PrometheusFormatter::new();
+    metrics::export(&mut prom);
+    prom.into_string()
+}
+
+#[test]
+fn test_multi_channel_in_prometheus_export() {
+    let base_name = unique_name("METRICS");
+    let server_handler = Arc::new(TestServerHandler::new());
+    let server = MultiServer::start(
+        &base_name,
+        server_handler.clone(),
+        MultiOptions {
+            max_clients: 2,
+            ..Default::default()
+        },
+    )
+    .expect("MultiServer start");
+    let client_handler = Arc::new(TestClientHandler::new());
+    let client = MultiClient::connect(
+        &base_name,
+        client_handler.clone(),
+        MultiClientOptions::default(),
+    )
+    .expect("MultiClient connect");
+    assert!(server_handler.wait_for_connects(1, Duration::from_secs(5)));
+
+    client.send(b"ping").expect("Client send");
+    assert!(server_handler.wait_for_messages(1, Duration::from_secs(2)));
+
+    let server_labels = format!("{{channel=\"{base_name}\",role=\"server\"}}");
+    let client_labels = format!("{{channel=\"{base_name}\",role=\"client\"}}");
+    let text = prometheus_text();
+    assert!(text.contains(&format!("xshm_received_messages_total{server_labels} 1\n")));
+    assert!(text.contains(&format!("xshm_received_bytes_total{server_labels} 4\n")));
+    assert!(text.contains(&format!("xshm_connects_total{server_labels} 1\n")));
+    assert!(text.contains(&format!("xshm_sent_messages_total{client_labels} 1\n")));
+
+    // Drop снимает каналы с учёта.
+    drop(client);
+    server.stop();
+    drop(server);
+    let text = prometheus_text();
+    assert!(!text.contains(&server_labels));
+    assert!(!text.contains(&client_labels));
 }
 
 #[test]
@@ -244,7 +340,7 @@ fn test_multi_multiple_clients_auto_slot() {
         let ch = Arc::new(TestClientHandler::new());
         println!("[TEST] Connecting client {}...", i);
         let client = MultiClient::connect(&base_name, ch.clone(), MultiClientOptions::default())
-            .expect(&format!("Client {} connect", i));
+            .unwrap_or_else(|_| panic!("Client {} connect", i));
 
         // Ждём подключения каждого клиента
         assert!(
@@ -291,7 +387,7 @@ fn test_multi_multiple_clients_auto_slot() {
         let msg = format!("Hello from client {}", i);
         client
             .send(msg.as_bytes())
-            .expect(&format!("Client {} send", i));
+            .unwrap_or_else(|_| panic!("Client {} send", i));
     }
 
     // Сервер должен получить все
@@ -301,6 +397,7 @@ fn test_multi_multiple_clients_auto_slot() {
     );
 
     println!("[TEST] Multiple clients auto-slot: PASSED");
+    server.stop();
 }
 
 #[test]
@@ -309,7 +406,7 @@ fn test_multi_client_reconnect() {
     println!("[TEST] Base name: {}", base_name);
 
     let server_handler = Arc::new(TestServerHandler::new());
-    let _server = MultiServer::start(&base_name, server_handler.clone(), MultiOptions::default())
+    let server = MultiServer::start(&base_name, server_handler.clone(), MultiOptions::default())
         .expect("MultiServer start");
 
     thread::sleep(Duration::from_millis(100));
@@ -347,6 +444,7 @@ fn test_multi_client_reconnect() {
     }
 
     println!("[TEST] Client reconnect: PASSED");
+    server.stop();
 }
 
 /// Главный тест конкурентного захвата слотов: N клиентов подключаются
@@ -410,6 +508,7 @@ fn test_multi_concurrent_connect_storm() {
 
     println!("[TEST] Concurrent connect storm: PASSED ({N} unique slots)");
     drop(results); // отключаем всех
+    server.stop();
 }
 
 /// Oversubscription: клиентов больше, чем слотов. Сервер обязан заполнить
@@ -467,4 +566,5 @@ fn test_multi_oversubscription() {
 
     println!("[TEST] Oversubscription: PASSED (filled {N}, no double-assignment)");
     drop(clients);
+    server.stop();
 }
@@ -4,13 +4,17 @@
 //! 1. Порядок операций write_pos/message_count
 //! 2. Валидацию magic/version при подключении
 //! 3. Корректность handshake с generation
+//! 4. Целостность чтения без копии под вытеснением (барьеры на aarch64)
 
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use xshm::{SharedClient, SharedServer, ShmError};
+use xshm::{
+    CreditWindow, SharedClient, SharedServer, ShmError, FEATURE_CHECKSUM, FEATURE_CREDITS,
+    FEATURE_HANDLES, FEATURE_TIMESTAMPS, RING_CAPACITY,
+};
 
 fn unique_name(tag: &str) -> String {
     use std::time::SystemTime;
@@ -323,7 +327,7 @@ fn test_bidirectional_ordering_stress() {
     );
 }
 
-/// Тест: проверка что compile-time assert работает (этот тест всегда проходит на x86/x64)
+/// Тест: проверка что compile-time assert работает (этот тест всегда проходит на x86/x64/aarch64)
 #[test]
 fn test_architecture_supported() {
     #[cfg(target_arch = "x86")]
@@ -332,6 +336,332 @@ fn test_architecture_supported() {
     #[cfg(target_arch = "x86_64")]
     println!("Running on x86_64 (64-bit)");
 
+    #[cfg(target_arch = "aarch64")]
+    println!("Running on aarch64");
+
     // Если мы здесь - значит архитектура поддерживается
-    assert!(cfg!(any(target_arch = "x86", target_arch = "x86_64")));
+    const {
+        assert!(cfg!(any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "aarch64"
+        )))
+    };
+}
+
+/// Тест: чтение без копии под непрерывным вытеснением не отдаёт порванных
+/// и повторных кадров. На слабо упорядоченной архитектуре (aarch64) это
+/// проверяет барьер между чтением кадра и фиксацией `read_pos`.
+#[test]
+fn test_borrowed_reads_are_never_torn_under_overwrite() {
+    const MESSAGES: u32 = 20_000;
+    let name = unique_name("TORN");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            for id in 1..=MESSAGES {
+                // Сообщение -- `id`, повторённый 4..=1000 раз: порванная
+                // копия видна как смесь разных слов.
+                let words = 4 + (id as usize * 7) % 997;
+                let payload: Vec<u8> = std::iter::repeat_n(id.to_le_bytes(), words)
+                    .flatten()
+                    .collect();
+                client.send_to_server(&payload)?;
+            }
+            Ok(())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    let mut last = 0u32;
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while last < MESSAGES {
+        assert!(Instant::now() < deadline, "stuck after message {last}");
+        let received = server.receive_with(|payload| {
+            let (first, rest) = payload.split_at(4);
+            let words_equal = rest.chunks(4).all(|word| word == first);
+            (u32::from_le_bytes(first.try_into().unwrap()), words_equal)
+        });
+        match received {
+            Ok((id, words_equal)) => {
+                assert!(words_equal, "torn message {id}");
+                assert!(id > last, "message {id} after {last}");
+                last = id;
+            }
+            Err(ShmError::QueueEmpty) => std::hint::spin_loop(),
+            Err(err) => panic!("receive: {err:?}"),
+        }
+    }
+    client_thread.join().unwrap().expect("client ok");
+}
+
+/// Тест: управляющее кольцо выбирается раньше накопленных bulk-кадров
+#[test]
+fn test_control_ring_drained_before_bulk() {
+    let name = unique_name("CONTROL");
+    // Четыре bulk-кадра должны уместиться в кольцо любого профиля.
+    const BULK_LEN: usize = if RING_CAPACITY / 5 < 60 * 1024 {
+        RING_CAPACITY / 5
+    } else {
+        60 * 1024
+    };
+
+    let mut server = SharedServer::start_with_control_rings(&name).expect("server start");
+    assert!(server.has_control_rings());
+
+    let (sent_tx, sent_rx) = std::sync::mpsc::channel();
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            assert!(client.has_control_rings());
+            let bulk = vec![0xAB; BULK_LEN];
+            for _ in 0..4 {
+                client.send_to_server(&bulk)?;
+            }
+            client.send_control_to_server(b"STOP")?;
+            let _ = sent_tx.send(());
+            thread::sleep(Duration::from_millis(200));
+            Ok(())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    sent_rx
+        .recv_timeout(Duration::from_secs(2))
+        .expect("client sent");
+
+    let mut buf = Vec::new();
+    let len = server.receive_from_client(&mut buf).expect("receive control");
+    assert_eq!(&buf[..len], b"STOP");
+    for _ in 0..4 {
+        let len = server.receive_from_client(&mut buf).expect("receive bulk");
+        assert_eq!(len, BULK_LEN);
+    }
+    client_thread.join().unwrap().expect("client ok");
+}
+
+/// Тест: на канале без control-колец срочная отправка не поддерживается
+#[test]
+fn test_control_send_without_control_rings() {
+    let name = unique_name("NO_CONTROL");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            assert!(!client.has_control_rings());
+            assert!(matches!(
+                client.send_control_to_server(b"X"),
+                Err(ShmError::Unsupported(_))
+            ));
+            Ok(())
+        }
+    });
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    client_thread.join().unwrap().expect("client ok");
+}
+
+/// Тест: в кредитном режиме отправитель получает QueueFull вместо вытеснения
+#[test]
+fn test_credit_window_blocks_instead_of_overwriting() {
+    let name = unique_name("CREDITS");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    server
+        .set_credit_window(Some(CreditWindow {
+            messages: 4,
+            ..CreditWindow::default()
+        }))
+        .expect("credit window");
+
+    let (sent_tx, sent_rx) = std::sync::mpsc::channel();
+    let (read_tx, read_rx) = std::sync::mpsc::channel();
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            for i in 0..4u8 {
+                let outcome = client.send_to_server(&[i; 2])?;
+                assert_eq!(outcome.overwritten, 0);
+            }
+            assert!(matches!(
+                client.send_to_server(b"XX"),
+                Err(ShmError::QueueFull)
+            ));
+            assert_eq!(client.send_credits().map(|c| c.messages), Some(0));
+            let _ = sent_tx.send(());
+
+            read_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+            assert_eq!(client.send_credits().map(|c| c.messages), Some(1));
+            client.send_to_server(&[4; 2])?;
+            Ok(())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    sent_rx
+        .recv_timeout(Duration::from_secs(2))
+        .expect("client sent");
+
+    let mut buf = Vec::new();
+    server.receive_from_client(&mut buf).expect("receive");
+    assert_eq!(buf, [0; 2]);
+    read_tx.send(()).unwrap();
+    client_thread.join().unwrap().expect("client ok");
+
+    for expected in 1..=4u8 {
+        server.receive_from_client(&mut buf).expect("receive");
+        assert_eq!(buf, [expected; 2]);
+    }
+}
+
+/// Тест: стороны согласуют пересечение своих возможностей
+#[test]
+fn test_feature_negotiation() {
+    let name = unique_name("FEATURES");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    server.set_features(FEATURE_CHECKSUM | FEATURE_CREDITS | FEATURE_TIMESTAMPS);
+
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<u32> {
+            let client = SharedClient::connect_with_features(
+                &name,
+                Duration::from_secs(2),
+                FEATURE_CHECKSUM | FEATURE_TIMESTAMPS | FEATURE_HANDLES,
+            )?;
+            Ok(client.negotiated_features())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    let client_features = client_thread.join().unwrap().expect("client ok");
+
+    assert_eq!(
+        server.negotiated_features(),
+        FEATURE_CHECKSUM | FEATURE_TIMESTAMPS
+    );
+    assert_eq!(client_features, server.negotiated_features());
+}
+
+/// Тест: просроченные сообщения не доходят до медленного получателя, а
+/// явный срок при отправке перекрывает срок канала
+#[test]
+fn test_expired_messages_are_skipped() {
+    let name = unique_name("TTL");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    let (sent_tx, sent_rx) = std::sync::mpsc::channel();
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            client.set_default_ttl(Some(Duration::from_millis(20)));
+            client.send_to_server(b"stale command")?;
+            client.send_to_server_with_ttl(b"keep", None)?;
+            client.send_to_server_with_ttl(b"long", Some(Duration::from_secs(60)))?;
+            let _ = sent_tx.send(());
+            Ok(())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    sent_rx
+        .recv_timeout(Duration::from_secs(2))
+        .expect("client sent");
+    client_thread.join().unwrap().expect("client ok");
+    thread::sleep(Duration::from_millis(40));
+
+    let mut buf = Vec::new();
+    server.receive_from_client(&mut buf).expect("receive");
+    assert_eq!(buf, b"keep");
+    server.receive_from_client(&mut buf).expect("receive");
+    assert_eq!(buf, b"long");
+    assert_eq!(server.expired_messages(), 1);
+    assert!(matches!(
+        server.receive_from_client(&mut buf),
+        Err(ShmError::QueueEmpty)
+    ));
+}
+
+/// Тест: пачка из send_batch_atomic_to_server видна серверу целиком --
+/// прочитав begin, он сразу читает update и commit той же пачки.
+#[test]
+fn test_atomic_batch_is_never_seen_partially() {
+    const BATCHES: u32 = 2000;
+    let name = unique_name("BATCH");
+
+    let mut server = SharedServer::start(&name).expect("server start");
+    let done = Arc::new(AtomicBool::new(false));
+    let client_thread = thread::spawn({
+        let name = name.clone();
+        let done = done.clone();
+        move || -> xshm::Result<()> {
+            let client = SharedClient::connect(&name, Duration::from_secs(2))?;
+            for i in 0..BATCHES {
+                let frames = [
+                    format!("begin {i}"),
+                    format!("update {i}"),
+                    format!("commit {i}"),
+                ];
+                let batch: Vec<&[u8]> = frames.iter().map(|frame| frame.as_bytes()).collect();
+                loop {
+                    match client.send_batch_atomic_to_server(&batch) {
+                        Ok(_) => break,
+                        Err(ShmError::QueueFull) => thread::yield_now(),
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            while !done.load(Ordering::Acquire) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Ok(())
+        }
+    });
+
+    server
+        .wait_for_client(Some(Duration::from_secs(2)))
+        .expect("wait client");
+    let mut buf = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    for i in 0..BATCHES {
+        loop {
+            match server.receive_from_client(&mut buf) {
+                Ok(_) => break,
+                Err(ShmError::QueueEmpty) => {
+                    assert!(Instant::now() < deadline, "batch {i} never arrived");
+                    thread::yield_now();
+                }
+                Err(err) => panic!("receive: {err:?}"),
+            }
+        }
+        assert_eq!(buf, format!("begin {i}").as_bytes());
+        for step in ["update", "commit"] {
+            server
+                .receive_from_client(&mut buf)
+                .unwrap_or_else(|err| panic!("partial batch {i}: {err:?}"));
+            assert_eq!(buf, format!("{step} {i}").as_bytes());
+        }
+    }
+    done.store(true, Ordering::Release);
+    client_thread.join().unwrap().expect("client ok");
 }
@@ -0,0 +1,100 @@
+//! Запись живого auto-канала и воспроизведение сессии в новый канал.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use xshm::record::{self, Recorder, RecordingHandler, ReplayOptions, Tap};
+use xshm::{AutoClient, AutoHandler, AutoOptions, AutoServer, ChannelKind};
+
+#[derive(Default)]
+struct Collector {
+    messages: Mutex<Vec<Vec<u8>>>,
+    signal: Condvar,
+}
+
+impl Collector {
+    fn wait_for(&self, count: usize) -> Vec<Vec<u8>> {
+        let guard = self.messages.lock().unwrap();
+        let (guard, _) = self
+            .signal
+            .wait_timeout_while(guard, Duration::from_secs(5), |m| m.len() < count)
+            .unwrap();
+        guard.clone()
+    }
+}
+
+impl AutoHandler for Collector {
+    fn on_message(&self, _direction: ChannelKind, payload: &[u8]) {
+        self.messages.lock().unwrap().push(payload.to_vec());
+        self.signal.notify_all();
+    }
+}
+
+fn connect(name: &str, server_handler: Arc<dyn AutoHandler>) -> (AutoServer, AutoClient) {
+    let server = AutoServer::start(name, server_handler, AutoOptions::default()).unwrap();
+    let client =
+        AutoClient::connect(name, Arc::new(Collector::default()), AutoOptions::default()).unwrap();
+    (server, client)
+}
+
+#[test]
+fn recorded_session_replays_into_live_channel() {
+    let path = std::env::temp_dir().join(format!("xshm_record_{}.xrec", std::process::id()));
+    let recorder = Arc::new(Recorder::create(&path).unwrap());
+
+    // Запись: исходящие клиента через Tap, ответы сервера -- через
+    // RecordingHandler клиента.
+    let received = Arc::new(Collector::default());
+    let replies = Arc::new(Collector::default());
+    let name = format!("XSHM_RECORD_A_{}", std::process::id());
+    let server = AutoServer::start(&name, received.clone(), AutoOptions::default()).unwrap();
+    let client = AutoClient::connect(
+        &name,
+        Arc::new(RecordingHandler::new(replies.clone(), recorder.clone())),
+        AutoOptions::default(),
+    )
+    .unwrap();
+    let client = Tap::new(client, recorder.clone());
+    for payload in [&b"one"[..], b"two", b"three"] {
+        client.send(payload).unwrap();
+    }
+    assert_eq!(received.wait_for(3).len(), 3);
+    server.send(b"reply").unwrap();
+    assert_eq!(replies.wait_for(1), vec![b"reply".to_vec()]);
+    drop(client);
+    drop(server);
+    recorder.flush().unwrap();
+
+    let records: Vec<_> = record::open(&path)
+        .unwrap()
+        .collect::<xshm::Result<_>>()
+        .unwrap();
+    let directions: Vec<_> = records.iter().map(|r| r.direction).collect();
+    assert_eq!(
+        directions,
+        [
+            ChannelKind::ClientToServer,
+            ChannelKind::ClientToServer,
+            ChannelKind::ClientToServer,
+            ChannelKind::ServerToClient,
+        ]
+    );
+
+    // Воспроизведение через клиента нового канала: уходят только
+    // исходящие клиента, в исходном порядке.
+    let replayed = Arc::new(Collector::default());
+    let name = format!("XSHM_RECORD_B_{}", std::process::id());
+    let (_server, client) = connect(&name, replayed.clone());
+    let options = ReplayOptions {
+        speed: 0.0,
+        ..ReplayOptions::default()
+    };
+    let sent = record::replay_into(record::open(&path).unwrap(), &client, &options).unwrap();
+    assert_eq!(sent, 3);
+    assert_eq!(
+        replayed.wait_for(3),
+        vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]
+    );
+
+    let _ = std::fs::remove_file(&path);
+}
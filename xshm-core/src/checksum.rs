@@ -0,0 +1,71 @@
+//! CRC-32 (IEEE 802.3, полином 0xEDB88320) для контроля целостности
+//! сообщений в кольце. Таблица считается на этапе компиляции -- без
+//! внешних зависимостей.
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static TABLE: [u32; 256] = make_table();
+
+/// Инкрементальный CRC-32: заголовок и payload лежат в кольце раздельно
+/// (и могут переноситься через границу), поэтому считаем по частям.
+#[derive(Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32(0xFFFF_FFFF)
+    }
+
+    pub fn update(mut self, data: &[u8]) -> Self {
+        for &byte in data {
+            self.0 = TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+        self
+    }
+
+    pub fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_reference_check_value() {
+        // Стандартное check-значение CRC-32/ISO-HDLC.
+        assert_eq!(Crc32::new().update(b"123456789").finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn incremental_equals_one_shot() {
+        let one_shot = Crc32::new().update(b"hello world").finish();
+        let parts = Crc32::new().update(b"hello").update(b" world").finish();
+        assert_eq!(one_shot, parts);
+    }
+}
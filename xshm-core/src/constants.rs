@@ -0,0 +1,315 @@
+/// Общее «магическое» значение для сегмента.
+pub const SHARED_MAGIC: u32 = 0x5853_484d; // 'XSHM'
+/// Текущая версия протокола: старшие 16 бит -- major (раскладка секции),
+/// младшие -- minor (только добавления в reserved-поля и флаги кадров).
+pub const SHARED_VERSION: u32 = 0x0002_0000;
+/// Диапазон версий пира, с которыми совместима эта сборка: тот же major,
+/// любой minor. Новые возможности включаются не по версии, а по
+/// согласованным `FEATURE_*`.
+pub const SHARED_VERSION_MIN_COMPATIBLE: u32 = 0x0002_0000;
+pub const SHARED_VERSION_MAX_COMPATIBLE: u32 = 0x0002_FFFF;
+
+// Профиль размеров выбирается cargo-фичей: `ring-64k`, `ring-256k`,
+// `ring-8m`; без них -- 2 МБ. Фичи аддитивны: если граф зависимостей
+// включил несколько, действует самый большой профиль. Оба пира канала
+// должны собираться с одним профилем (сервер публикует его в
+// `LAYOUT_RING_SHIFT_MASK`).
+
+/// Размер каждого кольцевого буфера (байты).
+#[cfg(all(
+    not(loom),
+    not(any(feature = "ring-64k", feature = "ring-256k", feature = "ring-8m"))
+))]
+pub const RING_CAPACITY: usize = 2 * 1024 * 1024;
+#[cfg(all(
+    not(loom),
+    feature = "ring-64k",
+    not(any(feature = "ring-256k", feature = "ring-8m"))
+))]
+pub const RING_CAPACITY: usize = 64 * 1024;
+#[cfg(all(not(loom), feature = "ring-256k", not(feature = "ring-8m")))]
+pub const RING_CAPACITY: usize = 256 * 1024;
+#[cfg(all(not(loom), feature = "ring-8m"))]
+pub const RING_CAPACITY: usize = 8 * 1024 * 1024;
+/// Под loom кольцо крошечное: переполнение и перенос через границу
+/// достигаются за пару операций, иначе модель не переберёт чередования.
+/// cbindgen:ignore
+#[cfg(loom)]
+pub const RING_CAPACITY: usize = 64;
+/// Маска размера (так как это степень двойки).
+pub const RING_MASK: u32 = (RING_CAPACITY as u32) - 1;
+
+/// Размер управляющего кольца канала с control-кольцами (байты): маленькие
+/// срочные команды не стоят в очереди за 64 КБ bulk-кадрами.
+pub const CONTROL_RING_CAPACITY: usize = 16 * 1024;
+
+/// Максимальное количество сообщений в очереди.
+#[cfg(all(
+    not(loom),
+    not(any(feature = "ring-64k", feature = "ring-256k", feature = "ring-8m"))
+))]
+pub const MAX_MESSAGES: u32 = 500;
+#[cfg(all(
+    not(loom),
+    feature = "ring-64k",
+    not(any(feature = "ring-256k", feature = "ring-8m"))
+))]
+pub const MAX_MESSAGES: u32 = 64;
+#[cfg(all(not(loom), feature = "ring-256k", not(feature = "ring-8m")))]
+pub const MAX_MESSAGES: u32 = 128;
+#[cfg(all(not(loom), feature = "ring-8m"))]
+pub const MAX_MESSAGES: u32 = 2000;
+/// cbindgen:ignore
+#[cfg(loom)]
+pub const MAX_MESSAGES: u32 = 4;
+/// Максимальный размер одного сообщения (длина в заголовке кадра -- u16).
+/// В 64 КБ кольце -- четверть кольца, чтобы вытеснение оставалось
+/// возможным.
+#[cfg(any(
+    loom,
+    not(feature = "ring-64k"),
+    feature = "ring-256k",
+    feature = "ring-8m"
+))]
+pub const MAX_MESSAGE_SIZE: usize = 65_535;
+#[cfg(all(
+    not(loom),
+    feature = "ring-64k",
+    not(any(feature = "ring-256k", feature = "ring-8m"))
+))]
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 - 1;
+/// Минимальный размер сообщения.
+pub const MIN_MESSAGE_SIZE: usize = 2;
+
+/// Размер служебного заголовка сообщения (байты).
+pub const MESSAGE_HEADER_SIZE: usize = 4; // u16 length + u16 flags/reserved
+
+/// Флаги заголовка сообщения. Старший байт зарезервирован за библиотекой,
+/// младший -- за пользовательскими флагами (кроме `MSG_FLAG_CHUNK` и
+/// `MSG_FLAG_SEQUENCE`).
+///
+/// За payload следует CRC-32 трейлер (`CHECKSUM_SIZE` байт), покрывающий
+/// заголовок (длину + флаги) и payload.
+pub const MSG_FLAG_CHECKSUM: u16 = 0x8000;
+/// Размер CRC-32 трейлера сообщения (байты).
+pub const CHECKSUM_SIZE: usize = 4;
+/// Управляющий кадр: payload -- значение handle'а (u64 LE), уже
+/// продублированного в процесс получателя. Такие кадры writer никогда не
+/// вытесняет при overflow -- иначе handle утёк бы в чужом процессе.
+pub const MSG_FLAG_HANDLE: u16 = 0x4000;
+/// Размер payload'а handle-кадра (байты).
+pub const HANDLE_FRAME_SIZE: usize = 8;
+/// За payload (перед CRC-32 трейлером) следует метка времени записи: u64
+/// LE, наносекунды монотонных часов машины (`CLOCK_MONOTONIC` / QPC),
+/// общих для всех процессов. Получатель считает по ней задержку доставки.
+pub const MSG_FLAG_TIMESTAMP: u16 = 0x2000;
+/// Размер метки времени сообщения (байты).
+pub const TIMESTAMP_SIZE: usize = 8;
+/// За payload (после метки времени, перед CRC-32) следует срок годности
+/// кадра: u64 LE, наносекунды тех же монотонных часов. Просроченный кадр
+/// читатель пропускает, а writer убирает из головы очереди перед записью.
+pub const MSG_FLAG_DEADLINE: u16 = 0x1000;
+/// Размер срока годности сообщения (байты).
+pub const DEADLINE_SIZE: usize = 8;
+/// Метка фиксации кадра: в битах `MSG_COMMIT_LAP_MASK` флагов -- номер
+/// круга кольца, на котором кадр начат (позиция / ёмкость, по модулю 8).
+/// Писатель бьёт `message_count` раньше `write_pos`; если он умер между
+/// ними, reader по метке отличает дописанный кадр за `write_pos` (и сам
+/// публикует его) от мусора прошлых кругов (и пропускает).
+pub const MSG_FLAG_COMMIT: u16 = 0x0800;
+/// Биты флагов с номером круга кадра (`MSG_FLAG_COMMIT`).
+pub const MSG_COMMIT_LAP_MASK: u16 = 0x0700;
+/// Кадр -- кусок сообщения больше `MAX_MESSAGE_SIZE`: payload начинается
+/// с заголовка куска (`CHUNK_HEADER_SIZE` байт: полная длина сообщения и
+/// смещение куска, оба u32 LE), дальше -- данные. Старший байт флагов
+/// занят целиком, поэтому бит взят из младшего.
+pub const MSG_FLAG_CHUNK: u16 = 0x0080;
+/// За payload (после срока годности, перед CRC-32) следует номер кадра:
+/// u32 LE, число кадров, записанных в кольцо до него (по модулю 2^32,
+/// с нуля после сброса кольца). Разрыв в номерах -- кадры, вытесненные
+/// до чтения.
+pub const MSG_FLAG_SEQUENCE: u16 = 0x0040;
+/// Размер номера кадра (байты).
+pub const SEQUENCE_SIZE: usize = 4;
+/// Биты флагов, которые задаёт приложение (`send_with_flags`) и которые
+/// получатель видит как есть; остальное -- библиотечные флаги.
+pub const MSG_USER_FLAGS_MASK: u16 = 0x003F;
+/// Размер заголовка куска (байты).
+pub const CHUNK_HEADER_SIZE: usize = 8;
+/// Наибольшее сообщение, которое собирается из кусков (64 МБ).
+pub const MAX_CHUNKED_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Имя события для данных, поступающих от сервера к клиенту.
+pub const EVENT_DATA_SUFFIX: &str = "DATA";
+/// Имя события для уведомления о свободном месте.
+pub const EVENT_SPACE_SUFFIX: &str = "SPACE";
+/// Имя события о подключении (ответ сервера).
+pub const EVENT_CONNECT_SUFFIX: &str = "CONNECT";
+/// Имя события запроса подключения (от клиента).
+pub const EVENT_CONNECT_REQ_SUFFIX: &str = "CONNECT_REQ";
+/// Имя события об отключении.
+pub const EVENT_DISCONNECT_SUFFIX: &str = "DISCONNECT";
+
+/// Состояния handshake.
+pub const HANDSHAKE_IDLE: u32 = 0;
+pub const HANDSHAKE_CLIENT_HELLO: u32 = 1;
+pub const HANDSHAKE_SERVER_READY: u32 = 2;
+
+/// Специальное значение: нет свободных слотов.
+pub const SLOT_ID_NO_SLOT: u32 = 0xFFFF_FFFF;
+
+/// Индекс в reserved[] СЕГМЕНТА СЛОТА для атомарного захвата слота multi-клиентом.
+/// Хранит токен захватившего клиента; `CLAIM_FREE` (0) = слот свободен.
+/// Захват выполняется через `compare_exchange(CLAIM_FREE -> token)` — это даёт
+/// конкурентное, lock-free распределение слотов без централизованного lobby.
+pub const RESERVED_CLAIM_INDEX: usize = 0;
+/// Значение «слот свободен» для claim.
+pub const CLAIM_FREE: u32 = 0;
+
+/// Индекс в reserved[] СЕГМЕНТА СЛОТА для PID процесса, захватившего claim.
+/// Записывается клиентом сразу после успешного CAS в `try_claim_slot`.
+/// Сервер использует его для liveness-проверки connected-слотов: клиент,
+/// упавший ПОСЛЕ завершения handshake (но не освободивший claim), иначе
+/// навсегда лишает сервер слота — событий от мёртвого процесса не будет.
+pub const RESERVED_OWNER_PID_INDEX: usize = 1;
+
+/// Индексы в reserved[] CONTROL BLOCK для PID сторон канала. Сервер пишет
+/// свой PID при создании секции, клиент -- перед HELLO. Нужны для
+/// дублирования handle'ов в процесс пира (`send_handle_to_*`); 0 -- пир
+/// PID не опубликовал (старая версия библиотеки).
+pub const RESERVED_SERVER_PID_INDEX: usize = 2;
+pub const RESERVED_CLIENT_PID_INDEX: usize = 3;
+
+/// Индекс в reserved[] CONTROL BLOCK для флагов раскладки секции
+/// (`LAYOUT_FLAG_*`). Выставляется сервером при создании секции.
+pub const RESERVED_LAYOUT_FLAGS_INDEX: usize = 4;
+/// За bulk-кольцами секции лежат два управляющих кольца
+/// (`CONTROL_RING_CAPACITY`), см. `dual_mapping_size`.
+pub const LAYOUT_FLAG_CONTROL_RINGS: u32 = 0x1;
+/// Биты флагов раскладки с log2(`RING_CAPACITY`) сервера; 0 -- сервер
+/// без профилей (2 МБ).
+pub const LAYOUT_RING_SHIFT_MASK: u32 = 0x3F00;
+/// Сдвиг поля `LAYOUT_RING_SHIFT_MASK`.
+pub const LAYOUT_RING_SHIFT_OFFSET: u32 = 8;
+
+/// Индексы в reserved[] CONTROL BLOCK для согласования возможностей.
+/// Сервер публикует свою маску `FEATURE_*` при создании секции, клиент
+/// перед HELLO пишет свою версию и маску, сервер на HELLO пишет
+/// пересечение. 0 в версии/маске -- пир старой версии без согласования.
+pub const RESERVED_SERVER_FEATURES_INDEX: usize = 5;
+pub const RESERVED_CLIENT_FEATURES_INDEX: usize = 6;
+pub const RESERVED_NEGOTIATED_FEATURES_INDEX: usize = 7;
+pub const RESERVED_CLIENT_VERSION_INDEX: usize = 8;
+
+/// Индекс в reserved[] CONTROL BLOCK для токена передачи канала новой
+/// версии сервера (`SharedServer::detach`/`adopt`); 0 -- канал никому не
+/// передаётся.
+pub const RESERVED_UPGRADE_TOKEN_INDEX: usize = 9;
+
+/// Индекс в reserved[] CONTROL BLOCK для геометрии секции
+/// ([`ring_geometry`]): `MAX_MESSAGES`, log2(`RING_CAPACITY`) и
+/// `LAYOUT_VERSION` создавшей её сборки. Пишется вместе с magic в
+/// `ControlBlock::reset`; 0 -- секция сервера старой версии, у которой
+/// есть только `LAYOUT_RING_SHIFT_MASK`.
+pub const RESERVED_RING_GEOMETRY_INDEX: usize = 10;
+/// Биты слова геометрии с `MAX_MESSAGES`.
+pub const GEOMETRY_MAX_MESSAGES_MASK: u32 = 0xFFFF;
+/// Биты слова геометрии с log2(`RING_CAPACITY`) и их сдвиг.
+pub const GEOMETRY_RING_SHIFT_MASK: u32 = 0xFF_0000;
+pub const GEOMETRY_RING_SHIFT_OFFSET: u32 = 16;
+/// Биты слова геометрии с `LAYOUT_VERSION` и их сдвиг.
+pub const GEOMETRY_LAYOUT_VERSION_MASK: u32 = 0xFF00_0000;
+pub const GEOMETRY_LAYOUT_VERSION_OFFSET: u32 = 24;
+
+/// Индексы в reserved[] CONTROL BLOCK для heartbeat сторон: миллисекунды
+/// монотонных часов (по модулю 2^32, не 0) последнего
+/// `SharedServer::heartbeat`/`SharedClient::heartbeat`; 0 -- сторона
+/// heartbeat не ведёт. Часы общие для всех процессов машины, так что
+/// возраст считается без обмена.
+pub const RESERVED_SERVER_HEARTBEAT_INDEX: usize = 11;
+pub const RESERVED_CLIENT_HEARTBEAT_INDEX: usize = 12;
+
+/// Индексы в reserved[] CONTROL BLOCK для причины отключения сторон
+/// (`DISCONNECT_REASON_*`): уходящая сторона пишет свою перед тем, как
+/// выставить событие disconnect, пир читает её, получив событие. Сервер
+/// обнуляет оба поля при каждом подключении; 0 -- причина не указана (пир
+/// упал или собран старой версией).
+pub const RESERVED_SERVER_DISCONNECT_REASON_INDEX: usize = 13;
+pub const RESERVED_CLIENT_DISCONNECT_REASON_INDEX: usize = 14;
+
+/// Причина не указана.
+pub const DISCONNECT_REASON_UNKNOWN: u32 = 0;
+/// Штатное закрытие (drop, остановка).
+pub const DISCONNECT_REASON_GRACEFUL: u32 = 1;
+/// Пир нарушил протокол (битый кусок сообщения, чужой ключ шифрования).
+pub const DISCONNECT_REASON_PROTOCOL_ERROR: u32 = 2;
+/// Сервер отключил клиента по своей инициативе.
+pub const DISCONNECT_REASON_KICKED: u32 = 3;
+/// Сторона уходит на обновление и скоро вернётся.
+pub const DISCONNECT_REASON_UPGRADING: u32 = 4;
+
+/// Число reserved-полей control block: с ними `ControlBlock` занимает две
+/// кэш-линии (128 байт). До major-версии 2 полей было 11 (одна линия).
+pub const CONTROL_RESERVED_SLOTS: usize = 27;
+
+/// Версия раскладки `ControlBlock`/`RingHeader` и формата кадра. Растёт с
+/// любым изменением этих структур, которое не отражено в
+/// `SHARED_VERSION`: сборки с разной версией раскладки секцию друг друга
+/// не открывают.
+pub const LAYOUT_VERSION: u32 = 1;
+
+/// Кадры с CRC-32 трейлером (`MSG_FLAG_CHECKSUM`).
+pub const FEATURE_CHECKSUM: u32 = 0x1;
+/// Управляющие (приоритетные) кольца (`LAYOUT_FLAG_CONTROL_RINGS`).
+pub const FEATURE_CONTROL_RINGS: u32 = 0x2;
+/// Кредитный режим колец (окно сообщений/байт вместо overwrite).
+pub const FEATURE_CREDITS: u32 = 0x4;
+/// Метки времени кадров (`MSG_FLAG_TIMESTAMP`).
+pub const FEATURE_TIMESTAMPS: u32 = 0x8;
+/// Передача handle'ов (`MSG_FLAG_HANDLE`).
+pub const FEATURE_HANDLES: u32 = 0x10;
+/// Срок годности сообщений (`MSG_FLAG_DEADLINE`).
+pub const FEATURE_TTL: u32 = 0x20;
+/// Сообщения больше `MAX_MESSAGE_SIZE` кусками (`MSG_FLAG_CHUNK`).
+pub const FEATURE_CHUNKING: u32 = 0x40;
+/// Номера кадров (`MSG_FLAG_SEQUENCE`).
+pub const FEATURE_SEQUENCE: u32 = 0x80;
+/// Все возможности, которые понимает эта сборка.
+pub const SUPPORTED_FEATURES: u32 = FEATURE_CHECKSUM
+    | FEATURE_CONTROL_RINGS
+    | FEATURE_CREDITS
+    | FEATURE_TIMESTAMPS
+    | FEATURE_HANDLES
+    | FEATURE_TTL
+    | FEATURE_CHUNKING
+    | FEATURE_SEQUENCE;
+/// Возможности, которые endpoint предлагает по умолчанию: куски собирает
+/// только auto-режим, он и добавляет `FEATURE_CHUNKING`.
+pub const DEFAULT_FEATURES: u32 = SUPPORTED_FEATURES & !FEATURE_CHUNKING;
+
+/// Совместима ли версия пира (или секции) с этой сборкой.
+pub const fn version_compatible(version: u32) -> bool {
+    version >= SHARED_VERSION_MIN_COMPATIBLE && version <= SHARED_VERSION_MAX_COMPATIBLE
+}
+
+/// Флаги раскладки с профилем колец этой сборки (без
+/// `LAYOUT_FLAG_CONTROL_RINGS`).
+pub const fn layout_ring_flags() -> u32 {
+    RING_CAPACITY.trailing_zeros() << LAYOUT_RING_SHIFT_OFFSET
+}
+
+/// Слово геометрии этой сборки (`RESERVED_RING_GEOMETRY_INDEX`).
+pub const fn ring_geometry() -> u32 {
+    MAX_MESSAGES
+        | RING_CAPACITY.trailing_zeros() << GEOMETRY_RING_SHIFT_OFFSET
+        | LAYOUT_VERSION << GEOMETRY_LAYOUT_VERSION_OFFSET
+}
+
+/// Секция с флагами раскладки `flags` собрана с тем же профилем колец,
+/// что и эта сборка.
+pub const fn ring_profile_matches(flags: u32) -> bool {
+    match (flags & LAYOUT_RING_SHIFT_MASK) >> LAYOUT_RING_SHIFT_OFFSET {
+        0 => RING_CAPACITY == 2 * 1024 * 1024,
+        shift => shift == RING_CAPACITY.trailing_zeros(),
+    }
+}
@@ -0,0 +1,136 @@
+use core::sync::atomic::Ordering;
+
+use crate::constants::*;
+use crate::sync::AtomicU32;
+
+#[repr(C, align(64))]
+pub struct RingHeader {
+    pub write_pos: AtomicU32,
+    pub read_pos: AtomicU32,
+    pub message_count: AtomicU32,
+    pub drop_count: AtomicU32,
+    pub sequence: AtomicU32,
+    pub connection_gen: AtomicU32,
+    pub handshake_state: AtomicU32,
+    /// Сообщения, отброшенные читателем из-за несовпадения CRC-32.
+    pub checksum_errors: AtomicU32,
+    /// Кредитный режим: окно читателя в сообщениях и байтах (0 -- режим
+    /// выключен, работает вытеснение старых кадров). Выставляет читатель.
+    pub credit_window_msgs: AtomicU32,
+    pub credit_window_bytes: AtomicU32,
+    /// Записанные/изъятые кадры и их байты (по модулю 2^32). Ведутся
+    /// всегда; свободные кредиты = окно - (sent - consumed).
+    pub sent_msgs: AtomicU32,
+    pub sent_bytes: AtomicU32,
+    pub consumed_msgs: AtomicU32,
+    pub consumed_bytes: AtomicU32,
+    /// Writer упёрся в кредиты: читатель, изъяв кадр, сигналит space.
+    pub credit_stalled: AtomicU32,
+    /// Просроченные кадры (`MSG_FLAG_DEADLINE`), пропущенные читателем или
+    /// убранные writer'ом.
+    pub expired_count: AtomicU32,
+}
+
+impl RingHeader {
+    pub fn reset(&self, generation: u32) {
+        self.write_pos.store(0, Ordering::Relaxed);
+        self.read_pos.store(0, Ordering::Relaxed);
+        self.message_count.store(0, Ordering::Relaxed);
+        self.drop_count.store(0, Ordering::Relaxed);
+        self.sequence.store(0, Ordering::Relaxed);
+        self.connection_gen.store(generation, Ordering::Relaxed);
+        self.handshake_state
+            .store(HANDSHAKE_IDLE, Ordering::Relaxed);
+        self.checksum_errors.store(0, Ordering::Relaxed);
+        self.credit_window_msgs.store(0, Ordering::Relaxed);
+        self.credit_window_bytes.store(0, Ordering::Relaxed);
+        self.sent_msgs.store(0, Ordering::Relaxed);
+        self.sent_bytes.store(0, Ordering::Relaxed);
+        self.consumed_msgs.store(0, Ordering::Relaxed);
+        self.consumed_bytes.store(0, Ordering::Relaxed);
+        self.credit_stalled.store(0, Ordering::Relaxed);
+        self.expired_count.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Default for RingHeader {
+    fn default() -> Self {
+        RingHeader {
+            write_pos: AtomicU32::new(0),
+            read_pos: AtomicU32::new(0),
+            message_count: AtomicU32::new(0),
+            drop_count: AtomicU32::new(0),
+            sequence: AtomicU32::new(0),
+            connection_gen: AtomicU32::new(0),
+            handshake_state: AtomicU32::new(HANDSHAKE_IDLE),
+            checksum_errors: AtomicU32::new(0),
+            credit_window_msgs: AtomicU32::new(0),
+            credit_window_bytes: AtomicU32::new(0),
+            sent_msgs: AtomicU32::new(0),
+            sent_bytes: AtomicU32::new(0),
+            consumed_msgs: AtomicU32::new(0),
+            consumed_bytes: AtomicU32::new(0),
+            credit_stalled: AtomicU32::new(0),
+            expired_count: AtomicU32::new(0),
+        }
+    }
+}
+
+#[repr(C, align(64))]
+pub struct ControlBlock {
+    pub magic: u32,
+    pub version: u32,
+    pub generation: AtomicU32,
+    pub server_state: AtomicU32,
+    pub client_state: AtomicU32,
+    /// Reserved поля для расширения протокола.
+    /// reserved[0] используется для передачи slot_id в multi-client режиме.
+    pub reserved: [AtomicU32; CONTROL_RESERVED_SLOTS],
+}
+
+impl ControlBlock {
+    pub fn reset(&mut self) {
+        self.magic = SHARED_MAGIC;
+        self.version = SHARED_VERSION;
+        self.generation.store(1, Ordering::Relaxed);
+        self.server_state.store(HANDSHAKE_IDLE, Ordering::Relaxed);
+        self.client_state.store(HANDSHAKE_IDLE, Ordering::Relaxed);
+        for r in &self.reserved {
+            r.store(0, Ordering::Relaxed);
+        }
+        self.reserved[RESERVED_RING_GEOMETRY_INDEX].store(ring_geometry(), Ordering::Relaxed);
+    }
+}
+
+impl Default for ControlBlock {
+    fn default() -> Self {
+        ControlBlock {
+            magic: SHARED_MAGIC,
+            version: SHARED_VERSION,
+            generation: AtomicU32::new(1),
+            server_state: AtomicU32::new(HANDSHAKE_IDLE),
+            client_state: AtomicU32::new(HANDSHAKE_IDLE),
+            reserved: core::array::from_fn(|i| {
+                AtomicU32::new(if i == RESERVED_RING_GEOMETRY_INDEX {
+                    ring_geometry()
+                } else {
+                    0
+                })
+            }),
+        }
+    }
+}
+
+/// Общий размер сегмента (контрольный блок + 2 хэдера + 2 кольца).
+pub const fn shared_mapping_size() -> usize {
+    core::mem::size_of::<ControlBlock>()
+        + core::mem::size_of::<RingHeader>() * 2
+        + RING_CAPACITY * 2
+}
+
+/// Размер секции с управляющими кольцами: за обычной раскладкой
+/// (`shared_mapping_size`) следуют RingHeader + кольцо управления
+/// сервер→клиент и RingHeader + кольцо управления клиент→сервер.
+pub const fn dual_mapping_size() -> usize {
+    shared_mapping_size() + (core::mem::size_of::<RingHeader>() + CONTROL_RING_CAPACITY) * 2
+}
@@ -0,0 +1,21 @@
+//! Общая для всех сторон канала часть xshm: раскладка секции
+//! (`layout`), протокольные константы и lock-free кольцо.
+//!
+//! Крейт `no_std` и не использует `alloc`, поэтому собирается и в
+//! kernel-mode драйвер, разделяющий с user-mode процессом ту же секцию:
+//! обе стороны компилируют одни и те же `#[repr(C)]` структуры и один код
+//! кольца, а не поддерживают вручную портированные C-заголовки.
+//!
+//! Ошибки кольца -- [`ring::RingError`]; основной крейт `xshm` переводит
+//! их в `ShmError` и добавляет чтение в `Vec`.
+
+#![cfg_attr(not(any(test, loom)), no_std)]
+#![forbid(unsafe_op_in_unsafe_fn)]
+
+pub mod checksum;
+pub mod constants;
+pub mod layout;
+pub mod ring;
+mod sync;
+
+pub use ring::{RingBuffer, RingError, WriteOutcome};
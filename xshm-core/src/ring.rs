@@ -0,0 +1,2822 @@
+//! Lock-free SPSC кольцевой буфер для shared memory IPC.
+//!
+//! Порядок доступа к памяти задан только моделью C11, без расчёта на x86
+//! TSO, поэтому кольцо корректно и на слабо упорядоченных архитектурах
+//! (aarch64):
+//! - writer пишет кадр, затем `message_count` (AcqRel) и `write_pos`
+//!   (AcqRel): reader, увидев их через Acquire, видит и кадр;
+//! - reader копирует кадр до фиксации (seqlock): Acquire-барьер держит
+//!   чтение кадра выше CAS по `read_pos`, а вытеснение writer'а -- CAS по
+//!   тому же `read_pos` -- провалит фиксацию, если слот уже перезаписан;
+//! - флаг `credit_stalled` -- SeqCst-барьеры с обеих сторон;
+//! - писатели одного процесса ([`RingBuffer::set_multi_producer`]) делят
+//!   место CAS'ом по локальному курсору, а кадры публикуют по очереди
+//!   занятия: каждый ждёт, пока `write_pos` дойдёт до его кадра.
+//!
+//! Под `--cfg loom` эти правила перебирают `loom_tests` (см. `sync.rs`).
+//!
+//! Кольцо не аллоцирует: чтение идёт в буфер вызывающего
+//! ([`RingBuffer::read_frame`]) или прямо из кольца
+//! ([`RingBuffer::read_live_frame_with`]), ошибки -- [`RingError`].
+
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::checksum::Crc32;
+use crate::constants::*;
+use crate::layout::RingHeader;
+
+/// Ошибки операций кольца.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingError {
+    /// Нет сообщений для чтения.
+    QueueEmpty,
+    /// Место занято кадрами, которые нельзя вытеснить (handle-кадры).
+    QueueFull,
+    MessageTooSmall,
+    MessageTooLarge,
+    /// Заголовок или длина кадра в кольце несогласованы.
+    Corrupted,
+    /// CRC-32 трейлер не совпал; кадр отброшен.
+    ChecksumMismatch,
+    /// Буфер чтения меньше payload'а `required` байт; кадр не забран.
+    BufferTooSmall {
+        required: usize,
+    },
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RingError::QueueEmpty => f.write_str("ring is empty"),
+            RingError::QueueFull => f.write_str("ring is full"),
+            RingError::MessageTooSmall => f.write_str("message too small"),
+            RingError::MessageTooLarge => f.write_str("message too large"),
+            RingError::Corrupted => f.write_str("ring data corrupted"),
+            RingError::ChecksumMismatch => f.write_str("message checksum mismatch"),
+            RingError::BufferTooSmall { required } => {
+                write!(f, "read buffer too small: {required} bytes required")
+            }
+        }
+    }
+}
+
+type Result<T> = core::result::Result<T, RingError>;
+
+/// Инициализированный буфер как приёмник `copy_from_wrapped`.
+fn as_uninit(bytes: &mut [u8]) -> &mut [MaybeUninit<u8>] {
+    // SAFETY: у MaybeUninit<u8> раскладка u8, а в срез пишутся только
+    // инициализированные байты.
+    unsafe { &mut *(bytes as *mut [u8] as *mut [MaybeUninit<u8>]) }
+}
+
+/// Прочитанный кадр: длина payload'а, флаги заголовка, метка времени
+/// записи (`MSG_FLAG_TIMESTAMP`), срок годности (`MSG_FLAG_DEADLINE`) и
+/// номер (`MSG_FLAG_SEQUENCE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    pub len: usize,
+    pub flags: u16,
+    pub timestamp: Option<u64>,
+    pub deadline: Option<u64>,
+    pub sequence: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WriteOutcome {
+    pub overwritten: u32,
+    pub was_empty: bool,
+    /// Свободные байты кольца сразу после публикации. Снимок: читатель
+    /// мог уже что-то изъять, так что место -- не меньше этого.
+    pub free_bytes_after: u32,
+    /// Кадров в очереди сразу после публикации (вместе с этим).
+    pub queue_len_after: u32,
+}
+
+/// Место под кадр, занятое [`RingBuffer::reserve`]: payload пишется прямо
+/// в кольцо ([`RingBuffer::reserved_payload`]), [`RingBuffer::commit`]
+/// дописывает заголовок с трейлерами и публикует кадр. Брошенная без
+/// `commit` резервация ничего не публикует.
+#[derive(Debug)]
+pub struct Reservation {
+    pos: u32,
+    len: usize,
+    flags: u16,
+    timestamp: Option<u64>,
+    deadline: Option<u64>,
+    overwritten: u32,
+}
+
+/// Сколько раз writer перечитывает заголовок, когда `message_count` ещё
+/// не догнал опустошённое reader'ом кольцо.
+const STALE_RETRY_LIMIT: u32 = 1024;
+
+/// Сколько пауз писатель ждёт очереди на публикацию спином, прежде чем
+/// перейти на `turn_backoff`.
+const TURN_SPIN_LIMIT: u32 = 64;
+
+/// Биты метки фиксации во флагах заголовка; наружу не отдаются.
+const COMMIT_BITS: u16 = MSG_FLAG_COMMIT | MSG_COMMIT_LAP_MASK;
+
+/// Место под кадры, занятое [`RingBuffer::claim_space`]: позиция первого
+/// кадра, число вытесненных кадров и -- в режиме нескольких писателей --
+/// поколение кольца (младшие 16 бит `connection_gen`), в котором оно занято.
+struct Claim {
+    pos: u32,
+    overwritten: u32,
+    shared: Option<u16>,
+}
+
+pub struct RingBuffer {
+    header: NonNull<RingHeader>,
+    storage: NonNull<u8>,
+    capacity: u32,
+    /// Дописывать CRC-32 трейлер к исходящим сообщениям. Чтение проверяет
+    /// трейлер по флагу в заголовке независимо от этой настройки.
+    checksum: AtomicBool,
+    /// Вытеснять старейшие кадры, когда под новый не хватает места;
+    /// выключено -- запись получает `QueueFull` (см.
+    /// [`set_evict`](Self::set_evict)).
+    evict: AtomicBool,
+    /// Дописывать номер кадра (`MSG_FLAG_SEQUENCE`) к исходящим кадрам.
+    sequence_numbers: AtomicBool,
+    /// Писать из нескольких потоков (см.
+    /// [`set_multi_producer`](Self::set_multi_producer)) и курсор
+    /// писателей: конец занятого места (биты 0..32), занятые, но не
+    /// опубликованные кадры (32..48) и поколение кольца (48..64).
+    multi_producer: AtomicBool,
+    claims: crate::sync::AtomicU64,
+    /// Пауза писателя, ждущего очереди на публикацию (см.
+    /// [`set_turn_backoff`](Self::set_turn_backoff)).
+    turn_backoff: fn(),
+    /// Кадры за `write_pos`, пропущенные этим reader'ом как неписаные
+    /// (см. [`torn_records`](Self::torn_records)), и позиция последнего
+    /// из них (`u64::MAX` -- не было).
+    torn: AtomicU32,
+    torn_at: AtomicU64,
+    /// Наибольшая занятость кольца (байты и кадры), которую видела эта
+    /// сторона: writer -- сразу после своих записей, reader -- перед
+    /// изъятием. Локальная, в секции не хранится.
+    peak_bytes: AtomicU32,
+    peak_messages: AtomicU32,
+}
+
+unsafe impl Send for RingBuffer {}
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    /// # Safety
+    /// `header` указывает на `RingHeader`, `data` -- на `RING_CAPACITY`
+    /// байт кольца; обе области живут дольше `RingBuffer` и не
+    /// пересекаются.
+    pub unsafe fn new(header: *mut RingHeader, data: *mut u8) -> Self {
+        // SAFETY: требования те же, ёмкость штатная.
+        unsafe { Self::with_capacity(header, data, RING_CAPACITY as u32) }
+    }
+
+    /// Кольцо нештатной ёмкости (например, `CONTROL_RING_CAPACITY`).
+    ///
+    /// # Safety
+    /// Как у [`new`](Self::new), но `data` -- на `capacity` байт.
+    /// `capacity` -- степень двойки.
+    pub unsafe fn with_capacity(header: *mut RingHeader, data: *mut u8, capacity: u32) -> Self {
+        assert!(capacity.is_power_of_two(), "ring capacity must be a power of two");
+        RingBuffer {
+            header: NonNull::new(header).expect("header pointer must be valid"),
+            storage: NonNull::new(data).expect("ring buffer pointer must be valid"),
+            capacity,
+            checksum: AtomicBool::new(false),
+            evict: AtomicBool::new(true),
+            sequence_numbers: AtomicBool::new(false),
+            multi_producer: AtomicBool::new(false),
+            claims: crate::sync::AtomicU64::new(0),
+            turn_backoff: crate::sync::spin_loop,
+            torn: AtomicU32::new(0),
+            torn_at: AtomicU64::new(u64::MAX),
+            peak_bytes: AtomicU32::new(0),
+            peak_messages: AtomicU32::new(0),
+        }
+    }
+
+    /// Включает/выключает CRC-32 трейлер для последующих записей.
+    pub fn set_checksum(&self, enabled: bool) {
+        self.checksum.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Включает/выключает вытеснение старых кадров (по умолчанию включено).
+    /// Без вытеснения запись в полное кольцо возвращает `QueueFull`, как в
+    /// кредитном режиме, и так же выставляет `credit_stalled`: читатель,
+    /// изъяв кадр, будит writer'а space-событием. Настройка writer'а, в
+    /// разделяемой памяти не хранится.
+    pub fn set_evict(&self, enabled: bool) {
+        self.evict.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn evicts(&self) -> bool {
+        self.evict.load(Ordering::Relaxed)
+    }
+
+    /// Включает/выключает номер кадра (`MSG_FLAG_SEQUENCE`) для
+    /// последующих записей. Номер -- `sent_msgs` заголовка на момент
+    /// записи; его сдвигает каждый записанный кадр, так что разрыв в
+    /// номерах у читателя -- кадры, которых он не получил (вытесненные или
+    /// записанные без номера).
+    pub fn set_sequence_numbers(&self, enabled: bool) {
+        self.sequence_numbers.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Включает/выключает запись из нескольких потоков процесса (MPSC).
+    /// Писатели занимают место CAS'ом по локальному курсору, копируют
+    /// payload параллельно и публикуют кадры в порядке занятия, так что
+    /// номера кадров идут подряд. Кредитное окно может быть превышено на
+    /// кадры, которые пишутся одновременно. [`reserve`](Self::reserve)
+    /// по-прежнему требует единственного писателя.
+    ///
+    /// Переключать, только пока в кольцо никто не пишет.
+    pub fn set_multi_producer(&self, enabled: bool) {
+        self.multi_producer.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_multi_producer(&self) -> bool {
+        self.multi_producer.load(Ordering::Relaxed)
+    }
+
+    /// Пауза писателя, который в режиме нескольких писателей ждёт
+    /// публикации чужих кадров дольше `TURN_SPIN_LIMIT` итераций; по
+    /// умолчанию -- тот же спин. Под std -- `std::thread::yield_now`: когда
+    /// писателей больше ядер, спин съедает квант того, чья очередь.
+    pub fn set_turn_backoff(&mut self, backoff: fn()) {
+        self.turn_backoff = backoff;
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    fn data_ptr(&self) -> *mut u8 {
+        self.storage.as_ptr()
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn reset(&self, generation: u32) {
+        self.header().reset(generation);
+    }
+
+    fn available_bytes(&self, write: u32, read: u32) -> i64 {
+        let used = write.wrapping_sub(read);
+        self.capacity as i64 - used as i64
+    }
+
+    fn mask(&self) -> usize {
+        (self.capacity - 1) as usize
+    }
+
+    fn mask_index(&self, pos: u32) -> usize {
+        pos as usize & self.mask()
+    }
+
+    /// Полный размер сообщения в кольце: заголовок + payload + трейлеры.
+    fn frame_size(msg_len: usize, flags: u16) -> usize {
+        MESSAGE_HEADER_SIZE
+            + msg_len
+            + Self::timestamp_size(flags)
+            + Self::deadline_size(flags)
+            + Self::sequence_size(flags)
+            + Self::checksum_size(flags)
+    }
+
+    /// Метка фиксации кадра, начатого с позиции `pos`.
+    fn commit_stamp(&self, pos: u32) -> u16 {
+        let lap = (pos >> self.capacity.trailing_zeros()) as u16;
+        MSG_FLAG_COMMIT | ((lap << 8) & MSG_COMMIT_LAP_MASK)
+    }
+
+    /// Кадр с позиции `pos` записан на этом круге. Кадры без метки (пир
+    /// старой версии) проверить нечем -- считаются своими.
+    fn stamp_matches(&self, flags: u16, pos: u32) -> bool {
+        flags & MSG_FLAG_COMMIT == 0 || flags & COMMIT_BITS == self.commit_stamp(pos)
+    }
+
+    fn timestamp_size(flags: u16) -> usize {
+        if flags & MSG_FLAG_TIMESTAMP != 0 {
+            TIMESTAMP_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn deadline_size(flags: u16) -> usize {
+        if flags & MSG_FLAG_DEADLINE != 0 {
+            DEADLINE_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn sequence_size(flags: u16) -> usize {
+        if flags & MSG_FLAG_SEQUENCE != 0 {
+            SEQUENCE_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn checksum_size(flags: u16) -> usize {
+        if flags & MSG_FLAG_CHECKSUM != 0 {
+            CHECKSUM_SIZE
+        } else {
+            0
+        }
+    }
+
+    /// # Safety
+    /// `index + data.len() <= capacity` (вызывающий код обязан гарантировать
+    /// отсутствие выхода за пределы `storage`; сам `copy_into` этого не
+    /// проверяет -- проверки границ выполняются в `copy_into_wrapped` через
+    /// модульную арифметику до вызова).
+    unsafe fn copy_into(&self, index: usize, data: &[u8]) {
+        // SAFETY: storage валиден на всё время жизни self (гарантия
+        // конструктора RingBuffer::new); index+data.len() <= capacity --
+        // инвариант вызывающей стороны (см. doc выше).
+        unsafe {
+            let ptr = self.data_ptr().add(index);
+            ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
+        }
+    }
+
+    /// # Safety
+    /// `index + dst.len() <= capacity` (см. `copy_into`).
+    unsafe fn copy_from(&self, index: usize, dst: &mut [MaybeUninit<u8>]) {
+        // SAFETY: storage валиден на всё время жизни self; index+dst.len()
+        // <= capacity -- инвариант вызывающей стороны (см. doc выше).
+        unsafe {
+            let ptr = self.data_ptr().add(index);
+            ptr.copy_to_nonoverlapping(dst.as_mut_ptr().cast::<u8>(), dst.len());
+        }
+    }
+
+    /// # Safety
+    /// `index < capacity` (читает 2 байта начиная с `index`, с wrap-around
+    /// через `copy_from_wrapped`, поэтому сам `index` не обязан оставлять
+    /// место под оба байта без переноса).
+    unsafe fn read_u16(&self, index: usize) -> u16 {
+        let mut buf = [0u8; 2];
+        // SAFETY: copy_from_wrapped сам обеспечивает wrap-around в пределах
+        // capacity -- единственное требование к index описано в doc выше.
+        unsafe { self.copy_from_wrapped(index, as_uninit(&mut buf)) };
+        u16::from_le_bytes(buf)
+    }
+
+    /// # Safety
+    /// `data.len() <= capacity` (иначе один и тот же байт будет записан
+    /// дважды при переносе через границу кольца; вызывающий код -- ring.rs
+    /// сам, всегда после проверки `total_required <= self.capacity` в
+    /// `write_message`).
+    unsafe fn copy_into_wrapped(&self, start: usize, data: &[u8]) {
+        let capacity = self.capacity as usize;
+        let start = start % capacity;
+        let first = capacity - start;
+        if data.len() <= first {
+            // SAFETY: start+data.len() <= capacity -- проверено веткой if.
+            unsafe { self.copy_into(start, data) };
+        } else {
+            // SAFETY: обе части (`first` и остаток) укладываются в
+            // [0, capacity) по построению (start+first == capacity).
+            unsafe { self.copy_into(start, &data[..first]) };
+            unsafe { self.copy_into(0, &data[first..]) };
+        }
+    }
+
+    /// # Safety
+    /// `dst.len() <= capacity` (см. `copy_into_wrapped`).
+    unsafe fn copy_from_wrapped(&self, start: usize, dst: &mut [MaybeUninit<u8>]) {
+        let capacity = self.capacity as usize;
+        let start = start % capacity;
+        let first = capacity - start;
+        if dst.len() <= first {
+            // SAFETY: start+dst.len() <= capacity -- проверено веткой if.
+            unsafe { self.copy_from(start, dst) };
+        } else {
+            // SAFETY: обе части укладываются в [0, capacity) по построению
+            // (start+first == capacity), как и в copy_into_wrapped.
+            unsafe { self.copy_from(start, &mut dst[..first]) };
+            unsafe { self.copy_from(0, &mut dst[first..]) };
+        }
+    }
+
+    /// Поле заголовка ушло с `seen`. CAS на то же значение, а не load: RMW
+    /// видит последнюю запись поля, и слот, который писатель занял после
+    /// чужого изъятия, не примется за порчу по устаревшему снимку.
+    fn moved_from(field: &crate::sync::AtomicU32, seen: u32) -> bool {
+        field
+            .compare_exchange(seen, seen, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+    }
+
+    fn discard_oldest(&self) -> Result<()> {
+        self.discard_head(None).map(|_| ())
+    }
+
+    /// Изымает кадр из головы очереди. С `expired_before` -- только
+    /// просроченный кадр (срок годности раньше этого момента), `Ok(false)`
+    /// -- голова ещё жива; такой кадр считается в `expired_count`, а не в
+    /// `drop_count`.
+    fn discard_head(&self, expired_before: Option<u64>) -> Result<bool> {
+        let header = self.header();
+
+        loop {
+            let read = header.read_pos.load(Ordering::Acquire);
+            let write = header.write_pos.load(Ordering::Acquire);
+            if read == write {
+                return Err(RingError::QueueEmpty);
+            }
+
+            let idx = self.mask_index(read);
+            // SAFETY: idx = read & mask всегда < capacity (mask_index).
+            let msg_len = unsafe { self.read_u16(idx) } as usize;
+            if !(MIN_MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&msg_len) {
+                // Слот мог уже занять писатель после чужого изъятия.
+                if Self::moved_from(&header.read_pos, read) {
+                    continue;
+                }
+                // Повреждённая длина в слоте. Не трогаем общий message_count
+                // деструктивно (его двигает и reader). Сигналим Corrupted —
+                // вызывающий код решает (auto-mode трактует как fatal -> reconnect,
+                // что сбросит буферы через handshake/generation).
+                return Err(RingError::Corrupted);
+            }
+            // SAFETY: (idx + 2) & mask < capacity.
+            let flags = unsafe { self.read_u16((idx + 2) & self.mask()) };
+            if flags & MSG_FLAG_HANDLE != 0 {
+                // Handle уже продублирован в процесс reader'а: вытеснение
+                // означало бы утечку. Писатель получает QueueFull (если
+                // reader не успел забрать кадр, пока мы читали флаги).
+                if Self::moved_from(&header.read_pos, read) {
+                    continue;
+                }
+                return Err(RingError::QueueFull);
+            }
+            let total = Self::frame_size(msg_len, flags);
+            if total as u32 > write.wrapping_sub(read) {
+                if Self::moved_from(&header.read_pos, read)
+                    || Self::moved_from(&header.write_pos, write)
+                {
+                    continue;
+                }
+                // Сообщение заходит за write_pos -- длина в слоте мусорная.
+                return Err(RingError::Corrupted);
+            }
+            if let Some(now) = expired_before {
+                if flags & MSG_FLAG_DEADLINE == 0 {
+                    return Ok(false);
+                }
+                let mut deadline = [0u8; DEADLINE_SIZE];
+                let at = idx + MESSAGE_HEADER_SIZE + msg_len + Self::timestamp_size(flags);
+                // SAFETY: 8 байт, wrap-around внутри copy_from_wrapped.
+                unsafe { self.copy_from_wrapped(at & self.mask(), as_uninit(&mut deadline)) };
+                if u64::from_le_bytes(deadline) >= now {
+                    if header.read_pos.load(Ordering::Acquire) != read {
+                        continue;
+                    }
+                    return Ok(false);
+                }
+            }
+            let new_read = read.wrapping_add(total as u32);
+
+            // CAS to avoid racing with read_message on the reader side
+            if header
+                .read_pos
+                .compare_exchange(read, new_read, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                header.message_count.fetch_sub(1, Ordering::AcqRel);
+                if expired_before.is_some() {
+                    header.expired_count.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    header.drop_count.fetch_add(1, Ordering::Relaxed);
+                }
+                self.record_consumed(total as u32);
+                return Ok(true);
+            }
+            // CAS failed — reader moved read_pos, retry with fresh values
+        }
+    }
+
+    pub fn write_message(&self, payload: &[u8]) -> Result<WriteOutcome> {
+        self.write_frame(payload, 0)
+    }
+
+    /// Включает кредитный режим (вызывает читатель кольца): writer пишет,
+    /// только пока в кольце меньше `messages` непрочитанных кадров и
+    /// `bytes` байт, иначе получает `QueueFull`; старые кадры никогда не
+    /// вытесняются. `messages == 0` выключает режим. Окно не больше
+    /// `MAX_MESSAGES`/ёмкости кольца -- иначе кредиты не гарантируют места.
+    pub fn set_credit_window(&self, messages: u32, bytes: u32) {
+        let header = self.header();
+        header
+            .credit_window_bytes
+            .store(bytes.min(self.capacity), Ordering::Relaxed);
+        header
+            .credit_window_msgs
+            .store(messages.min(MAX_MESSAGES), Ordering::Release);
+    }
+
+    /// Окно кредитного режима `(messages, bytes)`; `None` -- режим выключен.
+    pub fn credit_window(&self) -> Option<(u32, u32)> {
+        let header = self.header();
+        let messages = header.credit_window_msgs.load(Ordering::Acquire);
+        (messages != 0).then(|| (messages, header.credit_window_bytes.load(Ordering::Relaxed)))
+    }
+
+    /// Свободные кредиты writer'а `(messages, bytes)`; `None` -- режим
+    /// выключен.
+    pub fn available_credits(&self) -> Option<(u32, u32)> {
+        let (window_msgs, window_bytes) = self.credit_window()?;
+        let header = self.header();
+        let in_flight_msgs = header
+            .sent_msgs
+            .load(Ordering::SeqCst)
+            .wrapping_sub(header.consumed_msgs.load(Ordering::SeqCst));
+        let in_flight_bytes = header
+            .sent_bytes
+            .load(Ordering::SeqCst)
+            .wrapping_sub(header.consumed_bytes.load(Ordering::SeqCst));
+        Some((
+            window_msgs.saturating_sub(in_flight_msgs),
+            window_bytes.saturating_sub(in_flight_bytes),
+        ))
+    }
+
+    /// Хватает ли кредитов на кадр `frame` байт. Перед отказом writer
+    /// выставляет `credit_stalled` и перепроверяет: SeqCst с обеих сторон
+    /// гарантирует, что читатель либо увидит флаг, либо writer -- его
+    /// изъятие (без потерянного пробуждения).
+    fn has_credit(&self, frame: u32) -> bool {
+        self.has_credits(1, frame)
+    }
+
+    /// Как [`has_credit`](Self::has_credit), но на `frames` кадров общим
+    /// размером `bytes` байт.
+    fn has_credits(&self, frames: u32, bytes: u32) -> bool {
+        let fits = |ring: &Self| {
+            ring.available_credits()
+                .is_none_or(|(free_msgs, free_bytes)| free_msgs >= frames && free_bytes >= bytes)
+        };
+        if fits(self) {
+            return true;
+        }
+        self.header().credit_stalled.store(1, Ordering::SeqCst);
+        crate::sync::fence(Ordering::SeqCst);
+        fits(self)
+    }
+
+    /// Сбрасывает `credit_stalled` (вызывает читатель после изъятия кадра);
+    /// `true` -- writer ждёт кредитов и его надо разбудить space-событием.
+    pub fn take_credit_stall(&self) -> bool {
+        let header = self.header();
+        // Пара барьеру writer'а после флага: изъятие раньше чтения флага.
+        crate::sync::fence(Ordering::SeqCst);
+        header.credit_stalled.load(Ordering::SeqCst) != 0
+            && header.credit_stalled.swap(0, Ordering::SeqCst) != 0
+    }
+
+    fn record_consumed(&self, frame: u32) {
+        let header = self.header();
+        header.consumed_bytes.fetch_add(frame, Ordering::SeqCst);
+        header.consumed_msgs.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Флаги заголовка кадра: библиотечные `extra_flags` плюс трейлеры
+    /// по настройке кольца и переданным меткам.
+    fn frame_flags(&self, extra_flags: u16, timestamp: Option<u64>, deadline: Option<u64>) -> u16 {
+        let mut flags = extra_flags;
+        if self.checksum.load(Ordering::Relaxed) {
+            flags |= MSG_FLAG_CHECKSUM;
+        }
+        if timestamp.is_some() {
+            flags |= MSG_FLAG_TIMESTAMP;
+        }
+        if deadline.is_some() {
+            flags |= MSG_FLAG_DEADLINE;
+        }
+        if self.sequence_numbers.load(Ordering::Relaxed) {
+            flags |= MSG_FLAG_SEQUENCE;
+        }
+        flags
+    }
+
+    /// Номер следующего кадра: `sent_msgs` растёт только при публикациях,
+    /// а публикует в каждый момент один писатель (единственный или тот,
+    /// чья очередь в [`await_turn`](Self::await_turn)).
+    fn next_sequence(&self) -> u32 {
+        self.header().sent_msgs.load(Ordering::Relaxed)
+    }
+
+    /// Копирует payload кадра в кольцо с позиции `pos`; заголовок и
+    /// трейлеры дописывает [`seal_frame`](Self::seal_frame), позиции не
+    /// двигаются.
+    ///
+    /// # Safety
+    /// `frame_size(payload.len(), flags)` байт с `pos` (с переходом через
+    /// конец) -- свободная часть кольца, которую reader не читает и не
+    /// пишет другой писатель.
+    unsafe fn store_payload(&self, pos: u32, payload: &[u8]) {
+        let idx = self.mask_index(pos);
+        // SAFETY: payload -- не более MAX_MESSAGE_SIZE < capacity байт
+        // свободной части кольца (контракт вызывающего).
+        unsafe { self.copy_into_wrapped((idx + MESSAGE_HEADER_SIZE) & self.mask(), payload) };
+    }
+
+    /// Дописывает к уже лежащему в кольце payload'у заголовок с меткой
+    /// фиксации и трейлеры (CRC -- по payload'у в кольце).
+    ///
+    /// Флаги с меткой пишутся последними, после Release-барьера: reader
+    /// публикует кадр за писателя по одной метке (см.
+    /// [`read_frame_info_uninit`](Self::read_frame_info_uninit)), и к этому
+    /// моменту длина, трейлеры и CRC уже должны лежать в кольце -- иначе в
+    /// MPSC он опубликовал бы кадр соседа, который ещё запечатывается.
+    ///
+    /// # Safety
+    /// Как у [`store_payload`](Self::store_payload) для `len` байт
+    /// payload'а; payload уже записан.
+    unsafe fn seal_frame(
+        &self,
+        pos: u32,
+        len: usize,
+        flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+        sequence: u32,
+    ) {
+        let idx = self.mask_index(pos);
+        let flags = flags | self.commit_stamp(pos);
+        let len_le = (len as u16).to_le_bytes();
+        let flags_le = flags.to_le_bytes();
+        let timestamp_le = timestamp.unwrap_or(0).to_le_bytes();
+        let timestamp_le = &timestamp_le[..Self::timestamp_size(flags)];
+        let deadline_le = deadline.unwrap_or(0).to_le_bytes();
+        let deadline_le = &deadline_le[..Self::deadline_size(flags)];
+        let sequence_le = sequence.to_le_bytes();
+        let sequence_le = &sequence_le[..Self::sequence_size(flags)];
+        // SAFETY: каждый вызов copy_into_wrapped пишет <= capacity байт
+        // (len_le/flags -- по 2 байта, трейлеры -- 8, 8, 4 и 4 байта, а весь
+        // кадр по контракту помещается в свободную часть кольца).
+        unsafe {
+            self.copy_into_wrapped(idx, &len_le);
+            let trailer = idx + MESSAGE_HEADER_SIZE + len;
+            self.copy_into_wrapped(trailer & self.mask(), timestamp_le);
+            let trailer = trailer + timestamp_le.len();
+            self.copy_into_wrapped(trailer & self.mask(), deadline_le);
+            let trailer = trailer + deadline_le.len();
+            self.copy_into_wrapped(trailer & self.mask(), sequence_le);
+            if flags & MSG_FLAG_CHECKSUM != 0 {
+                let (head, tail) =
+                    self.payload_parts((idx + MESSAGE_HEADER_SIZE) & self.mask(), len);
+                let crc = Crc32::new()
+                    .update(&len_le)
+                    .update(&flags_le)
+                    .update(head)
+                    .update(tail)
+                    .update(timestamp_le)
+                    .update(deadline_le)
+                    .update(sequence_le)
+                    .finish();
+                self.copy_into_wrapped(
+                    (trailer + sequence_le.len()) & self.mask(),
+                    &crc.to_le_bytes(),
+                );
+            }
+            crate::sync::preemption_point(&self.header().write_pos);
+            crate::sync::fence(Ordering::Release);
+            self.copy_into_wrapped((idx + 2) & self.mask(), &flags_le);
+        }
+    }
+
+    /// `len` байт кольца с индекса `start`: до конца кольца и остаток с
+    /// начала (пустой, если перехода нет).
+    ///
+    /// # Safety
+    /// `start < capacity`, `len <= capacity`; пока срезы живы, эти байты
+    /// никто не читает и не пишет в обход них.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn payload_parts(&self, start: usize, len: usize) -> (&mut [u8], &mut [u8]) {
+        let first = len.min(self.capacity as usize - start);
+        // SAFETY: [start, start+first) и [0, len-first) -- внутри кольца и
+        // не пересекаются (len <= capacity); storage живёт дольше self.
+        unsafe {
+            (
+                core::slice::from_raw_parts_mut(self.data_ptr().add(start), first),
+                core::slice::from_raw_parts_mut(self.data_ptr(), len - first),
+            )
+        }
+    }
+
+    /// Сдвигает `write_pos` с `from` на `to`. Reader мог уже сам
+    /// опубликовать часть записанных кадров (см.
+    /// [`read_frame_info_uninit`](Self::read_frame_info_uninit)) -- тогда
+    /// двигаем с того места, докуда дошёл он.
+    fn publish(&self, from: u32, to: u32) {
+        let header = self.header();
+        let mut current = from;
+        while let Err(actual) =
+            header
+                .write_pos
+                .compare_exchange(current, to, Ordering::AcqRel, Ordering::Acquire)
+        {
+            if actual.wrapping_sub(from) >= to.wrapping_sub(from) {
+                return;
+            }
+            current = actual;
+        }
+    }
+
+    /// Запись с библиотечными флагами заголовка (`MSG_FLAG_HANDLE`);
+    /// `MSG_FLAG_CHECKSUM` добавляется по настройке кольца.
+    pub fn write_frame(&self, payload: &[u8], extra_flags: u16) -> Result<WriteOutcome> {
+        self.write_frame_stamped(payload, extra_flags, None)
+    }
+
+    /// Как [`write_frame`](Self::write_frame), плюс метка времени записи
+    /// `timestamp` (`MSG_FLAG_TIMESTAMP`). Часов у no_std кольца нет --
+    /// метку даёт вызывающий.
+    pub fn write_frame_stamped(
+        &self,
+        payload: &[u8],
+        extra_flags: u16,
+        timestamp: Option<u64>,
+    ) -> Result<WriteOutcome> {
+        self.write_frame_timed(payload, extra_flags, timestamp, None)
+    }
+
+    /// Как [`write_frame_stamped`](Self::write_frame_stamped), плюс срок
+    /// годности `deadline` (`MSG_FLAG_DEADLINE`) в той же шкале времени.
+    pub fn write_frame_timed(
+        &self,
+        payload: &[u8],
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+    ) -> Result<WriteOutcome> {
+        self.write_frame_evicting(payload, extra_flags, timestamp, deadline, true)
+    }
+
+    /// Как [`write_frame_timed`](Self::write_frame_timed), но никогда не
+    /// вытесняет непрочитанное, даже с включённым вытеснением: не хватает
+    /// места -- `QueueFull`, и ничего не записано.
+    pub fn try_write_frame_timed(
+        &self,
+        payload: &[u8],
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+    ) -> Result<WriteOutcome> {
+        self.write_frame_evicting(payload, extra_flags, timestamp, deadline, false)
+    }
+
+    fn write_frame_evicting(
+        &self,
+        payload: &[u8],
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+        evict: bool,
+    ) -> Result<WriteOutcome> {
+        let (claim, flags) = self.claim(
+            payload.len(),
+            extra_flags,
+            timestamp,
+            deadline,
+            evict,
+            self.is_multi_producer(),
+        )?;
+        // SAFETY: claim занял frame_size(len, flags) свободных байт с
+        // claim.pos -- кадр целиком в свободной части кольца, и другие
+        // писатели туда не пишут.
+        unsafe { self.store_payload(claim.pos, payload) };
+        self.await_turn(&claim, 1)?;
+        // SAFETY: то же место; payload записан.
+        unsafe {
+            self.seal_frame(
+                claim.pos,
+                payload.len(),
+                flags,
+                timestamp,
+                deadline,
+                self.next_sequence(),
+            )
+        };
+        let outcome = self.publish_frame(claim.pos, payload.len(), flags, claim.overwritten);
+        self.release_claim(&claim, 1);
+        Ok(outcome)
+    }
+
+    /// Занимает место под кадр с payload'ом длины `len`, не записывая его
+    /// (см. [`Reservation`]). Место освобождается так же, как в
+    /// [`write_frame_timed`](Self::write_frame_timed): без кредитного окна
+    /// старые кадры вытесняются сразу, и это не отменяется брошенной
+    /// резервацией. Метка времени и срок годности -- на момент `reserve`.
+    ///
+    /// Писатель один: пока резервация не зафиксирована, других записей в
+    /// кольцо быть не должно, в том числе в режиме нескольких писателей.
+    pub fn reserve(
+        &self,
+        len: usize,
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+    ) -> Result<Reservation> {
+        let (claim, flags) = self.claim(len, extra_flags, timestamp, deadline, true, false)?;
+        Ok(Reservation {
+            pos: claim.pos,
+            len,
+            flags,
+            timestamp,
+            deadline,
+            overwritten: claim.overwritten,
+        })
+    }
+
+    /// Payload резервации прямо в кольце: до конца кольца и остаток с его
+    /// начала (пустой, если payload не переходит через конец). Содержимое
+    /// не обнулено.
+    ///
+    /// # Safety
+    /// `reservation` -- от этого кольца; пока срезы живы, в кольцо не
+    /// пишут ни `write_*`, ни `reserve`/`commit`.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn reserved_payload(&self, reservation: &Reservation) -> (&mut [u8], &mut [u8]) {
+        let start = (self.mask_index(reservation.pos) + MESSAGE_HEADER_SIZE) & self.mask();
+        // SAFETY: claim отдал это место писателю; остальное -- контракт
+        // вызывающего.
+        unsafe { self.payload_parts(start, reservation.len) }
+    }
+
+    /// Дописывает заголовок и трейлеры к заполненному payload'у резервации
+    /// и публикует кадр.
+    pub fn commit(&self, reservation: Reservation) -> WriteOutcome {
+        let Reservation {
+            pos,
+            len,
+            flags,
+            timestamp,
+            deadline,
+            overwritten,
+        } = reservation;
+        // SAFETY: место занято claim и с тех пор за write_pos никто не
+        // писал (писатель один); payload заполнен через reserved_payload.
+        unsafe { self.seal_frame(pos, len, flags, timestamp, deadline, self.next_sequence()) };
+        self.publish_frame(pos, len, flags, overwritten)
+    }
+
+    /// Проверяет длину и освобождает место под кадр за `write_pos`
+    /// (вытесняя старые кадры, если нет кредитного окна). Возвращает
+    /// занятое место и флаги кадра; `shared` -- занять его наравне с
+    /// другими писателями (см. [`claim_space`](Self::claim_space)).
+    fn claim(
+        &self,
+        len: usize,
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+        evict: bool,
+        shared: bool,
+    ) -> Result<(Claim, u16)> {
+        if len < MIN_MESSAGE_SIZE {
+            return Err(RingError::MessageTooSmall);
+        }
+        if len > MAX_MESSAGE_SIZE {
+            return Err(RingError::MessageTooLarge);
+        }
+
+        let flags = self.frame_flags(extra_flags, timestamp, deadline);
+        let total_required = Self::frame_size(len, flags) as u32;
+        if total_required > self.capacity {
+            return Err(RingError::MessageTooLarge);
+        }
+
+        if !self.has_credit(total_required) {
+            return Err(RingError::QueueFull);
+        }
+
+        let claim = self.claim_space(total_required, 1, evict, shared)?;
+        Ok((claim, flags))
+    }
+
+    /// Освобождает `total_required` байт под `frames` кадров подряд.
+    /// `evict` -- вытеснять старые кадры (если нет кредитного окна и
+    /// вытеснение включено), иначе нехватка места -- `QueueFull`.
+    ///
+    /// `shared` -- место занимается CAS'ом по курсору `claims` за концом
+    /// уже занятого другими писателями, а не прямо за `write_pos`; такие
+    /// кадры публикуются после [`await_turn`](Self::await_turn), а
+    /// [`release_claim`](Self::release_claim) снимает их с курсора.
+    fn claim_space(
+        &self,
+        total_required: u32,
+        frames: u32,
+        evict: bool,
+        shared: bool,
+    ) -> Result<Claim> {
+        let header = self.header();
+        let mut overwritten = 0u32;
+        let mut stale_retries = 0u32;
+        let mut stalled = false;
+        let mut spins = 0u32;
+
+        loop {
+            // Курсор читается до write_pos: пока занятых кадров нет,
+            // write_pos не сдвинется раньше нашего CAS по курсору.
+            let generation = header.connection_gen.load(Ordering::Acquire) as u16;
+            let cursor = if shared {
+                self.claims.load(Ordering::Acquire)
+            } else {
+                0
+            };
+            let (claimed_end, in_flight) = Self::unpack_claims(cursor, generation);
+            let write = if in_flight == 0 {
+                header.write_pos.load(Ordering::Acquire)
+            } else {
+                claimed_end
+            };
+            let read = header.read_pos.load(Ordering::Acquire);
+            let available = self.available_bytes(write, read);
+            let count = header
+                .message_count
+                .load(Ordering::Acquire)
+                .saturating_add(in_flight);
+
+            // Снимок позиций устарел: курсор, write_pos или read_pos уже
+            // сдвинули (загрузки друг с другом не упорядочены).
+            let stale = || {
+                Self::moved_from(&header.read_pos, read)
+                    || (in_flight == 0 && Self::moved_from(&header.write_pos, write))
+                    || (shared
+                        && self
+                            .claims
+                            .compare_exchange(cursor, cursor, Ordering::AcqRel, Ordering::Acquire)
+                            .is_err())
+            };
+
+            // read_pos только догоняет write_pos: «занято больше ёмкости»
+            // бывает лишь при устаревшем снимке или мусоре в заголовке
+            // (враждебный/упавший пир).
+            if available < 0 {
+                if stale() {
+                    continue;
+                }
+                return Err(RingError::Corrupted);
+            }
+
+            if available < total_required as i64 || count.saturating_add(frames) > MAX_MESSAGES {
+                if count == 0 {
+                    // Reader мог освободить кольцо между загрузками read_pos и
+                    // message_count -- тогда это устаревший снимок, повторяем.
+                    if stale() || Self::moved_from(&header.message_count, 0) {
+                        continue;
+                    }
+                    // нет сообщений, но не хватает места — значит сообщение больше буфера
+                    return Err(RingError::MessageTooLarge);
+                }
+                if !evict || self.credit_window().is_some() || !self.evicts() {
+                    // Кредитный режим не вытесняет никогда, даже если окно
+                    // не уберегло от нехватки места (окно сменили на лету).
+                    if stalled {
+                        return Err(RingError::QueueFull);
+                    }
+                    // Как в has_credits: флаг, барьер, перепроверка --
+                    // читатель либо увидит флаг, либо мы -- изъятие.
+                    header.credit_stalled.store(1, Ordering::SeqCst);
+                    crate::sync::fence(Ordering::SeqCst);
+                    stalled = true;
+                    continue;
+                }
+                match self.discard_oldest() {
+                    Ok(()) => overwritten += 1,
+                    // Место держат ещё не опубликованные кадры других
+                    // писателей: вытеснить их можно будет после публикации.
+                    Err(RingError::QueueEmpty) if in_flight != 0 => {
+                        if !stale() {
+                            self.pause_for_turn(&mut spins);
+                        }
+                    }
+                    // Reader успел дочитать всё сам: место уже свободно (его
+                    // fetch_sub по message_count вот-вот станет виден). Если
+                    // счётчик так и не сходится с позициями -- заголовок битый.
+                    Err(RingError::QueueEmpty) => {
+                        stale_retries += 1;
+                        if stale_retries > STALE_RETRY_LIMIT {
+                            return Err(RingError::Corrupted);
+                        }
+                        crate::sync::spin_loop();
+                    }
+                    Err(err) => return Err(err),
+                }
+                continue;
+            }
+
+            // total_required <= available -- кадры целиком в свободной
+            // части кольца за write (write_pos или концом занятого места).
+            if shared {
+                let next = Self::pack_claims(
+                    write.wrapping_add(total_required),
+                    in_flight + frames,
+                    generation,
+                );
+                if self
+                    .claims
+                    .compare_exchange(cursor, next, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    continue;
+                }
+            }
+            return Ok(Claim {
+                pos: write,
+                overwritten,
+                shared: shared.then_some(generation),
+            });
+        }
+    }
+
+    fn pack_claims(end: u32, in_flight: u32, generation: u16) -> u64 {
+        u64::from(end) | u64::from(in_flight) << 32 | u64::from(generation) << 48
+    }
+
+    /// Конец занятого места и число занятых кадров по курсору писателей.
+    /// Курсор прошлого поколения (кольцо сброшено при переподключении)
+    /// считается пустым: его кадры уже не будут опубликованы.
+    fn unpack_claims(cursor: u64, generation: u16) -> (u32, u32) {
+        if (cursor >> 48) as u16 != generation {
+            return (0, 0);
+        }
+        (cursor as u32, u32::from((cursor >> 32) as u16))
+    }
+
+    /// Ждёт, пока писатели, занявшие место раньше, опубликуют свои кадры:
+    /// `write_pos` дойдёт до `claim.pos`. Если кольцо тем временем сброшено
+    /// (сменилось поколение) -- `Corrupted`, и `frames` кадров снимаются с
+    /// курсора.
+    fn await_turn(&self, claim: &Claim, frames: u32) -> Result<()> {
+        let Some(generation) = claim.shared else {
+            return Ok(());
+        };
+        let header = self.header();
+        let mut spins = 0u32;
+        while header.write_pos.load(Ordering::Acquire) != claim.pos {
+            if header.connection_gen.load(Ordering::Acquire) as u16 != generation {
+                self.release_claim(claim, frames);
+                return Err(RingError::Corrupted);
+            }
+            self.pause_for_turn(&mut spins);
+        }
+        Ok(())
+    }
+
+    fn pause_for_turn(&self, spins: &mut u32) {
+        if *spins < TURN_SPIN_LIMIT {
+            *spins += 1;
+            crate::sync::spin_loop();
+        } else {
+            (self.turn_backoff)();
+        }
+    }
+
+    /// Снимает `frames` кадров `claim` с курсора писателей после
+    /// публикации.
+    fn release_claim(&self, claim: &Claim, frames: u32) {
+        let Some(generation) = claim.shared else {
+            return;
+        };
+        let mut cursor = self.claims.load(Ordering::Acquire);
+        // Курсор следующего поколения уже не про наши кадры.
+        while (cursor >> 48) as u16 == generation {
+            let next = cursor - (u64::from(frames) << 32);
+            match self
+                .claims
+                .compare_exchange(cursor, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return,
+                Err(actual) => cursor = actual,
+            }
+        }
+    }
+
+    /// Публикует записанный с `write` кадр.
+    fn publish_frame(&self, write: u32, len: usize, flags: u16, overwritten: u32) -> WriteOutcome {
+        let header = self.header();
+        let total_required = Self::frame_size(len, flags) as u32;
+
+        // ВАЖНО: сначала увеличиваем message_count, потом обновляем write_pos
+        // Это гарантирует, что reader увидит count > 0 когда видит новый write_pos
+        // (release-запись счётчика идёт раньше release-CAS позиции)
+        header
+            .sent_bytes
+            .fetch_add(total_required, Ordering::SeqCst);
+        header.sent_msgs.fetch_add(1, Ordering::SeqCst);
+        let prev_count = header.message_count.fetch_add(1, Ordering::AcqRel);
+
+        self.publish(write, write.wrapping_add(total_required));
+        self.note_occupancy(write.wrapping_add(total_required), prev_count + 1);
+
+        if prev_count == 0 {
+            header.sequence.fetch_add(1, Ordering::Relaxed);
+        }
+
+        WriteOutcome {
+            overwritten,
+            was_empty: prev_count == 0,
+            free_bytes_after: self.free_bytes(),
+            queue_len_after: prev_count + 1,
+        }
+    }
+
+    /// Пишет `payloads` одной публикацией `write_pos`: читатель видит либо
+    /// все кадры пачки, либо ни одного. Метка времени и срок годности --
+    /// общие на пачку.
+    ///
+    /// Пачка не вытесняет старые кадры: не хватает свободного места или
+    /// кредитов -- `QueueFull`, и ничего не записано. Пачка больше пустого
+    /// кольца (или длиннее `MAX_MESSAGES`) -- `MessageTooLarge`. Пустая
+    /// пачка -- `Ok` без записи.
+    pub fn write_batch(
+        &self,
+        payloads: &[&[u8]],
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+    ) -> Result<WriteOutcome> {
+        self.write_batch_flagged(payloads, 0, timestamp, deadline)
+    }
+
+    /// [`write_batch`](Self::write_batch) с библиотечными флагами заголовка
+    /// (`MSG_FLAG_CHUNK`) на каждом кадре пачки.
+    pub fn write_batch_flagged(
+        &self,
+        payloads: &[&[u8]],
+        extra_flags: u16,
+        timestamp: Option<u64>,
+        deadline: Option<u64>,
+    ) -> Result<WriteOutcome> {
+        if payloads.is_empty() {
+            return Ok(WriteOutcome {
+                overwritten: 0,
+                was_empty: false,
+                free_bytes_after: self.free_bytes(),
+                queue_len_after: self.message_count(),
+            });
+        }
+        let flags = self.frame_flags(extra_flags, timestamp, deadline);
+        let mut total_required = 0usize;
+        for payload in payloads {
+            if payload.len() < MIN_MESSAGE_SIZE {
+                return Err(RingError::MessageTooSmall);
+            }
+            if payload.len() > MAX_MESSAGE_SIZE {
+                return Err(RingError::MessageTooLarge);
+            }
+            total_required += Self::frame_size(payload.len(), flags);
+        }
+        if total_required > self.capacity as usize || payloads.len() > MAX_MESSAGES as usize {
+            return Err(RingError::MessageTooLarge);
+        }
+        let total_required = total_required as u32;
+        let frames = payloads.len() as u32;
+
+        if !self.has_credits(frames, total_required) {
+            return Err(RingError::QueueFull);
+        }
+
+        let claim = self.claim_space(total_required, frames, false, self.is_multi_producer())?;
+        let write = claim.pos;
+        let mut pos = write;
+        for payload in payloads {
+            // SAFETY: вся пачка (total_required байт) занята claim_space,
+            // кадры идут подряд.
+            unsafe { self.store_payload(pos, payload) };
+            pos = pos.wrapping_add(Self::frame_size(payload.len(), flags) as u32);
+        }
+        self.await_turn(&claim, frames)?;
+        let mut pos = write;
+        let first_sequence = self.next_sequence();
+        for (i, payload) in payloads.iter().enumerate() {
+            let sequence = first_sequence.wrapping_add(i as u32);
+            // SAFETY: то же место; payload'ы записаны.
+            unsafe { self.seal_frame(pos, payload.len(), flags, timestamp, deadline, sequence) };
+            pos = pos.wrapping_add(Self::frame_size(payload.len(), flags) as u32);
+        }
+
+        let header = self.header();
+
+        // Как в write_frame_timed: счётчик раньше позиции, позиция -- одна
+        // на всю пачку.
+        header
+            .sent_bytes
+            .fetch_add(total_required, Ordering::SeqCst);
+        header.sent_msgs.fetch_add(frames, Ordering::SeqCst);
+        let prev_count = header.message_count.fetch_add(frames, Ordering::AcqRel);
+        self.publish(write, pos);
+        self.note_occupancy(pos, prev_count + frames);
+        self.release_claim(&claim, frames);
+
+        if prev_count == 0 {
+            header.sequence.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Ok(WriteOutcome {
+            overwritten: 0,
+            was_empty: prev_count == 0,
+            free_bytes_after: self.free_bytes(),
+            queue_len_after: prev_count + frames,
+        })
+    }
+
+    /// Чтение payload'а в `out`; возвращает его длину.
+    pub fn read_message(&self, out: &mut [u8]) -> Result<usize> {
+        self.read_frame(out).map(|(len, _)| len)
+    }
+
+    /// Чтение вместе с флагами заголовка (для различения handle-кадров).
+    /// Буфер на `MAX_MESSAGE_SIZE` байт вмещает любой кадр; меньший --
+    /// `RingError::BufferTooSmall`, кадр остаётся в кольце.
+    pub fn read_frame(&self, out: &mut [u8]) -> Result<(usize, u16)> {
+        self.read_frame_uninit(as_uninit(out))
+    }
+
+    /// [`read_frame`](Self::read_frame) в неинициализированный буфер
+    /// (например, `Vec::spare_capacity_mut`): при `Ok((len, _))` первые
+    /// `len` байт `out` инициализированы.
+    pub fn read_frame_uninit(&self, out: &mut [MaybeUninit<u8>]) -> Result<(usize, u16)> {
+        self.read_frame_info_uninit(out)
+            .map(|frame| (frame.len, frame.flags))
+    }
+
+    /// [`read_frame_uninit`](Self::read_frame_uninit) вместе с меткой
+    /// времени записи кадра.
+    pub fn read_frame_info_uninit(&self, out: &mut [MaybeUninit<u8>]) -> Result<FrameInfo> {
+        // Срок годности здесь не проверяется: «сейчас» -- 0.
+        self.read_frame_using(out, false, || 0, |_| false, |_, _| ())
+            .map(|((), frame)| frame)
+    }
+
+    /// [`read_live_frame_uninit`](Self::read_live_frame_uninit) без копии:
+    /// `f` получает payload и флаги прямо из кольца. Кадр, переходящий
+    /// через конец кольца, сначала копируется в `scratch` (мал --
+    /// `RingError::BufferTooSmall`, кадр остаётся в кольце).
+    ///
+    /// `f` вызывается ДО фиксации чтения: если писатель вытеснил кадр во
+    /// время вызова, результат отбрасывается и `f` зовётся снова со
+    /// следующим кадром. Возвращается результат того вызова, чей кадр
+    /// забран.
+    pub fn read_live_frame_with<T>(
+        &self,
+        scratch: &mut [MaybeUninit<u8>],
+        now: impl FnMut() -> u64,
+        f: impl FnMut(&[u8], u16) -> T,
+    ) -> Result<(T, FrameInfo)> {
+        self.read_frame_using(scratch, true, now, |_| false, f)
+    }
+
+    /// [`read_live_frame_with`](Self::read_live_frame_with) без изъятия:
+    /// кадр, для флагов которого `keep` вернул `true`, остаётся в кольце
+    /// (`read_pos` не двигается), остальные забираются как при чтении.
+    /// Битые и просроченные кадры забираются всегда, до `f` не доходя.
+    pub fn peek_live_frame_with<T>(
+        &self,
+        scratch: &mut [MaybeUninit<u8>],
+        now: impl FnMut() -> u64,
+        keep: impl FnMut(u16) -> bool,
+        f: impl FnMut(&[u8], u16) -> T,
+    ) -> Result<(T, FrameInfo)> {
+        self.read_frame_using(scratch, true, now, keep, f)
+    }
+
+    /// Общий цикл чтения. `borrow` -- отдавать `f` срез кольца, когда кадр
+    /// не переходит через конец; иначе payload копируется в `scratch`.
+    /// Кадры со сроком раньше `now()` забираются без вызова `f`; целый
+    /// кадр, для флагов которого `keep` вернул `true`, остаётся в кольце.
+    fn read_frame_using<T>(
+        &self,
+        scratch: &mut [MaybeUninit<u8>],
+        borrow: bool,
+        mut now: impl FnMut() -> u64,
+        mut keep: impl FnMut(u16) -> bool,
+        mut f: impl FnMut(&[u8], u16) -> T,
+    ) -> Result<(T, FrameInfo)> {
+        let header = self.header();
+
+        loop {
+            let count = header.message_count.load(Ordering::Acquire);
+            if count == 0 {
+                return Err(RingError::QueueEmpty);
+            }
+
+            let read = header.read_pos.load(Ordering::Acquire);
+            let write = header.write_pos.load(Ordering::Acquire);
+            // Счётчик > 0, а позиции равны: писатель учёл кадр в
+            // message_count, но ещё не сдвинул write_pos (или умер между ними).
+            let unpublished = read == write;
+            let idx = self.mask_index(read);
+            // Флаги -- до длины: метку seal_frame пишет последней, и
+            // увиденная метка гарантирует дописанные длину, трейлеры и CRC.
+            // SAFETY: (idx + 2) & mask < capacity.
+            let flags = unsafe { self.read_u16((idx + 2) & self.mask()) };
+            crate::sync::fence(Ordering::Acquire);
+            // SAFETY: idx = read & mask всегда < capacity (mask_index).
+            let msg_len = unsafe { self.read_u16(idx) } as usize;
+            let total = Self::frame_size(msg_len, flags);
+            // Кадр больше кольца (copy_from_wrapped требует len <= capacity)
+            // при штатной ёмкости недостижим, под loom (64 байта) -- та же
+            // гонка перезаписи, что и мусорная длина.
+            if !(MIN_MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&msg_len)
+                || total > self.capacity as usize
+                || !self.stamp_matches(flags, read)
+            {
+                // Заголовок мог быть «порван» перезаписью producer-а. Если
+                // read_pos уже сдвинулся — это гонка перезаписи, повторяем.
+                // За write_pos -- недописанный кадр, иначе буфер действительно
+                // повреждён.
+                if Self::moved_from(&header.read_pos, read) {
+                    continue;
+                }
+                if unpublished {
+                    self.note_torn(read, write);
+                    return Err(RingError::QueueEmpty);
+                }
+                return Err(RingError::Corrupted);
+            }
+            if unpublished {
+                // Кадр без метки проверить нечем -- ждём, пока писатель
+                // сдвинет write_pos сам.
+                if flags & MSG_FLAG_COMMIT == 0 {
+                    return Err(RingError::QueueEmpty);
+                }
+                // Метка этого круга ставится последней (seal_frame), так что
+                // кадр дописан целиком, даже если message_count > 0 из-за
+                // чужого кадра (в MPSC вытесняющий писатель сдвигает read_pos
+                // раньше, чем уменьшает счётчик), -- публикуем его за
+                // писателя. Живой писатель затем запишет в write_pos то же
+                // значение.
+                let _ = header.write_pos.compare_exchange(
+                    write,
+                    read.wrapping_add(total as u32),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+                continue;
+            }
+            if total as u32 > write.wrapping_sub(read) {
+                // Кадр заходит за write_pos -- длина в слоте мусорная, если
+                // позиции за это время не сдвинулись.
+                if Self::moved_from(&header.read_pos, read)
+                    || Self::moved_from(&header.write_pos, write)
+                {
+                    continue;
+                }
+                return Err(RingError::Corrupted);
+            }
+            let start = (idx + MESSAGE_HEADER_SIZE) & self.mask();
+            let in_place = borrow && start + msg_len <= self.capacity as usize;
+            if !in_place && scratch.len() < msg_len {
+                if header.read_pos.load(Ordering::Acquire) != read {
+                    continue;
+                }
+                return Err(RingError::BufferTooSmall { required: msg_len });
+            }
+            let new_read = read.wrapping_add(total as u32);
+
+            // ОПТИМИСТИЧНОЕ чтение ДО фиксации read_pos (seqlock-паттерн).
+            // Если producer перезапишет слот, пока мы копируем или пока `f`
+            // смотрит в кольцо, CAS ниже провалится, и прочитанное
+            // (потенциально битое) отбрасывается.
+            let payload: &[u8] = if in_place {
+                // SAFETY: start + msg_len <= capacity проверено выше;
+                // storage валиден на всё время жизни self.
+                unsafe { core::slice::from_raw_parts(self.data_ptr().add(start), msg_len) }
+            } else {
+                // SAFETY: scratch.len() >= msg_len проверено выше;
+                // copy_from_wrapped читает строго в пределах кольца (wrap по
+                // модулю capacity) и инициализирует первые msg_len байт.
+                unsafe {
+                    self.copy_from_wrapped(start, &mut scratch[..msg_len]);
+                    core::slice::from_raw_parts(scratch.as_ptr().cast::<u8>(), msg_len)
+                }
+            };
+            let trailer = idx + MESSAGE_HEADER_SIZE + msg_len;
+            let mut stored_timestamp = [0u8; TIMESTAMP_SIZE];
+            let timestamp_le = &mut stored_timestamp[..Self::timestamp_size(flags)];
+            // SAFETY: 0 или 8 байт, wrap-around внутри copy_from_wrapped.
+            unsafe { self.copy_from_wrapped(trailer & self.mask(), as_uninit(timestamp_le)) };
+            let trailer = trailer + timestamp_le.len();
+            let mut stored_deadline = [0u8; DEADLINE_SIZE];
+            let deadline_le = &mut stored_deadline[..Self::deadline_size(flags)];
+            // SAFETY: 0 или 8 байт, wrap-around внутри copy_from_wrapped.
+            unsafe { self.copy_from_wrapped(trailer & self.mask(), as_uninit(deadline_le)) };
+            let trailer = trailer + deadline_le.len();
+            let mut stored_sequence = [0u8; SEQUENCE_SIZE];
+            let sequence_le = &mut stored_sequence[..Self::sequence_size(flags)];
+            // SAFETY: 0 или 4 байта, wrap-around внутри copy_from_wrapped.
+            unsafe { self.copy_from_wrapped(trailer & self.mask(), as_uninit(sequence_le)) };
+            let mut stored_crc = [0u8; CHECKSUM_SIZE];
+            if flags & MSG_FLAG_CHECKSUM != 0 {
+                // SAFETY: 4 байта, wrap-around внутри copy_from_wrapped.
+                unsafe {
+                    self.copy_from_wrapped(
+                        (trailer + sequence_le.len()) & self.mask(),
+                        as_uninit(&mut stored_crc),
+                    );
+                }
+            }
+            let frame = FrameInfo {
+                len: msg_len,
+                flags: flags & !COMMIT_BITS,
+                timestamp: (flags & MSG_FLAG_TIMESTAMP != 0)
+                    .then(|| u64::from_le_bytes(stored_timestamp)),
+                deadline: (flags & MSG_FLAG_DEADLINE != 0)
+                    .then(|| u64::from_le_bytes(stored_deadline)),
+                sequence: (flags & MSG_FLAG_SEQUENCE != 0)
+                    .then(|| u32::from_le_bytes(stored_sequence)),
+            };
+
+            // Битое и просроченное до `f` не доходит, но в счётчики попадает
+            // только после фиксации: до CAS порванное перезаписью от порчи
+            // не отличить.
+            let corrupted = flags & MSG_FLAG_CHECKSUM != 0
+                && Crc32::new()
+                    .update(&(msg_len as u16).to_le_bytes())
+                    .update(&flags.to_le_bytes())
+                    .update(payload)
+                    .update(&stored_timestamp[..Self::timestamp_size(flags)])
+                    .update(&stored_deadline[..Self::deadline_size(flags)])
+                    .update(&stored_sequence[..Self::sequence_size(flags)])
+                    .finish()
+                    != u32::from_le_bytes(stored_crc);
+            let expired = frame.deadline.is_some_and(|deadline| deadline < now());
+            let value = (!corrupted && !expired).then(|| f(payload, frame.flags));
+
+            // Чтение кольца не должно «переехать» НИЖЕ CAS, иначе валидация
+            // теряет смысл: как в seqlock'ах `state`/`mailbox`, Acquire-барьер
+            // между копией и проверкой. На x86 это только барьер
+            // компилятора, на aarch64 -- `dmb ishld`.
+            crate::sync::fence(Ordering::Acquire);
+
+            // Фиксация: атомарно забираем слот. Провал => producer сдвинул read_pos
+            // (перезапись/конкурентный discard) => прочитанные байты невалидны,
+            // повторяем с актуальными значениями. Оставляемый кадр проверяется
+            // тем же CAS, только read_pos остаётся на месте.
+            let kept = value.is_some() && keep(frame.flags);
+            let target = if kept { read } else { new_read };
+            if header
+                .read_pos
+                .compare_exchange(read, target, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                continue;
+            }
+            if kept {
+                return Ok((value.unwrap(), frame));
+            }
+
+            let prev_count = header.message_count.fetch_sub(1, Ordering::AcqRel);
+            self.record_consumed(total as u32);
+            self.note_peak(write.wrapping_sub(read), prev_count);
+            if prev_count <= 1 {
+                header.sequence.fetch_add(1, Ordering::Relaxed);
+            }
+
+            // CAS прошёл: кадр не тронут перезаписью, значит несовпадение
+            // CRC -- реальная порча данных. Слот уже освобождён, битое
+            // сообщение просто отбрасывается.
+            match value {
+                Some(value) => return Ok((value, frame)),
+                None if corrupted => {
+                    header.checksum_errors.fetch_add(1, Ordering::Relaxed);
+                    return Err(RingError::ChecksumMismatch);
+                }
+                None => {
+                    header.expired_count.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Счётчик обещает кадр, а за `write_pos` лежит не кадр этого круга.
+    /// Так же на мгновение выглядит вытеснение последнего кадра между CAS
+    /// и `fetch_sub` -- поэтому кадр считается в `torn_records`, только
+    /// если позиции и счётчик стоят `STALE_RETRY_LIMIT` пауз, и один раз
+    /// на позицию. Счётчик сообщений не трогаем: отличить сбитый счётчик
+    /// от вытеснения на полпути reader не может.
+    fn note_torn(&self, read: u32, write: u32) {
+        if self.torn_at.load(Ordering::Relaxed) == u64::from(read) {
+            return;
+        }
+        let header = self.header();
+        let count = header.message_count.load(Ordering::Acquire);
+        for _ in 0..STALE_RETRY_LIMIT {
+            if count == 0
+                || header.read_pos.load(Ordering::Acquire) != read
+                || header.write_pos.load(Ordering::Acquire) != write
+                || header.message_count.load(Ordering::Acquire) != count
+            {
+                return;
+            }
+            crate::sync::spin_loop();
+        }
+        self.torn_at.store(u64::from(read), Ordering::Relaxed);
+        self.torn.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// [`read_frame_info_uninit`](Self::read_frame_info_uninit), пропуская
+    /// кадры со сроком годности раньше `now()` (считаются в
+    /// `expired_count`). `now` вызывается только для кадров со сроком.
+    pub fn read_live_frame_uninit(
+        &self,
+        out: &mut [MaybeUninit<u8>],
+        mut now: impl FnMut() -> u64,
+    ) -> Result<FrameInfo> {
+        loop {
+            let frame = self.read_frame_info_uninit(out)?;
+            match frame.deadline {
+                Some(deadline) if deadline < now() => {
+                    self.header().expired_count.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => return Ok(frame),
+            }
+        }
+    }
+
+    /// Убирает из головы очереди кадры, просроченные к `now` (вызывает
+    /// writer перед записью). Останавливается на первом живом кадре или
+    /// кадре без срока. Возвращает число убранных кадров.
+    pub fn purge_expired(&self, now: u64) -> u32 {
+        let mut purged = 0;
+        while let Ok(true) = self.discard_head(Some(now)) {
+            purged += 1;
+        }
+        purged
+    }
+
+    pub fn message_count(&self) -> u32 {
+        self.header().message_count.load(Ordering::Acquire)
+    }
+
+    pub fn drop_count(&self) -> u32 {
+        self.header().drop_count.load(Ordering::Acquire)
+    }
+
+    /// Сколько кадров опубликовано в кольцо с последнего сброса (по
+    /// модулю 2^32).
+    pub fn sent_messages(&self) -> u32 {
+        self.header().sent_msgs.load(Ordering::Acquire)
+    }
+
+    /// Сколько кадров изъято из кольца -- прочитано, отброшено читателем
+    /// или вытеснено writer'ом (по модулю 2^32).
+    pub fn consumed_messages(&self) -> u32 {
+        self.header().consumed_msgs.load(Ordering::Acquire)
+    }
+
+    /// Сколько сообщений отброшено читателем этого кольца из-за CRC-32.
+    pub fn checksum_errors(&self) -> u32 {
+        self.header().checksum_errors.load(Ordering::Acquire)
+    }
+
+    /// Сколько просроченных сообщений этого кольца пропущено или убрано.
+    pub fn expired_count(&self) -> u32 {
+        self.header().expired_count.load(Ordering::Acquire)
+    }
+
+    /// Сколько раз этот reader нашёл за `write_pos` обещанный счётчиком,
+    /// но не записанный на этом круге кадр и не выдал его. Счётчик
+    /// локальный, в секции не хранится.
+    pub fn torn_records(&self) -> u32 {
+        self.torn.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.message_count() == 0
+    }
+
+    /// Байты кадров между `read_pos` и `write_pos`.
+    pub fn used_bytes(&self) -> u32 {
+        let header = self.header();
+        let read = header.read_pos.load(Ordering::Acquire);
+        let write = header.write_pos.load(Ordering::Acquire);
+        // Снимок не атомарен, а чужой заголовок может быть мусорным --
+        // больше ёмкости не показываем.
+        write.wrapping_sub(read).min(self.capacity)
+    }
+
+    pub fn free_bytes(&self) -> u32 {
+        self.capacity - self.used_bytes()
+    }
+
+    /// Наибольшая занятость `(bytes, messages)`, которую видела эта
+    /// сторона с создания кольца или [`reset_high_water`](Self::reset_high_water).
+    pub fn high_water(&self) -> (u32, u32) {
+        (
+            self.peak_bytes.load(Ordering::Relaxed),
+            self.peak_messages.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn reset_high_water(&self) {
+        self.peak_bytes.store(0, Ordering::Relaxed);
+        self.peak_messages.store(0, Ordering::Relaxed);
+    }
+
+    /// Занятость после записи, опубликованной до `write_end`.
+    fn note_occupancy(&self, write_end: u32, messages: u32) {
+        let read = self.header().read_pos.load(Ordering::Acquire);
+        self.note_peak(write_end.wrapping_sub(read), messages);
+    }
+
+    fn note_peak(&self, bytes: u32, messages: u32) {
+        self.peak_bytes
+            .fetch_max(bytes.min(self.capacity), Ordering::Relaxed);
+        self.peak_messages
+            .fetch_max(messages.min(MAX_MESSAGES), Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod overflow_race_tests {
+    use super::*;
+    use crate::layout::RingHeader;
+    use std::alloc::{alloc_zeroed, dealloc, Layout};
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as O};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Владелец сырой выровненной памяти под один RingHeader + RING_CAPACITY.
+    struct RingMem {
+        ptr: *mut u8,
+        layout: Layout,
+    }
+    unsafe impl Send for RingMem {}
+    unsafe impl Sync for RingMem {}
+    impl Drop for RingMem {
+        fn drop(&mut self) {
+            // SAFETY: ptr/layout получены из alloc_zeroed в make_ring.
+            unsafe { dealloc(self.ptr, self.layout) };
+        }
+    }
+
+    fn make_ring() -> (RingBuffer, RingMem) {
+        let header_size = std::mem::size_of::<RingHeader>();
+        let total = header_size + RING_CAPACITY;
+        let layout = Layout::from_size_align(total, 64).unwrap();
+        // SAFETY: ненулевой размер; зануление валидно для AtomicU32 полей.
+        let ptr = unsafe { alloc_zeroed(layout) };
+        assert!(!ptr.is_null(), "alloc failed");
+        let header = ptr as *mut RingHeader;
+        // SAFETY: ptr выровнен на 64 и указывает на зануленный RingHeader.
+        unsafe { (*header).reset(1) };
+        // SAFETY: data сразу за заголовком, в пределах выделения.
+        let data = unsafe { ptr.add(header_size) };
+        // SAFETY: header и data валидны, не пересекаются, живут пока жив RingMem.
+        let ring = unsafe { RingBuffer::new(header, data) };
+        (ring, RingMem { ptr, layout })
+    }
+
+    // Большие сообщения: ~34 сообщения заполняют 2 МБ кольца ПО БАЙТАМ
+    // (а не по счётчику MAX_MESSAGES=500). Torn-read возможен только в
+    // байт-заполненном режиме, где write_pos & MASK == read_pos & MASK и
+    // producer физически перезаписывает слот, который читает consumer.
+    // С маленькими сообщениями переполнение наступает по счётчику задолго
+    // до байтового, write далеко впереди read, и гонка не открывается.
+    // Размер -- от MAX_MESSAGE_SIZE, чтобы тест шёл в любом профиле колец.
+    const PAYLOAD: usize = MAX_MESSAGE_SIZE * 15 / 16;
+    // seq-маркеры в трёх точках сообщения. Если producer перезапишет слот
+    // в середине копирования, маркеры начала/середины/конца разойдутся.
+    const MARK0: usize = 0;
+    const MARK1: usize = PAYLOAD / 2;
+    const MARK2: usize = PAYLOAD - 4;
+
+    /// Проставить seq в три маркера переиспользуемого буфера (без аллокаций
+    /// в горячем цикле — producer должен быть быстрым, чтобы успевать
+    /// перезаписывать слот во время копирования consumer-ом).
+    fn stamp(buf: &mut [u8], seq: u32) {
+        let s = seq.to_le_bytes();
+        buf[MARK0..MARK0 + 4].copy_from_slice(&s);
+        buf[MARK1..MARK1 + 4].copy_from_slice(&s);
+        buf[MARK2..MARK2 + 4].copy_from_slice(&s);
+    }
+
+    /// Err, если сообщение «порвано»: маркеры начала/середины/конца не совпали.
+    // Примечание: `use super::*` втягивает модульный `Result<T>` (ошибка = RingError),
+    // поэтому здесь используем полностью квалифицированный std-Result для String-ошибки.
+    fn check(msg: &[u8]) -> std::result::Result<(), String> {
+        if msg.len() != PAYLOAD {
+            return Err(format!("bad len {}", msg.len()));
+        }
+        let m0 = u32::from_le_bytes([msg[MARK0], msg[MARK0 + 1], msg[MARK0 + 2], msg[MARK0 + 3]]);
+        let m1 = u32::from_le_bytes([msg[MARK1], msg[MARK1 + 1], msg[MARK1 + 2], msg[MARK1 + 3]]);
+        let m2 = u32::from_le_bytes([msg[MARK2], msg[MARK2 + 1], msg[MARK2 + 2], msg[MARK2 + 3]]);
+        if m0 != m1 || m0 != m2 {
+            return Err(format!("torn: m0={m0} m1={m1} m2={m2}"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_does_not_tear_messages() {
+        let (ring, _mem) = make_ring();
+        let ring = Arc::new(ring);
+        let stop = Arc::new(AtomicBool::new(false));
+        let torn = Arc::new(AtomicU64::new(0));
+        let reads = Arc::new(AtomicU64::new(0));
+
+        let producer = {
+            let ring = ring.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                // Переиспользуемый буфер: producer на полной скорости держит
+                // кольцо байт-заполненным, постоянно перезаписывая старое.
+                let mut buf = vec![0u8; PAYLOAD];
+                let mut seq: u32 = 1;
+                while !stop.load(O::Acquire) {
+                    stamp(&mut buf, seq);
+                    let _ = ring.write_message(&buf); // overwrite разрешён
+                    seq = seq.wrapping_add(1);
+                }
+            })
+        };
+
+        let consumer = {
+            let ring = ring.clone();
+            let stop = stop.clone();
+            let torn = torn.clone();
+            let reads = reads.clone();
+            thread::spawn(move || {
+                let mut out = vec![0u8; PAYLOAD];
+                while !stop.load(O::Acquire) {
+                    match ring.read_message(&mut out) {
+                        Ok(len) => {
+                            if check(&out[..len]).is_err() {
+                                torn.fetch_add(1, O::AcqRel);
+                            }
+                            reads.fetch_add(1, O::AcqRel);
+                            // Лёгкая задержка: consumer чуть медленнее producer-а
+                            // -> кольцо остаётся заполненным -> producer пишет
+                            // ровно в слот, который мы читаем.
+                            for _ in 0..400 {
+                                std::hint::spin_loop();
+                            }
+                        }
+                        Err(_) => thread::yield_now(),
+                    }
+                }
+            })
+        };
+
+        thread::sleep(std::time::Duration::from_millis(800));
+        stop.store(true, O::Release);
+        producer.join().unwrap();
+        consumer.join().unwrap();
+
+        let reads = reads.load(O::Acquire);
+        let torn = torn.load(O::Acquire);
+        assert!(reads > 0, "consumer не прочитал ни одного сообщения");
+        assert_eq!(
+            torn, 0,
+            "обнаружены порванные сообщения: {torn} (из {reads} прочитанных)"
+        );
+    }
+
+    #[test]
+    fn checksum_roundtrip_survives_overwrite() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        // Переполняем по счётчику: discard_oldest обязан учитывать трейлер,
+        // иначе read_pos «съедет» и следующее чтение вернёт Corrupted.
+        for i in 0..(MAX_MESSAGES + 10) {
+            ring.write_message(&i.to_le_bytes()).unwrap();
+        }
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        let mut expected = 10u32;
+        while let Ok(len) = ring.read_message(&mut out) {
+            assert_eq!(&out[..len], &expected.to_le_bytes());
+            expected += 1;
+        }
+        assert_eq!(expected, MAX_MESSAGES + 10);
+        assert_eq!(ring.checksum_errors(), 0);
+    }
+
+    #[test]
+    fn write_outcome_reports_room_left() {
+        let (ring, _mem) = make_ring();
+        let first = ring.write_message(&[1u8; 100]).unwrap();
+        assert_eq!(first.queue_len_after, 1);
+        assert_eq!(first.free_bytes_after, ring.free_bytes());
+
+        let second = ring.write_message(&[2u8; 100]).unwrap();
+        assert_eq!(second.queue_len_after, 2);
+        assert!(second.free_bytes_after < first.free_bytes_after);
+
+        let batch = ring.write_batch(&[&[3u8; 10], &[4u8; 10]], None, None).unwrap();
+        assert_eq!(batch.queue_len_after, 4);
+        assert_eq!(batch.free_bytes_after, ring.capacity() - ring.used_bytes());
+
+        // Переполнение по счётчику: очередь упирается в MAX_MESSAGES.
+        let mut last = batch;
+        for _ in 0..MAX_MESSAGES {
+            last = ring.write_message(&[5u8; 4]).unwrap();
+        }
+        assert!(last.overwritten > 0);
+        assert_eq!(last.queue_len_after, MAX_MESSAGES);
+    }
+
+    #[test]
+    fn expired_frames_are_skipped_and_purged() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        ring.write_frame_timed(b"stale", 0, None, Some(100))
+            .unwrap();
+        ring.write_frame_timed(b"fresh", 0, Some(7), Some(300))
+            .unwrap();
+        ring.write_message(b"forever").unwrap();
+
+        let mut out = [MaybeUninit::uninit(); MAX_MESSAGE_SIZE];
+        let frame = ring.read_live_frame_uninit(&mut out, || 200).unwrap();
+        assert_eq!(
+            (frame.len, frame.timestamp, frame.deadline),
+            (5, Some(7), Some(300))
+        );
+        assert_eq!(ring.expired_count(), 1);
+        let frame = ring
+            .read_live_frame_uninit(&mut out, || unreachable!())
+            .unwrap();
+        assert_eq!((frame.len, frame.deadline), (7, None));
+        assert_eq!(ring.checksum_errors(), 0);
+
+        // Writer убирает просроченную голову, но не трогает кадр без срока
+        // и всё, что за ним.
+        ring.write_frame_timed(b"old", 0, None, Some(100)).unwrap();
+        ring.write_frame_timed(b"late", 0, None, Some(500)).unwrap();
+        ring.write_message(b"plain").unwrap();
+        ring.write_frame_timed(b"behind", 0, None, Some(100))
+            .unwrap();
+        assert_eq!(ring.purge_expired(400), 1);
+        assert_eq!(ring.purge_expired(400), 0);
+        assert_eq!(ring.purge_expired(600), 1);
+        assert_eq!(ring.message_count(), 2);
+        assert_eq!(ring.expired_count(), 3);
+        assert_eq!(ring.drop_count(), 0);
+    }
+
+    #[test]
+    fn checksum_mismatch_drops_only_the_corrupted_message() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        ring.write_message(b"first").unwrap();
+        ring.write_message(b"second").unwrap();
+
+        // Портим байт payload первого сообщения прямо в кольце.
+        // SAFETY: смещение внутри первого сообщения, кольцо живо.
+        unsafe { *ring.data_ptr().add(MESSAGE_HEADER_SIZE) ^= 0xFF };
+
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        assert_eq!(
+            ring.read_message(&mut out),
+            Err(RingError::ChecksumMismatch)
+        );
+        assert_eq!(ring.checksum_errors(), 1);
+        let len = ring.read_message(&mut out).unwrap();
+        assert_eq!(&out[..len], b"second");
+    }
+
+    #[test]
+    fn reader_accepts_mixed_checksum_and_plain_messages() {
+        let (ring, _mem) = make_ring();
+        ring.write_message(b"plain").unwrap();
+        ring.set_checksum(true);
+        ring.write_message(b"checked").unwrap();
+
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        let len = ring.read_message(&mut out).unwrap();
+        assert_eq!(&out[..len], b"plain");
+        let len = ring.read_message(&mut out).unwrap();
+        assert_eq!(&out[..len], b"checked");
+    }
+
+    #[test]
+    fn handle_frame_is_never_overwritten() {
+        let (ring, _mem) = make_ring();
+        ring.write_frame(&7u64.to_le_bytes(), MSG_FLAG_HANDLE)
+            .unwrap();
+        for _ in 1..MAX_MESSAGES {
+            ring.write_message(b"fill").unwrap();
+        }
+        // Переполнение упирается в handle-кадр: вытеснять его нельзя.
+        assert!(matches!(
+            ring.write_message(b"more"),
+            Err(RingError::QueueFull)
+        ));
+
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        let (len, flags) = ring.read_frame(&mut out).unwrap();
+        assert_eq!(
+            (len, flags & MSG_FLAG_HANDLE),
+            (HANDLE_FRAME_SIZE, MSG_FLAG_HANDLE)
+        );
+        assert_eq!(out[..len], 7u64.to_le_bytes());
+        // Теперь старейшее -- обычное сообщение, overflow снова вытесняет.
+        assert_eq!(ring.write_message(b"more").unwrap().overwritten, 0);
+        assert_eq!(ring.write_message(b"more").unwrap().overwritten, 1);
+    }
+
+    #[test]
+    fn full_ring_without_eviction_fails_and_flags_the_stall() {
+        let (ring, _mem) = make_ring();
+        ring.set_evict(false);
+        for _ in 0..MAX_MESSAGES {
+            ring.write_message(b"fill").unwrap();
+        }
+        assert!(matches!(
+            ring.write_message(b"more"),
+            Err(RingError::QueueFull)
+        ));
+        assert_eq!(ring.drop_count(), 0);
+
+        // Изъятие освобождает место, а флаг говорит читателю разбудить
+        // writer'а.
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        ring.read_message(&mut out).unwrap();
+        assert!(ring.take_credit_stall());
+        assert_eq!(ring.write_message(b"more").unwrap().overwritten, 0);
+
+        ring.set_evict(true);
+        assert_eq!(ring.write_message(b"more").unwrap().overwritten, 1);
+    }
+
+    #[test]
+    fn occupancy_and_high_water_follow_writes_and_reads() {
+        let (ring, _mem) = make_ring();
+        assert_eq!(
+            (ring.used_bytes(), ring.free_bytes()),
+            (0, RING_CAPACITY as u32)
+        );
+        for _ in 0..3 {
+            ring.write_message(b"fill").unwrap();
+        }
+        let frame = RingBuffer::frame_size(4, 0) as u32;
+        assert_eq!(ring.used_bytes(), 3 * frame);
+        assert_eq!(ring.free_bytes(), RING_CAPACITY as u32 - 3 * frame);
+        assert_eq!(ring.high_water(), (3 * frame, 3));
+
+        let mut out = [0u8; MAX_MESSAGE_SIZE];
+        ring.read_message(&mut out).unwrap();
+        ring.read_message(&mut out).unwrap();
+        ring.write_message(b"more").unwrap();
+        assert_eq!(ring.used_bytes(), 2 * frame);
+        // Пик остаётся, пока его не сбросят.
+        assert_eq!(ring.high_water(), (3 * frame, 3));
+        ring.reset_high_water();
+        assert_eq!(ring.high_water(), (0, 0));
+        ring.read_message(&mut out).unwrap();
+        assert_eq!(ring.high_water(), (2 * frame, 2));
+    }
+
+    #[test]
+    fn sequence_numbers_expose_overwritten_frames() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        ring.write_message(b"unnumbered").unwrap();
+        ring.set_sequence_numbers(true);
+        for _ in 1..MAX_MESSAGES {
+            ring.write_message(b"fill").unwrap();
+        }
+
+        let mut out = [MaybeUninit::uninit(); MAX_MESSAGE_SIZE];
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!(frame.sequence, None);
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!(
+            (frame.sequence, frame.flags),
+            (Some(1), MSG_FLAG_CHECKSUM | MSG_FLAG_SEQUENCE)
+        );
+        // Вытесненные кадры -- разрыв в номерах.
+        for _ in 0..4 {
+            ring.write_message(b"more").unwrap();
+        }
+        assert_eq!(ring.drop_count(), 2);
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!(frame.sequence, Some(4));
+        assert_eq!(ring.checksum_errors(), 0);
+
+        while ring.read_frame_info_uninit(&mut out).is_ok() {}
+        ring.write_batch(&[b"one", b"two"], None, None).unwrap();
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!(frame.sequence, Some(MAX_MESSAGES + 4));
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!(frame.sequence, Some(MAX_MESSAGES + 5));
+    }
+
+    #[test]
+    fn concurrent_producers_keep_frames_whole_ordered_and_numbered() {
+        const PRODUCERS: u8 = 4;
+        const PER_PRODUCER: u32 = 20_000;
+        let (mut ring, _mem) = make_ring();
+        ring.set_turn_backoff(thread::yield_now);
+        ring.set_checksum(true);
+        ring.set_sequence_numbers(true);
+        ring.set_multi_producer(true);
+        let ring = Arc::new(ring);
+
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|id| {
+                let ring = ring.clone();
+                thread::spawn(move || {
+                    // Номер писателя и его счётчик, повторённый по всему
+                    // payload'у: смесь кадров двух писателей видна сразу.
+                    let mut buf = [0u8; 1 + 4 * 16];
+                    buf[0] = id;
+                    for n in 0..PER_PRODUCER {
+                        for chunk in buf[1..].chunks_mut(4) {
+                            chunk.copy_from_slice(&n.to_le_bytes());
+                        }
+                        if n % 2 == 0 {
+                            ring.write_message(&buf).unwrap();
+                        } else {
+                            while matches!(
+                                ring.write_batch(&[&buf], None, None),
+                                Err(RingError::QueueFull)
+                            ) {
+                                thread::yield_now();
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut last = [None::<u32>; PRODUCERS as usize];
+        let mut last_sequence = None::<u32>;
+        let mut received = 0u32;
+        let mut out = [MaybeUninit::uninit(); MAX_MESSAGE_SIZE];
+        let mut read = |ring: &RingBuffer| -> bool {
+            let Ok(frame) = ring.read_frame_info_uninit(&mut out) else {
+                return false;
+            };
+            // SAFETY: при Ok первые frame.len байт инициализированы.
+            let msg: &[u8] =
+                unsafe { std::slice::from_raw_parts(out.as_ptr().cast::<u8>(), frame.len) };
+            let n = u32::from_le_bytes(msg[1..5].try_into().unwrap());
+            assert!(
+                msg[1..].chunks(4).all(|chunk| chunk == n.to_le_bytes()),
+                "torn frame: {msg:?}"
+            );
+            let producer = &mut last[msg[0] as usize];
+            assert!(
+                producer.is_none_or(|prev| prev < n),
+                "reordered: {prev:?} -> {n}",
+                prev = *producer
+            );
+            *producer = Some(n);
+            let sequence = frame.sequence.unwrap();
+            assert!(last_sequence.is_none_or(|prev| prev < sequence));
+            last_sequence = Some(sequence);
+            received += 1;
+            true
+        };
+        while producers.iter().any(|p| !p.is_finished()) {
+            if !read(&ring) {
+                thread::yield_now();
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        while read(&ring) {}
+
+        let total = u32::from(PRODUCERS) * PER_PRODUCER;
+        assert_eq!(received + ring.drop_count(), total);
+        assert_eq!(last_sequence, Some(total - 1));
+        assert_eq!(ring.checksum_errors(), 0);
+        assert_eq!(ring.message_count(), 0);
+    }
+
+    #[test]
+    fn batch_is_written_whole_or_not_at_all() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        let big = vec![0xAB; MAX_MESSAGE_SIZE];
+        let full_frames = RING_CAPACITY / (MESSAGE_HEADER_SIZE + MAX_MESSAGE_SIZE + CHECKSUM_SIZE);
+        for _ in 0..full_frames {
+            ring.write_message(&big).unwrap();
+        }
+
+        // Не влезает целиком -- не пишется ничего и ничего не вытесняется.
+        let first = vec![1u8; PAYLOAD];
+        let second = vec![2u8; 6_000];
+        assert_eq!(
+            ring.write_batch(&[&first, &second], None, None)
+                .unwrap_err(),
+            RingError::QueueFull
+        );
+        assert_eq!(ring.message_count(), full_frames as u32);
+        assert_eq!(ring.drop_count(), 0);
+        let too_big: Vec<&[u8]> = vec![&big; full_frames + 1];
+        assert_eq!(
+            ring.write_batch(&too_big, None, None).unwrap_err(),
+            RingError::MessageTooLarge
+        );
+
+        // Одно чтение освобождает место; пачка переходит через конец кольца.
+        let mut out = vec![0u8; MAX_MESSAGE_SIZE];
+        ring.read_message(&mut out).unwrap();
+        let outcome = ring.write_batch(&[&first, &second], None, Some(9)).unwrap();
+        assert!(!outcome.was_empty);
+        assert_eq!(ring.message_count(), full_frames as u32 + 1);
+
+        for _ in 1..full_frames {
+            ring.read_message(&mut out).unwrap();
+        }
+        for expected in [&first, &second] {
+            let (len, flags) = ring.read_frame(&mut out).unwrap();
+            assert_eq!(&out[..len], &expected[..]);
+            assert_ne!(flags & MSG_FLAG_DEADLINE, 0);
+        }
+        assert!(ring.is_empty());
+        assert_eq!(ring.checksum_errors(), 0);
+        assert_eq!(
+            ring.write_batch(&[], None, None)
+                .map(|outcome| outcome.was_empty),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn short_buffer_leaves_frame_in_ring() {
+        let (ring, _mem) = make_ring();
+        ring.write_message(b"payload").unwrap();
+
+        let mut short = [0u8; 4];
+        assert_eq!(
+            ring.read_message(&mut short),
+            Err(RingError::BufferTooSmall { required: 7 })
+        );
+        assert_eq!(ring.message_count(), 1);
+
+        let mut out = [0u8; 7];
+        assert_eq!(ring.read_message(&mut out), Ok(7));
+        assert_eq!(&out, b"payload");
+    }
+
+    #[test]
+    fn borrowed_read_is_in_place_unless_frame_wraps() {
+        let (ring, _mem) = make_ring();
+        let ring_bytes = ring.data_ptr() as usize..ring.data_ptr() as usize + RING_CAPACITY;
+        let mut calls = 0;
+        ring.write_frame_timed(b"stale", 0, None, Some(100))
+            .unwrap();
+        ring.write_message(b"inplace").unwrap();
+        let ((addr, payload), frame) = ring
+            .read_live_frame_with(
+                &mut [],
+                || 200,
+                |payload, _| {
+                    calls += 1;
+                    (payload.as_ptr() as usize, payload.to_vec())
+                },
+            )
+            .unwrap();
+        // Просроченный кадр забран без вызова `f`.
+        assert_eq!((calls, ring.expired_count()), (1, 1));
+        assert_eq!((payload.as_slice(), frame.len), (&b"inplace"[..], 7));
+        assert!(ring_bytes.contains(&addr));
+
+        // Доводим позицию до конца кольца: следующий большой кадр через
+        // него переходит.
+        let big = vec![0x5Au8; PAYLOAD];
+        let mut out = vec![0u8; MAX_MESSAGE_SIZE];
+        while ring.mask_index(ring.header().write_pos.load(O::Relaxed))
+            + MESSAGE_HEADER_SIZE
+            + big.len()
+            <= RING_CAPACITY
+        {
+            ring.write_message(&big).unwrap();
+            ring.read_message(&mut out).unwrap();
+        }
+        ring.write_message(&big).unwrap();
+        assert_eq!(
+            ring.read_live_frame_with(&mut [], || 0, |_, _| unreachable!()),
+            Err(RingError::BufferTooSmall {
+                required: big.len()
+            })
+        );
+        assert_eq!(ring.message_count(), 1);
+        let mut scratch = vec![MaybeUninit::uninit(); MAX_MESSAGE_SIZE];
+        let (copied, _) = ring
+            .read_live_frame_with(
+                &mut scratch,
+                || 0,
+                |payload, _| {
+                    assert!(!ring_bytes.contains(&(payload.as_ptr() as usize)));
+                    payload == big.as_slice()
+                },
+            )
+            .unwrap();
+        assert!(copied);
+
+        // Битый кадр не доходит до `f`.
+        ring.set_checksum(true);
+        ring.write_message(b"broken").unwrap();
+        let head = ring.mask_index(ring.header().read_pos.load(O::Relaxed));
+        // SAFETY: первый байт payload'а единственного кадра, кольцо живо.
+        unsafe {
+            *ring
+                .data_ptr()
+                .add((head + MESSAGE_HEADER_SIZE) & ring.mask()) ^= 0xFF
+        };
+        assert_eq!(
+            ring.read_live_frame_with(&mut scratch, || 0, |_, _| unreachable!()),
+            Err(RingError::ChecksumMismatch)
+        );
+        assert_eq!((ring.checksum_errors(), ring.message_count()), (1, 0));
+    }
+
+    #[test]
+    fn peek_leaves_kept_frame_in_ring() {
+        let (ring, _mem) = make_ring();
+        ring.write_frame_timed(b"stale", 0, None, Some(100))
+            .unwrap();
+        ring.write_frame(b"skipped", MSG_FLAG_HANDLE).unwrap();
+        ring.write_message(b"wanted").unwrap();
+        let keep = |flags: u16| flags & MSG_FLAG_HANDLE == 0;
+        let peek = || {
+            ring.peek_live_frame_with(&mut [], || 200, keep, |payload, _| payload.to_vec())
+                .unwrap()
+        };
+        // Просроченный кадр забран молча, не оставленный `keep` -- как
+        // при чтении, целевой остаётся в кольце.
+        let (payload, frame) = peek();
+        assert_eq!(
+            (payload.as_slice(), frame.flags),
+            (&b"skipped"[..], MSG_FLAG_HANDLE)
+        );
+        for _ in 0..2 {
+            let (payload, frame) = peek();
+            assert_eq!((payload.as_slice(), frame.len), (&b"wanted"[..], 6));
+        }
+        assert_eq!(ring.expired_count(), 1);
+        assert_eq!(ring.message_count(), 1);
+        let mut out = [0u8; 16];
+        assert_eq!(ring.read_message(&mut out), Ok(6));
+        assert_eq!(
+            ring.peek_live_frame_with(&mut [], || 0, keep, |_, _| ()),
+            Err(RingError::QueueEmpty)
+        );
+    }
+
+    #[test]
+    fn reservation_is_published_only_on_commit() {
+        let (ring, _mem) = make_ring();
+        ring.set_checksum(true);
+        let reservation = ring.reserve(5, 0, Some(42), None).unwrap();
+        // SAFETY: других записей, пока срезы живы, нет.
+        let (head, tail) = unsafe { ring.reserved_payload(&reservation) };
+        assert!(tail.is_empty());
+        head.copy_from_slice(b"hello");
+        assert_eq!(ring.message_count(), 0);
+        let outcome = ring.commit(reservation);
+        assert!(outcome.was_empty);
+        let mut out = [MaybeUninit::uninit(); 16];
+        let frame = ring.read_frame_info_uninit(&mut out).unwrap();
+        assert_eq!((frame.len, frame.timestamp), (5, Some(42)));
+
+        // Брошенная резервация ничего не публикует.
+        let _abandoned = ring.reserve(8, 0, None, None).unwrap();
+        assert_eq!(ring.message_count(), 0);
+        assert_eq!(
+            ring.reserve(1, 0, None, None).unwrap_err(),
+            RingError::MessageTooSmall
+        );
+
+        // Payload через конец кольца -- двумя частями, CRC по обеим.
+        let big = vec![0x5Au8; PAYLOAD];
+        let mut sink = vec![0u8; MAX_MESSAGE_SIZE];
+        while ring.mask_index(ring.header().write_pos.load(O::Relaxed))
+            + MESSAGE_HEADER_SIZE
+            + big.len()
+            <= RING_CAPACITY
+        {
+            ring.write_message(&big).unwrap();
+            ring.read_message(&mut sink).unwrap();
+        }
+        let reservation = ring.reserve(big.len(), 0, None, None).unwrap();
+        // SAFETY: как выше.
+        let (head, tail) = unsafe { ring.reserved_payload(&reservation) };
+        assert!(!head.is_empty() && !tail.is_empty());
+        assert_eq!(head.len() + tail.len(), big.len());
+        head.fill(0x5A);
+        tail.fill(0x5A);
+        ring.commit(reservation);
+        assert_eq!(ring.read_message(&mut sink), Ok(big.len()));
+        assert_eq!(&sink[..big.len()], big.as_slice());
+        assert_eq!(ring.checksum_errors(), 0);
+    }
+
+    /// Писатель умер, учтя кадр в message_count, но не сдвинув write_pos:
+    /// reader дочитывает его сам, кольцо остаётся согласованным.
+    #[test]
+    fn reader_publishes_frame_of_writer_that_died_before_write_pos() {
+        let (ring, _mem) = make_ring();
+        let header = ring.header();
+        ring.write_message(b"first").unwrap();
+        let write = header.write_pos.load(O::Acquire);
+        // SAFETY: кадр за write_pos -- в свободной части кольца.
+        unsafe {
+            ring.store_payload(write, b"last words");
+            ring.seal_frame(write, 10, 0, None, None, 0);
+        }
+        header.message_count.fetch_add(1, O::AcqRel);
+
+        let mut out = [0u8; 16];
+        assert_eq!(ring.read_message(&mut out), Ok(5));
+        let (len, flags) = ring.read_frame(&mut out).unwrap();
+        assert_eq!((&out[..len], flags), (&b"last words"[..], 0));
+        assert_eq!(ring.read_message(&mut out), Err(RingError::QueueEmpty));
+        assert_eq!(
+            header.write_pos.load(O::Acquire),
+            header.read_pos.load(O::Acquire)
+        );
+        assert_eq!(ring.torn_records(), 0);
+
+        ring.write_message(b"next").unwrap();
+        assert_eq!(ring.read_message(&mut out), Ok(4));
+        assert_eq!(&out[..4], b"next");
+    }
+
+    /// Счётчик обещает кадр, а за write_pos -- кадр прошлого круга:
+    /// reader не выдаёт его (ни дубликатом, ни Corrupted) и считает один
+    /// раз.
+    #[test]
+    fn stale_tail_is_skipped_and_counted() {
+        let (ring, _mem) = make_ring();
+        let header = ring.header();
+        let mut out = [0u8; 16];
+
+        ring.write_message(b"old lap").unwrap();
+        assert_eq!(ring.read_message(&mut out), Ok(7));
+        // Следующий круг: в слоте 0 -- кадр круга 0.
+        header.read_pos.store(RING_CAPACITY as u32, O::Relaxed);
+        header.write_pos.store(RING_CAPACITY as u32, O::Relaxed);
+        header.message_count.store(1, O::Release);
+        for _ in 0..2 {
+            assert_eq!(ring.read_message(&mut out), Err(RingError::QueueEmpty));
+        }
+        assert_eq!(ring.torn_records(), 1);
+
+        ring.write_message(b"fresh").unwrap();
+        assert_eq!(ring.read_message(&mut out), Ok(5));
+        assert_eq!(&out[..5], b"fresh");
+    }
+
+    /// Мусор в заголовке от враждебного/упавшего пира не должен вешать
+    /// writer'а в бесконечном цикле вытеснения.
+    #[test]
+    fn hostile_header_makes_write_fail_instead_of_spinning() {
+        let (ring, _mem) = make_ring();
+        let header = ring.header();
+
+        // read_pos «обогнал» write_pos: занято больше ёмкости.
+        header.read_pos.store(100, O::Relaxed);
+        header.write_pos.store(10, O::Relaxed);
+        assert!(matches!(
+            ring.write_message(b"xx"),
+            Err(RingError::Corrupted)
+        ));
+
+        // Кольцо пусто по позициям, но счётчик говорит «полно».
+        header.read_pos.store(0, O::Relaxed);
+        header.write_pos.store(0, O::Relaxed);
+        header.message_count.store(u32::MAX, O::Relaxed);
+        assert!(matches!(
+            ring.write_message(b"xx"),
+            Err(RingError::Corrupted)
+        ));
+
+        // Длина в слоте заходит за write_pos.
+        header.write_pos.store(8, O::Relaxed);
+        header.message_count.store(MAX_MESSAGES, O::Relaxed);
+        // SAFETY: первые байты кольца, кольцо живо.
+        unsafe { ring.copy_into(0, &1000u16.to_le_bytes()) };
+        assert!(matches!(
+            ring.write_message(b"xx"),
+            Err(RingError::Corrupted)
+        ));
+    }
+}
+
+/// Проверка порядка операций кольца моделью loom:
+/// `RUSTFLAGS="--cfg loom" cargo test -p xshm-core --release --lib loom`.
+///
+/// Под loom `RING_CAPACITY` = 64 и `MAX_MESSAGES` = 4 (см. constants.rs),
+/// поэтому переполнение по счётчику и по байтам (с переносом через границу)
+/// наступает за пару записей.
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// Кольцо в обычной куче: заголовок с loom-атомиками нельзя получить
+    /// занулением памяти, как в mapping'е.
+    struct TestRing {
+        ring: RingBuffer,
+        _header: Box<RingHeader>,
+        _data: Vec<u8>,
+    }
+
+    fn make_ring() -> Arc<TestRing> {
+        let mut header = Box::<RingHeader>::default();
+        let mut data = vec![0u8; RING_CAPACITY];
+        // SAFETY: header и data живут вместе с RingBuffer внутри TestRing.
+        let ring = unsafe { RingBuffer::new(&mut *header, data.as_mut_ptr()) };
+        Arc::new(TestRing {
+            ring,
+            _header: header,
+            _data: data,
+        })
+    }
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(f);
+    }
+
+    /// Сообщение `id` -- `len` байт, заполненных `id`: порванная копия видна
+    /// как смесь разных байт.
+    fn message(id: u8, len: usize) -> Vec<u8> {
+        vec![id; len]
+    }
+
+    fn id_of(msg: &[u8]) -> u8 {
+        assert!(msg.iter().all(|&b| b == msg[0]), "torn message: {msg:?}");
+        msg[0]
+    }
+
+    fn drain(ring: &RingBuffer, ids: &mut Vec<u8>) {
+        let mut out = [0u8; RING_CAPACITY];
+        while let Ok(len) = ring.read_message(&mut out) {
+            ids.push(id_of(&out[..len]));
+        }
+    }
+
+    #[test]
+    fn concurrent_write_and_read_preserve_order() {
+        model(|| {
+            let shared = make_ring();
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for id in 1..=3 {
+                        shared.ring.write_message(&message(id, 2)).unwrap();
+                    }
+                })
+            };
+
+            let mut ids = Vec::new();
+            let mut out = [0u8; RING_CAPACITY];
+            for _ in 0..3 {
+                match shared.ring.read_message(&mut out) {
+                    Ok(len) => ids.push(id_of(&out[..len])),
+                    Err(RingError::QueueEmpty) => thread::yield_now(),
+                    Err(err) => panic!("unexpected error: {err:?}"),
+                }
+            }
+            writer.join().unwrap();
+            drain(&shared.ring, &mut ids);
+
+            assert_eq!(ids, [1, 2, 3]);
+            assert_eq!(shared.ring.message_count(), 0);
+        });
+    }
+
+    #[test]
+    fn batch_becomes_visible_all_at_once() {
+        model(|| {
+            let shared = make_ring();
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let batch = [message(1, 2), message(2, 2), message(3, 2)];
+                    let batch: Vec<&[u8]> = batch.iter().map(Vec::as_slice).collect();
+                    shared.ring.write_batch(&batch, None, None).unwrap();
+                })
+            };
+
+            // Увидев первый кадр пачки, reader видит и остальные.
+            let mut ids = Vec::new();
+            drain(&shared.ring, &mut ids);
+            assert!(ids.is_empty() || ids == [1, 2, 3], "partial batch: {ids:?}");
+            writer.join().unwrap();
+            drain(&shared.ring, &mut ids);
+            assert_eq!(ids, [1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn discard_oldest_races_reader_without_loss_or_duplicates() {
+        model(|| {
+            let shared = make_ring();
+            for id in 0..MAX_MESSAGES as u8 {
+                shared.ring.write_message(&message(id, 2)).unwrap();
+            }
+
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    shared
+                        .ring
+                        .write_message(&message(MAX_MESSAGES as u8, 2))
+                        .unwrap()
+                })
+            };
+
+            let mut ids = Vec::new();
+            let mut out = [0u8; RING_CAPACITY];
+            if let Ok(len) = shared.ring.read_message(&mut out) {
+                ids.push(id_of(&out[..len]));
+            }
+            let outcome = writer.join().unwrap();
+            drain(&shared.ring, &mut ids);
+
+            // Каждое сообщение либо прочитано ровно один раз, либо вытеснено.
+            assert!(ids.windows(2).all(|w| w[0] < w[1]), "order: {ids:?}");
+            assert_eq!(ids.last(), Some(&(MAX_MESSAGES as u8)));
+            assert_eq!(
+                ids.len() as u32 + shared.ring.drop_count(),
+                MAX_MESSAGES + 1
+            );
+            assert_eq!(shared.ring.drop_count(), outcome.overwritten);
+            assert_eq!(shared.ring.message_count(), 0);
+        });
+    }
+
+    #[test]
+    fn byte_overflow_with_wraparound_never_tears_messages() {
+        // Кадр 4 + 20 = 24 байта: в 64-байтное кольцо влезают два, третий
+        // вытесняет старейший и переносится через границу.
+        const LEN: usize = 20;
+        model(|| {
+            let shared = make_ring();
+            shared.ring.write_message(&message(0, LEN)).unwrap();
+            shared.ring.write_message(&message(1, LEN)).unwrap();
+
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    shared.ring.write_message(&message(2, LEN)).unwrap();
+                    shared.ring.write_message(&message(3, LEN)).unwrap();
+                })
+            };
+
+            let mut ids = Vec::new();
+            let mut out = [0u8; RING_CAPACITY];
+            for _ in 0..2 {
+                if let Ok(len) = shared.ring.read_message(&mut out) {
+                    assert_eq!(len, LEN);
+                    ids.push(id_of(&out[..len]));
+                }
+            }
+            writer.join().unwrap();
+            drain(&shared.ring, &mut ids);
+
+            assert!(ids.windows(2).all(|w| w[0] < w[1]), "order: {ids:?}");
+            assert_eq!(ids.last(), Some(&3));
+            assert_eq!(ids.len() as u32 + shared.ring.drop_count(), 4);
+        });
+    }
+
+    #[test]
+    fn failed_write_without_eviction_is_never_left_unwoken() {
+        model(|| {
+            let shared = make_ring();
+            shared.ring.set_evict(false);
+            for id in 0..MAX_MESSAGES as u8 {
+                shared.ring.write_message(&message(id, 2)).unwrap();
+            }
+
+            let reader = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut out = [0u8; RING_CAPACITY];
+                    shared.ring.read_message(&mut out).unwrap();
+                    shared.ring.take_credit_stall()
+                })
+            };
+
+            let written = shared.ring.write_message(&message(9, 2));
+            let woken = reader.join().unwrap();
+            // Отказ без флага у читателя -- потерянное пробуждение.
+            match written {
+                Ok(outcome) => assert_eq!(outcome.overwritten, 0),
+                Err(RingError::QueueFull) => assert!(woken),
+                Err(err) => panic!("unexpected error: {err:?}"),
+            }
+            assert_eq!(shared.ring.drop_count(), 0);
+        });
+    }
+
+    #[test]
+    fn peek_races_overwrite_without_torn_or_lost_frames() {
+        const LEN: usize = 20;
+        model(|| {
+            let shared = make_ring();
+            shared.ring.write_message(&message(0, LEN)).unwrap();
+            shared.ring.write_message(&message(1, LEN)).unwrap();
+
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    shared.ring.write_message(&message(2, LEN)).unwrap();
+                })
+            };
+
+            // Подсмотренный кадр целый и остаётся головой очереди, пока его
+            // не вытеснили.
+            let mut scratch = [MaybeUninit::uninit(); RING_CAPACITY];
+            let peeked = shared
+                .ring
+                .peek_live_frame_with(&mut scratch, || 0, |_| true, |payload, _| id_of(payload))
+                .map(|(id, _)| id);
+            writer.join().unwrap();
+            let mut ids = Vec::new();
+            drain(&shared.ring, &mut ids);
+
+            let peeked = peeked.unwrap();
+            assert!(
+                ids.first() == Some(&peeked) || shared.ring.drop_count() > u32::from(peeked),
+                "peeked {peeked}, read {ids:?}"
+            );
+            assert_eq!(ids.len() as u32 + shared.ring.drop_count(), 3);
+        });
+    }
+
+    #[test]
+    fn borrowed_read_never_returns_an_overwritten_frame() {
+        // Чтение прямо из кольца: `f` видит кадр до фиксации, и вытеснение
+        // посреди вызова должно провалить CAS, а не отдать порванную копию.
+        const LEN: usize = 20;
+        model(|| {
+            let shared = make_ring();
+            shared.ring.write_message(&message(0, LEN)).unwrap();
+            shared.ring.write_message(&message(1, LEN)).unwrap();
+
+            let writer = {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    shared.ring.write_message(&message(2, LEN)).unwrap();
+                    shared.ring.write_message(&message(3, LEN)).unwrap();
+                })
+            };
+
+            let mut ids = Vec::new();
+            let mut scratch = [MaybeUninit::uninit(); RING_CAPACITY];
+            for _ in 0..2 {
+                if let Ok((payload, _)) = shared.ring.read_live_frame_with(
+                    &mut scratch,
+                    || 0,
+                    |payload, _| payload.to_vec(),
+                ) {
+                    ids.push(id_of(&payload));
+                }
+            }
+            writer.join().unwrap();
+            drain(&shared.ring, &mut ids);
+
+            assert!(ids.windows(2).all(|w| w[0] < w[1]), "order: {ids:?}");
+            assert_eq!(ids.len() as u32 + shared.ring.drop_count(), 4);
+        });
+    }
+
+    #[test]
+    fn concurrent_producers_publish_whole_frames_in_claim_order() {
+        model(|| {
+            let shared = make_ring();
+            shared.ring.set_sequence_numbers(true);
+            shared.ring.set_multi_producer(true);
+            let producers: Vec<_> = [1u8, 2]
+                .into_iter()
+                .map(|id| {
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        shared.ring.write_message(&message(id, 2)).unwrap();
+                    })
+                })
+                .collect();
+
+            let mut frames = Vec::new();
+            let mut out = [MaybeUninit::uninit(); RING_CAPACITY];
+            let mut read = |frames: &mut Vec<(u8, Option<u32>)>| {
+                let frame = shared.ring.read_frame_info_uninit(&mut out).ok()?;
+                // SAFETY: при Ok первые frame.len байт инициализированы.
+                let msg =
+                    unsafe { core::slice::from_raw_parts(out.as_ptr().cast::<u8>(), frame.len) };
+                frames.push((id_of(msg), frame.sequence));
+                Some(())
+            };
+            read(&mut frames);
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            while read(&mut frames).is_some() {}
+
+            // Номера -- в порядке публикации, без пропусков.
+            let sequences: Vec<_> = frames.iter().map(|&(_, seq)| seq).collect();
+            assert_eq!(sequences, [Some(0), Some(1)]);
+            let mut ids: Vec<_> = frames.iter().map(|&(id, _)| id).collect();
+            ids.sort_unstable();
+            assert_eq!(ids, [1, 2]);
+            assert_eq!(shared.ring.message_count(), 0);
+        });
+    }
+
+    #[test]
+    fn concurrent_producers_evict_without_tearing_or_losing_count() {
+        model(|| {
+            let shared = make_ring();
+            shared.ring.set_multi_producer(true);
+            for id in 0..MAX_MESSAGES as u8 {
+                shared.ring.write_message(&message(id, 2)).unwrap();
+            }
+            let producers: Vec<_> = [MAX_MESSAGES as u8, MAX_MESSAGES as u8 + 1]
+                .into_iter()
+                .map(|id| {
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        shared
+                            .ring
+                            .write_message(&message(id, 2))
+                            .unwrap()
+                            .overwritten
+                    })
+                })
+                .collect();
+
+            let overwritten: u32 = producers.into_iter().map(|p| p.join().unwrap()).sum();
+            let mut ids = Vec::new();
+            drain(&shared.ring, &mut ids);
+
+            // Писатель может вытеснить лишний кадр, не успев увидеть
+            // fetch_sub соседа, но каждый кадр либо прочитан, либо посчитан
+            // вытесненным.
+            assert_eq!(shared.ring.drop_count(), overwritten);
+            assert_eq!(ids.len() as u32 + overwritten, MAX_MESSAGES + 2);
+            let (old, new) = ids.split_at(ids.len() - 2);
+            assert!(old.windows(2).all(|w| w[0] < w[1]), "order: {ids:?}");
+            assert!(
+                new.iter().all(|&id| id >= MAX_MESSAGES as u8),
+                "order: {ids:?}"
+            );
+            assert_eq!(shared.ring.message_count(), 0);
+        });
+    }
+
+    #[test]
+    fn reader_publishes_only_sealed_frames_of_concurrent_producers() {
+        // Кадр больше половины кольца: каждая запись вытесняет предыдущую.
+        // Вытесняющий писатель сдвигает read_pos до write_pos раньше, чем
+        // уменьшает message_count, -- reader видит «неопубликованный» кадр
+        // соседа, пока тот его дописывает.
+        const LEN: usize = RING_CAPACITY / 2 - MESSAGE_HEADER_SIZE - CHECKSUM_SIZE + 2;
+        model(|| {
+            let shared = make_ring();
+            shared.ring.set_checksum(true);
+            shared.ring.set_multi_producer(true);
+            shared.ring.write_message(&message(0, LEN)).unwrap();
+            let producers: Vec<_> = [1u8, 2]
+                .into_iter()
+                .map(|id| {
+                    let shared = shared.clone();
+                    thread::spawn(move || {
+                        shared
+                            .ring
+                            .write_message(&message(id, LEN))
+                            .unwrap()
+                            .overwritten
+                    })
+                })
+                .collect();
+
+            let mut ids = Vec::new();
+            let mut out = [0u8; RING_CAPACITY];
+            if let Ok(len) = shared.ring.read_message(&mut out) {
+                ids.push(id_of(&out[..len]));
+            }
+            let overwritten: u32 = producers.into_iter().map(|p| p.join().unwrap()).sum();
+            drain(&shared.ring, &mut ids);
+
+            assert_eq!(shared.ring.checksum_errors(), 0, "read: {ids:?}");
+            assert_eq!(ids.len() as u32 + overwritten, 3, "read: {ids:?}");
+            assert_eq!(shared.ring.message_count(), 0);
+        });
+    }
+}
@@ -0,0 +1,42 @@
+//! Атомики структур, лежащих в shared memory (`layout.rs`), и курсора
+//! писателей кольца, барьер и пауза spin-цикла кольца.
+//!
+//! В обычной сборке -- `core`, под `RUSTFLAGS="--cfg loom"` -- `loom`: тогда
+//! loom-тесты кольца (`ring.rs`) перебирают все допустимые моделью памяти
+//! чередования операций writer'а и reader'а, а не полагаются на x86 TSO.
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::AtomicU32;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::AtomicU32;
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::AtomicU64;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::AtomicU64;
+
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic::fence;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::fence;
+
+/// Пауза в коротком цикле ожидания другой стороны. Под loom -- явная
+/// уступка планировщику модели, иначе цикл перебирался бы до лимита.
+#[inline]
+pub(crate) fn spin_loop() {
+    #[cfg(loom)]
+    loom::hint::spin_loop();
+    #[cfg(not(loom))]
+    core::hint::spin_loop();
+}
+
+/// Точка, где loom может переключить поток посреди записи обычной памяти
+/// кольца: сама такая запись точкой планирования не является, а загрузка
+/// `atomic` -- является. В обычной сборке -- ничего.
+#[inline]
+pub(crate) fn preemption_point(atomic: &AtomicU32) {
+    #[cfg(loom)]
+    atomic.load(core::sync::atomic::Ordering::Relaxed);
+    #[cfg(not(loom))]
+    let _ = atomic;
+}